@@ -0,0 +1,269 @@
+//! Python bindings for phantomfill.
+//!
+//! ```python
+//! import phantomfill as pf
+//!
+//! store = pf.open_store("hf.db")
+//! markets = store.markets(category="btc")
+//! bt = pf.run_backtest("hf.db", strategy="momentum", runs=20, seed=42)
+//! bt.report["realistic_total_pnl"], bt.summary["realistic_pnl_median"]
+//! df = bt.to_pandas()
+//! ```
+//!
+//! Results cross into Python as plain dicts and lists (through their serde
+//! representation), so nothing on the Python side holds Rust state. Backtests
+//! release the GIL while they run.
+
+use std::path::PathBuf;
+
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::IntoPyObjectExt;
+use pyo3::types::{PyDict, PyList};
+use serde::Serialize;
+
+use ::phantomfill::backtest::{BacktestConfig, RunOutput};
+use ::phantomfill::data::{DataStore, MarketFilter, SqliteStore};
+use ::phantomfill::strategies::{is_known_strategy, list_strategies};
+use ::phantomfill::types::Platform;
+
+fn runtime_error(err: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{:#}", err))
+}
+
+/// Convert any serializable value into the equivalent Python object.
+fn to_py<'py, T: Serialize>(py: Python<'py>, value: &T) -> PyResult<Bound<'py, PyAny>> {
+    let value = serde_json::to_value(value).map_err(|e| PyValueError::new_err(e.to_string()))?;
+    json_to_py(py, &value)
+}
+
+fn json_to_py<'py>(py: Python<'py>, value: &serde_json::Value) -> PyResult<Bound<'py, PyAny>> {
+    use serde_json::Value;
+    Ok(match value {
+        Value::Null => py.None().into_bound(py),
+        Value::Bool(b) => b.into_bound_py_any(py)?,
+        Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => i.into_bound_py_any(py)?,
+            (None, Some(u)) => u.into_bound_py_any(py)?,
+            _ => n.as_f64().unwrap_or(f64::NAN).into_bound_py_any(py)?,
+        },
+        Value::String(s) => s.into_bound_py_any(py)?,
+        Value::Array(items) => {
+            let items = items
+                .iter()
+                .map(|v| json_to_py(py, v))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, items)?.into_any()
+        }
+        Value::Object(fields) => {
+            let dict = PyDict::new(py);
+            for (k, v) in fields {
+                dict.set_item(k, json_to_py(py, v)?)?;
+            }
+            dict.into_any()
+        }
+    })
+}
+
+fn parse_platform(name: &str) -> PyResult<Platform> {
+    match name.to_ascii_lowercase().as_str() {
+        "polymarket" => Ok(Platform::Polymarket),
+        "kalshi" => Ok(Platform::Kalshi),
+        other => Err(PyValueError::new_err(format!(
+            "unknown platform `{}` (expected polymarket or kalshi)",
+            other
+        ))),
+    }
+}
+
+fn market_filter(
+    category: Option<String>,
+    platform: Option<&str>,
+    min_ts: Option<i64>,
+    max_ts: Option<i64>,
+) -> PyResult<MarketFilter> {
+    Ok(MarketFilter {
+        platform: platform.map(parse_platform).transpose()?,
+        category,
+        duration_secs: None,
+        min_ts,
+        max_ts,
+    })
+}
+
+/// A phantomfill SQLite database. Each call opens its own connection, so a
+/// `Store` can be shared freely between threads.
+#[pyclass(frozen)]
+struct Store {
+    path: PathBuf,
+}
+
+impl Store {
+    fn open(&self) -> PyResult<SqliteStore> {
+        SqliteStore::open(&self.path).map_err(runtime_error)
+    }
+}
+
+#[pymethods]
+impl Store {
+    #[new]
+    fn new(path: PathBuf) -> PyResult<Self> {
+        let store = Self { path };
+        store.open()?;
+        Ok(store)
+    }
+
+    #[getter]
+    fn path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    /// Markets in the store as a list of dicts, optionally filtered.
+    #[pyo3(signature = (*, category=None, platform=None, min_ts=None, max_ts=None))]
+    fn markets<'py>(
+        &self,
+        py: Python<'py>,
+        category: Option<String>,
+        platform: Option<&str>,
+        min_ts: Option<i64>,
+        max_ts: Option<i64>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let filter = market_filter(category, platform, min_ts, max_ts)?;
+        let markets = self.open()?.list_markets(&filter).map_err(runtime_error)?;
+        to_py(py, &markets)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Store({:?})", self.path.display().to_string())
+    }
+}
+
+/// The outcome of `run_backtest`.
+#[pyclass(frozen)]
+struct Backtest {
+    output: RunOutput,
+}
+
+#[pymethods]
+impl Backtest {
+    /// Aggregate report for the first run, as a dict.
+    #[getter]
+    fn report<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_py(py, &self.output.report)
+    }
+
+    /// Per-window results of the first run, as a list of dicts.
+    #[getter]
+    fn results<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_py(py, &self.output.results)
+    }
+
+    /// Distribution over all runs as a dict, or `None` for a single run.
+    #[getter]
+    fn summary<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_py(py, &self.output.summary)
+    }
+
+    /// Per-window results as a pandas DataFrame, one row per window.
+    fn to_pandas<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let pandas = py.import("pandas")?;
+        pandas.getattr("DataFrame")?.call1((self.results(py)?,))
+    }
+
+    fn __len__(&self) -> usize {
+        self.output.results.len()
+    }
+
+    fn __repr__(&self) -> String {
+        let report = &self.output.report;
+        format!(
+            "Backtest(strategy={:?}, windows={}, realistic_total_pnl={:.2})",
+            report.strategy_name, report.total_windows, report.realistic_total_pnl
+        )
+    }
+}
+
+/// Open a phantomfill database.
+#[pyfunction]
+fn open_store(path: PathBuf) -> PyResult<Store> {
+    Store::new(path)
+}
+
+/// Built-in strategies as `(name, description)` pairs.
+#[pyfunction]
+fn strategies() -> Vec<(&'static str, &'static str)> {
+    list_strategies()
+}
+
+/// Run a backtest over the markets in `db` and return a `Backtest`.
+///
+/// Pass `script` (a `.rhai` path, with optional `params`) instead of a
+/// built-in `strategy` name to run a scripted strategy.
+#[pyfunction]
+#[pyo3(signature = (
+    db, *, strategy="momentum", script=None, params=None, bid_price=0.49, shares=10.0,
+    min_bps=5.0, category=None, platform=None, min_ts=None, max_ts=None, limit=None,
+    sample=None, seed=None, runs=1,
+))]
+#[allow(clippy::too_many_arguments)]
+fn run_backtest(
+    py: Python<'_>,
+    db: PathBuf,
+    strategy: &str,
+    script: Option<PathBuf>,
+    params: Option<Vec<String>>,
+    bid_price: f64,
+    shares: f64,
+    min_bps: f64,
+    category: Option<String>,
+    platform: Option<&str>,
+    min_ts: Option<i64>,
+    max_ts: Option<i64>,
+    limit: Option<usize>,
+    sample: Option<usize>,
+    seed: Option<u64>,
+    runs: usize,
+) -> PyResult<Backtest> {
+    if script.is_none() && !is_known_strategy(strategy) {
+        return Err(PyValueError::new_err(format!("unknown strategy `{}`", strategy)));
+    }
+    let config = BacktestConfig {
+        strategy: strategy.to_string(),
+        script,
+        script_source: None,
+        params: params.unwrap_or_default(),
+        bid_price,
+        shares,
+        min_bps,
+        fees: Default::default(),
+        bankroll: None,
+        latency: None,
+        portfolio: None,
+        platform: platform.map(parse_platform).transpose()?,
+        category,
+        duration_secs: None,
+        min_ts,
+        max_ts,
+        limit,
+        sample,
+        seed,
+        runs,
+    };
+    let output = py.detach(move || -> anyhow::Result<RunOutput> {
+        config.run(&SqliteStore::open(&db)?)
+    });
+    Ok(Backtest {
+        output: output.map_err(runtime_error)?,
+    })
+}
+
+#[pymodule]
+fn phantomfill(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_class::<Store>()?;
+    m.add_class::<Backtest>()?;
+    m.add_function(wrap_pyfunction!(open_store, m)?)?;
+    m.add_function(wrap_pyfunction!(strategies, m)?)?;
+    m.add_function(wrap_pyfunction!(run_backtest, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! Records the build's version for stored runs, the compiler version for the
+//! plugin ABI check (`plugins` feature), regenerates the C header (`ffi`
+//! feature) and compiles the gRPC protocol (`grpc` feature).
+
+use std::process::Command;
+
+fn main() {
+    record_version();
+    if std::env::var_os("CARGO_FEATURE_PLUGINS").is_some() {
+        record_rustc_version();
+    }
+    #[cfg(feature = "ffi")]
+    generate_header();
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+/// The package version plus, in a git checkout, `git describe` of the
+/// commit being built: `0.1.0 (1a2b3c4-dirty)`.
+fn record_version() {
+    let package = std::env::var("CARGO_PKG_VERSION").unwrap_or_default();
+    let describe = Command::new("git")
+        .args(["describe", "--always", "--dirty"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty());
+    let version = match describe {
+        Some(describe) => format!("{} ({})", package, describe),
+        None => package,
+    };
+    println!("cargo:rustc-env=PHANTOMFILL_VERSION={}", version);
+    for path in [".git/HEAD", ".git/index"] {
+        if std::path::Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+}
+
+fn record_rustc_version() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|v| v.trim().to_string())
+        .unwrap_or_else(|| "unknown rustc".to_string());
+    println!("cargo:rustc-env=PHANTOMFILL_RUSTC_VERSION={}", version);
+    println!("cargo:rerun-if-env-changed=RUSTC");
+}
+
+#[cfg(feature = "ffi")]
+fn generate_header() {
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    let config = cbindgen::Config {
+        language: cbindgen::Language::C,
+        include_guard: Some("PHANTOMFILL_H".to_string()),
+        header: Some("/* Generated by cbindgen from src/ffi.rs; do not edit. */".to_string()),
+        cpp_compat: true,
+        documentation_style: cbindgen::DocumentationStyle::C99,
+        ..cbindgen::Config::default()
+    };
+    // Only the FFI module is parsed, so the rest of the crate stays free of
+    // cbindgen annotations.
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src("src/ffi.rs")
+        .generate()
+        .expect("failed to generate C header from src/ffi.rs")
+        .write_to_file("include/phantomfill.h");
+}
+
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    println!("cargo:rerun-if-changed=proto/phantomfill.proto");
+    // Use the vendored protoc so the feature builds without a system install.
+    let protoc = protoc_bin_vendored::protoc_bin_path().expect("no vendored protoc for this host");
+    std::env::set_var("PROTOC", protoc);
+    tonic_build::compile_protos("proto/phantomfill.proto").expect("failed to compile proto/phantomfill.proto");
+}
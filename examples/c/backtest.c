@@ -0,0 +1,34 @@
+/*
+ * Run a phantomfill backtest from C.
+ *
+ *   cargo rustc --release --lib --features ffi --crate-type cdylib
+ *   cc examples/c/backtest.c -Iinclude -Ltarget/release -lphantomfill -o backtest
+ *   LD_LIBRARY_PATH=target/release ./backtest hf.db
+ */
+#include <stdio.h>
+
+#include "phantomfill.h"
+
+int main(int argc, char **argv) {
+    if (argc < 2) {
+        fprintf(stderr, "usage: %s DB [CONFIG_JSON]\n", argv[0]);
+        return 2;
+    }
+    const char *config = argc > 2 ? argv[2] : "{\"strategy\": \"spread_arb\", \"seed\": 42}";
+
+    PfStore *store = pf_store_open(argv[1]);
+    if (!store) {
+        fprintf(stderr, "error: %s\n", pf_last_error());
+        return 1;
+    }
+    char *json = pf_run_backtest(store, config);
+    if (!json) {
+        fprintf(stderr, "error: %s\n", pf_last_error());
+        pf_store_free(store);
+        return 1;
+    }
+    printf("%s\n", json);
+    pf_string_free(json);
+    pf_store_free(store);
+    return 0;
+}
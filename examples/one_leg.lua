@@ -0,0 +1,28 @@
+-- One Leg (Lua)
+-- Lua port of examples/one_leg.rhai: bid both YES and NO at BID_PRICE,
+-- and as soon as one side fills, cancel the other.
+--
+-- Usage: pf run --script examples/one_leg.lua --db path/to/spread_arb.db
+-- (build with `cargo build --release --features lua`)
+
+local placed = false
+
+function on_tick(snap)
+    if not placed then
+        placed = true
+        return { bid("yes", BID_PRICE, SHARES), bid("no", BID_PRICE, SHARES) }
+    end
+
+    local yes, no = snap.orders.yes, snap.orders.no
+    if yes.filled and not no.filled and not no.cancelled then
+        return { cancel("no") }
+    end
+    if no.filled and not yes.filled and not yes.cancelled then
+        return { cancel("yes") }
+    end
+    return {}
+end
+
+function on_reset()
+    placed = false
+end
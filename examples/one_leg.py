@@ -0,0 +1,26 @@
+# One Leg (Python)
+# Python port of examples/one_leg.rhai: bid both YES and NO at BID_PRICE,
+# and as soon as one side fills, cancel the other.
+#
+# Usage: pf run --py-strategy examples/one_leg.py --db path/to/spread_arb.db
+# (build with `cargo build --release --features python`)
+
+
+class OneLeg:
+    def __init__(self):
+        self.placed = False
+
+    def on_tick(self, snap):
+        if not self.placed:
+            self.placed = True
+            return [bid("yes", BID_PRICE, SHARES), bid("no", BID_PRICE, SHARES)]
+
+        yes, no = snap["orders"]["yes"], snap["orders"]["no"]
+        if yes["filled"] and not no["filled"] and not no["cancelled"]:
+            return [cancel("no")]
+        if no["filled"] and not yes["filled"] and not yes["cancelled"]:
+            return [cancel("yes")]
+        return []
+
+    def reset(self):
+        self.placed = False
@@ -0,0 +1,44 @@
+#!/usr/bin/env python3
+# One Leg (external process)
+# examples/one_leg.rhai as a standalone program: bid both YES and NO at the
+# bid price, and as soon as one side fills, cancel the other. It talks to pf
+# over stdin/stdout, one JSON object per line, so it needs no pf build
+# features and could as well be written in any other language.
+#
+# Usage: pf run --exec python3 --exec-arg examples/one_leg_exec.py --db path/to/spread_arb.db
+
+import json
+import sys
+
+shares, bid_price = 10.0, 0.49
+placed = False
+
+
+def bid(side):
+    return {"type": "bid", "side": side, "price": bid_price, "shares": shares}
+
+
+def on_tick(orders):
+    global placed
+    if not placed:
+        placed = True
+        return [bid("yes"), bid("no")]
+
+    yes, no = orders["yes"], orders["no"]
+    if yes["filled"] and not no["filled"] and not no["cancelled"]:
+        return [{"type": "cancel", "side": "no"}]
+    if no["filled"] and not yes["filled"] and not yes["cancelled"]:
+        return [{"type": "cancel", "side": "yes"}]
+    return []
+
+
+for line in sys.stdin:
+    message = json.loads(line)
+    kind = message["type"]
+    if kind == "init":
+        shares, bid_price = message["shares"], message["bid_price"]
+    elif kind == "reset":
+        placed = False
+    elif kind == "tick":
+        # Exactly one line per tick, flushed, or pf waits for it.
+        print(json.dumps(on_tick(message["orders"])), flush=True)
@@ -0,0 +1,68 @@
+//! Example compiled strategy plugin.
+//!
+//! Build with the same toolchain as `pf`, then load the shared library:
+//!
+//! ```bash
+//! cargo build --release --manifest-path examples/plugin/Cargo.toml
+//! pf run --plugin examples/plugin/target/release/liblate_leader_plugin.so \
+//!     --plugin-param ENTRY_MS=240000 --db hf.db --native
+//! ```
+
+use phantomfill::strategies::plugin::PluginParams;
+use phantomfill::strategies::Strategy;
+use phantomfill::types::{Action, BookSnapshot, Price, Shares, Side};
+
+/// Bid the side with the higher best bid once the window passes `entry_ms`.
+struct LateLeader {
+    bid_price: Price,
+    shares: Shares,
+    entry_ms: i64,
+    placed: bool,
+}
+
+impl LateLeader {
+    fn new(params: &PluginParams) -> Self {
+        Self {
+            bid_price: Price::from_f64(params.bid_price),
+            shares: Shares::from_f64(params.shares),
+            entry_ms: params
+                .get("ENTRY_MS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(240_000),
+            placed: false,
+        }
+    }
+}
+
+impl Strategy for LateLeader {
+    fn name(&self) -> &str {
+        "late_leader"
+    }
+
+    fn description(&self) -> &str {
+        "Plugin example: bid the leading side after ENTRY_MS"
+    }
+
+    fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action> {
+        if self.placed || snap.offset_ms < self.entry_ms {
+            return vec![];
+        }
+        let (yes, no) = (snap.yes.best_bid.unwrap_or(0.0), snap.no.best_bid.unwrap_or(0.0));
+        if yes == no {
+            return vec![];
+        }
+        self.placed = true;
+        let side = if yes > no { Side::Yes } else { Side::No };
+        vec![Action::PlaceBid {
+            side,
+            price: self.bid_price,
+            shares: self.shares,
+        }]
+    }
+
+    fn reset(&mut self) {
+        self.placed = false;
+    }
+}
+
+phantomfill::declare_plugin!(|params| Box::new(LateLeader::new(params)));
@@ -0,0 +1,68 @@
+/* Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#ifndef PHANTOMFILL_H
+#define PHANTOMFILL_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// An open phantomfill database. Opaque to C.
+typedef struct PfStore PfStore;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Library version, e.g. `"0.1.0"`. The string is static; do not free it.
+const char *pf_version(void);
+
+// Message describing the last failed call on this thread, or NULL. Valid
+// until the next phantomfill call on the same thread; do not free it.
+const char *pf_last_error(void);
+
+// Open (creating if needed) the database at `path` and initialize its
+// schema. Returns NULL on failure. Free with [`pf_store_free`].
+//
+// # Safety
+//
+// `path` must be a NUL-terminated UTF-8 string.
+struct PfStore *pf_store_open(const char *path);
+
+// Close a store opened with [`pf_store_open`]. NULL is ignored.
+//
+// # Safety
+//
+// `store` must be NULL or a pointer from [`pf_store_open`] that has not
+// been freed.
+void pf_store_free(struct PfStore *store);
+
+// Run a backtest over `store` and return the results as a JSON object with
+// `report`, `results` (one entry per window) and `summary` (null for a
+// single run). `config_json` is an object with any of `strategy`, `script`,
+// `script_source`, `params`, `bid_price`, `shares`, `min_bps`, `platform`,
+// `category`, `duration_secs`, `min_ts`, `max_ts`, `limit`, `sample`, `seed`
+// and `runs`; `"{}"` runs the defaults. Returns NULL on failure. Free the
+// result with [`pf_string_free`].
+//
+// # Safety
+//
+// `store` must be a live pointer from [`pf_store_open`], not used from
+// another thread during the call, and `config_json` a NUL-terminated UTF-8
+// string.
+char *pf_run_backtest(const struct PfStore *store, const char *config_json);
+
+// Free a string returned by phantomfill. NULL is ignored.
+//
+// # Safety
+//
+// `s` must be NULL or a string returned by this library that has not been
+// freed.
+void pf_string_free(char *s);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PHANTOMFILL_H */
@@ -0,0 +1,251 @@
+//! Arrow IPC (Feather v2) export (`arrow` feature).
+//!
+//! Large result sets go to pandas, polars or R as typed columns instead of
+//! through a CSV round-trip:
+//!
+//! ```python
+//! import pyarrow.feather as feather
+//! results = feather.read_table("results.arrow").to_pandas()
+//! orders = feather.read_table("results.orders.arrow").to_pandas()
+//! ```
+//!
+//! Window results keep the [`WindowResult`] field names and the CSV column
+//! order, with each window's order events as a JSON string; order results
+//! are [`OrderRecord`]s.
+
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray, UInt32Array, UInt64Array};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema};
+
+use crate::events::OrderRecord;
+use crate::types::WindowResult;
+
+fn strings<'a>(values: impl Iterator<Item = &'a str>) -> ArrayRef {
+    Arc::new(values.map(Some).collect::<StringArray>())
+}
+
+fn opt_strings<'a>(values: impl Iterator<Item = Option<&'a str>>) -> ArrayRef {
+    Arc::new(values.collect::<StringArray>())
+}
+
+fn int64s(values: impl Iterator<Item = Option<i64>>) -> ArrayRef {
+    Arc::new(values.collect::<Int64Array>())
+}
+
+fn float64s(values: impl Iterator<Item = Option<f64>>) -> ArrayRef {
+    Arc::new(values.collect::<Float64Array>())
+}
+
+fn bools(values: impl Iterator<Item = bool>) -> ArrayRef {
+    Arc::new(values.map(Some).collect::<BooleanArray>())
+}
+
+/// Window results as one record batch.
+pub fn results_batch(results: &[WindowResult]) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("market_id", DataType::Utf8, false),
+        Field::new("platform", DataType::Utf8, false),
+        Field::new("category", DataType::Utf8, false),
+        Field::new("open_ts", DataType::Int64, false),
+        Field::new("close_ts", DataType::Int64, false),
+        Field::new("outcome", DataType::Utf8, false),
+        Field::new("predicted", DataType::Utf8, true),
+        Field::new("signal_offset_ms", DataType::Int64, true),
+        Field::new("bid_side", DataType::Utf8, true),
+        Field::new("bid_price", DataType::Float64, false),
+        Field::new("shares", DataType::Float64, false),
+        Field::new("filled", DataType::Boolean, false),
+        Field::new("queue_ahead_at_place", DataType::Float64, false),
+        Field::new("fill_time_ms", DataType::Int64, true),
+        Field::new("correct", DataType::Boolean, false),
+        Field::new("realistic_pnl", DataType::Float64, false),
+        Field::new("naive_pnl", DataType::Float64, false),
+        Field::new("ref_price_open", DataType::Float64, true),
+        Field::new("ref_price_close", DataType::Float64, true),
+        Field::new("aborted", DataType::Utf8, true),
+        Field::new("script_errors", DataType::UInt64, false),
+        Field::new("rejected_orders", DataType::UInt64, false),
+        Field::new("rejection", DataType::Utf8, true),
+        Field::new("stake", DataType::Float64, false),
+        Field::new("bankroll", DataType::Float64, true),
+        Field::new("events", DataType::Utf8, false),
+    ]);
+    let r = results;
+    let events = r
+        .iter()
+        .map(|r| serde_json::to_string(&r.events))
+        .collect::<serde_json::Result<Vec<_>>>()?;
+    let columns = vec![
+        strings(r.iter().map(|r| r.market_id.as_str())),
+        strings(r.iter().map(|r| r.platform.as_str())),
+        strings(r.iter().map(|r| r.category.as_str())),
+        int64s(r.iter().map(|r| Some(r.open_ts))),
+        int64s(r.iter().map(|r| Some(r.close_ts))),
+        strings(r.iter().map(|r| r.outcome.as_str())),
+        opt_strings(r.iter().map(|r| r.predicted.as_deref())),
+        int64s(r.iter().map(|r| r.signal_offset_ms)),
+        opt_strings(r.iter().map(|r| r.bid_side.as_deref())),
+        float64s(r.iter().map(|r| Some(r.bid_price))),
+        float64s(r.iter().map(|r| Some(r.shares))),
+        bools(r.iter().map(|r| r.filled)),
+        float64s(r.iter().map(|r| Some(r.queue_ahead_at_place))),
+        int64s(r.iter().map(|r| r.fill_time_ms)),
+        bools(r.iter().map(|r| r.correct)),
+        float64s(r.iter().map(|r| Some(r.realistic_pnl))),
+        float64s(r.iter().map(|r| Some(r.naive_pnl))),
+        float64s(r.iter().map(|r| r.ref_price_open)),
+        float64s(r.iter().map(|r| r.ref_price_close)),
+        opt_strings(r.iter().map(|r| r.aborted.as_deref())),
+        Arc::new(r.iter().map(|r| Some(r.script_errors as u64)).collect::<UInt64Array>()),
+        Arc::new(r.iter().map(|r| Some(r.rejected_orders as u64)).collect::<UInt64Array>()),
+        opt_strings(r.iter().map(|r| r.rejection.as_deref())),
+        float64s(r.iter().map(|r| Some(r.stake))),
+        float64s(r.iter().map(|r| r.bankroll)),
+        strings(events.iter().map(String::as_str)),
+    ];
+    RecordBatch::try_new(Arc::new(schema), columns).context("failed to build results batch")
+}
+
+/// Order records as one record batch.
+pub fn orders_batch(orders: &[OrderRecord]) -> Result<RecordBatch> {
+    let schema = Schema::new(vec![
+        Field::new("run", DataType::UInt64, false),
+        Field::new("market_id", DataType::Utf8, false),
+        Field::new("order_id", DataType::UInt32, false),
+        Field::new("side", DataType::Utf8, false),
+        Field::new("price", DataType::Float64, false),
+        Field::new("shares", DataType::Float64, false),
+        Field::new("placed_at_ms", DataType::Int64, false),
+        Field::new("queue_ahead", DataType::Float64, false),
+        Field::new("taker", DataType::Boolean, false),
+        Field::new("sell", DataType::Boolean, false),
+        Field::new("filled_shares", DataType::Float64, false),
+        Field::new("filled_at_ms", DataType::Int64, true),
+        Field::new("cancelled_at_ms", DataType::Int64, true),
+        Field::new("expired_at_ms", DataType::Int64, true),
+        Field::new("pnl", DataType::Float64, true),
+    ]);
+    let o = orders;
+    let columns = vec![
+        Arc::new(o.iter().map(|o| Some(o.run as u64)).collect::<UInt64Array>()) as ArrayRef,
+        strings(o.iter().map(|o| o.market_id.as_str())),
+        Arc::new(o.iter().map(|o| Some(o.order_id.get())).collect::<UInt32Array>()),
+        strings(o.iter().map(|o| o.side.label())),
+        float64s(o.iter().map(|o| Some(o.price))),
+        float64s(o.iter().map(|o| Some(o.shares))),
+        int64s(o.iter().map(|o| Some(o.placed_at_ms))),
+        float64s(o.iter().map(|o| Some(o.queue_ahead))),
+        bools(o.iter().map(|o| o.taker)),
+        bools(o.iter().map(|o| o.sell)),
+        float64s(o.iter().map(|o| Some(o.filled_shares))),
+        int64s(o.iter().map(|o| o.filled_at_ms)),
+        int64s(o.iter().map(|o| o.cancelled_at_ms)),
+        int64s(o.iter().map(|o| o.expired_at_ms)),
+        float64s(o.iter().map(|o| o.pnl)),
+    ];
+    RecordBatch::try_new(Arc::new(schema), columns).context("failed to build orders batch")
+}
+
+fn write_batch(batch: &RecordBatch, path: &Path) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let mut writer = FileWriter::try_new(file, &batch.schema())?;
+    writer.write(batch)?;
+    writer
+        .finish()
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Write window results to an Arrow IPC file at `path`.
+pub fn write_results(results: &[WindowResult], path: &Path) -> Result<()> {
+    write_batch(&results_batch(results)?, path)
+}
+
+/// Write order records to an Arrow IPC file at `path`.
+pub fn write_orders(orders: &[OrderRecord], path: &Path) -> Result<()> {
+    write_batch(&orders_batch(orders)?, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{OrderId, Side};
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{Float64Type, Int64Type};
+    use arrow_ipc::reader::FileReader;
+
+    fn read(path: &Path) -> RecordBatch {
+        let mut reader = FileReader::try_new(File::open(path).unwrap(), None).unwrap();
+        reader.next().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_results_and_orders_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let result = WindowResult {
+            market_id: "m1".into(),
+            platform: "polymarket".into(),
+            category: "btc".into(),
+            open_ts: 1000,
+            close_ts: 1300,
+            outcome: "YES".into(),
+            predicted: None,
+            signal_offset_ms: Some(90_000),
+            bid_side: Some("YES".into()),
+            bid_price: 0.49,
+            shares: 10.0,
+            filled: true,
+            queue_ahead_at_place: 120.0,
+            fill_time_ms: None,
+            correct: true,
+            realistic_pnl: 5.1,
+            naive_pnl: 5.1,
+            ref_price_open: None,
+            ref_price_close: Some(66_100.0),
+            aborted: None,
+            script_errors: 2,
+            rejected_orders: 0,
+            rejection: None,
+            stake: 0.0,
+            bankroll: None,
+            events: Vec::new(),
+        };
+        let path = dir.path().join("results.arrow");
+        write_results(&[result.clone(), result], &path).unwrap();
+        let batch = read(&path);
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(batch.schema().field(0).name(), "market_id");
+        let pnl = batch.column_by_name("realistic_pnl").unwrap().as_primitive::<Float64Type>();
+        assert_eq!(pnl.value(1), 5.1);
+        assert!(batch.column_by_name("fill_time_ms").unwrap().is_null(0));
+
+        let order = OrderRecord {
+            run: 1,
+            market_id: "m1".into(),
+            order_id: OrderId::new(1),
+            side: Side::No,
+            price: 0.49,
+            shares: 10.0,
+            placed_at_ms: 0,
+            queue_ahead: 50.0,
+            filled_at_ms: Some(4_000),
+            cancelled_at_ms: None,
+            expired_at_ms: None,
+            pnl: Some(5.1),
+            taker: false,
+            sell: false,
+            filled_shares: 10.0,
+        };
+        let path = dir.path().join("orders.arrow");
+        write_orders(&[order], &path).unwrap();
+        let batch = read(&path);
+        assert_eq!(batch.column_by_name("side").unwrap().as_string::<i32>().value(0), "NO");
+        let filled = batch.column_by_name("filled_at_ms").unwrap().as_primitive::<Int64Type>();
+        assert_eq!(filled.value(0), 4_000);
+        assert!(batch.column_by_name("expired_at_ms").unwrap().is_null(0));
+    }
+}
@@ -0,0 +1,1229 @@
+//! One-call backtests for library users.
+//!
+//! [`BacktestBuilder`] wires a data source, a strategy factory and a fill
+//! model into the [`ReplayEngine`], runs one or more seeded passes and
+//! returns the per-window results alongside the [`Report`] (and, for
+//! several runs, the [`MonteCarloSummary`]). This is the same orchestration
+//! `pf run` uses:
+//!
+//! ```no_run
+//! use phantomfill::backtest::BacktestBuilder;
+//! use phantomfill::data::{MarketFilter, SqliteStore};
+//! use phantomfill::strategies::{create_strategy, StrategyParams};
+//!
+//! let store = SqliteStore::open("hf.db".as_ref())?;
+//! let output = BacktestBuilder::new()
+//!     .store(&store)
+//!     .strategy(|| create_strategy("spread_arb", &StrategyParams::default()).unwrap())
+//!     .filter(MarketFilter {
+//!         category: Some("btc".into()),
+//!         ..MarketFilter::default()
+//!     })
+//!     .seed(42)
+//!     .runs(20)
+//!     .run()?;
+//! println!("median PnL {:.2}", output.summary.unwrap().realistic_pnl_median);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+#[cfg(feature = "wasm")]
+use web_time::Instant;
+
+use anyhow::{anyhow, bail, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::checkpoint::{self, Checkpoint};
+#[cfg(feature = "mmap")]
+use crate::data::MappedSnapshots;
+use crate::data::{select_markets, ticks_to_snapshots, DataStore, MarketFilter, SnapshotCache};
+use crate::events::{EngineEvent, EventBus};
+use crate::fill::{DeLiseConfig, DeLiseFillModel, FillModel};
+use crate::portfolio::PortfolioLimits;
+use crate::replay::{CancelToken, ProgressFn, ReplayConfig, ReplayEngine, RunProgress};
+use crate::report::{MonteCarloSummary, Report};
+#[cfg(feature = "scripting")]
+use crate::strategies::ScriptFactory;
+use crate::strategies::{BuiltinFactory, FnFactory, Strategy, StrategyFactory, StrategyParams, StrategySpec};
+use crate::types::{Bankroll, BookSnapshot, FeeModel, Latency, Market, Platform, WindowResult};
+
+type FillModelFn<'a> = Box<dyn Fn(Option<u64>) -> Box<dyn FillModel> + 'a>;
+type SnapshotLoader<'a> = dyn Fn(&str) -> Result<Vec<BookSnapshot>> + 'a;
+type SnapshotsFn<'a> = Box<SnapshotLoader<'a>>;
+type RunHook<'a> = Box<dyn FnMut(usize, &[WindowResult]) -> Result<()> + 'a>;
+type WindowHook<'a> = Box<dyn FnMut(usize, &WindowResult) + 'a>;
+/// Markets done by each run, and their sum.
+type RunsDone = Arc<(Vec<AtomicUsize>, AtomicUsize)>;
+
+/// Where markets and their snapshots come from.
+enum Source<'a> {
+    /// A store, over an explicit market list or all markets it lists.
+    Store(&'a dyn DataStore, Option<Vec<Market>>),
+    Markets(Vec<Market>, SnapshotsFn<'a>),
+    /// A store with a memory-mapped snapshot cache in front of it.
+    #[cfg(feature = "mmap")]
+    Mapped(&'a dyn DataStore, MappedSnapshots),
+    /// A store with an in-memory snapshot cache in front of it.
+    Cached(&'a dyn DataStore, &'a SnapshotCache),
+}
+
+impl Source<'_> {
+    fn store(&self) -> Option<&dyn DataStore> {
+        match self {
+            Source::Store(store, _) => Some(*store),
+            Source::Markets(..) => None,
+            #[cfg(feature = "mmap")]
+            Source::Mapped(store, _) => Some(*store),
+            Source::Cached(store, _) => Some(*store),
+        }
+    }
+
+    /// Call `pass` with a snapshot loader for one pass over `markets`.
+    /// Stores stream the ticks of the whole pass instead of querying each
+    /// market separately.
+    fn with_snapshots<T>(
+        &self,
+        markets: &[Market],
+        pass: &mut dyn FnMut(&SnapshotLoader<'_>) -> Result<T>,
+    ) -> Result<T> {
+        match self {
+            Source::Store(store, _) => {
+                let mut results = None;
+                store.stream_ticks(markets, &mut |ticks| {
+                    results = Some(pass(&|id: &str| Ok(ticks_to_snapshots(id, &ticks(id)?))));
+                })?;
+                match results {
+                    Some(results) => results,
+                    None => bail!("store did not stream ticks for the pass"),
+                }
+            }
+            Source::Markets(_, snapshots) => pass(snapshots),
+            #[cfg(feature = "mmap")]
+            Source::Mapped(store, cache) => pass(&|id: &str| match cache.load(id) {
+                Some(snapshots) => snapshots,
+                None => Ok(ticks_to_snapshots(id, &store.load_ticks(id)?)),
+            }),
+            // Once every market is cached, passes skip the store entirely.
+            Source::Cached(store, cache) if markets.iter().all(|m| cache.contains(&m.id)) => {
+                pass(&|id: &str| cache.get_or_load(id, || Ok(ticks_to_snapshots(id, &store.load_ticks(id)?))))
+            }
+            Source::Cached(store, cache) => {
+                let mut results = None;
+                store.stream_ticks(markets, &mut |ticks| {
+                    results = Some(pass(&|id: &str| {
+                        cache.get_or_load(id, || Ok(ticks_to_snapshots(id, &ticks(id)?)))
+                    }));
+                })?;
+                match results {
+                    Some(results) => results,
+                    None => bail!("store did not stream ticks for the pass"),
+                }
+            }
+        }
+    }
+}
+
+/// Everything a finished backtest produced.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunOutput {
+    /// The strategy configuration that produced these results.
+    pub strategy: StrategySpec,
+    /// Report for the first run.
+    pub report: Report,
+    /// Per-window results of the first run.
+    pub results: Vec<WindowResult>,
+    /// Distribution over all runs, when more than one was requested.
+    pub summary: Option<MonteCarloSummary>,
+}
+
+/// What every run's engine is built from; shared by parallel runs.
+struct EngineParts {
+    config: ReplayConfig,
+    cancel: Option<CancelToken>,
+    progress: Option<(ProgressFn, RunsDone)>,
+    started: Instant,
+    /// Each run's seed, for the engine's latency jitter.
+    seeds: Vec<Option<u64>>,
+}
+
+impl EngineParts {
+    fn engine(&self, run: usize, fill_model: Box<dyn FillModel>, events: Option<EventBus>) -> ReplayEngine {
+        let mut engine = ReplayEngine::new(fill_model, self.config.clone());
+        if let Some(seed) = self.seeds[run] {
+            engine = engine.with_seed(seed);
+        }
+        if let Some(events) = events {
+            engine = engine.with_events(events);
+        }
+        if let Some(ref cancel) = self.cancel {
+            engine = engine.with_cancel(cancel.clone());
+        }
+        if let Some((ref progress, ref done)) = self.progress {
+            let (progress, done, started) = (Arc::clone(progress), Arc::clone(done), self.started);
+            engine = engine.with_progress(Arc::new(move |p: &RunProgress| {
+                let (per_run, total) = &*done;
+                let before = per_run[run].swap(p.markets_done, Ordering::Relaxed);
+                let markets_done = total.fetch_add(p.markets_done - before, Ordering::Relaxed) + p.markets_done - before;
+                progress(&RunProgress {
+                    markets_done,
+                    markets_total: per_run.len() * p.markets_total,
+                    elapsed: started.elapsed(),
+                })
+            }));
+        }
+        engine
+    }
+}
+
+/// Sent from a parallel run to the thread that owns the hooks.
+enum Finished {
+    Window(usize, Box<WindowResult>),
+    /// A run's results and, when the caller has a bus, its buffered events.
+    Run(usize, Vec<WindowResult>, Vec<EngineEvent>),
+}
+
+/// Run one pass per fill model on up to `threads` threads, all reading one
+/// in-memory copy of the snapshots loaded up front. `finish` sees the runs
+/// in order, each with its events (when `events` is set), so a bus gets the
+/// same sequence as from sequential runs; `on_window` calls interleave.
+#[allow(clippy::too_many_arguments)]
+fn run_parallel(
+    threads: usize,
+    source: &Source<'_>,
+    markets: &[Market],
+    strategy: &dyn StrategyFactory,
+    parts: &EngineParts,
+    events: bool,
+    fill_models: Vec<Box<dyn FillModel>>,
+    mut on_window: Option<&mut WindowHook<'_>>,
+    finish: &mut dyn FnMut(usize, Vec<WindowResult>, Vec<EngineEvent>) -> Result<()>,
+) -> Result<()> {
+    let cache: HashMap<&str, Result<Vec<BookSnapshot>>> = source.with_snapshots(markets, &mut |load| {
+        Ok(markets.iter().map(|m| (m.id.as_str(), load(&m.id))).collect())
+    })?;
+    let snapshots = |id: &str| match cache.get(id) {
+        Some(Ok(snapshots)) => Ok(snapshots.clone()),
+        Some(Err(e)) => Err(anyhow!("{:#}", e)),
+        None => Err(anyhow!("market {} was not preloaded", id)),
+    };
+
+    let workers = threads.min(fill_models.len());
+    let jobs = Mutex::new(fill_models.into_iter().enumerate());
+    let stop = AtomicBool::new(false);
+    let stream_windows = on_window.is_some();
+    thread::scope(|scope| -> Result<()> {
+        let (tx, rx) = mpsc::channel();
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (jobs, stop, snapshots) = (&jobs, &stop, &snapshots);
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let Some((i, fill_model)) = jobs.lock().unwrap().next() else {
+                        break;
+                    };
+                    let bus = events.then(EventBus::new);
+                    let buffered = bus.as_ref().map(EventBus::channel);
+                    let engine = parts.engine(i, fill_model, bus);
+                    let results = engine.run_all_with(markets, snapshots, strategy, &mut |r| {
+                        if stream_windows {
+                            let _ = tx.send(Finished::Window(i, Box::new(r.clone())));
+                        }
+                    });
+                    let events = buffered.map_or_else(Vec::new, |rx| rx.try_iter().collect());
+                    if tx.send(Finished::Run(i, results, events)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        for finished in rx {
+            match finished {
+                Finished::Window(i, result) => {
+                    if let Some(hook) = on_window.as_mut() {
+                        hook(i, &result);
+                    }
+                }
+                Finished::Run(i, results, events) => {
+                    pending.insert(i, (results, events));
+                    while let Some((results, events)) = pending.remove(&next) {
+                        if let Err(e) = finish(next, results, events) {
+                            stop.store(true, Ordering::Relaxed);
+                            return Err(e);
+                        }
+                        next += 1;
+                    }
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Fluent configuration for a backtest; see the [module docs](self).
+///
+/// Only a data source and a strategy are required. The fill model defaults
+/// to [`DeLiseFillModel`], seeded per run.
+pub struct BacktestBuilder<'a> {
+    source: Option<Source<'a>>,
+    strategy: Option<Box<dyn StrategyFactory + 'a>>,
+    name: Option<String>,
+    fill_model: FillModelFn<'a>,
+    filter: MarketFilter,
+    limit: Option<usize>,
+    sample: Option<usize>,
+    seed: Option<u64>,
+    runs: usize,
+    threads: usize,
+    config: ReplayConfig,
+    checkpoint: Option<(PathBuf, usize)>,
+    #[cfg(feature = "mmap")]
+    snapshot_cache: Option<PathBuf>,
+    memory_cache: Option<&'a SnapshotCache>,
+    on_run: Option<RunHook<'a>>,
+    on_window: Option<WindowHook<'a>>,
+    events: Option<EventBus>,
+    cancel: Option<CancelToken>,
+    progress: Option<ProgressFn>,
+}
+
+impl Default for BacktestBuilder<'_> {
+    fn default() -> Self {
+        Self {
+            source: None,
+            strategy: None,
+            name: None,
+            fill_model: Box::new(|seed| {
+                Box::new(DeLiseFillModel::new(DeLiseConfig {
+                    seed,
+                    ..DeLiseConfig::default()
+                }))
+            }),
+            filter: MarketFilter::default(),
+            limit: None,
+            sample: None,
+            seed: None,
+            runs: 1,
+            threads: 1,
+            config: ReplayConfig::default(),
+            checkpoint: None,
+            #[cfg(feature = "mmap")]
+            snapshot_cache: None,
+            memory_cache: None,
+            on_run: None,
+            on_window: None,
+            events: None,
+            cancel: None,
+            progress: None,
+        }
+    }
+}
+
+impl<'a> BacktestBuilder<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read markets and ticks from a native store.
+    pub fn store(mut self, store: &'a dyn DataStore) -> Self {
+        self.source = Some(Source::Store(store, None));
+        self
+    }
+
+    /// Read ticks from a store, but for an explicit market list instead of
+    /// the markets it lists (e.g. one prepared for precomputed signals).
+    pub fn store_markets(mut self, store: &'a dyn DataStore, markets: Vec<Market>) -> Self {
+        self.source = Some(Source::Store(store, Some(markets)));
+        self
+    }
+
+    /// Use an explicit market list and snapshot loader instead of a store
+    /// (e.g. the capture database, or synthetic data).
+    pub fn markets(
+        mut self,
+        markets: Vec<Market>,
+        snapshots: impl Fn(&str) -> Result<Vec<BookSnapshot>> + 'a,
+    ) -> Self {
+        self.source = Some(Source::Markets(markets, Box::new(snapshots)));
+        self
+    }
+
+    /// Strategy constructor, called once per market window (wrapped in a
+    /// [`FnFactory`]; use [`factory`](Self::factory) to record parameters).
+    pub fn strategy(self, build: impl Fn() -> Box<dyn Strategy> + Send + Sync + 'a) -> Self {
+        self.factory(FnFactory::new(build))
+    }
+
+    /// Strategy factory, built from once per market window. Its
+    /// [`spec`](StrategyFactory::spec) is returned in [`RunOutput::strategy`].
+    pub fn factory(mut self, factory: impl StrategyFactory + 'a) -> Self {
+        self.strategy = Some(Box::new(factory));
+        self
+    }
+
+    /// Name shown in the report (default: the strategy's own `name()`).
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Fill model factory, called once per run with that run's seed.
+    pub fn fill_model(mut self, factory: impl Fn(Option<u64>) -> Box<dyn FillModel> + 'a) -> Self {
+        self.fill_model = Box::new(factory);
+        self
+    }
+
+    /// Only run markets matching `filter`.
+    pub fn filter(mut self, filter: MarketFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Keep only the first `n` markets (after sampling).
+    pub fn limit(mut self, n: usize) -> Self {
+        self.limit = Some(n);
+        self
+    }
+
+    /// Run a random subset of `n` markets (reproducible with [`seed`](Self::seed)).
+    pub fn sample(mut self, n: usize) -> Self {
+        self.sample = Some(n);
+        self
+    }
+
+    /// Seed for market sampling and the fill model. Run `i` of a Monte Carlo
+    /// batch uses `seed + i`; without a seed every run draws its own.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Number of Monte Carlo runs (default 1).
+    pub fn runs(mut self, runs: usize) -> Self {
+        self.runs = runs;
+        self
+    }
+
+    /// Run up to `n` Monte Carlo runs at once (default 1). Parallel runs read
+    /// one in-memory copy of every market's snapshots, loaded before the
+    /// first run, instead of streaming them each pass, so memory grows with
+    /// the market set. Seeds, [`on_run`](Self::on_run) calls and events
+    /// keep run order; [`on_window`](Self::on_window) calls from different
+    /// runs interleave.
+    pub fn threads(mut self, n: usize) -> Self {
+        self.threads = n.max(1);
+        self
+    }
+
+    /// Bid price, shares and abort behaviour passed to the engine.
+    pub fn replay_config(mut self, config: ReplayConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Resume from (and save progress to) a checkpoint file every `every`
+    /// markets. Single runs only, and a [`sample`](Self::sample) needs a
+    /// [`seed`](Self::seed) so a resume draws the same markets.
+    pub fn checkpoint(mut self, path: impl Into<PathBuf>, every: usize) -> Self {
+        self.checkpoint = Some((path.into(), every));
+        self
+    }
+
+    /// Read snapshots from a memory-mapped cache file (see
+    /// [`MappedSnapshots`]), building it from the store on the first run.
+    /// Markets missing from an existing cache are read from the store.
+    /// Ignored for [`markets`](Self::markets) sources.
+    #[cfg(feature = "mmap")]
+    pub fn snapshot_cache(mut self, path: impl Into<PathBuf>) -> Self {
+        self.snapshot_cache = Some(path.into());
+        self
+    }
+
+    /// Keep snapshots read from the store in `cache`, so later runs (and
+    /// later backtests sharing the cache) read them from memory. A
+    /// `snapshot_cache` file takes precedence.
+    /// Ignored for [`markets`](Self::markets) sources.
+    pub fn memory_cache(mut self, cache: &'a SnapshotCache) -> Self {
+        self.memory_cache = Some(cache);
+        self
+    }
+
+    /// Called after each run with its index and results; an error stops the
+    /// backtest and is returned from [`run`](Self::run).
+    pub fn on_run(mut self, hook: impl FnMut(usize, &[WindowResult]) -> Result<()> + 'a) -> Self {
+        self.on_run = Some(Box::new(hook));
+        self
+    }
+
+    /// Called as each window finishes with the run index and its result, for
+    /// streaming results before a pass completes. Not available with a
+    /// checkpoint.
+    pub fn on_window(mut self, hook: impl FnMut(usize, &WindowResult) + 'a) -> Self {
+        self.on_window = Some(Box::new(hook));
+        self
+    }
+
+    /// Report orders, fills, windows and progress to `events` (see
+    /// [`crate::events`]), plus a [`EngineEvent::RunCompleted`] per pass.
+    pub fn events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Abort the backtest once `cancel` is set: the current pass stops
+    /// before its next window and [`run`](Self::run) returns an error (a
+    /// checkpointed run saves its checkpoint first).
+    pub fn cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Called after every market with progress over the whole backtest
+    /// (all runs together), including an [`eta`](RunProgress::eta).
+    pub fn on_progress(mut self, progress: impl Fn(&RunProgress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Run the backtest.
+    pub fn run(mut self) -> Result<RunOutput> {
+        let Some(source) = self.source.take() else {
+            bail!("backtest needs a data source: call .store(...) or .markets(...)");
+        };
+        let Some(strategy) = self.strategy.take() else {
+            bail!("backtest needs a strategy: call .strategy(...) or .factory(...)");
+        };
+        if self.runs == 0 {
+            bail!("backtest needs at least one run");
+        }
+        if self.checkpoint.is_some() && self.runs > 1 {
+            bail!("checkpoints are only supported for single runs");
+        }
+        if self.checkpoint.is_some() && self.on_window.is_some() {
+            bail!("per-window hooks are not supported with checkpoints");
+        }
+        if self.checkpoint.is_some() && self.sample.is_some() && self.seed.is_none() {
+            bail!("a checkpointed run with a market sample needs a seed, or a resume would draw different markets");
+        }
+
+        let markets = match source {
+            Source::Store(store, None) => store.list_markets(&self.filter)?,
+            Source::Store(_, Some(ref markets)) | Source::Markets(ref markets, _) => markets
+                .iter()
+                .filter(|m| self.filter.matches(m))
+                .cloned()
+                .collect(),
+            #[cfg(feature = "mmap")]
+            Source::Mapped(..) => unreachable!("only built below"),
+            Source::Cached(..) => unreachable!("only built below"),
+        };
+        let markets = select_markets(markets, self.limit, self.sample, self.seed);
+        #[cfg(feature = "mmap")]
+        let source = match (source, self.snapshot_cache.take()) {
+            (Source::Store(store, _), Some(path)) => {
+                Source::Mapped(store, MappedSnapshots::open_or_build(&path, store, &markets)?)
+            }
+            (source, _) => source,
+        };
+        let source = match (source, self.memory_cache) {
+            (Source::Store(store, _), Some(cache)) => Source::Cached(store, cache),
+            (source, _) => source,
+        };
+        let name = self
+            .name
+            .take()
+            .unwrap_or_else(|| strategy.name().to_string());
+
+        let run_seeds: Vec<Option<u64>> = match (self.runs, self.seed) {
+            (1, seed) => vec![seed],
+            (runs, Some(seed)) => (0..runs as u64).map(|i| Some(seed + i)).collect(),
+            (runs, None) => {
+                let mut rng = rand::thread_rng();
+                (0..runs).map(|_| Some(rng.gen())).collect()
+            }
+        };
+
+        let runs = run_seeds.len();
+        let fill_models: Vec<Box<dyn FillModel>> = run_seeds.iter().map(|&seed| (self.fill_model)(seed)).collect();
+        let fill_model_names: Vec<String> = fill_models.iter().map(|m| m.name().to_string()).collect();
+        let fill_model_settings: Vec<String> = fill_models.iter().map(|m| m.settings()).collect();
+        if source.store().is_some_and(|s| !s.loads_depth()) {
+            if let Some(model) = fill_models.iter().find(|m| m.uses_depth()) {
+                bail!(
+                    "fill model `{}` reads depth ladders, but the store is set to skip them",
+                    model.name()
+                );
+            }
+        }
+        let parts = EngineParts {
+            config: self.config.clone(),
+            cancel: self.cancel.clone(),
+            progress: self.progress.take().map(|progress| {
+                let per_run = (0..runs).map(|_| AtomicUsize::new(0)).collect();
+                (progress, Arc::new((per_run, AtomicUsize::new(0))))
+            }),
+            started: Instant::now(),
+            seeds: run_seeds.clone(),
+        };
+
+        let signal_offset_ms = strategy.spec().signal_offset_ms();
+        let mut first: Option<(Report, Vec<WindowResult>)> = None;
+        let mut reports = Vec::with_capacity(runs);
+        let mut finish = |i: usize, results: Vec<WindowResult>, events: Vec<EngineEvent>| -> Result<()> {
+            if let Some(ref bus) = self.events {
+                events.into_iter().for_each(|event| bus.emit(event));
+            }
+            if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+                bail!("backtest cancelled during run {}", i + 1);
+            }
+            if let Some(hook) = self.on_run.as_mut() {
+                hook(i, &results)?;
+            }
+
+            let report =
+                Report::from_results_with_signal(&results, &name, &fill_model_names[i], signal_offset_ms);
+            if let Some(ref events) = self.events {
+                events.emit(EngineEvent::RunCompleted {
+                    run: i,
+                    runs,
+                    report: Box::new(report.clone()),
+                });
+            }
+            if first.is_none() {
+                first = Some((report.clone(), results));
+            }
+            reports.push(report);
+            Ok(())
+        };
+
+        if self.threads > 1 && runs > 1 {
+            run_parallel(
+                self.threads,
+                &source,
+                &markets,
+                strategy.as_ref(),
+                &parts,
+                self.events.is_some(),
+                fill_models,
+                self.on_window.as_mut(),
+                &mut finish,
+            )?;
+        } else {
+            for (i, fill_model) in fill_models.into_iter().enumerate() {
+                let engine = parts.engine(i, fill_model, self.events.clone());
+                let results = source.with_snapshots(&markets, &mut |snapshots| {
+                    Ok(match self.checkpoint {
+                        Some((ref path, every)) => {
+                            let settings = format!(
+                                "{:?} {:?} {} {:?}",
+                                strategy.spec(),
+                                self.config,
+                                fill_model_settings[i],
+                                run_seeds[i]
+                            );
+                            let mut checkpoint =
+                                Checkpoint::load_or_new(path, &name, &checkpoint::fingerprint(&settings))?;
+                            engine.run_all_resumable(
+                                &markets,
+                                snapshots,
+                                strategy.as_ref(),
+                                &mut checkpoint,
+                                path,
+                                every,
+                            )?
+                        }
+                        None => match self.on_window.as_mut() {
+                            Some(hook) => engine.run_all_with(&markets, snapshots, strategy.as_ref(), &mut |r| hook(i, r)),
+                            None => engine.run_all(&markets, snapshots, strategy.as_ref()),
+                        },
+                    })
+                })?;
+                finish(i, results, Vec::new())?;
+            }
+        }
+
+        let (report, results) = first.expect("at least one run");
+        let summary = (reports.len() > 1).then(|| MonteCarloSummary::from_reports(reports, self.seed));
+        Ok(RunOutput {
+            strategy: strategy.spec(),
+            report,
+            results,
+            summary,
+        })
+    }
+}
+
+/// Backtest settings as data, for embedders that configure runs from JSON
+/// (the C API, the browser build). Field names and defaults follow the
+/// `pf run` flags; unknown fields are rejected.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BacktestConfig {
+    /// Built-in strategy name; ignored when a script is given.
+    pub strategy: String,
+    /// Path of a `.rhai` script to run instead.
+    pub script: Option<PathBuf>,
+    /// Inline Rhai source to run instead (takes precedence over `script`).
+    pub script_source: Option<String>,
+    /// `KEY=VALUE` settings: script constants, as with `--script-param`, or
+    /// a built-in strategy's params, as with `--param`.
+    pub params: Vec<String>,
+    pub bid_price: f64,
+    pub shares: f64,
+    pub min_bps: f64,
+    /// Fees and rebates, as with `--maker-fee`, `--taker-fee` and friends.
+    pub fees: FeeModel,
+    /// Starting capital and sizing rule, as with `--bankroll` and
+    /// `--sizing`; unset trades the fixed `shares`.
+    pub bankroll: Option<Bankroll>,
+    /// Order-entry delay, as with `--latency-ms` and `--latency-jitter-ms`.
+    pub latency: Option<Latency>,
+    /// Limits across windows open at once, as with `--max-positions`,
+    /// `--max-capital` and `--max-category-capital`.
+    pub portfolio: Option<PortfolioLimits>,
+    pub platform: Option<Platform>,
+    pub category: Option<String>,
+    /// Window length in seconds, as with `--timeframe`.
+    pub duration_secs: Option<i64>,
+    pub min_ts: Option<i64>,
+    pub max_ts: Option<i64>,
+    pub limit: Option<usize>,
+    pub sample: Option<usize>,
+    pub seed: Option<u64>,
+    pub runs: usize,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            strategy: "momentum".to_string(),
+            script: None,
+            script_source: None,
+            params: Vec::new(),
+            bid_price: 0.49,
+            shares: 10.0,
+            min_bps: 5.0,
+            fees: FeeModel::default(),
+            bankroll: None,
+            latency: None,
+            portfolio: None,
+            platform: None,
+            category: None,
+            duration_secs: None,
+            min_ts: None,
+            max_ts: None,
+            limit: None,
+            sample: None,
+            seed: None,
+            runs: 1,
+        }
+    }
+}
+
+impl BacktestConfig {
+    /// Run the configured backtest over the markets in `store`.
+    pub fn run(&self, store: &dyn DataStore) -> Result<RunOutput> {
+        self.run_with(store, |_, _| Ok(()))
+    }
+
+    /// Like [`run`](Self::run), calling `on_run` after each pass (see
+    /// [`BacktestBuilder::on_run`]).
+    pub fn run_with(
+        &self,
+        store: &dyn DataStore,
+        on_run: impl FnMut(usize, &[WindowResult]) -> Result<()>,
+    ) -> Result<RunOutput> {
+        self.run_hooked(store, on_run, None)
+    }
+
+    /// Like [`run`](Self::run), calling `on_window` as each window finishes
+    /// (see [`BacktestBuilder::on_window`]).
+    pub fn run_streaming(
+        &self,
+        store: &dyn DataStore,
+        on_window: impl FnMut(usize, &WindowResult),
+    ) -> Result<RunOutput> {
+        self.run_hooked(store, |_, _| Ok(()), Some(Box::new(on_window)))
+    }
+
+    fn run_hooked<'a>(
+        &'a self,
+        store: &'a dyn DataStore,
+        on_run: impl FnMut(usize, &[WindowResult]) -> Result<()> + 'a,
+        on_window: Option<WindowHook<'a>>,
+    ) -> Result<RunOutput> {
+        if self.runs == 0 {
+            bail!("runs must be at least 1");
+        }
+        let mut builder = BacktestBuilder::new()
+            .store(store)
+            .factory(self.factory()?)
+            .filter(MarketFilter {
+                platform: self.platform.clone(),
+                category: self.category.clone(),
+                duration_secs: self.duration_secs,
+                min_ts: self.min_ts,
+                max_ts: self.max_ts,
+            })
+            .runs(self.runs)
+            .replay_config(ReplayConfig {
+                bid_price: self.bid_price,
+                shares: self.shares,
+                fees: self.fees,
+                bankroll: self.bankroll,
+                latency: self.latency,
+                portfolio: self.portfolio.clone(),
+                ..ReplayConfig::default()
+            })
+            .on_run(on_run);
+        if let Some(n) = self.limit {
+            builder = builder.limit(n);
+        }
+        if let Some(n) = self.sample {
+            builder = builder.sample(n);
+        }
+        if let Some(seed) = self.seed {
+            builder = builder.seed(seed);
+        }
+        if let Some(hook) = on_window {
+            builder.on_window = Some(hook);
+        }
+        builder.run()
+    }
+
+    /// The factory for the configured strategy or script. A bad name or a
+    /// script that fails to compile is an error here rather than a panic per
+    /// window.
+    #[cfg(feature = "scripting")]
+    pub fn factory(&self) -> Result<Box<dyn StrategyFactory>> {
+        if let Some(ref source) = self.script_source {
+            return Ok(Box::new(ScriptFactory::from_source(
+                "script",
+                source,
+                self.shares,
+                self.bid_price,
+                &self.params,
+            )?));
+        }
+        if let Some(ref path) = self.script {
+            return Ok(Box::new(ScriptFactory::from_file(
+                path,
+                self.shares,
+                self.bid_price,
+                &self.params,
+            )?));
+        }
+        self.builtin_factory()
+    }
+
+    /// The factory for the configured strategy; scripts need the `scripting`
+    /// feature.
+    #[cfg(not(feature = "scripting"))]
+    pub fn factory(&self) -> Result<Box<dyn StrategyFactory>> {
+        if self.script.is_some() || self.script_source.is_some() {
+            bail!("scripted strategies need phantomfill's `scripting` feature");
+        }
+        self.builtin_factory()
+    }
+
+    fn builtin_factory(&self) -> Result<Box<dyn StrategyFactory>> {
+        let mut params = StrategyParams {
+            bid_price: self.bid_price,
+            shares: self.shares,
+            min_bps: self.min_bps,
+            ..StrategyParams::default()
+        };
+        params.apply(&self.strategy, &self.params)?;
+        Ok(Box::new(BuiltinFactory::new(&self.strategy, params)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::{generate_dataset, BenchConfig};
+    use crate::data::SqliteStore;
+    use crate::strategies::{create_strategy, StrategyParams, DEFAULT_SIGNAL_OFFSET_MS};
+
+    fn seeded_store() -> SqliteStore {
+        let store = SqliteStore::in_memory().unwrap();
+        store.init().unwrap();
+        let config = BenchConfig {
+            markets: 6,
+            snapshots_per_market: 20,
+            seed: 3,
+        };
+        for (market, ticks) in generate_dataset(&config) {
+            store.insert_market(&market).unwrap();
+            store.insert_ticks(&ticks).unwrap();
+        }
+        store
+    }
+
+    fn spread_arb() -> Box<dyn Strategy> {
+        create_strategy("spread_arb", &StrategyParams::default()).unwrap()
+    }
+
+    #[test]
+    fn test_single_run_from_store() {
+        let store = seeded_store();
+        let output = BacktestBuilder::new()
+            .store(&store)
+            .strategy(spread_arb)
+            .seed(1)
+            .limit(4)
+            .run()
+            .unwrap();
+
+        assert_eq!(output.results.len(), 4);
+        assert_eq!(output.report.total_windows, 4);
+        assert_eq!(output.report.strategy_name, "spread_arb");
+        assert_eq!(output.report.fill_model_name, "delise-3rule");
+        assert!(output.summary.is_none());
+    }
+
+    #[test]
+    fn test_depth_needed_by_fill_model() {
+        let err = BacktestBuilder::new()
+            .store(&seeded_store().with_depth(false))
+            .strategy(spread_arb)
+            .run()
+            .unwrap_err();
+        assert!(err.to_string().contains("`delise-3rule` reads depth ladders"));
+    }
+
+    #[test]
+    fn test_monte_carlo_is_reproducible_and_calls_hook() {
+        let store = seeded_store();
+        let run = |hook_calls: &mut Vec<usize>| {
+            BacktestBuilder::new()
+                .store(&store)
+                .strategy(spread_arb)
+                .name("arb")
+                .seed(9)
+                .runs(3)
+                .on_run(|i, results| {
+                    assert_eq!(results.len(), 6);
+                    hook_calls.push(i);
+                    Ok(())
+                })
+                .run()
+                .unwrap()
+        };
+        let mut calls = Vec::new();
+        let a = run(&mut calls);
+        let b = run(&mut Vec::new());
+        assert_eq!(calls, vec![0, 1, 2]);
+
+        let (sa, sb) = (a.summary.unwrap(), b.summary.unwrap());
+        assert_eq!(sa.runs, 3);
+        assert_eq!(sa.realistic_pnl_median, sb.realistic_pnl_median);
+        assert_eq!(a.report.strategy_name, "arb");
+    }
+
+    #[test]
+    fn test_memory_cache_loads_each_market_once() {
+        let store = seeded_store();
+        let cache = SnapshotCache::new();
+        let run = |cache: Option<&SnapshotCache>| {
+            let builder = BacktestBuilder::new().store(&store).strategy(spread_arb).seed(9).runs(3);
+            match cache {
+                Some(cache) => builder.memory_cache(cache),
+                None => builder,
+            }
+            .run()
+            .unwrap()
+            .summary
+            .unwrap()
+        };
+        let cached = run(Some(&cache));
+        assert_eq!(cache.len(), 6);
+        // Loaded on the first run, served from memory on the other two.
+        assert_eq!(cache.hits_and_misses(), (12, 6));
+        assert_eq!(cached.realistic_pnl_median, run(None).realistic_pnl_median);
+
+        // A second backtest sharing the cache loads nothing.
+        run(Some(&cache));
+        assert_eq!(cache.hits_and_misses(), (30, 6));
+    }
+
+    #[test]
+    fn test_parallel_runs_match_sequential() {
+        let store = seeded_store();
+        let run = |threads: usize| {
+            let bus = EventBus::new();
+            let events = bus.channel();
+            let mut calls = Vec::new();
+            let mut windows = 0;
+            let output = BacktestBuilder::new()
+                .store(&store)
+                .strategy(spread_arb)
+                .seed(9)
+                .runs(5)
+                .threads(threads)
+                .events(bus)
+                .on_run(|i, _| {
+                    calls.push(i);
+                    Ok(())
+                })
+                .on_window(|_, _| windows += 1)
+                .run()
+                .unwrap();
+            let runs: Vec<usize> = crate::events::order_records(events.try_iter())
+                .iter()
+                .map(|o| o.run)
+                .collect();
+            (output, calls, windows, runs)
+        };
+        let (a, a_calls, a_windows, a_runs) = run(1);
+        let (b, b_calls, b_windows, b_runs) = run(3);
+        assert_eq!(a_calls, vec![0, 1, 2, 3, 4]);
+        assert_eq!(b_calls, a_calls);
+        assert_eq!((a_windows, b_windows), (30, 30));
+        assert_eq!(b_runs, a_runs);
+        let pnl = |o: &RunOutput| o.results.iter().map(|r| r.realistic_pnl).collect::<Vec<_>>();
+        assert_eq!(pnl(&b), pnl(&a));
+        let (sa, sb) = (a.summary.unwrap(), b.summary.unwrap());
+        assert_eq!(sb.realistic_pnl_median, sa.realistic_pnl_median);
+        assert_eq!(sb.fill_rate_mean, sa.fill_rate_mean);
+    }
+
+    #[test]
+    fn test_events_follow_the_run() {
+        let store = seeded_store();
+        let bus = EventBus::new();
+        let events = bus.channel();
+        let output = BacktestBuilder::new()
+            .store(&store)
+            .strategy(spread_arb)
+            .seed(4)
+            .runs(2)
+            .events(bus)
+            .run()
+            .unwrap();
+
+        let events: Vec<EngineEvent> = events.try_iter().collect();
+        let count = |f: fn(&EngineEvent) -> bool| events.iter().filter(|e| f(e)).count();
+        assert_eq!(count(|e| matches!(e, EngineEvent::WindowCompleted(_))), 12);
+        assert_eq!(count(|e| matches!(e, EngineEvent::Progress { .. })), 12);
+        assert_eq!(count(|e| matches!(e, EngineEvent::RunCompleted { .. })), 2);
+        let placed = count(|e| matches!(e, EngineEvent::OrderPlaced { .. }));
+        let filled = count(|e| matches!(e, EngineEvent::OrderFilled { .. }));
+        assert!(placed >= output.report.trades_taken && placed > 0);
+        assert!(filled >= output.report.fills && filled <= placed);
+
+        let orders = crate::events::order_records(events.iter().cloned());
+        assert_eq!(orders.len(), placed);
+        assert_eq!(orders.iter().filter(|o| o.filled_at_ms.is_some()).count(), filled);
+        assert_eq!((orders[0].run, orders.last().unwrap().run), (0, 1));
+        assert_eq!(orders[0].order_id.get(), 1);
+        for order in &orders {
+            let ends = [order.filled_at_ms, order.cancelled_at_ms, order.expired_at_ms];
+            assert_eq!(ends.iter().flatten().count(), 1, "{:?}", order);
+            assert_eq!(order.pnl.is_some(), order.filled_shares > 0.0, "{:?}", order);
+        }
+        match events.last().unwrap() {
+            EngineEvent::RunCompleted { run, runs, .. } => assert_eq!((*run, *runs), (1, 2)),
+            other => panic!("unexpected last event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_progress_and_cancel() {
+        let store = seeded_store();
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        BacktestBuilder::new()
+            .store(&store)
+            .strategy(spread_arb)
+            .seed(1)
+            .runs(2)
+            .on_progress(move |p| sink.lock().unwrap().push((p.markets_done, p.markets_total)))
+            .run()
+            .unwrap();
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 12);
+        assert_eq!(seen[6], (7, 12));
+        assert_eq!(*seen.last().unwrap(), (12, 12));
+
+        let progress = RunProgress {
+            markets_done: 3,
+            markets_total: 12,
+            elapsed: std::time::Duration::from_secs(6),
+        };
+        assert_eq!(progress.eta(), Some(std::time::Duration::from_secs(18)));
+
+        let cancel = CancelToken::new();
+        let token = cancel.clone();
+        let mut windows = 0;
+        let err = BacktestBuilder::new()
+            .store(&store)
+            .strategy(spread_arb)
+            .cancel(cancel)
+            .on_progress(move |p| {
+                if p.markets_done == 2 {
+                    token.cancel();
+                }
+            })
+            .on_window(|_, _| windows += 1)
+            .run()
+            .unwrap_err();
+        assert!(err.to_string().contains("cancelled"));
+        assert_eq!(windows, 2);
+    }
+
+    #[test]
+    fn test_markets_source_filter_and_errors() {
+        let dataset = generate_dataset(&BenchConfig {
+            markets: 4,
+            snapshots_per_market: 10,
+            seed: 5,
+        });
+        let cutoff = dataset[2].0.open_ts.secs();
+        let snapshots: std::collections::HashMap<_, _> = dataset
+            .iter()
+            .map(|(m, t)| (m.id.clone(), ticks_to_snapshots(&m.id, t)))
+            .collect();
+        let markets: Vec<Market> = dataset.into_iter().map(|(m, _)| m).collect();
+
+        let output = BacktestBuilder::new()
+            .markets(markets, |id| Ok(snapshots[id].clone()))
+            .strategy(spread_arb)
+            .filter(MarketFilter {
+                min_ts: Some(cutoff),
+                ..MarketFilter::default()
+            })
+            .run()
+            .unwrap();
+        assert_eq!(output.results.len(), 2);
+
+        assert!(BacktestBuilder::new().strategy(spread_arb).run().is_err());
+        let store = seeded_store();
+        assert!(BacktestBuilder::new().store(&store).run().is_err());
+        let err = BacktestBuilder::new()
+            .store(&store)
+            .strategy(spread_arb)
+            .runs(2)
+            .checkpoint("unused.json", 10)
+            .run()
+            .unwrap_err();
+        assert!(err.to_string().contains("single runs"));
+        let err = BacktestBuilder::new()
+            .store(&store)
+            .strategy(spread_arb)
+            .checkpoint("unused.json", 10)
+            .on_window(|_, _| {})
+            .run()
+            .unwrap_err();
+        assert!(err.to_string().contains("checkpoints"));
+        let err = BacktestBuilder::new()
+            .store(&store)
+            .strategy(spread_arb)
+            .sample(3)
+            .checkpoint("unused.json", 10)
+            .run()
+            .unwrap_err();
+        assert!(err.to_string().contains("needs a seed"));
+    }
+
+    #[test]
+    fn test_report_measures_placement_from_strategy_signal() {
+        let store = seeded_store();
+        let params = StrategyParams {
+            signal_offset_ms: 60_000,
+            ..StrategyParams::default()
+        };
+        let output = BacktestBuilder::new()
+            .store(&store)
+            .factory(BuiltinFactory::new("momentum", params).unwrap())
+            .seed(1)
+            .run()
+            .unwrap();
+        assert_eq!(output.report.signal_offset_ms, 60_000);
+        let output = BacktestBuilder::new().store(&store).strategy(spread_arb).seed(1).run().unwrap();
+        assert_eq!(output.report.signal_offset_ms, DEFAULT_SIGNAL_OFFSET_MS);
+    }
+
+    #[test]
+    fn test_checkpoint_fingerprints_fill_settings() {
+        let store = seeded_store();
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("run.ckpt");
+        let run = |cancel_latency_ms: i64| {
+            BacktestBuilder::new()
+                .store(&store)
+                .strategy(spread_arb)
+                .seed(1)
+                .fill_model(move |seed| {
+                    Box::new(DeLiseFillModel::new(DeLiseConfig {
+                        cancel_latency_ms,
+                        seed,
+                        ..DeLiseConfig::default()
+                    }))
+                })
+                .checkpoint(&path, 10)
+                .run()
+        };
+        run(0).unwrap();
+        assert!(run(0).is_ok());
+        let err = run(500).unwrap_err().to_string();
+        assert!(err.contains("different settings"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn test_config_from_json() {
+        let store = seeded_store();
+        let config: BacktestConfig =
+            serde_json::from_str(r#"{"strategy": "spread_arb", "seed": 4, "limit": 3}"#).unwrap();
+        let output = config.run(&store).unwrap();
+        assert_eq!(output.report.strategy_name, "spread_arb");
+        assert_eq!(output.strategy.params["bid_price"], 0.49);
+        assert_eq!(output.results.len(), 3);
+
+        let script = BacktestConfig {
+            script_source: Some("fn on_tick(snap) { [] }\nfn on_reset() {}".into()),
+            ..BacktestConfig::default()
+        };
+        #[cfg(feature = "scripting")]
+        assert_eq!(script.run(&store).unwrap().report.trades_taken, 0);
+        #[cfg(not(feature = "scripting"))]
+        assert!(script.run(&store).unwrap_err().to_string().contains("`scripting` feature"));
+
+        let unknown = BacktestConfig {
+            strategy: "nope".into(),
+            ..BacktestConfig::default()
+        };
+        assert!(unknown.run(&store).unwrap_err().to_string().contains("unknown strategy"));
+        assert!(serde_json::from_str::<BacktestConfig>(r#"{"shars": 5}"#).is_err());
+    }
+
+    #[test]
+    fn test_config_streams_windows() {
+        let store = seeded_store();
+        let config = BacktestConfig {
+            strategy: "spread_arb".into(),
+            seed: Some(2),
+            runs: 2,
+            ..BacktestConfig::default()
+        };
+        let mut streamed = Vec::new();
+        let output = config
+            .run_streaming(&store, |run, result| streamed.push((run, result.market_id.clone())))
+            .unwrap();
+        assert_eq!(streamed.len(), 12);
+        let first: Vec<_> = streamed.iter().filter(|(run, _)| *run == 0).map(|(_, id)| id).collect();
+        let ids: Vec<_> = output.results.iter().map(|r| &r.market_id).collect();
+        assert_eq!(first, ids);
+    }
+}
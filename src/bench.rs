@@ -0,0 +1,254 @@
+//! Built-in performance benchmark over a synthetic dataset.
+//!
+//! Generates deterministic markets and ticks, then times the three hot paths
+//! of a backtest: importing into SQLite, building snapshots from ticks, and
+//! replaying snapshots through the fill model.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use smallvec::smallvec;
+
+use crate::data::{ticks_to_snapshots, DataStore, SqliteStore};
+use crate::fill::{DeLiseConfig, DeLiseFillModel};
+use crate::replay::{ReplayConfig, ReplayEngine};
+use crate::strategies::{BuiltinFactory, StrategyParams};
+use crate::types::{
+    BookSnapshot, BookTick, Market, MarketId, OffsetMs, Outcome, Platform, PriceLevel, Side, UnixSecs,
+};
+
+/// Size and shape of the synthetic dataset.
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    pub markets: usize,
+    /// Snapshots per market (each is one YES and one NO tick).
+    pub snapshots_per_market: usize,
+    pub seed: u64,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        Self {
+            markets: 200,
+            snapshots_per_market: 300,
+            seed: 42,
+        }
+    }
+}
+
+/// Timings for each benchmark stage.
+#[derive(Debug)]
+pub struct BenchReport {
+    pub markets: usize,
+    pub ticks: usize,
+    pub snapshots: usize,
+    pub import_time: Duration,
+    pub snapshot_time: Duration,
+    pub replay_time: Duration,
+}
+
+impl BenchReport {
+    pub fn import_ticks_per_sec(&self) -> f64 {
+        rate(self.ticks, self.import_time)
+    }
+
+    pub fn snapshots_per_sec(&self) -> f64 {
+        rate(self.snapshots, self.snapshot_time)
+    }
+
+    pub fn replay_ticks_per_sec(&self) -> f64 {
+        rate(self.snapshots, self.replay_time)
+    }
+
+    pub fn print(&self) {
+        println!();
+        println!("{}", "=".repeat(55));
+        println!("  PhantomFill Benchmark");
+        println!(
+            "  {} markets, {} ticks, {} snapshots",
+            self.markets, self.ticks, self.snapshots
+        );
+        println!("{}", "=".repeat(55));
+        println!();
+        println!(
+            "  Import:    {:>12.0} ticks/s     ({:.2?})",
+            self.import_ticks_per_sec(),
+            self.import_time
+        );
+        println!(
+            "  Snapshots: {:>12.0} snapshots/s ({:.2?})",
+            self.snapshots_per_sec(),
+            self.snapshot_time
+        );
+        println!(
+            "  Replay:    {:>12.0} ticks/s     ({:.2?})",
+            self.replay_ticks_per_sec(),
+            self.replay_time
+        );
+        println!();
+    }
+}
+
+fn rate(count: usize, elapsed: Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+    if secs > 0.0 {
+        count as f64 / secs
+    } else {
+        0.0
+    }
+}
+
+/// Generate a deterministic synthetic dataset: 5-minute BTC windows with a
+/// random-walk oracle and two depth levels per side.
+pub fn generate_dataset(config: &BenchConfig) -> Vec<(Market, Vec<BookTick>)> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let duration_secs = 300;
+    let step_ms = (duration_secs * 1000 / config.snapshots_per_market.max(1) as i64).max(1);
+
+    (0..config.markets)
+        .map(|m| {
+            let open_ts = UnixSecs::new(1_700_000_000 + m as i64 * duration_secs);
+            let id = format!("bench-btc-5m-{}", open_ts);
+            let market = Market {
+                id: id.clone(),
+                platform: Platform::Polymarket,
+                description: "synthetic benchmark market".to_string(),
+                category: "btc".to_string(),
+                open_ts,
+                close_ts: open_ts + duration_secs,
+                duration_secs,
+                outcome: Some(if rng.gen_bool(0.5) {
+                    Outcome::Yes
+                } else {
+                    Outcome::No
+                }),
+                outcome_ts: None,
+            };
+
+            let market_id = MarketId::from(id);
+            let mut oracle = 50_000.0;
+            let mut ticks = Vec::with_capacity(config.snapshots_per_market * 2);
+            for i in 0..config.snapshots_per_market {
+                let offset_ms = OffsetMs::new(i as i64 * step_ms);
+                oracle += rng.gen_range(-20.0..20.0);
+                for side in [Side::Yes, Side::No] {
+                    let best_bid = 0.45 + rng.gen_range(0..6) as f64 * 0.01;
+                    ticks.push(BookTick {
+                        market_id: market_id.clone(),
+                        side,
+                        timestamp_ms: open_ts.at(offset_ms),
+                        offset_ms,
+                        best_bid: Some(best_bid),
+                        best_bid_size: Some(100.0),
+                        best_ask: Some(best_bid + 0.01),
+                        best_ask_size: Some(rng.gen_range(10.0..300.0)),
+                        depth: smallvec![
+                            PriceLevel {
+                                price: 0.49,
+                                cumulative_size: rng.gen_range(50.0..500.0),
+                            },
+                            PriceLevel {
+                                price: 0.50,
+                                cumulative_size: rng.gen_range(20.0..200.0),
+                            },
+                        ],
+                        total_bid_depth: 800.0,
+                        total_ask_depth: 600.0,
+                        reference_price: Some(oracle - 5.0),
+                        oracle_price: Some(oracle),
+                    });
+                }
+            }
+            (market, ticks)
+        })
+        .collect()
+}
+
+/// Run all benchmark stages. The import stage writes to `db_path` if given,
+/// otherwise to an in-memory database.
+pub fn run_bench(config: &BenchConfig, db_path: Option<&Path>) -> Result<BenchReport> {
+    let dataset = generate_dataset(config);
+    let ticks: usize = dataset.iter().map(|(_, t)| t.len()).sum();
+
+    let store = match db_path {
+        Some(p) => SqliteStore::open(p)?,
+        None => SqliteStore::in_memory()?,
+    };
+    store.init()?;
+
+    let start = Instant::now();
+    for (market, market_ticks) in &dataset {
+        store.insert_market(market)?;
+        store.insert_ticks(market_ticks)?;
+    }
+    let import_time = start.elapsed();
+
+    let start = Instant::now();
+    let snapshots: HashMap<String, Vec<BookSnapshot>> = dataset
+        .iter()
+        .map(|(m, t)| (m.id.clone(), ticks_to_snapshots(&m.id, t)))
+        .collect();
+    let snapshot_time = start.elapsed();
+    let snapshot_count = snapshots.values().map(Vec::len).sum();
+
+    let markets: Vec<Market> = dataset.into_iter().map(|(m, _)| m).collect();
+    let engine = ReplayEngine::new(
+        Box::new(DeLiseFillModel::new(DeLiseConfig {
+            seed: Some(config.seed),
+            ..DeLiseConfig::default()
+        })),
+        ReplayConfig::default(),
+    );
+    let start = Instant::now();
+    engine.run_all(
+        &markets,
+        &|id| Ok(snapshots.get(id).cloned().unwrap_or_default()),
+        &BuiltinFactory::new("spread_arb", StrategyParams::default())?,
+    );
+    let replay_time = start.elapsed();
+
+    Ok(BenchReport {
+        markets: markets.len(),
+        ticks,
+        snapshots: snapshot_count,
+        import_time,
+        snapshot_time,
+        replay_time,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small() -> BenchConfig {
+        BenchConfig {
+            markets: 3,
+            snapshots_per_market: 10,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn test_generate_dataset_shape_and_determinism() {
+        let a = generate_dataset(&small());
+        let b = generate_dataset(&small());
+
+        assert_eq!(a.len(), 3);
+        assert!(a.iter().all(|(_, t)| t.len() == 20));
+        assert_eq!(a[2].1[5].oracle_price, b[2].1[5].oracle_price);
+        assert!(a[0].0.close_ts <= a[1].0.open_ts);
+    }
+
+    #[test]
+    fn test_run_bench_counts() {
+        let report = run_bench(&small(), None).unwrap();
+        assert_eq!(report.markets, 3);
+        assert_eq!(report.ticks, 60);
+        assert_eq!(report.snapshots, 30);
+    }
+}
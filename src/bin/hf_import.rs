@@ -3,8 +3,11 @@ use std::path::PathBuf;
 use anyhow::{Context, Result};
 use clap::Parser;
 
-use phantomfill::data::huggingface::{fetch_binance_klines, import_hf_directory, parse_filename};
+use phantomfill::data::huggingface::{
+    fetch_binance_klines, import_hf_directory_with_workers, scan_date_range,
+};
 use phantomfill::data::{DataStore, SqliteStore};
+use phantomfill::logging::{self, LogFormat};
 
 #[derive(Parser)]
 #[command(
@@ -35,17 +38,19 @@ struct Cli {
     /// Limit number of files to import
     #[arg(long)]
     limit: Option<usize>,
+
+    /// Parse threads (default: one per core); one thread writes the database
+    #[arg(long)]
+    workers: Option<usize>,
+
+    /// Log output format
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
 }
 
 fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
-        )
-        .init();
-
     let cli = Cli::parse();
+    logging::init(cli.log_format);
 
     let dir = PathBuf::from(&cli.dir);
     let dest_path = PathBuf::from(&cli.dest);
@@ -82,8 +87,18 @@ fn main() -> Result<()> {
     store.init().context("failed to initialize schema")?;
 
     // Run import.
-    let stats = import_hf_directory(&dir, &store, &klines, cli.coin.as_deref(), cli.limit)
-        .context("import failed")?;
+    let workers = cli
+        .workers
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+    let stats = import_hf_directory_with_workers(
+        &dir,
+        &store,
+        &klines,
+        cli.coin.as_deref(),
+        cli.limit,
+        workers,
+    )
+    .context("import failed")?;
 
     println!();
     println!("Import complete:");
@@ -91,42 +106,9 @@ fn main() -> Result<()> {
     println!("  Files skipped:    {}", stats.files_skipped);
     println!("  Markets imported: {}", stats.markets_imported);
     println!("  Ticks imported:   {}", stats.ticks_imported);
+    println!("  Trades imported:  {}", stats.trades_imported);
     println!("  Rows filtered:    {}", stats.rows_filtered);
     println!();
 
     Ok(())
 }
-
-/// Scan the directory for NDJSON files and determine the min/max timestamps
-/// for the Binance kline fetch.
-fn scan_date_range(dir: &PathBuf, coin_filter: Option<&str>) -> Result<(i64, i64)> {
-    let mut min_ts: Option<i64> = None;
-    let mut max_ts: Option<i64> = None;
-
-    for entry in std::fs::read_dir(dir)
-        .with_context(|| format!("failed to read dir {}", dir.display()))?
-    {
-        let entry = entry?;
-        let path = entry.path();
-        let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-
-        if let Ok(parsed) = parse_filename(filename) {
-            if let Some(coin) = coin_filter {
-                if parsed.coin != coin {
-                    continue;
-                }
-            }
-            let ts = parsed.open_ts;
-            min_ts = Some(min_ts.map_or(ts, |m: i64| m.min(ts)));
-            max_ts = Some(max_ts.map_or(
-                ts + parsed.duration_secs,
-                |m: i64| m.max(ts + parsed.duration_secs),
-            ));
-        }
-    }
-
-    match (min_ts, max_ts) {
-        (Some(min), Some(max)) => Ok((min * 1000, max * 1000)),
-        _ => anyhow::bail!("no valid NDJSON files found in {}", dir.display()),
-    }
-}
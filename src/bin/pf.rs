@@ -1,502 +1,2899 @@
-use std::path::PathBuf;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
 
 use anyhow::{bail, Context, Result};
-use clap::{Parser, Subcommand};
-
-use phantomfill::data::polymarket::{import_from_capture_db, ticks_to_snapshots, PolymarketStore};
-use phantomfill::data::{DataStore, MarketFilter, SqliteStore};
-use phantomfill::fill::{DeLiseConfig, DeLiseFillModel};
-use phantomfill::report::{MonteCarloSummary, Report};
-use phantomfill::replay::{ReplayConfig, ReplayEngine};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+
+use phantomfill::backtest::{BacktestBuilder, RunOutput};
+use phantomfill::bench::{run_bench, BenchConfig};
+use phantomfill::data::hf_hub::{default_cache_dir, download_hf_dataset, HF_DATASET};
+use phantomfill::data::huggingface::{fetch_binance_klines, import_hf_directory_with_workers, scan_date_range};
+use phantomfill::data::stats::parse_timeframe;
+use phantomfill::data::validate::{
+    read_market_ids, validate_store, write_market_ids, IssueKind, MarketReport, ValidateConfig,
+};
+use phantomfill::data::outcomes::{
+    apply_fixes, backfill_outcomes, fetch_klines_for, is_unresolved, resolve_from_klines, resolve_from_oracle,
+    verify_outcomes,
+};
+use phantomfill::data::polymarket::{import_from_capture_db, PolymarketStore};
+use phantomfill::data::{import_csv_files, select_markets, ticks_to_snapshots, CsvMapping, DataStore, DbStats, MarketFilter, RunMeta, SnapshotCache, SqliteStore};
+use phantomfill::doctor::{self, CheckStatus};
+use phantomfill::events::{order_records, EngineEvent, EventBus, OrderRecord};
+use phantomfill::fill::{DeLiseConfig, DeLiseFillModel, FillModel, TradeTape, TradeTapeFillModel};
+use phantomfill::invariants::SnapshotCheck;
+use phantomfill::logging::{self, LogFormat};
+use phantomfill::report::{MonteCarloSummary, Report, RunDiff};
+use phantomfill::portfolio::{parse_category_cap, PortfolioLimits};
+use phantomfill::replay::{EvaluationMode, ReplayConfig};
+use phantomfill::scenarios::{self, Expectation};
+use phantomfill::signals::{self, SignalReport};
 use phantomfill::strategies::fade::{compute_fade_signals, FadeMomentum};
-use phantomfill::strategies::scripted::RhaiStrategy;
-use phantomfill::strategies::{create_strategy, is_known_strategy, list_strategies};
+use phantomfill::strategies::regime::{parse_hours, RegimeConfig, RegimeFilter};
+use phantomfill::strategies::subprocess::{SubprocessCommand, SubprocessPool};
+use phantomfill::strategies::scripted::{
+    parse_script_param, CompiledScript, PersistentState, RhaiStrategy, ScriptLimits, DEFAULT_HISTORY_LEN,
+};
+use phantomfill::strategies::{
+    create_strategy, is_known_strategy, list_strategies, param_keys, FnFactory, StrategyFactory,
+    StrategyParams, StrategySpec,
+};
+use phantomfill::sweep::{self, parse_axis, Sweep, SweepGrid};
+use phantomfill::trace;
+use phantomfill::types::{Bankroll, BookSnapshot, FeeCurve, FeeModel, Latency, Market, Platform, Sizing, UnixSecs, WindowResult};
 
 #[derive(Parser)]
 #[command(name = "pf", about = "PhantomFill -- the honest prediction market backtester")]
 struct Cli {
+    /// Log output format
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+// Parsed once at startup; boxing RunArgs isn't worth the churn.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Run a backtest simulation
-    Run {
-        /// Strategy to simulate
-        #[arg(short, long, default_value = "momentum")]
-        strategy: String,
+    Run(RunArgs),
 
-        /// Path to a custom .rhai strategy script (overrides --strategy)
-        #[arg(long)]
-        script: Option<PathBuf>,
+    /// Run several built-in strategies over the same markets and fill
+    /// seeds and compare them side by side
+    Compare(CompareArgs),
+
+    /// Score a strategy's calls without simulating fills: how often the
+    /// side it backs wins, by signal strength
+    Signals(SignalsArgs),
 
-        /// Bid price
-        #[arg(long, default_value = "0.49")]
-        bid_price: f64,
+    /// Grid-search strategy and fill-model parameters, ranked by realistic PnL
+    Sweep(SweepArgs),
 
-        /// Shares per order
-        #[arg(long, default_value = "10")]
-        shares: f64,
+    /// List available strategies
+    Strategies,
+
+    /// Import data from capture database into PhantomFill format
+    Import {
+        /// Source database path
+        #[arg(long)]
+        source: Option<String>,
 
-        /// Minimum momentum (bps) for signal-based strategies
-        #[arg(long, default_value = "5")]
-        min_bps: f64,
+        /// Destination database path
+        #[arg(long)]
+        dest: String,
 
-        /// Path to source database (default: ~/.local/share/pm_trader/spread_arb.db)
+        /// Filter by asset (e.g. "btc")
         #[arg(long)]
-        db: Option<String>,
+        asset: Option<String>,
+    },
 
-        /// Export results to CSV
+    /// Import CSV orderbook dumps using a column-mapping config
+    ImportCsv {
+        /// CSV files to import
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// TOML file mapping CSV columns to tick fields
         #[arg(long)]
-        csv: Option<String>,
+        mapping: PathBuf,
 
-        /// Random seed for reproducible results
+        /// Destination database path
         #[arg(long)]
-        seed: Option<u64>,
+        dest: String,
+    },
 
-        /// Number of Monte Carlo runs (default: 1 = single run)
-        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
-        runs: u32,
+    /// Import the HuggingFace crypto derivatives dataset, optionally
+    /// downloading it from the hub first
+    ImportHf(HfImportArgs),
 
-        /// Minimum streak length for fade strategy
-        #[arg(long, default_value = "3")]
-        min_streak: usize,
+    /// Benchmark import, snapshot and replay throughput on synthetic data
+    Bench {
+        /// Number of synthetic markets
+        #[arg(long, default_value = "200")]
+        markets: usize,
 
-        /// Maximum streak length for fade strategy
-        #[arg(long, default_value = "6")]
-        max_streak: usize,
+        /// Snapshots per market
+        #[arg(long, default_value = "300")]
+        snapshots: usize,
 
-        /// Use PhantomFill native SQLite format (requires --db)
+        /// Random seed for the synthetic dataset
+        #[arg(long, default_value = "42")]
+        seed: u64,
+
+        /// Import into this database file instead of an in-memory one
         #[arg(long)]
-        native: bool,
+        db: Option<PathBuf>,
     },
 
-    /// List available strategies
-    Strategies,
+    /// Check the environment and databases for common problems
+    Doctor {
+        /// Database to check (repeatable)
+        #[arg(long)]
+        db: Vec<PathBuf>,
 
-    /// Import data from capture database into PhantomFill format
-    Import {
-        /// Source database path
+        /// Output directory to check for write access (repeatable; default: .)
         #[arg(long)]
-        source: Option<String>,
+        out_dir: Vec<PathBuf>,
 
-        /// Destination database path
+        /// Skip network reachability checks
         #[arg(long)]
-        dest: String,
+        offline: bool,
+    },
 
-        /// Filter by asset (e.g. "btc")
+    /// Backfill outcomes of unresolved markets from Binance klines, or
+    /// else the oracle prices recorded with their ticks
+    Resolve {
+        /// PhantomFill native SQLite database
         #[arg(long)]
-        asset: Option<String>,
+        db: String,
+
+        /// Report what would be resolved without writing it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Audit log of the outcomes written (default: <db>.outcomes.log)
+        #[arg(long)]
+        audit_log: Option<PathBuf>,
     },
-}
 
-fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
-        )
-        .init();
+    /// Check a native database's ticks for data quality problems
+    Validate {
+        /// PhantomFill native SQLite database
+        #[arg(long)]
+        db: String,
 
-    let cli = Cli::parse();
+        /// Flag stretches longer than this without a tick on either side
+        #[arg(long, default_value = "60")]
+        max_gap_secs: f64,
 
-    match cli.command {
-        Commands::Run {
-            strategy,
-            script,
-            bid_price,
-            shares,
-            min_bps,
-            min_streak,
-            max_streak,
-            db,
-            csv,
-            seed,
-            runs,
-            native,
-        } => cmd_run(
-            strategy, script, bid_price, shares, min_bps, min_streak, max_streak, db, csv, seed,
-            runs as usize, native,
-        ),
-        Commands::Strategies => cmd_strategies(),
-        Commands::Import {
-            source,
-            dest,
-            asset,
-        } => cmd_import(source, dest, asset),
-    }
-}
+        /// Write the ids of markets with issues here, for `pf run --exclude-file`
+        #[arg(long)]
+        exclusions: Option<PathBuf>,
+    },
 
-#[allow(clippy::too_many_arguments)]
-fn cmd_run(
-    strategy_name: String,
-    script: Option<PathBuf>,
-    bid_price: f64,
-    shares: f64,
-    min_bps: f64,
-    min_streak: usize,
-    max_streak: usize,
-    db_path: Option<String>,
-    csv_path: Option<String>,
-    seed: Option<u64>,
-    runs: usize,
-    native: bool,
-) -> Result<()> {
-    // If a script is provided, validate it can load; otherwise validate built-in strategy.
-    let using_script = script.is_some();
-    if let Some(ref path) = script {
-        // Validate the script loads successfully (compile check).
-        RhaiStrategy::from_file(path, shares, bid_price)
-            .with_context(|| format!("failed to load script {}", path.display()))?;
-    } else if !is_known_strategy(&strategy_name) {
-        let names: Vec<&str> = list_strategies().iter().map(|(n, _)| *n).collect();
-        bail!(
-            "unknown strategy '{}'. available: {}",
-            strategy_name,
-            names.join(", ")
-        );
-    }
+    /// Inspect a native database
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
 
-    if native {
-        return cmd_run_native(
-            strategy_name,
-            script,
-            bid_price,
-            shares,
-            min_bps,
-            min_streak,
-            max_streak,
-            db_path,
-            csv_path,
-            seed,
-            runs,
-        );
-    }
+    /// List and compare runs stored with `pf run --save-run`
+    Runs {
+        #[command(subcommand)]
+        command: RunsCommand,
+    },
 
-    // Open data store.
-    let store = match db_path {
-        Some(ref p) => {
-            let path = PathBuf::from(p);
-            PolymarketStore::open(&path)
-                .with_context(|| format!("failed to open database at {}", p))?
-        }
-        None => PolymarketStore::open_default().context("failed to open default database")?,
-    };
+    /// Inspect and repair stored market outcomes
+    Outcomes {
+        #[command(subcommand)]
+        command: OutcomesCommand,
+    },
 
-    // Load markets with outcomes.
-    let markets = store
-        .list_markets_with_outcomes()
-        .context("failed to list markets")?;
+    /// Develop and check strategy scripts
+    Script {
+        #[command(subcommand)]
+        command: ScriptCommand,
+    },
 
-    if markets.is_empty() {
-        bail!("no markets found in database");
-    }
+    /// Answer newline-delimited JSON-RPC requests on stdin/stdout
+    Rpc {
+        /// PhantomFill native SQLite database
+        #[arg(long)]
+        db: PathBuf,
+    },
 
-    let display_name = if let Some(ref path) = script {
-        format!("script:{}", path.display())
-    } else {
-        strategy_name.clone()
-    };
+    /// Serve a database over HTTP with a backtest job queue (requires the
+    /// `server` feature)
+    #[cfg(feature = "server")]
+    Serve {
+        /// PhantomFill native SQLite database
+        #[arg(long)]
+        db: PathBuf,
 
-    println!(
-        "Loaded {} markets. Running strategy '{}' (bid={}, shares={}, min_bps={})...",
-        markets.len(),
-        display_name,
-        bid_price,
-        shares,
-        min_bps
-    );
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: String,
+    },
 
-    let fill_model_name = "delise-3rule";
+    /// Serve a database over gRPC (requires the `grpc` feature)
+    #[cfg(feature = "grpc")]
+    Grpc {
+        /// PhantomFill native SQLite database
+        #[arg(long)]
+        db: PathBuf,
 
-    // Build strategy factory (fade needs pre-computed signals).
-    let fade_signals = if !using_script && strategy_name == "fade" {
-        let signals = std::sync::Arc::new(compute_fade_signals(&markets, min_streak, max_streak));
-        println!(
-            "  Fade signals: {} of {} windows (streak {}..={})",
-            signals.len(),
-            markets.len(),
-            min_streak,
-            max_streak
-        );
-        Some(signals)
-    } else {
-        None
-    };
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:50051")]
+        bind: String,
+    },
+}
 
-    let make_strategy = |_sn: &str| -> Box<dyn phantomfill::strategies::Strategy> {
-        if let Some(ref path) = script {
-            Box::new(
-                RhaiStrategy::from_file(path, shares, bid_price)
-                    .expect("script already validated"),
-            )
-        } else if let Some(ref signals) = fade_signals {
-            Box::new(FadeMomentum::new(bid_price, shares, signals.clone()))
-        } else {
-            create_strategy(_sn, bid_price, shares, min_bps).expect("strategy already validated")
-        }
-    };
+/// Axes take a list (`0.45,0.47,0.49`) or an inclusive range
+/// (`0.45:0.49:0.01`).
+#[derive(Args)]
+struct SweepArgs {
+    /// Built-in strategy to sweep
+    #[arg(short, long, default_value = "momentum")]
+    strategy: String,
 
-    if runs <= 1 {
-        let fill_model = Box::new(DeLiseFillModel::new(DeLiseConfig {
-            seed,
-            ..DeLiseConfig::default()
-        }));
+    /// Path to source database (default: ~/.local/share/pm_trader/spread_arb.db)
+    #[arg(long)]
+    db: Option<String>,
 
-        let engine = ReplayEngine::new(
-            fill_model,
-            ReplayConfig {
-                bid_price,
-                shares,
-            },
-        );
+    /// Use PhantomFill native SQLite format (requires --db)
+    #[arg(long)]
+    native: bool,
 
-        let results = engine.run_all(
-            &markets,
-            &|slug| store.load_snapshots(slug),
-            &|| make_strategy(&strategy_name),
-        );
+    /// Bid prices
+    #[arg(long, default_value = "0.49")]
+    bid_price: String,
 
-        let report = Report::from_results(&results, &display_name, fill_model_name);
-        report.print();
+    /// Shares per order
+    #[arg(long, default_value = "10")]
+    shares: String,
 
-        if let Some(ref path) = csv_path {
-            let csv_path_buf = PathBuf::from(path);
-            Report::export_csv(&results, &csv_path_buf)
-                .with_context(|| format!("failed to export CSV to {}", path))?;
-            println!("Results exported to {}", path);
-        }
-    } else {
-        let mut reports = Vec::new();
-        for i in 0..runs {
-            let run_seed = seed.map(|s| s + i as u64).unwrap_or_else(|| {
-                use rand::Rng;
-                rand::thread_rng().gen()
-            });
-            let fill_model = Box::new(DeLiseFillModel::new(DeLiseConfig {
-                seed: Some(run_seed),
-                ..DeLiseConfig::default()
-            }));
-            let engine = ReplayEngine::new(
-                fill_model,
-                ReplayConfig {
-                    bid_price,
-                    shares,
-                },
-            );
-            let results = engine.run_all(
-                &markets,
-                &|slug| store.load_snapshots(slug),
-                &|| make_strategy(&strategy_name),
-            );
+    /// Minimum momentum (bps) for signal-based strategies
+    #[arg(long, default_value = "5")]
+    min_bps: String,
 
-            if i == 0 {
-                if let Some(ref path) = csv_path {
-                    let csv_path_buf = PathBuf::from(path);
-                    Report::export_csv(&results, &csv_path_buf)
-                        .with_context(|| format!("failed to export CSV to {}", path))?;
-                    println!("Results exported to {}", path);
-                }
-            }
+    /// Signal time in ms from the open, for the strategy and the fill model
+    #[arg(long, default_value = "90000")]
+    signal_offset_ms: String,
 
-            let report = Report::from_results(&results, &display_name, fill_model_name);
-            reports.push(report);
+    /// Fixed strategy param as KEY=VALUE for every point (repeatable)
+    #[arg(long, value_name = "KEY=VALUE")]
+    param: Vec<String>,
 
-            if (i + 1) % 10 == 0 || i + 1 == runs {
-                println!("Monte Carlo run {}/{} complete", i + 1, runs);
-            }
-        }
-        let summary = MonteCarloSummary::from_reports(reports, seed);
-        summary.print();
-    }
+    /// DeLise non-adverse fill probability per second
+    #[arg(long, default_value = "0.02")]
+    rf: String,
 
-    Ok(())
-}
+    /// DeLise fill probability on an adverse tick
+    #[arg(long, default_value = "0.99")]
+    adverse_fill_prob: String,
 
-/// Run backtest against PhantomFill native SQLite format (e.g. imported HF data).
-#[allow(clippy::too_many_arguments)]
-fn cmd_run_native(
-    strategy_name: String,
-    script: Option<PathBuf>,
-    bid_price: f64,
-    shares: f64,
-    min_bps: f64,
-    min_streak: usize,
-    max_streak: usize,
-    db_path: Option<String>,
-    csv_path: Option<String>,
+    /// DeLise max queue ahead (shares) for winner fills after the signal
+    #[arg(long, default_value = "50")]
+    winner_queue_threshold: String,
+
+    /// Fill seed shared by every point (default: random)
+    #[arg(long)]
     seed: Option<u64>,
-    runs: usize,
-) -> Result<()> {
-    let db = db_path.as_deref().ok_or_else(|| {
-        anyhow::anyhow!("--native mode requires --db path to a PhantomFill SQLite database")
-    })?;
 
-    let store = SqliteStore::open(&PathBuf::from(db))
-        .with_context(|| format!("failed to open native database at {}", db))?;
+    /// Only run the first N markets
+    #[arg(long)]
+    limit: Option<usize>,
 
-    let markets = store
-        .list_markets(&MarketFilter::default())
-        .context("failed to list markets")?;
+    /// Run a random subset of N markets (reproducible with --seed)
+    #[arg(long)]
+    sample: Option<usize>,
 
-    if markets.is_empty() {
-        bail!("no markets found in native database");
-    }
+    /// Export the ranked points to CSV
+    #[arg(long)]
+    csv: Option<String>,
 
-    let display_name = if let Some(ref path) = script {
-        format!("script:{}", path.display())
-    } else {
-        strategy_name.clone()
-    };
+    /// Points shown in the ranked table
+    #[arg(long, default_value = "20")]
+    top: usize,
+}
 
-    println!(
-        "Loaded {} markets (native). Running strategy '{}' (bid={}, shares={}, min_bps={})...",
-        markets.len(),
-        display_name,
-        bid_price,
-        shares,
-        min_bps
-    );
+#[derive(Subcommand)]
+enum ScriptCommand {
+    /// Run a strategy through synthetic scenarios (flat, up_move, down_move,
+    /// adverse_sweep, empty_book) and check its actions
+    Test(ScriptTestArgs),
+}
 
-    let fill_model_name = "delise-3rule";
+#[derive(Args)]
+struct ScriptTestArgs {
+    /// Only run this scenario (repeatable; default: all)
+    #[arg(long = "scenario", value_name = "NAME")]
+    scenarios: Vec<String>,
 
-    // Closure to load snapshots from the native store.
-    let load_snapshots = |market_id: &str| -> anyhow::Result<Vec<_>> {
-        let ticks = store.load_ticks(market_id)?;
-        Ok(ticks_to_snapshots(market_id, &ticks))
-    };
+    /// Expected actions in a scenario: none, any, bid, bid:yes, cancel:no, ...
+    /// (repeatable)
+    #[arg(long, value_name = "SCENARIO=EXPECTATION")]
+    expect: Vec<String>,
 
-    // Build strategy factory (fade needs pre-computed signals).
-    let using_script = script.is_some();
-    let fade_signals = if !using_script && strategy_name == "fade" {
-        let signals = std::sync::Arc::new(compute_fade_signals(&markets, min_streak, max_streak));
-        println!(
-            "  Fade signals: {} of {} windows (streak {}..={})",
-            signals.len(),
-            markets.len(),
-            min_streak,
-            max_streak
-        );
-        Some(signals)
-    } else {
-        None
-    };
+    /// Strategy and script options, as for `pf run`
+    #[command(flatten)]
+    run: RunArgs,
+}
 
-    let make_strategy = |_sn: &str| -> Box<dyn phantomfill::strategies::Strategy> {
-        if let Some(ref path) = script {
-            Box::new(
-                RhaiStrategy::from_file(path, shares, bid_price)
-                    .expect("script already validated"),
-            )
-        } else if let Some(ref signals) = fade_signals {
-            Box::new(FadeMomentum::new(bid_price, shares, signals.clone()))
-        } else {
-            create_strategy(_sn, bid_price, shares, min_bps).expect("strategy already validated")
-        }
-    };
+#[derive(Subcommand)]
+enum DbCommand {
+    /// Market counts, tick coverage, outcomes and tick density
+    Stats {
+        /// PhantomFill native SQLite database
+        #[arg(long)]
+        db: String,
+    },
+}
 
-    if runs <= 1 {
-        let fill_model = Box::new(DeLiseFillModel::new(DeLiseConfig {
-            seed,
-            ..DeLiseConfig::default()
-        }));
-        let engine = ReplayEngine::new(fill_model, ReplayConfig { bid_price, shares });
+#[derive(Subcommand)]
+enum RunsCommand {
+    /// Stored runs with their strategy, fill model, seed and version
+    List {
+        /// SQLite database the runs were saved to
+        #[arg(long)]
+        db: String,
+    },
 
-        let results = engine.run_all(&markets, &load_snapshots, &|| {
-            make_strategy(&strategy_name)
-        });
+    /// Compare two stored runs window by window
+    Diff {
+        /// SQLite database the runs were saved to
+        #[arg(long)]
+        db: String,
 
-        let report = Report::from_results(&results, &display_name, fill_model_name);
-        report.print();
+        /// Run id of the baseline (A)
+        run_a: i64,
 
-        if let Some(ref path) = csv_path {
-            let csv_path_buf = PathBuf::from(path);
-            Report::export_csv(&results, &csv_path_buf)
-                .with_context(|| format!("failed to export CSV to {}", path))?;
-            println!("Results exported to {}", path);
-        }
-    } else {
-        let mut reports = Vec::new();
-        for i in 0..runs {
-            let run_seed = seed.map(|s| s + i as u64).unwrap_or_else(|| {
-                use rand::Rng;
-                rand::thread_rng().gen()
-            });
-            let fill_model = Box::new(DeLiseFillModel::new(DeLiseConfig {
-                seed: Some(run_seed),
-                ..DeLiseConfig::default()
-            }));
-            let engine = ReplayEngine::new(fill_model, ReplayConfig { bid_price, shares });
-            let results = engine.run_all(&markets, &load_snapshots, &|| {
-                make_strategy(&strategy_name)
-            });
-
-            if i == 0 {
-                if let Some(ref path) = csv_path {
-                    let csv_path_buf = PathBuf::from(path);
-                    Report::export_csv(&results, &csv_path_buf)
-                        .with_context(|| format!("failed to export CSV to {}", path))?;
-                    println!("Results exported to {}", path);
-                }
-            }
+        /// Run id to compare against it (B)
+        run_b: i64,
 
-            let report = Report::from_results(&results, &display_name, fill_model_name);
-            reports.push(report);
+        /// Diverged windows to list, largest PnL difference first
+        #[arg(long, default_value = "20")]
+        top: usize,
+    },
+}
 
-            if (i + 1) % 10 == 0 || i + 1 == runs {
-                println!("Monte Carlo run {}/{} complete", i + 1, runs);
-            }
-        }
-        let summary = MonteCarloSummary::from_reports(reports, seed);
-        summary.print();
-    }
+#[derive(Subcommand)]
+enum OutcomesCommand {
+    /// Compare stored outcomes against Binance klines
+    Verify {
+        /// PhantomFill native SQLite database
+        #[arg(long)]
+        db: String,
 
-    Ok(())
+        /// Overwrite mismatched outcomes with the kline resolution
+        #[arg(long)]
+        fix: bool,
+
+        /// Audit log for --fix (default: <db>.outcomes.log)
+        #[arg(long)]
+        audit_log: Option<PathBuf>,
+    },
 }
 
-fn cmd_strategies() -> Result<()> {
-    println!();
-    println!("Available strategies:");
-    println!();
-    for (name, description) in list_strategies() {
-        println!("  {:<16} {}", name, description);
-    }
-    println!();
-    Ok(())
+#[derive(Args)]
+struct CompareArgs {
+    /// Built-in strategies to compare, comma-separated
+    #[arg(long, value_delimiter = ',', required = true)]
+    strategies: Vec<String>,
+
+    #[command(flatten)]
+    run: RunArgs,
 }
 
-fn cmd_import(source: Option<String>, dest: String, asset: Option<String>) -> Result<()> {
-    // Resolve source path.
-    let source_path = match source {
-        Some(ref p) => PathBuf::from(p),
-        None => {
-            let home = std::env::var("HOME").context("HOME not set")?;
-            PathBuf::from(home).join(".local/share/pm_trader/spread_arb.db")
-        }
-    };
+#[derive(Args)]
+struct SignalsArgs {
+    /// Split the calls into this many equal-count strength buckets
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+    buckets: u32,
 
-    println!("Importing from: {}", source_path.display());
-    println!("Destination:    {}", dest);
-    if let Some(ref a) = asset {
-        println!("Asset filter:   {}", a);
-    }
+    #[command(flatten)]
+    run: RunArgs,
+}
 
-    // Open destination store and initialize schema.
-    let dest_path = PathBuf::from(&dest);
-    let store = SqliteStore::open(&dest_path)
-        .with_context(|| format!("failed to open destination at {}", dest))?;
-    store.init().context("failed to initialize destination schema")?;
+#[derive(Args)]
+struct HfImportArgs {
+    /// Directory of NDJSON files (with --download: where to cache them)
+    #[arg(long, required_unless_present = "download")]
+    dir: Option<PathBuf>,
 
-    // Run import.
-    let stats = import_from_capture_db(&source_path, &store, asset.as_deref())
-        .context("import failed")?;
+    /// Fetch the dataset files from the HuggingFace hub before importing,
+    /// resuming partial downloads and skipping files already cached
+    #[arg(long)]
+    download: bool,
 
-    println!();
-    println!("Import complete:");
-    println!("  Markets imported: {}", stats.markets_imported);
-    println!("  Ticks imported:   {}", stats.ticks_imported);
-    println!("  Markets skipped:  {}", stats.markets_skipped);
+    /// Dataset repository to download
+    #[arg(long, default_value = HF_DATASET, requires = "download")]
+    repo: String,
+
+    /// Destination database path
+    #[arg(long)]
+    dest: String,
+
+    /// Filter by coin (e.g. "btc")
+    #[arg(long)]
+    coin: Option<String>,
+
+    /// Binance symbol for oracle prices
+    #[arg(long, default_value = "BTCUSDT")]
+    symbol: String,
+
+    /// Skip fetching Binance klines (outcomes will be None)
+    #[arg(long)]
+    no_oracle: bool,
+
+    /// Limit number of files to download and import
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// Parse threads (default: one per core)
+    #[arg(long)]
+    workers: Option<usize>,
+}
+
+#[derive(Args, Clone)]
+struct RunArgs {
+    /// Read options from this TOML file; flags on the command line override
+    /// it
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Strategy to simulate
+    #[arg(short, long, default_value = "momentum")]
+    strategy: String,
+
+    /// Path to a custom .rhai (or .lua) strategy script, or a directory of them
+    /// (repeatable; overrides --strategy)
+    #[arg(long)]
+    script: Vec<PathBuf>,
+
+    /// Path to a Python strategy file (requires the `python` feature)
+    #[cfg(feature = "python")]
+    #[arg(long, conflicts_with = "script", group = "external")]
+    py_strategy: Option<PathBuf>,
+
+    /// Path to a compiled strategy plugin (.so/.dylib/.dll; requires the
+    /// `plugins` feature)
+    #[cfg(feature = "plugins")]
+    #[arg(long, conflicts_with = "script", group = "external")]
+    plugin: Option<PathBuf>,
+
+    /// Parameter passed to the plugin as KEY=VALUE (repeatable)
+    #[cfg(feature = "plugins")]
+    #[arg(long, value_name = "KEY=VALUE", requires = "plugin")]
+    plugin_param: Vec<String>,
+
+    /// Program to run as the strategy, exchanging JSON lines over its stdin
+    /// and stdout (see the README's External Process Strategies)
+    #[arg(long, value_name = "PROGRAM", conflicts_with = "script", group = "external")]
+    exec: Option<PathBuf>,
+
+    /// Argument passed to the --exec program (repeatable)
+    #[arg(long, value_name = "ARG", requires = "exec", allow_hyphen_values = true)]
+    exec_arg: Vec<String>,
+
+    /// Constant passed to the script as KEY=VALUE (repeatable)
+    #[arg(long, value_name = "KEY=VALUE", requires = "script")]
+    script_param: Vec<String>,
+
+    /// Built-in strategy param as KEY=VALUE (repeatable; `pf strategies`
+    /// lists each strategy's params)
+    #[arg(long, value_name = "KEY=VALUE", conflicts_with = "script")]
+    param: Vec<String>,
+
+    /// Max Rhai operations per script callback (0 = unlimited)
+    #[arg(long, default_value = "1000000")]
+    script_max_ops: u64,
+
+    /// Wall-clock budget per script callback, in milliseconds
+    #[arg(long, default_value = "1000")]
+    script_timeout_ms: u64,
+
+    /// Stop the run on the first script runtime error instead of logging
+    /// it and treating the callback as a no-op
+    #[arg(long, requires = "script")]
+    strict_scripts: bool,
+
+    /// Snapshots kept for the script's history(i) / oracle_ago(s) helpers
+    #[arg(long, default_value_t = DEFAULT_HISTORY_LEN)]
+    script_history: usize,
+
+    /// Bid price
+    #[arg(long, default_value = "0.49")]
+    bid_price: f64,
+
+    /// Shares per order
+    #[arg(long, default_value = "10")]
+    shares: f64,
+
+    /// Reject bids above this many shares (venue tick and minimum-size
+    /// rules always apply)
+    #[arg(long)]
+    max_shares: Option<f64>,
+
+    /// Keep filling resting orders between the close and the market's
+    /// recorded resolution time
+    #[arg(long)]
+    fill_until_resolution: bool,
+
+    /// Check each snapshot for corrupt book data (crossed quotes, negative
+    /// sizes, non-monotone depth, offsets outside the window) and skip,
+    /// repair or abort the window on a violation
+    #[arg(long, value_enum, default_value_t = SnapshotCheck::Off)]
+    check_snapshots: SnapshotCheck,
+
+    /// How resting orders fill: delise (queue and sweeps estimated from
+    /// book changes) or tape (the market's imported trades; requires
+    /// --native and a database imported with trade rows)
+    #[arg(long, value_enum, default_value_t = FillModelKind::Delise)]
+    fill_model: FillModelKind,
+
+    /// Cancel round trip for the delise fill model: a cancelled order can
+    /// still be swept on snapshots up to this many milliseconds later
+    #[arg(long, default_value = "0")]
+    cancel_latency_ms: i64,
+
+    /// Trade fee rate on resting (maker) fills; negative for a rebate
+    #[arg(long, default_value = "0", allow_negative_numbers = true)]
+    maker_fee: f64,
+
+    /// Trade fee rate on taker fills
+    #[arg(long, default_value = "0")]
+    taker_fee: f64,
+
+    /// How trade fees scale with price: notional (rate x price) or
+    /// variance (rate x price x (1 - price), Kalshi's shape)
+    #[arg(long, value_enum, default_value_t = FeeCurve::Notional)]
+    fee_curve: FeeCurve,
+
+    /// Dollars per filled share charged at settlement
+    #[arg(long, default_value = "0")]
+    settlement_fee: f64,
+
+    /// Fraction of a winning share's profit taken at payout
+    #[arg(long, default_value = "0")]
+    winner_fee: f64,
+
+    /// Size orders from a bankroll starting at this many dollars, carried
+    /// from window to window, instead of trading --shares regardless
+    #[arg(long)]
+    bankroll: Option<f64>,
+
+    /// How orders are sized from --bankroll: fixed (--shares, capped at
+    /// the cash left), fraction (--stake-fraction of the bankroll) or kelly
+    /// (--stake-fraction of the Kelly stake for --win-prob)
+    #[arg(long, value_enum, default_value_t = SizingRule::Fixed, requires = "bankroll")]
+    sizing: SizingRule,
+
+    /// Fraction of the bankroll (--sizing fraction) or of the Kelly stake
+    /// (--sizing kelly, default 1) staked per order
+    #[arg(long, required_if_eq("sizing", "fraction"))]
+    stake_fraction: Option<f64>,
+
+    /// Probability an order wins, for --sizing kelly
+    #[arg(long, required_if_eq("sizing", "kelly"))]
+    win_prob: Option<f64>,
+
+    /// Delay every order placement, take and cancel by this many
+    /// milliseconds: it takes effect on the first snapshot at least this
+    /// much later
+    #[arg(long)]
+    latency_ms: Option<i64>,
+
+    /// Add up to this many milliseconds of random latency per action
+    /// (the same draws for a market in every run)
+    #[arg(long, requires = "latency_ms")]
+    latency_jitter_ms: Option<i64>,
+
+    /// Refuse entries while this many other windows open at the same time
+    /// already hold positions
+    #[arg(long)]
+    max_positions: Option<usize>,
+
+    /// Refuse orders that would commit more than this many dollars across
+    /// all windows open at the same time
+    #[arg(long, value_name = "USD")]
+    max_capital: Option<f64>,
+
+    /// Cap the dollars committed at once within each category (USD), or
+    /// within one (CATEGORY=USD); repeatable
+    #[arg(long, value_name = "[CATEGORY=]USD", value_parser = parse_category_cap)]
+    max_category_capital: Vec<(Option<String>, f64)>,
+
+    /// Run windows that overlap in time (say btc 5m and 15m) side by side
+    /// on one clock, so they share the bankroll and portfolio limits as
+    /// they trade, instead of one after another
+    #[arg(long, conflicts_with = "resume")]
+    shared_timeline: bool,
+
+    /// Minimum momentum (bps) for signal-based strategies
+    #[arg(long, default_value = "5")]
+    min_bps: f64,
+
+    /// Only let the strategy enter between these UTC hours, e.g. 13-21
+    /// (13:00 to 21:00) or 22-4; cancels and sells still go through
+    #[arg(long, value_name = "START-END", value_parser = parse_hours)]
+    only_hours: Option<(u32, u32)>,
+
+    /// Only let the strategy enter once the oracle's realized volatility
+    /// between the open and the signal time reaches this many bps
+    #[arg(long, value_name = "BPS")]
+    min_realized_vol_bps: Option<f64>,
+
+    /// Path to source database (default: ~/.local/share/pm_trader/spread_arb.db)
+    #[arg(long)]
+    db: Option<String>,
+
+    /// Export results to CSV
+    #[arg(long)]
+    csv: Option<String>,
+
+    /// Export results to an Arrow IPC (Feather) file, and per-order results
+    /// next to it as NAME.orders.arrow (requires the `arrow` feature)
+    #[cfg(feature = "arrow")]
+    #[arg(long)]
+    arrow: Option<String>,
+
+    /// Store every run's window results in this SQLite database (created if
+    /// missing; may be the --db file), with the strategy, settings, fill
+    /// model, seed and version that produced them
+    #[arg(long, value_name = "DB")]
+    save_run: Option<PathBuf>,
+
+    /// Random seed for reproducible results
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Number of Monte Carlo runs (default: 1 = single run)
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    runs: u32,
+
+    /// Monte Carlo runs to execute at once. Above 1, all threads replay one
+    /// in-memory copy of every selected market's snapshots; PERSIST_STATE
+    /// scripts always run one pass at a time
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    threads: u32,
+
+    /// Minimum streak length for fade strategy
+    #[arg(long, default_value = "3")]
+    min_streak: usize,
+
+    /// Maximum streak length for fade strategy
+    #[arg(long, default_value = "6")]
+    max_streak: usize,
+
+    /// Use PhantomFill native SQLite format (requires --db)
+    #[arg(long)]
+    native: bool,
+
+    /// Only run the market ids listed in this file, one per line
+    #[arg(long)]
+    include_file: Option<PathBuf>,
+
+    /// Skip the market ids listed in this file, one per line (e.g. from
+    /// `pf validate --exclusions`)
+    #[arg(long)]
+    exclude_file: Option<PathBuf>,
+
+    /// Only run markets in this category (e.g. btc)
+    #[arg(long)]
+    category: Option<String>,
+
+    /// Only run markets with this window length (e.g. 5m, 15m, 1h)
+    #[arg(long, value_parser = parse_timeframe)]
+    timeframe: Option<i64>,
+
+    /// Only run markets opening at or after this time (YYYY-MM-DD, RFC 3339
+    /// or Unix seconds)
+    #[arg(long, value_parser = parse_time)]
+    from: Option<i64>,
+
+    /// Only run markets closing at or before this time (YYYY-MM-DD, RFC 3339
+    /// or Unix seconds)
+    #[arg(long, value_parser = parse_time)]
+    to: Option<i64>,
+
+    /// Only run markets on this platform (e.g. polymarket, kalshi)
+    #[arg(long)]
+    platform: Option<String>,
+
+    /// Only run the first N markets
+    #[arg(long, visible_alias = "max-markets")]
+    limit: Option<usize>,
+
+    /// Run a random subset of N markets (reproducible with --seed)
+    #[arg(long)]
+    sample: Option<usize>,
+
+    /// Re-run whenever the --script file changes (on a 100-market sample
+    /// unless --limit/--sample is given)
+    #[arg(long, requires = "script", conflicts_with = "resume")]
+    watch: bool,
+
+    /// Replay only this market and print a tick-by-tick log of its
+    /// snapshots, the strategy's actions and the fill model's decisions
+    #[arg(long, value_name = "ID", conflicts_with_all = ["watch", "resume", "save_run"])]
+    trace_market: Option<String>,
+
+    /// Memory-mapped snapshot cache for --native runs: built on the first
+    /// run, read instead of SQLite afterwards; delete it after re-importing
+    /// (requires the `mmap` feature)
+    #[cfg(feature = "mmap")]
+    #[arg(long, requires = "native")]
+    snapshot_cache: Option<PathBuf>,
+
+    /// Keep --native snapshots in memory after their first load, so later
+    /// runs, strategies and --watch re-runs skip SQLite
+    #[arg(long, requires = "native")]
+    memory_cache: bool,
+
+    /// Most markets --memory-cache keeps, evicting the least recently used
+    #[arg(long, requires = "memory_cache")]
+    memory_cache_markets: Option<usize>,
+
+    /// Checkpoint file: resume from it if present, and save progress to it
+    #[arg(long)]
+    resume: Option<PathBuf>,
+
+    /// Save the checkpoint every N markets (with --resume)
+    #[arg(long, default_value = "100")]
+    checkpoint_every: usize,
+}
+
+fn main() -> Result<()> {
+    let cli = parse_cli()?;
+    if matches!(cli.command, Commands::Rpc { .. }) {
+        logging::init_stderr(cli.log_format);
+    } else {
+        logging::init(cli.log_format);
+    }
+
+    match cli.command {
+        Commands::Run(args) => cmd_run(args),
+        Commands::Compare(args) => cmd_compare(args),
+        Commands::Signals(args) => cmd_signals(args),
+        Commands::Sweep(args) => cmd_sweep(args),
+        Commands::Strategies => cmd_strategies(),
+        Commands::Import {
+            source,
+            dest,
+            asset,
+        } => cmd_import(source, dest, asset),
+        Commands::ImportCsv { files, mapping, dest } => cmd_import_csv(files, mapping, dest),
+        Commands::ImportHf(args) => cmd_import_hf(args),
+        Commands::Bench {
+            markets,
+            snapshots,
+            seed,
+            db,
+        } => cmd_bench(markets, snapshots, seed, db),
+        Commands::Doctor {
+            db,
+            out_dir,
+            offline,
+        } => cmd_doctor(db, out_dir, offline),
+        Commands::Resolve { db, dry_run, audit_log } => cmd_resolve(db, dry_run, audit_log),
+        Commands::Validate {
+            db,
+            max_gap_secs,
+            exclusions,
+        } => cmd_validate(&db, max_gap_secs, exclusions),
+        Commands::Db {
+            command: DbCommand::Stats { db },
+        } => cmd_db_stats(&db),
+        Commands::Runs {
+            command: RunsCommand::List { db },
+        } => cmd_runs_list(&db),
+        Commands::Runs {
+            command: RunsCommand::Diff { db, run_a, run_b, top },
+        } => cmd_runs_diff(&db, run_a, run_b, top),
+        Commands::Outcomes {
+            command: OutcomesCommand::Verify { db, fix, audit_log },
+        } => cmd_outcomes_verify(db, fix, audit_log),
+        Commands::Script {
+            command: ScriptCommand::Test(args),
+        } => cmd_script_test(args),
+        Commands::Rpc { db } => cmd_rpc(&db),
+        #[cfg(feature = "server")]
+        Commands::Serve { db, bind } => phantomfill::server::serve(&db, &bind),
+        #[cfg(feature = "grpc")]
+        Commands::Grpc { db, bind } => phantomfill::grpc::serve(&db, &bind),
+    }
+}
+
+/// Parse the command line, expanding a run's `--config FILE` into the flags
+/// it sets. Those go right after the subcommand, ahead of the command
+/// line's own flags, so a flag given on both is taken from the command line.
+fn parse_cli() -> Result<Cli> {
+    let cli = Cli::parse();
+    let (path, config): (&[&str], _) = match &cli.command {
+        Commands::Run(args) => (&["run"], &args.config),
+        Commands::Compare(args) => (&["compare"], &args.run.config),
+        Commands::Signals(args) => (&["signals"], &args.run.config),
+        Commands::Script {
+            command: ScriptCommand::Test(args),
+        } => (&["script", "test"], &args.run.config),
+        _ => return Ok(cli),
+    };
+    let Some(config) = config else {
+        return Ok(cli);
+    };
+
+    let mut command = Cli::command();
+    for name in path {
+        command = command
+            .find_subcommand(name)
+            .cloned()
+            .expect("subcommand exists");
+    }
+    let flags = config_flags(config, &command)?;
+
+    let mut argv: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    let mut at = 1;
+    for name in path {
+        at += argv[at..].iter().position(|a| a == name).expect("subcommand parsed") + 1;
+    }
+    argv.splice(at..at, flags.into_iter().map(Into::into));
+    let matches = Cli::command()
+        .args_override_self(true)
+        .try_get_matches_from(argv)
+        .unwrap_or_else(|e| e.exit());
+    Ok(Cli::from_arg_matches(&matches)?)
+}
+
+/// The flags a `--config` file sets, for `command`'s arguments. Keys are the
+/// long flag names, with `_` or `-`; `true` sets a switch and a list repeats
+/// its flag. The `params`, `script_params` and `plugin_params` tables become
+/// `--param`, `--script-param` and `--plugin-param` KEY=VALUE pairs.
+fn config_flags(path: &Path, command: &clap::Command) -> Result<Vec<String>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let table: toml::Table = toml::from_str(&text).with_context(|| format!("invalid config {}", path.display()))?;
+
+    let scalar = |key: &str, value: &toml::Value| -> Result<String> {
+        Ok(match value {
+            toml::Value::String(s) => s.clone(),
+            toml::Value::Integer(n) => n.to_string(),
+            toml::Value::Float(x) => x.to_string(),
+            toml::Value::Boolean(b) => b.to_string(),
+            toml::Value::Datetime(dt) => dt.to_string(),
+            _ => bail!("`{}` in {} takes a single value", key, path.display()),
+        })
+    };
+
+    let mut flags = Vec::new();
+    for (key, value) in &table {
+        let flag = match key.as_str() {
+            "params" => "param".to_string(),
+            "script_params" | "script-params" => "script-param".to_string(),
+            "plugin_params" | "plugin-params" => "plugin-param".to_string(),
+            _ => key.replace('_', "-"),
+        };
+        let known = command
+            .get_arguments()
+            .any(|a| a.get_long() == Some(flag.as_str()));
+        if !known || flag == "config" {
+            bail!("unknown option `{}` in {}", key, path.display());
+        }
+        match value {
+            toml::Value::Table(pairs) => {
+                if !flag.ends_with("param") {
+                    bail!("`{}` in {} takes a value, not a table", key, path.display());
+                }
+                for (k, v) in pairs {
+                    flags.push(format!("--{}={}={}", flag, k, scalar(k, v)?));
+                }
+            }
+            toml::Value::Array(items) => {
+                for item in items {
+                    flags.push(format!("--{}={}", flag, scalar(key, item)?));
+                }
+            }
+            toml::Value::Boolean(true) => flags.push(format!("--{}", flag)),
+            toml::Value::Boolean(false) => {}
+            _ => flags.push(format!("--{}={}", flag, scalar(key, value)?)),
+        }
+    }
+    Ok(flags)
+}
+
+fn cmd_run(args: RunArgs) -> Result<()> {
+    // If scripts are provided, validate they load; otherwise validate built-in strategy.
+    let scripts = script_paths(&args.script)?;
+    if let Some(external) = external_strategy(&args) {
+        external.load(&args)?;
+    } else if !scripts.is_empty() {
+        for path in &scripts {
+            // Validate the script loads successfully (compile check).
+            load_script(&args, path, None)
+                .with_context(|| format!("failed to load script {}", path.display()))?;
+        }
+    } else {
+        check_strategy(&args.strategy)?;
+        strategy_params(&args, std::slice::from_ref(&args.strategy))?;
+    }
+    if let Some(ref id) = args.trace_market {
+        if scripts.len() > 1 {
+            bail!("--trace-market follows one strategy; pass a single --script");
+        }
+        return trace_market(&args, scripts.first(), id);
+    }
+    run_from_db(&args, &scripts, std::slice::from_ref(&args.strategy))
+}
+
+fn cmd_compare(args: CompareArgs) -> Result<()> {
+    let run = &args.run;
+    if !run.script.is_empty() || external_strategy(run).is_some() {
+        bail!("pf compare takes built-in --strategies; compare scripts with repeated --script on pf run");
+    }
+    if run.watch || run.trace_market.is_some() {
+        bail!("--watch and --trace-market are not supported by pf compare");
+    }
+    let mut strategies: Vec<String> = Vec::new();
+    for name in args.strategies.iter().map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        check_strategy(name)?;
+        if !strategies.iter().any(|s| s == name) {
+            strategies.push(name.to_string());
+        }
+    }
+    if strategies.len() < 2 {
+        bail!("--strategies needs at least two different strategies");
+    }
+    strategy_params(run, &strategies)?;
+    run_from_db(run, &[], &strategies)
+}
+
+fn cmd_signals(args: SignalsArgs) -> Result<()> {
+    let run = &args.run;
+    if run.watch || run.resume.is_some() || run.save_run.is_some() || run.runs > 1 || run.trace_market.is_some() {
+        bail!("pf signals replays each window once and fills nothing; --watch, --resume, --save-run, --runs and --trace-market don't apply");
+    }
+    let scripts = script_paths(&run.script)?;
+    if scripts.len() > 1 {
+        bail!("pf signals scores one strategy at a time; pass a single --script");
+    }
+    if let Some(external) = external_strategy(run) {
+        external.load(run)?;
+    } else if let Some(path) = scripts.first() {
+        load_script(run, path, None).with_context(|| format!("failed to load script {}", path.display()))?;
+    } else {
+        check_strategy(&run.strategy)?;
+        strategy_params(run, std::slice::from_ref(&run.strategy))?;
+    }
+
+    with_markets(run, |markets, snapshots| score_signals(&args, scripts.first(), markets, snapshots))
+}
+
+/// Open the database named by `args` and call `f` with its markets, after
+/// the filters and id lists, and a snapshot loader.
+fn with_markets(
+    args: &RunArgs,
+    f: impl FnOnce(Vec<Market>, &dyn Fn(&str) -> Result<Vec<BookSnapshot>>) -> Result<()>,
+) -> Result<()> {
+    if args.native {
+        let db = args.db.as_deref().ok_or_else(|| {
+            anyhow::anyhow!("--native mode requires --db path to a PhantomFill SQLite database")
+        })?;
+        let store = SqliteStore::open(&PathBuf::from(db))
+            .with_context(|| format!("failed to open native database at {}", db))?;
+        let markets = store
+            .list_markets(&market_filter(args))
+            .context("failed to list markets")?;
+        let markets = apply_id_lists(args, markets)?;
+        return f(markets, &|id| Ok(ticks_to_snapshots(id, &store.load_ticks(id)?)));
+    }
+
+    let store = match args.db {
+        Some(ref p) => PolymarketStore::open(&PathBuf::from(p))
+            .with_context(|| format!("failed to open database at {}", p))?,
+        None => PolymarketStore::open_default().context("failed to open default database")?,
+    };
+    let filter = market_filter(args);
+    let markets: Vec<Market> = store
+        .list_markets_with_outcomes()
+        .context("failed to list markets")?
+        .into_iter()
+        .filter(|m| filter.matches(m))
+        .collect();
+    let markets = apply_id_lists(args, markets)?;
+    f(markets, &|slug| store.load_snapshots(slug))
+}
+
+/// Replay `markets` in signal-only mode and print what the strategy called.
+fn score_signals(
+    args: &SignalsArgs,
+    script: Option<&PathBuf>,
+    markets: Vec<Market>,
+    snapshots: &dyn Fn(&str) -> Result<Vec<BookSnapshot>>,
+) -> Result<()> {
+    let run = &args.run;
+    if markets.is_empty() {
+        bail!("no markets found in database");
+    }
+    let external = external_strategy(run);
+    let builtin = external.is_none() && script.is_none();
+    let params = if builtin {
+        strategy_params(run, std::slice::from_ref(&run.strategy))?
+    } else {
+        StrategyParams::default()
+    };
+    let fade_signals = (builtin && run.strategy == "fade").then(|| {
+        std::sync::Arc::new(compute_fade_signals(&markets, params.min_streak, params.max_streak))
+    });
+    let markets = select_markets(markets, run.limit, run.sample, run.seed);
+    let display_name = strategy_display_name(run, external.as_ref(), script);
+
+    println!(
+        "Scoring the calls of '{}' over {} markets (no fills)...",
+        display_name,
+        markets.len()
+    );
+    let loaded = script.map(|path| LoadedScript::load(run, path)).transpose()?;
+    let state = PersistentState::default();
+    let factory = FnFactory::new(|| {
+        start_strategy(
+            &display_name,
+            build_strategy(run, external.as_ref(), loaded.as_ref(), &state, &display_name, fade_signals.as_ref(), &params),
+        )
+    });
+    let windows = signals::evaluate(&markets, snapshots, &factory, replay_config(run))?;
+
+    println!();
+    SignalReport::new(&windows, args.buckets as usize).print();
+    if let Some(ref path) = run.csv {
+        signals::export_csv(&windows, Path::new(path))?;
+        println!();
+        println!("Signals exported to {}", path);
+    }
+    Ok(())
+}
+
+/// Replay market `id` under the DeLise fill model and print its trace.
+fn trace_market(args: &RunArgs, script: Option<&PathBuf>, id: &str) -> Result<()> {
+    if args.fill_model == FillModelKind::Tape {
+        bail!("--trace-market explains the delise fill model's decisions; it can't trace --fill-model tape");
+    }
+    with_markets(args, |markets, snapshots| {
+        let Some(market) = markets.iter().find(|m| m.id == id) else {
+            bail!("market {} not found (or excluded by the filters)", id);
+        };
+        let external = external_strategy(args);
+        let builtin = external.is_none() && script.is_none();
+        let params = if builtin {
+            strategy_params(args, std::slice::from_ref(&args.strategy))?
+        } else {
+            StrategyParams::default()
+        };
+        // Fade reads the streak leading into the window from the markets before it.
+        let fade_signals = (builtin && args.strategy == "fade").then(|| {
+            std::sync::Arc::new(compute_fade_signals(&markets, params.min_streak, params.max_streak))
+        });
+        let snaps = snapshots(id).with_context(|| format!("failed to load snapshots for {}", id))?;
+        let loaded = script.map(|path| LoadedScript::load(args, path)).transpose()?;
+        let state = PersistentState::default();
+        let name = strategy_display_name(args, external.as_ref(), script);
+        let mut strategy = build_strategy(args, external.as_ref(), loaded.as_ref(), &state, &name, fade_signals.as_ref(), &params)?;
+        let fill_model = DeLiseFillModel::new(DeLiseConfig {
+            seed: args.seed,
+            cancel_latency_ms: args.cancel_latency_ms,
+            ..DeLiseConfig::default()
+        })
+        .traced();
+        println!("Tracing '{}' on {}", name, id);
+        trace::trace_window(
+            fill_model,
+            replay_config(args),
+            args.seed.unwrap_or(0),
+            market,
+            &snaps,
+            strategy.as_mut(),
+            &mut std::io::stdout().lock(),
+        )?;
+        Ok(())
+    })
+}
+
+/// What reports call the strategy `args` selects.
+fn strategy_display_name(args: &RunArgs, external: Option<&ExternalStrategy<'_>>, script: Option<&PathBuf>) -> String {
+    match (external, script) {
+        (Some(external), _) => external.display_name(),
+        (None, Some(path)) => format!("script:{}", path.display()),
+        (None, None) => args.strategy.clone(),
+    }
+}
+
+/// The built-in strategy settings: the bid, size and streak flags, then
+/// each --param, which at least one of `strategies` must read.
+fn strategy_params(args: &RunArgs, strategies: &[String]) -> Result<StrategyParams> {
+    let mut params = StrategyParams {
+        bid_price: args.bid_price,
+        shares: args.shares,
+        min_bps: args.min_bps,
+        min_streak: args.min_streak,
+        max_streak: args.max_streak,
+        ..StrategyParams::default()
+    };
+    for assignment in &args.param {
+        let key = assignment.split_once('=').map_or(assignment.as_str(), |(key, _)| key.trim());
+        let Some(strategy) = strategies
+            .iter()
+            .find(|s| param_keys(s).contains(&key))
+            .or(strategies.first())
+        else {
+            bail!("--param only applies to built-in strategies");
+        };
+        params.apply(strategy, std::slice::from_ref(assignment))?;
+    }
+    Ok(params)
+}
+
+fn check_strategy(name: &str) -> Result<()> {
+    if !is_known_strategy(name) {
+        let names: Vec<&str> = list_strategies().iter().map(|(n, _)| *n).collect();
+        bail!("unknown strategy '{}'. available: {}", name, names.join(", "));
+    }
+    Ok(())
+}
+
+/// Open the database named by `args` and run `scripts`, or with none the
+/// built-in `strategies`, over its markets.
+fn run_from_db(args: &RunArgs, scripts: &[PathBuf], strategies: &[String]) -> Result<()> {
+    if args.native {
+        let db = args.db.as_deref().ok_or_else(|| {
+            anyhow::anyhow!("--native mode requires --db path to a PhantomFill SQLite database")
+        })?;
+
+        let store = SqliteStore::open(&PathBuf::from(db))
+            .with_context(|| format!("failed to open native database at {}", db))?;
+
+        let markets = store
+            .list_markets(&market_filter(args))
+            .context("failed to list markets")?;
+        let markets = apply_id_lists(args, markets)?;
+
+        if markets.is_empty() {
+            bail!("no markets found in native database");
+        }
+
+        let tape = match args.fill_model {
+            FillModelKind::Delise => None,
+            FillModelKind::Tape => {
+                let tape = TradeTape::load(&store, &markets).context("failed to load trades")?;
+                if tape.is_empty() {
+                    bail!("--fill-model tape: no trades in {}; re-import it with trade rows", db);
+                }
+                println!("Loaded {} trades", tape.len());
+                Some(tape)
+            }
+        };
+
+        let cache = match args.memory_cache_markets {
+            Some(max) => SnapshotCache::with_capacity(max),
+            None => SnapshotCache::new(),
+        };
+        let cache = args.memory_cache.then_some(&cache);
+
+        return run_or_watch(
+            args,
+            scripts,
+            strategies,
+            markets,
+            Snapshots::Store(&store, tape.as_ref(), cache),
+            " (native)",
+        );
+    }
+
+    if args.fill_model == FillModelKind::Tape {
+        bail!("--fill-model tape requires --native");
+    }
+
+    // Open data store.
+    let store = match args.db {
+        Some(ref p) => {
+            let path = PathBuf::from(p);
+            PolymarketStore::open(&path)
+                .with_context(|| format!("failed to open database at {}", p))?
+        }
+        None => PolymarketStore::open_default().context("failed to open default database")?,
+    };
+
+    // Load markets with outcomes.
+    let filter = market_filter(args);
+    let markets: Vec<Market> = store
+        .list_markets_with_outcomes()
+        .context("failed to list markets")?
+        .into_iter()
+        .filter(|m| filter.matches(m))
+        .collect();
+    let markets = apply_id_lists(args, markets)?;
+
+    if markets.is_empty() {
+        bail!("no markets found in database");
+    }
+
+    run_or_watch(args, scripts, strategies, markets, Snapshots::Loader(&|slug| store.load_snapshots(slug)), "")
+}
+
+/// The markets selected by --category, --timeframe, --from, --to and
+/// --platform.
+fn market_filter(args: &RunArgs) -> MarketFilter {
+    MarketFilter {
+        platform: args.platform.as_deref().map(Platform::from),
+        category: args.category.clone(),
+        duration_secs: args.timeframe,
+        min_ts: args.from,
+        max_ts: args.to,
+    }
+}
+
+/// Unix seconds from `YYYY-MM-DD` (midnight UTC), an RFC 3339 time or a
+/// plain number of seconds.
+fn parse_time(value: &str) -> Result<i64> {
+    if let Ok(secs) = value.parse::<i64>() {
+        return Ok(secs);
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(chrono::NaiveTime::MIN).and_utc().timestamp());
+    }
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.timestamp())
+        .map_err(|_| anyhow::anyhow!("expected YYYY-MM-DD, an RFC 3339 time or Unix seconds, got `{}`", value))
+}
+
+/// `markets` narrowed to those listed in --include-file, then without those
+/// listed in --exclude-file.
+fn apply_id_lists(args: &RunArgs, mut markets: Vec<Market>) -> Result<Vec<Market>> {
+    if let Some(ref path) = args.include_file {
+        let included = read_market_ids(path)?;
+        markets.retain(|m| included.contains(&m.id));
+        println!("Included {} of {} markets listed in {}", markets.len(), included.len(), path.display());
+    }
+    if let Some(ref path) = args.exclude_file {
+        let excluded = read_market_ids(path)?;
+        let before = markets.len();
+        markets.retain(|m| !excluded.contains(&m.id));
+        println!("Excluded {} markets listed in {}", before - markets.len(), path.display());
+    }
+    Ok(markets)
+}
+
+fn cmd_sweep(args: SweepArgs) -> Result<()> {
+    let axis = |name: &str, value: &str| parse_axis(value).with_context(|| format!("bad --{}", name));
+    let grid = SweepGrid {
+        bid_price: axis("bid-price", &args.bid_price)?,
+        shares: axis("shares", &args.shares)?,
+        min_bps: axis("min-bps", &args.min_bps)?,
+        signal_offset_ms: axis("signal-offset-ms", &args.signal_offset_ms)?
+            .into_iter()
+            .map(|ms| ms.round() as i64)
+            .collect(),
+        rf: axis("rf", &args.rf)?,
+        adverse_fill_prob: axis("adverse-fill-prob", &args.adverse_fill_prob)?,
+        winner_queue_threshold: axis("winner-queue-threshold", &args.winner_queue_threshold)?,
+    };
+    // Seed drawn here so it can be printed for a rerun.
+    let seed = args.seed.unwrap_or_else(|| {
+        use rand::Rng;
+        rand::thread_rng().gen()
+    });
+    let mut params = StrategyParams::default();
+    params.apply(&args.strategy, &args.param)?;
+    let sweep = Sweep {
+        strategy: args.strategy.clone(),
+        params,
+        grid,
+        seed: Some(seed),
+        ..Sweep::default()
+    };
+
+    // Every point replays the same markets, so load their snapshots once.
+    let (markets, cache) = if args.native {
+        let db = args.db.as_deref().ok_or_else(|| {
+            anyhow::anyhow!("--native mode requires --db path to a PhantomFill SQLite database")
+        })?;
+        let store = SqliteStore::open(&PathBuf::from(db))
+            .with_context(|| format!("failed to open native database at {}", db))?;
+        let markets = store
+            .list_markets(&MarketFilter::default())
+            .context("failed to list markets")?;
+        preload(&args, seed, markets, |id| Ok(ticks_to_snapshots(id, &store.load_ticks(id)?)))?
+    } else {
+        let store = match args.db {
+            Some(ref p) => PolymarketStore::open(&PathBuf::from(p))
+                .with_context(|| format!("failed to open database at {}", p))?,
+            None => PolymarketStore::open_default().context("failed to open default database")?,
+        };
+        let markets = store
+            .list_markets_with_outcomes()
+            .context("failed to list markets")?;
+        preload(&args, seed, markets, |slug| store.load_snapshots(slug))?
+    };
+    let snapshots = |id: &str| -> Result<Vec<BookSnapshot>> {
+        cache
+            .get(id)
+            .cloned()
+            .with_context(|| format!("market {} was not loaded", id))
+    };
+
+    let points = sweep.grid.len();
+    println!(
+        "Sweeping '{}' over {} points x {} markets (seed {})...",
+        args.strategy,
+        points,
+        markets.len(),
+        seed
+    );
+    let ranked = sweep.run(&markets, &snapshots, |i, r| {
+        println!(
+            "  [{}/{}] bid={} shares={} min_bps={} signal={}ms rf={} adv={} queue={}: realistic {:+.2}",
+            i + 1,
+            points,
+            r.point.bid_price,
+            r.point.shares,
+            r.point.min_bps,
+            r.point.signal_offset_ms,
+            r.point.rf,
+            r.point.adverse_fill_prob,
+            r.point.winner_queue_threshold,
+            r.report.realistic_total_pnl
+        );
+    })?;
+    sweep::print_table(&ranked, args.top);
+
+    if let Some(ref path) = args.csv {
+        sweep::export_csv(&ranked, std::path::Path::new(path))
+            .with_context(|| format!("failed to export CSV to {}", path))?;
+        println!("Sweep exported to {}", path);
+    }
+    Ok(())
+}
+
+/// Snapshots by market id, loaded up front.
+type Preloaded = HashMap<String, Vec<BookSnapshot>>;
+
+/// The sweep's markets (after --limit/--sample) and their snapshots.
+fn preload(
+    args: &SweepArgs,
+    seed: u64,
+    markets: Vec<Market>,
+    load: impl Fn(&str) -> Result<Vec<BookSnapshot>>,
+) -> Result<(Vec<Market>, Preloaded)> {
+    if markets.is_empty() {
+        bail!("no markets found in database");
+    }
+    let markets = select_markets(markets, args.limit, args.sample, Some(seed));
+    let snapshots = markets
+        .iter()
+        .map(|m| Ok((m.id.clone(), load(&m.id)?)))
+        .collect::<Result<_>>()?;
+    Ok((markets, snapshots))
+}
+
+fn cmd_script_test(args: ScriptTestArgs) -> Result<()> {
+    let run = &args.run;
+    let scenarios = scenarios::select(&args.scenarios)?;
+    let expectations = args
+        .expect
+        .iter()
+        .map(|e| scenarios::parse_expect(e))
+        .collect::<Result<Vec<_>>>()?;
+
+    let scripts = script_paths(&run.script)?;
+    let external = external_strategy(run);
+    if external.is_none() && scripts.is_empty() && !is_known_strategy(&run.strategy) {
+        let names: Vec<&str> = list_strategies().iter().map(|(n, _)| *n).collect();
+        bail!(
+            "unknown strategy '{}'. available: {}",
+            run.strategy,
+            names.join(", ")
+        );
+    }
+    let targets: Vec<Option<&PathBuf>> = if scripts.is_empty() {
+        vec![None]
+    } else {
+        scripts.iter().map(Some).collect()
+    };
+
+    let config = ReplayConfig {
+        bid_price: run.bid_price,
+        shares: run.shares,
+        stop_on_abort: false,
+        max_shares: run.max_shares,
+        fill_until_resolution: run.fill_until_resolution,
+        snapshot_check: run.check_snapshots,
+        fees: fee_model(run),
+        bankroll: bankroll(run),
+        latency: latency(run),
+        mode: EvaluationMode::Execute,
+        portfolio: portfolio(run),
+        shared_timeline: run.shared_timeline,
+    };
+    let seed = run.seed.unwrap_or(0);
+    let mut failed = 0;
+    let mut total = 0;
+
+    for script in targets {
+        let (display_name, make_strategy): (String, Box<dyn Fn() -> Result<_>>) =
+            match (external.as_ref(), script) {
+                (Some(external), _) => (
+                    external.display_name(),
+                    Box::new(move || external.load(run)),
+                ),
+                (None, Some(path)) => (
+                    format!("script:{}", path.display()),
+                    Box::new(move || load_script(run, path, None)),
+                ),
+                (None, None) => (
+                    run.strategy.clone(),
+                    Box::new(|| {
+                        create_strategy(&run.strategy, &strategy_params(run, std::slice::from_ref(&run.strategy))?)
+                            .context("unknown strategy")
+                    }),
+                ),
+            };
+        // Fail fast on compile errors before running any scenario.
+        make_strategy().with_context(|| format!("failed to load {}", display_name))?;
+
+        println!("Testing '{}' against {} scenario(s)", display_name, scenarios.len());
+        for scenario in &scenarios {
+            let wanted: Vec<Expectation> = expectations
+                .iter()
+                .filter(|(name, _)| name == scenario.name)
+                .map(|(_, e)| *e)
+                .collect();
+            let mut strategy = make_strategy()?;
+            let outcome =
+                scenarios::run_scenario(scenario, strategy.as_mut(), config.clone(), seed, &wanted);
+
+            total += 1;
+            let status = if outcome.passed() { "ok" } else { "FAIL" };
+            println!(
+                "  {:<14} {:<5} {}",
+                scenario.name,
+                status,
+                scenarios::summarize_actions(&outcome.actions, 3)
+            );
+            for failure in &outcome.failures {
+                println!("  {:<14}       - {}", "", failure);
+            }
+            if !outcome.passed() {
+                failed += 1;
+            }
+        }
+        println!();
+    }
+
+    if failed > 0 {
+        bail!("{} of {} scenario run(s) failed", failed, total);
+    }
+    println!("All {} scenario run(s) passed", total);
+    Ok(())
+}
+
+/// Expand `--script` arguments: files are kept as given, directories
+/// contribute their `.rhai` (and, with the `lua` feature, `.lua`) files in
+/// name order.
+fn script_paths(args: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for arg in args {
+        if !arg.is_dir() {
+            paths.push(arg.clone());
+            continue;
+        }
+        let mut found: Vec<PathBuf> = std::fs::read_dir(arg)
+            .with_context(|| format!("failed to read script directory {}", arg.display()))?
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| {
+                p.extension()
+                    .is_some_and(|ext| ext == "rhai" || (cfg!(feature = "lua") && ext == "lua"))
+            })
+            .collect();
+        if found.is_empty() {
+            bail!("no scripts found in {}", arg.display());
+        }
+        found.sort();
+        paths.extend(found);
+    }
+    Ok(paths)
+}
+
+/// Where `pf run` reads snapshots from.
+#[derive(Clone, Copy)]
+enum Snapshots<'a> {
+    /// A native store; the backtest streams its ticks (or maps them from
+    /// --snapshot-cache). `tape` is its trade tape under --fill-model tape,
+    /// and `cache` the --memory-cache shared by every backtest.
+    Store(&'a SqliteStore, Option<&'a TradeTape>, Option<&'a SnapshotCache>),
+    Loader(&'a dyn Fn(&str) -> Result<Vec<BookSnapshot>>),
+}
+
+/// Markets used per iteration in --watch mode when no --limit/--sample is set.
+const WATCH_SAMPLE: usize = 100;
+
+/// Run the backtest once, or with --watch re-run it every time a script
+/// file changes. Watch runs use a fixed market sample and seed so results
+/// are comparable between edits.
+fn run_or_watch(
+    args: &RunArgs,
+    scripts: &[PathBuf],
+    strategies: &[String],
+    markets: Vec<Market>,
+    snapshots: Snapshots<'_>,
+    source_label: &str,
+) -> Result<()> {
+    if !args.watch {
+        return run_backtest(args, scripts, strategies, markets, snapshots, source_label);
+    }
+
+    let mut watch_args = args.clone();
+    if watch_args.limit.is_none() && watch_args.sample.is_none() {
+        watch_args.sample = Some(WATCH_SAMPLE);
+    }
+    watch_args.seed.get_or_insert(0);
+
+    let mut last_modified: Vec<_> = scripts.iter().map(|p| modified_time(p)).collect();
+    loop {
+        // A broken edit should not end the session: report and keep watching.
+        let outcome = run_backtest(&watch_args, scripts, strategies, markets.clone(), snapshots, source_label);
+        if let Err(e) = outcome {
+            eprintln!("Error: {:#}", e);
+        }
+
+        println!("Watching {} script(s) for changes (Ctrl-C to stop)...", scripts.len());
+        let changed = wait_for_change(scripts, &mut last_modified);
+        println!();
+        println!("{} changed, re-running...", changed.display());
+    }
+}
+
+fn modified_time(path: &std::path::Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Poll until one of the files' modification time differs from `last`,
+/// updating `last` and returning the changed path.
+fn wait_for_change<'a>(
+    paths: &'a [PathBuf],
+    last: &mut [Option<std::time::SystemTime>],
+) -> &'a PathBuf {
+    loop {
+        std::thread::sleep(std::time::Duration::from_millis(300));
+        for (path, last) in paths.iter().zip(last.iter_mut()) {
+            // Editors may briefly remove the file while saving; wait for it to return.
+            let current = modified_time(path);
+            if current.is_some() && current != *last {
+                *last = current;
+                return path;
+            }
+        }
+    }
+}
+
+/// One window's strategy: the external strategy, the script, or the
+/// built-in `name` (fade from `fade_signals`), behind a [`RegimeFilter`]
+/// under --only-hours or --min-realized-vol-bps. Everything was validated
+/// before the run started.
+fn build_strategy(
+    args: &RunArgs,
+    external: Option<&ExternalStrategy<'_>>,
+    script: Option<&LoadedScript>,
+    state: &PersistentState,
+    name: &str,
+    fade_signals: Option<&std::sync::Arc<HashMap<String, phantomfill::types::Side>>>,
+    params: &StrategyParams,
+) -> Result<Box<dyn phantomfill::strategies::Strategy>> {
+    let strategy: Box<dyn phantomfill::strategies::Strategy> = if let Some(external) = external {
+        external.load(args)?
+    } else if let Some(script) = script {
+        script.start(args, Some(state))
+    } else if let (Some(signals), "fade") = (fade_signals, name) {
+        Box::new(FadeMomentum::new(params.bid_price, params.shares, signals.clone()))
+    } else {
+        create_strategy(name, params).with_context(|| format!("unknown strategy `{}`", name))?
+    };
+    let regime = RegimeConfig {
+        hours: args.only_hours,
+        min_realized_vol_bps: args.min_realized_vol_bps,
+        signal_offset_ms: params.signal_offset_ms,
+    };
+    Ok(if regime.is_active() {
+        Box::new(RegimeFilter::new(strategy, regime))
+    } else {
+        strategy
+    })
+}
+
+/// [`build_strategy`] for a factory: a strategy that fails to start (an
+/// external process that won't spawn, a script file deleted mid-run) aborts
+/// its window with the error instead of stopping the run.
+fn start_strategy(
+    name: &str,
+    strategy: Result<Box<dyn phantomfill::strategies::Strategy>>,
+) -> Box<dyn phantomfill::strategies::Strategy> {
+    strategy.unwrap_or_else(|e| {
+        Box::new(FailedStart {
+            name: name.to_string(),
+            error: format!("{:#}", e),
+        })
+    })
+}
+
+/// Stands in for a strategy that failed to start, aborting on the first
+/// snapshot with the reason.
+struct FailedStart {
+    name: String,
+    error: String,
+}
+
+impl phantomfill::strategies::Strategy for FailedStart {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        "strategy that failed to start"
+    }
+
+    fn on_tick(&mut self, _snap: &BookSnapshot) -> Vec<phantomfill::types::Action> {
+        Vec::new()
+    }
+
+    fn reset(&mut self) {}
+
+    fn abort_reason(&self) -> Option<&str> {
+        Some(&self.error)
+    }
+}
+
+/// Load a `--script` strategy with any `--script-param` constants. `.lua`
+/// files use the Lua backend, anything else is Rhai. Rhai scripts that opt
+/// into `PERSIST_STATE` share `state` across every instance given it.
+fn load_script(
+    args: &RunArgs,
+    path: &std::path::Path,
+    state: Option<&PersistentState>,
+) -> Result<Box<dyn phantomfill::strategies::Strategy>> {
+    Ok(LoadedScript::load(args, path)?.start(args, state))
+}
+
+/// A `--script` read and compiled once per run, so once per --watch
+/// iteration, for every window to start its own instance from.
+struct LoadedScript {
+    code: ScriptCode,
+    /// Whether the script opts into `PERSIST_STATE`.
+    persists_state: bool,
+}
+
+enum ScriptCode {
+    Rhai(CompiledScript),
+    /// Lua VMs can't share a compiled chunk, so every instance parses the
+    /// source again; the file is still read only once.
+    #[cfg(feature = "lua")]
+    Lua {
+        name: String,
+        source: String,
+        params: Vec<(String, rhai::Dynamic)>,
+    },
+}
+
+impl LoadedScript {
+    /// Read and compile `path`, and run its top level once so a script
+    /// that can't start fails here rather than in every window.
+    fn load(args: &RunArgs, path: &std::path::Path) -> Result<Self> {
+        let params = args
+            .script_param
+            .iter()
+            .map(|p| parse_script_param(p))
+            .collect::<Result<Vec<_>>>()?;
+        let code = if path.extension().is_some_and(|ext| ext == "lua") {
+            lua_code(path, params)?
+        } else {
+            ScriptCode::Rhai(CompiledScript::from_file(path, args.shares, args.bid_price, &params)?)
+        };
+        let script = Self {
+            code,
+            persists_state: false,
+        };
+        let (probe, persists_state): (Box<dyn phantomfill::strategies::Strategy>, bool) = match script.code {
+            ScriptCode::Rhai(ref compiled) => {
+                let probe = start_rhai(compiled, args, None);
+                let persists_state = probe.persists_state();
+                (Box::new(probe), persists_state)
+            }
+            #[cfg(feature = "lua")]
+            ScriptCode::Lua { .. } => (script.start(args, None), false),
+        };
+        if let Some(error) = probe.abort_reason() {
+            bail!("failed to load script: {}: {}", path.display(), error);
+        }
+        Ok(Self { persists_state, ..script })
+    }
+
+    /// A fresh instance for one window. Rhai instances that opt into
+    /// `PERSIST_STATE` share `state` with every other instance given it.
+    fn start(&self, args: &RunArgs, state: Option<&PersistentState>) -> Box<dyn phantomfill::strategies::Strategy> {
+        match self.code {
+            ScriptCode::Rhai(ref compiled) => Box::new(start_rhai(compiled, args, state)),
+            #[cfg(feature = "lua")]
+            ScriptCode::Lua {
+                ref name,
+                ref source,
+                ref params,
+            } => Box::new(
+                phantomfill::strategies::lua::LuaStrategy::from_source_or_aborted(
+                    name,
+                    source,
+                    args.shares,
+                    args.bid_price,
+                    params,
+                )
+                .with_limits(script_limits(args))
+                .with_history(args.script_history)
+                .with_strict(args.strict_scripts),
+            ),
+        }
+    }
+}
+
+/// An instance of a compiled Rhai script, set up from `args`.
+fn start_rhai(compiled: &CompiledScript, args: &RunArgs, state: Option<&PersistentState>) -> RhaiStrategy {
+    let mut strategy = RhaiStrategy::from_compiled(compiled)
+        .with_limits(script_limits(args))
+        .with_history(args.script_history)
+        .with_strict(args.strict_scripts);
+    if let Some(state) = state {
+        strategy = strategy.with_persistent_state(state);
+    }
+    strategy
+}
+
+fn script_limits(args: &RunArgs) -> ScriptLimits {
+    ScriptLimits {
+        max_operations: args.script_max_ops,
+        timeout: std::time::Duration::from_millis(args.script_timeout_ms),
+        ..ScriptLimits::default()
+    }
+}
+
+#[cfg(feature = "lua")]
+fn lua_code(path: &std::path::Path, params: Vec<(String, rhai::Dynamic)>) -> Result<ScriptCode> {
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read script: {}", path.display()))?;
+    let name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "unknown".to_string());
+    Ok(ScriptCode::Lua { name, source, params })
+}
+
+#[cfg(not(feature = "lua"))]
+fn lua_code(path: &std::path::Path, _params: Vec<(String, rhai::Dynamic)>) -> Result<ScriptCode> {
+    bail!("{}: pf was built without the `lua` feature", path.display())
+}
+
+/// A strategy from outside the built-in registry and scripts: a Python
+/// file, a compiled plugin or a program run as a subprocess.
+#[derive(Clone)]
+enum ExternalStrategy<'a> {
+    Python(&'a std::path::Path),
+    Plugin(&'a std::path::Path),
+    /// The program and its arguments, with the pool every window of the
+    /// run takes its process from.
+    Process(&'a std::path::Path, &'a [String], SubprocessPool),
+}
+
+impl ExternalStrategy<'_> {
+    fn display_name(&self) -> String {
+        match self {
+            Self::Python(path) => format!("python:{}", path.display()),
+            Self::Plugin(path) => format!("plugin:{}", path.display()),
+            Self::Process(path, args, _) => {
+                let mut name = format!("exec:{}", path.display());
+                for arg in *args {
+                    name.push(' ');
+                    name.push_str(arg);
+                }
+                name
+            }
+        }
+    }
+
+    fn load(&self, args: &RunArgs) -> Result<Box<dyn phantomfill::strategies::Strategy>> {
+        match self {
+            Self::Python(path) => load_py_strategy(args, path),
+            Self::Plugin(path) => load_plugin(args, path),
+            Self::Process(_, _, pool) => Ok(Box::new(pool.strategy()?)),
+        }
+    }
+}
+
+fn external_strategy(args: &RunArgs) -> Option<ExternalStrategy<'_>> {
+    py_strategy_path(args)
+        .map(ExternalStrategy::Python)
+        .or_else(|| plugin_path(args).map(ExternalStrategy::Plugin))
+        .or_else(|| {
+            args.exec
+                .as_deref()
+                .map(|path| ExternalStrategy::Process(path, &args.exec_arg, process_pool(args, path)))
+        })
+}
+
+/// One pool per run, so windows run one after another reuse a process
+/// instead of starting one each.
+fn process_pool(args: &RunArgs, path: &std::path::Path) -> SubprocessPool {
+    let mut command = SubprocessCommand::new(path);
+    command.args = args.exec_arg.clone();
+    command.shares = args.shares;
+    command.bid_price = args.bid_price;
+    SubprocessPool::new(command)
+}
+
+#[cfg(feature = "python")]
+fn py_strategy_path(args: &RunArgs) -> Option<&std::path::Path> {
+    args.py_strategy.as_deref()
+}
+
+#[cfg(not(feature = "python"))]
+fn py_strategy_path(_args: &RunArgs) -> Option<&std::path::Path> {
+    None
+}
+
+#[cfg(feature = "python")]
+fn load_py_strategy(
+    args: &RunArgs,
+    path: &std::path::Path,
+) -> Result<Box<dyn phantomfill::strategies::Strategy>> {
+    let strategy =
+        phantomfill::strategies::python::PyStrategy::from_file(path, args.shares, args.bid_price)?;
+    Ok(Box::new(strategy))
+}
+
+#[cfg(not(feature = "python"))]
+fn load_py_strategy(
+    _args: &RunArgs,
+    path: &std::path::Path,
+) -> Result<Box<dyn phantomfill::strategies::Strategy>> {
+    bail!("{}: pf was built without the `python` feature", path.display())
+}
+
+#[cfg(feature = "plugins")]
+fn plugin_path(args: &RunArgs) -> Option<&std::path::Path> {
+    args.plugin.as_deref()
+}
+
+#[cfg(not(feature = "plugins"))]
+fn plugin_path(_args: &RunArgs) -> Option<&std::path::Path> {
+    None
+}
+
+#[cfg(feature = "plugins")]
+fn load_plugin(
+    args: &RunArgs,
+    path: &std::path::Path,
+) -> Result<Box<dyn phantomfill::strategies::Strategy>> {
+    use phantomfill::strategies::plugin::{PluginLibrary, PluginParams};
+    let params = args
+        .plugin_param
+        .iter()
+        .map(|p| {
+            p.split_once('=')
+                .map(|(k, v)| (k.trim().to_string(), v.to_string()))
+                .with_context(|| format!("plugin parameter `{}` must be key=value", p))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    // dlopen reference-counts, so reopening per window reuses the loaded library.
+    PluginLibrary::open(path)?.create(&PluginParams {
+        shares: args.shares,
+        bid_price: args.bid_price,
+        params,
+    })
+}
+
+#[cfg(not(feature = "plugins"))]
+fn load_plugin(
+    _args: &RunArgs,
+    path: &std::path::Path,
+) -> Result<Box<dyn phantomfill::strategies::Strategy>> {
+    bail!("{}: pf was built without the `plugins` feature", path.display())
+}
+
+/// Shared backtest driver for both the capture and native data sources.
+///
+/// With several scripts, each is run against the same markets and fill
+/// seeds and a comparison table is printed at the end.
+fn run_backtest(
+    args: &RunArgs,
+    scripts: &[PathBuf],
+    strategies: &[String],
+    markets: Vec<Market>,
+    snapshots: Snapshots<'_>,
+    source_label: &str,
+) -> Result<()> {
+    let bid_price = args.bid_price;
+    let shares = args.shares;
+    let runs = args.runs as usize;
+    let external = external_strategy(args);
+
+    if args.resume.is_some() && runs > 1 {
+        bail!("--resume is only supported for single runs (--runs 1)");
+    }
+    // Built-in strategies run only when there are no scripts.
+    let strategies = if scripts.is_empty() { strategies } else { &[] };
+    let several = scripts.len() > 1 || strategies.len() > 1;
+    if args.resume.is_some() && several {
+        bail!("--resume is only supported for a single strategy");
+    }
+    if external.is_some() && !args.param.is_empty() {
+        bail!("--param only applies to built-in strategies");
+    }
+    let params = if external.is_some() { StrategyParams::default() } else { strategy_params(args, strategies)? };
+    // Compiled once here, so a --watch iteration picks up the latest edit
+    // and a broken one fails only that iteration.
+    let loaded = scripts
+        .iter()
+        .map(|path| LoadedScript::load(args, path))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Build strategy factory (fade needs pre-computed signals over the full
+    // history, before any --limit/--sample narrowing breaks up the streaks).
+    let fade_signals = if strategies.iter().any(|s| s == "fade") {
+        let signals = std::sync::Arc::new(compute_fade_signals(
+            &markets,
+            params.min_streak,
+            params.max_streak,
+        ));
+        Some(signals)
+    } else {
+        None
+    };
+
+    let total_markets = markets.len();
+    let markets = select_markets(markets, args.limit, args.sample, args.seed);
+    if markets.len() < total_markets {
+        println!(
+            "Selected {} of {} markets (limit={:?}, sample={:?})",
+            markets.len(),
+            total_markets,
+            args.limit,
+            args.sample
+        );
+    }
+
+    // Every strategy sees the same fill seeds; pick one up front when
+    // comparing several and none was given.
+    let seed = args.seed.or_else(|| {
+        several.then(|| {
+            use rand::Rng;
+            rand::thread_rng().gen()
+        })
+    });
+    if args.seed.is_none() {
+        if let Some(s) = seed {
+            println!("Using seed {} for all strategies", s);
+        }
+    }
+
+    let display_names: Vec<String> = if let Some(ref external) = external {
+        vec![external.display_name()]
+    } else if scripts.is_empty() {
+        strategies.to_vec()
+    } else {
+        scripts
+            .iter()
+            .map(|p| format!("script:{}", p.display()))
+            .collect()
+    };
+
+    println!(
+        "Loaded {} markets{}. Running strategy '{}' (bid={}, shares={}, min_bps={})...",
+        markets.len(),
+        source_label,
+        display_names.join("', '"),
+        bid_price,
+        shares,
+        args.min_bps
+    );
+
+    if let Some(ref signals) = fade_signals {
+        println!(
+            "  Fade signals: {} of {} windows (streak {}..={})",
+            signals.len(),
+            total_markets,
+            params.min_streak,
+            params.max_streak
+        );
+    }
+
+    let saved_runs = match args.save_run {
+        Some(ref path) => {
+            let store = SqliteStore::open(path)
+                .with_context(|| format!("failed to open results database at {}", path.display()))?;
+            store.init().context("failed to initialize results database schema")?;
+            Some((store, path))
+        }
+        None => None,
+    };
+
+    let mut reports = Vec::new();
+    let mut summaries = Vec::new();
+
+    for (idx, display_name) in display_names.iter().enumerate() {
+        let script = scripts.get(idx);
+        let loaded = loaded.get(idx);
+        // Shared by every window's instance of a PERSIST_STATE script.
+        let state = PersistentState::default();
+        let factory = FnFactory::new(|| {
+            start_strategy(
+                display_name,
+                build_strategy(args, external.as_ref(), loaded, &state, display_name, fade_signals.as_ref(), &params),
+            )
+        });
+
+        // With several scripts or strategies, suffix export names with each
+        // script's stem or strategy's name.
+        let export_path = |p: &str| match script {
+            Some(script) if several => export_path_for(p, script),
+            None if several => export_path_for(p, std::path::Path::new(display_name)),
+            _ => PathBuf::from(p),
+        };
+        let exports = Exports {
+            csv: args.csv.as_deref().map(export_path),
+            arrow: arrow_path(args).map(export_path),
+            save_run: saved_runs.as_ref().map(|(store, path)| SaveRun {
+                store,
+                path,
+                meta: RunMeta::new(
+                    StrategySpec {
+                        name: display_name.clone(),
+                        params: run_params(args, display_name, script, &params),
+                    },
+                    &fill_model_name(snapshots),
+                    seed,
+                ),
+                run_ids: RefCell::default(),
+            }),
+        };
+
+        if runs <= 1 {
+            let report = run_single(
+                args,
+                &markets,
+                snapshots,
+                &factory,
+                display_name,
+                seed,
+                &exports,
+            )?;
+            reports.push(report);
+        } else {
+            let persists = match loaded {
+                Some(script) if external.is_none() => script.persists_state,
+                _ => false,
+            };
+            let summary = run_monte_carlo(
+                args,
+                &markets,
+                snapshots,
+                &factory,
+                display_name,
+                seed,
+                &exports,
+                persists.then_some(&state),
+            )?;
+            summaries.push(summary);
+        }
+    }
+
+    if reports.len() > 1 {
+        Report::print_comparison(&reports);
+    }
+    if summaries.len() > 1 {
+        MonteCarloSummary::print_comparison(&summaries);
+    }
+
+    Ok(())
+}
+
+/// `results.csv` + `dir/foo.rhai` -> `results.foo.csv` (likewise for
+/// `.arrow`).
+fn export_path_for(path: &str, script: &std::path::Path) -> PathBuf {
+    let path = PathBuf::from(path);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "csv".to_string());
+    let script_stem = script
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!("{}.{}.{}", stem, script_stem, ext))
+}
+
+/// Where one strategy's results go (--csv, --arrow).
+struct Exports<'a> {
+    csv: Option<PathBuf>,
+    arrow: Option<PathBuf>,
+    save_run: Option<SaveRun<'a>>,
+}
+
+/// Where --save-run stores each run, and what it stores with them.
+struct SaveRun<'a> {
+    store: &'a SqliteStore,
+    path: &'a Path,
+    /// Run `i` is stored with seed `seed + i`.
+    meta: RunMeta,
+    run_ids: RefCell<Vec<i64>>,
+}
+
+impl Exports<'_> {
+    /// Store run `run`'s results under --save-run.
+    fn save(&self, run: usize, results: &[WindowResult]) -> Result<()> {
+        let Some(ref save) = self.save_run else {
+            return Ok(());
+        };
+        let meta = RunMeta {
+            seed: save.meta.seed.map(|s| s + run as u64),
+            ..save.meta.clone()
+        };
+        let id = save
+            .store
+            .insert_results(&meta, results)
+            .with_context(|| format!("failed to save results to {}", save.path.display()))?;
+        save.run_ids.borrow_mut().push(id);
+        Ok(())
+    }
+
+    /// Record engine events when per-order results are exported.
+    fn attach<'a>(&self, builder: BacktestBuilder<'a>) -> (BacktestBuilder<'a>, Option<Receiver<EngineEvent>>) {
+        if self.arrow.is_none() {
+            return (builder, None);
+        }
+        let bus = EventBus::new();
+        let events = bus.channel();
+        (builder.events(bus), Some(events))
+    }
+
+    fn write(&self, output: &RunOutput, events: Option<Receiver<EngineEvent>>) -> Result<()> {
+        if let Some(ref path) = self.csv {
+            Report::export_csv(&output.results, path)
+                .with_context(|| format!("failed to export CSV to {}", path.display()))?;
+            println!("Results exported to {}", path.display());
+        }
+        if let Some(ref path) = self.arrow {
+            let orders = events.map(|rx| order_records(rx.try_iter())).unwrap_or_default();
+            export_arrow(output, &orders, path)?;
+        }
+        if let Some(ref save) = self.save_run {
+            match save.run_ids.borrow().as_slice() {
+                [] => {}
+                [id] => println!("Saved as run {} in {}", id, save.path.display()),
+                [first, .., last] => {
+                    println!("Saved as runs {}-{} in {}", first, last, save.path.display())
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The settings behind a `pf run` strategy, stored with --save-run results.
+fn run_params(
+    args: &RunArgs,
+    strategy: &str,
+    script: Option<&PathBuf>,
+    builtin: &StrategyParams,
+) -> BTreeMap<String, serde_json::Value> {
+    let mut params = if param_keys(strategy).is_empty() {
+        BTreeMap::from([
+            ("bid_price".to_string(), args.bid_price.into()),
+            ("shares".to_string(), args.shares.into()),
+            ("min_bps".to_string(), args.min_bps.into()),
+        ])
+    } else {
+        builtin.to_map(strategy)
+    };
+    params.insert("fees".to_string(), serde_json::json!(fee_model(args)));
+    if let Some(path) = script {
+        params.insert("script".to_string(), path.display().to_string().into());
+    }
+    if !args.script_param.is_empty() {
+        params.insert("script_param".to_string(), args.script_param.clone().into());
+    }
+    if let Some(bankroll) = bankroll(args) {
+        params.insert("bankroll".to_string(), serde_json::json!(bankroll));
+    }
+    if let Some(latency) = latency(args) {
+        params.insert("latency".to_string(), serde_json::json!(latency));
+    }
+    if let Some(portfolio) = portfolio(args) {
+        params.insert("portfolio".to_string(), serde_json::json!(portfolio));
+    }
+    if args.shared_timeline {
+        params.insert("shared_timeline".to_string(), true.into());
+    }
+    if let Some(max) = args.max_shares {
+        params.insert("max_shares".to_string(), max.into());
+    }
+    if args.cancel_latency_ms > 0 {
+        params.insert("cancel_latency_ms".to_string(), args.cancel_latency_ms.into());
+    }
+    if args.fill_until_resolution {
+        params.insert("fill_until_resolution".to_string(), true.into());
+    }
+    params
+}
+
+/// The name of the fill model [`backtest`] replays `snapshots` with.
+fn fill_model_name(snapshots: Snapshots<'_>) -> String {
+    match snapshots {
+        Snapshots::Store(_, Some(tape), _) => TradeTapeFillModel::new(tape.clone()).name().to_string(),
+        _ => DeLiseFillModel::new(DeLiseConfig::default()).name().to_string(),
+    }
+}
+
+#[cfg(feature = "mmap")]
+fn snapshot_cache<'a>(args: &RunArgs, builder: BacktestBuilder<'a>) -> BacktestBuilder<'a> {
+    match args.snapshot_cache {
+        Some(ref path) => builder.snapshot_cache(path),
+        None => builder,
+    }
+}
+
+#[cfg(not(feature = "mmap"))]
+fn snapshot_cache<'a>(_args: &RunArgs, builder: BacktestBuilder<'a>) -> BacktestBuilder<'a> {
+    builder
+}
+
+#[cfg(feature = "arrow")]
+fn arrow_path(args: &RunArgs) -> Option<&str> {
+    args.arrow.as_deref()
+}
+
+#[cfg(not(feature = "arrow"))]
+fn arrow_path(_args: &RunArgs) -> Option<&str> {
+    None
+}
+
+#[cfg(feature = "arrow")]
+fn export_arrow(output: &RunOutput, orders: &[OrderRecord], path: &std::path::Path) -> Result<()> {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let orders_path = path.with_file_name(format!("{}.orders.arrow", stem));
+    phantomfill::arrow::write_results(&output.results, path)?;
+    phantomfill::arrow::write_orders(orders, &orders_path)?;
+    println!("Results exported to {} and {}", path.display(), orders_path.display());
+    Ok(())
+}
+
+#[cfg(not(feature = "arrow"))]
+fn export_arrow(_output: &RunOutput, _orders: &[OrderRecord], path: &std::path::Path) -> Result<()> {
+    bail!("{}: pf was built without the `arrow` feature", path.display())
+}
+
+/// With --strict-scripts, fail on the window that stopped the run.
+fn check_strict(args: &RunArgs, results: &[WindowResult]) -> Result<()> {
+    if !args.strict_scripts {
+        return Ok(());
+    }
+    match results.iter().find(|r| r.aborted.is_some()) {
+        Some(r) => bail!(
+            "script error in market {} (--strict-scripts): {}",
+            r.market_id,
+            r.aborted.as_deref().unwrap_or_default()
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Engine settings shared by single and Monte Carlo runs.
+fn backtest<'a>(
+    args: &'a RunArgs,
+    markets: &[Market],
+    snapshots: Snapshots<'a>,
+    factory: &'a dyn StrategyFactory,
+    display_name: &str,
+    seed: Option<u64>,
+) -> BacktestBuilder<'a> {
+    let (builder, tape) = match snapshots {
+        Snapshots::Store(store, tape, cache) => {
+            let builder = snapshot_cache(args, BacktestBuilder::new().store_markets(store, markets.to_vec()));
+            let builder = match cache {
+                Some(cache) => builder.memory_cache(cache),
+                None => builder,
+            };
+            (builder, tape)
+        }
+        Snapshots::Loader(load) => (BacktestBuilder::new().markets(markets.to_vec(), load), None),
+    };
+    let builder = match tape {
+        Some(tape) => builder.fill_model(|_| Box::new(TradeTapeFillModel::new(tape.clone())) as Box<dyn FillModel>),
+        None if args.cancel_latency_ms > 0 => builder.fill_model(|seed| {
+            Box::new(DeLiseFillModel::new(DeLiseConfig {
+                seed,
+                cancel_latency_ms: args.cancel_latency_ms,
+                ..DeLiseConfig::default()
+            })) as Box<dyn FillModel>
+        }),
+        None => builder,
+    };
+    let builder = builder
+        .factory(factory)
+        .name(display_name)
+        .replay_config(replay_config(args));
+    match seed {
+        Some(seed) => builder.seed(seed),
+        None => builder,
+    }
+}
+
+/// The engine settings `args` select.
+fn replay_config(args: &RunArgs) -> ReplayConfig {
+    ReplayConfig {
+        bid_price: args.bid_price,
+        shares: args.shares,
+        stop_on_abort: args.strict_scripts,
+        max_shares: args.max_shares,
+        fill_until_resolution: args.fill_until_resolution,
+        snapshot_check: args.check_snapshots,
+        fees: fee_model(args),
+        bankroll: bankroll(args),
+        latency: latency(args),
+        mode: EvaluationMode::Execute,
+        portfolio: portfolio(args),
+        shared_timeline: args.shared_timeline,
+    }
+}
+
+/// The --maker-fee, --taker-fee, --fee-curve, --settlement-fee and
+/// --winner-fee schedule.
+/// The `--fill-model` choices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum FillModelKind {
+    Delise,
+    Tape,
+}
+
+/// How `--sizing` sizes orders from `--bankroll`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum SizingRule {
+    Fixed,
+    Fraction,
+    Kelly,
+}
+
+fn bankroll(args: &RunArgs) -> Option<Bankroll> {
+    let fraction = args.stake_fraction.unwrap_or(1.0);
+    let sizing = match args.sizing {
+        SizingRule::Fixed => Sizing::Fixed,
+        SizingRule::Fraction => Sizing::FixedFraction { fraction },
+        SizingRule::Kelly => Sizing::Kelly {
+            win_prob: args.win_prob.unwrap_or_default(),
+            fraction,
+        },
+    };
+    Some(Bankroll {
+        starting_capital: args.bankroll?,
+        sizing,
+    })
+}
+
+/// The --max-positions, --max-capital and --max-category-capital limits,
+/// if any are set.
+fn portfolio(args: &RunArgs) -> Option<PortfolioLimits> {
+    let mut limits = PortfolioLimits {
+        max_positions: args.max_positions,
+        max_capital: args.max_capital,
+        ..PortfolioLimits::default()
+    };
+    for (category, cap) in &args.max_category_capital {
+        match category {
+            Some(category) => {
+                limits.category_caps.insert(category.clone(), *cap);
+            }
+            None => limits.max_category_capital = Some(*cap),
+        }
+    }
+    limits.is_active().then_some(limits)
+}
+
+fn latency(args: &RunArgs) -> Option<Latency> {
+    Some(Latency {
+        ms: args.latency_ms?,
+        jitter_ms: args.latency_jitter_ms.unwrap_or(0),
+    })
+}
+
+fn fee_model(args: &RunArgs) -> FeeModel {
+    FeeModel {
+        maker_fee: args.maker_fee,
+        taker_fee: args.taker_fee,
+        curve: args.fee_curve,
+        settlement_fee: args.settlement_fee,
+        winner_fee: args.winner_fee,
+    }
+}
+
+/// One backtest pass for a single strategy, printing its report.
+fn run_single(
+    args: &RunArgs,
+    markets: &[Market],
+    snapshots: Snapshots<'_>,
+    factory: &dyn StrategyFactory,
+    display_name: &str,
+    seed: Option<u64>,
+    exports: &Exports,
+) -> Result<Report> {
+    let builder = backtest(args, markets, snapshots, factory, display_name, seed).on_run(|i, results| {
+        check_strict(args, results)?;
+        exports.save(i, results)
+    });
+    let (mut builder, events) = exports.attach(builder);
+    if let Some(ref path) = args.resume {
+        builder = builder.checkpoint(path, args.checkpoint_every);
+    }
+    let output = builder.run()?;
+
+    output.report.print();
+
+    exports.write(&output, events)?;
+    Ok(output.report)
+}
+
+/// Monte Carlo runs for a single strategy (run `i` seeded with `seed + i`),
+/// printing the summary. Runs go in parallel with --threads unless the script
+/// keeps persistent `state`, which then starts empty each run.
+#[allow(clippy::too_many_arguments)]
+fn run_monte_carlo(
+    args: &RunArgs,
+    markets: &[Market],
+    snapshots: Snapshots<'_>,
+    factory: &dyn StrategyFactory,
+    display_name: &str,
+    seed: Option<u64>,
+    exports: &Exports,
+    state: Option<&PersistentState>,
+) -> Result<MonteCarloSummary> {
+    let runs = args.runs as usize;
+    let threads = if state.is_some() { 1 } else { args.threads as usize };
+    let (builder, events) = exports.attach(backtest(args, markets, snapshots, factory, display_name, seed));
+    let output = builder
+        .runs(runs)
+        .threads(threads)
+        .on_run(|i, results| {
+            check_strict(args, results)?;
+            exports.save(i, results)?;
+            if let Some(state) = state {
+                state.clear();
+            }
+            if (i + 1) % 10 == 0 || i + 1 == runs {
+                println!("Monte Carlo run {}/{} complete", i + 1, runs);
+            }
+            Ok(())
+        })
+        .run()?;
+
+    exports.write(&output, events)?;
+
+    let summary = output.summary.expect("several runs");
+    summary.print();
+    Ok(summary)
+}
+
+fn cmd_rpc(db: &std::path::Path) -> Result<()> {
+    let store = SqliteStore::open(db).with_context(|| format!("failed to open {}", db.display()))?;
+    phantomfill::rpc::serve(&store, std::io::stdin().lock(), std::io::stdout().lock())
+}
+
+fn cmd_strategies() -> Result<()> {
+    println!();
+    println!("Available strategies:");
+    println!();
+    let defaults = StrategyParams::default();
+    for (name, description) in list_strategies() {
+        println!("  {:<16} {}", name, description);
+        let params: Vec<String> = defaults
+            .to_map(name)
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        if !params.is_empty() {
+            println!("  {:<16} params: {}", "", params.join(" "));
+        }
+    }
+    println!();
+    println!("Set params with --param KEY=VALUE.");
+    println!();
+    Ok(())
+}
+
+fn cmd_import(source: Option<String>, dest: String, asset: Option<String>) -> Result<()> {
+    // Resolve source path.
+    let source_path = match source {
+        Some(ref p) => PathBuf::from(p),
+        None => {
+            let home = std::env::var("HOME").context("HOME not set")?;
+            PathBuf::from(home).join(".local/share/pm_trader/spread_arb.db")
+        }
+    };
+
+    println!("Importing from: {}", source_path.display());
+    println!("Destination:    {}", dest);
+    if let Some(ref a) = asset {
+        println!("Asset filter:   {}", a);
+    }
+
+    // Open destination store and initialize schema.
+    let dest_path = PathBuf::from(&dest);
+    let store = SqliteStore::open(&dest_path)
+        .with_context(|| format!("failed to open destination at {}", dest))?;
+    store.init().context("failed to initialize destination schema")?;
+
+    // Run import.
+    let stats = import_from_capture_db(&source_path, &store, asset.as_deref())
+        .context("import failed")?;
+
+    println!();
+    println!("Import complete:");
+    println!("  Markets updated:   {}", stats.markets_imported);
+    println!("  Ticks added:       {}", stats.ticks_imported);
+    println!("  Markets unchanged: {}", stats.markets_unchanged);
+    println!("  Ticks already in:  {}", stats.ticks_existing);
+    println!("  Markets skipped:   {}", stats.markets_skipped);
+    println!();
+
+    Ok(())
+}
+
+fn cmd_import_csv(files: Vec<PathBuf>, mapping: PathBuf, dest: String) -> Result<()> {
+    let mapping = CsvMapping::load(&mapping)?;
+
+    println!("Importing {} CSV file(s)", files.len());
+    println!("Destination:    {}", dest);
+
+    let store = SqliteStore::open(Path::new(&dest))
+        .with_context(|| format!("failed to open destination at {}", dest))?;
+    store
+        .init()
+        .context("failed to initialize destination schema")?;
+
+    let stats = import_csv_files(&files, &mapping, &store).context("import failed")?;
+
+    println!();
+    println!("Import complete:");
+    println!("  Rows read:        {}", stats.rows_read);
+    println!("  Rows skipped:     {}", stats.rows_skipped);
+    println!("  Outside window:   {}", stats.rows_outside_window);
+    println!("  Markets imported: {}", stats.markets_imported);
+    println!("  Ticks imported:   {}", stats.ticks_imported);
+    println!();
+
+    Ok(())
+}
+
+fn cmd_import_hf(args: HfImportArgs) -> Result<()> {
+    let dest = args.dest;
+    let dir = match args.dir {
+        Some(dir) => dir,
+        None => default_cache_dir(&args.repo)?,
+    };
+
+    if args.download {
+        println!("Downloading {} into {}", args.repo, dir.display());
+        let stats = download_hf_dataset(&args.repo, &dir, args.coin.as_deref(), args.limit)
+            .context("download failed")?;
+        println!("  Files listed:     {}", stats.files_listed);
+        println!("  Files downloaded: {}", stats.files_downloaded);
+        println!("  Files cached:     {}", stats.files_cached);
+        println!("  Bytes downloaded: {}", stats.bytes_downloaded);
+        println!();
+    }
+
+    println!("Importing from: {}", dir.display());
+    println!("Destination:    {}", dest);
+    if let Some(ref coin) = args.coin {
+        println!("Coin filter:    {}", coin);
+    }
+
+    let klines = if args.no_oracle {
+        HashMap::new()
+    } else {
+        let (start_ms, end_ms) = scan_date_range(&dir, args.coin.as_deref())?;
+        fetch_binance_klines(&args.symbol, start_ms, end_ms).context("failed to fetch Binance klines")?
+    };
+
+    let store = SqliteStore::open(Path::new(&dest))
+        .with_context(|| format!("failed to open destination at {}", dest))?;
+    store
+        .init()
+        .context("failed to initialize destination schema")?;
+
+    let workers = args
+        .workers
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+    let stats = import_hf_directory_with_workers(&dir, &store, &klines, args.coin.as_deref(), args.limit, workers)
+        .context("import failed")?;
+
+    println!();
+    println!("Import complete:");
+    println!("  Files processed:  {}", stats.files_processed);
+    println!("  Files skipped:    {}", stats.files_skipped);
+    println!("  Markets imported: {}", stats.markets_imported);
+    println!("  Ticks imported:   {}", stats.ticks_imported);
+    println!("  Trades imported:  {}", stats.trades_imported);
+    println!();
+
+    Ok(())
+}
+
+fn cmd_validate(db: &str, max_gap_secs: f64, exclusions: Option<PathBuf>) -> Result<()> {
+    let store = SqliteStore::open(Path::new(db))
+        .with_context(|| format!("failed to open native database at {}", db))?;
+    let markets = store
+        .list_markets(&MarketFilter::default())
+        .context("failed to list markets")?;
+    let config = ValidateConfig {
+        max_gap_ms: (max_gap_secs * 1000.0).round() as i64,
+    };
+    let reports = validate_store(&store, &markets, &config)?;
+
+    let flagged: Vec<&MarketReport> = reports.iter().filter(|r| !r.is_clean()).collect();
+    for report in &flagged {
+        println!("{} ({} ticks)", report.market_id, report.ticks);
+        for issue in &report.issues {
+            println!("  {}", issue);
+        }
+    }
+
+    let mut by_kind: BTreeMap<IssueKind, usize> = BTreeMap::new();
+    for issue in flagged.iter().flat_map(|r| &r.issues) {
+        *by_kind.entry(issue.kind).or_default() += 1;
+    }
+    println!();
+    println!("{} of {} markets have issues", flagged.len(), reports.len());
+    for (kind, markets) in &by_kind {
+        println!("  {:<24} {} markets", kind.label(), markets);
+    }
+
+    if let Some(path) = exclusions {
+        write_market_ids(&path, flagged.iter().map(|r| r.market_id.as_str()))?;
+        println!();
+        println!("Exclusion list written to {} (use with pf run --exclude-file)", path.display());
+    }
+    Ok(())
+}
+
+fn cmd_db_stats(db: &str) -> Result<()> {
+    let store = SqliteStore::open(Path::new(db))
+        .with_context(|| format!("failed to open native database at {}", db))?;
+    let stats = DbStats::collect(&store)?;
+    println!("Database: {}", db);
+    println!();
+    stats.print();
+    println!();
+    Ok(())
+}
+
+fn cmd_runs_list(db: &str) -> Result<()> {
+    let store = SqliteStore::open(Path::new(db))
+        .with_context(|| format!("failed to open results database at {}", db))?;
+    let runs = store.list_runs().context("failed to list stored runs")?;
+    if runs.is_empty() {
+        println!("No stored runs in {} (save some with pf run --save-run)", db);
+        return Ok(());
+    }
+    println!(
+        "  {:>5}  {:<16}  {:<28} {:<13} {:>10} {:>8}  version",
+        "id", "created (UTC)", "strategy", "fill model", "seed", "windows"
+    );
+    for run in &runs {
+        let created = UnixSecs::new(run.meta.created_at)
+            .to_datetime()
+            .map_or_else(|| run.meta.created_at.to_string(), |dt| dt.format("%Y-%m-%d %H:%M").to_string());
+        let seed = run.meta.seed.map_or_else(|| "-".to_string(), |s| s.to_string());
+        println!(
+            "  {:>5}  {:<16}  {:<28.28} {:<13.13} {:>10} {:>8}  {}",
+            run.id, created, run.meta.strategy.name, run.meta.fill_model, seed, run.windows, run.meta.version
+        );
+    }
+    Ok(())
+}
+
+fn cmd_runs_diff(db: &str, run_a: i64, run_b: i64, top: usize) -> Result<()> {
+    let store = SqliteStore::open(Path::new(db))
+        .with_context(|| format!("failed to open results database at {}", db))?;
+    let meta = |id: i64| -> Result<RunMeta> {
+        Ok(store
+            .load_run(id)?
+            .with_context(|| format!("no stored run with id {} in {}", id, db))?
+            .meta)
+    };
+    let (a, b) = (meta(run_a)?, meta(run_b)?);
+
+    for (label, id, meta) in [("A", run_a, &a), ("B", run_b, &b)] {
+        let seed = meta.seed.map_or_else(|| "none".to_string(), |s| s.to_string());
+        println!(
+            "  {}: run {} -- {} + {}, seed {}, {}",
+            label,
+            id,
+            meta.strategy.name,
+            meta.fill_model,
+            seed,
+            if meta.version.is_empty() { "unknown version" } else { &meta.version }
+        );
+    }
+    let keys: std::collections::BTreeSet<&String> =
+        a.strategy.params.keys().chain(b.strategy.params.keys()).collect();
+    for key in keys {
+        let (pa, pb) = (a.strategy.params.get(key), b.strategy.params.get(key));
+        if pa != pb {
+            let show = |v: Option<&serde_json::Value>| v.map_or_else(|| "-".to_string(), |v| v.to_string());
+            println!("     {}: {} -> {}", key, show(pa), show(pb));
+        }
+    }
+    println!();
+
+    RunDiff::from_store(&store, run_a, run_b)?.print(top);
+    Ok(())
+}
+
+fn cmd_bench(markets: usize, snapshots: usize, seed: u64, db: Option<PathBuf>) -> Result<()> {
+    if let Some(ref path) = db {
+        if path.exists() {
+            bail!("benchmark database {} already exists", path.display());
+        }
+    }
+
+    let config = BenchConfig {
+        markets,
+        snapshots_per_market: snapshots,
+        seed,
+    };
+    println!(
+        "Benchmarking {} synthetic markets x {} snapshots (seed {})...",
+        markets, snapshots, seed
+    );
+    let report = run_bench(&config, db.as_deref())?;
+    report.print();
+    Ok(())
+}
+
+fn cmd_doctor(dbs: Vec<PathBuf>, out_dirs: Vec<PathBuf>, offline: bool) -> Result<()> {
+    let mut checks = vec![doctor::check_default_capture_db()];
+    checks.extend(dbs.iter().map(|p| doctor::check_database(p)));
+
+    let out_dirs = if out_dirs.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        out_dirs
+    };
+    checks.extend(out_dirs.iter().map(|d| doctor::check_write_access(d)));
+
+    if !offline {
+        checks.extend(doctor::check_network());
+    }
+
+    doctor::print_checks(&checks);
+
+    let failed = checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Fail)
+        .count();
+    if failed > 0 {
+        bail!("{} of {} checks failed", failed, checks.len());
+    }
+    println!("All checks passed.");
+    Ok(())
+}
+
+fn cmd_resolve(db: String, dry_run: bool, audit_log: Option<PathBuf>) -> Result<()> {
+    let store = SqliteStore::open(&PathBuf::from(&db))
+        .with_context(|| format!("failed to open native database at {}", db))?;
+
+    let markets: Vec<Market> = store
+        .list_markets(&MarketFilter::default())
+        .context("failed to list markets")?
+        .into_iter()
+        .filter(is_unresolved)
+        .collect();
+    if markets.is_empty() {
+        println!("No unresolved markets.");
+        return Ok(());
+    }
+
+    println!("Resolving {} markets against Binance klines...", markets.len());
+    let klines = fetch_klines_for(&markets);
+    let stats = backfill_outcomes(&markets, &|m| resolve_from_klines(&klines, m), &|m| {
+        match store.load_ticks(&m.id) {
+            Ok(ticks) => resolve_from_oracle(m, &ticks),
+            Err(e) => {
+                eprintln!("Warning: failed to load ticks of {}: {:#}", m.id, e);
+                None
+            }
+        }
+    });
+
+    println!();
+    println!("Outcome backfill:");
+    println!("  Unresolved:   {}", stats.unresolved);
+    println!("  From klines:  {}", stats.from_klines.len());
+    println!("  From oracle:  {}", stats.from_oracle.len());
+    println!("  Unresolvable: {}", stats.unresolvable());
+
+    if !dry_run && stats.resolved() > 0 {
+        let audit_path = audit_log.unwrap_or_else(|| PathBuf::from(format!("{}.outcomes.log", db)));
+        let resolved = apply_fixes(&store, &markets, &stats.from_klines, &audit_path, "binance")?
+            + apply_fixes(&store, &markets, &stats.from_oracle, &audit_path, "oracle")?;
+        println!("  Resolved:     {} (audit log: {})", resolved, audit_path.display());
+    }
+    println!();
+
+    Ok(())
+}
+
+fn cmd_outcomes_verify(db: String, fix: bool, audit_log: Option<PathBuf>) -> Result<()> {
+    let db_path = PathBuf::from(&db);
+    let store = SqliteStore::open(&db_path)
+        .with_context(|| format!("failed to open native database at {}", db))?;
+
+    let markets = store
+        .list_markets(&MarketFilter::default())
+        .context("failed to list markets")?;
+    if markets.is_empty() {
+        bail!("no markets found in native database");
+    }
+
+    println!(
+        "Verifying {} market outcomes against Binance klines...",
+        markets.len()
+    );
+    let klines = fetch_klines_for(&markets);
+    let stats = verify_outcomes(&markets, &|m| resolve_from_klines(&klines, m));
+
+    if !stats.mismatches.is_empty() {
+        println!();
+        println!("  {:<40} {:>6} {:>8}", "market", "stored", "expected");
+        for mm in &stats.mismatches {
+            println!(
+                "  {:<40} {:>6} {:>8}",
+                mm.market_id,
+                mm.stored.as_ref().map_or("NONE", |o| o.label()),
+                mm.expected.label()
+            );
+        }
+    }
+
+    println!();
+    println!("Outcome verification:");
+    println!("  Checked:      {}", stats.checked);
+    println!("  Agreed:       {}", stats.agreed);
+    println!("  Mismatched:   {}", stats.mismatches.len());
+    println!("  Unresolvable: {}", stats.unresolvable);
+    if stats.void > 0 {
+        println!("  Void (kept):  {}", stats.void);
+    }
+
+    if fix && !stats.mismatches.is_empty() {
+        let audit_path = audit_log.unwrap_or_else(|| PathBuf::from(format!("{}.outcomes.log", db)));
+        let fixed = apply_fixes(&store, &markets, &stats.mismatches, &audit_path, "binance")?;
+        println!(
+            "  Fixed:        {} (audit log: {})",
+            fixed,
+            audit_path.display()
+        );
+    }
     println!();
 
     Ok(())
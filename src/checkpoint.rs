@@ -0,0 +1,205 @@
+//! On-disk checkpoints so long runs can be interrupted and resumed.
+//!
+//! A checkpoint records which markets have already been replayed together
+//! with the results produced so far. Resuming skips the completed markets
+//! and appends to the saved results. A checkpoint only resumes the run
+//! configuration that wrote it, identified by a [`fingerprint`].
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Market, WindowResult};
+
+/// Progress of a (possibly interrupted) backtest run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Display name of the strategy the checkpoint was produced with.
+    pub strategy: String,
+    /// [`fingerprint`] of the strategy, replay and fill settings; empty in
+    /// checkpoints written before it was recorded.
+    #[serde(default)]
+    pub fingerprint: String,
+    /// Market ids that have been fully processed (with or without a result).
+    pub completed: HashSet<String>,
+    /// Results collected so far, in processing order.
+    pub results: Vec<WindowResult>,
+}
+
+impl Checkpoint {
+    pub fn new(strategy: &str, fingerprint: &str) -> Self {
+        Self {
+            strategy: strategy.to_string(),
+            fingerprint: fingerprint.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Load a checkpoint from `path`, or start a fresh one if the file does
+    /// not exist yet. Fails if the file belongs to a different strategy or
+    /// run configuration.
+    pub fn load_or_new(path: &Path, strategy: &str, fingerprint: &str) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new(strategy, fingerprint));
+        }
+
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read checkpoint {}", path.display()))?;
+        let checkpoint: Checkpoint = serde_json::from_str(&data)
+            .with_context(|| format!("failed to parse checkpoint {}", path.display()))?;
+
+        if checkpoint.strategy != strategy {
+            bail!(
+                "checkpoint {} was written by strategy '{}', not '{}'",
+                path.display(),
+                checkpoint.strategy,
+                strategy
+            );
+        }
+        if checkpoint.fingerprint != fingerprint {
+            bail!(
+                "checkpoint {} was written with different settings (fingerprint '{}', now '{}'); \
+                 delete it to start over",
+                path.display(),
+                checkpoint.fingerprint,
+                fingerprint
+            );
+        }
+        Ok(checkpoint)
+    }
+
+    /// Forget markets that are not in `markets`, so results from a wider
+    /// selection don't leak into a resumed run over a narrower one.
+    pub fn retain_markets(&mut self, markets: &[Market]) {
+        let ids: HashSet<&str> = markets.iter().map(|m| m.id.as_str()).collect();
+        self.completed.retain(|id| ids.contains(id.as_str()));
+        self.results.retain(|r| ids.contains(r.market_id.as_str()));
+    }
+
+    /// Write the checkpoint atomically (temp file + rename) so a crash
+    /// mid-write never leaves a truncated file behind.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("tmp");
+        let data = serde_json::to_string(self).context("failed to serialize checkpoint")?;
+        std::fs::write(&tmp, data)
+            .with_context(|| format!("failed to write checkpoint {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("failed to move checkpoint into {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn is_completed(&self, market_id: &str) -> bool {
+        self.completed.contains(market_id)
+    }
+
+    /// Mark a market as processed, keeping its result if it produced one.
+    pub fn record(&mut self, market_id: &str, result: Option<WindowResult>) {
+        self.completed.insert(market_id.to_string());
+        if let Some(r) = result {
+            self.results.push(r);
+        }
+    }
+}
+
+/// Stable identifier of a run configuration: 64-bit FNV-1a of
+/// `description`, in hex. Unlike `DefaultHasher`, it doesn't change between
+/// Rust releases, so checkpoints survive a toolchain upgrade.
+pub fn fingerprint(description: &str) -> String {
+    let hash = description.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn make_result(market_id: &str) -> WindowResult {
+        WindowResult {
+            market_id: market_id.to_string(),
+            platform: "polymarket".to_string(),
+            category: "btc".to_string(),
+            open_ts: 1000,
+            close_ts: 1300,
+            outcome: "YES".to_string(),
+            predicted: Some("YES".to_string()),
+            signal_offset_ms: Some(0),
+            bid_side: Some("YES".to_string()),
+            bid_price: 0.49,
+            shares: 10.0,
+            filled: true,
+            queue_ahead_at_place: 100.0,
+            fill_time_ms: Some(1000),
+            correct: true,
+            realistic_pnl: 5.1,
+            naive_pnl: 5.1,
+            ref_price_open: None,
+            ref_price_close: None,
+            aborted: None,
+            script_errors: 0,
+            rejected_orders: 0,
+            rejection: None,
+            stake: 0.0,
+            bankroll: None,
+            events: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("run.ckpt");
+
+        let mut ckpt = Checkpoint::new("momentum", "abc");
+        ckpt.record("m1", Some(make_result("m1")));
+        ckpt.record("m2", None);
+        ckpt.save(&path).unwrap();
+
+        let loaded = Checkpoint::load_or_new(&path, "momentum", "abc").unwrap();
+        assert!(loaded.is_completed("m1"));
+        assert!(loaded.is_completed("m2"));
+        assert!(!loaded.is_completed("m3"));
+        assert_eq!(loaded.results.len(), 1);
+        assert_eq!(loaded.results[0].market_id, "m1");
+    }
+
+    #[test]
+    fn test_missing_file_starts_fresh() {
+        let tmp = TempDir::new().unwrap();
+        let ckpt = Checkpoint::load_or_new(&tmp.path().join("none.ckpt"), "momentum", "abc").unwrap();
+        assert!(ckpt.completed.is_empty());
+        assert_eq!(ckpt.strategy, "momentum");
+        assert_eq!(ckpt.fingerprint, "abc");
+    }
+
+    #[test]
+    fn test_strategy_mismatch_errors() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("run.ckpt");
+        Checkpoint::new("momentum", "abc").save(&path).unwrap();
+
+        let err = Checkpoint::load_or_new(&path, "fade", "abc").unwrap_err().to_string();
+        assert!(err.contains("momentum"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn test_fingerprint_mismatch_errors() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("run.ckpt");
+        Checkpoint::new("momentum", &fingerprint("bid=0.49")).save(&path).unwrap();
+
+        assert!(Checkpoint::load_or_new(&path, "momentum", &fingerprint("bid=0.49")).is_ok());
+        let err = Checkpoint::load_or_new(&path, "momentum", &fingerprint("bid=0.45"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("different settings"), "unexpected error: {}", err);
+
+        // Checkpoints from before fingerprints can't be vouched for.
+        std::fs::write(&path, r#"{"strategy": "momentum", "completed": [], "results": []}"#).unwrap();
+        assert!(Checkpoint::load_or_new(&path, "momentum", &fingerprint("bid=0.49")).is_err());
+    }
+}
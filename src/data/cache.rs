@@ -0,0 +1,179 @@
+//! In-memory snapshot cache shared across runs.
+//!
+//! A Monte Carlo backtest replays the same markets once per run, and a plain
+//! store source streams every market's ticks from SQLite and groups them
+//! into snapshots again on each pass. [`SnapshotCache`] keeps each market's
+//! snapshots after its first load, so later runs (and parallel ones) read
+//! them from memory. Unbounded, it ends up holding every market of the
+//! backtest; [`SnapshotCache::with_capacity`] keeps only the most recently
+//! used markets and reloads the rest when they come up again.
+//!
+//! For more markets than fit in memory, the memory-mapped
+//! `MappedSnapshots` file (`mmap` feature) is the alternative.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+use crate::types::{BookSnapshot, Market, MarketId};
+
+use super::snapshots::ticks_to_snapshots;
+use super::store::DataStore;
+
+/// Cached markets and their last use, plus hit counts.
+#[derive(Debug, Default)]
+struct Entries {
+    markets: HashMap<MarketId, (Arc<[BookSnapshot]>, u64)>,
+    clock: u64,
+    hits: usize,
+    misses: usize,
+}
+
+/// Snapshots by market, shared by every run of a backtest; see the
+/// [module docs](self). Safe to share between threads.
+#[derive(Debug, Default)]
+pub struct SnapshotCache {
+    capacity: Option<usize>,
+    entries: Mutex<Entries>,
+}
+
+impl SnapshotCache {
+    /// A cache that keeps every market it loads.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A cache that keeps at most `max_markets` markets (at least one),
+    /// evicting the least recently used.
+    pub fn with_capacity(max_markets: usize) -> Self {
+        Self {
+            capacity: Some(max_markets.max(1)),
+            entries: Mutex::default(),
+        }
+    }
+
+    /// `market_id`'s snapshots, from the cache or else from `load`, whose
+    /// result is kept. Loading happens outside the lock, so threads missing
+    /// different markets load them side by side; errors are not cached.
+    pub fn get_or_load(
+        &self,
+        market_id: &str,
+        load: impl FnOnce() -> Result<Vec<BookSnapshot>>,
+    ) -> Result<Vec<BookSnapshot>> {
+        {
+            let mut entries = self.entries.lock().unwrap();
+            entries.clock += 1;
+            let clock = entries.clock;
+            if let Some((snapshots, used)) = entries.markets.get_mut(market_id) {
+                *used = clock;
+                let snapshots = snapshots.to_vec();
+                entries.hits += 1;
+                return Ok(snapshots);
+            }
+            entries.misses += 1;
+        }
+        let snapshots = load()?;
+        self.insert(market_id, snapshots.clone());
+        Ok(snapshots)
+    }
+
+    /// Cache `snapshots` as `market_id`'s, evicting the least recently used
+    /// market if the cache is full.
+    pub fn insert(&self, market_id: &str, snapshots: Vec<BookSnapshot>) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.clock += 1;
+        let clock = entries.clock;
+        entries.markets.insert(MarketId::from(market_id), (snapshots.into(), clock));
+        if let Some(capacity) = self.capacity {
+            while entries.markets.len() > capacity {
+                let oldest = entries
+                    .markets
+                    .iter()
+                    .min_by_key(|(_, (_, used))| *used)
+                    .map(|(id, _)| id.clone())
+                    .expect("cache is not empty");
+                entries.markets.remove(&oldest);
+            }
+        }
+    }
+
+    /// Load `markets` from `store` in one streamed pass, skipping those
+    /// already cached. A bounded cache keeps the last ones loaded.
+    pub fn preload(&self, store: &dyn DataStore, markets: &[Market]) -> Result<()> {
+        let missing: Vec<Market> = markets.iter().filter(|m| !self.contains(&m.id)).cloned().collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        let mut result = Ok(());
+        store.stream_ticks(&missing, &mut |ticks| {
+            result = missing.iter().try_for_each(|m| {
+                self.insert(&m.id, ticks_to_snapshots(&m.id, &ticks(&m.id)?));
+                Ok(())
+            });
+        })?;
+        result
+    }
+
+    pub fn contains(&self, market_id: &str) -> bool {
+        self.entries.lock().unwrap().markets.contains_key(market_id)
+    }
+
+    /// Markets cached.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().markets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lookups served from the cache, and lookups that had to load.
+    pub fn hits_and_misses(&self) -> (usize, usize) {
+        let entries = self.entries.lock().unwrap();
+        (entries.hits, entries.misses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::make_test_snap;
+
+    fn snaps(count: i64) -> Vec<BookSnapshot> {
+        (0..count).map(|i| make_test_snap(i * 1000, None, 100.0, 100.0)).collect()
+    }
+
+    #[test]
+    fn test_loads_each_market_once() {
+        let cache = SnapshotCache::new();
+        let loads = std::cell::Cell::new(0);
+        for _ in 0..3 {
+            let got = cache
+                .get_or_load("m1", || {
+                    loads.set(loads.get() + 1);
+                    Ok(snaps(2))
+                })
+                .unwrap();
+            assert_eq!(got.len(), 2);
+        }
+        assert_eq!(loads.get(), 1);
+        assert_eq!(cache.hits_and_misses(), (2, 1));
+
+        // Failed loads are not cached.
+        assert!(cache.get_or_load("m2", || anyhow::bail!("no ticks")).is_err());
+        assert!(!cache.contains("m2"));
+    }
+
+    #[test]
+    fn test_bounded_cache_evicts_least_recently_used() {
+        let cache = SnapshotCache::with_capacity(2);
+        cache.insert("m1", snaps(1));
+        cache.insert("m2", snaps(1));
+        cache.get_or_load("m1", || unreachable!()).unwrap();
+        cache.insert("m3", snaps(1));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains("m1") && cache.contains("m3"));
+        assert!(!cache.contains("m2"));
+    }
+}
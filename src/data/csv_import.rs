@@ -0,0 +1,601 @@
+//! Generic CSV orderbook import.
+//!
+//! Users with their own capture pipelines usually have orderbook dumps as
+//! CSV, one row per side per book update, with column names of their own.
+//! Rather than a Rust adapter per layout, [`CsvMapping`] describes which
+//! column holds each [`BookTick`] field, loaded from a TOML file:
+//!
+//! ```toml
+//! delimiter = ","
+//! timestamp_unit = "ms"     # "s", "ms" or "us"; RFC 3339 strings also parse
+//!
+//! [columns]
+//! market_id = "market"
+//! timestamp = "ts"
+//! side = "outcome"
+//! best_bid = "bid"
+//! best_bid_size = "bid_size"
+//! best_ask = "ask"
+//! best_ask_size = "ask_size"
+//! total_bid_depth = "bid_depth"
+//!
+//! [sides]
+//! yes = ["up"]
+//! no = ["down"]
+//!
+//! [market]
+//! category = "btc"
+//! duration_secs = 900
+//! ```
+//!
+//! Rows are grouped by market, so one market may span several files. A
+//! market opens at its `open_ts` column if mapped, otherwise at its first
+//! timestamp rounded down to a multiple of `duration_secs`.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::types::{BookTick, DepthLevels, Market, MarketId, Outcome, Platform, PriceLevel, Side, UnixSecs};
+
+use super::store::DataStore;
+
+/// Rows that fail to parse are logged individually up to this many per file.
+const LOGGED_BAD_ROWS: usize = 5;
+
+/// Statistics from a CSV import.
+#[derive(Debug, Default)]
+pub struct CsvImportStats {
+    pub rows_read: usize,
+    /// Rows with an unknown side or a value that doesn't parse.
+    pub rows_skipped: usize,
+    /// Rows timestamped outside their market's window.
+    pub rows_outside_window: usize,
+    pub markets_imported: usize,
+    pub ticks_imported: usize,
+}
+
+/// Unit of numeric timestamps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampUnit {
+    S,
+    #[default]
+    Ms,
+    Us,
+}
+
+/// How to read a CSV orderbook dump; see the [module docs](self).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CsvMapping {
+    #[serde(default = "default_delimiter")]
+    pub delimiter: char,
+    #[serde(default)]
+    pub timestamp_unit: TimestampUnit,
+    /// Separator inside the depth list columns.
+    #[serde(default = "default_list_separator")]
+    pub list_separator: char,
+    /// Whether depth sizes are already cumulative from the best bid down,
+    /// rather than per level.
+    #[serde(default)]
+    pub depth_cumulative: bool,
+    pub columns: CsvColumns,
+    #[serde(default)]
+    pub sides: SideValues,
+    pub market: MarketDefaults,
+}
+
+/// Header names of the columns holding each field. Only `market_id`,
+/// `timestamp` and `side` are required; an empty cell in an optional
+/// column reads as missing.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CsvColumns {
+    pub market_id: String,
+    pub timestamp: String,
+    pub side: String,
+    pub best_bid: Option<String>,
+    pub best_bid_size: Option<String>,
+    pub best_ask: Option<String>,
+    pub best_ask_size: Option<String>,
+    pub total_bid_depth: Option<String>,
+    pub total_ask_depth: Option<String>,
+    /// Bid ladder prices, as a `list_separator`-separated list.
+    pub depth_prices: Option<String>,
+    /// Sizes matching `depth_prices`.
+    pub depth_sizes: Option<String>,
+    pub reference_price: Option<String>,
+    pub oracle_price: Option<String>,
+    /// Window open time, in `timestamp_unit` or RFC 3339.
+    pub open_ts: Option<String>,
+    /// Resolved outcome: `YES`/`NO`/`VOID` or a value from `[sides]`.
+    pub outcome: Option<String>,
+    /// Per-row category, overriding `market.category`.
+    pub category: Option<String>,
+}
+
+/// Values of the side column meaning each side, matched case-insensitively.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SideValues {
+    pub yes: Vec<String>,
+    pub no: Vec<String>,
+}
+
+impl Default for SideValues {
+    fn default() -> Self {
+        Self {
+            yes: vec!["yes".into(), "up".into()],
+            no: vec!["no".into(), "down".into()],
+        }
+    }
+}
+
+impl SideValues {
+    fn side(&self, value: &str) -> Option<Side> {
+        let value = value.trim();
+        if self.yes.iter().any(|v| v.eq_ignore_ascii_case(value)) {
+            Some(Side::Yes)
+        } else if self.no.iter().any(|v| v.eq_ignore_ascii_case(value)) {
+            Some(Side::No)
+        } else {
+            None
+        }
+    }
+}
+
+/// Market fields the CSV doesn't carry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketDefaults {
+    #[serde(default = "default_platform")]
+    pub platform: String,
+    #[serde(default)]
+    pub category: String,
+    pub duration_secs: i64,
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_list_separator() -> char {
+    ';'
+}
+
+fn default_platform() -> String {
+    "polymarket".into()
+}
+
+impl CsvMapping {
+    /// Parse a mapping from TOML.
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let mapping: Self = toml::from_str(text).context("invalid CSV mapping")?;
+        if mapping.market.duration_secs <= 0 {
+            bail!("market.duration_secs must be positive");
+        }
+        if !mapping.delimiter.is_ascii() {
+            bail!("delimiter must be a single ASCII character");
+        }
+        if mapping.columns.depth_prices.is_some() != mapping.columns.depth_sizes.is_some() {
+            bail!("columns.depth_prices and columns.depth_sizes must be mapped together");
+        }
+        Ok(mapping)
+    }
+
+    /// Read a mapping from a TOML file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_toml(&text).with_context(|| format!("in {}", path.display()))
+    }
+
+    fn timestamp_ms(&self, value: &str) -> Option<i64> {
+        let value = value.trim();
+        if let Ok(n) = value.parse::<f64>() {
+            let ms = match self.timestamp_unit {
+                TimestampUnit::S => n * 1000.0,
+                TimestampUnit::Ms => n,
+                TimestampUnit::Us => n / 1000.0,
+            };
+            return ms.is_finite().then(|| ms.round() as i64);
+        }
+        if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+            return Some(dt.timestamp_millis());
+        }
+        chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")
+            .ok()
+            .map(|dt| dt.and_utc().timestamp_millis())
+    }
+}
+
+/// Header positions of the mapped columns.
+struct Indices {
+    market_id: usize,
+    timestamp: usize,
+    side: usize,
+    best_bid: Option<usize>,
+    best_bid_size: Option<usize>,
+    best_ask: Option<usize>,
+    best_ask_size: Option<usize>,
+    total_bid_depth: Option<usize>,
+    total_ask_depth: Option<usize>,
+    depth_prices: Option<usize>,
+    depth_sizes: Option<usize>,
+    reference_price: Option<usize>,
+    oracle_price: Option<usize>,
+    open_ts: Option<usize>,
+    outcome: Option<usize>,
+    category: Option<usize>,
+}
+
+impl Indices {
+    fn new(columns: &CsvColumns, headers: &csv::StringRecord) -> Result<Self> {
+        let find = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim() == name)
+                .ok_or_else(|| anyhow!("column `{}` is not in the header", name))
+        };
+        let optional = |name: &Option<String>| name.as_deref().map(find).transpose();
+        Ok(Self {
+            market_id: find(&columns.market_id)?,
+            timestamp: find(&columns.timestamp)?,
+            side: find(&columns.side)?,
+            best_bid: optional(&columns.best_bid)?,
+            best_bid_size: optional(&columns.best_bid_size)?,
+            best_ask: optional(&columns.best_ask)?,
+            best_ask_size: optional(&columns.best_ask_size)?,
+            total_bid_depth: optional(&columns.total_bid_depth)?,
+            total_ask_depth: optional(&columns.total_ask_depth)?,
+            depth_prices: optional(&columns.depth_prices)?,
+            depth_sizes: optional(&columns.depth_sizes)?,
+            reference_price: optional(&columns.reference_price)?,
+            oracle_price: optional(&columns.oracle_price)?,
+            open_ts: optional(&columns.open_ts)?,
+            outcome: optional(&columns.outcome)?,
+            category: optional(&columns.category)?,
+        })
+    }
+}
+
+/// A parsed row, before its market's window is known.
+struct Row {
+    side: Side,
+    timestamp_ms: i64,
+    best_bid: Option<f64>,
+    best_bid_size: Option<f64>,
+    best_ask: Option<f64>,
+    best_ask_size: Option<f64>,
+    total_bid_depth: f64,
+    total_ask_depth: f64,
+    depth: DepthLevels,
+    reference_price: Option<f64>,
+    oracle_price: Option<f64>,
+}
+
+/// Rows and market-level fields collected for one market.
+#[derive(Default)]
+struct PendingMarket {
+    rows: Vec<Row>,
+    open_ms: Option<i64>,
+    outcome: Option<Outcome>,
+    category: Option<String>,
+}
+
+/// Collects rows from any number of CSV sources, then writes their markets
+/// to a store.
+struct Importer<'a> {
+    mapping: &'a CsvMapping,
+    markets: HashMap<MarketId, PendingMarket>,
+    stats: CsvImportStats,
+}
+
+impl<'a> Importer<'a> {
+    fn new(mapping: &'a CsvMapping) -> Self {
+        Self {
+            mapping,
+            markets: HashMap::new(),
+            stats: CsvImportStats::default(),
+        }
+    }
+
+    fn read(&mut self, reader: impl Read, source: &str) -> Result<()> {
+        let mut csv = csv::ReaderBuilder::new()
+            .delimiter(self.mapping.delimiter as u8)
+            .flexible(true)
+            .from_reader(reader);
+        let indices = Indices::new(&self.mapping.columns, csv.headers()?)
+            .with_context(|| format!("in {}", source))?;
+
+        let mut bad_rows = 0;
+        for (line, record) in csv.records().enumerate() {
+            let record = record.with_context(|| format!("failed to read {}", source))?;
+            self.stats.rows_read += 1;
+            if let Err(e) = self.add_row(&indices, &record) {
+                self.stats.rows_skipped += 1;
+                bad_rows += 1;
+                if bad_rows <= LOGGED_BAD_ROWS {
+                    // Line numbers count the header as line 1.
+                    warn!("{} line {}: {:#}", source, line + 2, e);
+                }
+            }
+        }
+        if bad_rows > LOGGED_BAD_ROWS {
+            warn!("{}: {} rows skipped in total", source, bad_rows);
+        }
+        Ok(())
+    }
+
+    fn add_row(&mut self, ix: &Indices, record: &csv::StringRecord) -> Result<()> {
+        let mapping = self.mapping;
+        let cell = |i: Option<usize>| i.and_then(|i| record.get(i)).map(str::trim).filter(|v| !v.is_empty());
+        let number = |i: Option<usize>| -> Result<Option<f64>> {
+            cell(i)
+                .map(|v| v.parse::<f64>().map_err(|_| anyhow!("`{}` is not a number", v)))
+                .transpose()
+        };
+
+        let market_id = cell(Some(ix.market_id)).ok_or_else(|| anyhow!("missing market id"))?;
+        let side_value = cell(Some(ix.side)).unwrap_or_default();
+        let side = mapping
+            .sides
+            .side(side_value)
+            .ok_or_else(|| anyhow!("unknown side `{}`", side_value))?;
+        let timestamp = cell(Some(ix.timestamp)).unwrap_or_default();
+        let timestamp_ms = mapping
+            .timestamp_ms(timestamp)
+            .ok_or_else(|| anyhow!("bad timestamp `{}`", timestamp))?;
+        let open_ms = cell(ix.open_ts)
+            .map(|v| mapping.timestamp_ms(v).ok_or_else(|| anyhow!("bad open time `{}`", v)))
+            .transpose()?;
+        let outcome = cell(ix.outcome).and_then(|v| {
+            Outcome::from_label(&v.to_ascii_uppercase()).or_else(|| {
+                mapping.sides.side(v).map(|side| match side {
+                    Side::Yes => Outcome::Yes,
+                    Side::No => Outcome::No,
+                })
+            })
+        });
+
+        let best_bid = number(ix.best_bid)?;
+        let total_bid_depth = number(ix.total_bid_depth)?.unwrap_or(0.0);
+        let depth = match (cell(ix.depth_prices), cell(ix.depth_sizes)) {
+            (Some(prices), Some(sizes)) => self.depth_ladder(prices, sizes)?,
+            // Like the HF import, put all bid depth at the best bid when
+            // there's no ladder: conservative for queue position.
+            _ => match best_bid {
+                Some(price) if total_bid_depth > 0.0 => {
+                    std::iter::once(PriceLevel { price, cumulative_size: total_bid_depth }).collect()
+                }
+                _ => DepthLevels::new(),
+            },
+        };
+
+        let row = Row {
+            side,
+            timestamp_ms,
+            best_bid,
+            best_bid_size: number(ix.best_bid_size)?,
+            best_ask: number(ix.best_ask)?,
+            best_ask_size: number(ix.best_ask_size)?,
+            total_bid_depth,
+            total_ask_depth: number(ix.total_ask_depth)?.unwrap_or(0.0),
+            depth,
+            reference_price: number(ix.reference_price)?,
+            oracle_price: number(ix.oracle_price)?,
+        };
+
+        let pending = self.markets.entry(MarketId::from(market_id)).or_default();
+        pending.open_ms = pending.open_ms.or(open_ms);
+        if outcome.is_some() {
+            pending.outcome = outcome;
+        }
+        if let Some(category) = cell(ix.category) {
+            pending.category = Some(category.to_string());
+        }
+        pending.rows.push(row);
+        Ok(())
+    }
+
+    /// The bid ladder in ascending price order, with sizes cumulative
+    /// from the best bid down.
+    fn depth_ladder(&self, prices: &str, sizes: &str) -> Result<DepthLevels> {
+        let list = |values: &str| -> Result<Vec<f64>> {
+            values
+                .split(self.mapping.list_separator)
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse::<f64>().map_err(|_| anyhow!("`{}` is not a number", v)))
+                .collect()
+        };
+        let prices = list(prices)?;
+        let sizes = list(sizes)?;
+        if prices.len() != sizes.len() {
+            bail!("{} depth prices but {} sizes", prices.len(), sizes.len());
+        }
+        let mut levels: Vec<(f64, f64)> = prices.into_iter().zip(sizes).collect();
+        levels.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let mut cumulative = 0.0;
+        let mut depth: DepthLevels = levels
+            .into_iter()
+            .map(|(price, size)| {
+                cumulative = if self.mapping.depth_cumulative { size } else { cumulative + size };
+                PriceLevel { price, cumulative_size: cumulative }
+            })
+            .collect();
+        depth.reverse();
+        Ok(depth)
+    }
+
+    fn write(mut self, dest: &dyn DataStore) -> Result<CsvImportStats> {
+        let defaults = &self.mapping.market;
+        let duration = defaults.duration_secs;
+        let mut markets: Vec<_> = self.markets.into_iter().collect();
+        markets.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (id, mut pending) in markets {
+            pending.rows.sort_by_key(|r| r.timestamp_ms);
+            let open_ts = match pending.open_ms {
+                Some(ms) => UnixSecs::from_millis(ms),
+                None => {
+                    let first = pending.rows[0].timestamp_ms.div_euclid(1000);
+                    UnixSecs::new(first - first.rem_euclid(duration))
+                }
+            };
+            let market = Market {
+                id: id.to_string(),
+                platform: Platform::from(defaults.platform.as_str()),
+                description: id.to_string(),
+                category: pending.category.unwrap_or_else(|| defaults.category.clone()),
+                open_ts,
+                close_ts: open_ts + duration,
+                duration_secs: duration,
+                outcome: pending.outcome,
+                outcome_ts: None,
+            };
+
+            let window_ms = duration * 1000;
+            let ticks: Vec<BookTick> = pending
+                .rows
+                .into_iter()
+                .filter_map(|row| {
+                    let offset = open_ts.offset_of(row.timestamp_ms);
+                    if !(0..=window_ms).contains(&offset.ms()) {
+                        self.stats.rows_outside_window += 1;
+                        return None;
+                    }
+                    Some(BookTick {
+                        market_id: id.clone(),
+                        side: row.side,
+                        timestamp_ms: row.timestamp_ms,
+                        offset_ms: offset,
+                        best_bid: row.best_bid,
+                        best_bid_size: row.best_bid_size,
+                        best_ask: row.best_ask,
+                        best_ask_size: row.best_ask_size,
+                        depth: row.depth,
+                        total_bid_depth: row.total_bid_depth,
+                        total_ask_depth: row.total_ask_depth,
+                        reference_price: row.reference_price,
+                        oracle_price: row.oracle_price,
+                    })
+                })
+                .collect();
+            if ticks.is_empty() {
+                continue;
+            }
+
+            dest.insert_market(&market)?;
+            dest.insert_ticks(&ticks)?;
+            self.stats.markets_imported += 1;
+            self.stats.ticks_imported += ticks.len();
+        }
+
+        dest.analyze()?;
+        Ok(self.stats)
+    }
+}
+
+/// Import one CSV source into `dest`.
+pub fn import_csv(reader: impl Read, mapping: &CsvMapping, dest: &dyn DataStore) -> Result<CsvImportStats> {
+    let mut importer = Importer::new(mapping);
+    importer.read(reader, "CSV input")?;
+    importer.write(dest)
+}
+
+/// Import CSV files into `dest`, grouping rows by market across files.
+pub fn import_csv_files(paths: &[PathBuf], mapping: &CsvMapping, dest: &dyn DataStore) -> Result<CsvImportStats> {
+    let mut importer = Importer::new(mapping);
+    for path in paths {
+        let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        importer.read(file, &path.display().to_string())?;
+    }
+    let stats = importer.write(dest)?;
+    info!(
+        "CSV import: {} rows, {} markets, {} ticks",
+        stats.rows_read, stats.markets_imported, stats.ticks_imported
+    );
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::MemoryStore;
+
+    const MAPPING: &str = r#"
+        timestamp_unit = "s"
+
+        [columns]
+        market_id = "market"
+        timestamp = "time"
+        side = "token"
+        best_bid = "bid"
+        best_bid_size = "bid_sz"
+        best_ask = "ask"
+        depth_prices = "ladder_px"
+        depth_sizes = "ladder_sz"
+        outcome = "winner"
+
+        [sides]
+        yes = ["up"]
+        no = ["down"]
+
+        [market]
+        category = "btc"
+        duration_secs = 900
+    "#;
+
+    #[test]
+    fn test_imports_mapped_columns() {
+        let csv = "\
+market,time,token,bid,bid_sz,ask,ladder_px,ladder_sz,winner
+m1,1700000010,up,0.49,20,0.51,0.48;0.49,30;20,
+m1,1700000010,down,0.50,10,0.52,,,up
+m1,1700000005,sideways,0.50,10,0.52,,,
+m1,1700000020,up,oops,10,0.52,,,
+m1,1700000950,up,0.60,10,0.62,,,
+";
+        let mapping = CsvMapping::from_toml(MAPPING).unwrap();
+        let store = MemoryStore::new();
+        let stats = import_csv(csv.as_bytes(), &mapping, &store).unwrap();
+        assert_eq!(stats.rows_read, 5);
+        assert_eq!(stats.rows_skipped, 2);
+        assert_eq!(stats.rows_outside_window, 1);
+        assert_eq!((stats.markets_imported, stats.ticks_imported), (1, 2));
+
+        let market = &store.list_markets(&Default::default()).unwrap()[0];
+        // 1700000010 rounded down to a 15-minute boundary.
+        assert_eq!(market.open_ts, UnixSecs::new(1699999200));
+        assert_eq!(market.outcome, Some(Outcome::Yes));
+        assert_eq!(market.category, "btc");
+
+        let ticks = store.load_ticks("m1").unwrap();
+        let yes = ticks.iter().find(|t| t.side == Side::Yes).unwrap();
+        assert_eq!(yes.offset_ms.ms(), 810_000);
+        assert_eq!(yes.best_bid, Some(0.49));
+        let ladder: Vec<(f64, f64)> = yes.depth.iter().map(|l| (l.price, l.cumulative_size)).collect();
+        assert_eq!(ladder, vec![(0.48, 50.0), (0.49, 20.0)]);
+        let no = ticks.iter().find(|t| t.side == Side::No).unwrap();
+        assert_eq!(no.best_ask, Some(0.52));
+        assert!(no.depth.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_bad_mappings() {
+        assert!(CsvMapping::from_toml("[columns]\nmarket_id = \"m\"").is_err());
+        let typo = MAPPING.replace("best_bid =", "bestbid =");
+        assert!(CsvMapping::from_toml(&typo).is_err());
+
+        let mapping = CsvMapping::from_toml(MAPPING).unwrap();
+        let err = import_csv("market,time\nm1,1\n".as_bytes(), &mapping, &MemoryStore::new()).unwrap_err();
+        assert!(format!("{:#}", err).contains("column `token`"));
+    }
+}
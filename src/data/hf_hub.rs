@@ -0,0 +1,269 @@
+//! Download of HuggingFace dataset files (`net` feature).
+//!
+//! [`download_hf_dataset`] lists a dataset repository on the hub and
+//! fetches its NDJSON files into a local cache directory, keeping the
+//! repository's layout, so [`import_hf_directory`](super::import_hf_directory)
+//! can read them without a manual `git clone`. A file is written to
+//! `<name>.part` first and renamed only once its checksum matches the hub's,
+//! so a file present under its own name is complete. An interrupted download
+//! resumes from its `.part` file with an HTTP range request. Set `HF_TOKEN`
+//! for gated or private datasets.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use ring::digest::{self, Context as Digest, SHA1_FOR_LEGACY_USE_ONLY, SHA256};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use super::huggingface::parse_filename;
+
+/// The dataset `pf import-hf --download` fetches by default.
+pub const HF_DATASET: &str = "trentmkelly/polymarket_crypto_derivatives";
+
+const HF_ENDPOINT: &str = "https://huggingface.co";
+
+/// Statistics from a dataset download.
+#[derive(Debug, Default)]
+pub struct HfDownloadStats {
+    /// NDJSON files selected for download.
+    pub files_listed: usize,
+    pub files_downloaded: usize,
+    /// Files already complete in the cache.
+    pub files_cached: usize,
+    pub bytes_downloaded: u64,
+}
+
+/// A file in a dataset repository, as listed by the hub's tree API.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HubFile {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub path: String,
+    #[serde(default)]
+    pub size: u64,
+    /// Git blob id: SHA-1 over `blob <size>\0` and the content.
+    pub oid: String,
+    /// Present for files stored in Git LFS, which is most data files.
+    pub lfs: Option<HubLfs>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HubLfs {
+    /// SHA-256 of the content.
+    pub oid: String,
+    pub size: u64,
+}
+
+impl HubFile {
+    fn size(&self) -> u64 {
+        self.lfs.as_ref().map_or(self.size, |lfs| lfs.size)
+    }
+}
+
+/// The default cache directory for `repo`:
+/// `$XDG_CACHE_HOME/phantomfill/hf/<owner>__<name>`, falling back to
+/// `~/.cache`.
+pub fn default_cache_dir(repo: &str) -> Result<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var("HOME").context("HOME not set")?).join(".cache"),
+    };
+    Ok(base.join("phantomfill/hf").join(repo.replace('/', "__")))
+}
+
+fn get(url: &str) -> ureq::Request {
+    let request = ureq::get(url);
+    match std::env::var("HF_TOKEN") {
+        Ok(token) if !token.is_empty() => request.set("Authorization", &format!("Bearer {}", token)),
+        _ => request,
+    }
+}
+
+/// The URL in a `Link` header's `rel="next"` entry.
+fn next_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        params
+            .contains("rel=\"next\"")
+            .then(|| url.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+    })
+}
+
+/// Every file in the dataset repository `repo` at `revision`.
+pub fn list_dataset_files(repo: &str, revision: &str) -> Result<Vec<HubFile>> {
+    let mut files = Vec::new();
+    let mut url = Some(format!(
+        "{}/api/datasets/{}/tree/{}?recursive=true",
+        HF_ENDPOINT, repo, revision
+    ));
+    while let Some(page) = url {
+        let response = get(&page)
+            .call()
+            .with_context(|| format!("failed to list files of dataset {}", repo))?;
+        url = response.header("link").and_then(next_link);
+        let entries: Vec<HubFile> = response
+            .into_json()
+            .context("failed to parse the hub's file listing")?;
+        files.extend(entries.into_iter().filter(|f| f.kind == "file"));
+    }
+    Ok(files)
+}
+
+/// Hash `path` the way the hub identifies `file`: SHA-256 for LFS files,
+/// the git blob id otherwise.
+fn verify(path: &Path, file: &HubFile) -> Result<()> {
+    let (mut hasher, expected) = match &file.lfs {
+        Some(lfs) => (Digest::new(&SHA256), lfs.oid.as_str()),
+        None => {
+            let mut hasher = Digest::new(&SHA1_FOR_LEGACY_USE_ONLY);
+            hasher.update(format!("blob {}\0", file.size()).as_bytes());
+            (hasher, file.oid.as_str())
+        }
+    };
+    let mut reader = File::open(path)?;
+    let mut buf = vec![0; 1 << 16];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    let actual = hex(hasher.finish());
+    if actual != expected {
+        bail!("checksum mismatch for {}: expected {}, got {}", file.path, expected, actual);
+    }
+    Ok(())
+}
+
+fn hex(digest: digest::Digest) -> String {
+    digest.as_ref().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Fetch `file` into `dest`, resuming from `dest.part` if a previous
+/// attempt left one. Returns the bytes transferred.
+fn download_file(repo: &str, revision: &str, file: &HubFile, dest: &Path) -> Result<u64> {
+    let part = dest.with_file_name(format!(
+        "{}.part",
+        dest.file_name().and_then(|n| n.to_str()).unwrap_or_default()
+    ));
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+
+    let url = format!("{}/datasets/{}/resolve/{}/{}", HF_ENDPOINT, repo, revision, file.path);
+    let mut have = fs::metadata(&part).map_or(0, |m| m.len());
+    if have > file.size() {
+        have = 0;
+    }
+    let mut request = get(&url);
+    if have > 0 {
+        request = request.set("Range", &format!("bytes={}-", have));
+    }
+    let response = request
+        .call()
+        .with_context(|| format!("failed to download {}", file.path))?;
+
+    // A server that ignores the range sends the whole file again.
+    let resumed = have > 0 && response.status() == 206;
+    let mut out = if resumed {
+        OpenOptions::new().append(true).open(&part)?
+    } else {
+        File::create(&part)?
+    };
+    let written = io::copy(&mut response.into_reader(), &mut out)
+        .with_context(|| format!("download of {} interrupted", file.path))?;
+    out.flush()?;
+    drop(out);
+
+    if let Err(e) = verify(&part, file) {
+        fs::remove_file(&part)?;
+        return Err(e);
+    }
+    fs::rename(&part, dest)?;
+    Ok(written)
+}
+
+/// Download the NDJSON files of dataset `repo` into `cache_dir`, skipping
+/// files already there. `filter_coin` and `limit` select files the same way
+/// [`import_hf_directory`](super::import_hf_directory) does, so a filtered
+/// import only downloads what it reads.
+pub fn download_hf_dataset(
+    repo: &str,
+    cache_dir: &Path,
+    filter_coin: Option<&str>,
+    limit: Option<usize>,
+) -> Result<HfDownloadStats> {
+    let mut files: Vec<HubFile> = list_dataset_files(repo, "main")?
+        .into_iter()
+        .filter(|f| {
+            let name = f.path.rsplit('/').next().unwrap_or_default();
+            match parse_filename(name) {
+                Ok(parsed) => filter_coin.is_none_or(|coin| parsed.coin == coin),
+                Err(_) => false,
+            }
+        })
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    if let Some(max) = limit {
+        files.truncate(max);
+    }
+
+    let mut stats = HfDownloadStats {
+        files_listed: files.len(),
+        ..Default::default()
+    };
+    info!("downloading {} files of {} into {}", files.len(), repo, cache_dir.display());
+
+    for file in &files {
+        let dest = cache_dir.join(&file.path);
+        if fs::metadata(&dest).is_ok_and(|m| m.len() == file.size()) {
+            stats.files_cached += 1;
+            continue;
+        }
+        let bytes = download_file(repo, "main", file, &dest).inspect_err(|e| warn!("{:#}", e))?;
+        stats.files_downloaded += 1;
+        stats.bytes_downloaded += bytes;
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hub_file(oid: &str, lfs: Option<&str>) -> HubFile {
+        HubFile {
+            kind: "file".into(),
+            path: "btc15m_market1_2026-01-15_10-30-00.ndjson".into(),
+            size: 6,
+            oid: oid.into(),
+            lfs: lfs.map(|oid| HubLfs { oid: oid.into(), size: 6 }),
+        }
+    }
+
+    #[test]
+    fn test_verify_checks_git_and_lfs_hashes() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("file.ndjson");
+        fs::write(&path, "hello\n").unwrap();
+
+        assert!(verify(&path, &hub_file("ce013625030ba8dba906f756967f9e9ca394464a", None)).is_ok());
+        let sha256 = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+        assert!(verify(&path, &hub_file("unused", Some(sha256))).is_ok());
+        assert!(verify(&path, &hub_file("0000", None)).is_err());
+    }
+
+    #[test]
+    fn test_next_link() {
+        let header = "<https://huggingface.co/api/datasets/x/tree/main?cursor=abc>; rel=\"next\"";
+        assert_eq!(
+            next_link(header).as_deref(),
+            Some("https://huggingface.co/api/datasets/x/tree/main?cursor=abc")
+        );
+        assert_eq!(next_link("<https://example.com>; rel=\"prev\""), None);
+    }
+}
@@ -1,18 +1,26 @@
 //! Import adapter for the HuggingFace `trentmkelly/polymarket_crypto_derivatives` dataset.
 //!
 //! Reads NDJSON files with tick-by-tick Polymarket orderbook data and writes
-//! them into PhantomFill's native SQLite format.
+//! them into PhantomFill's native SQLite format. Trade rows that carry a
+//! price and size are kept as the market's trade tape (`pf_trades`).
 
 use std::collections::HashMap;
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
 
 use anyhow::{bail, Context, Result};
 use serde::Deserialize;
+use smallvec::smallvec;
 use tracing::{debug, info, warn};
 
-use crate::types::{BookTick, Market, Outcome, Platform, PriceLevel, Side};
+use crate::types::{
+    BookTick, DepthLevels, Market, MarketId, OffsetMs, Outcome, Platform, PriceLevel, Side, TakerSide,
+    Trade, UnixSecs,
+};
 
 use super::store::DataStore;
 
@@ -42,6 +50,15 @@ pub struct HfRow {
     pub bid_size_total: Option<f64>,
     /// Total ask-side depth across all levels.
     pub ask_size_total: Option<f64>,
+    /// Trade rows: execution price.
+    #[serde(default)]
+    pub price: Option<f64>,
+    /// Trade rows: shares executed.
+    #[serde(default)]
+    pub size: Option<f64>,
+    /// Trade rows: taker side, `"BUY"` or `"SELL"`.
+    #[serde(default)]
+    pub side: Option<String>,
 }
 
 // ---------------------------------------------------------------------------
@@ -53,8 +70,8 @@ pub struct HfRow {
 pub struct ParsedFilename {
     /// PhantomFill market id, e.g. `"hf-btc15m-42"`.
     pub market_id: String,
-    /// Window open time (Unix seconds, UTC).
-    pub open_ts: i64,
+    /// Window open time. Filename datetimes are UTC.
+    pub open_ts: UnixSecs,
     /// Coin symbol, e.g. `"btc"`.
     pub coin: String,
     /// Timeframe label, e.g. `"15m"`.
@@ -114,7 +131,7 @@ pub fn parse_filename(name: &str) -> Result<ParsedFilename> {
                 date_part, time_part
             )
         })?;
-    let open_ts = dt.and_utc().timestamp();
+    let open_ts = UnixSecs::from_datetime(dt.and_utc());
 
     let market_id = format!("hf-{}{}-{}", coin, timeframe, market_num);
 
@@ -134,21 +151,13 @@ pub fn parse_filename(name: &str) -> Result<ParsedFilename> {
 /// Convert one HF dataset row into a [`BookTick`].
 ///
 /// Returns `None` for trade rows (type != 1) or rows without a clear side.
-pub fn map_row(row: &HfRow, market_id: &str, duration_secs: i64) -> Option<BookTick> {
+pub fn map_row(row: &HfRow, market_id: &MarketId, duration_secs: i64) -> Option<BookTick> {
     if row.row_type != 1 {
         return None;
     }
 
-    let side = if row.outcome_up == Some(1) {
-        Side::Yes
-    } else if row.outcome_down == Some(1) {
-        Side::No
-    } else {
-        return None;
-    };
-
-    let duration_ms = duration_secs * 1000;
-    let offset_ms = (row.progress * duration_ms as f64).round() as i64;
+    let side = row_side(row)?;
+    let offset_ms = row_offset(row, duration_secs);
 
     let total_bid_depth = row.bid_size_total.unwrap_or(0.0);
     let total_ask_depth = row.ask_size_total.unwrap_or(0.0);
@@ -157,16 +166,16 @@ pub fn map_row(row: &HfRow, market_id: &str, duration_secs: i64) -> Option<BookT
     // This makes the fill model harder to fill (safer than optimistic).
     let depth = match row.best_bid {
         Some(price) if total_bid_depth > 0.0 => {
-            vec![PriceLevel {
+            smallvec![PriceLevel {
                 price,
                 cumulative_size: total_bid_depth,
             }]
         }
-        _ => vec![],
+        _ => DepthLevels::new(),
     };
 
     Some(BookTick {
-        market_id: market_id.to_string(),
+        market_id: market_id.clone(),
         side,
         timestamp_ms: row.ts,
         offset_ms,
@@ -182,10 +191,49 @@ pub fn map_row(row: &HfRow, market_id: &str, duration_secs: i64) -> Option<BookT
     })
 }
 
+/// Convert one HF trade row (type 2) into a [`Trade`].
+///
+/// Returns `None` for snapshot rows, rows without a clear side, and trade
+/// rows missing a price or a positive size.
+pub fn map_trade_row(row: &HfRow, market_id: &MarketId, duration_secs: i64) -> Option<Trade> {
+    if row.row_type != 2 {
+        return None;
+    }
+    let side = row_side(row)?;
+    let price = row.price?;
+    let size = row.size.filter(|s| *s > 0.0)?;
+
+    Some(Trade {
+        market_id: market_id.clone(),
+        side,
+        timestamp_ms: row.ts,
+        offset_ms: row_offset(row, duration_secs),
+        price,
+        size,
+        taker_side: row.side.as_deref().and_then(TakerSide::from_label),
+    })
+}
+
+fn row_side(row: &HfRow) -> Option<Side> {
+    if row.outcome_up == Some(1) {
+        Some(Side::Yes)
+    } else if row.outcome_down == Some(1) {
+        Some(Side::No)
+    } else {
+        None
+    }
+}
+
+fn row_offset(row: &HfRow, duration_secs: i64) -> OffsetMs {
+    let duration_ms = duration_secs * 1000;
+    OffsetMs::new((row.progress * duration_ms as f64).round() as i64)
+}
+
 // ---------------------------------------------------------------------------
 // Binance klines (oracle resolution)
 // ---------------------------------------------------------------------------
 
+#[cfg(feature = "net")]
 /// Fetch Binance 15m klines for a time range.
 ///
 /// Returns a map from kline open time (ms) → (open_price, close_price).
@@ -194,14 +242,27 @@ pub fn fetch_binance_klines(
     symbol: &str,
     start_ms: i64,
     end_ms: i64,
+) -> Result<HashMap<i64, (f64, f64)>> {
+    fetch_binance_klines_interval(symbol, "15m", start_ms, end_ms)
+}
+
+#[cfg(feature = "net")]
+/// Fetch Binance klines of any interval (e.g. `"5m"`, `"1h"`) for a time range.
+///
+/// Same return shape as [`fetch_binance_klines`].
+pub fn fetch_binance_klines_interval(
+    symbol: &str,
+    interval: &str,
+    start_ms: i64,
+    end_ms: i64,
 ) -> Result<HashMap<i64, (f64, f64)>> {
     let mut klines = HashMap::new();
     let mut current_start = start_ms;
 
     loop {
         let url = format!(
-            "https://api.binance.com/api/v3/klines?symbol={}&interval=15m&startTime={}&endTime={}&limit=1000",
-            symbol, current_start, end_ms
+            "https://api.binance.com/api/v3/klines?symbol={}&interval={}&startTime={}&endTime={}&limit=1000",
+            symbol, interval, current_start, end_ms
         );
 
         let body: String = ureq::get(&url)
@@ -242,20 +303,20 @@ pub fn fetch_binance_klines(
         current_start = last_open + 1;
     }
 
-    info!("fetched {} Binance klines for {}", klines.len(), symbol);
+    info!("fetched {} Binance {} klines for {}", klines.len(), interval, symbol);
     Ok(klines)
 }
 
 /// Determine the outcome of a window from Binance kline data.
 ///
-/// Looks up the kline whose open time matches `open_ts_secs * 1000`.
+/// Looks up the kline whose open time (Binance keys are Unix milliseconds)
+/// matches `open_ts`.
 /// Returns `Outcome::Yes` if close > open (price went up), else `Outcome::No`.
 pub fn determine_outcome(
     klines: &HashMap<i64, (f64, f64)>,
-    open_ts_secs: i64,
+    open_ts: UnixSecs,
 ) -> Option<Outcome> {
-    let open_ts_ms = open_ts_secs * 1000;
-    klines.get(&open_ts_ms).map(|(open, close)| {
+    klines.get(&open_ts.to_millis()).map(|(open, close)| {
         if close > open {
             Outcome::Yes
         } else {
@@ -264,6 +325,24 @@ pub fn determine_outcome(
     })
 }
 
+/// Infer a window's outcome from its own book when no klines are available:
+/// the side whose last best bid reached 0.90 won. `None` if neither side
+/// converged before the data ends.
+pub fn outcome_from_final_book(ticks: &[BookTick]) -> Option<Outcome> {
+    let last_bid = |side: Side| {
+        ticks
+            .iter()
+            .rev()
+            .find(|t| t.side == side && t.best_bid.is_some())
+            .and_then(|t| t.best_bid)
+    };
+    match (last_bid(Side::Yes), last_bid(Side::No)) {
+        (Some(yes), _) if yes >= 0.90 => Some(Outcome::Yes),
+        (_, Some(no)) if no >= 0.90 => Some(Outcome::No),
+        _ => None,
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Import pipeline
 // ---------------------------------------------------------------------------
@@ -275,6 +354,7 @@ pub struct HfImportStats {
     pub files_skipped: usize,
     pub markets_imported: usize,
     pub ticks_imported: usize,
+    pub trades_imported: usize,
     pub rows_filtered: usize,
 }
 
@@ -289,9 +369,61 @@ pub fn import_single_file(
 ) -> Result<(usize, usize)> {
     let file =
         fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
-    let reader = BufReader::new(file);
+    import_ndjson(BufReader::new(file), &path.display().to_string(), parsed, dest, outcome)
+}
+
+/// Import NDJSON rows from any reader (a file, an uploaded buffer) as the
+/// market described by `parsed`. `source` names the input in error messages.
+///
+/// Returns `(ticks imported, rows filtered)`; trades go in alongside.
+pub fn import_ndjson(
+    reader: impl BufRead,
+    source: &str,
+    parsed: &ParsedFilename,
+    dest: &dyn DataStore,
+    outcome: Option<Outcome>,
+) -> Result<(usize, usize)> {
+    dest.insert_market(&hf_market(parsed, outcome))?;
+
+    let mut ticks = Vec::with_capacity(10_000);
+    let mut trades = Vec::new();
+    let (imported, filtered) = read_ticks(
+        reader,
+        source,
+        parsed,
+        &mut |tick| {
+            ticks.push(tick);
+            if ticks.len() >= 10_000 {
+                dest.insert_ticks(&ticks)?;
+                ticks.clear();
+            }
+            Ok(())
+        },
+        &mut |trade| {
+            trades.push(trade);
+            Ok(())
+        },
+    )?;
+
+    if !ticks.is_empty() {
+        dest.insert_ticks(&ticks)?;
+    }
+    dest.insert_trades(&trades)?;
+
+    debug!(
+        market_id = %parsed.market_id,
+        imported,
+        trades = trades.len(),
+        filtered,
+        "imported file"
+    );
+
+    Ok((imported, filtered))
+}
 
-    let market = Market {
+/// The market a file described by `parsed` imports as.
+fn hf_market(parsed: &ParsedFilename, outcome: Option<Outcome>) -> Market {
+    Market {
         id: parsed.market_id.clone(),
         platform: Platform::Polymarket,
         description: format!(
@@ -305,56 +437,46 @@ pub fn import_single_file(
         close_ts: parsed.open_ts + parsed.duration_secs,
         duration_secs: parsed.duration_secs,
         outcome,
-    };
-    dest.insert_market(&market)?;
+        outcome_ts: None,
+    }
+}
 
-    let mut ticks = Vec::with_capacity(10_000);
+/// Parse NDJSON rows, passing each mapped tick to `on_tick` and each
+/// mapped trade to `on_trade`.
+///
+/// Returns `(ticks mapped, rows filtered)`.
+fn read_ticks(
+    reader: impl BufRead,
+    source: &str,
+    parsed: &ParsedFilename,
+    on_tick: &mut dyn FnMut(BookTick) -> Result<()>,
+    on_trade: &mut dyn FnMut(Trade) -> Result<()>,
+) -> Result<(usize, usize)> {
+    let market_id = MarketId::from(parsed.market_id.as_str());
     let mut imported = 0usize;
     let mut filtered = 0usize;
 
     for (line_num, line) in reader.lines().enumerate() {
-        let line = line.with_context(|| {
-            format!("I/O error at line {} of {}", line_num + 1, path.display())
-        })?;
+        let line = line
+            .with_context(|| format!("I/O error at line {} of {}", line_num + 1, source))?;
         if line.is_empty() {
             continue;
         }
 
         let row: HfRow = serde_json::from_str(&line).with_context(|| {
-            format!(
-                "JSON parse error at line {} of {}",
-                line_num + 1,
-                path.display()
-            )
+            format!("JSON parse error at line {} of {}", line_num + 1, source)
         })?;
 
-        match map_row(&row, &parsed.market_id, parsed.duration_secs) {
-            Some(tick) => {
-                ticks.push(tick);
-                imported += 1;
-            }
-            None => {
-                filtered += 1;
-            }
-        }
-
-        if ticks.len() >= 10_000 {
-            dest.insert_ticks(&ticks)?;
-            ticks.clear();
+        if let Some(tick) = map_row(&row, &market_id, parsed.duration_secs) {
+            on_tick(tick)?;
+            imported += 1;
+        } else if let Some(trade) = map_trade_row(&row, &market_id, parsed.duration_secs) {
+            on_trade(trade)?;
+        } else {
+            filtered += 1;
         }
     }
 
-    if !ticks.is_empty() {
-        dest.insert_ticks(&ticks)?;
-    }
-
-    debug!(
-        market_id = %parsed.market_id,
-        imported,
-        filtered,
-        "imported file"
-    );
-
     Ok((imported, filtered))
 }
 
@@ -384,13 +506,70 @@ fn collect_ndjson_recursive(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()>
     Ok(())
 }
 
-/// Import all NDJSON files from a directory into the destination store.
+/// The time span covered by the NDJSON files under `dir`, in Unix
+/// milliseconds: the earliest window open to the latest window close. Used
+/// to bound the Binance kline fetch before an import.
+pub fn scan_date_range(dir: &Path, filter_coin: Option<&str>) -> Result<(i64, i64)> {
+    let mut min_ts: Option<UnixSecs> = None;
+    let mut max_ts: Option<UnixSecs> = None;
+
+    for path in collect_ndjson_files(dir)? {
+        let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if let Ok(parsed) = parse_filename(filename) {
+            if filter_coin.is_some_and(|coin| parsed.coin != coin) {
+                continue;
+            }
+            let ts = parsed.open_ts;
+            min_ts = Some(min_ts.map_or(ts, |m| m.min(ts)));
+            max_ts = Some(max_ts.map_or(ts + parsed.duration_secs, |m| m.max(ts + parsed.duration_secs)));
+        }
+    }
+
+    match (min_ts, max_ts) {
+        (Some(min), Some(max)) => Ok((min.to_millis(), max.to_millis())),
+        _ => bail!("no valid NDJSON files found in {}", dir.display()),
+    }
+}
+
+/// Ticks buffered by the directory importer's writer before it commits
+/// them in one transaction.
+const WRITE_BATCH_TICKS: usize = 200_000;
+
+/// Import all NDJSON files from a directory into the destination store,
+/// parsing with one worker per available core.
 pub fn import_hf_directory(
     dir: &Path,
     dest: &dyn DataStore,
     klines: &HashMap<i64, (f64, f64)>,
     filter_coin: Option<&str>,
     limit: Option<usize>,
+) -> Result<HfImportStats> {
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    import_hf_directory_with_workers(dir, dest, klines, filter_coin, limit, workers)
+}
+
+/// A file fully parsed by an import worker.
+struct ParsedFile {
+    name: String,
+    market: Market,
+    ticks: Vec<BookTick>,
+    trades: Vec<Trade>,
+    filtered: usize,
+}
+
+/// [`import_hf_directory`] with `workers` parse threads.
+///
+/// Workers parse whole files in parallel and hand them to the calling
+/// thread, the only one writing to `dest`, which commits ticks in large
+/// batches. Each file's ticks stay contiguous and in file order; files may
+/// be written in any order. A file that fails to parse is skipped whole.
+pub fn import_hf_directory_with_workers(
+    dir: &Path,
+    dest: &dyn DataStore,
+    klines: &HashMap<i64, (f64, f64)>,
+    filter_coin: Option<&str>,
+    limit: Option<usize>,
+    workers: usize,
 ) -> Result<HfImportStats> {
     let mut stats = HfImportStats::default();
 
@@ -401,7 +580,8 @@ pub fn import_hf_directory(
 
     info!("found {} NDJSON files in {}", entries.len(), dir.display());
 
-    for (i, path) in entries.iter().enumerate() {
+    let mut jobs = Vec::with_capacity(entries.len());
+    for path in &entries {
         let filename = path
             .file_name()
             .and_then(|n| n.to_str())
@@ -424,32 +604,144 @@ pub fn import_hf_directory(
         }
 
         let outcome = determine_outcome(klines, parsed.open_ts);
+        jobs.push((path, filename, parsed, outcome));
+    }
 
-        match import_single_file(path, &parsed, dest, outcome) {
-            Ok((imported, filtered)) => {
+    let workers = workers.clamp(1, jobs.len().max(1));
+    let next = AtomicUsize::new(0);
+    // Bounded, so parsed files never pile up ahead of the writer.
+    let (tx, rx) = mpsc::sync_channel::<Result<ParsedFile, (String, anyhow::Error)>>(workers * 2);
+
+    thread::scope(|scope| -> Result<()> {
+        for _ in 0..workers {
+            let (tx, next, jobs) = (tx.clone(), &next, &jobs);
+            scope.spawn(move || {
+                while let Some((path, filename, parsed, outcome)) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let parsed_file = parse_file(path, parsed).map(|(ticks, trades, filtered)| ParsedFile {
+                        name: filename.to_string(),
+                        market: hf_market(parsed, *outcome),
+                        ticks,
+                        trades,
+                        filtered,
+                    });
+                    if tx.send(parsed_file.map_err(|e| (filename.to_string(), e))).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        let mut pending = Pending {
+            ticks: Vec::with_capacity(WRITE_BATCH_TICKS),
+            ..Pending::default()
+        };
+        let mut done = stats.files_skipped;
+        let total = entries.len();
+
+        for received in rx {
+            done += 1;
+            match received {
+                Ok(file) => match dest.insert_market(&file.market) {
+                    Ok(()) => {
+                        pending.files.push((file.name, file.ticks.len(), file.trades.len(), file.filtered));
+                        pending.ticks.extend(file.ticks);
+                        pending.trades.extend(file.trades);
+                    }
+                    Err(e) => {
+                        warn!("error importing {}: {}", file.name, e);
+                        stats.files_skipped += 1;
+                    }
+                },
+                Err((filename, e)) => {
+                    warn!("error importing {}: {}", filename, e);
+                    stats.files_skipped += 1;
+                }
+            }
+
+            if pending.ticks.len() >= WRITE_BATCH_TICKS {
+                flush(dest, &mut pending, &mut stats);
+            }
+
+            if done % 100 == 0 || done == total {
+                flush(dest, &mut pending, &mut stats);
+                info!(
+                    files_done = done,
+                    files_total = total,
+                    markets = stats.markets_imported,
+                    ticks = stats.ticks_imported,
+                    "import progress"
+                );
+            }
+        }
+        flush(dest, &mut pending, &mut stats);
+        Ok(())
+    })?;
+
+    dest.analyze()?;
+    Ok(stats)
+}
+
+type ParsedRows = (Vec<BookTick>, Vec<Trade>, usize);
+
+/// Parse one file into its ticks and trades; returns
+/// `(ticks, trades, rows filtered)`.
+fn parse_file(path: &Path, parsed: &ParsedFilename) -> Result<ParsedRows> {
+    let file =
+        fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut ticks = Vec::new();
+    let mut trades = Vec::new();
+    let (_, filtered) = read_ticks(
+        BufReader::new(file),
+        &path.display().to_string(),
+        parsed,
+        &mut |tick| {
+            ticks.push(tick);
+            Ok(())
+        },
+        &mut |trade| {
+            trades.push(trade);
+            Ok(())
+        },
+    )?;
+    Ok((ticks, trades, filtered))
+}
+
+/// Rows buffered by the directory importer's writer, and the files they
+/// came from as `(name, ticks, trades, rows filtered)`.
+#[derive(Default)]
+struct Pending {
+    ticks: Vec<BookTick>,
+    trades: Vec<Trade>,
+    files: Vec<(String, usize, usize, usize)>,
+}
+
+/// Commit the writer's buffered rows and count the files they came from
+/// as imported (or skipped, if the write fails).
+fn flush(dest: &dyn DataStore, pending: &mut Pending, stats: &mut HfImportStats) {
+    if pending.files.is_empty() {
+        return;
+    }
+    match dest.insert_ticks_and_trades(&pending.ticks, &pending.trades) {
+        Ok(_) => {
+            for (name, imported, trades, filtered) in pending.files.drain(..) {
+                debug!(file = %name, imported, trades, filtered, "imported file");
                 stats.ticks_imported += imported;
+                stats.trades_imported += trades;
                 stats.rows_filtered += filtered;
                 stats.markets_imported += 1;
                 stats.files_processed += 1;
             }
-            Err(e) => {
-                warn!("error importing {}: {}", filename, e);
+        }
+        Err(e) => {
+            for (name, ..) in pending.files.drain(..) {
+                warn!("error importing {}: {}", name, e);
                 stats.files_skipped += 1;
             }
         }
-
-        if (i + 1) % 100 == 0 || i + 1 == entries.len() {
-            info!(
-                "progress: {}/{} files, {} markets, {} ticks",
-                i + 1,
-                entries.len(),
-                stats.markets_imported,
-                stats.ticks_imported
-            );
-        }
     }
-
-    Ok(stats)
+    pending.ticks.clear();
+    pending.trades.clear();
 }
 
 // ---------------------------------------------------------------------------
@@ -460,6 +752,7 @@ pub fn import_hf_directory(
 mod tests {
     use super::*;
     use crate::data::store::SqliteStore;
+    use crate::data::MarketFilter;
     use std::io::Write;
     use tempfile::TempDir;
 
@@ -477,7 +770,7 @@ mod tests {
             .unwrap()
             .and_utc()
             .timestamp();
-        assert_eq!(p.open_ts, expected);
+        assert_eq!(p.open_ts.secs(), expected);
     }
 
     #[test]
@@ -535,9 +828,12 @@ mod tests {
             best_ask_size: Some(200.0),
             bid_size_total: Some(500.0),
             ask_size_total: Some(300.0),
+            price: None,
+            size: None,
+            side: None,
         };
 
-        let tick = map_row(&row, "hf-btc15m-1", 900).unwrap();
+        let tick = map_row(&row, &"hf-btc15m-1".into(), 900).unwrap();
         assert_eq!(tick.side, Side::Yes);
         assert_eq!(tick.timestamp_ms, 1705315800000);
         assert_eq!(tick.offset_ms, 450_000); // 0.5 * 900_000
@@ -563,9 +859,12 @@ mod tests {
             best_ask_size: Some(60.0),
             bid_size_total: Some(200.0),
             ask_size_total: Some(100.0),
+            price: None,
+            size: None,
+            side: None,
         };
 
-        let tick = map_row(&row, "hf-btc15m-1", 900).unwrap();
+        let tick = map_row(&row, &"hf-btc15m-1".into(), 900).unwrap();
         assert_eq!(tick.side, Side::No);
         assert_eq!(tick.offset_ms, 0);
     }
@@ -584,9 +883,12 @@ mod tests {
             best_ask_size: Some(200.0),
             bid_size_total: Some(500.0),
             ask_size_total: Some(300.0),
+            price: None,
+            size: None,
+            side: None,
         };
 
-        assert!(map_row(&row, "hf-btc15m-1", 900).is_none());
+        assert!(map_row(&row, &"hf-btc15m-1".into(), 900).is_none());
     }
 
     #[test]
@@ -603,9 +905,12 @@ mod tests {
             best_ask_size: None,
             bid_size_total: None,
             ask_size_total: None,
+            price: None,
+            size: None,
+            side: None,
         };
 
-        assert!(map_row(&row, "hf-btc15m-1", 900).is_none());
+        assert!(map_row(&row, &"hf-btc15m-1".into(), 900).is_none());
     }
 
     #[test]
@@ -622,9 +927,12 @@ mod tests {
             best_ask_size: Some(200.0),
             bid_size_total: Some(0.0),
             ask_size_total: Some(300.0),
+            price: None,
+            size: None,
+            side: None,
         };
 
-        let tick = map_row(&row, "hf-btc15m-1", 900).unwrap();
+        let tick = map_row(&row, &"hf-btc15m-1".into(), 900).unwrap();
         assert!(tick.depth.is_empty());
     }
 
@@ -642,9 +950,12 @@ mod tests {
             best_ask_size: Some(200.0),
             bid_size_total: Some(500.0),
             ask_size_total: Some(300.0),
+            price: None,
+            size: None,
+            side: None,
         };
 
-        let tick = map_row(&row, "hf-btc5m-1", 300).unwrap();
+        let tick = map_row(&row, &"hf-btc5m-1".into(), 300).unwrap();
         assert_eq!(tick.offset_ms, 300_000); // 1.0 * 300_000
     }
 
@@ -654,7 +965,7 @@ mod tests {
     fn test_determine_outcome_up() {
         let mut klines = HashMap::new();
         klines.insert(1705315800000i64, (100000.0, 100100.0));
-        let outcome = determine_outcome(&klines, 1705315800);
+        let outcome = determine_outcome(&klines, UnixSecs::new(1705315800));
         assert_eq!(outcome, Some(Outcome::Yes));
     }
 
@@ -662,7 +973,7 @@ mod tests {
     fn test_determine_outcome_down() {
         let mut klines = HashMap::new();
         klines.insert(1705315800000i64, (100100.0, 100000.0));
-        let outcome = determine_outcome(&klines, 1705315800);
+        let outcome = determine_outcome(&klines, UnixSecs::new(1705315800));
         assert_eq!(outcome, Some(Outcome::No));
     }
 
@@ -671,14 +982,41 @@ mod tests {
         let mut klines = HashMap::new();
         klines.insert(1705315800000i64, (100000.0, 100000.0));
         // close == open → No (not strictly up)
-        let outcome = determine_outcome(&klines, 1705315800);
+        let outcome = determine_outcome(&klines, UnixSecs::new(1705315800));
         assert_eq!(outcome, Some(Outcome::No));
     }
 
     #[test]
     fn test_determine_outcome_missing() {
         let klines = HashMap::new();
-        assert_eq!(determine_outcome(&klines, 1705315800), None);
+        assert_eq!(determine_outcome(&klines, UnixSecs::new(1705315800)), None);
+    }
+
+    #[test]
+    fn test_outcome_from_final_book() {
+        let tick = |side, bid| {
+            let row = HfRow {
+                ts: 0,
+                progress: 1.0,
+                row_type: 1,
+                outcome_up: Some((side == Side::Yes) as i32),
+                outcome_down: Some((side == Side::No) as i32),
+                best_bid: Some(bid),
+                best_bid_size: None,
+                best_ask: None,
+                best_ask_size: None,
+                bid_size_total: None,
+                ask_size_total: None,
+                price: None,
+                size: None,
+                side: None,
+            };
+            map_row(&row, &"m".into(), 300).unwrap()
+        };
+        let ticks = [tick(Side::Yes, 0.50), tick(Side::No, 0.48), tick(Side::No, 0.95), tick(Side::Yes, 0.04)];
+        assert_eq!(outcome_from_final_book(&ticks), Some(Outcome::No));
+        assert_eq!(outcome_from_final_book(&ticks[..2]), None);
+        assert_eq!(outcome_from_final_book(&[]), None);
     }
 
     // -- import pipeline (end-to-end with temp files) -------------------------
@@ -752,6 +1090,63 @@ mod tests {
         assert_eq!(ticks.len(), 20);
     }
 
+    #[test]
+    fn test_import_keeps_trade_tape() {
+        let trade = |progress: f64, price: f64, side: &str| {
+            format!(
+                r#"{{"ts":1705315800000,"progress":{},"type":2,"outcome_up":0,"outcome_down":1,"price":{},"size":40.0,"side":"{}"}}"#,
+                progress, price, side
+            )
+        };
+        let lines = [
+            make_ndjson_line(0.1, false, 0.48),
+            trade(0.2, 0.48, "SELL"),
+            trade(0.3, 0.47, "BUY"),
+            make_trade_line(),
+        ];
+        let dest = SqliteStore::in_memory().unwrap();
+        dest.init().unwrap();
+
+        let parsed = parse_filename("btc15m_market1_2026-01-15_10-30-00.ndjson").unwrap();
+        let (imported, filtered) =
+            import_ndjson(lines.join("\n").as_bytes(), "test", &parsed, &dest, None).unwrap();
+        assert_eq!((imported, filtered), (1, 1)); // the trade without a price is dropped
+
+        let trades = dest.load_trades("hf-btc15m-1").unwrap();
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].side, Side::No);
+        assert_eq!(trades[0].offset_ms, OffsetMs::new(180_000));
+        assert_eq!(trades[0].taker_side, Some(TakerSide::Sell));
+        assert_eq!((trades[1].price, trades[1].size), (0.47, 40.0));
+    }
+
+    #[test]
+    fn test_reimporting_a_directory_adds_nothing() {
+        let tmp = TempDir::new().unwrap();
+        let trade = |progress: f64, price: f64| {
+            format!(
+                r#"{{"ts":1705315800000,"progress":{},"type":2,"outcome_up":0,"outcome_down":1,"price":{},"size":40.0,"side":"BUY"}}"#,
+                progress, price
+            )
+        };
+        let lines = [
+            make_ndjson_line(0.1, true, 0.49),
+            make_ndjson_line(0.1, false, 0.48),
+            trade(0.2, 0.48),
+            trade(0.3, 0.47),
+        ];
+        write_ndjson_file(tmp.path(), "btc15m_market1_2026-01-15_10-30-00.ndjson", &lines);
+
+        let dest = SqliteStore::in_memory().unwrap();
+        dest.init().unwrap();
+        let klines = HashMap::new();
+        for _ in 0..2 {
+            import_hf_directory(tmp.path(), &dest, &klines, None, None).unwrap();
+        }
+        assert_eq!(dest.load_ticks("hf-btc15m-1").unwrap().len(), 2);
+        assert_eq!(dest.load_trades("hf-btc15m-1").unwrap().len(), 2);
+    }
+
     #[test]
     fn test_import_directory_multiple_files() {
         let tmp = TempDir::new().unwrap();
@@ -780,6 +1175,54 @@ mod tests {
         assert_eq!(stats.files_processed, 3);
         assert_eq!(stats.markets_imported, 3);
         assert_eq!(stats.ticks_imported, 30); // 3 files * 5 offsets * 2 sides
+
+        // The import ends with ANALYZE, so the planner has statistics.
+        let analyzed: i64 = dest
+            .conn()
+            .query_row("SELECT COUNT(*) FROM sqlite_stat1 WHERE tbl = 'pf_ticks'", [], |r| r.get(0))
+            .unwrap();
+        assert!(analyzed > 0);
+    }
+
+    #[test]
+    fn test_import_directory_workers_match_serial() {
+        let tmp = TempDir::new().unwrap();
+        for i in 0..12 {
+            let lines: Vec<String> = (0..=i)
+                .map(|j| make_ndjson_line(j as f64 / 12.0, j % 2 == 0, 0.40 + j as f64 / 100.0))
+                .chain([make_trade_line()])
+                .collect();
+            let filename = format!("btc5m_market{}_2026-01-15_10-{:02}-00.ndjson", i, i * 5);
+            write_ndjson_file(tmp.path(), &filename, &lines);
+        }
+        write_ndjson_file(tmp.path(), "btc5m_market99_2026-01-15_11-00-00.ndjson", &["{".to_string()]);
+
+        let import = |workers| {
+            let dest = SqliteStore::in_memory().unwrap();
+            dest.init().unwrap();
+            let stats =
+                import_hf_directory_with_workers(tmp.path(), &dest, &HashMap::new(), None, None, workers).unwrap();
+            (stats, dest)
+        };
+        let (serial, serial_db) = import(1);
+        let (parallel, parallel_db) = import(4);
+        assert_eq!(serial.files_processed, 12);
+        assert_eq!(serial.files_skipped, 1);
+        assert_eq!(serial.ticks_imported, 78);
+        assert_eq!(serial.rows_filtered, 12);
+        assert_eq!(
+            (parallel.files_processed, parallel.files_skipped, parallel.ticks_imported, parallel.rows_filtered),
+            (12, 1, 78, 12)
+        );
+
+        let markets = serial_db.list_markets(&MarketFilter::default()).unwrap();
+        assert_eq!(markets.len(), 12);
+        for market in &markets {
+            let offsets = |db: &SqliteStore| -> Vec<(i64, Option<f64>)> {
+                db.load_ticks(&market.id).unwrap().iter().map(|t| (t.offset_ms.ms(), t.best_bid)).collect()
+            };
+            assert_eq!(offsets(&serial_db), offsets(&parallel_db));
+        }
     }
 
     #[test]
@@ -0,0 +1,530 @@
+//! Memory-mapped binary snapshot cache (`mmap` feature).
+//!
+//! Grouping ticks into snapshots costs the same on every run. This module
+//! writes the grouped [`BookSnapshot`]s of many markets into one compact
+//! file of fixed-size little-endian records that is memory-mapped on open:
+//! [`MappedSnapshots::views`] reads fields straight out of the mapping, and
+//! [`MappedSnapshots::load`] turns a market back into owned snapshots
+//! without touching SQLite or a parser.
+//!
+//! Layout: a header (`PFSNAPS\0`, version, market count, index offset),
+//! then per market its snapshot records followed by its depth levels, then
+//! an index of `(market id, record offset, record count, level offset,
+//! level count)`. The file is not updated when the source database
+//! changes; delete it after re-importing.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use memmap2::Mmap;
+use tracing::info;
+
+use crate::types::{BookSnapshot, Market, MarketId, OffsetMs, PriceLevel, Side, SideState};
+
+use super::snapshots::ticks_to_snapshots;
+use super::store::DataStore;
+
+const MAGIC: &[u8; 8] = b"PFSNAPS\0";
+/// Version of the record layout below. Bump it when the layout changes.
+pub const MAPPED_FORMAT_VERSION: u32 = 1;
+
+const HEADER_LEN: usize = 24;
+/// best_bid, best_bid_size, best_ask, best_ask_size, total_bid_depth,
+/// total_ask_depth, then depth start and length as two u32s.
+const SIDE_LEN: usize = 56;
+/// offset_ms, timestamp_ms, reference_price, oracle_price, presence flags,
+/// then the YES and NO sides.
+const RECORD_LEN: usize = 40 + 2 * SIDE_LEN;
+const LEVEL_LEN: usize = 16;
+
+const HAS_REFERENCE: u64 = 1;
+const HAS_ORACLE: u64 = 1 << 1;
+/// Flag bits of a side's four optional top-of-book fields start here.
+const YES_FLAGS: u32 = 2;
+const NO_FLAGS: u32 = 6;
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().expect("4 bytes"))
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().expect("8 bytes"))
+}
+
+fn i64_at(bytes: &[u8], at: usize) -> i64 {
+    i64::from_le_bytes(bytes[at..at + 8].try_into().expect("8 bytes"))
+}
+
+fn f64_at(bytes: &[u8], at: usize) -> f64 {
+    f64::from_le_bytes(bytes[at..at + 8].try_into().expect("8 bytes"))
+}
+
+/// Where one market's records and levels sit in the file.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    records: usize,
+    count: usize,
+    levels: usize,
+    level_count: usize,
+}
+
+/// A read-only, memory-mapped snapshot cache; see the [module docs](self).
+#[derive(Debug)]
+pub struct MappedSnapshots {
+    mmap: Mmap,
+    index: HashMap<String, Entry>,
+}
+
+impl MappedSnapshots {
+    /// Map a cache written by [`MappedSnapshotsWriter`], checking its header
+    /// and index against the file size.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        // Safety: the cache is only ever replaced by renaming a finished
+        // file over it, never modified in place.
+        let mmap = unsafe { Mmap::map(&file) }.with_context(|| format!("failed to map {}", path.display()))?;
+        let index = Self::read_index(&mmap).with_context(|| format!("invalid snapshot cache {}", path.display()))?;
+        Ok(Self { mmap, index })
+    }
+
+    fn read_index(bytes: &[u8]) -> Result<HashMap<String, Entry>> {
+        if bytes.len() < HEADER_LEN || &bytes[..8] != MAGIC {
+            bail!("not a snapshot cache");
+        }
+        let version = u32_at(bytes, 8);
+        if version != MAPPED_FORMAT_VERSION {
+            bail!(
+                "snapshot cache format {} is not supported (expected {})",
+                version,
+                MAPPED_FORMAT_VERSION
+            );
+        }
+        let markets = u32_at(bytes, 12) as usize;
+        let mut at = u64_at(bytes, 16) as usize;
+
+        let mut index = HashMap::with_capacity(markets);
+        for _ in 0..markets {
+            if at + 4 > bytes.len() {
+                bail!("truncated index");
+            }
+            let id_len = u32_at(bytes, at) as usize;
+            let fields = at + 4 + id_len;
+            if fields + 32 > bytes.len() {
+                bail!("truncated index");
+            }
+            let id = std::str::from_utf8(&bytes[at + 4..fields]).context("market id is not UTF-8")?;
+            let entry = Entry {
+                records: u64_at(bytes, fields) as usize,
+                count: u64_at(bytes, fields + 8) as usize,
+                levels: u64_at(bytes, fields + 16) as usize,
+                level_count: u64_at(bytes, fields + 24) as usize,
+            };
+            if entry.records + entry.count * RECORD_LEN > bytes.len()
+                || entry.levels + entry.level_count * LEVEL_LEN > bytes.len()
+            {
+                bail!("market {} points past the end of the file", id);
+            }
+            index.insert(id.to_string(), entry);
+            at = fields + 32;
+        }
+        Ok(index)
+    }
+
+    /// Open the cache at `path`, first building it from `store` for
+    /// `markets` if the file does not exist yet.
+    pub fn open_or_build(path: &Path, store: &dyn DataStore, markets: &[Market]) -> Result<Self> {
+        if !path.exists() {
+            let mut writer = MappedSnapshotsWriter::create(path)?;
+            let mut failed = None;
+            store.stream_ticks(markets, &mut |ticks| {
+                for market in markets {
+                    let result = ticks(&market.id)
+                        .and_then(|t| writer.add(&market.id, &ticks_to_snapshots(&market.id, &t)));
+                    if let Err(e) = result {
+                        failed = Some(e);
+                        return;
+                    }
+                }
+            })?;
+            if let Some(e) = failed {
+                return Err(e);
+            }
+            writer.finish()?;
+            info!(path = %path.display(), markets = markets.len(), "wrote snapshot cache");
+        }
+        Self::open(path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn contains(&self, market_id: &str) -> bool {
+        self.index.contains_key(market_id)
+    }
+
+    /// Market ids in the cache, in no particular order.
+    pub fn market_ids(&self) -> impl Iterator<Item = &str> {
+        self.index.keys().map(String::as_str)
+    }
+
+    /// A market's snapshots read in place, or `None` if it is not cached.
+    pub fn views(&self, market_id: &str) -> Option<impl ExactSizeIterator<Item = SnapshotView<'_>>> {
+        let (id, entry) = self.index.get_key_value(market_id)?;
+        let records = &self.mmap[entry.records..entry.records + entry.count * RECORD_LEN];
+        let levels = &self.mmap[entry.levels..entry.levels + entry.level_count * LEVEL_LEN];
+        Some(records.chunks_exact(RECORD_LEN).map(move |record| SnapshotView {
+            market_id: id,
+            record,
+            levels,
+        }))
+    }
+
+    /// A market's snapshots as owned [`BookSnapshot`]s, or `None` if it is
+    /// not cached. Sides stored unchanged from the previous snapshot share
+    /// its [`SideState`], as in
+    /// [`ticks_to_snapshots`](super::ticks_to_snapshots).
+    pub fn load(&self, market_id: &str) -> Option<Result<Vec<BookSnapshot>>> {
+        let views = self.views(market_id)?;
+        let id = MarketId::from(market_id);
+        let (mut yes, mut no) = (None, None);
+        Some(
+            views
+                .map(|v| {
+                    Ok(BookSnapshot {
+                        market_id: id.clone(),
+                        offset_ms: v.offset_ms(),
+                        timestamp_ms: v.timestamp_ms(),
+                        yes: shared_side(&mut yes, v.side(Side::Yes))?,
+                        no: shared_side(&mut no, v.side(Side::No))?,
+                        reference_price: v.reference_price(),
+                        oracle_price: v.oracle_price(),
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+/// `view` as a [`SideState`], reusing `prev`'s if the stored side is
+/// byte-for-byte the same.
+fn shared_side<'a>(prev: &mut Option<(SideView<'a>, Arc<SideState>)>, view: SideView<'a>) -> Result<Arc<SideState>> {
+    if let Some((last, state)) = prev {
+        if last.bytes == view.bytes && last.flags == view.flags {
+            return Ok(Arc::clone(state));
+        }
+    }
+    let state = Arc::new(view.to_state()?);
+    *prev = Some((view, Arc::clone(&state)));
+    Ok(state)
+}
+
+/// One cached snapshot, read field by field from the mapping.
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotView<'a> {
+    market_id: &'a str,
+    record: &'a [u8],
+    levels: &'a [u8],
+}
+
+impl<'a> SnapshotView<'a> {
+    pub fn market_id(&self) -> &'a str {
+        self.market_id
+    }
+
+    pub fn offset_ms(&self) -> OffsetMs {
+        OffsetMs::new(i64_at(self.record, 0))
+    }
+
+    pub fn timestamp_ms(&self) -> i64 {
+        i64_at(self.record, 8)
+    }
+
+    fn flags(&self) -> u64 {
+        u64_at(self.record, 32)
+    }
+
+    pub fn reference_price(&self) -> Option<f64> {
+        (self.flags() & HAS_REFERENCE != 0).then(|| f64_at(self.record, 16))
+    }
+
+    pub fn oracle_price(&self) -> Option<f64> {
+        (self.flags() & HAS_ORACLE != 0).then(|| f64_at(self.record, 24))
+    }
+
+    pub fn side(&self, side: Side) -> SideView<'a> {
+        let (start, shift) = match side {
+            Side::Yes => (40, YES_FLAGS),
+            Side::No => (40 + SIDE_LEN, NO_FLAGS),
+        };
+        SideView {
+            bytes: &self.record[start..start + SIDE_LEN],
+            flags: (self.flags() >> shift) & 0b1111,
+            levels: self.levels,
+        }
+    }
+
+    /// Copy the view into an owned snapshot. Fails if its depth levels lie
+    /// outside the market's level block (a corrupt file).
+    pub fn to_snapshot(&self) -> Result<BookSnapshot> {
+        Ok(BookSnapshot {
+            market_id: self.market_id.into(),
+            offset_ms: self.offset_ms(),
+            timestamp_ms: self.timestamp_ms(),
+            yes: Arc::new(self.side(Side::Yes).to_state()?),
+            no: Arc::new(self.side(Side::No).to_state()?),
+            reference_price: self.reference_price(),
+            oracle_price: self.oracle_price(),
+        })
+    }
+}
+
+/// One side of a [`SnapshotView`].
+#[derive(Debug, Clone, Copy)]
+pub struct SideView<'a> {
+    bytes: &'a [u8],
+    flags: u64,
+    levels: &'a [u8],
+}
+
+impl<'a> SideView<'a> {
+    fn optional(&self, bit: u64, at: usize) -> Option<f64> {
+        (self.flags & (1 << bit) != 0).then(|| f64_at(self.bytes, at))
+    }
+
+    pub fn best_bid(&self) -> Option<f64> {
+        self.optional(0, 0)
+    }
+
+    pub fn best_bid_size(&self) -> Option<f64> {
+        self.optional(1, 8)
+    }
+
+    pub fn best_ask(&self) -> Option<f64> {
+        self.optional(2, 16)
+    }
+
+    pub fn best_ask_size(&self) -> Option<f64> {
+        self.optional(3, 24)
+    }
+
+    pub fn total_bid_depth(&self) -> f64 {
+        f64_at(self.bytes, 32)
+    }
+
+    pub fn total_ask_depth(&self) -> f64 {
+        f64_at(self.bytes, 40)
+    }
+
+    /// Raw bytes of the side's depth levels, if they lie inside the level
+    /// block.
+    fn depth_bytes(&self) -> Option<&'a [u8]> {
+        let start = u32_at(self.bytes, 48) as usize * LEVEL_LEN;
+        let len = u32_at(self.bytes, 52) as usize * LEVEL_LEN;
+        self.levels.get(start..start + len)
+    }
+
+    /// Depth levels, by price; empty if the record is corrupt.
+    pub fn depth(&self) -> impl ExactSizeIterator<Item = PriceLevel> + 'a {
+        self.depth_bytes().unwrap_or_default().chunks_exact(LEVEL_LEN).map(|l| PriceLevel {
+            price: f64_at(l, 0),
+            cumulative_size: f64_at(l, 8),
+        })
+    }
+
+    pub fn to_state(&self) -> Result<SideState> {
+        if self.depth_bytes().is_none() {
+            bail!("depth levels out of range");
+        }
+        Ok(SideState {
+            best_bid: self.best_bid(),
+            best_bid_size: self.best_bid_size(),
+            best_ask: self.best_ask(),
+            best_ask_size: self.best_ask_size(),
+            depth: self.depth().collect(),
+            total_bid_depth: self.total_bid_depth(),
+            total_ask_depth: self.total_ask_depth(),
+        })
+    }
+}
+
+/// Writes a [`MappedSnapshots`] file one market at a time. The file
+/// appears at its path only once [`finish`](Self::finish) succeeds.
+pub struct MappedSnapshotsWriter {
+    out: BufWriter<File>,
+    path: PathBuf,
+    partial: PathBuf,
+    pos: usize,
+    index: Vec<(String, Entry)>,
+}
+
+impl MappedSnapshotsWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        let file = File::create(&partial).with_context(|| format!("failed to create {}", partial.display()))?;
+        let mut out = BufWriter::new(file);
+        out.write_all(&[0; HEADER_LEN])?;
+        Ok(Self {
+            out,
+            path: path.to_path_buf(),
+            partial,
+            pos: HEADER_LEN,
+            index: Vec::new(),
+        })
+    }
+
+    /// Append one market's snapshots. A side shared with the previous
+    /// snapshot reuses its depth levels instead of writing them again.
+    pub fn add(&mut self, market_id: &str, snapshots: &[BookSnapshot]) -> Result<()> {
+        let records = self.pos;
+        let mut levels: Vec<&PriceLevel> = Vec::new();
+        let mut prev: [Option<(&Arc<SideState>, u32)>; 2] = [None, None];
+        for snap in snapshots {
+            let mut flags = 0;
+            if snap.reference_price.is_some() {
+                flags |= HAS_REFERENCE;
+            }
+            if snap.oracle_price.is_some() {
+                flags |= HAS_ORACLE;
+            }
+            for (state, shift) in [(&snap.yes, YES_FLAGS), (&snap.no, NO_FLAGS)] {
+                let present = [state.best_bid, state.best_bid_size, state.best_ask, state.best_ask_size];
+                for (bit, value) in present.iter().enumerate() {
+                    if value.is_some() {
+                        flags |= 1 << (shift + bit as u32);
+                    }
+                }
+            }
+
+            let mut record = Vec::with_capacity(RECORD_LEN);
+            record.extend(snap.offset_ms.ms().to_le_bytes());
+            record.extend(snap.timestamp_ms.to_le_bytes());
+            record.extend(snap.reference_price.unwrap_or(0.0).to_le_bytes());
+            record.extend(snap.oracle_price.unwrap_or(0.0).to_le_bytes());
+            record.extend(flags.to_le_bytes());
+            for (i, state) in [&snap.yes, &snap.no].into_iter().enumerate() {
+                for value in [state.best_bid, state.best_bid_size, state.best_ask, state.best_ask_size] {
+                    record.extend(value.unwrap_or(0.0).to_le_bytes());
+                }
+                record.extend(state.total_bid_depth.to_le_bytes());
+                record.extend(state.total_ask_depth.to_le_bytes());
+                let start = match prev[i] {
+                    Some((shared, start)) if Arc::ptr_eq(shared, state) => start,
+                    _ => {
+                        let start = u32::try_from(levels.len()).context("too many depth levels")?;
+                        levels.extend(&state.depth);
+                        start
+                    }
+                };
+                prev[i] = Some((state, start));
+                record.extend(start.to_le_bytes());
+                record.extend((state.depth.len() as u32).to_le_bytes());
+            }
+            debug_assert_eq!(record.len(), RECORD_LEN);
+            self.out.write_all(&record)?;
+        }
+        self.pos += snapshots.len() * RECORD_LEN;
+
+        let level_start = self.pos;
+        for level in &levels {
+            self.out.write_all(&level.price.to_le_bytes())?;
+            self.out.write_all(&level.cumulative_size.to_le_bytes())?;
+        }
+        self.pos += levels.len() * LEVEL_LEN;
+
+        self.index.push((
+            market_id.to_string(),
+            Entry {
+                records,
+                count: snapshots.len(),
+                levels: level_start,
+                level_count: levels.len(),
+            },
+        ));
+        Ok(())
+    }
+
+    /// Write the index and header, then move the file into place.
+    pub fn finish(mut self) -> Result<()> {
+        let index_at = self.pos as u64;
+        for (id, entry) in &self.index {
+            self.out.write_all(&(id.len() as u32).to_le_bytes())?;
+            self.out.write_all(id.as_bytes())?;
+            for value in [entry.records, entry.count, entry.levels, entry.level_count] {
+                self.out.write_all(&(value as u64).to_le_bytes())?;
+            }
+        }
+        self.out.seek(SeekFrom::Start(0))?;
+        self.out.write_all(MAGIC)?;
+        self.out.write_all(&MAPPED_FORMAT_VERSION.to_le_bytes())?;
+        self.out.write_all(&(self.index.len() as u32).to_le_bytes())?;
+        self.out.write_all(&index_at.to_le_bytes())?;
+        self.out
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()
+            .with_context(|| format!("failed to write {}", self.partial.display()))?;
+        std::fs::rename(&self.partial, &self.path)
+            .with_context(|| format!("failed to move cache into place at {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::{generate_dataset, BenchConfig};
+    use crate::data::MemoryStore;
+
+    #[test]
+    fn test_roundtrip_matches_store_snapshots() {
+        let store = MemoryStore::new();
+        let mut markets = Vec::new();
+        let config = BenchConfig {
+            markets: 3,
+            snapshots_per_market: 25,
+            seed: 4,
+        };
+        for (market, ticks) in generate_dataset(&config) {
+            store.insert_market(&market).unwrap();
+            store.insert_ticks(&ticks).unwrap();
+            markets.push(market);
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshots.pfs");
+        let cache = MappedSnapshots::open_or_build(&path, &store, &markets).unwrap();
+        assert_eq!(cache.len(), 3);
+        assert!(!dir.path().join("snapshots.pfs.partial").exists());
+
+        let json = |s: &[BookSnapshot]| serde_json::to_string(s).unwrap();
+        for market in &markets {
+            let expected = ticks_to_snapshots(&market.id, &store.load_ticks(&market.id).unwrap());
+            let loaded = cache.load(&market.id).unwrap().unwrap();
+            assert_eq!(json(&loaded), json(&expected));
+
+            let view = cache.views(&market.id).unwrap().last().unwrap();
+            let last = expected.last().unwrap();
+            assert_eq!(view.offset_ms(), last.offset_ms);
+            assert_eq!(view.side(Side::Yes).best_bid(), last.yes.best_bid);
+            assert_eq!(view.side(Side::No).depth().len(), last.no.depth.len());
+        }
+        assert!(cache.load("missing").is_none());
+
+        // An existing file is reused, not rebuilt.
+        let reopened = MappedSnapshots::open_or_build(&path, &MemoryStore::new(), &[]).unwrap();
+        assert_eq!(reopened.len(), 3);
+
+        std::fs::write(&path, b"not a cache at all, just text").unwrap();
+        assert!(MappedSnapshots::open(&path).is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! In-memory [`DataStore`] for builds without SQLite (e.g. WebAssembly) and
+//! for callers that already hold their data.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use anyhow::{bail, Result};
+
+use crate::types::{BookTick, Market, Side, Trade, WindowResult};
+
+use super::store::{DataStore, MarketFilter, ResultFilter, RunMeta, StoredRun};
+
+/// `(market, side, timestamp, price bits, size bits)`, the key SQLite's
+/// unique index on trades enforces.
+type TradeKey = (String, Side, i64, u64, u64);
+
+/// Markets, ticks and persisted runs kept in memory, with the same ordering
+/// guarantees as [`SqliteStore`](super::SqliteStore): markets by `open_ts`,
+/// ticks by offset then side (NO before YES), depth levels by price, trades
+/// by offset then insertion, runs and their results in insertion order.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    markets: Mutex<Vec<Market>>,
+    ticks: Mutex<HashMap<String, Vec<BookTick>>>,
+    /// `(market, side, timestamp)` of every stored tick, the key SQLite's
+    /// unique index enforces.
+    tick_keys: Mutex<HashSet<(String, Side, i64)>>,
+    trades: Mutex<HashMap<String, Vec<Trade>>>,
+    /// The key of every stored trade.
+    trade_keys: Mutex<HashSet<TradeKey>>,
+    runs: Mutex<Vec<(RunMeta, Vec<WindowResult>)>>,
+    watermarks: Mutex<HashMap<(String, String), i64>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DataStore for MemoryStore {
+    fn init(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn insert_market(&self, market: &Market) -> Result<()> {
+        let mut markets = self.markets.lock().unwrap();
+        match markets.iter_mut().find(|m| m.id == market.id) {
+            Some(existing) => *existing = market.clone(),
+            None => markets.push(market.clone()),
+        }
+        Ok(())
+    }
+
+    fn insert_ticks(&self, ticks: &[BookTick]) -> Result<usize> {
+        let mut stored = self.ticks.lock().unwrap();
+        let mut keys = self.tick_keys.lock().unwrap();
+        let mut inserted = 0;
+        for tick in ticks {
+            if !keys.insert((tick.market_id.to_string(), tick.side, tick.timestamp_ms)) {
+                continue;
+            }
+            let mut tick = tick.clone();
+            tick.depth.sort_by(|a, b| a.price.total_cmp(&b.price));
+            stored.entry(tick.market_id.to_string()).or_default().push(tick);
+            inserted += 1;
+        }
+        Ok(inserted)
+    }
+
+    fn list_markets(&self, filter: &MarketFilter) -> Result<Vec<Market>> {
+        let mut markets: Vec<Market> = self
+            .markets
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|m| filter.matches(m))
+            .cloned()
+            .collect();
+        markets.sort_by_key(|m| m.open_ts);
+        Ok(markets)
+    }
+
+    fn load_ticks(&self, market_id: &str) -> Result<Vec<BookTick>> {
+        let mut ticks = self
+            .ticks
+            .lock()
+            .unwrap()
+            .get(market_id)
+            .cloned()
+            .unwrap_or_default();
+        ticks.sort_by_key(|t| (t.offset_ms, t.side == Side::Yes));
+        Ok(ticks)
+    }
+
+    fn insert_trades(&self, trades: &[Trade]) -> Result<()> {
+        let mut stored = self.trades.lock().unwrap();
+        let mut keys = self.trade_keys.lock().unwrap();
+        for trade in trades {
+            let key = (
+                trade.market_id.to_string(),
+                trade.side,
+                trade.timestamp_ms,
+                trade.price.to_bits(),
+                trade.size.to_bits(),
+            );
+            if !keys.insert(key) {
+                continue;
+            }
+            stored.entry(trade.market_id.to_string()).or_default().push(trade.clone());
+        }
+        Ok(())
+    }
+
+    fn load_trades(&self, market_id: &str) -> Result<Vec<Trade>> {
+        let mut trades = self
+            .trades
+            .lock()
+            .unwrap()
+            .get(market_id)
+            .cloned()
+            .unwrap_or_default();
+        trades.sort_by_key(|t| t.offset_ms);
+        Ok(trades)
+    }
+
+    fn import_watermark(&self, source: &str, market_id: &str) -> Result<Option<i64>> {
+        let key = (source.to_string(), market_id.to_string());
+        Ok(self.watermarks.lock().unwrap().get(&key).copied())
+    }
+
+    fn set_import_watermark(&self, source: &str, market_id: &str, last_tick_ms: i64) -> Result<()> {
+        let key = (source.to_string(), market_id.to_string());
+        self.watermarks.lock().unwrap().insert(key, last_tick_ms);
+        Ok(())
+    }
+
+    fn insert_results(&self, meta: &RunMeta, results: &[WindowResult]) -> Result<i64> {
+        let mut runs = self.runs.lock().unwrap();
+        runs.push((meta.clone(), results.to_vec()));
+        Ok(runs.len() as i64)
+    }
+
+    fn list_runs(&self) -> Result<Vec<StoredRun>> {
+        let runs = self.runs.lock().unwrap();
+        Ok(runs
+            .iter()
+            .enumerate()
+            .map(|(i, (meta, results))| StoredRun {
+                id: i as i64 + 1,
+                meta: meta.clone(),
+                windows: results.len(),
+            })
+            .collect())
+    }
+
+    fn load_results(&self, run_id: i64, filter: &ResultFilter) -> Result<Vec<WindowResult>> {
+        let runs = self.runs.lock().unwrap();
+        let Some((_, results)) = usize::try_from(run_id - 1).ok().and_then(|i| runs.get(i)) else {
+            bail!("no stored run with id {}", run_id);
+        };
+        Ok(results.iter().filter(|r| filter.matches(r)).cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{OffsetMs, Outcome, Platform, PriceLevel, UnixSecs};
+    use smallvec::smallvec;
+
+    fn market(id: &str, open_ts: i64, category: &str) -> Market {
+        Market {
+            id: id.into(),
+            platform: Platform::Polymarket,
+            description: String::new(),
+            category: category.into(),
+            open_ts: UnixSecs::new(open_ts),
+            close_ts: UnixSecs::new(open_ts + 300),
+            duration_secs: 300,
+            outcome: Some(Outcome::Yes),
+            outcome_ts: None,
+        }
+    }
+
+    fn tick(market_id: &str, side: Side, offset_ms: i64) -> BookTick {
+        BookTick {
+            market_id: market_id.into(),
+            side,
+            timestamp_ms: offset_ms,
+            offset_ms: OffsetMs::new(offset_ms),
+            best_bid: Some(0.49),
+            best_bid_size: Some(10.0),
+            best_ask: Some(0.51),
+            best_ask_size: Some(10.0),
+            depth: smallvec![
+                PriceLevel { price: 0.49, cumulative_size: 30.0 },
+                PriceLevel { price: 0.48, cumulative_size: 60.0 },
+            ],
+            total_bid_depth: 60.0,
+            total_ask_depth: 10.0,
+            reference_price: None,
+            oracle_price: None,
+        }
+    }
+
+    #[test]
+    fn test_memory_store_matches_sqlite_ordering() {
+        let store = MemoryStore::new();
+        store.insert_market(&market("b", 2000, "eth")).unwrap();
+        store.insert_market(&market("a", 1000, "btc")).unwrap();
+        let mut replaced = market("a", 1000, "btc");
+        replaced.outcome = Some(Outcome::No);
+        store.insert_market(&replaced).unwrap();
+
+        let all = store.list_markets(&MarketFilter::default()).unwrap();
+        assert_eq!(all.iter().map(|m| m.id.as_str()).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(all[0].outcome, Some(Outcome::No));
+        let btc = MarketFilter {
+            category: Some("btc".into()),
+            ..MarketFilter::default()
+        };
+        assert_eq!(store.list_markets(&btc).unwrap().len(), 1);
+
+        store
+            .insert_ticks(&[tick("a", Side::Yes, 1000), tick("a", Side::Yes, 0), tick("a", Side::No, 0)])
+            .unwrap();
+        let ticks = store.load_ticks("a").unwrap();
+        let order: Vec<_> = ticks.iter().map(|t| (t.offset_ms.ms(), t.side)).collect();
+        assert_eq!(order, [(0, Side::No), (0, Side::Yes), (1000, Side::Yes)]);
+        assert_eq!(ticks[0].depth[0].price, 0.48);
+        assert!(store.load_ticks("missing").unwrap().is_empty());
+    }
+}
@@ -1,8 +1,35 @@
+pub mod cache;
+pub mod csv_import;
 pub mod huggingface;
+#[cfg(feature = "net")]
+pub mod hf_hub;
+#[cfg(feature = "mmap")]
+pub mod mapped;
+pub mod memory;
+#[cfg(feature = "net")]
+pub mod outcomes;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "sqlite")]
 pub mod polymarket;
 pub mod schema;
+pub mod snapshots;
+pub mod stats;
 pub mod store;
+pub mod validate;
 
+pub use cache::SnapshotCache;
+pub use csv_import::{import_csv, import_csv_files, CsvImportStats, CsvMapping};
 pub use huggingface::{import_hf_directory, HfImportStats};
-pub use polymarket::{import_from_capture_db, ticks_to_snapshots, ImportStats, PolymarketStore};
-pub use store::{DataStore, MarketFilter, SqliteStore};
+#[cfg(feature = "mmap")]
+pub use mapped::MappedSnapshots;
+pub use memory::MemoryStore;
+#[cfg(feature = "parquet")]
+pub use parquet::{export_market_parquet, import_parquet_directory, ParquetImportStats};
+#[cfg(feature = "sqlite")]
+pub use polymarket::{import_from_capture_db, ImportStats, PolymarketStore};
+pub use snapshots::ticks_to_snapshots;
+pub use stats::DbStats;
+pub use store::{select_markets, DataStore, MarketFilter, ResultFilter, RunMeta, StoredRun, TickLoader};
+#[cfg(feature = "sqlite")]
+pub use store::SqliteStore;
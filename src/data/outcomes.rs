@@ -0,0 +1,375 @@
+//! Outcome verification: cross-check stored market outcomes against an
+//! independent resolution source (Binance klines) and optionally fix them,
+//! or backfill the outcomes of markets that were imported without one.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+
+use crate::types::{BookTick, Market, Outcome, UnixSecs};
+
+use super::huggingface::{determine_outcome, fetch_binance_klines_interval};
+use super::store::DataStore;
+
+/// A market whose stored outcome disagrees with the resolution source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutcomeMismatch {
+    pub market_id: String,
+    /// Outcome currently stored in the database (`None` if unresolved).
+    pub stored: Option<Outcome>,
+    /// Outcome according to the resolution source.
+    pub expected: Outcome,
+}
+
+/// Summary of a verification pass.
+#[derive(Debug, Default)]
+pub struct VerifyStats {
+    pub checked: usize,
+    pub agreed: usize,
+    /// Markets the resolution source had no data for.
+    pub unresolvable: usize,
+    /// Markets stored as void. The venue cancelled them, so price data
+    /// cannot overrule that; they are left alone.
+    pub void: usize,
+    pub mismatches: Vec<OutcomeMismatch>,
+}
+
+/// Compare every market's stored outcome with `resolve(market)`.
+///
+/// Markets for which `resolve` returns `None` are counted as unresolvable and
+/// never reported as mismatches.
+pub fn verify_outcomes(
+    markets: &[Market],
+    resolve: &dyn Fn(&Market) -> Option<Outcome>,
+) -> VerifyStats {
+    let mut stats = VerifyStats::default();
+
+    for market in markets {
+        stats.checked += 1;
+        if market.outcome == Some(Outcome::Void) {
+            stats.void += 1;
+            continue;
+        }
+        match resolve(market) {
+            None => stats.unresolvable += 1,
+            Some(expected) if market.outcome == Some(expected) => stats.agreed += 1,
+            Some(expected) => stats.mismatches.push(OutcomeMismatch {
+                market_id: market.id.clone(),
+                stored: market.outcome,
+                expected,
+            }),
+        }
+    }
+
+    stats
+}
+
+/// Whether `market` still needs an outcome: none stored, or `UNRESOLVED`.
+pub fn is_unresolved(market: &Market) -> bool {
+    matches!(market.outcome, None | Some(Outcome::Unresolved))
+}
+
+/// Summary of a backfill pass; see [`backfill_outcomes`].
+#[derive(Debug, Default)]
+pub struct BackfillStats {
+    /// Markets that needed an outcome.
+    pub unresolved: usize,
+    /// Outcomes found in Binance klines.
+    pub from_klines: Vec<OutcomeMismatch>,
+    /// Outcomes found in the oracle prices recorded with the market's ticks.
+    pub from_oracle: Vec<OutcomeMismatch>,
+}
+
+impl BackfillStats {
+    pub fn resolved(&self) -> usize {
+        self.from_klines.len() + self.from_oracle.len()
+    }
+
+    /// Markets neither source could resolve.
+    pub fn unresolvable(&self) -> usize {
+        self.unresolved - self.resolved()
+    }
+}
+
+/// Find outcomes for the [unresolved](is_unresolved) `markets`, from
+/// `klines` where it has one and `oracle` otherwise. Settled and void
+/// markets are left alone.
+pub fn backfill_outcomes(
+    markets: &[Market],
+    klines: &dyn Fn(&Market) -> Option<Outcome>,
+    oracle: &dyn Fn(&Market) -> Option<Outcome>,
+) -> BackfillStats {
+    let mut stats = BackfillStats::default();
+    for market in markets.iter().filter(|m| is_unresolved(m)) {
+        stats.unresolved += 1;
+        let resolved = |expected| OutcomeMismatch {
+            market_id: market.id.clone(),
+            stored: market.outcome,
+            expected,
+        };
+        if let Some(expected) = klines(market) {
+            stats.from_klines.push(resolved(expected));
+        } else if let Some(expected) = oracle(market) {
+            stats.from_oracle.push(resolved(expected));
+        }
+    }
+    stats
+}
+
+/// How far from the window's open and close the first and last oracle
+/// prices may be for [`resolve_from_oracle`] to trust them.
+pub const ORACLE_SLACK_MS: i64 = 10_000;
+
+/// Resolve a market from the oracle prices (e.g. Chainlink) recorded with
+/// its ticks: up if the last price at the close is above the first at the
+/// open. `None` unless the recorded prices reach both ends of the window,
+/// within [`ORACLE_SLACK_MS`].
+pub fn resolve_from_oracle(market: &Market, ticks: &[BookTick]) -> Option<Outcome> {
+    let (open_ms, close_ms) = (market.open_ts.to_millis(), market.close_ts.to_millis());
+    let mut prices = ticks
+        .iter()
+        .filter(|t| (open_ms..=close_ms).contains(&t.timestamp_ms))
+        .filter_map(|t| Some((t.timestamp_ms, t.oracle_price?)));
+    let (first_ms, first) = prices.next()?;
+    let (last_ms, last) = prices.next_back()?;
+    if first_ms - open_ms > ORACLE_SLACK_MS || close_ms - last_ms > ORACLE_SLACK_MS {
+        return None;
+    }
+    Some(if last > first { Outcome::Yes } else { Outcome::No })
+}
+
+/// Binance symbol for a market category, e.g. `"btc"` → `"BTCUSDT"`.
+pub fn binance_symbol(category: &str) -> String {
+    format!("{}USDT", category.to_uppercase())
+}
+
+/// Binance kline interval matching a window duration, if one exists.
+pub fn binance_interval(duration_secs: i64) -> Option<&'static str> {
+    match duration_secs {
+        300 => Some("5m"),
+        900 => Some("15m"),
+        3600 => Some("1h"),
+        _ => None,
+    }
+}
+
+/// Klines keyed by (symbol, interval), each a map of open time (ms) →
+/// (open, close).
+pub type KlineCache = HashMap<(String, &'static str), HashMap<i64, (f64, f64)>>;
+
+/// Fetch the Binance klines needed to resolve `markets`, one request batch
+/// per (symbol, interval) pair. Pairs that fail to download are logged and
+/// left out, so their markets come back as unresolvable.
+pub fn fetch_klines_for(markets: &[Market]) -> KlineCache {
+    let mut ranges: HashMap<(String, &'static str), (UnixSecs, UnixSecs)> = HashMap::new();
+    for m in markets {
+        let Some(interval) = binance_interval(m.duration_secs) else {
+            continue;
+        };
+        let key = (binance_symbol(&m.category), interval);
+        let range = ranges.entry(key).or_insert((m.open_ts, m.close_ts));
+        range.0 = range.0.min(m.open_ts);
+        range.1 = range.1.max(m.close_ts);
+    }
+
+    let mut cache = KlineCache::new();
+    for ((symbol, interval), (start, end)) in ranges {
+        match fetch_binance_klines_interval(&symbol, interval, start.to_millis(), end.to_millis()) {
+            Ok(klines) => {
+                cache.insert((symbol, interval), klines);
+            }
+            Err(e) => warn!("failed to fetch {} {} klines: {}", symbol, interval, e),
+        }
+    }
+    cache
+}
+
+/// Resolve a market from a [`KlineCache`]: up if the window's kline closed
+/// above its open.
+pub fn resolve_from_klines(cache: &KlineCache, market: &Market) -> Option<Outcome> {
+    let interval = binance_interval(market.duration_secs)?;
+    let klines = cache.get(&(binance_symbol(&market.category), interval))?;
+    determine_outcome(klines, market.open_ts)
+}
+
+/// Overwrite the stored outcome of each mismatched market and append one
+/// line per change to the audit log at `audit_path`.
+///
+/// Audit lines are tab-separated: UTC time, market id, old outcome, new
+/// outcome, source.
+pub fn apply_fixes(
+    store: &dyn DataStore,
+    markets: &[Market],
+    mismatches: &[OutcomeMismatch],
+    audit_path: &Path,
+    source: &str,
+) -> Result<usize> {
+    let by_id: HashMap<&str, &Market> = markets.iter().map(|m| (m.id.as_str(), m)).collect();
+
+    let mut audit = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(audit_path)
+        .with_context(|| format!("failed to open audit log {}", audit_path.display()))?;
+
+    let mut fixed = 0;
+    for mm in mismatches {
+        let Some(market) = by_id.get(mm.market_id.as_str()) else {
+            continue;
+        };
+        let mut updated = (*market).clone();
+        updated.outcome = Some(mm.expected);
+        store.insert_market(&updated)?;
+
+        writeln!(
+            audit,
+            "{}\t{}\t{}\t{}\t{}",
+            chrono::Utc::now().to_rfc3339(),
+            mm.market_id,
+            mm.stored.as_ref().map_or("NONE", |o| o.label()),
+            mm.expected.label(),
+            source
+        )
+        .context("failed to write audit log")?;
+        fixed += 1;
+    }
+
+    info!(fixed, audit = %audit_path.display(), "outcome fixes applied");
+    Ok(fixed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::store::{MarketFilter, SqliteStore};
+    use crate::types::Platform;
+
+    fn market(id: &str, outcome: Option<Outcome>) -> Market {
+        Market {
+            id: id.to_string(),
+            platform: Platform::Polymarket,
+            description: String::new(),
+            category: "btc".to_string(),
+            open_ts: UnixSecs::new(900),
+            close_ts: UnixSecs::new(1800),
+            duration_secs: 900,
+            outcome,
+            outcome_ts: None,
+        }
+    }
+
+    #[test]
+    fn test_verify_counts_and_mismatches() {
+        let markets = vec![
+            market("agree", Some(Outcome::Yes)),
+            market("wrong", Some(Outcome::No)),
+            market("unset", None),
+            market("unknown", Some(Outcome::Yes)),
+            market("voided", Some(Outcome::Void)),
+            market("pending", Some(Outcome::Unresolved)),
+        ];
+        let stats = verify_outcomes(&markets, &|m| match m.id.as_str() {
+            "unknown" => None,
+            _ => Some(Outcome::Yes),
+        });
+
+        assert_eq!(stats.checked, 6);
+        assert_eq!(stats.agreed, 1);
+        assert_eq!(stats.unresolvable, 1);
+        assert_eq!(stats.void, 1);
+        assert_eq!(stats.mismatches.len(), 3);
+        assert_eq!(stats.mismatches[0].market_id, "wrong");
+        assert_eq!(stats.mismatches[1].stored, None);
+        assert_eq!(stats.mismatches[2].stored, Some(Outcome::Unresolved));
+    }
+
+    #[test]
+    fn test_resolve_from_klines() {
+        let mut cache = KlineCache::new();
+        let mut klines = HashMap::new();
+        klines.insert(900_000i64, (100.0, 101.0));
+        cache.insert(("BTCUSDT".to_string(), "15m"), klines);
+
+        assert_eq!(
+            resolve_from_klines(&cache, &market("m", None)),
+            Some(Outcome::Yes)
+        );
+        let mut eth = market("e", None);
+        eth.category = "eth".to_string();
+        assert_eq!(resolve_from_klines(&cache, &eth), None);
+    }
+
+    #[test]
+    fn test_backfill_prefers_klines_then_oracle() {
+        let markets = vec![
+            market("settled", Some(Outcome::Yes)),
+            market("voided", Some(Outcome::Void)),
+            market("klines", None),
+            market("oracle", Some(Outcome::Unresolved)),
+            market("neither", None),
+        ];
+        let stats = backfill_outcomes(
+            &markets,
+            &|m| (m.id == "klines").then_some(Outcome::No),
+            &|m| (m.id != "neither").then_some(Outcome::Yes),
+        );
+        assert_eq!((stats.unresolved, stats.resolved(), stats.unresolvable()), (3, 2, 1));
+        assert_eq!(stats.from_klines[0].market_id, "klines");
+        assert_eq!(stats.from_klines[0].expected, Outcome::No);
+        assert_eq!(stats.from_oracle[0].market_id, "oracle");
+        assert_eq!(stats.from_oracle[0].stored, Some(Outcome::Unresolved));
+    }
+
+    #[test]
+    fn test_resolve_from_oracle_needs_both_ends() {
+        let tick = |secs: i64, oracle_price| BookTick {
+            market_id: "m".into(),
+            side: crate::types::Side::Yes,
+            timestamp_ms: secs * 1000,
+            offset_ms: crate::types::OffsetMs::new((secs - 900) * 1000),
+            best_bid: None,
+            best_bid_size: None,
+            best_ask: None,
+            best_ask_size: None,
+            depth: Default::default(),
+            total_bid_depth: 0.0,
+            total_ask_depth: 0.0,
+            reference_price: None,
+            oracle_price,
+        };
+        let m = market("m", None);
+        let ticks = [tick(899, Some(50.0)), tick(902, Some(100.0)), tick(1500, None), tick(1795, Some(99.0))];
+        assert_eq!(resolve_from_oracle(&m, &ticks), Some(Outcome::No));
+        // The recording stops a minute before the close.
+        assert_eq!(resolve_from_oracle(&m, &ticks[..3]), None);
+        assert_eq!(resolve_from_oracle(&m, &[]), None);
+    }
+
+    #[test]
+    fn test_apply_fixes_updates_store_and_audits() {
+        let store = SqliteStore::in_memory().unwrap();
+        store.init().unwrap();
+        let markets = vec![market("m1", Some(Outcome::No))];
+        store.insert_market(&markets[0]).unwrap();
+
+        let tmp = tempfile::TempDir::new().unwrap();
+        let audit = tmp.path().join("audit.log");
+        let mismatches = vec![OutcomeMismatch {
+            market_id: "m1".to_string(),
+            stored: Some(Outcome::No),
+            expected: Outcome::Yes,
+        }];
+
+        let fixed = apply_fixes(&store, &markets, &mismatches, &audit, "binance").unwrap();
+        assert_eq!(fixed, 1);
+
+        let loaded = store.list_markets(&MarketFilter::default()).unwrap();
+        assert_eq!(loaded[0].outcome, Some(Outcome::Yes));
+
+        let log = std::fs::read_to_string(&audit).unwrap();
+        assert!(log.contains("m1\tNO\tYES\tbinance"), "audit log: {}", log);
+    }
+}
@@ -0,0 +1,356 @@
+//! Columnar per-market Parquet tick files (`parquet` feature).
+//!
+//! SQLite gets slow once a database holds millions of ticks. This module
+//! writes each market's ticks to its own Snappy-compressed Parquet file,
+//! one row per [`BookTick`], with the depth ladder as two list columns and
+//! the [`Market`] itself as JSON in the file's key-value metadata, so a
+//! directory of files can be loaded back into any [`DataStore`] without a
+//! separate market table:
+//!
+//! ```python
+//! import pyarrow.parquet as pq
+//! ticks = pq.read_table("parquet/btc-updown-15m-1700000000.parquet").to_pandas()
+//! ```
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use arrow_array::builder::{Float64Builder, ListBuilder};
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, Int64Type};
+use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use parquet::format::KeyValue;
+use tracing::{info, warn};
+
+use crate::types::{BookTick, DepthLevels, Market, MarketId, OffsetMs, PriceLevel, Side};
+
+use super::store::DataStore;
+
+/// Key-value metadata entry holding the market as JSON.
+const MARKET_KEY: &str = "phantomfill.market";
+
+/// Ticks per record batch, both written and read.
+const BATCH_TICKS: usize = 65_536;
+
+/// Statistics from a Parquet directory import.
+#[derive(Debug, Default)]
+pub struct ParquetImportStats {
+    pub files_processed: usize,
+    pub files_skipped: usize,
+    pub markets_imported: usize,
+    pub ticks_imported: usize,
+}
+
+fn schema() -> Schema {
+    let list = || DataType::List(Arc::new(Field::new("item", DataType::Float64, false)));
+    Schema::new(vec![
+        Field::new("side", DataType::Utf8, false),
+        Field::new("timestamp_ms", DataType::Int64, false),
+        Field::new("offset_ms", DataType::Int64, false),
+        Field::new("best_bid", DataType::Float64, true),
+        Field::new("best_bid_size", DataType::Float64, true),
+        Field::new("best_ask", DataType::Float64, true),
+        Field::new("best_ask_size", DataType::Float64, true),
+        Field::new("depth_price", list(), false),
+        Field::new("depth_size", list(), false),
+        Field::new("total_bid_depth", DataType::Float64, false),
+        Field::new("total_ask_depth", DataType::Float64, false),
+        Field::new("reference_price", DataType::Float64, true),
+        Field::new("oracle_price", DataType::Float64, true),
+    ])
+}
+
+fn float64s(values: impl Iterator<Item = Option<f64>>) -> ArrayRef {
+    Arc::new(values.collect::<Float64Array>())
+}
+
+fn depth_list(ticks: &[BookTick], value: impl Fn(&PriceLevel) -> f64) -> ArrayRef {
+    let item = Field::new("item", DataType::Float64, false);
+    let mut builder = ListBuilder::new(Float64Builder::new()).with_field(Arc::new(item));
+    for tick in ticks {
+        builder.append_value(tick.depth.iter().map(|l| Some(value(l))));
+    }
+    Arc::new(builder.finish())
+}
+
+fn ticks_batch(schema: &Arc<Schema>, ticks: &[BookTick]) -> Result<RecordBatch> {
+    let t = ticks;
+    let columns = vec![
+        Arc::new(t.iter().map(|t| Some(t.side.label())).collect::<StringArray>()) as ArrayRef,
+        Arc::new(t.iter().map(|t| Some(t.timestamp_ms)).collect::<Int64Array>()),
+        Arc::new(t.iter().map(|t| Some(t.offset_ms.ms())).collect::<Int64Array>()),
+        float64s(t.iter().map(|t| t.best_bid)),
+        float64s(t.iter().map(|t| t.best_bid_size)),
+        float64s(t.iter().map(|t| t.best_ask)),
+        float64s(t.iter().map(|t| t.best_ask_size)),
+        depth_list(t, |l| l.price),
+        depth_list(t, |l| l.cumulative_size),
+        float64s(t.iter().map(|t| Some(t.total_bid_depth))),
+        float64s(t.iter().map(|t| Some(t.total_ask_depth))),
+        float64s(t.iter().map(|t| t.reference_price)),
+        float64s(t.iter().map(|t| t.oracle_price)),
+    ];
+    RecordBatch::try_new(schema.clone(), columns).context("failed to build ticks batch")
+}
+
+/// Write `market` and its ticks from `store` to a Parquet file at `path`.
+/// Returns the number of ticks written.
+pub fn export_market_parquet(store: &dyn DataStore, market: &Market, path: &Path) -> Result<usize> {
+    let ticks = store.load_ticks(&market.id)?;
+    let schema = Arc::new(schema());
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_key_value_metadata(Some(vec![KeyValue::new(
+            MARKET_KEY.to_string(),
+            serde_json::to_string(market)?,
+        )]))
+        .build();
+    let file = File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(props))?;
+    for chunk in ticks.chunks(BATCH_TICKS) {
+        writer.write(&ticks_batch(&schema, chunk)?)?;
+    }
+    writer
+        .close()
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(ticks.len())
+}
+
+/// A file name for `market_id` that is safe on every platform.
+pub fn parquet_file_name(market_id: &str) -> String {
+    let stem: String = market_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect();
+    format!("{}.parquet", stem)
+}
+
+fn column<'a>(batch: &'a RecordBatch, name: &str) -> Result<&'a ArrayRef> {
+    batch
+        .column_by_name(name)
+        .ok_or_else(|| anyhow!("missing column {}", name))
+}
+
+fn opt_f64(batch: &RecordBatch, name: &str) -> Result<Vec<Option<f64>>> {
+    Ok(column(batch, name)?.as_primitive::<Float64Type>().iter().collect())
+}
+
+fn f64s(batch: &RecordBatch, name: &str) -> Result<Vec<f64>> {
+    Ok(opt_f64(batch, name)?.into_iter().map(|v| v.unwrap_or(0.0)).collect())
+}
+
+fn batch_ticks(batch: &RecordBatch, market_id: &MarketId) -> Result<Vec<BookTick>> {
+    let sides = column(batch, "side")?.as_string::<i32>();
+    let timestamps = column(batch, "timestamp_ms")?.as_primitive::<Int64Type>();
+    let offsets = column(batch, "offset_ms")?.as_primitive::<Int64Type>();
+    let best_bid = opt_f64(batch, "best_bid")?;
+    let best_bid_size = opt_f64(batch, "best_bid_size")?;
+    let best_ask = opt_f64(batch, "best_ask")?;
+    let best_ask_size = opt_f64(batch, "best_ask_size")?;
+    let depth_price = column(batch, "depth_price")?.as_list::<i32>();
+    let depth_size = column(batch, "depth_size")?.as_list::<i32>();
+    let total_bid_depth = f64s(batch, "total_bid_depth")?;
+    let total_ask_depth = f64s(batch, "total_ask_depth")?;
+    let reference_price = opt_f64(batch, "reference_price")?;
+    let oracle_price = opt_f64(batch, "oracle_price")?;
+
+    (0..batch.num_rows())
+        .map(|i| {
+            let side = match sides.value(i) {
+                "YES" => Side::Yes,
+                "NO" => Side::No,
+                other => bail!("unknown side {:?}", other),
+            };
+            let prices = depth_price.value(i);
+            let sizes = depth_size.value(i);
+            let (prices, sizes) = (prices.as_primitive::<Float64Type>(), sizes.as_primitive::<Float64Type>());
+            if prices.len() != sizes.len() {
+                bail!("depth_price and depth_size lengths differ in row {}", i);
+            }
+            let depth: DepthLevels = prices
+                .values()
+                .iter()
+                .zip(sizes.values().iter())
+                .map(|(&price, &cumulative_size)| PriceLevel { price, cumulative_size })
+                .collect();
+            Ok(BookTick {
+                market_id: market_id.clone(),
+                side,
+                timestamp_ms: timestamps.value(i),
+                offset_ms: OffsetMs::new(offsets.value(i)),
+                best_bid: best_bid[i],
+                best_bid_size: best_bid_size[i],
+                best_ask: best_ask[i],
+                best_ask_size: best_ask_size[i],
+                depth,
+                total_bid_depth: total_bid_depth[i],
+                total_ask_depth: total_ask_depth[i],
+                reference_price: reference_price[i],
+                oracle_price: oracle_price[i],
+            })
+        })
+        .collect()
+}
+
+/// Read a file written by [`export_market_parquet`], calling `f` with each
+/// batch of ticks. Returns the market.
+fn read_market_batches(path: &Path, f: &mut dyn FnMut(Vec<BookTick>) -> Result<()>) -> Result<Market> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let market = builder
+        .metadata()
+        .file_metadata()
+        .key_value_metadata()
+        .and_then(|kv| kv.iter().find(|kv| kv.key == MARKET_KEY))
+        .and_then(|kv| kv.value.as_deref())
+        .ok_or_else(|| anyhow!("{} has no {} metadata", path.display(), MARKET_KEY))?;
+    let market: Market =
+        serde_json::from_str(market).with_context(|| format!("bad market metadata in {}", path.display()))?;
+    let market_id: MarketId = market.id.as_str().into();
+    for batch in builder.with_batch_size(BATCH_TICKS).build()? {
+        f(batch_ticks(&batch?, &market_id)?)?;
+    }
+    Ok(market)
+}
+
+/// Read a file written by [`export_market_parquet`] into its market and ticks.
+pub fn read_market_parquet(path: &Path) -> Result<(Market, Vec<BookTick>)> {
+    let mut ticks = Vec::new();
+    let market = read_market_batches(path, &mut |batch| {
+        ticks.extend(batch);
+        Ok(())
+    })?;
+    Ok((market, ticks))
+}
+
+fn collect_parquet_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("failed to read dir {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "parquet") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Import every `.parquet` file in `dir` (not recursively) into `dest`. A
+/// file that fails to read is skipped whole; ticks already written from it
+/// stay.
+pub fn import_parquet_directory(dir: &Path, dest: &dyn DataStore) -> Result<ParquetImportStats> {
+    let mut stats = ParquetImportStats::default();
+    let files = collect_parquet_files(dir)?;
+    info!("found {} Parquet files in {}", files.len(), dir.display());
+
+    for path in &files {
+        let mut ticks = 0;
+        let read = read_market_batches(path, &mut |batch| {
+            ticks += batch.len();
+            dest.insert_ticks(&batch).map(|_| ())
+        });
+        match read {
+            Ok(market) => {
+                dest.insert_market(&market)?;
+                stats.files_processed += 1;
+                stats.markets_imported += 1;
+                stats.ticks_imported += ticks;
+            }
+            Err(e) => {
+                warn!("skipping {}: {:#}", path.display(), e);
+                stats.files_skipped += 1;
+            }
+        }
+    }
+    dest.analyze()?;
+    info!(
+        "imported {} markets, {} ticks from {} files ({} skipped)",
+        stats.markets_imported, stats.ticks_imported, stats.files_processed, stats.files_skipped
+    );
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::MemoryStore;
+    use crate::types::{Outcome, Platform, UnixSecs};
+
+    fn tick(market_id: &MarketId, side: Side, offset_ms: i64, levels: usize) -> BookTick {
+        BookTick {
+            market_id: market_id.clone(),
+            side,
+            timestamp_ms: 1_700_000_000_000 + offset_ms,
+            offset_ms: OffsetMs::new(offset_ms),
+            best_bid: Some(0.49),
+            best_bid_size: Some(120.0),
+            best_ask: (offset_ms > 0).then_some(0.51),
+            best_ask_size: None,
+            depth: (0..levels)
+                .map(|i| PriceLevel {
+                    price: 0.49 - i as f64 / 100.0,
+                    cumulative_size: 120.0 * (i + 1) as f64,
+                })
+                .collect(),
+            total_bid_depth: 500.0,
+            total_ask_depth: 300.0,
+            reference_price: Some(66_000.0),
+            oracle_price: None,
+        }
+    }
+
+    #[test]
+    fn test_market_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = MemoryStore::new();
+        let market = Market {
+            id: "btc/updown 15m".into(),
+            platform: Platform::Other("manifold".into()),
+            description: "BTC up or down".into(),
+            category: "btc".into(),
+            open_ts: UnixSecs::new(1_700_000_000),
+            close_ts: UnixSecs::new(1_700_000_900),
+            duration_secs: 900,
+            outcome: Some(Outcome::Yes),
+            outcome_ts: None,
+        };
+        let id: MarketId = market.id.as_str().into();
+        let ticks: Vec<BookTick> = (0..10)
+            .map(|i| tick(&id, if i % 2 == 0 { Side::Yes } else { Side::No }, i * 100, i as usize % 4))
+            .collect();
+        store.insert_market(&market).unwrap();
+        store.insert_ticks(&ticks).unwrap();
+
+        assert_eq!(parquet_file_name(&market.id), "btc_updown_15m.parquet");
+        let path = dir.path().join(parquet_file_name(&market.id));
+        assert_eq!(export_market_parquet(&store, &market, &path).unwrap(), 10);
+        std::fs::write(dir.path().join("junk.parquet"), b"not parquet").unwrap();
+
+        let dest = MemoryStore::new();
+        let stats = import_parquet_directory(dir.path(), &dest).unwrap();
+        assert_eq!((stats.files_processed, stats.files_skipped, stats.ticks_imported), (1, 1, 10));
+        let markets = dest.list_markets(&Default::default()).unwrap();
+        assert_eq!(markets.len(), 1);
+        assert_eq!(markets[0].platform, Platform::Other("manifold".into()));
+        assert_eq!(markets[0].outcome, Some(Outcome::Yes));
+
+        let (expected, loaded) = (store.load_ticks(&market.id).unwrap(), dest.load_ticks(&market.id).unwrap());
+        assert_eq!(loaded.len(), 10);
+        for (a, b) in expected.iter().zip(&loaded) {
+            assert_eq!((a.side, a.offset_ms, a.timestamp_ms), (b.side, b.offset_ms, b.timestamp_ms));
+            assert_eq!((a.best_ask, a.best_ask_size, a.oracle_price), (b.best_ask, b.best_ask_size, b.oracle_price));
+            assert_eq!(a.depth.len(), b.depth.len());
+            for (x, y) in a.depth.iter().zip(&b.depth) {
+                assert_eq!((x.price, x.cumulative_size), (y.price, y.cumulative_size));
+            }
+        }
+    }
+}
@@ -1,14 +1,16 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use rusqlite::{Connection, OpenFlags};
 use tracing::debug;
 
 use crate::types::{
-    BookSnapshot, BookTick, Market, Outcome, Platform, PriceLevel, Side, SideState,
+    BookSnapshot, BookTick, DepthLevels, Market, MarketId, OffsetMs, Outcome, Platform, PriceLevel, Side,
+    UnixSecs, LEGACY_DEPTH_PRICES,
 };
 
 use super::schema;
+pub use super::snapshots::ticks_to_snapshots;
 use super::store::DataStore;
 
 // ---------------------------------------------------------------------------
@@ -19,6 +21,8 @@ use super::store::DataStore;
 /// directly and produces PhantomFill's platform-agnostic types.
 pub struct PolymarketStore {
     conn: Connection,
+    /// `bid_levels`, or `NULL` for captures without the full ladder.
+    bid_levels: &'static str,
 }
 
 impl PolymarketStore {
@@ -26,14 +30,19 @@ impl PolymarketStore {
     pub fn open(path: &Path) -> Result<Self> {
         let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
         conn.execute_batch("PRAGMA query_only = ON;")?;
-        Ok(Self { conn })
+        let bid_levels = ladder_column(&conn)?;
+        Ok(Self { conn, bid_levels })
     }
 
     /// Open from the default pm-spread-arb database path.
     pub fn open_default() -> Result<Self> {
+        Self::open(&Self::default_path()?)
+    }
+
+    /// Default pm-spread-arb database location (`~/.local/share/pm_trader/spread_arb.db`).
+    pub fn default_path() -> Result<PathBuf> {
         let home = std::env::var("HOME").context("HOME not set")?;
-        let path = Path::new(&home).join(".local/share/pm_trader/spread_arb.db");
-        Self::open(&path)
+        Ok(Path::new(&home).join(".local/share/pm_trader/spread_arb.db"))
     }
 
     /// List all available markets derived from distinct slugs in `book_ticks`.
@@ -43,7 +52,7 @@ impl PolymarketStore {
             let slug: String = row.get(0)?;
             let asset: String = row.get(1)?;
             let timeframe: String = row.get(2)?;
-            let window_ts: i64 = row.get(3)?;
+            let window_ts: UnixSecs = row.get(3)?;
 
             let duration_secs = timeframe_to_secs(&timeframe);
 
@@ -56,6 +65,7 @@ impl PolymarketStore {
                 close_ts: window_ts + duration_secs,
                 duration_secs,
                 outcome: None,
+                outcome_ts: None,
             })
         })?;
 
@@ -82,45 +92,33 @@ impl PolymarketStore {
 
     /// Load all [`BookTick`]s for a slug, ordered by offset_ms then side.
     pub fn load_ticks(&self, slug: &str) -> Result<Vec<BookTick>> {
-        let mut stmt = self.conn.prepare(schema::PM_LOAD_TICKS)?;
+        let sql = schema::PM_LOAD_TICKS.replace("{bid_levels}", self.bid_levels);
+        let mut stmt = self.conn.prepare(&sql)?;
+        let market_id = MarketId::from(slug);
 
         let rows = stmt.query_map([slug], |row| {
-            let slug: String = row.get(0)?;
-            let side_str: String = row.get(1)?;
-            let tick_ms: i64 = row.get(2)?;
-            let offset_ms: i64 = row.get(3)?;
-            let best_bid: Option<f64> = row.get(4)?;
-            let best_bid_size: Option<f64> = row.get(5)?;
-            let best_ask: Option<f64> = row.get(6)?;
-            let best_ask_size: Option<f64> = row.get(7)?;
-            let depth_049: Option<f64> = row.get(8)?;
-            let depth_050: Option<f64> = row.get(9)?;
-            let depth_051: Option<f64> = row.get(10)?;
-            let total_bid_depth: Option<f64> = row.get(11)?;
-            let total_ask_depth: Option<f64> = row.get(12)?;
-            let btc_price: Option<f64> = row.get(13)?;
-            let chainlink_price: Option<f64> = row.get(14)?;
-
-            Ok(BookTick {
-                market_id: slug,
-                side: map_side(&side_str),
-                timestamp_ms: tick_ms,
-                offset_ms,
-                best_bid,
-                best_bid_size,
-                best_ask,
-                best_ask_size,
-                depth: build_depth_levels(depth_049, depth_050, depth_051),
-                total_bid_depth: total_bid_depth.unwrap_or(0.0),
-                total_ask_depth: total_ask_depth.unwrap_or(0.0),
-                reference_price: btc_price,
-                oracle_price: chainlink_price,
+            Ok(RawTick {
+                side: row.get(1)?,
+                tick_ms: row.get(2)?,
+                offset_ms: row.get(3)?,
+                best_bid: row.get(4)?,
+                best_bid_size: row.get(5)?,
+                best_ask: row.get(6)?,
+                best_ask_size: row.get(7)?,
+                depth_at_049: row.get(8)?,
+                depth_at_050: row.get(9)?,
+                depth_at_051: row.get(10)?,
+                total_bid_depth: row.get::<_, Option<f64>>(11)?.unwrap_or(0.0),
+                total_ask_depth: row.get::<_, Option<f64>>(12)?.unwrap_or(0.0),
+                btc_price: row.get(13)?,
+                chainlink_price: row.get(14)?,
+                bid_levels: row.get(15)?,
             })
         })?;
 
         let mut ticks = Vec::new();
         for r in rows {
-            ticks.push(r?);
+            ticks.push(map_tick(&market_id, &r?)?);
         }
 
         debug!("loaded {} ticks for slug {}", ticks.len(), slug);
@@ -221,114 +219,67 @@ fn timeframe_to_secs(tf: &str) -> i64 {
     }
 }
 
-/// Build a `Vec<PriceLevel>` from the three depth columns.
+/// The `book_ticks` column holding the full bid ladder, or `NULL` when the
+/// capture predates it and only has the three `depth_at_*` columns.
+fn ladder_column(conn: &Connection) -> Result<&'static str> {
+    let mut stmt = conn.prepare("SELECT 1 FROM pragma_table_info('book_ticks') WHERE name = 'bid_levels'")?;
+    Ok(if stmt.exists([])? { "bid_levels" } else { "NULL" })
+}
+
+/// Build [`DepthLevels`] from a `bid_levels` ladder: a JSON array of
+/// `[price, size]` pairs with per-level sizes, in any order. Sizes are made
+/// cumulative from the best bid down and empty levels are dropped.
+fn parse_bid_levels(json: &str) -> Result<DepthLevels> {
+    let mut levels: Vec<(f64, f64)> = serde_json::from_str(json).context("bid_levels is not a [[price, size], ...] array")?;
+    if let Some(&(price, size)) = levels.iter().find(|(p, s)| !p.is_finite() || !s.is_finite() || *s < 0.0) {
+        bail!("bad bid level [{}, {}]", price, size);
+    }
+    levels.retain(|&(_, size)| size > 0.0);
+    levels.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let mut cumulative = 0.0;
+    let mut depth: DepthLevels = levels
+        .into_iter()
+        .map(|(price, size)| {
+            cumulative += size;
+            PriceLevel { price, cumulative_size: cumulative }
+        })
+        .collect();
+    depth.reverse();
+    Ok(depth)
+}
+
+/// Build [`DepthLevels`] from the three depth columns.
 /// Only includes levels where the depth value is present and positive.
 fn build_depth_levels(
     depth_049: Option<f64>,
     depth_050: Option<f64>,
     depth_051: Option<f64>,
-) -> Vec<PriceLevel> {
-    let mut levels = Vec::with_capacity(3);
-    if let Some(d) = depth_049 {
-        if d > 0.0 {
-            levels.push(PriceLevel { price: 0.49, cumulative_size: d });
-        }
-    }
-    if let Some(d) = depth_050 {
-        if d > 0.0 {
-            levels.push(PriceLevel { price: 0.50, cumulative_size: d });
-        }
-    }
-    if let Some(d) = depth_051 {
-        if d > 0.0 {
-            levels.push(PriceLevel { price: 0.51, cumulative_size: d });
-        }
-    }
-    levels
-}
-
-/// Convert a `BookTick` into a `SideState`.
-fn tick_to_side_state(tick: &BookTick) -> SideState {
-    SideState {
-        best_bid: tick.best_bid,
-        best_bid_size: tick.best_bid_size,
-        best_ask: tick.best_ask,
-        best_ask_size: tick.best_ask_size,
-        depth: tick.depth.clone(),
-        total_bid_depth: tick.total_bid_depth,
-        total_ask_depth: tick.total_ask_depth,
-    }
-}
-
-/// Group ticks into [`BookSnapshot`]s by offset_ms.
-///
-/// At each offset, UP (Yes) and/or DOWN (No) ticks are combined into one
-/// snapshot. If a side is missing at a given offset, the previous snapshot's
-/// state for that side is carried forward.
-pub fn ticks_to_snapshots(market_id: &str, ticks: &[BookTick]) -> Vec<BookSnapshot> {
-    if ticks.is_empty() {
-        return Vec::new();
-    }
-
-    let mut snapshots = Vec::new();
-    let mut prev_yes = SideState::default();
-    let mut prev_no = SideState::default();
-
-    let mut i = 0;
-    while i < ticks.len() {
-        let offset = ticks[i].offset_ms;
-        let timestamp = ticks[i].timestamp_ms;
-        let mut yes_state: Option<SideState> = None;
-        let mut no_state: Option<SideState> = None;
-        let mut ref_price: Option<f64> = None;
-        let mut oracle_price: Option<f64> = None;
-
-        // Consume all ticks at this offset_ms.
-        while i < ticks.len() && ticks[i].offset_ms == offset {
-            let tick = &ticks[i];
-            match tick.side {
-                Side::Yes => yes_state = Some(tick_to_side_state(tick)),
-                Side::No => no_state = Some(tick_to_side_state(tick)),
-            }
-            if ref_price.is_none() {
-                ref_price = tick.reference_price;
-            }
-            if oracle_price.is_none() {
-                oracle_price = tick.oracle_price;
-            }
-            i += 1;
-        }
-
-        let yes = yes_state.unwrap_or_else(|| prev_yes.clone());
-        let no = no_state.unwrap_or_else(|| prev_no.clone());
-
-        prev_yes = yes.clone();
-        prev_no = no.clone();
-
-        snapshots.push(BookSnapshot {
-            market_id: market_id.to_string(),
-            offset_ms: offset,
-            timestamp_ms: timestamp,
-            yes,
-            no,
-            reference_price: ref_price,
-            oracle_price,
-        });
-    }
-
-    snapshots
+) -> DepthLevels {
+    LEGACY_DEPTH_PRICES
+        .into_iter()
+        .zip([depth_049, depth_050, depth_051])
+        .filter_map(|(price, depth)| depth.filter(|&d| d > 0.0).map(|d| PriceLevel { price, cumulative_size: d }))
+        .collect()
 }
 
 // ---------------------------------------------------------------------------
 // Import pipeline (existing code — reads source DB, writes to PhantomFill DB)
 // ---------------------------------------------------------------------------
 
-/// Statistics from an import run.
+/// Statistics from an import run. Imports are incremental: re-running one
+/// against a grown capture database only adds the new ticks.
 #[derive(Debug, Default)]
 pub struct ImportStats {
+    /// Markets that gained ticks.
     pub markets_imported: usize,
+    /// Ticks added.
     pub ticks_imported: usize,
+    /// Markets with too few ticks or no oracle prices.
     pub markets_skipped: usize,
+    /// Markets with nothing new since the last import.
+    pub markets_unchanged: usize,
+    /// Ticks read from the source that the destination already held.
+    pub ticks_existing: usize,
 }
 
 /// Minimum number of ticks a market must have to be imported.
@@ -346,13 +297,18 @@ pub fn import_from_capture_db(
 ) -> Result<ImportStats> {
     let src = Connection::open(source_path)
         .with_context(|| format!("Failed to open source DB: {}", source_path.display()))?;
+    let source = source_path
+        .canonicalize()
+        .unwrap_or_else(|_| source_path.to_path_buf());
 
-    import_from_connection(&src, dest, filter)
+    import_from_connection(&src, &source.to_string_lossy(), dest, filter)
 }
 
 /// Inner function that works on an already-opened connection (testable with in-memory DBs).
+/// `source` keys the destination's import watermarks.
 fn import_from_connection(
     src: &Connection,
+    source: &str,
     dest: &dyn DataStore,
     filter: Option<&str>,
 ) -> Result<ImportStats> {
@@ -360,7 +316,7 @@ fn import_from_connection(
 
     // Discover distinct markets (slug, asset, timeframe, window_ts)
     let mut market_sql = String::from(
-        "SELECT DISTINCT slug, asset, timeframe, window_ts FROM book_ticks",
+        "SELECT slug, asset, timeframe, window_ts, MAX(tick_ms) FROM book_ticks",
     );
     let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
 
@@ -373,30 +329,39 @@ fn import_from_connection(
         params.push(Box::new(f.to_string()));
     }
 
-    market_sql.push_str(" ORDER BY window_ts");
+    market_sql.push_str(" GROUP BY slug, asset, timeframe, window_ts ORDER BY window_ts");
+    let tick_sql = format!(
+        "SELECT tick_ms, offset_ms, side, best_bid, best_bid_size, best_ask, best_ask_size,
+                depth_at_049, depth_at_050, depth_at_051,
+                total_bid_depth, total_ask_depth, btc_price, chainlink_price, {}
+         FROM book_ticks WHERE slug = ? ORDER BY offset_ms, side",
+        ladder_column(src)?
+    );
 
     let param_refs: Vec<&dyn rusqlite::types::ToSql> =
         params.iter().map(|p| p.as_ref()).collect();
     let mut stmt = src.prepare(&market_sql)?;
-    let market_keys: Vec<(String, String, String, i64)> = stmt
+    let market_keys: Vec<(String, String, String, i64, i64)> = stmt
         .query_map(param_refs.as_slice(), |row| {
             Ok((
                 row.get::<_, String>(0)?,
                 row.get::<_, String>(1)?,
                 row.get::<_, String>(2)?,
                 row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
             ))
         })?
         .collect::<std::result::Result<Vec<_>, _>>()?;
 
-    for (slug, asset, timeframe, window_ts) in &market_keys {
+    for (slug, asset, timeframe, window_ts, last_tick_ms) in &market_keys {
+        let watermark = dest.import_watermark(source, slug)?;
+        if watermark.is_some_and(|w| w >= *last_tick_ms) {
+            stats.markets_unchanged += 1;
+            continue;
+        }
+
         // Load all ticks for this market window
-        let mut tick_stmt = src.prepare_cached(
-            "SELECT tick_ms, offset_ms, side, best_bid, best_bid_size, best_ask, best_ask_size,
-                    depth_at_049, depth_at_050, depth_at_051,
-                    total_bid_depth, total_ask_depth, btc_price, chainlink_price
-             FROM book_ticks WHERE slug = ? ORDER BY offset_ms, side",
-        )?;
+        let mut tick_stmt = src.prepare_cached(&tick_sql)?;
 
         let raw_ticks: Vec<RawTick> = tick_stmt
             .query_map([slug], |row| {
@@ -415,6 +380,7 @@ fn import_from_connection(
                     total_ask_depth: row.get::<_, Option<f64>>(11)?.unwrap_or(0.0),
                     btc_price: row.get(12)?,
                     chainlink_price: row.get(13)?,
+                    bid_levels: row.get(14)?,
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -435,6 +401,7 @@ fn import_from_connection(
         let outcome = determine_outcome(&raw_ticks);
 
         let duration_secs = parse_duration(timeframe);
+        let window_ts = UnixSecs::checked(*window_ts).with_context(|| format!("window_ts of {}", slug))?;
         let close_ts = window_ts + duration_secs;
 
         let market = Market {
@@ -442,33 +409,42 @@ fn import_from_connection(
             platform: Platform::Polymarket,
             description: format!("{} {} {}", asset.to_uppercase(), timeframe, slug),
             category: asset.clone(),
-            open_ts: *window_ts,
+            open_ts: window_ts,
             close_ts,
             duration_secs,
             outcome,
+            outcome_ts: None,
         };
 
-        dest.insert_market(&market)?;
-
-        // Convert ticks
+        // Convert the ticks past the watermark; the outcome above still
+        // uses them all.
+        let market_id = MarketId::from(slug.as_str());
         let book_ticks: Vec<BookTick> = raw_ticks
             .iter()
-            .map(|rt| map_tick(slug, rt))
-            .collect();
-
-        dest.insert_ticks(&book_ticks)?;
-
-        stats.markets_imported += 1;
-        stats.ticks_imported += book_ticks.len();
+            .filter(|rt| watermark.is_none_or(|w| rt.tick_ms > w))
+            .map(|rt| map_tick(&market_id, rt))
+            .collect::<Result<_>>()?;
+
+        let added = dest.insert_ticks(&book_ticks)?;
+        stats.ticks_existing += raw_ticks.len() - added;
+        if added > 0 {
+            dest.insert_market(&market)?;
+            stats.markets_imported += 1;
+            stats.ticks_imported += added;
+        } else {
+            stats.markets_unchanged += 1;
+        }
+        dest.set_import_watermark(source, slug, *last_tick_ms)?;
     }
 
+    dest.analyze()?;
     Ok(stats)
 }
 
 /// Raw tick from the capture database.
 struct RawTick {
     tick_ms: i64,
-    offset_ms: i64,
+    offset_ms: OffsetMs,
     side: String,
     best_bid: Option<f64>,
     best_bid_size: Option<f64>,
@@ -481,11 +457,18 @@ struct RawTick {
     total_ask_depth: f64,
     btc_price: Option<f64>,
     chainlink_price: Option<f64>,
+    /// Full bid ladder as JSON, when the capture records one.
+    bid_levels: Option<String>,
 }
 
-fn map_tick(market_id: &str, rt: &RawTick) -> BookTick {
-    BookTick {
-        market_id: market_id.to_string(),
+fn map_tick(market_id: &MarketId, rt: &RawTick) -> Result<BookTick> {
+    let depth = match &rt.bid_levels {
+        Some(json) => parse_bid_levels(json)
+            .with_context(|| format!("{} tick at {} ms", market_id, rt.tick_ms))?,
+        None => build_depth_levels(rt.depth_at_049, rt.depth_at_050, rt.depth_at_051),
+    };
+    Ok(BookTick {
+        market_id: market_id.clone(),
         side: map_side(&rt.side),
         timestamp_ms: rt.tick_ms,
         offset_ms: rt.offset_ms,
@@ -493,12 +476,12 @@ fn map_tick(market_id: &str, rt: &RawTick) -> BookTick {
         best_bid_size: rt.best_bid_size,
         best_ask: rt.best_ask,
         best_ask_size: rt.best_ask_size,
-        depth: build_depth_levels(rt.depth_at_049, rt.depth_at_050, rt.depth_at_051),
+        depth,
         total_bid_depth: rt.total_bid_depth,
         total_ask_depth: rt.total_ask_depth,
         reference_price: rt.btc_price,
         oracle_price: rt.chainlink_price,
-    }
+    })
 }
 
 fn determine_outcome(ticks: &[RawTick]) -> Option<Outcome> {
@@ -575,7 +558,7 @@ mod tests {
 
         for i in 0..count {
             let offset = (i as i64) * 1000;
-            let tick_ms = 1000_000 + offset;
+            let tick_ms = 1_000_000 + offset;
             // Linearly interpolate oracle price
             let frac = if count > 1 { i as f64 / (count - 1) as f64 } else { 1.0 };
             let oracle = oracle_open + (oracle_close - oracle_open) * frac;
@@ -598,7 +581,7 @@ mod tests {
         let dest = SqliteStore::in_memory().unwrap();
         dest.init().unwrap();
 
-        let stats = import_from_connection(&src, &dest, None).unwrap();
+        let stats = import_from_connection(&src, "src", &dest, None).unwrap();
         assert_eq!(stats.markets_imported, 1);
         assert_eq!(stats.ticks_imported, 20); // 10 offsets * 2 sides
         assert_eq!(stats.markets_skipped, 0);
@@ -611,6 +594,32 @@ mod tests {
         assert_eq!(markets[0].platform, Platform::Polymarket);
     }
 
+    #[test]
+    fn test_reimport_adds_only_new_ticks() {
+        let src = create_test_source_db();
+        insert_test_ticks(&src, "btc-updown-5m-1000", 10, 66000.0, 66100.0);
+        let dest = SqliteStore::in_memory().unwrap();
+        dest.init().unwrap();
+        import_from_connection(&src, "src", &dest, None).unwrap();
+
+        let stats = import_from_connection(&src, "src", &dest, None).unwrap();
+        assert_eq!((stats.markets_imported, stats.ticks_imported), (0, 0));
+        assert_eq!(stats.markets_unchanged, 1);
+
+        // The capture grows by two offsets.
+        insert_test_ticks(&src, "btc-updown-5m-1000", 12, 66000.0, 66100.0);
+        let stats = import_from_connection(&src, "src", &dest, None).unwrap();
+        assert_eq!((stats.markets_imported, stats.ticks_imported), (1, 4));
+        assert_eq!(dest.load_ticks("btc-updown-5m-1000").unwrap().len(), 24);
+
+        // Without a watermark (a new source key), the ticks are still
+        // recognised as already present.
+        let stats = import_from_connection(&src, "copy", &dest, None).unwrap();
+        assert_eq!(stats.ticks_imported, 0);
+        assert_eq!(stats.markets_unchanged, 1);
+        assert_eq!(dest.load_ticks("btc-updown-5m-1000").unwrap().len(), 24);
+    }
+
     #[test]
     fn test_import_outcome_no() {
         let src = create_test_source_db();
@@ -620,7 +629,7 @@ mod tests {
         let dest = SqliteStore::in_memory().unwrap();
         dest.init().unwrap();
 
-        let stats = import_from_connection(&src, &dest, None).unwrap();
+        let stats = import_from_connection(&src, "src", &dest, None).unwrap();
         assert_eq!(stats.markets_imported, 1);
 
         let markets = dest.list_markets(&Default::default()).unwrap();
@@ -636,7 +645,7 @@ mod tests {
         let dest = SqliteStore::in_memory().unwrap();
         dest.init().unwrap();
 
-        let stats = import_from_connection(&src, &dest, None).unwrap();
+        let stats = import_from_connection(&src, "src", &dest, None).unwrap();
         assert_eq!(stats.markets_imported, 0);
         assert_eq!(stats.markets_skipped, 1);
     }
@@ -666,7 +675,7 @@ mod tests {
         let dest = SqliteStore::in_memory().unwrap();
         dest.init().unwrap();
 
-        let stats = import_from_connection(&src, &dest, Some("btc")).unwrap();
+        let stats = import_from_connection(&src, "src", &dest, Some("btc")).unwrap();
         assert_eq!(stats.markets_imported, 1);
 
         let markets = dest.list_markets(&Default::default()).unwrap();
@@ -682,7 +691,7 @@ mod tests {
         let dest = SqliteStore::in_memory().unwrap();
         dest.init().unwrap();
 
-        import_from_connection(&src, &dest, None).unwrap();
+        import_from_connection(&src, "src", &dest, None).unwrap();
 
         let ticks = dest.load_ticks("btc-updown-5m-5000").unwrap();
         assert!(!ticks.is_empty());
@@ -697,6 +706,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_import_full_ladder() {
+        let src = create_test_source_db();
+        insert_test_ticks(&src, "btc-updown-5m-5000", 10, 66000.0, 66100.0);
+        src.execute_batch(
+            "ALTER TABLE book_ticks ADD COLUMN bid_levels TEXT;
+             UPDATE book_ticks SET bid_levels = '[[0.45, 300], [0.49, 100], [0.47, 0], [0.48, 250.5], [0.3, 1000]]'
+             WHERE side = 'UP';",
+        )
+        .unwrap();
+
+        let dest = SqliteStore::in_memory().unwrap();
+        dest.init().unwrap();
+        import_from_connection(&src, "src", &dest, None).unwrap();
+
+        let ticks = dest.load_ticks("btc-updown-5m-5000").unwrap();
+        for tick in &ticks {
+            let ladder: Vec<(f64, f64)> = tick.depth.iter().map(|l| (l.price, l.cumulative_size)).collect();
+            if tick.side == Side::Yes {
+                assert_eq!(ladder, vec![(0.3, 1650.5), (0.45, 650.5), (0.48, 350.5), (0.49, 100.0)]);
+            } else {
+                // A NULL ladder falls back to the three depth columns.
+                assert_eq!(ladder, vec![(0.49, 500.0), (0.50, 120.0), (0.51, 50.0)]);
+            }
+        }
+
+        src.execute_batch("UPDATE book_ticks SET bid_levels = '[[0.49]]', tick_ms = tick_ms + 100000;")
+            .unwrap();
+        let err = import_from_connection(&src, "src", &dest, None).unwrap_err();
+        assert!(format!("{:#}", err).contains("btc-updown-5m-5000 tick at"), "{:#}", err);
+    }
+
     #[test]
     fn test_side_mapping() {
         let src = create_test_source_db();
@@ -705,7 +746,7 @@ mod tests {
         let dest = SqliteStore::in_memory().unwrap();
         dest.init().unwrap();
 
-        import_from_connection(&src, &dest, None).unwrap();
+        import_from_connection(&src, "src", &dest, None).unwrap();
 
         let ticks = dest.load_ticks("btc-updown-5m-6000").unwrap();
         let yes_count = ticks.iter().filter(|t| t.side == Side::Yes).count();
@@ -766,165 +807,6 @@ mod tests {
         assert!(levels.is_empty());
     }
 
-    #[test]
-    fn test_tick_to_side_state_conversion() {
-        let tick = BookTick {
-            market_id: "test".into(),
-            side: Side::Yes,
-            timestamp_ms: 1000,
-            offset_ms: 0,
-            best_bid: Some(0.49),
-            best_bid_size: Some(100.0),
-            best_ask: Some(0.51),
-            best_ask_size: Some(200.0),
-            depth: vec![PriceLevel { price: 0.49, cumulative_size: 500.0 }],
-            total_bid_depth: 500.0,
-            total_ask_depth: 200.0,
-            reference_price: Some(66000.0),
-            oracle_price: None,
-        };
-
-        let state = tick_to_side_state(&tick);
-        assert_eq!(state.best_bid, Some(0.49));
-        assert_eq!(state.best_ask, Some(0.51));
-        assert_eq!(state.depth.len(), 1);
-        assert!((state.total_bid_depth - 500.0).abs() < 1e-9);
-    }
-
-    #[test]
-    fn test_ticks_to_snapshots_both_sides() {
-        let ticks = vec![
-            BookTick {
-                market_id: "m1".into(),
-                side: Side::No,
-                timestamp_ms: 1000,
-                offset_ms: 0,
-                best_bid: Some(0.48),
-                best_bid_size: Some(50.0),
-                best_ask: Some(0.52),
-                best_ask_size: Some(60.0),
-                depth: vec![],
-                total_bid_depth: 50.0,
-                total_ask_depth: 60.0,
-                reference_price: Some(66000.0),
-                oracle_price: None,
-            },
-            BookTick {
-                market_id: "m1".into(),
-                side: Side::Yes,
-                timestamp_ms: 1000,
-                offset_ms: 0,
-                best_bid: Some(0.49),
-                best_bid_size: Some(100.0),
-                best_ask: Some(0.51),
-                best_ask_size: Some(200.0),
-                depth: vec![],
-                total_bid_depth: 500.0,
-                total_ask_depth: 200.0,
-                reference_price: Some(66000.0),
-                oracle_price: Some(66010.0),
-            },
-        ];
-
-        let snaps = ticks_to_snapshots("m1", &ticks);
-        assert_eq!(snaps.len(), 1);
-        assert_eq!(snaps[0].yes.best_bid, Some(0.49));
-        assert_eq!(snaps[0].no.best_bid, Some(0.48));
-        assert_eq!(snaps[0].reference_price, Some(66000.0));
-        assert_eq!(snaps[0].oracle_price, Some(66010.0));
-    }
-
-    #[test]
-    fn test_ticks_to_snapshots_carry_forward() {
-        let ticks = vec![
-            BookTick {
-                market_id: "m1".into(),
-                side: Side::No,
-                timestamp_ms: 1000,
-                offset_ms: 0,
-                best_bid: Some(0.48),
-                best_bid_size: Some(50.0),
-                best_ask: Some(0.52),
-                best_ask_size: Some(60.0),
-                depth: vec![],
-                total_bid_depth: 50.0,
-                total_ask_depth: 60.0,
-                reference_price: Some(66000.0),
-                oracle_price: None,
-            },
-            BookTick {
-                market_id: "m1".into(),
-                side: Side::Yes,
-                timestamp_ms: 1000,
-                offset_ms: 0,
-                best_bid: Some(0.49),
-                best_bid_size: Some(100.0),
-                best_ask: Some(0.51),
-                best_ask_size: Some(200.0),
-                depth: vec![],
-                total_bid_depth: 500.0,
-                total_ask_depth: 200.0,
-                reference_price: Some(66000.0),
-                oracle_price: None,
-            },
-            // Next offset: only YES side present.
-            BookTick {
-                market_id: "m1".into(),
-                side: Side::Yes,
-                timestamp_ms: 2000,
-                offset_ms: 1000,
-                best_bid: Some(0.50),
-                best_bid_size: Some(110.0),
-                best_ask: Some(0.51),
-                best_ask_size: Some(210.0),
-                depth: vec![],
-                total_bid_depth: 510.0,
-                total_ask_depth: 210.0,
-                reference_price: Some(66100.0),
-                oracle_price: None,
-            },
-        ];
-
-        let snaps = ticks_to_snapshots("m1", &ticks);
-        assert_eq!(snaps.len(), 2);
-        // Second snapshot YES updated, NO carried forward.
-        assert_eq!(snaps[1].yes.best_bid, Some(0.50));
-        assert_eq!(snaps[1].no.best_bid, Some(0.48));
-        assert_eq!(snaps[1].no.best_ask, Some(0.52));
-    }
-
-    #[test]
-    fn test_ticks_to_snapshots_empty() {
-        let snaps = ticks_to_snapshots("m1", &[]);
-        assert!(snaps.is_empty());
-    }
-
-    #[test]
-    fn test_ticks_to_snapshots_single_side_defaults() {
-        let ticks = vec![BookTick {
-            market_id: "m1".into(),
-            side: Side::Yes,
-            timestamp_ms: 1000,
-            offset_ms: 0,
-            best_bid: Some(0.49),
-            best_bid_size: Some(100.0),
-            best_ask: Some(0.51),
-            best_ask_size: Some(200.0),
-            depth: vec![],
-            total_bid_depth: 500.0,
-            total_ask_depth: 200.0,
-            reference_price: Some(66000.0),
-            oracle_price: None,
-        }];
-
-        let snaps = ticks_to_snapshots("m1", &ticks);
-        assert_eq!(snaps.len(), 1);
-        // NO side uses SideState::default().
-        assert_eq!(snaps[0].no.best_bid, None);
-        assert_eq!(snaps[0].no.best_ask, None);
-        assert!((snaps[0].no.total_bid_depth).abs() < 1e-9);
-    }
-
     // -----------------------------------------------------------------------
     // PolymarketStore integration tests (require real DB)
     // -----------------------------------------------------------------------
@@ -945,7 +827,7 @@ mod tests {
         let m = &markets[0];
         assert_eq!(m.platform, Platform::Polymarket);
         assert!(!m.id.is_empty());
-        assert!(m.open_ts > 0);
+        assert!(m.open_ts > UnixSecs::new(0));
         assert!(m.close_ts > m.open_ts);
         assert!(m.duration_secs == 300 || m.duration_secs == 900);
     }
@@ -974,7 +856,7 @@ mod tests {
             assert!(pair[0].offset_ms <= pair[1].offset_ms);
         }
         for s in &snaps {
-            assert_eq!(s.market_id, *slug);
+            assert_eq!(*s.market_id, **slug);
         }
     }
 
@@ -1,5 +1,24 @@
 //! DDL for PhantomFill's own SQLite tables.
 
+/// Version of the native schema, stored in SQLite's `user_version` pragma.
+/// Bump with every change to the native DDL below, indexes included, and
+/// add it to this list; [`DataStore::init`](super::DataStore::init)
+/// upgrades older databases in place and refuses newer ones.
+///
+/// 1. `pf_markets`, `pf_ticks` and `pf_depth_levels`
+/// 2. `pf_runs` and `pf_results`
+/// 3. covering replay indexes
+/// 4. `pf_results.rejected_orders` and `rejection`
+/// 5. `pf_markets.outcome_ts`
+/// 6. `pf_results.stake` and `bankroll`
+/// 7. `pf_trades`
+/// 8. `pf_imports` and the tick uniqueness index
+/// 9. `pf_runs.version`
+/// 10. `pf_results.events`
+/// 11. the trade uniqueness index
+/// 12. `idx_pf_ticks_market_side_offset` dropped
+pub const SCHEMA_VERSION: i64 = 12;
+
 pub const CREATE_MARKETS: &str = "
 CREATE TABLE IF NOT EXISTS pf_markets (
     id            TEXT PRIMARY KEY,
@@ -9,7 +28,8 @@ CREATE TABLE IF NOT EXISTS pf_markets (
     open_ts       INTEGER NOT NULL,
     close_ts      INTEGER NOT NULL,
     duration_secs INTEGER NOT NULL,
-    outcome       TEXT
+    outcome       TEXT,
+    outcome_ts    INTEGER
 );
 ";
 
@@ -40,11 +60,169 @@ CREATE TABLE IF NOT EXISTS pf_depth_levels (
 );
 ";
 
+/// Executed trades from a market's trade tape (importers that see them,
+/// e.g. the HuggingFace dataset's trade rows). `taker_side` is `BUY`,
+/// `SELL` or NULL when the feed does not say. Like ticks, a trade already
+/// stored is skipped on insert; see [`CREATE_TRADES_UNIQUE`].
+pub const CREATE_TRADES: &str = "
+CREATE TABLE IF NOT EXISTS pf_trades (
+    id           INTEGER PRIMARY KEY AUTOINCREMENT,
+    market_id    TEXT NOT NULL,
+    side         TEXT NOT NULL,
+    timestamp_ms INTEGER NOT NULL,
+    offset_ms    INTEGER NOT NULL,
+    price        REAL NOT NULL,
+    size         REAL NOT NULL,
+    taker_side   TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_pf_trades_market_offset ON pf_trades(market_id, offset_ms);
+";
+
+/// How far each market has been imported from each source, so a re-run
+/// of an import against a growing source only reads what's new. `source`
+/// is the importer's key for where the ticks came from (the capture
+/// database's path for `pf import`); `last_tick_ms` is the newest source
+/// timestamp imported.
+pub const CREATE_IMPORTS: &str = "
+CREATE TABLE IF NOT EXISTS pf_imports (
+    source       TEXT NOT NULL,
+    market_id    TEXT NOT NULL,
+    last_tick_ms INTEGER NOT NULL,
+    imported_at  INTEGER NOT NULL,
+    PRIMARY KEY (source, market_id)
+);
+";
+
+/// One market's trades, in tape order.
+pub const LOAD_TRADES: &str = "
+SELECT market_id, side, timestamp_ms, offset_ms, price, size, taker_side
+FROM pf_trades WHERE market_id = ?1 ORDER BY offset_ms, id
+";
+
+/// One persisted backtest run; `params` is the strategy's params as JSON.
+pub const CREATE_RUNS: &str = "
+CREATE TABLE IF NOT EXISTS pf_runs (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at INTEGER NOT NULL,
+    strategy   TEXT NOT NULL,
+    params     TEXT NOT NULL DEFAULT '{}',
+    fill_model TEXT NOT NULL,
+    seed       INTEGER,
+    version    TEXT NOT NULL DEFAULT ''
+);
+";
+
+/// Window results of persisted runs, one row per `WindowResult`.
+pub const CREATE_RESULTS: &str = "
+CREATE TABLE IF NOT EXISTS pf_results (
+    run_id               INTEGER NOT NULL,
+    market_id            TEXT NOT NULL,
+    platform             TEXT NOT NULL,
+    category             TEXT NOT NULL,
+    open_ts              INTEGER NOT NULL,
+    close_ts             INTEGER NOT NULL,
+    outcome              TEXT NOT NULL,
+    predicted            TEXT,
+    signal_offset_ms     INTEGER,
+    bid_side             TEXT,
+    bid_price            REAL NOT NULL,
+    shares               REAL NOT NULL,
+    filled               INTEGER NOT NULL,
+    queue_ahead_at_place REAL NOT NULL,
+    fill_time_ms         INTEGER,
+    correct              INTEGER NOT NULL,
+    realistic_pnl        REAL NOT NULL,
+    naive_pnl            REAL NOT NULL,
+    ref_price_open       REAL,
+    ref_price_close      REAL,
+    aborted              TEXT,
+    script_errors        INTEGER NOT NULL DEFAULT 0,
+    rejected_orders      INTEGER NOT NULL DEFAULT 0,
+    rejection            TEXT,
+    stake                REAL NOT NULL DEFAULT 0,
+    bankroll             REAL,
+    events               TEXT,
+    FOREIGN KEY (run_id) REFERENCES pf_runs(id)
+);
+";
+
+/// `pf_markets` columns added since the table was introduced; see
+/// [`RESULTS_ADDED_COLUMNS`].
+pub const MARKETS_ADDED_COLUMNS: &[(&str, &str)] = &[("outcome_ts", "INTEGER")];
+
+/// `pf_runs` columns added since the table was introduced; see
+/// [`RESULTS_ADDED_COLUMNS`].
+pub const RUNS_ADDED_COLUMNS: &[(&str, &str)] = &[("version", "TEXT NOT NULL DEFAULT ''")];
+
+/// `pf_results` columns added since the table was introduced, with their
+/// declarations; [`DataStore::init`](super::DataStore::init) adds any an
+/// older database is missing.
+pub const RESULTS_ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("rejected_orders", "INTEGER NOT NULL DEFAULT 0"),
+    ("rejection", "TEXT"),
+    ("stake", "REAL NOT NULL DEFAULT 0"),
+    ("bankroll", "REAL"),
+    ("events", "TEXT"),
+];
+
+/// `(market_id, offset_ms, side)` hands replay a market's ticks already in
+/// replay order (plus the tick ids, for joining depth); the depth index
+/// covers whole ladders, so they are read without touching the table. The
+/// narrower indexes these replace are dropped.
 pub const CREATE_INDEXES: &str = "
-CREATE INDEX IF NOT EXISTS idx_pf_ticks_market ON pf_ticks(market_id);
+CREATE INDEX IF NOT EXISTS idx_pf_ticks_market_offset_side ON pf_ticks(market_id, offset_ms, side);
 CREATE INDEX IF NOT EXISTS idx_pf_ticks_offset ON pf_ticks(offset_ms);
-CREATE INDEX IF NOT EXISTS idx_pf_ticks_market_side_offset ON pf_ticks(market_id, side, offset_ms);
-CREATE INDEX IF NOT EXISTS idx_pf_depth_tick ON pf_depth_levels(tick_id);
+CREATE INDEX IF NOT EXISTS idx_pf_depth_tick_levels ON pf_depth_levels(tick_id, price, cumulative_size);
+CREATE INDEX IF NOT EXISTS idx_pf_results_run ON pf_results(run_id, open_ts);
+DROP INDEX IF EXISTS idx_pf_ticks_market;
+DROP INDEX IF EXISTS idx_pf_depth_tick;
+DROP INDEX IF EXISTS idx_pf_ticks_market_side_offset;
+";
+
+/// A tick is identified by its market, side and timestamp; inserting one
+/// that's already stored is a no-op. [`DEDUP_TICKS`] runs first on
+/// databases created before the index, keeping the first copy of each.
+pub const CREATE_TICKS_UNIQUE: &str = "
+CREATE UNIQUE INDEX IF NOT EXISTS idx_pf_ticks_unique ON pf_ticks(market_id, side, timestamp_ms);
+";
+
+/// A trade is identified by its market, side, timestamp, price and size,
+/// so re-importing a tape doesn't double its volume. [`DEDUP_TRADES`] runs
+/// first on databases created before the index.
+pub const CREATE_TRADES_UNIQUE: &str = "
+CREATE UNIQUE INDEX IF NOT EXISTS idx_pf_trades_unique ON pf_trades(market_id, side, timestamp_ms, price, size);
+";
+
+/// Remove duplicate trades, keeping the earliest inserted.
+pub const DEDUP_TRADES: &str = "
+DELETE FROM pf_trades
+WHERE id NOT IN (SELECT MIN(id) FROM pf_trades GROUP BY market_id, side, timestamp_ms, price, size);
+";
+
+/// Remove duplicate ticks, and their depth levels, keeping the earliest
+/// inserted.
+pub const DEDUP_TICKS: &str = "
+CREATE TEMP TABLE pf_duplicate_ticks AS
+    SELECT id FROM pf_ticks
+    WHERE id NOT IN (SELECT MIN(id) FROM pf_ticks GROUP BY market_id, side, timestamp_ms);
+DELETE FROM pf_depth_levels WHERE tick_id IN (SELECT id FROM pf_duplicate_ticks);
+DELETE FROM pf_ticks WHERE id IN (SELECT id FROM pf_duplicate_ticks);
+DROP TABLE pf_duplicate_ticks;
+";
+
+/// One market's ticks, in replay order.
+pub const LOAD_TICKS: &str = "
+SELECT id, market_id, side, timestamp_ms, offset_ms,
+       best_bid, best_bid_size, best_ask, best_ask_size,
+       total_bid_depth, total_ask_depth, reference_price, oracle_price
+FROM pf_ticks WHERE market_id = ?1 ORDER BY offset_ms, side
+";
+
+/// Depth levels of all of one market's ticks, by tick then price.
+pub const LOAD_DEPTH_LEVELS: &str = "
+SELECT d.tick_id, d.price, d.cumulative_size
+FROM pf_depth_levels d JOIN pf_ticks t ON t.id = d.tick_id
+WHERE t.market_id = ?1 ORDER BY d.tick_id, d.price
 ";
 
 // ---------------------------------------------------------------------------
@@ -66,6 +244,8 @@ ORDER BY window_ts
 ";
 
 /// Load all ticks for a given slug, ordered by offset_ms then side.
+/// `{bid_levels}` is replaced with the ladder column, or `NULL` for captures
+/// without one.
 pub const PM_LOAD_TICKS: &str = "
 SELECT
     slug,
@@ -82,7 +262,8 @@ SELECT
     total_bid_depth,
     total_ask_depth,
     btc_price,
-    chainlink_price
+    chainlink_price,
+    {bid_levels}
 FROM book_ticks
 WHERE slug = ?1
 ORDER BY offset_ms, side
@@ -0,0 +1,350 @@
+//! Grouping per-side [`BookTick`] rows into the [`BookSnapshot`]s the replay
+//! engine consumes. Shared by every data source.
+//!
+//! Snapshots leave the process (API responses, on-disk caches, JSON test
+//! fixtures) as a [`SnapshotSet`], which records the schema version they
+//! were written with.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{BookSnapshot, BookTick, MarketId, Side, SideState};
+
+/// Version of the serialized [`BookSnapshot`] layout. Bump it when a field
+/// is renamed, removed or changes meaning. Adding an optional field does
+/// not need a bump.
+pub const SNAPSHOT_SCHEMA_VERSION: u32 = 1;
+
+/// One market's snapshots in the versioned wire format:
+///
+/// ```json
+/// {"schema_version": 1, "market_id": "btc-5m-1", "snapshots": [
+///   {"market_id": "btc-5m-1", "offset_ms": 0, "timestamp_ms": 1700000000000,
+///    "yes": {"best_bid": 0.49, "depth": [{"price": 0.49, "cumulative_size": 30.0}],
+///            "total_bid_depth": 30.0},
+///    "no": {}, "reference_price": 97000.5, "oracle_price": null}
+/// ]}
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotSet {
+    pub schema_version: u32,
+    pub market_id: String,
+    pub snapshots: Vec<BookSnapshot>,
+}
+
+impl SnapshotSet {
+    /// Wrap `snapshots` at the current schema version.
+    pub fn new(market_id: impl Into<String>, snapshots: Vec<BookSnapshot>) -> Self {
+        Self {
+            schema_version: SNAPSHOT_SCHEMA_VERSION,
+            market_id: market_id.into(),
+            snapshots,
+        }
+    }
+
+    /// Parse a set, rejecting schema versions this build does not know.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let set: SnapshotSet = serde_json::from_str(json).context("invalid snapshot set")?;
+        if set.schema_version != SNAPSHOT_SCHEMA_VERSION {
+            bail!(
+                "snapshot schema version {} is not supported (expected {})",
+                set.schema_version,
+                SNAPSHOT_SCHEMA_VERSION
+            );
+        }
+        Ok(set)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Read a set written by [`save`](Self::save) (or a hand-written fixture).
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Self::from_json(&json).with_context(|| format!("failed to load {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_json()?)
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+/// Convert a `BookTick` into a `SideState`.
+fn tick_to_side_state(tick: &BookTick) -> SideState {
+    SideState {
+        best_bid: tick.best_bid,
+        best_bid_size: tick.best_bid_size,
+        best_ask: tick.best_ask,
+        best_ask_size: tick.best_ask_size,
+        depth: tick.depth.clone(),
+        total_bid_depth: tick.total_bid_depth,
+        total_ask_depth: tick.total_ask_depth,
+    }
+}
+
+/// Group ticks into [`BookSnapshot`]s by offset_ms.
+///
+/// At each offset, UP (Yes) and/or DOWN (No) ticks are combined into one
+/// snapshot. If a side is missing at a given offset, the previous snapshot's
+/// state for that side is carried forward.
+pub fn ticks_to_snapshots(market_id: &str, ticks: &[BookTick]) -> Vec<BookSnapshot> {
+    if ticks.is_empty() {
+        return Vec::new();
+    }
+
+    let market_id = MarketId::from(market_id);
+    let mut snapshots = Vec::new();
+    let mut prev_yes = Arc::new(SideState::default());
+    let mut prev_no = Arc::new(SideState::default());
+
+    let mut i = 0;
+    while i < ticks.len() {
+        let offset = ticks[i].offset_ms;
+        let timestamp = ticks[i].timestamp_ms;
+        let mut yes_state: Option<SideState> = None;
+        let mut no_state: Option<SideState> = None;
+        let mut ref_price: Option<f64> = None;
+        let mut oracle_price: Option<f64> = None;
+
+        // Consume all ticks at this offset_ms.
+        while i < ticks.len() && ticks[i].offset_ms == offset {
+            let tick = &ticks[i];
+            match tick.side {
+                Side::Yes => yes_state = Some(tick_to_side_state(tick)),
+                Side::No => no_state = Some(tick_to_side_state(tick)),
+            }
+            if ref_price.is_none() {
+                ref_price = tick.reference_price;
+            }
+            if oracle_price.is_none() {
+                oracle_price = tick.oracle_price;
+            }
+            i += 1;
+        }
+
+        // A side without a tick here shares the previous snapshot's state.
+        let yes = yes_state.map_or_else(|| Arc::clone(&prev_yes), Arc::new);
+        let no = no_state.map_or_else(|| Arc::clone(&prev_no), Arc::new);
+
+        prev_yes = Arc::clone(&yes);
+        prev_no = Arc::clone(&no);
+
+        snapshots.push(BookSnapshot {
+            market_id: market_id.clone(),
+            offset_ms: offset,
+            timestamp_ms: timestamp,
+            yes,
+            no,
+            reference_price: ref_price,
+            oracle_price,
+        });
+    }
+
+    snapshots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DepthLevels, OffsetMs, PriceLevel};
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_tick_to_side_state_conversion() {
+        let tick = BookTick {
+            market_id: "test".into(),
+            side: Side::Yes,
+            timestamp_ms: 1000,
+            offset_ms: OffsetMs::new(0),
+            best_bid: Some(0.49),
+            best_bid_size: Some(100.0),
+            best_ask: Some(0.51),
+            best_ask_size: Some(200.0),
+            depth: smallvec![PriceLevel { price: 0.49, cumulative_size: 500.0 }],
+            total_bid_depth: 500.0,
+            total_ask_depth: 200.0,
+            reference_price: Some(66000.0),
+            oracle_price: None,
+        };
+
+        let state = tick_to_side_state(&tick);
+        assert_eq!(state.best_bid, Some(0.49));
+        assert_eq!(state.best_ask, Some(0.51));
+        assert_eq!(state.depth.len(), 1);
+        assert!((state.total_bid_depth - 500.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ticks_to_snapshots_both_sides() {
+        let ticks = vec![
+            BookTick {
+                market_id: "m1".into(),
+                side: Side::No,
+                timestamp_ms: 1000,
+                offset_ms: OffsetMs::new(0),
+                best_bid: Some(0.48),
+                best_bid_size: Some(50.0),
+                best_ask: Some(0.52),
+                best_ask_size: Some(60.0),
+                depth: DepthLevels::new(),
+                total_bid_depth: 50.0,
+                total_ask_depth: 60.0,
+                reference_price: Some(66000.0),
+                oracle_price: None,
+            },
+            BookTick {
+                market_id: "m1".into(),
+                side: Side::Yes,
+                timestamp_ms: 1000,
+                offset_ms: OffsetMs::new(0),
+                best_bid: Some(0.49),
+                best_bid_size: Some(100.0),
+                best_ask: Some(0.51),
+                best_ask_size: Some(200.0),
+                depth: DepthLevels::new(),
+                total_bid_depth: 500.0,
+                total_ask_depth: 200.0,
+                reference_price: Some(66000.0),
+                oracle_price: Some(66010.0),
+            },
+        ];
+
+        let snaps = ticks_to_snapshots("m1", &ticks);
+        assert_eq!(snaps.len(), 1);
+        assert_eq!(snaps[0].yes.best_bid, Some(0.49));
+        assert_eq!(snaps[0].no.best_bid, Some(0.48));
+        assert_eq!(snaps[0].reference_price, Some(66000.0));
+        assert_eq!(snaps[0].oracle_price, Some(66010.0));
+    }
+
+    #[test]
+    fn test_ticks_to_snapshots_carry_forward() {
+        let ticks = vec![
+            BookTick {
+                market_id: "m1".into(),
+                side: Side::No,
+                timestamp_ms: 1000,
+                offset_ms: OffsetMs::new(0),
+                best_bid: Some(0.48),
+                best_bid_size: Some(50.0),
+                best_ask: Some(0.52),
+                best_ask_size: Some(60.0),
+                depth: DepthLevels::new(),
+                total_bid_depth: 50.0,
+                total_ask_depth: 60.0,
+                reference_price: Some(66000.0),
+                oracle_price: None,
+            },
+            BookTick {
+                market_id: "m1".into(),
+                side: Side::Yes,
+                timestamp_ms: 1000,
+                offset_ms: OffsetMs::new(0),
+                best_bid: Some(0.49),
+                best_bid_size: Some(100.0),
+                best_ask: Some(0.51),
+                best_ask_size: Some(200.0),
+                depth: DepthLevels::new(),
+                total_bid_depth: 500.0,
+                total_ask_depth: 200.0,
+                reference_price: Some(66000.0),
+                oracle_price: None,
+            },
+            // Next offset: only YES side present.
+            BookTick {
+                market_id: "m1".into(),
+                side: Side::Yes,
+                timestamp_ms: 2000,
+                offset_ms: OffsetMs::new(1000),
+                best_bid: Some(0.50),
+                best_bid_size: Some(110.0),
+                best_ask: Some(0.51),
+                best_ask_size: Some(210.0),
+                depth: DepthLevels::new(),
+                total_bid_depth: 510.0,
+                total_ask_depth: 210.0,
+                reference_price: Some(66100.0),
+                oracle_price: None,
+            },
+        ];
+
+        let snaps = ticks_to_snapshots("m1", &ticks);
+        assert_eq!(snaps.len(), 2);
+        // Second snapshot YES updated, NO carried forward.
+        assert_eq!(snaps[1].yes.best_bid, Some(0.50));
+        assert_eq!(snaps[1].no.best_bid, Some(0.48));
+        assert_eq!(snaps[1].no.best_ask, Some(0.52));
+        // The carried-forward side is shared, not copied.
+        assert!(Arc::ptr_eq(&snaps[0].no, &snaps[1].no));
+        assert!(!Arc::ptr_eq(&snaps[0].yes, &snaps[1].yes));
+        // As is the market id.
+        assert!(Arc::ptr_eq(&snaps[0].market_id, &snaps[1].market_id));
+    }
+
+    #[test]
+    fn test_ticks_to_snapshots_empty() {
+        let snaps = ticks_to_snapshots("m1", &[]);
+        assert!(snaps.is_empty());
+    }
+
+    #[test]
+    fn test_ticks_to_snapshots_single_side_defaults() {
+        let ticks = vec![BookTick {
+            market_id: "m1".into(),
+            side: Side::Yes,
+            timestamp_ms: 1000,
+            offset_ms: OffsetMs::new(0),
+            best_bid: Some(0.49),
+            best_bid_size: Some(100.0),
+            best_ask: Some(0.51),
+            best_ask_size: Some(200.0),
+            depth: DepthLevels::new(),
+            total_bid_depth: 500.0,
+            total_ask_depth: 200.0,
+            reference_price: Some(66000.0),
+            oracle_price: None,
+        }];
+
+        let snaps = ticks_to_snapshots("m1", &ticks);
+        assert_eq!(snaps.len(), 1);
+        // NO side uses SideState::default().
+        assert_eq!(snaps[0].no.best_bid, None);
+        assert_eq!(snaps[0].no.best_ask, None);
+        assert!((snaps[0].no.total_bid_depth).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_snapshot_set_round_trip_and_versioning() {
+        let fixture = r#"{"schema_version": 1, "market_id": "m1", "snapshots": [
+            {"market_id": "m1", "offset_ms": 0, "timestamp_ms": 1700000000000,
+             "yes": {"best_bid": 0.49, "depth": [{"price": 0.49, "cumulative_size": 30.0}],
+                     "total_bid_depth": 30.0},
+             "no": {}, "reference_price": 97000.5}
+        ]}"#;
+        let set = SnapshotSet::from_json(fixture).unwrap();
+        let snap = &set.snapshots[0];
+        assert_eq!(snap.yes.bid_depth_at(0.49), 30.0);
+        assert_eq!(snap.no.best_bid, None);
+        assert_eq!(snap.oracle_price, None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("m1.json");
+        set.save(&path).unwrap();
+        let loaded = SnapshotSet::load(&path).unwrap();
+        assert_eq!(loaded.schema_version, SNAPSHOT_SCHEMA_VERSION);
+        assert_eq!(loaded.snapshots[0].timestamp_ms, 1_700_000_000_000);
+        assert_eq!(loaded.snapshots[0].reference_price, Some(97000.5));
+        assert_eq!(loaded.to_json().unwrap(), set.to_json().unwrap());
+
+        let future = fixture.replace(r#""schema_version": 1"#, r#""schema_version": 2"#);
+        let err = SnapshotSet::from_json(&future).unwrap_err();
+        assert!(err.to_string().contains("version 2 is not supported"));
+        assert!(SnapshotSet::from_json(r#"{"market_id": "m1"}"#).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+//! Summary statistics of a store's contents (`pf db stats`).
+//!
+//! A quick look before backtesting: how many markets there are per
+//! platform, category and timeframe, the period they cover, how many are
+//! resolved, and how densely their books were captured. Sparse markets
+//! replay with few decision points and fill poorly under the DeLise rules,
+//! so the density histogram is the usual first sign of a bad import.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+
+use crate::types::{Outcome, UnixSecs};
+
+use super::store::{DataStore, MarketFilter};
+
+/// Upper bounds (exclusive) of the tick density buckets, in ticks per
+/// minute of market window; the last bucket is open-ended.
+pub const DENSITY_BUCKETS: &[f64] = &[1.0, 10.0, 60.0, 300.0, 1200.0];
+
+/// What a store holds; see the [module docs](self).
+#[derive(Debug, Default)]
+pub struct DbStats {
+    pub markets: usize,
+    pub by_platform: BTreeMap<String, usize>,
+    pub by_category: BTreeMap<String, usize>,
+    /// Markets by window length, labelled like `"15m"`.
+    pub by_timeframe: BTreeMap<String, usize>,
+    pub ticks: usize,
+    /// Markets with no ticks at all.
+    pub empty_markets: usize,
+    /// Earliest window open and latest window close.
+    pub coverage: Option<(UnixSecs, UnixSecs)>,
+    pub outcome_yes: usize,
+    pub outcome_no: usize,
+    pub outcome_void: usize,
+    pub unresolved: usize,
+    /// Markets per [`DENSITY_BUCKETS`] bucket, plus the open-ended last one.
+    /// Empty markets are left out.
+    pub density: Vec<usize>,
+}
+
+/// `"15m"`, `"1h"` or `"90s"`.
+pub fn timeframe_label(duration_secs: i64) -> String {
+    if duration_secs > 0 && duration_secs % 3600 == 0 {
+        format!("{}h", duration_secs / 3600)
+    } else if duration_secs > 0 && duration_secs % 60 == 0 {
+        format!("{}m", duration_secs / 60)
+    } else {
+        format!("{}s", duration_secs)
+    }
+}
+
+/// Parse a window length like `"15m"`, `"1h"`, `"90s"` or `"1d"` into
+/// seconds; the inverse of [`timeframe_label`].
+pub fn parse_timeframe(label: &str) -> Result<i64> {
+    let label = label.trim();
+    let (digits, unit) = label.split_at(label.len() - label.chars().last().map_or(0, char::len_utf8));
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => bail!("timeframe `{}` needs a unit: s, m, h or d (e.g. 15m)", label),
+    };
+    let n: i64 = digits
+        .parse()
+        .with_context(|| format!("bad timeframe `{}`", label))?;
+    if n <= 0 {
+        bail!("timeframe `{}` must be positive", label);
+    }
+    Ok(n * scale)
+}
+
+impl DbStats {
+    /// Gather statistics over every market in `store`.
+    pub fn collect(store: &dyn DataStore) -> Result<Self> {
+        let markets = store.list_markets(&MarketFilter::default())?;
+        let tick_counts = store.count_ticks()?;
+
+        let mut stats = DbStats {
+            markets: markets.len(),
+            density: vec![0; DENSITY_BUCKETS.len() + 1],
+            ..Default::default()
+        };
+        for market in &markets {
+            *stats.by_platform.entry(market.platform.as_str().to_string()).or_default() += 1;
+            *stats.by_category.entry(market.category.clone()).or_default() += 1;
+            *stats.by_timeframe.entry(timeframe_label(market.duration_secs)).or_default() += 1;
+            stats.coverage = Some(match stats.coverage {
+                Some((first, last)) => (first.min(market.open_ts), last.max(market.close_ts)),
+                None => (market.open_ts, market.close_ts),
+            });
+            match market.outcome {
+                Some(Outcome::Yes) => stats.outcome_yes += 1,
+                Some(Outcome::No) => stats.outcome_no += 1,
+                Some(Outcome::Void) => stats.outcome_void += 1,
+                Some(Outcome::Unresolved) | None => stats.unresolved += 1,
+            }
+
+            let ticks = tick_counts.get(&market.id).copied().unwrap_or(0);
+            stats.ticks += ticks;
+            if ticks == 0 {
+                stats.empty_markets += 1;
+                continue;
+            }
+            let per_minute = ticks as f64 / (market.duration_secs.max(1) as f64 / 60.0);
+            let bucket = DENSITY_BUCKETS
+                .iter()
+                .position(|&bound| per_minute < bound)
+                .unwrap_or(DENSITY_BUCKETS.len());
+            stats.density[bucket] += 1;
+        }
+        Ok(stats)
+    }
+
+    pub fn print(&self) {
+        println!("  Markets: {}", self.markets);
+        for (title, counts) in [
+            ("platform", &self.by_platform),
+            ("category", &self.by_category),
+            ("timeframe", &self.by_timeframe),
+        ] {
+            let line: Vec<String> = counts
+                .iter()
+                .map(|(name, n)| format!("{} {}", if name.is_empty() { "(none)" } else { name }, n))
+                .collect();
+            println!("    by {:<10} {}", format!("{}:", title), line.join(", "));
+        }
+        println!();
+        println!("  Ticks:   {} ({} markets without any)", self.ticks, self.empty_markets);
+        if let Some((first, last)) = self.coverage {
+            let date = |ts: UnixSecs| {
+                ts.to_datetime()
+                    .map_or_else(|| ts.to_string(), |dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
+            };
+            println!("  Covers:  {} to {}", date(first), date(last));
+        }
+        println!(
+            "  Outcomes: {} YES, {} NO, {} VOID, {} unresolved",
+            self.outcome_yes, self.outcome_no, self.outcome_void, self.unresolved
+        );
+        println!();
+
+        println!("  Tick density (ticks per minute of window):");
+        let widest = self.density.iter().copied().max().unwrap_or(0).max(1);
+        for (i, count) in self.density.iter().enumerate() {
+            let label = match i {
+                0 => format!("< {}", DENSITY_BUCKETS[0]),
+                i if i == DENSITY_BUCKETS.len() => format!(">= {}", DENSITY_BUCKETS[i - 1]),
+                i => format!("{}-{}", DENSITY_BUCKETS[i - 1], DENSITY_BUCKETS[i]),
+            };
+            let bar = "#".repeat((count * 40).div_ceil(widest));
+            println!("{}", format!("    {:>10} {:>7}  {}", label, count, bar).trim_end());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::MemoryStore;
+    use crate::types::{BookTick, Market, OffsetMs, Platform, Side};
+
+    fn market(id: &str, open_ts: i64, duration_secs: i64, outcome: Option<Outcome>) -> Market {
+        Market {
+            id: id.into(),
+            platform: Platform::Polymarket,
+            description: String::new(),
+            category: "btc".into(),
+            open_ts: UnixSecs::new(open_ts),
+            close_ts: UnixSecs::new(open_ts + duration_secs),
+            duration_secs,
+            outcome,
+            outcome_ts: None,
+        }
+    }
+
+    fn ticks(id: &str, count: i64) -> Vec<BookTick> {
+        (0..count)
+            .map(|i| BookTick {
+                market_id: id.into(),
+                side: Side::Yes,
+                timestamp_ms: i * 100,
+                offset_ms: OffsetMs::new(i * 100),
+                best_bid: Some(0.49),
+                best_bid_size: Some(100.0),
+                best_ask: Some(0.51),
+                best_ask_size: Some(100.0),
+                depth: Default::default(),
+                total_bid_depth: 0.0,
+                total_ask_depth: 0.0,
+                reference_price: None,
+                oracle_price: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_collect() {
+        let store = MemoryStore::new();
+        store.insert_market(&market("a", 1_000, 900, Some(Outcome::Yes))).unwrap();
+        store.insert_market(&market("b", 5_000, 300, None)).unwrap();
+        store.insert_market(&market("c", 2_000, 3600, Some(Outcome::No))).unwrap();
+        store.insert_ticks(&ticks("a", 150)).unwrap(); // 10 per minute
+        store.insert_ticks(&ticks("b", 2)).unwrap(); // 0.4 per minute
+
+        let stats = DbStats::collect(&store).unwrap();
+        assert_eq!(stats.markets, 3);
+        assert_eq!(stats.ticks, 152);
+        assert_eq!(stats.empty_markets, 1);
+        assert_eq!(stats.coverage, Some((UnixSecs::new(1_000), UnixSecs::new(5_600))));
+        assert_eq!((stats.outcome_yes, stats.outcome_no, stats.unresolved), (1, 1, 1));
+        assert_eq!(stats.by_timeframe["15m"], 1);
+        assert_eq!(stats.by_timeframe["1h"], 1);
+        assert_eq!(stats.density, vec![1, 0, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_parse_timeframe() {
+        for secs in [90, 300, 900, 3600, 14_400] {
+            assert_eq!(parse_timeframe(&timeframe_label(secs)).unwrap(), secs);
+        }
+        assert_eq!(parse_timeframe("1d").unwrap(), 86_400);
+        assert!(parse_timeframe("15").is_err());
+        assert!(parse_timeframe("0m").is_err());
+        assert!(parse_timeframe("").is_err());
+    }
+}
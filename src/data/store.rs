@@ -1,144 +1,673 @@
-use anyhow::Result;
-use rusqlite::Connection;
+use std::collections::HashMap;
 
-use crate::types::{BookTick, Market, Outcome, Platform, PriceLevel, Side};
+use anyhow::{bail, Result};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+#[cfg(feature = "sqlite")]
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
 
+use crate::strategies::StrategySpec;
+use crate::types::{BookTick, Market, Platform, Trade, WindowResult};
+#[cfg(feature = "sqlite")]
+use crate::types::{DepthLevels, MarketId, OffsetMs, Outcome, PriceLevel, Side, TakerSide, UnixSecs};
+
+#[cfg(feature = "sqlite")]
 use super::schema;
 
+// Timestamps are stored as plain integers. Reading a market time that is
+// evidently in milliseconds fails rather than producing a window in the
+// year 50000.
+#[cfg(feature = "sqlite")]
+impl rusqlite::types::ToSql for UnixSecs {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(self.secs().into())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl rusqlite::types::FromSql for UnixSecs {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        UnixSecs::checked(value.as_i64()?).map_err(|e| rusqlite::types::FromSqlError::Other(e.into()))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl rusqlite::types::ToSql for OffsetMs {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(self.ms().into())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl rusqlite::types::FromSql for OffsetMs {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        value.as_i64().map(OffsetMs::new)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl rusqlite::types::ToSql for Platform {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(self.as_str().into())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl rusqlite::types::FromSql for Platform {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        value.as_str().map(Platform::from)
+    }
+}
+
 /// Filter criteria for listing markets.
 #[derive(Debug, Default)]
 pub struct MarketFilter {
     pub platform: Option<Platform>,
+    pub category: Option<String>,
+    /// Window length, e.g. 900 for 15-minute markets.
+    pub duration_secs: Option<i64>,
+    pub min_ts: Option<i64>,
+    pub max_ts: Option<i64>,
+}
+
+impl MarketFilter {
+    /// Whether `market` passes the filter, with the same semantics as
+    /// [`DataStore::list_markets`] (open at or after `min_ts`, closed at or
+    /// before `max_ts`).
+    pub fn matches(&self, market: &Market) -> bool {
+        self.platform.as_ref().is_none_or(|p| *p == market.platform)
+            && self.category.as_ref().is_none_or(|c| *c == market.category)
+            && self.duration_secs.is_none_or(|d| d == market.duration_secs)
+            && self.min_ts.is_none_or(|ts| market.open_ts.secs() >= ts)
+            && self.max_ts.is_none_or(|ts| market.close_ts.secs() <= ts)
+    }
+}
+
+/// Filter criteria for persisted window results, with the same time bounds
+/// as [`MarketFilter`].
+#[derive(Debug, Default, Clone)]
+pub struct ResultFilter {
     pub category: Option<String>,
     pub min_ts: Option<i64>,
     pub max_ts: Option<i64>,
 }
 
+impl ResultFilter {
+    /// Whether `result` passes the filter, with the same semantics as
+    /// [`DataStore::load_results`].
+    pub fn matches(&self, result: &WindowResult) -> bool {
+        self.category.as_ref().is_none_or(|c| *c == result.category)
+            && self.min_ts.is_none_or(|ts| result.open_ts >= ts)
+            && self.max_ts.is_none_or(|ts| result.close_ts <= ts)
+    }
+}
+
+/// What produced a set of persisted results.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunMeta {
+    pub strategy: StrategySpec,
+    pub fill_model: String,
+    pub seed: Option<u64>,
+    /// Unix seconds.
+    pub created_at: i64,
+    /// The [`VERSION`](crate::VERSION) that produced the results; empty for
+    /// runs stored before it was recorded.
+    #[serde(default)]
+    pub version: String,
+}
+
+impl RunMeta {
+    /// Metadata stamped with the current time.
+    pub fn new(strategy: StrategySpec, fill_model: &str, seed: Option<u64>) -> Self {
+        Self {
+            strategy,
+            fill_model: fill_model.to_string(),
+            seed,
+            created_at: chrono::Utc::now().timestamp(),
+            version: crate::VERSION.to_string(),
+        }
+    }
+}
+
+/// A persisted run as listed by [`DataStore::list_runs`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredRun {
+    pub id: i64,
+    #[serde(flatten)]
+    pub meta: RunMeta,
+    /// Number of window results stored for the run.
+    pub windows: usize,
+}
+
+/// Narrow a market list for quick iteration on large databases.
+///
+/// `sample` draws a random subset of that many markets (seeded when `seed` is
+/// given, so the same subset is picked on every run). `limit` then keeps only
+/// the first N. Chronological order is preserved in both cases.
+pub fn select_markets(
+    markets: Vec<Market>,
+    limit: Option<usize>,
+    sample: Option<usize>,
+    seed: Option<u64>,
+) -> Vec<Market> {
+    let mut markets = match sample {
+        Some(n) if n < markets.len() => {
+            let mut rng = match seed {
+                Some(s) => StdRng::seed_from_u64(s),
+                None => StdRng::from_entropy(),
+            };
+            let mut picked = rand::seq::index::sample(&mut rng, markets.len(), n).into_vec();
+            picked.sort_unstable();
+
+            let mut keep = vec![false; markets.len()];
+            for idx in picked {
+                keep[idx] = true;
+            }
+            markets
+                .into_iter()
+                .zip(keep)
+                .filter_map(|(m, k)| k.then_some(m))
+                .collect()
+        }
+        _ => markets,
+    };
+
+    if let Some(n) = limit {
+        markets.truncate(n);
+    }
+    markets
+}
+
+/// Loads one market's ticks; see [`DataStore::stream_ticks`].
+pub type TickLoader<'a> = dyn Fn(&str) -> Result<Vec<BookTick>> + 'a;
+
 /// Abstraction over tick/market storage.
 pub trait DataStore {
     fn init(&self) -> Result<()>;
     fn insert_market(&self, market: &Market) -> Result<()>;
-    fn insert_ticks(&self, ticks: &[BookTick]) -> Result<()>;
+    /// Insert ticks, skipping any the store already holds (same market,
+    /// side and timestamp). Returns how many were new.
+    fn insert_ticks(&self, ticks: &[BookTick]) -> Result<usize>;
     fn list_markets(&self, filter: &MarketFilter) -> Result<Vec<Market>>;
     fn load_ticks(&self, market_id: &str) -> Result<Vec<BookTick>>;
+
+    /// Number of ticks stored per market id; markets without ticks may be
+    /// left out.
+    fn count_ticks(&self) -> Result<HashMap<String, usize>> {
+        self.list_markets(&MarketFilter::default())?
+            .into_iter()
+            .map(|m| Ok((m.id.clone(), self.load_ticks(&m.id)?.len())))
+            .collect()
+    }
+
+    /// Call `f` with a tick loader for a pass over `markets`. The loader
+    /// returns the same ticks as [`load_ticks`](Self::load_ticks); stores
+    /// that can stream (SQLite) read all of them in one query, in `markets`
+    /// order, so callers should request markets in that order. Skipped
+    /// markets are fine; going back falls back to `load_ticks`.
+    fn stream_ticks(&self, markets: &[Market], f: &mut dyn FnMut(&TickLoader<'_>)) -> Result<()> {
+        let _ = markets;
+        f(&|id| self.load_ticks(id));
+        Ok(())
+    }
+
+    /// Persist executed trades from a market's trade tape, skipping any the
+    /// store already holds (same market, side, timestamp, price and size).
+    /// Stores that keep no tape refuse anything but an empty slice.
+    fn insert_trades(&self, trades: &[Trade]) -> Result<()> {
+        if !trades.is_empty() {
+            bail!("this store does not keep trades");
+        }
+        Ok(())
+    }
+
+    /// [`insert_ticks`](Self::insert_ticks) and
+    /// [`insert_trades`](Self::insert_trades) together; SQLite writes both
+    /// in one transaction, so a failed write leaves neither behind.
+    fn insert_ticks_and_trades(&self, ticks: &[BookTick], trades: &[Trade]) -> Result<usize> {
+        let inserted = self.insert_ticks(ticks)?;
+        self.insert_trades(trades)?;
+        Ok(inserted)
+    }
+
+    /// One market's trades by offset; empty if none were imported.
+    fn load_trades(&self, market_id: &str) -> Result<Vec<Trade>> {
+        let _ = market_id;
+        Ok(Vec::new())
+    }
+
+    /// Persist one run's window results; returns the new run id.
+    fn insert_results(&self, meta: &RunMeta, results: &[WindowResult]) -> Result<i64>;
+    /// Persisted runs, oldest first.
+    fn list_runs(&self) -> Result<Vec<StoredRun>>;
+    /// Results of run `run_id` passing `filter`, in the order they were
+    /// inserted. Fails if the run does not exist.
+    fn load_results(&self, run_id: i64, filter: &ResultFilter) -> Result<Vec<WindowResult>>;
+
+    /// One persisted run, if it exists.
+    fn load_run(&self, run_id: i64) -> Result<Option<StoredRun>> {
+        Ok(self.list_runs()?.into_iter().find(|r| r.id == run_id))
+    }
+
+    /// Whether loaded ticks carry their depth ladders.
+    fn loads_depth(&self) -> bool {
+        true
+    }
+
+    /// Newest source timestamp of `market_id` already imported from
+    /// `source`, an importer-chosen key such as the source database's path.
+    fn import_watermark(&self, source: &str, market_id: &str) -> Result<Option<i64>> {
+        let _ = (source, market_id);
+        Ok(None)
+    }
+
+    /// Record that `market_id` is imported from `source` up to
+    /// `last_tick_ms`. Stores that keep no watermarks ignore it.
+    fn set_import_watermark(&self, source: &str, market_id: &str, last_tick_ms: i64) -> Result<()> {
+        let _ = (source, market_id, last_tick_ms);
+        Ok(())
+    }
+
+    /// Refresh query-planner statistics after a bulk import. The importers
+    /// call this once they are done; stores without a planner ignore it.
+    fn analyze(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// SQLite-backed implementation.
+#[cfg(feature = "sqlite")]
 pub struct SqliteStore {
     conn: Connection,
+    depth: bool,
 }
 
+#[cfg(feature = "sqlite")]
 impl SqliteStore {
     pub fn new(conn: Connection) -> Self {
-        Self { conn }
+        Self { conn, depth: true }
     }
 
-    /// Open a file-backed database.
+    /// Open a file-backed database, adding any columns an older one lacks.
     pub fn open(path: &std::path::Path) -> Result<Self> {
         let conn = Connection::open(path)?;
         conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA synchronous=NORMAL;")?;
-        Ok(Self { conn })
+        let store = Self::new(conn);
+        store.add_missing_columns("pf_markets", schema::MARKETS_ADDED_COLUMNS)?;
+        store.add_missing_columns("pf_runs", schema::RUNS_ADDED_COLUMNS)?;
+        store.add_missing_columns("pf_results", schema::RESULTS_ADDED_COLUMNS)?;
+        Ok(store)
     }
 
     /// Open an in-memory database (useful for tests).
     pub fn in_memory() -> Result<Self> {
         let conn = Connection::open_in_memory()?;
-        Ok(Self { conn })
+        Ok(Self::new(conn))
+    }
+
+    /// Whether to read depth ladders (`pf_depth_levels`) with ticks; on by
+    /// default. Off, loaded ticks have empty `depth`, which roughly halves
+    /// load time and memory for fill models that only read the top of the
+    /// book (see [`FillModel::uses_depth`](crate::fill::FillModel::uses_depth)).
+    pub fn with_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
     }
 
     /// Borrow the underlying connection (for importers that need raw access).
     pub fn conn(&self) -> &Connection {
         &self.conn
     }
+
+    /// The query behind [`stream_ticks`](DataStore::stream_ticks), over the
+    /// markets in `temp.pf_stream_order`. `CROSS JOIN` pins that table as
+    /// the outer loop: the planner has no statistics for it and could
+    /// otherwise scan every tick.
+    fn stream_sql(&self) -> String {
+        let (levels, join, order) = if self.depth {
+            (
+                "d.price, d.cumulative_size",
+                "LEFT JOIN pf_depth_levels d ON d.tick_id = t.id",
+                ", d.price",
+            )
+        } else {
+            ("NULL, NULL", "", "")
+        };
+        format!(
+            "SELECT o.ord, t.id, t.market_id, t.side, t.timestamp_ms, t.offset_ms,
+                    t.best_bid, t.best_bid_size, t.best_ask, t.best_ask_size,
+                    t.total_bid_depth, t.total_ask_depth, t.reference_price, t.oracle_price,
+                    {}
+             FROM temp.pf_stream_order o
+             CROSS JOIN pf_ticks t ON t.market_id = o.market_id
+             {}
+             ORDER BY o.ord, t.offset_ms, t.side, t.id{}",
+            levels, join, order
+        )
+    }
+
+    /// Add any of `columns` that `table` lacks, for databases created
+    /// before they were. A missing table is left for `init` to create.
+    fn add_missing_columns(&self, table: &str, columns: &[(&str, &str)]) -> Result<()> {
+        let mut stmt = self.conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let existing = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if existing.is_empty() {
+            return Ok(());
+        }
+        for (name, declaration) in columns {
+            if !existing.iter().any(|c| c == name) {
+                self.conn
+                    .execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, name, declaration))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn has_index(&self, name: &str) -> Result<bool> {
+        Ok(self
+            .conn
+            .query_row("SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = ?1", [name], |_| Ok(()))
+            .optional()?
+            .is_some())
+    }
+
+    /// Schema version recorded by [`DataStore::init`] (0 if never stamped).
+    pub fn schema_version(&self) -> Result<i64> {
+        Ok(self
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))?)
+    }
+}
+
+/// One row of [`SqliteStore::stream_ticks`]: a market's position in the
+/// pass, the tick and its id, and one of the tick's depth levels.
+#[cfg(feature = "sqlite")]
+type StreamRow = (usize, i64, BookTick, Option<PriceLevel>);
+
+/// Cursor over the single query behind [`SqliteStore::stream_ticks`].
+#[cfg(feature = "sqlite")]
+struct TickStream<'s> {
+    rows: rusqlite::Rows<'s>,
+    order: std::collections::HashMap<&'s str, usize>,
+    /// Position of the first market not yet returned.
+    next: usize,
+    /// A row read past the end of the previous market.
+    pending: Option<StreamRow>,
+    /// Id of the market being read, shared by its ticks.
+    market_id: MarketId,
+}
+
+#[cfg(feature = "sqlite")]
+impl TickStream<'_> {
+    fn read_row(&mut self) -> Result<Option<StreamRow>> {
+        if let Some(row) = self.pending.take() {
+            return Ok(Some(row));
+        }
+        let Some(row) = self.rows.next()? else {
+            return Ok(None);
+        };
+        let market_id = row.get_ref(2)?.as_str()?;
+        if *self.market_id != *market_id {
+            self.market_id = market_id.into();
+        }
+        let side_str: String = row.get(3)?;
+        let price: Option<f64> = row.get(14)?;
+        let level = match price {
+            Some(price) => Some(PriceLevel {
+                price,
+                cumulative_size: row.get(15)?,
+            }),
+            None => None,
+        };
+        let tick = BookTick {
+            market_id: self.market_id.clone(),
+            side: if side_str == "YES" { Side::Yes } else { Side::No },
+            timestamp_ms: row.get(4)?,
+            offset_ms: row.get(5)?,
+            best_bid: row.get(6)?,
+            best_bid_size: row.get(7)?,
+            best_ask: row.get(8)?,
+            best_ask_size: row.get(9)?,
+            total_bid_depth: row.get(10)?,
+            total_ask_depth: row.get(11)?,
+            reference_price: row.get(12)?,
+            oracle_price: row.get(13)?,
+            depth: DepthLevels::new(),
+        };
+        Ok(Some((row.get::<_, i64>(0)? as usize, row.get(1)?, tick, level)))
+    }
+
+    /// Ticks of the market at position `ord`, skipping any earlier markets
+    /// that were not asked for.
+    fn read_market(&mut self, ord: usize) -> Result<Vec<BookTick>> {
+        let mut ticks: Vec<BookTick> = Vec::new();
+        let mut last_id = None;
+        while let Some(row) = self.read_row()? {
+            let (row_ord, tick_id, tick, level) = row;
+            if row_ord < ord {
+                continue;
+            }
+            if row_ord > ord {
+                self.pending = Some((row_ord, tick_id, tick, level));
+                break;
+            }
+            if last_id != Some(tick_id) {
+                ticks.push(tick);
+                last_id = Some(tick_id);
+            }
+            if let Some(level) = level {
+                ticks.last_mut().expect("tick pushed above").depth.push(level);
+            }
+        }
+        self.next = ord + 1;
+        Ok(ticks)
+    }
+}
+
+/// Insert `ticks` within `tx`, skipping any already stored; see
+/// [`DataStore::insert_ticks`].
+#[cfg(feature = "sqlite")]
+fn write_ticks(tx: &rusqlite::Transaction<'_>, ticks: &[BookTick]) -> Result<usize> {
+    let mut tick_stmt = tx.prepare_cached(
+        "INSERT OR IGNORE INTO pf_ticks
+         (market_id, side, timestamp_ms, offset_ms,
+          best_bid, best_bid_size, best_ask, best_ask_size,
+          total_bid_depth, total_ask_depth, reference_price, oracle_price)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+    )?;
+    let mut depth_stmt = tx.prepare_cached(
+        "INSERT INTO pf_depth_levels (tick_id, price, cumulative_size)
+         VALUES (?1, ?2, ?3)",
+    )?;
+
+    let mut inserted = 0;
+    for t in ticks {
+        let added = tick_stmt.execute(rusqlite::params![
+            t.market_id,
+            t.side.label(),
+            t.timestamp_ms,
+            t.offset_ms,
+            t.best_bid,
+            t.best_bid_size,
+            t.best_ask,
+            t.best_ask_size,
+            t.total_bid_depth,
+            t.total_ask_depth,
+            t.reference_price,
+            t.oracle_price,
+        ])?;
+        if added == 0 {
+            continue;
+        }
+        inserted += 1;
+
+        if !t.depth.is_empty() {
+            let tick_id = tx.last_insert_rowid();
+            for lvl in &t.depth {
+                depth_stmt.execute(rusqlite::params![
+                    tick_id,
+                    lvl.price,
+                    lvl.cumulative_size,
+                ])?;
+            }
+        }
+    }
+    Ok(inserted)
+}
+
+/// Insert `trades` within `tx`, skipping any already stored; see
+/// [`DataStore::insert_trades`].
+#[cfg(feature = "sqlite")]
+fn write_trades(tx: &rusqlite::Transaction<'_>, trades: &[Trade]) -> Result<()> {
+    let mut stmt = tx.prepare_cached(
+        "INSERT OR IGNORE INTO pf_trades
+         (market_id, side, timestamp_ms, offset_ms, price, size, taker_side)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    for t in trades {
+        stmt.execute(rusqlite::params![
+            t.market_id,
+            t.side.label(),
+            t.timestamp_ms,
+            t.offset_ms,
+            t.price,
+            t.size,
+            t.taker_side.as_ref().map(|s| s.label()),
+        ])?;
+    }
+    Ok(())
 }
 
+#[cfg(feature = "sqlite")]
 impl DataStore for SqliteStore {
     fn init(&self) -> Result<()> {
+        let version = self.schema_version()?;
+        if version > schema::SCHEMA_VERSION {
+            bail!(
+                "database schema v{} is newer than supported v{}; upgrade phantomfill",
+                version,
+                schema::SCHEMA_VERSION
+            );
+        }
         self.conn.execute_batch(schema::CREATE_MARKETS)?;
         self.conn.execute_batch(schema::CREATE_TICKS)?;
         self.conn.execute_batch(schema::CREATE_DEPTH_LEVELS)?;
+        self.conn.execute_batch(schema::CREATE_TRADES)?;
+        self.conn.execute_batch(schema::CREATE_RUNS)?;
+        self.conn.execute_batch(schema::CREATE_RESULTS)?;
+        self.conn.execute_batch(schema::CREATE_IMPORTS)?;
+        self.add_missing_columns("pf_markets", schema::MARKETS_ADDED_COLUMNS)?;
+        self.add_missing_columns("pf_runs", schema::RUNS_ADDED_COLUMNS)?;
+        self.add_missing_columns("pf_results", schema::RESULTS_ADDED_COLUMNS)?;
         self.conn.execute_batch(schema::CREATE_INDEXES)?;
+        if !self.has_index("idx_pf_ticks_unique")? {
+            let tx = self.conn.unchecked_transaction()?;
+            tx.execute_batch(schema::DEDUP_TICKS)?;
+            tx.execute_batch(schema::CREATE_TICKS_UNIQUE)?;
+            tx.commit()?;
+        }
+        if !self.has_index("idx_pf_trades_unique")? {
+            let tx = self.conn.unchecked_transaction()?;
+            tx.execute_batch(schema::DEDUP_TRADES)?;
+            tx.execute_batch(schema::CREATE_TRADES_UNIQUE)?;
+            tx.commit()?;
+        }
+        self.conn
+            .pragma_update(None, "user_version", schema::SCHEMA_VERSION)?;
         Ok(())
     }
 
     fn insert_market(&self, m: &Market) -> Result<()> {
         self.conn.execute(
             "INSERT OR REPLACE INTO pf_markets
-             (id, platform, description, category, open_ts, close_ts, duration_secs, outcome)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+             (id, platform, description, category, open_ts, close_ts, duration_secs, outcome, outcome_ts)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
             rusqlite::params![
                 m.id,
-                m.platform.to_string(),
+                m.platform,
                 m.description,
                 m.category,
                 m.open_ts,
                 m.close_ts,
                 m.duration_secs,
                 m.outcome.as_ref().map(|o| o.label()),
+                m.outcome_ts,
             ],
         )?;
         Ok(())
     }
 
-    fn insert_ticks(&self, ticks: &[BookTick]) -> Result<()> {
+    fn insert_ticks(&self, ticks: &[BookTick]) -> Result<usize> {
         let tx = self.conn.unchecked_transaction()?;
-        {
-            let mut tick_stmt = tx.prepare_cached(
-                "INSERT INTO pf_ticks
-                 (market_id, side, timestamp_ms, offset_ms,
-                  best_bid, best_bid_size, best_ask, best_ask_size,
-                  total_bid_depth, total_ask_depth, reference_price, oracle_price)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
-            )?;
-            let mut depth_stmt = tx.prepare_cached(
-                "INSERT INTO pf_depth_levels (tick_id, price, cumulative_size)
-                 VALUES (?1, ?2, ?3)",
-            )?;
-
-            for t in ticks {
-                tick_stmt.execute(rusqlite::params![
-                    t.market_id,
-                    t.side.label(),
-                    t.timestamp_ms,
-                    t.offset_ms,
-                    t.best_bid,
-                    t.best_bid_size,
-                    t.best_ask,
-                    t.best_ask_size,
-                    t.total_bid_depth,
-                    t.total_ask_depth,
-                    t.reference_price,
-                    t.oracle_price,
-                ])?;
+        let inserted = write_ticks(&tx, ticks)?;
+        tx.commit()?;
+        Ok(inserted)
+    }
 
-                if !t.depth.is_empty() {
-                    let tick_id = tx.last_insert_rowid();
-                    for lvl in &t.depth {
-                        depth_stmt.execute(rusqlite::params![
-                            tick_id,
-                            lvl.price,
-                            lvl.cumulative_size,
-                        ])?;
-                    }
-                }
-            }
-        }
+    fn insert_trades(&self, trades: &[Trade]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        write_trades(&tx, trades)?;
         tx.commit()?;
         Ok(())
     }
 
+    fn insert_ticks_and_trades(&self, ticks: &[BookTick], trades: &[Trade]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let inserted = write_ticks(&tx, ticks)?;
+        write_trades(&tx, trades)?;
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    fn load_trades(&self, market_id: &str) -> Result<Vec<Trade>> {
+        // Databases imported before the tape was kept have no table.
+        let has_table: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'pf_trades')",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_table {
+            return Ok(Vec::new());
+        }
+        let id = MarketId::from(market_id);
+        let mut stmt = self.conn.prepare_cached(schema::LOAD_TRADES)?;
+        let trades = stmt
+            .query_map([market_id], |row| {
+                let side: String = row.get(1)?;
+                let taker_side: Option<String> = row.get(6)?;
+                Ok(Trade {
+                    market_id: id.clone(),
+                    side: if side == "YES" { Side::Yes } else { Side::No },
+                    timestamp_ms: row.get(2)?,
+                    offset_ms: row.get(3)?,
+                    price: row.get(4)?,
+                    size: row.get(5)?,
+                    taker_side: taker_side.as_deref().and_then(TakerSide::from_label),
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(trades)
+    }
+
     fn list_markets(&self, filter: &MarketFilter) -> Result<Vec<Market>> {
-        let mut sql = String::from("SELECT id, platform, description, category, open_ts, close_ts, duration_secs, outcome FROM pf_markets WHERE 1=1");
+        let mut sql = String::from("SELECT id, platform, description, category, open_ts, close_ts, duration_secs, outcome, outcome_ts FROM pf_markets WHERE 1=1");
         let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
 
         if let Some(ref p) = filter.platform {
             sql.push_str(" AND platform = ?");
-            params.push(Box::new(p.to_string()));
+            params.push(Box::new(p.clone()));
         }
         if let Some(ref c) = filter.category {
             sql.push_str(" AND category = ?");
             params.push(Box::new(c.clone()));
         }
+        if let Some(d) = filter.duration_secs {
+            sql.push_str(" AND duration_secs = ?");
+            params.push(Box::new(d));
+        }
         if let Some(ts) = filter.min_ts {
             sql.push_str(" AND open_ts >= ?");
             params.push(Box::new(ts));
@@ -153,23 +682,17 @@ impl DataStore for SqliteStore {
         let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
         let mut stmt = self.conn.prepare(&sql)?;
         let rows = stmt.query_map(param_refs.as_slice(), |row| {
-            let platform_str: String = row.get(1)?;
             let outcome_str: Option<String> = row.get(7)?;
             Ok(Market {
                 id: row.get(0)?,
-                platform: match platform_str.as_str() {
-                    "kalshi" => Platform::Kalshi,
-                    _ => Platform::Polymarket,
-                },
+                platform: row.get(1)?,
                 description: row.get(2)?,
                 category: row.get(3)?,
                 open_ts: row.get(4)?,
                 close_ts: row.get(5)?,
                 duration_secs: row.get(6)?,
-                outcome: outcome_str.map(|s| match s.as_str() {
-                    "YES" => Outcome::Yes,
-                    _ => Outcome::No,
-                }),
+                outcome: outcome_str.as_deref().and_then(Outcome::from_label),
+                outcome_ts: row.get(8)?,
             })
         })?;
 
@@ -182,12 +705,8 @@ impl DataStore for SqliteStore {
 
     fn load_ticks(&self, market_id: &str) -> Result<Vec<BookTick>> {
         // Load ticks
-        let mut stmt = self.conn.prepare(
-            "SELECT id, market_id, side, timestamp_ms, offset_ms,
-                    best_bid, best_bid_size, best_ask, best_ask_size,
-                    total_bid_depth, total_ask_depth, reference_price, oracle_price
-             FROM pf_ticks WHERE market_id = ? ORDER BY offset_ms, side",
-        )?;
+        let id = MarketId::from(market_id);
+        let mut stmt = self.conn.prepare_cached(schema::LOAD_TICKS)?;
 
         let tick_rows: Vec<(i64, BookTick)> = stmt
             .query_map([market_id], |row| {
@@ -195,7 +714,7 @@ impl DataStore for SqliteStore {
                 Ok((
                     row.get::<_, i64>(0)?,
                     BookTick {
-                        market_id: row.get(1)?,
+                        market_id: id.clone(),
                         side: if side_str == "YES" {
                             Side::Yes
                         } else {
@@ -211,30 +730,25 @@ impl DataStore for SqliteStore {
                         total_ask_depth: row.get(10)?,
                         reference_price: row.get(11)?,
                         oracle_price: row.get(12)?,
-                        depth: Vec::new(),
+                        depth: DepthLevels::new(),
                     },
                 ))
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
-        // Load depth levels for all tick IDs
-        if tick_rows.is_empty() {
-            return Ok(Vec::new());
+        if !self.depth {
+            return Ok(tick_rows.into_iter().map(|(_, tick)| tick).collect());
         }
 
-        let tick_ids: Vec<i64> = tick_rows.iter().map(|(id, _)| *id).collect();
-        let placeholders: String = tick_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-        let sql = format!(
-            "SELECT tick_id, price, cumulative_size FROM pf_depth_levels WHERE tick_id IN ({}) ORDER BY tick_id, price",
-            placeholders
-        );
-        let mut depth_stmt = self.conn.prepare(&sql)?;
-        let param_refs: Vec<&dyn rusqlite::types::ToSql> =
-            tick_ids.iter().map(|id| id as &dyn rusqlite::types::ToSql).collect();
+        // Load depth levels for all of the market's ticks. Joining on the
+        // market (rather than binding every tick id in an `IN (...)` list)
+        // keeps the query within SQLite's host-parameter limit however
+        // many ticks a market has.
+        let mut depth_stmt = self.conn.prepare_cached(schema::LOAD_DEPTH_LEVELS)?;
 
-        let mut depth_map: std::collections::HashMap<i64, Vec<PriceLevel>> =
-            std::collections::HashMap::new();
-        let depth_rows = depth_stmt.query_map(param_refs.as_slice(), |row| {
+        let mut depth_map: std::collections::HashMap<i64, DepthLevels> =
+            std::collections::HashMap::with_capacity(tick_rows.len());
+        let depth_rows = depth_stmt.query_map([market_id], |row| {
             Ok((
                 row.get::<_, i64>(0)?,
                 PriceLevel {
@@ -260,12 +774,280 @@ impl DataStore for SqliteStore {
 
         Ok(ticks)
     }
+
+    fn stream_ticks(&self, markets: &[Market], f: &mut dyn FnMut(&TickLoader<'_>)) -> Result<()> {
+        // The pass order goes into a temp table so one query can walk every
+        // market's ticks (via the market index) in exactly that order.
+        self.conn.execute_batch(
+            "DROP TABLE IF EXISTS temp.pf_stream_order;
+             CREATE TEMP TABLE pf_stream_order (ord INTEGER PRIMARY KEY, market_id TEXT NOT NULL);",
+        )?;
+        {
+            let tx = self.conn.unchecked_transaction()?;
+            {
+                let mut stmt = tx.prepare("INSERT INTO temp.pf_stream_order (ord, market_id) VALUES (?1, ?2)")?;
+                for (i, m) in markets.iter().enumerate() {
+                    stmt.execute(rusqlite::params![i as i64, m.id])?;
+                }
+            }
+            tx.commit()?;
+        }
+
+        let mut stmt = self.conn.prepare(&self.stream_sql())?;
+        let stream = std::cell::RefCell::new(TickStream {
+            rows: stmt.query([])?,
+            order: markets.iter().enumerate().map(|(i, m)| (m.id.as_str(), i)).collect(),
+            next: 0,
+            pending: None,
+            market_id: MarketId::default(),
+        });
+        f(&|id| {
+            let mut stream = stream.borrow_mut();
+            match stream.order.get(id).copied().filter(|&ord| ord >= stream.next) {
+                Some(ord) => stream.read_market(ord),
+                None => self.load_ticks(id),
+            }
+        });
+        drop(stream);
+        drop(stmt);
+
+        self.conn.execute_batch("DROP TABLE temp.pf_stream_order;")?;
+        Ok(())
+    }
+
+    fn count_ticks(&self) -> Result<HashMap<String, usize>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT market_id, COUNT(*) FROM pf_ticks GROUP BY market_id")?;
+        let counts = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(counts)
+    }
+
+    fn import_watermark(&self, source: &str, market_id: &str) -> Result<Option<i64>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT last_tick_ms FROM pf_imports WHERE source = ?1 AND market_id = ?2",
+                [source, market_id],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn set_import_watermark(&self, source: &str, market_id: &str, last_tick_ms: i64) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO pf_imports (source, market_id, last_tick_ms, imported_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![source, market_id, last_tick_ms, chrono::Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    fn analyze(&self) -> Result<()> {
+        // Sampling a bounded number of index rows keeps this to seconds on
+        // databases with tens of millions of ticks.
+        self.conn.execute_batch("PRAGMA analysis_limit = 1000; ANALYZE;")?;
+        Ok(())
+    }
+
+    fn insert_results(&self, meta: &RunMeta, results: &[WindowResult]) -> Result<i64> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO pf_runs (created_at, strategy, params, fill_model, seed, version)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                meta.created_at,
+                meta.strategy.name,
+                serde_json::to_string(&meta.strategy.params)?,
+                meta.fill_model,
+                meta.seed.map(|s| s as i64),
+                meta.version,
+            ],
+        )?;
+        let run_id = tx.last_insert_rowid();
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO pf_results
+                 (run_id, market_id, platform, category, open_ts, close_ts, outcome,
+                  predicted, signal_offset_ms, bid_side, bid_price, shares, filled,
+                  queue_ahead_at_place, fill_time_ms, correct, realistic_pnl, naive_pnl,
+                  ref_price_open, ref_price_close, aborted, script_errors, rejected_orders,
+                  rejection, stake, bankroll, events)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15,
+                         ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25, ?26, ?27)",
+            )?;
+            for r in results {
+                let events = if r.events.is_empty() {
+                    None
+                } else {
+                    Some(serde_json::to_string(&r.events)?)
+                };
+                stmt.execute(rusqlite::params![
+                    run_id,
+                    r.market_id,
+                    r.platform,
+                    r.category,
+                    r.open_ts,
+                    r.close_ts,
+                    r.outcome,
+                    r.predicted,
+                    r.signal_offset_ms,
+                    r.bid_side,
+                    r.bid_price,
+                    r.shares,
+                    r.filled,
+                    r.queue_ahead_at_place,
+                    r.fill_time_ms,
+                    r.correct,
+                    r.realistic_pnl,
+                    r.naive_pnl,
+                    r.ref_price_open,
+                    r.ref_price_close,
+                    r.aborted,
+                    r.script_errors as i64,
+                    r.rejected_orders as i64,
+                    r.rejection,
+                    r.stake,
+                    r.bankroll,
+                    events,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(run_id)
+    }
+
+    fn list_runs(&self) -> Result<Vec<StoredRun>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT r.id, r.created_at, r.strategy, r.params, r.fill_model, r.seed,
+                    (SELECT COUNT(*) FROM pf_results WHERE run_id = r.id), r.version
+             FROM pf_runs r ORDER BY r.id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, Option<i64>>(5)?,
+                row.get::<_, i64>(6)?,
+                row.get::<_, String>(7)?,
+            ))
+        })?;
+
+        let mut runs = Vec::new();
+        for r in rows {
+            let (id, created_at, name, params, fill_model, seed, windows, version) = r?;
+            runs.push(StoredRun {
+                id,
+                meta: RunMeta {
+                    strategy: StrategySpec {
+                        name,
+                        params: serde_json::from_str(&params)?,
+                    },
+                    fill_model,
+                    seed: seed.map(|s| s as u64),
+                    created_at,
+                    version,
+                },
+                windows: windows as usize,
+            });
+        }
+        Ok(runs)
+    }
+
+    fn load_results(&self, run_id: i64, filter: &ResultFilter) -> Result<Vec<WindowResult>> {
+        let exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM pf_runs WHERE id = ?)",
+            [run_id],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            anyhow::bail!("no stored run with id {}", run_id);
+        }
+
+        let mut sql = String::from(
+            "SELECT market_id, platform, category, open_ts, close_ts, outcome,
+                    predicted, signal_offset_ms, bid_side, bid_price, shares, filled,
+                    queue_ahead_at_place, fill_time_ms, correct, realistic_pnl, naive_pnl,
+                    ref_price_open, ref_price_close, aborted, script_errors, rejected_orders,
+                    rejection, stake, bankroll, events
+             FROM pf_results WHERE run_id = ?",
+        );
+        let mut params: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(run_id)];
+
+        if let Some(ref c) = filter.category {
+            sql.push_str(" AND category = ?");
+            params.push(Box::new(c.clone()));
+        }
+        if let Some(ts) = filter.min_ts {
+            sql.push_str(" AND open_ts >= ?");
+            params.push(Box::new(ts));
+        }
+        if let Some(ts) = filter.max_ts {
+            sql.push_str(" AND close_ts <= ?");
+            params.push(Box::new(ts));
+        }
+
+        sql.push_str(" ORDER BY rowid");
+
+        let param_refs: Vec<&dyn rusqlite::types::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(param_refs.as_slice(), |row| {
+            Ok((WindowResult {
+                market_id: row.get(0)?,
+                platform: row.get(1)?,
+                category: row.get(2)?,
+                open_ts: row.get(3)?,
+                close_ts: row.get(4)?,
+                outcome: row.get(5)?,
+                predicted: row.get(6)?,
+                signal_offset_ms: row.get(7)?,
+                bid_side: row.get(8)?,
+                bid_price: row.get(9)?,
+                shares: row.get(10)?,
+                filled: row.get(11)?,
+                queue_ahead_at_place: row.get(12)?,
+                fill_time_ms: row.get(13)?,
+                correct: row.get(14)?,
+                realistic_pnl: row.get(15)?,
+                naive_pnl: row.get(16)?,
+                ref_price_open: row.get(17)?,
+                ref_price_close: row.get(18)?,
+                aborted: row.get(19)?,
+                script_errors: row.get::<_, i64>(20)? as usize,
+                rejected_orders: row.get::<_, i64>(21)? as usize,
+                rejection: row.get(22)?,
+                stake: row.get(23)?,
+                bankroll: row.get(24)?,
+                events: Vec::new(),
+            }, row.get::<_, Option<String>>(25)?))
+        })?;
+
+        let mut results = Vec::new();
+        for r in rows {
+            let (mut result, events) = r?;
+            if let Some(events) = events {
+                result.events = serde_json::from_str(&events)?;
+            }
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    fn loads_depth(&self) -> bool {
+        self.depth
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{BookTick, Market, Outcome, Platform, PriceLevel, Side};
+    use crate::types::{BookTick, Market, OrderEvent, OrderId, Outcome, Platform, PriceLevel, Side};
+    use smallvec::smallvec;
 
     fn setup() -> SqliteStore {
         let store = SqliteStore::in_memory().unwrap();
@@ -279,24 +1061,25 @@ mod tests {
             platform: Platform::Polymarket,
             description: format!("Test market {}", id),
             category: "btc".to_string(),
-            open_ts: 1000,
-            close_ts: 1300,
+            open_ts: UnixSecs::new(1000),
+            close_ts: UnixSecs::new(1300),
             duration_secs: 300,
             outcome: Some(Outcome::Yes),
+            outcome_ts: None,
         }
     }
 
     fn sample_tick(market_id: &str, side: Side, offset_ms: i64) -> BookTick {
         BookTick {
-            market_id: market_id.to_string(),
+            market_id: market_id.into(),
             side,
-            timestamp_ms: 1000_000 + offset_ms,
-            offset_ms,
+            timestamp_ms: 1_000_000 + offset_ms,
+            offset_ms: OffsetMs::new(offset_ms),
             best_bid: Some(0.49),
             best_bid_size: Some(100.0),
             best_ask: Some(0.51),
             best_ask_size: Some(200.0),
-            depth: vec![
+            depth: smallvec![
                 PriceLevel { price: 0.49, cumulative_size: 500.0 },
                 PriceLevel { price: 0.50, cumulative_size: 120.0 },
                 PriceLevel { price: 0.51, cumulative_size: 50.0 },
@@ -308,6 +1091,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_init_stamps_schema_version() {
+        let store = SqliteStore::in_memory().unwrap();
+        assert_eq!(store.schema_version().unwrap(), 0);
+        store.init().unwrap();
+        assert_eq!(store.schema_version().unwrap(), schema::SCHEMA_VERSION);
+
+        store.conn.pragma_update(None, "user_version", 1).unwrap();
+        store.init().unwrap();
+        assert_eq!(store.schema_version().unwrap(), schema::SCHEMA_VERSION);
+
+        store
+            .conn
+            .pragma_update(None, "user_version", schema::SCHEMA_VERSION + 1)
+            .unwrap();
+        let err = store.init().unwrap_err().to_string();
+        assert!(err.contains("newer than supported"), "{}", err);
+    }
+
+    #[test]
+    fn test_init_drops_replaced_indexes() {
+        let store = setup();
+        store
+            .conn
+            .execute_batch("CREATE INDEX idx_pf_ticks_market_side_offset ON pf_ticks(market_id, side, offset_ms);")
+            .unwrap();
+        store.init().unwrap();
+        assert!(!store.has_index("idx_pf_ticks_market_side_offset").unwrap());
+        assert!(store.has_index("idx_pf_ticks_market_offset_side").unwrap());
+    }
+
     #[test]
     fn test_insert_and_list_markets() {
         let store = setup();
@@ -345,6 +1159,37 @@ mod tests {
             .unwrap();
         assert_eq!(weather.len(), 1);
         assert_eq!(weather[0].platform, Platform::Kalshi);
+
+        // Filter by timeframe
+        let m4 = Market {
+            duration_secs: 900,
+            close_ts: UnixSecs::new(1900),
+            ..sample_market("market-4")
+        };
+        store.insert_market(&m4).unwrap();
+        let fifteen = store
+            .list_markets(&MarketFilter {
+                duration_secs: Some(900),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(fifteen.len(), 1);
+        assert_eq!(fifteen[0].id, "market-4");
+
+        // Venues without built-in support round-trip by name
+        let m3 = Market {
+            platform: Platform::from("Manifold"),
+            ..sample_market("market-3")
+        };
+        store.insert_market(&m3).unwrap();
+        let manifold = store
+            .list_markets(&MarketFilter {
+                platform: Some(Platform::Other("manifold".to_string())),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(manifold.len(), 1);
+        assert_eq!(manifold[0].platform.as_str(), "manifold");
     }
 
     #[test]
@@ -366,6 +1211,56 @@ mod tests {
         assert_eq!(loaded[2].offset_ms, 1000);
     }
 
+    #[test]
+    fn test_duplicate_ticks_are_ignored() {
+        let store = setup();
+        let ticks = vec![sample_tick("m1", Side::Yes, 0), sample_tick("m1", Side::No, 0)];
+        assert_eq!(store.insert_ticks(&ticks).unwrap(), 2);
+        assert_eq!(store.insert_ticks(&[ticks[0].clone(), sample_tick("m1", Side::Yes, 1000)]).unwrap(), 1);
+
+        let loaded = store.load_ticks("m1").unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded[0].depth.len(), 3);
+    }
+
+    #[test]
+    fn test_init_removes_duplicates_before_adding_the_unique_index() {
+        let store = setup();
+        store.conn.execute_batch("DROP INDEX idx_pf_ticks_unique").unwrap();
+        let tick = sample_tick("m1", Side::Yes, 0);
+        store.insert_ticks(&[tick.clone(), tick]).unwrap();
+        assert_eq!(store.load_ticks("m1").unwrap().len(), 2);
+
+        store.init().unwrap();
+        let loaded = store.load_ticks("m1").unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].depth.len(), 3);
+    }
+
+    #[test]
+    fn test_duplicate_trades_are_ignored() {
+        let store = setup();
+        let trade = |offset_ms: i64, size: f64| Trade {
+            market_id: "t1".into(),
+            side: Side::Yes,
+            timestamp_ms: 1_000_000 + offset_ms,
+            offset_ms: OffsetMs::new(offset_ms),
+            price: 0.49,
+            size,
+            taker_side: None,
+        };
+        store.insert_trades(&[trade(0, 25.0), trade(0, 10.0)]).unwrap();
+        store.insert_trades(&[trade(0, 25.0), trade(500, 25.0)]).unwrap();
+        assert_eq!(store.load_trades("t1").unwrap().len(), 3);
+
+        // Databases from before the index keep the first copy of each.
+        store.conn.execute_batch("DROP INDEX idx_pf_trades_unique").unwrap();
+        store.insert_trades(&[trade(0, 25.0), trade(500, 25.0)]).unwrap();
+        assert_eq!(store.load_trades("t1").unwrap().len(), 5);
+        store.init().unwrap();
+        assert_eq!(store.load_trades("t1").unwrap().len(), 3);
+    }
+
     #[test]
     fn test_depth_levels_roundtrip() {
         let store = setup();
@@ -384,20 +1279,151 @@ mod tests {
         assert!((loaded[0].depth[2].price - 0.51).abs() < 1e-9);
     }
 
+    #[test]
+    fn test_trades_roundtrip() {
+        let store = setup();
+        let trade = |offset_ms: i64, price: f64, taker_side| Trade {
+            market_id: "t1".into(),
+            side: Side::Yes,
+            timestamp_ms: 1_000_000 + offset_ms,
+            offset_ms: OffsetMs::new(offset_ms),
+            price,
+            size: 25.0,
+            taker_side,
+        };
+        store
+            .insert_trades(&[trade(900, 0.49, Some(TakerSide::Sell)), trade(300, 0.50, None)])
+            .unwrap();
+
+        let loaded = store.load_trades("t1").unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].offset_ms, OffsetMs::new(300));
+        assert_eq!(loaded[0].taker_side, None);
+        assert_eq!(loaded[1].taker_side, Some(TakerSide::Sell));
+        assert_eq!(loaded[1].side, Side::Yes);
+        assert!(store.load_trades("other").unwrap().is_empty());
+
+        // Databases from before the tape have no table at all.
+        store.conn().execute_batch("DROP TABLE pf_trades").unwrap();
+        assert!(store.load_trades("t1").unwrap().is_empty());
+    }
+
+    /// `EXPLAIN QUERY PLAN` details for `sql`, one line per step.
+    fn query_plan(store: &SqliteStore, sql: &str) -> String {
+        let mut stmt = store.conn().prepare(&format!("EXPLAIN QUERY PLAN {}", sql)).unwrap();
+        let params = rusqlite::params_from_iter(std::iter::repeat_n("m1", stmt.parameter_count()));
+        let details = stmt.query_map(params, |row| row.get::<_, String>(3)).unwrap();
+        details.map(|d| d.unwrap()).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn test_replay_queries_use_covering_indexes() {
+        let store = setup();
+        for i in 0..20 {
+            let m = sample_market(&format!("m{}", i));
+            store.insert_market(&m).unwrap();
+            let ticks: Vec<BookTick> = (0..50)
+                .flat_map(|j| [Side::Yes, Side::No].map(|side| sample_tick(&m.id, side, j * 100)))
+                .collect();
+            store.insert_ticks(&ticks).unwrap();
+        }
+        store.analyze().unwrap();
+        store
+            .conn()
+            .execute_batch("CREATE TEMP TABLE pf_stream_order (ord INTEGER PRIMARY KEY, market_id TEXT NOT NULL);")
+            .unwrap();
+
+        let ticks = query_plan(&store, schema::LOAD_TICKS);
+        assert!(ticks.contains("USING INDEX idx_pf_ticks_market_offset_side (market_id=?)"), "{}", ticks);
+        let depth = query_plan(&store, schema::LOAD_DEPTH_LEVELS);
+        assert!(depth.contains("USING COVERING INDEX idx_pf_depth_tick_levels (tick_id=?)"), "{}", depth);
+        let stream = query_plan(&store, &store.stream_sql());
+        assert!(stream.contains("USING INDEX idx_pf_ticks_market_offset_side (market_id=?)"), "{}", stream);
+        assert!(stream.contains("USING COVERING INDEX idx_pf_depth_tick_levels (tick_id=?)"), "{}", stream);
+        for plan in [&ticks, &stream] {
+            assert!(!plan.contains("TEMP B-TREE"), "{}", plan);
+        }
+    }
+
+    #[test]
+    fn test_skip_depth_levels() {
+        let store = setup().with_depth(false);
+        assert!(!store.loads_depth());
+        let m = sample_market("d1");
+        store.insert_market(&m).unwrap();
+        store.insert_ticks(&[sample_tick("d1", Side::Yes, 0), sample_tick("d1", Side::No, 0)]).unwrap();
+
+        let loaded = store.load_ticks("d1").unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert!(loaded.iter().all(|t| t.depth.is_empty() && t.best_bid.is_some()));
+
+        let mut streamed = Vec::new();
+        store.stream_ticks(&[m], &mut |ticks| streamed = ticks("d1").unwrap()).unwrap();
+        assert_eq!(streamed.len(), 2);
+        assert!(streamed.iter().all(|t| t.depth.is_empty()));
+    }
+
+    #[test]
+    fn test_load_ticks_beyond_parameter_limit() {
+        // More ticks than SQLite's default host-parameter limit (32766).
+        let store = setup();
+        store.insert_market(&sample_market("big")).unwrap();
+        let ticks: Vec<BookTick> = (0..33_000).map(|i| sample_tick("big", Side::Yes, i)).collect();
+        store.insert_ticks(&ticks).unwrap();
+
+        let loaded = store.load_ticks("big").unwrap();
+        assert_eq!(loaded.len(), 33_000);
+        assert!(loaded.iter().all(|t| t.depth.len() == 3));
+    }
+
+    #[test]
+    fn test_stream_ticks_matches_load_ticks() {
+        let store = setup();
+        let markets: Vec<Market> = ["s1", "s2", "s3", "s4"].iter().map(|id| sample_market(id)).collect();
+        for m in &markets {
+            store.insert_market(m).unwrap();
+        }
+        // s3 has no ticks; s1's depth-less tick sorts between depth ticks.
+        let mut bare = sample_tick("s1", Side::No, 0);
+        bare.depth.clear();
+        store
+            .insert_ticks(&[
+                sample_tick("s2", Side::Yes, 0),
+                sample_tick("s1", Side::Yes, 1000),
+                bare,
+                sample_tick("s1", Side::Yes, 0),
+                sample_tick("s4", Side::No, 0),
+            ])
+            .unwrap();
+        let json = |ticks: Vec<BookTick>| serde_json::to_string(&ticks).unwrap();
+
+        let mut calls = 0;
+        store
+            .stream_ticks(&markets, &mut |ticks| {
+                calls += 1;
+                for id in ["s1", "s3", "s4", "s2", "other"] {
+                    assert_eq!(json(ticks(id).unwrap()), json(store.load_ticks(id).unwrap()), "{}", id);
+                }
+            })
+            .unwrap();
+        assert_eq!(calls, 1);
+        assert_eq!(store.load_ticks("s1").unwrap().len(), 3);
+    }
+
     #[test]
     fn test_market_filter_by_timestamp() {
         let store = setup();
         store
             .insert_market(&Market {
-                open_ts: 100,
-                close_ts: 400,
+                open_ts: UnixSecs::new(100),
+                close_ts: UnixSecs::new(400),
                 ..sample_market("early")
             })
             .unwrap();
         store
             .insert_market(&Market {
-                open_ts: 500,
-                close_ts: 800,
+                open_ts: UnixSecs::new(500),
+                close_ts: UnixSecs::new(800),
                 ..sample_market("late")
             })
             .unwrap();
@@ -431,6 +1457,58 @@ mod tests {
         assert!(markets.is_empty());
     }
 
+    #[test]
+    fn test_millisecond_market_times_fail_to_load() {
+        let store = setup();
+        store.insert_market(&sample_market("m1")).unwrap();
+        store
+            .conn()
+            .execute("UPDATE pf_markets SET open_ts = 1705314600000", [])
+            .unwrap();
+        let err = store.list_markets(&MarketFilter::default()).unwrap_err();
+        assert!(format!("{:#}", err).contains("not in Unix seconds"));
+    }
+
+    fn numbered_markets(n: i64) -> Vec<Market> {
+        (0..n)
+            .map(|i| Market {
+                open_ts: UnixSecs::new(i * 300),
+                close_ts: UnixSecs::new(i * 300 + 300),
+                ..sample_market(&format!("m{}", i))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_select_markets_limit() {
+        let selected = select_markets(numbered_markets(10), Some(3), None, None);
+        let ids: Vec<&str> = selected.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, vec!["m0", "m1", "m2"]);
+    }
+
+    #[test]
+    fn test_select_markets_sample_is_seeded_and_ordered() {
+        let a = select_markets(numbered_markets(50), None, Some(10), Some(7));
+        let b = select_markets(numbered_markets(50), None, Some(10), Some(7));
+        assert_eq!(a.len(), 10);
+        let ids_a: Vec<&str> = a.iter().map(|m| m.id.as_str()).collect();
+        let ids_b: Vec<&str> = b.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids_a, ids_b, "same seed must pick the same subset");
+        assert!(a.windows(2).all(|w| w[0].open_ts < w[1].open_ts));
+    }
+
+    #[test]
+    fn test_select_markets_sample_larger_than_input() {
+        let selected = select_markets(numbered_markets(4), None, Some(10), Some(1));
+        assert_eq!(selected.len(), 4);
+    }
+
+    #[test]
+    fn test_select_markets_sample_then_limit() {
+        let selected = select_markets(numbered_markets(50), Some(2), Some(10), Some(3));
+        assert_eq!(selected.len(), 2);
+    }
+
     #[test]
     fn test_market_upsert() {
         let store = setup();
@@ -445,5 +1523,137 @@ mod tests {
         let loaded = store.list_markets(&MarketFilter::default()).unwrap();
         assert_eq!(loaded.len(), 1);
         assert_eq!(loaded[0].outcome, Some(Outcome::No));
+
+        // A voided market stays void rather than reading back as NO.
+        m.outcome = Some(Outcome::Void);
+        store.insert_market(&m).unwrap();
+        let loaded = store.list_markets(&MarketFilter::default()).unwrap();
+        assert_eq!(loaded[0].outcome, Some(Outcome::Void));
+        assert_eq!(loaded[0].outcome_ts, None);
+
+        m.outcome_ts = Some(m.close_ts + 45);
+        store.insert_market(&m).unwrap();
+        let loaded = store.list_markets(&MarketFilter::default()).unwrap();
+        assert_eq!(loaded[0].outcome_ts, Some(m.close_ts + 45));
+    }
+
+    fn sample_result(market_id: &str, category: &str, open_ts: i64) -> WindowResult {
+        WindowResult {
+            market_id: market_id.into(),
+            platform: "polymarket".into(),
+            category: category.into(),
+            open_ts,
+            close_ts: open_ts + 300,
+            outcome: "YES".into(),
+            predicted: Some("YES".into()),
+            signal_offset_ms: Some(90_000),
+            bid_side: Some("YES".into()),
+            bid_price: 0.49,
+            shares: 10.0,
+            filled: true,
+            queue_ahead_at_place: 120.0,
+            fill_time_ms: None,
+            correct: true,
+            realistic_pnl: 5.1,
+            naive_pnl: 5.1,
+            ref_price_open: None,
+            ref_price_close: Some(66_100.0),
+            aborted: Some("budget".into()),
+            script_errors: 2,
+            rejected_orders: 1,
+            rejection: Some("4 shares below the 5 minimum".into()),
+            stake: 4.9,
+            bankroll: Some(105.1),
+            events: vec![
+                OrderEvent::Placed {
+                    order_id: OrderId::new(1),
+                    side: Side::Yes,
+                    price: 0.49,
+                    shares: 10.0,
+                    offset_ms: 1_000,
+                    queue_ahead: 120.0,
+                    taker: false,
+                    sell: false,
+                },
+                OrderEvent::Filled {
+                    order_id: OrderId::new(1),
+                    side: Side::Yes,
+                    price: 0.49,
+                    shares: 10.0,
+                    offset_ms: 61_000,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_results_roundtrip_and_filter() {
+        let store = setup();
+        let spec = StrategySpec {
+            name: "momentum".into(),
+            params: [("bid_price".to_string(), 0.49.into())].into(),
+        };
+        let meta = RunMeta::new(spec, "delise", Some(u64::MAX));
+        let results = vec![
+            sample_result("a", "btc", 1000),
+            sample_result("b", "eth", 2000),
+            sample_result("c", "btc", 3000),
+        ];
+        let first = store.insert_results(&meta, &results).unwrap();
+        let second = store.insert_results(&meta, &results[..1]).unwrap();
+
+        let runs = store.list_runs().unwrap();
+        assert_eq!(runs.iter().map(|r| (r.id, r.windows)).collect::<Vec<_>>(), [(first, 3), (second, 1)]);
+        assert_eq!(runs[0].meta, meta);
+        assert_eq!(store.load_run(second).unwrap().unwrap().windows, 1);
+
+        let all = store.load_results(first, &ResultFilter::default()).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].aborted.as_deref(), Some("budget"));
+        assert_eq!(all[0].script_errors, 2);
+        assert_eq!(all[0].rejected_orders, 1);
+        assert_eq!(all[0].rejection.as_deref(), Some("4 shares below the 5 minimum"));
+        assert_eq!(all[0].events, sample_result("a", "btc", 1000).events);
+        assert!(all[0].filled && all[0].fill_time_ms.is_none());
+
+        let filter = ResultFilter {
+            category: Some("btc".into()),
+            min_ts: Some(2000),
+            ..ResultFilter::default()
+        };
+        let btc = store.load_results(first, &filter).unwrap();
+        assert_eq!(btc.iter().map(|r| r.market_id.as_str()).collect::<Vec<_>>(), ["c"]);
+        assert!(store.load_results(99, &ResultFilter::default()).is_err());
+    }
+
+    #[test]
+    fn test_init_adds_new_columns_to_older_databases() {
+        let store = setup();
+        store
+            .conn
+            .execute_batch(
+                "ALTER TABLE pf_results DROP COLUMN events;
+                 ALTER TABLE pf_results DROP COLUMN bankroll;
+                 ALTER TABLE pf_results DROP COLUMN stake;
+                 ALTER TABLE pf_results DROP COLUMN rejection;
+                 ALTER TABLE pf_results DROP COLUMN rejected_orders;
+                 ALTER TABLE pf_markets DROP COLUMN outcome_ts;
+                 ALTER TABLE pf_runs DROP COLUMN version;",
+            )
+            .unwrap();
+        store.init().unwrap();
+        store.insert_market(&sample_market("a")).unwrap();
+        assert_eq!(store.list_markets(&MarketFilter::default()).unwrap().len(), 1);
+        let spec = StrategySpec {
+            name: "momentum".into(),
+            params: Default::default(),
+        };
+        let meta = RunMeta::new(spec, "delise", None);
+        let run = store.insert_results(&meta, &[sample_result("a", "btc", 1000)]).unwrap();
+        let loaded = store.load_results(run, &ResultFilter::default()).unwrap();
+        assert_eq!(loaded[0].rejected_orders, 1);
+        assert_eq!(loaded[0].bankroll, Some(105.1));
+        assert_eq!(loaded[0].events.len(), 2);
+        assert_eq!(store.list_runs().unwrap()[0].meta.version, crate::VERSION);
     }
 }
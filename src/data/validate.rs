@@ -0,0 +1,344 @@
+//! Data quality checks over stored ticks (`pf validate`).
+//!
+//! Capture pipelines drop connections, reorder messages and occasionally
+//! record garbage. A window with a crossed book or a five-minute hole in
+//! it replays, but its results say more about the capture than about the
+//! strategy. [`validate_store`] checks every market's ticks and reports,
+//! per market, what it found; [`write_market_ids`] saves the flagged ids
+//! as an exclusion list for `pf run --exclude-file`.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::types::{BookTick, Market, OffsetMs, Side};
+
+use super::store::DataStore;
+
+/// Thresholds for [`validate_market`].
+#[derive(Debug, Clone)]
+pub struct ValidateConfig {
+    /// Longest stretch without a tick on either side before it counts as a
+    /// gap.
+    pub max_gap_ms: i64,
+}
+
+impl Default for ValidateConfig {
+    fn default() -> Self {
+        Self { max_gap_ms: 60_000 }
+    }
+}
+
+/// What's wrong with a market's ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum IssueKind {
+    /// No ticks at all.
+    NoTicks,
+    /// Ticks on only one side.
+    MissingSide,
+    /// A tick timestamped earlier than the one before it on the same side,
+    /// though its offset is later.
+    OutOfOrder,
+    /// Two ticks on the same side at the same offset.
+    DuplicateOffset,
+    /// Best bid above best ask.
+    CrossedBook,
+    /// A price outside `[0, 1]`.
+    PriceOutOfRange,
+    /// No tick on either side for longer than [`ValidateConfig::max_gap_ms`].
+    Gap,
+}
+
+impl IssueKind {
+    pub fn label(&self) -> &str {
+        match self {
+            IssueKind::NoTicks => "no ticks",
+            IssueKind::MissingSide => "missing side",
+            IssueKind::OutOfOrder => "out-of-order timestamps",
+            IssueKind::DuplicateOffset => "duplicate offsets",
+            IssueKind::CrossedBook => "crossed book",
+            IssueKind::PriceOutOfRange => "price outside [0, 1]",
+            IssueKind::Gap => "time gap",
+        }
+    }
+}
+
+/// One kind of problem found in a market, on one side (or both, for gaps
+/// and missing ticks).
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub side: Option<Side>,
+    /// Ticks (or gaps) affected.
+    pub count: usize,
+    /// Where it first happens.
+    pub first_offset: Option<OffsetMs>,
+    /// The longest gap, for [`IssueKind::Gap`].
+    pub longest_ms: Option<i64>,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind.label())?;
+        if let Some(side) = self.side {
+            write!(f, " on {}", side)?;
+        }
+        if self.kind != IssueKind::NoTicks && self.kind != IssueKind::MissingSide {
+            write!(f, ": {}", self.count)?;
+        }
+        if let Some(ms) = self.longest_ms {
+            write!(f, ", longest {:.1}s", ms as f64 / 1000.0)?;
+        }
+        if let Some(offset) = self.first_offset {
+            write!(f, " (first at {:.1}s)", offset.secs_f64())?;
+        }
+        Ok(())
+    }
+}
+
+/// The issues found in one market; empty if it's clean.
+#[derive(Debug, Clone)]
+pub struct MarketReport {
+    pub market_id: String,
+    pub ticks: usize,
+    pub issues: Vec<Issue>,
+}
+
+impl MarketReport {
+    pub fn is_clean(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Counts one kind of issue on one side.
+struct Tally {
+    kind: IssueKind,
+    side: Option<Side>,
+    count: usize,
+    first: Option<OffsetMs>,
+    longest: Option<i64>,
+}
+
+impl Tally {
+    fn new(kind: IssueKind, side: Option<Side>) -> Self {
+        Self { kind, side, count: 0, first: None, longest: None }
+    }
+
+    fn hit(&mut self, offset: OffsetMs) {
+        self.count += 1;
+        self.first.get_or_insert(offset);
+    }
+
+    fn into_issue(self) -> Option<Issue> {
+        (self.count > 0).then_some(Issue {
+            kind: self.kind,
+            side: self.side,
+            count: self.count,
+            first_offset: self.first,
+            longest_ms: self.longest,
+        })
+    }
+}
+
+/// Check one market's ticks, in the order [`DataStore::load_ticks`]
+/// returns them (by offset).
+pub fn validate_market(market: &Market, ticks: &[BookTick], config: &ValidateConfig) -> MarketReport {
+    let mut report = MarketReport {
+        market_id: market.id.clone(),
+        ticks: ticks.len(),
+        issues: Vec::new(),
+    };
+    if ticks.is_empty() {
+        report.issues.push(Issue {
+            kind: IssueKind::NoTicks,
+            side: None,
+            count: 0,
+            first_offset: None,
+            longest_ms: None,
+        });
+        return report;
+    }
+
+    for side in [Side::Yes, Side::No] {
+        let side_ticks: Vec<&BookTick> = ticks.iter().filter(|t| t.side == side).collect();
+        if side_ticks.is_empty() {
+            report.issues.push(Issue {
+                kind: IssueKind::MissingSide,
+                side: Some(side),
+                count: 0,
+                first_offset: None,
+                longest_ms: None,
+            });
+            continue;
+        }
+
+        let mut out_of_order = Tally::new(IssueKind::OutOfOrder, Some(side));
+        let mut duplicate = Tally::new(IssueKind::DuplicateOffset, Some(side));
+        let mut crossed = Tally::new(IssueKind::CrossedBook, Some(side));
+        let mut out_of_range = Tally::new(IssueKind::PriceOutOfRange, Some(side));
+        let mut previous: Option<&BookTick> = None;
+        for tick in side_ticks {
+            if let Some(prev) = previous {
+                if tick.offset_ms == prev.offset_ms {
+                    duplicate.hit(tick.offset_ms);
+                } else if tick.timestamp_ms < prev.timestamp_ms {
+                    out_of_order.hit(tick.offset_ms);
+                }
+            }
+            if let (Some(bid), Some(ask)) = (tick.best_bid, tick.best_ask) {
+                if bid > ask {
+                    crossed.hit(tick.offset_ms);
+                }
+            }
+            let prices = [tick.best_bid, tick.best_ask]
+                .into_iter()
+                .flatten()
+                .chain(tick.depth.iter().map(|l| l.price));
+            if prices.into_iter().any(|p| !(0.0..=1.0).contains(&p)) {
+                out_of_range.hit(tick.offset_ms);
+            }
+            previous = Some(tick);
+        }
+        report
+            .issues
+            .extend([out_of_order, duplicate, crossed, out_of_range].into_iter().filter_map(Tally::into_issue));
+    }
+
+    let mut gaps = Tally::new(IssueKind::Gap, None);
+    for pair in ticks.windows(2) {
+        let gap = pair[1].offset_ms.ms() - pair[0].offset_ms.ms();
+        if gap > config.max_gap_ms {
+            gaps.hit(pair[0].offset_ms);
+            gaps.longest = Some(gaps.longest.unwrap_or(0).max(gap));
+        }
+    }
+    report.issues.extend(gaps.into_issue());
+    report
+}
+
+/// Check every market in `markets`, streaming their ticks from `store`.
+pub fn validate_store(store: &dyn DataStore, markets: &[Market], config: &ValidateConfig) -> Result<Vec<MarketReport>> {
+    let mut reports = Vec::with_capacity(markets.len());
+    let mut result: Result<()> = Ok(());
+    store.stream_ticks(markets, &mut |ticks| {
+        result = markets.iter().try_for_each(|m| {
+            reports.push(validate_market(m, &ticks(&m.id)?, config));
+            Ok(())
+        });
+    })?;
+    result?;
+    Ok(reports)
+}
+
+/// Write `ids` one per line, the format [`read_market_ids`] reads.
+pub fn write_market_ids<'a>(path: &Path, ids: impl IntoIterator<Item = &'a str>) -> Result<()> {
+    let mut text = String::new();
+    for id in ids {
+        text.push_str(id);
+        text.push('\n');
+    }
+    std::fs::write(path, text).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Read a list of market ids, one per line. Blank lines and `#` comments
+/// are ignored.
+pub fn read_market_ids(path: &Path) -> Result<HashSet<String>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|id| !id.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Platform, PriceLevel, UnixSecs};
+
+    fn market() -> Market {
+        Market {
+            id: "m1".into(),
+            platform: Platform::Polymarket,
+            description: String::new(),
+            category: "btc".into(),
+            open_ts: UnixSecs::new(0),
+            close_ts: UnixSecs::new(900),
+            duration_secs: 900,
+            outcome: None,
+            outcome_ts: None,
+        }
+    }
+
+    fn tick(side: Side, offset_ms: i64, timestamp_ms: i64, bid: f64, ask: f64) -> BookTick {
+        BookTick {
+            market_id: "m1".into(),
+            side,
+            timestamp_ms,
+            offset_ms: OffsetMs::new(offset_ms),
+            best_bid: Some(bid),
+            best_bid_size: Some(10.0),
+            best_ask: Some(ask),
+            best_ask_size: Some(10.0),
+            depth: [PriceLevel { price: bid, cumulative_size: 10.0 }].into_iter().collect(),
+            total_bid_depth: 10.0,
+            total_ask_depth: 10.0,
+            reference_price: None,
+            oracle_price: None,
+        }
+    }
+
+    fn kinds(report: &MarketReport) -> Vec<(IssueKind, Option<Side>, usize)> {
+        report.issues.iter().map(|i| (i.kind, i.side, i.count)).collect()
+    }
+
+    #[test]
+    fn test_clean_market() {
+        let ticks = vec![
+            tick(Side::No, 0, 0, 0.49, 0.51),
+            tick(Side::Yes, 0, 0, 0.49, 0.51),
+            tick(Side::No, 1000, 1000, 0.48, 0.52),
+            tick(Side::Yes, 1000, 1000, 0.48, 0.52),
+        ];
+        let report = validate_market(&market(), &ticks, &ValidateConfig::default());
+        assert!(report.is_clean(), "{:?}", report);
+    }
+
+    #[test]
+    fn test_detects_each_issue() {
+        let ticks = vec![
+            tick(Side::Yes, 0, 5000, 0.49, 0.51),
+            tick(Side::Yes, 1000, 1000, 0.49, 0.51), // timestamp went backwards
+            tick(Side::Yes, 1000, 1100, 0.49, 0.51), // same offset
+            tick(Side::Yes, 2000, 2000, 0.55, 0.51), // crossed
+            tick(Side::Yes, 120_000, 120_000, 1.2, 1.3), // after a gap, out of range
+        ];
+        let report = validate_market(&market(), &ticks, &ValidateConfig::default());
+        assert_eq!(
+            kinds(&report),
+            vec![
+                (IssueKind::OutOfOrder, Some(Side::Yes), 1),
+                (IssueKind::DuplicateOffset, Some(Side::Yes), 1),
+                (IssueKind::CrossedBook, Some(Side::Yes), 1),
+                (IssueKind::PriceOutOfRange, Some(Side::Yes), 1),
+                (IssueKind::MissingSide, Some(Side::No), 0),
+                (IssueKind::Gap, None, 1),
+            ]
+        );
+        let gap = report.issues.last().unwrap();
+        assert_eq!(gap.to_string(), "time gap: 1, longest 118.0s (first at 2.0s)");
+    }
+
+    #[test]
+    fn test_market_id_lists_roundtrip() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("exclude.txt");
+        write_market_ids(&path, ["a", "b"]).unwrap();
+        std::fs::write(&path, std::fs::read_to_string(&path).unwrap() + "# outage\n\nc  # flaky\n").unwrap();
+        let ids = read_market_ids(&path).unwrap();
+        assert_eq!(ids, ["a", "b", "c"].into_iter().map(String::from).collect());
+    }
+}
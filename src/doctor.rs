@@ -0,0 +1,309 @@
+//! Environment and data sanity checks behind `pf doctor`.
+
+use std::path::Path;
+use std::time::Duration;
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::data::polymarket::PolymarketStore;
+use crate::data::schema::SCHEMA_VERSION;
+
+/// Network endpoints the importers depend on: label, URL and the fix to
+/// suggest when it can't be reached.
+pub const ENDPOINTS: &[(&str, &str, &str)] = &[
+    (
+        "Binance",
+        "https://api.binance.com/api/v3/ping",
+        "check connectivity/proxy settings, or import with --no-oracle",
+    ),
+    (
+        "Polymarket Gamma",
+        "https://gamma-api.polymarket.com/markets?limit=1",
+        "check connectivity/proxy settings; market metadata can't be fetched until it answers",
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn label(&self) -> &str {
+        match self {
+            CheckStatus::Ok => "OK",
+            CheckStatus::Warn => "WARN",
+            CheckStatus::Fail => "FAIL",
+        }
+    }
+}
+
+/// Outcome of a single check, with a suggested fix when it did not pass.
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn problem(
+        status: CheckStatus,
+        name: impl Into<String>,
+        detail: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            status,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Look for the capture database at its default location.
+pub fn check_default_capture_db() -> Check {
+    let name = "default capture DB";
+    match PolymarketStore::default_path() {
+        Ok(path) if path.exists() => check_database(&path),
+        Ok(path) => Check::problem(
+            CheckStatus::Warn,
+            name,
+            format!("{} not found", path.display()),
+            "pass --db to `pf run`, or import data with `pf import` / `pf-hf-import`",
+        ),
+        Err(e) => Check::problem(CheckStatus::Warn, name, e.to_string(), "set HOME"),
+    }
+}
+
+/// Identify a database as native or capture format and check its schema.
+pub fn check_database(path: &Path) -> Check {
+    let name = format!("database {}", path.display());
+    if !path.exists() {
+        return Check::problem(
+            CheckStatus::Fail,
+            name,
+            "file does not exist",
+            "check the path",
+        );
+    }
+
+    let conn = match Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY) {
+        Ok(c) => c,
+        Err(e) => {
+            return Check::problem(
+                CheckStatus::Fail,
+                name,
+                format!("cannot open: {}", e),
+                "check file permissions and that it is a SQLite database",
+            )
+        }
+    };
+
+    let has = |kind: &str, name: &str| -> bool {
+        conn.query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = ? AND name = ?",
+            [kind, name],
+            |_| Ok(()),
+        )
+        .is_ok()
+    };
+    let has_table = |table: &str| has("table", table);
+
+    if has_table("pf_markets") && has_table("pf_ticks") && has_table("pf_depth_levels") {
+        let version: i64 = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap_or(0);
+        return match version {
+            v if v == SCHEMA_VERSION
+                && !(has("index", "idx_pf_ticks_market_offset_side")
+                    && has("index", "idx_pf_ticks_unique")
+                    && has("index", "idx_pf_trades_unique")) =>
+            {
+                Check::problem(
+                    CheckStatus::Warn,
+                    name,
+                    format!("native schema v{} without the replay indexes or the uniqueness indexes", v),
+                    "re-run any import into this database to add them",
+                )
+            }
+            v if v == SCHEMA_VERSION => {
+                Check::ok(name, format!("native schema v{} (use --native)", v))
+            }
+            0 => Check::problem(
+                CheckStatus::Warn,
+                name,
+                "native schema, unversioned",
+                "re-run any import into this database to stamp the schema version",
+            ),
+            v if v > SCHEMA_VERSION => Check::problem(
+                CheckStatus::Fail,
+                name,
+                format!("native schema v{} is newer than supported v{}", v, SCHEMA_VERSION),
+                "upgrade phantomfill",
+            ),
+            v => Check::problem(
+                CheckStatus::Warn,
+                name,
+                format!("native schema v{} is older than v{}", v, SCHEMA_VERSION),
+                "re-run any import into this database to upgrade it in place",
+            ),
+        };
+    }
+
+    if has_table("book_ticks") {
+        return Check::ok(name, "capture database (book_ticks)");
+    }
+
+    Check::problem(
+        CheckStatus::Fail,
+        name,
+        "neither a PhantomFill nor a capture database",
+        "create one with `pf import` or `pf-hf-import`",
+    )
+}
+
+/// Verify that files can be created in `dir`.
+pub fn check_write_access(dir: &Path) -> Check {
+    let name = format!("write access {}", dir.display());
+    let probe = dir.join(format!(".pf-doctor-{}", std::process::id()));
+    match std::fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            Check::ok(name, "writable")
+        }
+        Err(e) => Check::problem(
+            CheckStatus::Fail,
+            name,
+            e.to_string(),
+            "create the directory or choose another output path",
+        ),
+    }
+}
+
+/// Probe every one of [`ENDPOINTS`].
+pub fn check_network() -> Vec<Check> {
+    probe_endpoints(ENDPOINTS, check_endpoint)
+}
+
+fn probe_endpoints(endpoints: &[(&str, &str, &str)], probe: impl Fn(&str, &str, &str) -> Check) -> Vec<Check> {
+    endpoints.iter().map(|&(label, url, fix)| probe(label, url, fix)).collect()
+}
+
+/// Issue a GET against `url` and report whether it answered, suggesting
+/// `fix` if not.
+pub fn check_endpoint(label: &str, url: &str, fix: &str) -> Check {
+    let name = format!("network {}", label);
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(5))
+        .build();
+    match agent.get(url).call() {
+        Ok(resp) => Check::ok(name, format!("{} ({})", url, resp.status())),
+        Err(e) => Check::problem(CheckStatus::Fail, name, e.to_string(), fix),
+    }
+}
+
+/// Print checks as an aligned table followed by suggested fixes.
+pub fn print_checks(checks: &[Check]) {
+    println!();
+    for c in checks {
+        println!("  [{:<4}] {:<40} {}", c.status.label(), c.name, c.detail);
+        if let Some(ref fix) = c.fix {
+            println!("         fix: {}", fix);
+        }
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{DataStore, SqliteStore};
+
+    #[test]
+    fn test_network_probes_every_endpoint() {
+        let checks = probe_endpoints(ENDPOINTS, |label, url, fix| {
+            Check::problem(CheckStatus::Fail, format!("network {}", label), url, fix)
+        });
+        let names: Vec<&str> = checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["network Binance", "network Polymarket Gamma"]);
+        assert!(checks[0].fix.as_deref().unwrap().contains("--no-oracle"));
+        assert!(checks[1].detail.contains("gamma-api.polymarket.com"));
+    }
+
+    #[test]
+    fn test_check_database_native() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("pf.db");
+        SqliteStore::open(&path).unwrap().init().unwrap();
+
+        let check = check_database(&path);
+        assert_eq!(check.status, CheckStatus::Ok, "{:?}", check);
+        assert!(check.detail.contains("native"));
+
+        Connection::open(&path)
+            .unwrap()
+            .execute_batch("DROP INDEX idx_pf_ticks_market_offset_side;")
+            .unwrap();
+        let check = check_database(&path);
+        assert_eq!(check.status, CheckStatus::Warn, "{:?}", check);
+        assert!(check.detail.contains("replay indexes"));
+
+        let conn = Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION - 1).unwrap();
+        let check = check_database(&path);
+        assert_eq!(check.status, CheckStatus::Warn, "{:?}", check);
+        assert!(check.detail.contains("older"), "{:?}", check);
+
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1).unwrap();
+        let check = check_database(&path);
+        assert_eq!(check.status, CheckStatus::Fail, "{:?}", check);
+        assert!(check.detail.contains("newer"), "{:?}", check);
+    }
+
+    #[test]
+    fn test_check_database_capture_and_unknown() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let capture = tmp.path().join("capture.db");
+        Connection::open(&capture)
+            .unwrap()
+            .execute_batch("CREATE TABLE book_ticks (slug TEXT);")
+            .unwrap();
+        assert_eq!(check_database(&capture).status, CheckStatus::Ok);
+
+        let other = tmp.path().join("other.db");
+        Connection::open(&other)
+            .unwrap()
+            .execute_batch("CREATE TABLE foo (x INTEGER);")
+            .unwrap();
+        assert_eq!(check_database(&other).status, CheckStatus::Fail);
+
+        let missing = check_database(&tmp.path().join("missing.db"));
+        assert_eq!(missing.status, CheckStatus::Fail);
+        assert!(missing.fix.is_some());
+    }
+
+    #[test]
+    fn test_check_write_access() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        assert_eq!(check_write_access(tmp.path()).status, CheckStatus::Ok);
+        assert_eq!(
+            check_write_access(&tmp.path().join("nope")).status,
+            CheckStatus::Fail
+        );
+        assert_eq!(std::fs::read_dir(tmp.path()).unwrap().count(), 0);
+    }
+}
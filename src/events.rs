@@ -0,0 +1,379 @@
+//! Engine events for live dashboards.
+//!
+//! Attach an [`EventBus`] to a [`ReplayEngine`](crate::replay::ReplayEngine)
+//! (or a [`BacktestBuilder`](crate::backtest::BacktestBuilder)) and it
+//! reports each order's lifecycle, finished windows and run progress as
+//! they happen.
+//! Subscribe with a callback, or take a channel to drain from another
+//! thread:
+//!
+//! ```no_run
+//! # fn example(store: &phantomfill::data::SqliteStore) -> anyhow::Result<()> {
+//! use phantomfill::backtest::BacktestBuilder;
+//! use phantomfill::events::{EngineEvent, EventBus};
+//! use phantomfill::strategies::{create_strategy, StrategyParams};
+//!
+//! let bus = EventBus::new();
+//! let events = bus.channel();
+//! std::thread::spawn(move || {
+//!     for event in events {
+//!         if let EngineEvent::Progress { markets_done, markets_total } = event {
+//!             eprint!("\r{}/{}", markets_done, markets_total);
+//!         }
+//!     }
+//! });
+//! BacktestBuilder::new()
+//!     .store(store)
+//!     .strategy(|| create_strategy("momentum", &StrategyParams::default()).unwrap())
+//!     .events(bus)
+//!     .run()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::fill::FillCheck;
+use crate::report::Report;
+use crate::types::{OrderId, Side, WindowResult};
+
+/// Something the engine did. Offsets are milliseconds since window open.
+/// Order events carry the order's id, unique within its window.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EngineEvent {
+    /// The engine accepted a bid from the strategy.
+    OrderPlaced {
+        market_id: String,
+        order_id: OrderId,
+        side: Side,
+        price: f64,
+        shares: f64,
+        offset_ms: i64,
+        /// Estimated size ahead in the queue when the bid was placed.
+        queue_ahead: f64,
+        /// Placed with [`Action::TakeAsk`](crate::types::Action::TakeAsk):
+        /// filled as far as the asks allow at once and the rest cancelled.
+        /// `price` is its average price if it bought anything.
+        taker: bool,
+        /// Placed with [`Action::Sell`](crate::types::Action::Sell): sells
+        /// shares bought on `side` at `price` (its average price, if it
+        /// took from the bids).
+        sell: bool,
+    },
+    /// The engine refused a bid that breaks the venue's order rules.
+    OrderRejected {
+        market_id: String,
+        side: Side,
+        price: f64,
+        shares: f64,
+        offset_ms: i64,
+        reason: String,
+    },
+    /// The fill model filled part of a resting bid; `shares` is this fill
+    /// and `remaining` what is left to fill.
+    OrderPartiallyFilled {
+        market_id: String,
+        order_id: OrderId,
+        side: Side,
+        price: f64,
+        shares: f64,
+        remaining: f64,
+        offset_ms: i64,
+    },
+    /// A bid filled in full; `shares` is the order's size.
+    OrderFilled {
+        market_id: String,
+        order_id: OrderId,
+        side: Side,
+        price: f64,
+        shares: f64,
+        offset_ms: i64,
+    },
+    /// A resting bid was shrunk in place, keeping its queue position;
+    /// `shares` is the order's new size, fills included.
+    OrderAmended {
+        market_id: String,
+        order_id: OrderId,
+        side: Side,
+        shares: f64,
+        offset_ms: i64,
+    },
+    /// A resting bid was cancelled, by the strategy or because it aborted.
+    OrderCancelled {
+        market_id: String,
+        order_id: OrderId,
+        side: Side,
+        offset_ms: i64,
+    },
+    /// How the fill model judged a resting order on a snapshot. Only
+    /// models that record their checks send these; see
+    /// [`FillModel::take_checks`](crate::fill::FillModel::take_checks).
+    FillChecked {
+        market_id: String,
+        offset_ms: i64,
+        #[serde(flatten)]
+        check: FillCheck,
+    },
+    /// A bid was still resting when its window ended.
+    OrderExpired {
+        market_id: String,
+        order_id: OrderId,
+        side: Side,
+        offset_ms: i64,
+    },
+    /// A filled bid was settled. `pnl` is what it added to the window's
+    /// realistic PnL: 0 for void markets and fills the fill model discards.
+    OrderResolved {
+        market_id: String,
+        order_id: OrderId,
+        side: Side,
+        pnl: f64,
+    },
+    /// A window was settled.
+    WindowCompleted(Box<WindowResult>),
+    /// A market of the current pass was processed (or skipped).
+    Progress {
+        markets_done: usize,
+        markets_total: usize,
+    },
+    /// A whole pass finished; `run` counts from 0.
+    RunCompleted {
+        run: usize,
+        runs: usize,
+        report: Box<Report>,
+    },
+}
+
+type Subscriber = Box<dyn Fn(&EngineEvent) + Send>;
+
+/// Fan-out of [`EngineEvent`]s to any number of subscribers. Clones share
+/// the same subscribers, so a bus can be handed to several engines (one per
+/// Monte Carlo run) and still be subscribed to afterwards.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus")
+            .field("subscribers", &self.subscribers.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Call `callback` with every event, on the engine's thread. Callbacks
+    /// should be quick and must not subscribe to the same bus.
+    pub fn subscribe(&self, callback: impl Fn(&EngineEvent) + Send + 'static) {
+        self.subscribers.lock().unwrap().push(Box::new(callback));
+    }
+
+    /// Receive every event on a channel. The channel is unbounded, so a
+    /// slow reader never stalls the engine; once the receiver is dropped,
+    /// events for it are discarded.
+    pub fn channel(&self) -> mpsc::Receiver<EngineEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribe(move |event| {
+            let _ = tx.send(event.clone());
+        });
+        rx
+    }
+
+    /// Deliver `event` to every subscriber.
+    pub fn emit(&self, event: EngineEvent) {
+        for subscriber in self.subscribers.lock().unwrap().iter() {
+            subscriber(&event);
+        }
+    }
+}
+
+/// One order's lifecycle, reassembled from the engine's events; see
+/// [`order_records`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderRecord {
+    /// Pass the order belongs to, counting from 0.
+    pub run: usize,
+    pub market_id: String,
+    pub order_id: OrderId,
+    pub side: Side,
+    pub price: f64,
+    pub shares: f64,
+    pub placed_at_ms: i64,
+    pub queue_ahead: f64,
+    pub taker: bool,
+    /// A sell of shares bought on `side`.
+    #[serde(default)]
+    pub sell: bool,
+    /// Shares filled, in part or in full.
+    pub filled_shares: f64,
+    /// When the order filled in full.
+    pub filled_at_ms: Option<i64>,
+    pub cancelled_at_ms: Option<i64>,
+    pub expired_at_ms: Option<i64>,
+    /// Set once an order that filled (at least in part) is resolved.
+    pub pnl: Option<f64>,
+}
+
+/// Per-order results from a stream of events (e.g. a bus
+/// [`channel`](EventBus::channel) drained after a backtest). Runs are told
+/// apart by [`EngineEvent::RunCompleted`]; later events attach to the order
+/// with their market and id in the same run.
+pub fn order_records(events: impl IntoIterator<Item = EngineEvent>) -> Vec<OrderRecord> {
+    fn find<'r>(records: &'r mut [OrderRecord], run: usize, market_id: &str, id: OrderId) -> Option<&'r mut OrderRecord> {
+        records
+            .iter_mut()
+            .rev()
+            .find(|r| r.run == run && r.order_id == id && r.market_id == market_id)
+    }
+
+    let mut records: Vec<OrderRecord> = Vec::new();
+    let mut run = 0;
+    for event in events {
+        match event {
+            EngineEvent::OrderPlaced {
+                market_id,
+                order_id,
+                side,
+                price,
+                shares,
+                offset_ms,
+                queue_ahead,
+                taker,
+                sell,
+            } => records.push(OrderRecord {
+                run,
+                market_id,
+                order_id,
+                side,
+                price,
+                shares,
+                placed_at_ms: offset_ms,
+                queue_ahead,
+                taker,
+                sell,
+                filled_shares: 0.0,
+                filled_at_ms: None,
+                cancelled_at_ms: None,
+                expired_at_ms: None,
+                pnl: None,
+            }),
+            EngineEvent::OrderPartiallyFilled {
+                market_id,
+                order_id,
+                shares,
+                ..
+            } => {
+                if let Some(record) = find(&mut records, run, &market_id, order_id) {
+                    record.filled_shares += shares;
+                }
+            }
+            EngineEvent::OrderFilled {
+                market_id,
+                order_id,
+                shares,
+                offset_ms,
+                ..
+            } => {
+                if let Some(record) = find(&mut records, run, &market_id, order_id) {
+                    record.filled_shares = shares;
+                    record.filled_at_ms = Some(offset_ms);
+                }
+            }
+            EngineEvent::OrderAmended {
+                market_id,
+                order_id,
+                shares,
+                ..
+            } => {
+                if let Some(record) = find(&mut records, run, &market_id, order_id) {
+                    record.shares = shares;
+                }
+            }
+            EngineEvent::OrderCancelled {
+                market_id,
+                order_id,
+                offset_ms,
+                ..
+            } => {
+                if let Some(record) = find(&mut records, run, &market_id, order_id) {
+                    record.cancelled_at_ms = Some(offset_ms);
+                }
+            }
+            EngineEvent::OrderExpired {
+                market_id,
+                order_id,
+                offset_ms,
+                ..
+            } => {
+                if let Some(record) = find(&mut records, run, &market_id, order_id) {
+                    record.expired_at_ms = Some(offset_ms);
+                }
+            }
+            EngineEvent::OrderResolved {
+                market_id,
+                order_id,
+                pnl,
+                ..
+            } => {
+                if let Some(record) = find(&mut records, run, &market_id, order_id) {
+                    record.pnl = Some(pnl);
+                }
+            }
+            EngineEvent::RunCompleted { .. } => run += 1,
+            EngineEvent::OrderRejected { .. }
+            | EngineEvent::FillChecked { .. }
+            | EngineEvent::WindowCompleted(_)
+            | EngineEvent::Progress { .. } => {}
+        }
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bus_fans_out_to_callbacks_and_channels() {
+        let bus = EventBus::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        bus.subscribe(move |event| {
+            if let EngineEvent::Progress { markets_done, .. } = event {
+                sink.lock().unwrap().push(*markets_done);
+            }
+        });
+        let rx = bus.channel();
+        let dropped = bus.clone().channel();
+        drop(dropped);
+
+        for done in 1..=3 {
+            bus.clone().emit(EngineEvent::Progress {
+                markets_done: done,
+                markets_total: 3,
+            });
+        }
+        assert_eq!(*seen.lock().unwrap(), vec![1, 2, 3]);
+        assert_eq!(rx.try_iter().count(), 3);
+
+        let json = serde_json::to_value(EngineEvent::OrderCancelled {
+            market_id: "m".into(),
+            order_id: OrderId::new(2),
+            side: Side::Yes,
+            offset_ms: 500,
+        })
+        .unwrap();
+        assert_eq!(json["type"], "order_cancelled");
+        assert_eq!(json["side"], "Yes");
+        assert_eq!(json["order_id"], 2);
+    }
+}
@@ -0,0 +1,224 @@
+//! C ABI for embedding the backtester (`ffi` feature).
+//!
+//! The surface is deliberately small: open a store, run a backtest described
+//! by a JSON config, and read the results back as JSON. Building with the
+//! feature regenerates `include/phantomfill.h`; a shared library comes from
+//!
+//! ```text
+//! cargo rustc --release --lib --features ffi --crate-type cdylib
+//! ```
+//!
+//! Every call that can fail returns `NULL` (or a negative status) and leaves
+//! a message for [`pf_last_error`] on the calling thread. Strings returned by
+//! the library must be released with [`pf_string_free`].
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::backtest::BacktestConfig;
+use crate::data::{DataStore, SqliteStore};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// An open phantomfill database. Opaque to C.
+pub struct PfStore {
+    store: SqliteStore,
+}
+
+fn set_last_error(err: anyhow::Error) {
+    let message = format!("{:#}", err).replace('\0', " ");
+    LAST_ERROR.with(|slot| *slot.borrow_mut() = CString::new(message).ok());
+}
+
+/// Run `f`, turning errors and panics into a `NULL`/[`pf_last_error`] pair.
+fn guard<T>(f: impl FnOnce() -> Result<*mut T>) -> *mut T {
+    LAST_ERROR.with(|slot| slot.borrow_mut().take());
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(err)) => {
+            set_last_error(err);
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_last_error(anyhow!("phantomfill panicked"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+///
+/// `s` must be NULL or a NUL-terminated string.
+unsafe fn str_arg<'a>(s: *const c_char, what: &str) -> Result<&'a str> {
+    if s.is_null() {
+        bail!("{} is NULL", what);
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .with_context(|| format!("{} is not valid UTF-8", what))
+}
+
+fn into_c_string(s: String) -> Result<*mut c_char> {
+    Ok(CString::new(s)?.into_raw())
+}
+
+fn run_backtest(store: &SqliteStore, config_json: &str) -> Result<String> {
+    let config: BacktestConfig =
+        serde_json::from_str(config_json).context("invalid backtest config")?;
+    Ok(serde_json::to_string(&config.run(store)?)?)
+}
+
+/// Library version, e.g. `"0.1.0"`. The string is static; do not free it.
+#[no_mangle]
+pub extern "C" fn pf_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// Message describing the last failed call on this thread, or NULL. Valid
+/// until the next phantomfill call on the same thread; do not free it.
+#[no_mangle]
+pub extern "C" fn pf_last_error() -> *const c_char {
+    LAST_ERROR.with(|slot| slot.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+/// Open (creating if needed) the database at `path` and initialize its
+/// schema. Returns NULL on failure. Free with [`pf_store_free`].
+///
+/// # Safety
+///
+/// `path` must be a NUL-terminated UTF-8 string.
+#[no_mangle]
+pub unsafe extern "C" fn pf_store_open(path: *const c_char) -> *mut PfStore {
+    guard(|| {
+        let path = str_arg(path, "path")?;
+        let store = SqliteStore::open(Path::new(path))
+            .with_context(|| format!("failed to open {}", path))?;
+        store.init()?;
+        Ok(Box::into_raw(Box::new(PfStore { store })))
+    })
+}
+
+/// Close a store opened with [`pf_store_open`]. NULL is ignored.
+///
+/// # Safety
+///
+/// `store` must be NULL or a pointer from [`pf_store_open`] that has not
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn pf_store_free(store: *mut PfStore) {
+    if !store.is_null() {
+        drop(Box::from_raw(store));
+    }
+}
+
+/// Run a backtest over `store` and return the results as a JSON object with
+/// `report`, `results` (one entry per window) and `summary` (null for a
+/// single run). `config_json` is an object with any of `strategy`, `script`,
+/// `script_source`, `params`, `bid_price`, `shares`, `min_bps`, `platform`,
+/// `category`, `duration_secs`, `min_ts`, `max_ts`, `limit`, `sample`, `seed`
+/// and `runs`; `"{}"` runs the defaults. Returns NULL on failure. Free the
+/// result with [`pf_string_free`].
+///
+/// # Safety
+///
+/// `store` must be a live pointer from [`pf_store_open`], not used from
+/// another thread during the call, and `config_json` a NUL-terminated UTF-8
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn pf_run_backtest(store: *const PfStore, config_json: *const c_char) -> *mut c_char {
+    guard(|| {
+        let store = store.as_ref().context("store is NULL")?;
+        let config = str_arg(config_json, "config_json")?;
+        into_c_string(run_backtest(&store.store, config)?)
+    })
+}
+
+/// Free a string returned by phantomfill. NULL is ignored.
+///
+/// # Safety
+///
+/// `s` must be NULL or a string returned by this library that has not been
+/// freed.
+#[no_mangle]
+pub unsafe extern "C" fn pf_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::{generate_dataset, BenchConfig};
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    fn last_error() -> String {
+        let err = pf_last_error();
+        assert!(!err.is_null());
+        unsafe { CStr::from_ptr(err) }.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_open_run_and_free() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ffi.db");
+        let path_c = c(path.to_str().unwrap());
+
+        unsafe {
+            let store = pf_store_open(path_c.as_ptr());
+            assert!(!store.is_null());
+            let config = BenchConfig {
+                markets: 4,
+                snapshots_per_market: 20,
+                seed: 5,
+            };
+            for (market, ticks) in generate_dataset(&config) {
+                (*store).store.insert_market(&market).unwrap();
+                (*store).store.insert_ticks(&ticks).unwrap();
+            }
+
+            let json = pf_run_backtest(store, c(r#"{"strategy": "spread_arb", "seed": 1, "runs": 2}"#).as_ptr());
+            assert!(!json.is_null(), "{}", last_error());
+            let value: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+            pf_string_free(json);
+
+            assert_eq!(value["report"]["strategy_name"], "spread_arb");
+            assert_eq!(value["results"].as_array().unwrap().len(), 4);
+            assert_eq!(value["summary"]["runs"], 2);
+            assert!(pf_last_error().is_null());
+
+            pf_store_free(store);
+        }
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        unsafe {
+            assert!(pf_store_open(ptr::null()).is_null());
+            assert!(last_error().contains("path is NULL"));
+
+            let store = Box::into_raw(Box::new(PfStore {
+                store: SqliteStore::in_memory().unwrap(),
+            }));
+            (*store).store.init().unwrap();
+            assert!(pf_run_backtest(store, c(r#"{"strategy": "nope"}"#).as_ptr()).is_null());
+            assert!(last_error().contains("unknown strategy `nope`"));
+            assert!(pf_run_backtest(store, c(r#"{"shars": 5}"#).as_ptr()).is_null());
+            assert!(last_error().contains("invalid backtest config"));
+            pf_store_free(store);
+        }
+        let version = unsafe { CStr::from_ptr(pf_version()) };
+        assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+    }
+}
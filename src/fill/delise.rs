@@ -1,18 +1,23 @@
 //! DeLise 3-rule fill model adapted for prediction markets.
 //!
 //! Based on DeLise (2024) "The Negative Drift of a Limit Order Fill":
-//! - Rule 1: Adverse tick (best_ask <= our bid) => fill with high probability
+//! - Rule 1: Adverse tick (best_ask <= our bid) => fill with high probability,
+//!   in part when the sweep runs out before clearing the whole order
 //! - Rule 2: Non-adverse tick => small Rf probability of fill per second
 //! - Rule 3: Price moves are discrete ($0.01 on Polymarket)
 //!
+//! A cancel is not instant: for `cancel_latency_ms` after the engine applies
+//! it, the order can still be hit by an adverse sweep (Rule 1), though not
+//! by Rule 2 flow.
+//!
 //! Adaptations for prediction markets:
 //! - Queue position estimated from bid depth at order price
 //! - Taker volume estimated from depth changes between snapshots
 //! - Adverse selection filter based on pre/post-signal timing
 
-use crate::fill::model::FillModel;
+use crate::fill::model::{market_seed, FillCheck, FillModel, FillRule};
 use crate::fill::queue;
-use crate::types::{BookSnapshot, Side, SimOrder};
+use crate::types::{BookSnapshot, OffsetMs, OrderId, Price, Shares, Side, SimOrder};
 
 use rand::rngs::StdRng;
 use rand::SeedableRng;
@@ -31,7 +36,11 @@ pub struct DeLiseConfig {
     pub signal_offset_ms: i64,
     /// Taker rate multiplier after signal becomes public (default 1.8).
     pub post_signal_taker_mult: f64,
-    /// Optional seed for reproducible RNG. None uses entropy.
+    /// Cancel round trip (ms): a cancelled order stays exposed to adverse
+    /// sweeps on snapshots up to this long after the cancel (default 0).
+    pub cancel_latency_ms: i64,
+    /// Optional seed for reproducible RNG, mixed with each market's id so
+    /// a market draws the same whatever ran before it. None uses entropy.
     pub seed: Option<u64>,
 }
 
@@ -43,6 +52,7 @@ impl Default for DeLiseConfig {
             winner_queue_threshold: 50.0,
             signal_offset_ms: 90_000,
             post_signal_taker_mult: 1.8,
+            cancel_latency_ms: 0,
             seed: None,
         }
     }
@@ -55,6 +65,8 @@ pub struct DeLiseFillModel {
     /// Deterministic mode for testing — when Some, this value is used
     /// instead of random sampling for the Rf check.
     deterministic_rand: Option<f64>,
+    /// With [`traced`](Self::traced), the checks not yet taken.
+    checks: Option<RefCell<Vec<FillCheck>>>,
 }
 
 impl DeLiseFillModel {
@@ -67,9 +79,18 @@ impl DeLiseFillModel {
             config,
             rng: RefCell::new(rng),
             deterministic_rand: None,
+            checks: None,
         }
     }
 
+    /// Record how every resting order is judged on each tick, for
+    /// [`FillModel::take_checks`]. Draws are unchanged, so a traced model
+    /// fills exactly like an untraced one with the same seed.
+    pub fn traced(mut self) -> Self {
+        self.checks = Some(RefCell::default());
+        self
+    }
+
     /// Create with deterministic random value for testing.
     /// The value is used in place of the RNG for Rf checks.
     #[cfg(test)]
@@ -78,6 +99,7 @@ impl DeLiseFillModel {
             config,
             rng: RefCell::new(StdRng::seed_from_u64(0)),
             deterministic_rand: Some(rand_val),
+            checks: None,
         }
     }
 
@@ -109,6 +131,53 @@ impl DeLiseFillModel {
         // P(fill in dt) = 1 - (1 - rf)^dt
         1.0 - (1.0 - rf).powf(dt_secs)
     }
+
+    fn record(&self, order: &SimOrder, rule: FillRule, roll: Option<f64>, probability: Option<f64>, filled: Shares) {
+        if let Some(ref checks) = self.checks {
+            checks.borrow_mut().push(FillCheck {
+                order_id: order.id,
+                side: order.side,
+                rule,
+                roll,
+                probability,
+                queue_ahead: order.queue_ahead,
+                queue_consumed: order.queue_consumed,
+                filled: filled.to_f64(),
+            });
+        }
+    }
+
+    /// Whether `order`'s cancel is still on its way to the venue at
+    /// `offset_ms`, leaving the rest of the order exposed to sweeps.
+    fn cancel_in_flight(&self, order: &SimOrder, offset_ms: OffsetMs) -> bool {
+        !order.filled
+            && !order.taker
+            && order
+                .cancelled_at_ms
+                .is_some_and(|at| offset_ms - at <= self.config.cancel_latency_ms)
+    }
+}
+
+/// One side of a snapshot as the fill rules see it.
+struct SideTick {
+    best_ask: Option<Price>,
+    /// Estimated sweep volume on an adverse tick: the ask size at the top.
+    sweep_volume: f64,
+}
+
+impl SideTick {
+    fn new(snap: &BookSnapshot, side: Side) -> Self {
+        let state = queue::side_state(snap, side);
+        Self {
+            best_ask: state.best_ask.map(Price::from_f64),
+            sweep_volume: state.best_ask_size.unwrap_or(0.0),
+        }
+    }
+
+    /// Same test as [`queue::is_adverse_tick`] for a bid at `price`.
+    fn is_adverse(&self, price: Price) -> bool {
+        self.best_ask.is_some_and(|ask| ask <= price)
+    }
 }
 
 impl FillModel for DeLiseFillModel {
@@ -116,16 +185,21 @@ impl FillModel for DeLiseFillModel {
         "delise-3rule"
     }
 
+    fn settings(&self) -> String {
+        format!("{} {:?}", self.name(), self.config)
+    }
+
     fn create_order(
         &self,
         side: Side,
-        price: f64,
-        shares: f64,
+        price: Price,
+        shares: Shares,
         snap: &BookSnapshot,
-        offset_ms: i64,
+        offset_ms: OffsetMs,
     ) -> SimOrder {
-        let queue_ahead = queue::queue_position(snap, side, price);
+        let queue_ahead = queue::queue_position(snap, side, price.to_f64());
         SimOrder {
+            id: OrderId::default(),
             side,
             price,
             shares,
@@ -133,7 +207,11 @@ impl FillModel for DeLiseFillModel {
             queue_ahead,
             queue_consumed: 0.0,
             filled: false,
+            filled_shares: Shares::default(),
             filled_at_ms: None,
+            cancelled_at_ms: None,
+            taker: false,
+            sell: false,
         }
     }
 
@@ -141,55 +219,85 @@ impl FillModel for DeLiseFillModel {
         &self,
         snap: &BookSnapshot,
         orders: &mut [SimOrder],
-        prev_offset_ms: i64,
+        prev_offset_ms: OffsetMs,
     ) -> Vec<usize> {
         let dt_ms = snap.offset_ms - prev_offset_ms;
         let mut filled_indices = Vec::new();
 
+        // Everything but the order's own price and queue is the same for
+        // every order on a side, so work it out once per snapshot.
+        let is_post_signal = snap.offset_ms >= self.config.signal_offset_ms;
+        let fill_prob = self.rf_fill_probability(dt_ms, is_post_signal);
+        let (yes, no) = (SideTick::new(snap, Side::Yes), SideTick::new(snap, Side::No));
+
         for (i, order) in orders.iter_mut().enumerate() {
-            if order.filled {
+            let racing = self.config.cancel_latency_ms > 0 && self.cancel_in_flight(order, snap.offset_ms);
+            if !order.is_open() && !racing {
                 continue;
             }
 
             // Orders cannot be filled on the same tick they were placed.
             if order.placed_at_ms == snap.offset_ms {
+                self.record(order, FillRule::JustPlaced, None, None, Shares::default());
                 continue;
             }
 
-            let is_post_signal = snap.offset_ms >= self.config.signal_offset_ms;
+            let side = match order.side {
+                Side::Yes => &yes,
+                Side::No => &no,
+            };
 
             // Rule 1: Adverse tick — best_ask <= our bid price
-            if queue::is_adverse_tick(snap, order.side, order.price) {
-                // Estimate sweep volume from the ask size at our price
-                let state = queue::side_state(snap, order.side);
-                let sweep_volume = state.best_ask_size.unwrap_or(0.0);
-
+            if side.is_adverse(order.price) {
                 // Advance queue consumed by sweep volume
-                order.queue_consumed += sweep_volume;
-
-                // If sweep clears through our position, fill with adverse_fill_prob
-                if order.queue_consumed >= order.queue_ahead
-                    && self.sample_uniform() < self.config.adverse_fill_prob
-                {
-                    order.filled = true;
-                    order.filled_at_ms = Some(snap.offset_ms);
-                    filled_indices.push(i);
+                order.queue_consumed += side.sweep_volume;
+
+                // Once the sweeps reach our position, whatever they clear past
+                // it fills the order (with adverse_fill_prob), in part if they
+                // run out first.
+                let past_us = order.queue_consumed - order.queue_ahead;
+                let roll = (past_us >= 0.0).then(|| self.sample_uniform());
+                let mut filled = Shares::default();
+                if roll.is_some_and(|r| r < self.config.adverse_fill_prob) {
+                    let swept = Shares::from_f64(past_us) - order.filled_shares;
+                    filled = order.fill(swept, snap.offset_ms);
+                    if filled > Shares::default() {
+                        filled_indices.push(i);
+                    }
                 }
+                self.record(order, FillRule::Adverse, roll, Some(self.config.adverse_fill_prob), filled);
+                continue;
+            }
+
+            // Only a sweep beats a cancel to the book.
+            if racing {
+                self.record(order, FillRule::CancelPending, None, None, Shares::default());
                 continue;
             }
 
             // Rule 2: Non-adverse tick — small probability of fill from retail flow
-            let fill_prob = self.rf_fill_probability(dt_ms, is_post_signal);
-            if self.sample_uniform() < fill_prob {
-                order.filled = true;
-                order.filled_at_ms = Some(snap.offset_ms);
+            let roll = self.sample_uniform();
+            let mut filled = Shares::default();
+            if roll < fill_prob {
+                filled = order.fill_all(snap.offset_ms);
                 filled_indices.push(i);
             }
+            self.record(order, FillRule::Flow, Some(roll), Some(fill_prob), filled);
         }
 
         filled_indices
     }
 
+    fn take_checks(&self) -> Vec<FillCheck> {
+        self.checks.as_ref().map(|c| c.take()).unwrap_or_default()
+    }
+
+    fn begin_market(&self, market_id: &str) {
+        if let Some(seed) = self.config.seed {
+            *self.rng.borrow_mut() = StdRng::seed_from_u64(market_seed(seed, market_id));
+        }
+    }
+
     fn adverse_selection_filter(&self, order: &SimOrder, is_winner: bool) -> bool {
         let fill_offset = match order.filled_at_ms {
             Some(ms) => ms,
@@ -218,6 +326,7 @@ impl FillModel for DeLiseFillModel {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Arc;
     use crate::types::{PriceLevel, SideState};
 
     fn make_side(
@@ -249,11 +358,11 @@ mod tests {
         no: SideState,
     ) -> BookSnapshot {
         BookSnapshot {
-            market_id: "test".to_string(),
-            offset_ms,
+            market_id: "test".into(),
+            offset_ms: OffsetMs::new(offset_ms),
             timestamp_ms: offset_ms,
-            yes,
-            no,
+            yes: Arc::new(yes),
+            no: Arc::new(no),
             reference_price: None,
             oracle_price: None,
         }
@@ -271,11 +380,11 @@ mod tests {
     fn test_create_order_captures_queue_position() {
         let model = DeLiseFillModel::new(DeLiseConfig::default());
         let snap = default_snap(5000);
-        let order = model.create_order(Side::Yes, 0.49, 10.0, &snap, 5000);
+        let order = model.create_order(Side::Yes, Price::from_f64(0.49), Shares::from_f64(10.0), &snap, OffsetMs::new(5000));
 
         assert_eq!(order.side, Side::Yes);
-        assert_eq!(order.price, 0.49);
-        assert_eq!(order.shares, 10.0);
+        assert_eq!(order.price, Price::from_f64(0.49));
+        assert_eq!(order.shares, Shares::from_f64(10.0));
         assert_eq!(order.placed_at_ms, 5000);
         assert_eq!(order.queue_ahead, 200.0);
         assert!(!order.filled);
@@ -289,7 +398,7 @@ mod tests {
             make_side(None, None, None, vec![]),
             SideState::default(),
         );
-        let order = model.create_order(Side::Yes, 0.49, 10.0, &snap, 1000);
+        let order = model.create_order(Side::Yes, Price::from_f64(0.49), Shares::from_f64(10.0), &snap, OffsetMs::new(1000));
 
         // No depth => queue_ahead = 0
         assert_eq!(order.queue_ahead, 0.0);
@@ -308,21 +417,111 @@ mod tests {
             SideState::default(),
         );
 
-        let mut orders = vec![SimOrder {
-            side: Side::Yes,
-            price: 0.49,
-            shares: 10.0,
-            placed_at_ms: 1000,
-            queue_ahead: 200.0,
-            queue_consumed: 0.0,
-            filled: false,
-            filled_at_ms: None,
-        }];
+        let mut orders = vec![SimOrder::test_bid(Side::Yes, 1000, 200.0)];
 
-        let filled = model.process_tick(&snap, &mut orders, 1000);
+        let filled = model.process_tick(&snap, &mut orders, OffsetMs::new(1000));
         assert_eq!(filled, vec![0]);
         assert!(orders[0].filled);
-        assert_eq!(orders[0].filled_at_ms, Some(2000));
+        assert_eq!(orders[0].filled_at_ms, Some(OffsetMs::new(2000)));
+    }
+
+    #[test]
+    fn test_adverse_sweeps_fill_in_part() {
+        let model = DeLiseFillModel::new_deterministic(DeLiseConfig::default(), 0.0);
+        let sweep = |offset_ms, size| {
+            make_snap_with(
+                offset_ms,
+                make_side(Some(0.49), Some(0.49), Some(size), vec![(0.49, 200.0)]),
+                SideState::default(),
+            )
+        };
+        let mut orders = vec![model.create_order(
+            Side::Yes,
+            Price::from_f64(0.49),
+            Shares::from_f64(10.0),
+            &default_snap(1000),
+            OffsetMs::new(1000),
+        )];
+
+        // 204 swept against 200 ahead: 4 of our 10 shares fill.
+        assert_eq!(model.process_tick(&sweep(2000, 204.0), &mut orders, OffsetMs::new(1000)), vec![0]);
+        assert_eq!(orders[0].filled_shares, Shares::from_f64(4.0));
+        assert!(orders[0].is_open());
+        assert_eq!(orders[0].filled_at_ms, Some(OffsetMs::new(2000)));
+
+        assert_eq!(model.process_tick(&sweep(3000, 50.0), &mut orders, OffsetMs::new(2000)), vec![0]);
+        assert!(orders[0].filled);
+        assert_eq!(orders[0].remaining(), Shares::default());
+        assert_eq!(orders[0].filled_at_ms, Some(OffsetMs::new(2000)));
+    }
+
+    #[test]
+    fn test_seeded_draws_depend_only_on_seed_and_market() {
+        let config = DeLiseConfig {
+            seed: Some(7),
+            ..DeLiseConfig::default()
+        };
+        let draws = |model: &DeLiseFillModel, market_id: &str| {
+            model.begin_market(market_id);
+            (0..4).map(|_| model.sample_uniform()).collect::<Vec<_>>()
+        };
+
+        let fresh = DeLiseFillModel::new(config.clone());
+        let b = draws(&fresh, "b");
+        let after_a = DeLiseFillModel::new(config.clone());
+        draws(&after_a, "a");
+        assert_eq!(draws(&after_a, "b"), b);
+        assert_ne!(draws(&after_a, "a"), b);
+
+        let other_seed = DeLiseFillModel::new(DeLiseConfig {
+            seed: Some(8),
+            ..config
+        });
+        assert_ne!(draws(&other_seed, "b"), b);
+    }
+
+    #[test]
+    fn test_sweep_beats_cancel_in_flight() {
+        let config = DeLiseConfig {
+            cancel_latency_ms: 500,
+            ..DeLiseConfig::default()
+        };
+        // rand=0.0 passes every probability check, Rf included.
+        let model = DeLiseFillModel::new_deterministic(config, 0.0);
+        let sweep = |offset_ms| {
+            make_snap_with(
+                offset_ms,
+                make_side(Some(0.49), Some(0.49), Some(300.0), vec![(0.49, 200.0)]),
+                SideState::default(),
+            )
+        };
+        let cancelled = || {
+            let mut order = model.create_order(
+                Side::Yes,
+                Price::from_f64(0.49),
+                Shares::from_f64(10.0),
+                &default_snap(0),
+                OffsetMs::new(0),
+            );
+            order.cancelled_at_ms = Some(OffsetMs::new(1000));
+            vec![order]
+        };
+
+        // Within the round trip only a sweep fills the cancelled order.
+        let mut orders = cancelled();
+        assert!(model.process_tick(&default_snap(1400), &mut orders, OffsetMs::new(1000)).is_empty());
+        assert_eq!(model.process_tick(&sweep(1500), &mut orders, OffsetMs::new(1400)), vec![0]);
+        assert!(orders[0].filled);
+
+        // Once the cancel has landed, nothing does.
+        let mut orders = cancelled();
+        assert!(model.process_tick(&sweep(1600), &mut orders, OffsetMs::new(1000)).is_empty());
+        assert_eq!(orders[0].filled_shares, Shares::default());
+
+        // Without a round trip a cancel is final at once.
+        let model = DeLiseFillModel::new_deterministic(DeLiseConfig::default(), 0.0);
+        let mut orders = cancelled();
+        assert!(model.process_tick(&sweep(1100), &mut orders, OffsetMs::new(1000)).is_empty());
     }
 
     #[test]
@@ -336,18 +535,9 @@ mod tests {
             SideState::default(),
         );
 
-        let mut orders = vec![SimOrder {
-            side: Side::Yes,
-            price: 0.49,
-            shares: 10.0,
-            placed_at_ms: 1000,
-            queue_ahead: 200.0,
-            queue_consumed: 0.0,
-            filled: false,
-            filled_at_ms: None,
-        }];
+        let mut orders = vec![SimOrder::test_bid(Side::Yes, 1000, 200.0)];
 
-        let filled = model.process_tick(&snap, &mut orders, 1000);
+        let filled = model.process_tick(&snap, &mut orders, OffsetMs::new(1000));
         assert!(filled.is_empty());
         assert!(!orders[0].filled);
         // But queue_consumed should have advanced
@@ -360,18 +550,9 @@ mod tests {
         let model = DeLiseFillModel::new_deterministic(DeLiseConfig::default(), 0.0);
         let snap = default_snap(2000);
 
-        let mut orders = vec![SimOrder {
-            side: Side::Yes,
-            price: 0.49,
-            shares: 10.0,
-            placed_at_ms: 1000,
-            queue_ahead: 200.0,
-            queue_consumed: 0.0,
-            filled: false,
-            filled_at_ms: None,
-        }];
+        let mut orders = vec![SimOrder::test_bid(Side::Yes, 1000, 200.0)];
 
-        let filled = model.process_tick(&snap, &mut orders, 1000);
+        let filled = model.process_tick(&snap, &mut orders, OffsetMs::new(1000));
         assert_eq!(filled, vec![0]);
         assert!(orders[0].filled);
     }
@@ -382,18 +563,9 @@ mod tests {
         let model = DeLiseFillModel::new_deterministic(DeLiseConfig::default(), 0.999);
         let snap = default_snap(2000);
 
-        let mut orders = vec![SimOrder {
-            side: Side::Yes,
-            price: 0.49,
-            shares: 10.0,
-            placed_at_ms: 1000,
-            queue_ahead: 200.0,
-            queue_consumed: 0.0,
-            filled: false,
-            filled_at_ms: None,
-        }];
+        let mut orders = vec![SimOrder::test_bid(Side::Yes, 1000, 200.0)];
 
-        let filled = model.process_tick(&snap, &mut orders, 1000);
+        let filled = model.process_tick(&snap, &mut orders, OffsetMs::new(1000));
         assert!(filled.is_empty());
         assert!(!orders[0].filled);
     }
@@ -404,17 +576,12 @@ mod tests {
         let snap = default_snap(3000);
 
         let mut orders = vec![SimOrder {
-            side: Side::Yes,
-            price: 0.49,
-            shares: 10.0,
-            placed_at_ms: 1000,
-            queue_ahead: 200.0,
-            queue_consumed: 0.0,
             filled: true,
-            filled_at_ms: Some(2000),
+            filled_at_ms: Some(OffsetMs::new(2000)),
+            ..SimOrder::test_bid(Side::Yes, 1000, 200.0)
         }];
 
-        let filled = model.process_tick(&snap, &mut orders, 2000);
+        let filled = model.process_tick(&snap, &mut orders, OffsetMs::new(2000));
         assert!(filled.is_empty());
     }
 
@@ -422,14 +589,9 @@ mod tests {
     fn test_adverse_selection_pre_signal_winner() {
         let model = DeLiseFillModel::new(DeLiseConfig::default());
         let order = SimOrder {
-            side: Side::Yes,
-            price: 0.49,
-            shares: 10.0,
-            placed_at_ms: 5000,
-            queue_ahead: 200.0,
-            queue_consumed: 0.0,
             filled: true,
-            filled_at_ms: Some(80_000), // before signal_offset_ms (90_000)
+            filled_at_ms: Some(OffsetMs::new(80_000)), // before signal_offset_ms (90_000)
+            ..SimOrder::test_bid(Side::Yes, 5000, 200.0)
         };
         // Pre-signal winner fills always survive
         assert!(model.adverse_selection_filter(&order, true));
@@ -439,14 +601,9 @@ mod tests {
     fn test_adverse_selection_pre_signal_loser() {
         let model = DeLiseFillModel::new(DeLiseConfig::default());
         let order = SimOrder {
-            side: Side::Yes,
-            price: 0.49,
-            shares: 10.0,
-            placed_at_ms: 5000,
-            queue_ahead: 200.0,
-            queue_consumed: 0.0,
             filled: true,
-            filled_at_ms: Some(80_000),
+            filled_at_ms: Some(OffsetMs::new(80_000)),
+            ..SimOrder::test_bid(Side::Yes, 5000, 200.0)
         };
         assert!(model.adverse_selection_filter(&order, false));
     }
@@ -455,14 +612,10 @@ mod tests {
     fn test_adverse_selection_post_signal_winner_early_queue() {
         let model = DeLiseFillModel::new(DeLiseConfig::default());
         let order = SimOrder {
-            side: Side::Yes,
-            price: 0.49,
-            shares: 10.0,
-            placed_at_ms: 5000,
-            queue_ahead: 30.0, // < winner_queue_threshold (50.0)
-            queue_consumed: 0.0,
             filled: true,
-            filled_at_ms: Some(100_000),
+            filled_at_ms: Some(OffsetMs::new(100_000)),
+            // 30 ahead: < winner_queue_threshold (50.0)
+            ..SimOrder::test_bid(Side::Yes, 5000, 30.0)
         };
         // Early queue => survives
         assert!(model.adverse_selection_filter(&order, true));
@@ -472,14 +625,10 @@ mod tests {
     fn test_adverse_selection_post_signal_winner_late_queue() {
         let model = DeLiseFillModel::new(DeLiseConfig::default());
         let order = SimOrder {
-            side: Side::Yes,
-            price: 0.49,
-            shares: 10.0,
-            placed_at_ms: 5000,
-            queue_ahead: 200.0, // >> winner_queue_threshold (50.0)
-            queue_consumed: 0.0,
             filled: true,
-            filled_at_ms: Some(100_000),
+            filled_at_ms: Some(OffsetMs::new(100_000)),
+            // 200 ahead: >> winner_queue_threshold (50.0)
+            ..SimOrder::test_bid(Side::Yes, 5000, 200.0)
         };
         // Late queue + winner + post-signal => blocked
         assert!(!model.adverse_selection_filter(&order, true));
@@ -489,14 +638,10 @@ mod tests {
     fn test_adverse_selection_post_signal_loser_always_passes() {
         let model = DeLiseFillModel::new(DeLiseConfig::default());
         let order = SimOrder {
-            side: Side::Yes,
-            price: 0.49,
-            shares: 10.0,
-            placed_at_ms: 5000,
-            queue_ahead: 500.0, // large queue, doesn't matter for losers
-            queue_consumed: 0.0,
             filled: true,
-            filled_at_ms: Some(100_000),
+            filled_at_ms: Some(OffsetMs::new(100_000)),
+            // 500 ahead: large queue, doesn't matter for losers
+            ..SimOrder::test_bid(Side::Yes, 5000, 500.0)
         };
         // Loser fills always survive, even post-signal
         assert!(model.adverse_selection_filter(&order, false));
@@ -505,16 +650,7 @@ mod tests {
     #[test]
     fn test_adverse_selection_unfilled_order() {
         let model = DeLiseFillModel::new(DeLiseConfig::default());
-        let order = SimOrder {
-            side: Side::Yes,
-            price: 0.49,
-            shares: 10.0,
-            placed_at_ms: 5000,
-            queue_ahead: 200.0,
-            queue_consumed: 0.0,
-            filled: false,
-            filled_at_ms: None,
-        };
+        let order = SimOrder::test_bid(Side::Yes, 5000, 200.0);
         // Unfilled orders don't survive the filter
         assert!(!model.adverse_selection_filter(&order, true));
     }
@@ -532,42 +668,20 @@ mod tests {
 
         let mut orders = vec![
             // This one fills (queue_ahead=200, sweep=300)
-            SimOrder {
-                side: Side::Yes,
-                price: 0.49,
-                shares: 10.0,
-                placed_at_ms: 1000,
-                queue_ahead: 200.0,
-                queue_consumed: 0.0,
-                filled: false,
-                filled_at_ms: None,
-            },
+            SimOrder::test_bid(Side::Yes, 1000, 200.0),
             // This one already filled — should be skipped
             SimOrder {
-                side: Side::Yes,
-                price: 0.49,
-                shares: 10.0,
-                placed_at_ms: 500,
-                queue_ahead: 100.0,
                 queue_consumed: 100.0,
                 filled: true,
-                filled_at_ms: Some(1500),
+                filled_at_ms: Some(OffsetMs::new(1500)),
+                ..SimOrder::test_bid(Side::Yes, 500, 100.0)
             },
             // This one on No side — no adverse tick on No side => Rf path
             // With rand=0.0 and dt=1000ms, Rf will trigger
-            SimOrder {
-                side: Side::No,
-                price: 0.49,
-                shares: 10.0,
-                placed_at_ms: 1000,
-                queue_ahead: 200.0,
-                queue_consumed: 0.0,
-                filled: false,
-                filled_at_ms: None,
-            },
+            SimOrder::test_bid(Side::No, 1000, 200.0),
         ];
 
-        let filled = model.process_tick(&snap, &mut orders, 1000);
+        let filled = model.process_tick(&snap, &mut orders, OffsetMs::new(1000));
         assert_eq!(filled, vec![0, 2]);
         assert!(orders[0].filled);
         assert!(orders[2].filled);
@@ -602,19 +716,10 @@ mod tests {
             SideState::default(),
         );
 
-        let mut orders = vec![SimOrder {
-            side: Side::Yes,
-            price: 0.49,
-            shares: 10.0,
-            placed_at_ms: 1000,
-            queue_ahead: 200.0,
-            queue_consumed: 0.0,
-            filled: false,
-            filled_at_ms: None,
-        }];
+        let mut orders = vec![SimOrder::test_bid(Side::Yes, 1000, 200.0)];
 
         // First tick: no fill yet
-        let filled = model.process_tick(&snap1, &mut orders, 1000);
+        let filled = model.process_tick(&snap1, &mut orders, OffsetMs::new(1000));
         assert!(filled.is_empty());
         assert!((orders[0].queue_consumed - 120.0).abs() < f64::EPSILON);
 
@@ -625,10 +730,10 @@ mod tests {
         );
 
         // Second tick: fill
-        let filled = model.process_tick(&snap2, &mut orders, 2000);
+        let filled = model.process_tick(&snap2, &mut orders, OffsetMs::new(2000));
         assert_eq!(filled, vec![0]);
         assert!(orders[0].filled);
-        assert_eq!(orders[0].filled_at_ms, Some(3000));
+        assert_eq!(orders[0].filled_at_ms, Some(OffsetMs::new(3000)));
     }
 
     #[test]
@@ -642,18 +747,9 @@ mod tests {
             SideState::default(),
         );
 
-        let mut orders = vec![SimOrder {
-            side: Side::Yes,
-            price: 0.49,
-            shares: 10.0,
-            placed_at_ms: 500,
-            queue_ahead: 0.0,
-            queue_consumed: 0.0,
-            filled: false,
-            filled_at_ms: None,
-        }];
+        let mut orders = vec![SimOrder::test_bid(Side::Yes, 500, 0.0)];
 
-        let filled = model.process_tick(&snap, &mut orders, 500);
+        let filled = model.process_tick(&snap, &mut orders, OffsetMs::new(500));
         assert_eq!(filled, vec![0]);
         assert!(orders[0].filled);
     }
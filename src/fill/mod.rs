@@ -1,6 +1,8 @@
 pub mod delise;
 pub mod model;
 pub mod queue;
+pub mod tape;
 
 pub use delise::{DeLiseConfig, DeLiseFillModel};
-pub use model::FillModel;
+pub use model::{FillCheck, FillModel, FillRule};
+pub use tape::{TradeTape, TradeTapeFillModel};
@@ -1,4 +1,39 @@
-use crate::types::{BookSnapshot, Side, SimOrder};
+use serde::Serialize;
+
+use crate::types::{BookSnapshot, Cash, OffsetMs, OrderId, Price, Shares, Side, SimOrder};
+
+/// The rule a fill model applied to a resting order on one snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FillRule {
+    /// Placed on this snapshot, so it can't fill yet.
+    JustPlaced,
+    /// The best ask reached the bid: the sweep ate into the queue ahead.
+    Adverse,
+    /// No sweep: a random draw against the flow fill probability.
+    Flow,
+    /// Cancelled, with the cancel still on its way and no sweep to beat it.
+    CancelPending,
+}
+
+/// How a fill model judged one resting order on one snapshot; see
+/// [`FillModel::take_checks`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FillCheck {
+    pub order_id: OrderId,
+    pub side: Side,
+    pub rule: FillRule,
+    /// The uniform draw, if the rule made one, and the probability it had
+    /// to come in under to fill.
+    pub roll: Option<f64>,
+    pub probability: Option<f64>,
+    /// Size ahead of the order when it was placed, and how much of it the
+    /// sweeps have consumed so far (this snapshot's included).
+    pub queue_ahead: f64,
+    pub queue_consumed: f64,
+    /// Shares the check filled.
+    pub filled: f64,
+}
 
 /// Trait for fill simulation models.
 ///
@@ -7,26 +42,206 @@ use crate::types::{BookSnapshot, Side, SimOrder};
 pub trait FillModel: Send {
     fn name(&self) -> &str;
 
-    /// Create a new SimOrder based on current book state.
+    /// The model's name with every setting that changes how it fills, so a
+    /// checkpoint can tell runs with differently configured models apart.
+    /// Models without settings just return their name.
+    fn settings(&self) -> String {
+        self.name().to_string()
+    }
+
+    /// Create a new SimOrder based on current book state. The engine
+    /// assigns its [`id`](SimOrder::id).
     fn create_order(
         &self,
         side: Side,
-        price: f64,
-        shares: f64,
+        price: Price,
+        shares: Shares,
         snap: &BookSnapshot,
-        offset_ms: i64,
+        offset_ms: OffsetMs,
     ) -> SimOrder;
 
-    /// Process a tick: advance queue position, check for fills.
-    /// Returns indices of newly filled orders.
+    /// Process a tick: advance queue position, check for fills. Only
+    /// [open](SimOrder::is_open) orders may fill; cancelled ones must be
+    /// left alone, unless the model simulates the cancel's round trip (see
+    /// [`DeLiseConfig::cancel_latency_ms`](crate::fill::DeLiseConfig::cancel_latency_ms)).
+    /// Fill with [`SimOrder::fill`] (setting `filled` alone fills the whole
+    /// order). Returns indices of orders that filled, in part or in full.
     fn process_tick(
         &self,
         snap: &BookSnapshot,
         orders: &mut [SimOrder],
-        prev_offset_ms: i64,
+        prev_offset_ms: OffsetMs,
     ) -> Vec<usize>;
 
+    /// Execute a taker buy of up to `shares` on `side` against `snap`,
+    /// paying no more than `max_price` a share. The order comes back filled
+    /// for what the asks supplied, at its average price; the engine cancels
+    /// whatever is left. The default walks the displayed asks with
+    /// [`take_asks`].
+    fn take_order(
+        &self,
+        side: Side,
+        shares: Shares,
+        max_price: Price,
+        snap: &BookSnapshot,
+        offset_ms: OffsetMs,
+    ) -> SimOrder {
+        let mut order = SimOrder {
+            id: OrderId::default(),
+            side,
+            price: max_price,
+            shares,
+            placed_at_ms: offset_ms,
+            queue_ahead: 0.0,
+            queue_consumed: 0.0,
+            filled: false,
+            filled_shares: Shares::default(),
+            filled_at_ms: None,
+            cancelled_at_ms: None,
+            taker: true,
+            sell: false,
+        };
+        if let Some((bought, price)) = take_asks(snap, side, shares, max_price) {
+            order.price = price;
+            order.fill(bought, offset_ms);
+        }
+        order
+    }
+
     /// After outcome is known, apply adverse selection filter.
     /// Returns true if the fill "survives" (is realistic).
     fn adverse_selection_filter(&self, order: &SimOrder, is_winner: bool) -> bool;
+
+    /// Called as each market window begins. Seeded models reseed here
+    /// from [`market_seed`], so a market's draws depend only on the seed
+    /// and the market, not on which markets ran before it.
+    fn begin_market(&self, market_id: &str) {
+        let _ = market_id;
+    }
+
+    /// Whether the model reads depth ladders (`SideState::depth`), e.g. to
+    /// estimate queue position. Models that only look at the top of the
+    /// book return false, so stores may skip loading ladders for them.
+    fn uses_depth(&self) -> bool {
+        true
+    }
+
+    /// How each resting order was judged since the last call, for models
+    /// that record it (see [`DeLiseFillModel::traced`]); empty otherwise.
+    ///
+    /// [`DeLiseFillModel::traced`]: crate::fill::DeLiseFillModel::traced
+    fn take_checks(&self) -> Vec<FillCheck> {
+        Vec::new()
+    }
+}
+
+/// Boxed models are models too, so `ReplayEngine<Box<dyn FillModel>>` (the
+/// default, chosen at runtime) and `ReplayEngine<DeLiseFillModel>` share one
+/// implementation.
+impl<F: FillModel + ?Sized> FillModel for Box<F> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn settings(&self) -> String {
+        (**self).settings()
+    }
+
+    fn create_order(
+        &self,
+        side: Side,
+        price: Price,
+        shares: Shares,
+        snap: &BookSnapshot,
+        offset_ms: OffsetMs,
+    ) -> SimOrder {
+        (**self).create_order(side, price, shares, snap, offset_ms)
+    }
+
+    fn process_tick(
+        &self,
+        snap: &BookSnapshot,
+        orders: &mut [SimOrder],
+        prev_offset_ms: OffsetMs,
+    ) -> Vec<usize> {
+        (**self).process_tick(snap, orders, prev_offset_ms)
+    }
+
+    fn take_order(
+        &self,
+        side: Side,
+        shares: Shares,
+        max_price: Price,
+        snap: &BookSnapshot,
+        offset_ms: OffsetMs,
+    ) -> SimOrder {
+        (**self).take_order(side, shares, max_price, snap, offset_ms)
+    }
+
+    fn adverse_selection_filter(&self, order: &SimOrder, is_winner: bool) -> bool {
+        (**self).adverse_selection_filter(order, is_winner)
+    }
+
+    fn begin_market(&self, market_id: &str) {
+        (**self).begin_market(market_id)
+    }
+
+    fn uses_depth(&self) -> bool {
+        (**self).uses_depth()
+    }
+
+    fn take_checks(&self) -> Vec<FillCheck> {
+        (**self).take_checks()
+    }
+}
+
+/// A per-market seed derived from a run's `seed`: FNV-1a over the market
+/// id, starting from the seed.
+pub fn market_seed(seed: u64, market_id: &str) -> u64 {
+    market_id
+        .bytes()
+        .fold(seed ^ 0xcbf2_9ce4_8422_2325, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Buy up to `shares` on `side` from the asks in `snap`, cheapest level
+/// first, never above `max_price`. Walks the
+/// [ask ladder](BookSnapshot::ask_depth), or just the best ask when the
+/// snapshot has no depth. Returns the shares bought and their average price,
+/// rounded up to the next [`Price`] unit, or `None` if nothing was cheap
+/// enough.
+pub fn take_asks(snap: &BookSnapshot, side: Side, shares: Shares, max_price: Price) -> Option<(Shares, Price)> {
+    let mut levels: Vec<(Price, Shares)> = Vec::new();
+    let mut previous = 0.0;
+    for level in snap.ask_depth(side) {
+        levels.push((Price::from_f64(level.price), Shares::from_f64(level.cumulative_size - previous)));
+        previous = level.cumulative_size;
+    }
+    if levels.is_empty() {
+        let book = match side {
+            Side::Yes => &snap.yes,
+            Side::No => &snap.no,
+        };
+        if let (Some(ask), Some(size)) = (book.best_ask, book.best_ask_size) {
+            levels.push((Price::from_f64(ask), Shares::from_f64(size)));
+        }
+    }
+
+    let mut bought = Shares::default();
+    let mut cost = Cash::default();
+    for (price, size) in levels {
+        if price > max_price || bought >= shares {
+            break;
+        }
+        let take = Shares::from_units(size.units().min(shares.units() - bought.units()));
+        if take.units() <= 0 {
+            continue;
+        }
+        bought = Shares::from_units(bought.units() + take.units());
+        cost += take * price;
+    }
+    if bought.units() == 0 {
+        return None;
+    }
+    let average = (cost.units() + bought.units() as i128 - 1) / bought.units() as i128;
+    Some((bought, Price::from_units(average as i64)))
 }
@@ -1,4 +1,4 @@
-use crate::types::{BookSnapshot, Side, SideState};
+use crate::types::{BookSnapshot, Price, PriceLevel, Side, SideState, LEGACY_DEPTH_PRICES};
 
 /// Get the SideState for a given Side from a BookSnapshot.
 pub fn side_state(snap: &BookSnapshot, side: Side) -> &SideState {
@@ -11,10 +11,46 @@ pub fn side_state(snap: &BookSnapshot, side: Side) -> &SideState {
 /// Estimate queue position (shares ahead) for a new order at `price` on `side`.
 ///
 /// Uses the cumulative bid depth at the given price from the current snapshot.
-/// If no depth data is available, returns 0.
+/// A full ladder lists every price with size resting at it, so a price
+/// between two levels has the next better level's depth ahead of it. The
+/// sparse legacy ladder ([`LEGACY_DEPTH_PRICES`]) doesn't, so there a price
+/// between two levels interpolates linearly between their depths. Either
+/// way, below the lowest level everything captured is ahead, and above the
+/// highest nothing is. If no depth data is available, returns 0.
 pub fn queue_position(snap: &BookSnapshot, side: Side, price: f64) -> f64 {
+    const EPSILON: f64 = 1e-9;
+
     let state = side_state(snap, side);
-    state.bid_depth_at(price)
+    let legacy = state
+        .depth
+        .iter()
+        .all(|l| LEGACY_DEPTH_PRICES.iter().any(|&p| (l.price - p).abs() < EPSILON));
+    if !legacy {
+        return state.bid_depth_at(price);
+    }
+
+    let mut below: Option<&PriceLevel> = None;
+    let mut above: Option<&PriceLevel> = None;
+    for level in &state.depth {
+        if (level.price - price).abs() < EPSILON {
+            return level.cumulative_size;
+        }
+        if level.price < price {
+            if below.is_none_or(|b| level.price > b.price) {
+                below = Some(level);
+            }
+        } else if above.is_none_or(|a| level.price < a.price) {
+            above = Some(level);
+        }
+    }
+    match (below, above) {
+        (Some(lo), Some(hi)) => {
+            let t = (price - lo.price) / (hi.price - lo.price);
+            lo.cumulative_size + t * (hi.cumulative_size - lo.cumulative_size)
+        }
+        (None, Some(hi)) => hi.cumulative_size,
+        _ => 0.0,
+    }
 }
 
 /// Estimate taker volume consumed between two consecutive snapshots.
@@ -43,11 +79,12 @@ pub fn estimate_taker_volume(
 /// Check if an adverse tick occurred: the best ask dropped to or below our bid price.
 ///
 /// This means someone is aggressively selling into the bids at our price level,
-/// sweeping through resting orders.
-pub fn is_adverse_tick(snap: &BookSnapshot, side: Side, our_bid: f64) -> bool {
+/// sweeping through resting orders. The ask is compared as a [`Price`], so
+/// float noise in the feed cannot hide a touch.
+pub fn is_adverse_tick(snap: &BookSnapshot, side: Side, our_bid: Price) -> bool {
     let state = side_state(snap, side);
     match state.best_ask {
-        Some(ask) => ask <= our_bid,
+        Some(ask) => Price::from_f64(ask) <= our_bid,
         None => false,
     }
 }
@@ -55,7 +92,8 @@ pub fn is_adverse_tick(snap: &BookSnapshot, side: Side, our_bid: f64) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{BookSnapshot, PriceLevel, SideState};
+    use std::sync::Arc;
+    use crate::types::{BookSnapshot, OffsetMs, PriceLevel, SideState};
 
     fn make_snap(
         best_bid: Option<f64>,
@@ -78,11 +116,11 @@ mod tests {
             total_ask_depth: 0.0,
         };
         BookSnapshot {
-            market_id: "test".to_string(),
-            offset_ms: 0,
+            market_id: "test".into(),
+            offset_ms: OffsetMs::new(0),
             timestamp_ms: 0,
-            yes: side,
-            no: SideState::default(),
+            yes: Arc::new(side),
+            no: Arc::default(),
             reference_price: None,
             oracle_price: None,
         }
@@ -94,6 +132,29 @@ mod tests {
         assert_eq!(queue_position(&snap, Side::Yes, 0.49), 500.0);
     }
 
+    #[test]
+    fn test_queue_position_full_ladder_takes_next_better_level() {
+        let snap = make_snap(
+            Some(0.52),
+            Some(0.53),
+            vec![(0.40, 2000.0), (0.45, 1000.0), (0.50, 400.0), (0.52, 100.0)],
+        );
+        assert_eq!(queue_position(&snap, Side::Yes, 0.47), 400.0);
+        assert_eq!(queue_position(&snap, Side::Yes, 0.51), 100.0);
+        assert_eq!(queue_position(&snap, Side::Yes, 0.45), 1000.0);
+        assert_eq!(queue_position(&snap, Side::Yes, 0.30), 2000.0);
+        assert_eq!(queue_position(&snap, Side::Yes, 0.55), 0.0);
+    }
+
+    #[test]
+    fn test_queue_position_interpolates_legacy_levels() {
+        let snap = make_snap(Some(0.51), Some(0.52), vec![(0.49, 500.0), (0.51, 100.0)]);
+        assert!((queue_position(&snap, Side::Yes, 0.50) - 300.0).abs() < 1e-9);
+        assert_eq!(queue_position(&snap, Side::Yes, 0.49), 500.0);
+        assert_eq!(queue_position(&snap, Side::Yes, 0.45), 500.0);
+        assert_eq!(queue_position(&snap, Side::Yes, 0.52), 0.0);
+    }
+
     #[test]
     fn test_queue_position_empty_depth() {
         let snap = make_snap(Some(0.49), Some(0.51), vec![]);
@@ -119,18 +180,22 @@ mod tests {
     #[test]
     fn test_adverse_tick_detected() {
         let snap = make_snap(Some(0.49), Some(0.49), vec![(0.49, 100.0)]);
-        assert!(is_adverse_tick(&snap, Side::Yes, 0.49));
+        assert!(is_adverse_tick(&snap, Side::Yes, Price::from_f64(0.49)));
+
+        // One ulp above 0.49 from float noise upstream: still a touch at 0.49.
+        let snap = make_snap(Some(0.48), Some(0.49000000000000005), vec![(0.49, 100.0)]);
+        assert!(is_adverse_tick(&snap, Side::Yes, Price::from_f64(0.49)));
     }
 
     #[test]
     fn test_no_adverse_tick() {
         let snap = make_snap(Some(0.49), Some(0.51), vec![(0.49, 100.0)]);
-        assert!(!is_adverse_tick(&snap, Side::Yes, 0.49));
+        assert!(!is_adverse_tick(&snap, Side::Yes, Price::from_f64(0.49)));
     }
 
     #[test]
     fn test_adverse_tick_no_ask() {
         let snap = make_snap(Some(0.49), None, vec![(0.49, 100.0)]);
-        assert!(!is_adverse_tick(&snap, Side::Yes, 0.49));
+        assert!(!is_adverse_tick(&snap, Side::Yes, Price::from_f64(0.49)));
     }
 }
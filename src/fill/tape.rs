@@ -0,0 +1,276 @@
+//! Trade-tape fill model.
+//!
+//! Where a market's executed trades were imported (`pf_trades`), there is no
+//! need to guess taker flow from depth changes: every trade that printed at
+//! our price ate into the queue ahead of us, and every trade that printed
+//! below it went through our level entirely.
+//!
+//! - Queue position at placement is the bid depth at our price, as in DeLise.
+//! - A trade at our price on our side consumes its size from the queue;
+//!   what gets past our position fills the order, in part if need be.
+//! - A trade below our price fills whatever is left of the order.
+//! - Trades whose taker bought (lifting asks) never touch the bids and are
+//!   skipped; trades without a taker side are counted.
+//!
+//! Markets without a tape never fill, so this model only makes sense on
+//! data imported with trades.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::data::DataStore;
+use crate::fill::model::FillModel;
+use crate::fill::queue;
+use crate::types::{
+    BookSnapshot, Market, MarketId, OffsetMs, OrderId, Price, Shares, Side, SimOrder, TakerSide, Trade,
+};
+
+/// Executed trades by market, each market's sorted by offset. Cheap to
+/// clone, so one tape serves every run of a backtest.
+#[derive(Debug, Clone, Default)]
+pub struct TradeTape {
+    trades: Arc<HashMap<MarketId, Vec<Trade>>>,
+}
+
+impl TradeTape {
+    /// Build a tape from trades in any order.
+    pub fn new(trades: impl IntoIterator<Item = Trade>) -> Self {
+        let mut by_market: HashMap<MarketId, Vec<Trade>> = HashMap::new();
+        for trade in trades {
+            by_market.entry(trade.market_id.clone()).or_default().push(trade);
+        }
+        for trades in by_market.values_mut() {
+            trades.sort_by_key(|t| t.offset_ms);
+        }
+        Self { trades: Arc::new(by_market) }
+    }
+
+    /// Load the tape of every market in `markets` from `store`.
+    pub fn load(store: &dyn DataStore, markets: &[Market]) -> Result<Self> {
+        let mut trades = Vec::new();
+        for market in markets {
+            trades.extend(store.load_trades(&market.id)?);
+        }
+        Ok(Self::new(trades))
+    }
+
+    /// Total trades across all markets.
+    pub fn len(&self) -> usize {
+        self.trades.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Trades in `market_id` with offsets in `(after, through]`.
+    pub fn between(&self, market_id: &str, after: OffsetMs, through: OffsetMs) -> &[Trade] {
+        let Some(trades) = self.trades.get(market_id) else {
+            return &[];
+        };
+        let start = trades.partition_point(|t| t.offset_ms <= after);
+        let end = trades.partition_point(|t| t.offset_ms <= through);
+        &trades[start..end.max(start)]
+    }
+}
+
+/// Fill model driven by a market's actual trade tape.
+#[derive(Debug, Clone)]
+pub struct TradeTapeFillModel {
+    tape: TradeTape,
+}
+
+impl TradeTapeFillModel {
+    pub fn new(tape: TradeTape) -> Self {
+        Self { tape }
+    }
+}
+
+impl FillModel for TradeTapeFillModel {
+    fn name(&self) -> &str {
+        "trade-tape"
+    }
+
+    fn create_order(
+        &self,
+        side: Side,
+        price: Price,
+        shares: Shares,
+        snap: &BookSnapshot,
+        offset_ms: OffsetMs,
+    ) -> SimOrder {
+        SimOrder {
+            id: OrderId::default(),
+            side,
+            price,
+            shares,
+            placed_at_ms: offset_ms,
+            queue_ahead: queue::queue_position(snap, side, price.to_f64()),
+            queue_consumed: 0.0,
+            filled: false,
+            filled_shares: Shares::default(),
+            filled_at_ms: None,
+            cancelled_at_ms: None,
+            taker: false,
+            sell: false,
+        }
+    }
+
+    fn process_tick(
+        &self,
+        snap: &BookSnapshot,
+        orders: &mut [SimOrder],
+        prev_offset_ms: OffsetMs,
+    ) -> Vec<usize> {
+        let mut filled_indices = Vec::new();
+
+        for (i, order) in orders.iter_mut().enumerate() {
+            // Orders cannot be filled on the same tick they were placed.
+            if !order.is_open() || order.placed_at_ms == snap.offset_ms {
+                continue;
+            }
+
+            let after = prev_offset_ms.max(order.placed_at_ms);
+            let before = order.filled_shares;
+            for trade in self.tape.between(&snap.market_id, after, snap.offset_ms) {
+                if trade.side != order.side || trade.taker_side == Some(TakerSide::Buy) {
+                    continue;
+                }
+                let price = Price::from_f64(trade.price);
+                if price < order.price {
+                    // Printed through our level: nothing is left ahead of us.
+                    order.queue_consumed = order.queue_consumed.max(order.queue_ahead);
+                    order.fill_all(snap.offset_ms);
+                    break;
+                }
+                if price == order.price {
+                    order.queue_consumed += trade.size;
+                    let past_us = order.queue_consumed - order.queue_ahead;
+                    if past_us > 0.0 {
+                        order.fill(Shares::from_f64(past_us) - order.filled_shares, snap.offset_ms);
+                    }
+                    if !order.is_open() {
+                        break;
+                    }
+                }
+            }
+            if order.filled_shares > before {
+                filled_indices.push(i);
+            }
+        }
+
+        filled_indices
+    }
+
+    /// Fills come from trades that actually printed, so every one stands.
+    fn adverse_selection_filter(&self, order: &SimOrder, _is_winner: bool) -> bool {
+        order.filled_at_ms.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PriceLevel, SideState};
+
+    fn snap(offset_ms: i64) -> BookSnapshot {
+        let yes = SideState {
+            best_bid: Some(0.49),
+            best_bid_size: Some(100.0),
+            best_ask: Some(0.51),
+            best_ask_size: Some(100.0),
+            depth: [PriceLevel { price: 0.49, cumulative_size: 100.0 }].into_iter().collect(),
+            total_bid_depth: 100.0,
+            total_ask_depth: 100.0,
+        };
+        BookSnapshot {
+            market_id: "m1".into(),
+            offset_ms: OffsetMs::new(offset_ms),
+            timestamp_ms: offset_ms,
+            yes: Arc::new(yes),
+            no: Arc::default(),
+            reference_price: None,
+            oracle_price: None,
+        }
+    }
+
+    fn trade(offset_ms: i64, side: Side, price: f64, size: f64, taker_side: Option<TakerSide>) -> Trade {
+        Trade {
+            market_id: "m1".into(),
+            side,
+            timestamp_ms: offset_ms,
+            offset_ms: OffsetMs::new(offset_ms),
+            price,
+            size,
+            taker_side,
+        }
+    }
+
+    fn bid(model: &TradeTapeFillModel) -> Vec<SimOrder> {
+        vec![model.create_order(Side::Yes, Price::from_f64(0.49), Shares::from_f64(10.0), &snap(1000), OffsetMs::new(1000))]
+    }
+
+    #[test]
+    fn test_trades_at_our_price_work_through_the_queue() {
+        let model = TradeTapeFillModel::new(TradeTape::new([
+            trade(1000, Side::Yes, 0.49, 500.0, None), // same tick as placement: ignored
+            trade(1500, Side::Yes, 0.49, 96.0, Some(TakerSide::Sell)),
+            trade(1600, Side::No, 0.49, 500.0, None), // other side
+            trade(1700, Side::Yes, 0.49, 500.0, Some(TakerSide::Buy)), // lifted asks
+            trade(1800, Side::Yes, 0.50, 500.0, None), // above our bid
+            trade(2500, Side::Yes, 0.49, 8.0, None),
+            trade(3500, Side::Yes, 0.49, 50.0, None),
+        ]));
+        let mut orders = bid(&model);
+        assert_eq!(orders[0].queue_ahead, 100.0);
+
+        assert!(model.process_tick(&snap(2000), &mut orders, OffsetMs::new(1000)).is_empty());
+        assert_eq!(orders[0].queue_consumed, 96.0);
+
+        // 104 traded against 100 ahead: 4 of our 10 shares fill.
+        assert_eq!(model.process_tick(&snap(3000), &mut orders, OffsetMs::new(2000)), vec![0]);
+        assert_eq!(orders[0].filled_shares, Shares::from_f64(4.0));
+        assert!(orders[0].is_open());
+
+        assert_eq!(model.process_tick(&snap(4000), &mut orders, OffsetMs::new(3000)), vec![0]);
+        assert!(orders[0].filled);
+        assert_eq!(orders[0].filled_at_ms, Some(OffsetMs::new(3000)));
+        assert!(model.adverse_selection_filter(&orders[0], true));
+    }
+
+    #[test]
+    fn test_trade_below_our_price_fills_the_rest() {
+        let model = TradeTapeFillModel::new(TradeTape::new([trade(1500, Side::Yes, 0.48, 1.0, None)]));
+        let mut orders = bid(&model);
+        assert_eq!(model.process_tick(&snap(2000), &mut orders, OffsetMs::new(1000)), vec![0]);
+        assert!(orders[0].filled);
+    }
+
+    #[test]
+    fn test_no_tape_never_fills() {
+        let model = TradeTapeFillModel::new(TradeTape::default());
+        let mut orders = bid(&model);
+        assert!(model.process_tick(&snap(2000), &mut orders, OffsetMs::new(1000)).is_empty());
+        assert!(!model.adverse_selection_filter(&orders[0], false));
+    }
+
+    #[test]
+    fn test_tape_between_is_half_open() {
+        let tape = TradeTape::new([
+            trade(3000, Side::Yes, 0.49, 1.0, None),
+            trade(1000, Side::Yes, 0.49, 1.0, None),
+            trade(2000, Side::Yes, 0.49, 1.0, None),
+        ]);
+        assert_eq!(tape.len(), 3);
+        let offsets: Vec<_> = tape
+            .between("m1", OffsetMs::new(1000), OffsetMs::new(3000))
+            .iter()
+            .map(|t| t.offset_ms)
+            .collect();
+        assert_eq!(offsets, vec![OffsetMs::new(2000), OffsetMs::new(3000)]);
+        assert!(tape.between("m1", OffsetMs::new(3000), OffsetMs::new(1000)).is_empty());
+        assert!(tape.between("m2", OffsetMs::new(0), OffsetMs::new(9000)).is_empty());
+    }
+}
@@ -0,0 +1,412 @@
+//! gRPC service (`grpc` feature): `pf grpc`.
+//!
+//! A typed alternative to [`server`](crate::server) for services that drive
+//! backtests programmatically. The contract lives in
+//! `proto/phantomfill.proto` (package `phantomfill.v1`):
+//!
+//! | RPC                   | Description                                       |
+//! |-----------------------|---------------------------------------------------|
+//! | `RunBacktest`         | Report, per-window results and Monte Carlo summary |
+//! | `StreamWindowResults` | Each window's result as it finishes, for all runs |
+//! | `ListMarkets`         | Markets matching a filter                         |
+//!
+//! Calls run concurrently, each on a blocking thread with its own
+//! connection to the database.
+
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use tracing::info;
+
+use crate::backtest::{BacktestConfig, RunOutput};
+use crate::data::{DataStore, MarketFilter, SqliteStore};
+use crate::report::{MonteCarloSummary, Report};
+use crate::strategies::is_known_strategy;
+use crate::types::{Market, Platform, UnixSecs, WindowResult};
+
+/// Generated messages and service traits.
+pub mod proto {
+    tonic::include_proto!("phantomfill.v1");
+}
+
+use proto::backtester_server::{Backtester, BacktesterServer};
+
+impl From<&Report> for proto::Report {
+    fn from(r: &Report) -> Self {
+        Self {
+            strategy_name: r.strategy_name.clone(),
+            fill_model_name: r.fill_model_name.clone(),
+            total_windows: r.total_windows as u64,
+            trades_taken: r.trades_taken as u64,
+            fills: r.fills as u64,
+            correct: r.correct as u64,
+            skipped: r.skipped as u64,
+            aborted: r.aborted as u64,
+            script_errors: r.script_errors as u64,
+            error_windows: r.error_windows as u64,
+            fill_rate: r.fill_rate,
+            naive_win_rate: r.naive_win_rate,
+            realistic_win_rate: r.realistic_win_rate,
+            naive_total_pnl: r.naive_total_pnl,
+            realistic_total_pnl: r.realistic_total_pnl,
+            phantom_fill_gap: r.phantom_fill_gap,
+            avg_naive_pnl: r.avg_naive_pnl,
+            avg_realistic_pnl: r.avg_realistic_pnl,
+            avg_queue_ahead: r.avg_queue_ahead,
+            avg_fill_time_ms: r.avg_fill_time_ms,
+            rejected_orders: r.rejected_orders as u64,
+            void: r.void as u64,
+            unresolved: r.unresolved as u64,
+            bankroll: r.bankroll.as_ref().map(|b| proto::BankrollSummary {
+                starting: b.starting,
+                terminal: b.terminal,
+                peak: b.peak,
+                max_drawdown: b.max_drawdown,
+                max_drawdown_pct: b.max_drawdown_pct,
+                equity_curve: b.equity_curve.clone(),
+            }),
+        }
+    }
+}
+
+impl From<&MonteCarloSummary> for proto::MonteCarloSummary {
+    fn from(s: &MonteCarloSummary) -> Self {
+        Self {
+            runs: s.runs as u64,
+            seed: s.seed,
+            naive_total_pnl: s.naive_total_pnl,
+            realistic_pnl_mean: s.realistic_pnl_mean,
+            realistic_pnl_median: s.realistic_pnl_median,
+            realistic_pnl_p5: s.realistic_pnl_p5,
+            realistic_pnl_p95: s.realistic_pnl_p95,
+            realistic_pnl_std: s.realistic_pnl_std,
+            fill_rate_mean: s.fill_rate_mean,
+            win_rate_mean: s.win_rate_mean,
+            phantom_gap_median: s.phantom_gap_median,
+            reports: s.reports.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<&WindowResult> for proto::WindowResult {
+    fn from(w: &WindowResult) -> Self {
+        Self {
+            run: 0,
+            market_id: w.market_id.clone(),
+            platform: w.platform.clone(),
+            category: w.category.clone(),
+            open_ts: w.open_ts,
+            close_ts: w.close_ts,
+            outcome: w.outcome.clone(),
+            predicted: w.predicted.clone(),
+            signal_offset_ms: w.signal_offset_ms,
+            bid_side: w.bid_side.clone(),
+            bid_price: w.bid_price,
+            shares: w.shares,
+            filled: w.filled,
+            queue_ahead_at_place: w.queue_ahead_at_place,
+            fill_time_ms: w.fill_time_ms,
+            correct: w.correct,
+            realistic_pnl: w.realistic_pnl,
+            naive_pnl: w.naive_pnl,
+            ref_price_open: w.ref_price_open,
+            ref_price_close: w.ref_price_close,
+            aborted: w.aborted.clone(),
+            script_errors: w.script_errors as u64,
+            rejected_orders: w.rejected_orders as u64,
+            rejection: w.rejection.clone(),
+            stake: w.stake,
+            bankroll: w.bankroll,
+        }
+    }
+}
+
+impl From<&Market> for proto::Market {
+    fn from(m: &Market) -> Self {
+        Self {
+            id: m.id.clone(),
+            platform: m.platform.to_string(),
+            description: m.description.clone(),
+            category: m.category.clone(),
+            open_ts: m.open_ts.secs(),
+            close_ts: m.close_ts.secs(),
+            duration_secs: m.duration_secs,
+            outcome: m.outcome.map(|o| o.label().to_string()),
+            outcome_ts: m.outcome_ts.map(UnixSecs::secs),
+        }
+    }
+}
+
+impl From<&RunOutput> for proto::BacktestResponse {
+    fn from(output: &RunOutput) -> Self {
+        Self {
+            report: Some((&output.report).into()),
+            results: output.results.iter().map(Into::into).collect(),
+            summary: output.summary.as_ref().map(Into::into),
+        }
+    }
+}
+
+fn market_filter(filter: Option<proto::MarketFilter>) -> MarketFilter {
+    let filter = filter.unwrap_or_default();
+    MarketFilter {
+        platform: filter.platform.map(Platform::from),
+        category: filter.category,
+        duration_secs: filter.duration_secs,
+        min_ts: filter.min_ts,
+        max_ts: filter.max_ts,
+    }
+}
+
+/// Validate a request and turn it into a [`BacktestConfig`], applying the
+/// `pf run` defaults for unset fields.
+fn backtest_config(req: proto::BacktestRequest) -> anyhow::Result<BacktestConfig> {
+    let filter = market_filter(req.filter);
+    let defaults = BacktestConfig::default();
+    let config = BacktestConfig {
+        strategy: req.strategy.unwrap_or(defaults.strategy),
+        script: req.script.map(PathBuf::from),
+        script_source: req.script_source,
+        params: req.params,
+        bid_price: req.bid_price.unwrap_or(defaults.bid_price),
+        shares: req.shares.unwrap_or(defaults.shares),
+        min_bps: req.min_bps.unwrap_or(defaults.min_bps),
+        fees: defaults.fees,
+        bankroll: defaults.bankroll,
+        latency: defaults.latency,
+        portfolio: defaults.portfolio,
+        platform: filter.platform,
+        category: filter.category,
+        duration_secs: filter.duration_secs,
+        min_ts: filter.min_ts,
+        max_ts: filter.max_ts,
+        limit: req.limit.map(|n| n as usize),
+        sample: req.sample.map(|n| n as usize),
+        seed: req.seed,
+        runs: req.runs.map_or(defaults.runs, |n| n as usize),
+    };
+    if config.script.is_none() && config.script_source.is_none() && !is_known_strategy(&config.strategy) {
+        bail!("unknown strategy `{}`", config.strategy);
+    }
+    if config.runs == 0 {
+        bail!("runs must be at least 1");
+    }
+    Ok(config)
+}
+
+fn invalid(err: anyhow::Error) -> Status {
+    Status::invalid_argument(format!("{:#}", err))
+}
+
+fn internal(err: impl std::fmt::Display) -> Status {
+    Status::internal(err.to_string())
+}
+
+fn backtest_failed(err: anyhow::Error) -> Status {
+    Status::internal(format!("{:#}", err))
+}
+
+/// [`Backtester`] over one native database.
+#[derive(Debug, Clone)]
+pub struct BacktestService {
+    db: PathBuf,
+}
+
+impl BacktestService {
+    pub fn new(db: impl Into<PathBuf>) -> Self {
+        Self { db: db.into() }
+    }
+
+    fn open(&self) -> anyhow::Result<SqliteStore> {
+        SqliteStore::open(&self.db).with_context(|| format!("failed to open {}", self.db.display()))
+    }
+}
+
+#[tonic::async_trait]
+impl Backtester for BacktestService {
+    async fn run_backtest(
+        &self,
+        request: Request<proto::BacktestRequest>,
+    ) -> Result<Response<proto::BacktestResponse>, Status> {
+        let config = backtest_config(request.into_inner()).map_err(invalid)?;
+        let service = self.clone();
+        let output = tokio::task::spawn_blocking(move || config.run(&service.open()?))
+            .await
+            .map_err(internal)?
+            .map_err(backtest_failed)?;
+        Ok(Response::new((&output).into()))
+    }
+
+    type StreamWindowResultsStream = ReceiverStream<Result<proto::WindowResult, Status>>;
+
+    async fn stream_window_results(
+        &self,
+        request: Request<proto::BacktestRequest>,
+    ) -> Result<Response<Self::StreamWindowResultsStream>, Status> {
+        let config = backtest_config(request.into_inner()).map_err(invalid)?;
+        let service = self.clone();
+        let (tx, rx) = mpsc::channel(64);
+        tokio::task::spawn_blocking(move || {
+            let outcome = service.open().and_then(|store| {
+                config.run_streaming(&store, |run, window| {
+                    let mut message = proto::WindowResult::from(window);
+                    message.run = run as u32;
+                    // A closed channel means the client went away; the run
+                    // finishes regardless.
+                    let _ = tx.blocking_send(Ok(message));
+                })
+            });
+            if let Err(e) = outcome {
+                let _ = tx.blocking_send(Err(backtest_failed(e)));
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn list_markets(
+        &self,
+        request: Request<proto::ListMarketsRequest>,
+    ) -> Result<Response<proto::ListMarketsResponse>, Status> {
+        let filter = market_filter(request.into_inner().filter);
+        let service = self.clone();
+        let markets = tokio::task::spawn_blocking(move || service.open()?.list_markets(&filter))
+            .await
+            .map_err(internal)?
+            .map_err(backtest_failed)?;
+        Ok(Response::new(proto::ListMarketsResponse {
+            markets: markets.iter().map(Into::into).collect(),
+        }))
+    }
+}
+
+/// Serve `db` over gRPC on `addr` until the process is stopped.
+pub fn serve(db: &Path, addr: &str) -> anyhow::Result<()> {
+    let addr: SocketAddr = addr.parse().with_context(|| format!("invalid address `{}`", addr))?;
+    let service = BacktestService::new(db);
+    // Fail fast on a bad path before binding the port.
+    service.open()?;
+
+    let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
+    runtime.block_on(async {
+        info!("serving {} over gRPC on {}", db.display(), addr);
+        tonic::transport::Server::builder()
+            .add_service(BacktesterServer::new(service))
+            .serve(addr)
+            .await
+            .with_context(|| format!("gRPC server on {} failed", addr))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::{generate_dataset, BenchConfig};
+    use tokio_stream::StreamExt;
+
+    fn seeded_db(dir: &Path) -> PathBuf {
+        let path = dir.join("grpc.db");
+        let store = SqliteStore::open(&path).unwrap();
+        store.init().unwrap();
+        let config = BenchConfig {
+            markets: 4,
+            snapshots_per_market: 20,
+            seed: 7,
+        };
+        for (market, ticks) in generate_dataset(&config) {
+            store.insert_market(&market).unwrap();
+            store.insert_ticks(&ticks).unwrap();
+        }
+        path
+    }
+
+    fn request(strategy: &str, runs: u32) -> proto::BacktestRequest {
+        proto::BacktestRequest {
+            strategy: Some(strategy.into()),
+            seed: Some(3),
+            runs: Some(runs),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_request_defaults_and_validation() {
+        let config = backtest_config(proto::BacktestRequest::default()).unwrap();
+        assert_eq!(config.strategy, "momentum");
+        assert_eq!(config.bid_price, 0.49);
+        assert_eq!(config.runs, 1);
+
+        let filtered = backtest_config(proto::BacktestRequest {
+            filter: Some(proto::MarketFilter {
+                platform: Some("kalshi".into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(filtered.platform, Some(Platform::Kalshi));
+
+        let other_platform = backtest_config(proto::BacktestRequest {
+            filter: Some(proto::MarketFilter {
+                platform: Some("betfair".into()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(other_platform.platform, Some(Platform::Other("betfair".into())));
+        assert!(backtest_config(request("nope", 1)).unwrap_err().to_string().contains("unknown strategy"));
+        assert!(backtest_config(request("spread_arb", 0)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_service_runs_streams_and_lists() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = BacktestService::new(seeded_db(dir.path()));
+
+        let markets = service
+            .list_markets(Request::new(proto::ListMarketsRequest::default()))
+            .await
+            .unwrap()
+            .into_inner()
+            .markets;
+        assert_eq!(markets.len(), 4);
+        assert_eq!(markets[0].platform, "polymarket");
+
+        let response = service
+            .run_backtest(Request::new(request("spread_arb", 2)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.report.unwrap().total_windows, 4);
+        assert_eq!(response.results.len(), 4);
+        assert_eq!(response.summary.unwrap().reports.len(), 2);
+
+        let stream = service
+            .stream_window_results(Request::new(request("spread_arb", 2)))
+            .await
+            .unwrap()
+            .into_inner();
+        let windows: Vec<_> = stream.collect::<Result<_, _>>().await.unwrap();
+        assert_eq!(windows.len(), 8);
+        assert_eq!(windows.iter().filter(|w| w.run == 1).count(), 4);
+        let first: Vec<_> = windows.iter().take(4).map(|w| &w.market_id).collect();
+        let ids: Vec<_> = response.results.iter().map(|w| &w.market_id).collect();
+        assert_eq!(first, ids);
+
+        let err = service
+            .run_backtest(Request::new(request("nope", 1)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        let missing = BacktestService::new(dir.path().join("missing").join("x.db"));
+        let err = missing
+            .run_backtest(Request::new(request("spread_arb", 1)))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::Internal);
+    }
+}
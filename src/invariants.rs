@@ -0,0 +1,250 @@
+//! Snapshot invariant checks.
+//!
+//! A corrupt capture (a crossed book, negative sizes, depth that shrinks
+//! away from the touch, a snapshot stamped outside its window) can hand the
+//! fill model fills no real book would give. With
+//! [`ReplayConfig::snapshot_check`](crate::replay::ReplayConfig::snapshot_check)
+//! set, the engine checks every snapshot before using it and skips it,
+//! repairs it or aborts the window, logging each [`Violation`].
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{BookSnapshot, OffsetMs, Side, SideState};
+
+/// What the engine does with a snapshot that breaks an invariant.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotCheck {
+    /// Use snapshots as they are.
+    #[default]
+    Off,
+    /// Drop the snapshot.
+    Skip,
+    /// Clamp negative sizes, drop crossed quotes and restore cumulative
+    /// depth, then use the snapshot. Snapshots outside the window or out of
+    /// order can't be repaired and are dropped.
+    Repair,
+    /// Abort the window, with the violations as the reason.
+    Abort,
+}
+
+/// One broken invariant.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// The side's best bid is above its best ask.
+    Crossed { side: Side, best_bid: f64, best_ask: f64 },
+    /// A size or depth is negative (or NaN).
+    NegativeSize { side: Side, field: &'static str, value: f64 },
+    /// Cumulative depth at `price` is smaller than at the better `previous_price`.
+    NonMonotoneDepth {
+        side: Side,
+        price: f64,
+        cumulative_size: f64,
+        previous_price: f64,
+        previous_size: f64,
+    },
+    /// The offset is before the open or after the end of the window.
+    OutOfWindow { offset_ms: OffsetMs, end_ms: OffsetMs },
+    /// The offset is earlier than the previous snapshot's.
+    OutOfOrder { offset_ms: OffsetMs, previous_ms: OffsetMs },
+}
+
+impl Violation {
+    /// Whether [`repair`] fixes this violation.
+    pub fn is_repairable(&self) -> bool {
+        !matches!(self, Violation::OutOfWindow { .. } | Violation::OutOfOrder { .. })
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Crossed { side, best_bid, best_ask } => {
+                write!(f, "{} book crossed: best bid {} > best ask {}", side, best_bid, best_ask)
+            }
+            Violation::NegativeSize { side, field, value } => write!(f, "{} {} is {}", side, field, value),
+            Violation::NonMonotoneDepth {
+                side,
+                price,
+                cumulative_size,
+                previous_price,
+                previous_size,
+            } => write!(
+                f,
+                "{} cumulative depth {} at {} is below {} at {}",
+                side, cumulative_size, price, previous_size, previous_price
+            ),
+            Violation::OutOfWindow { offset_ms, end_ms } => {
+                write!(f, "offset {}ms is outside the window (0..={}ms)", offset_ms, end_ms)
+            }
+            Violation::OutOfOrder { offset_ms, previous_ms } => {
+                write!(f, "offset {}ms is before the previous snapshot at {}ms", offset_ms, previous_ms)
+            }
+        }
+    }
+}
+
+/// Depth levels from the best (highest) price down, as `(price, cumulative_size)`.
+fn levels_best_first(state: &SideState) -> Vec<(f64, f64)> {
+    let mut levels: Vec<(f64, f64)> = state.depth.iter().map(|l| (l.price, l.cumulative_size)).collect();
+    levels.sort_by(|a, b| b.0.total_cmp(&a.0));
+    levels
+}
+
+fn is_negative(value: f64) -> bool {
+    value.is_nan() || value < 0.0
+}
+
+fn side_violations(side: Side, state: &SideState, out: &mut Vec<Violation>) {
+    let sizes = [
+        ("best_bid_size", state.best_bid_size),
+        ("best_ask_size", state.best_ask_size),
+        ("total_bid_depth", Some(state.total_bid_depth)),
+        ("total_ask_depth", Some(state.total_ask_depth)),
+    ];
+    for (field, value) in sizes {
+        if let Some(value) = value.filter(|v| is_negative(*v)) {
+            out.push(Violation::NegativeSize { side, field, value });
+        }
+    }
+    if let (Some(best_bid), Some(best_ask)) = (state.best_bid, state.best_ask) {
+        if best_bid > best_ask {
+            out.push(Violation::Crossed { side, best_bid, best_ask });
+        }
+    }
+    let levels = levels_best_first(state);
+    for &(_, cumulative_size) in &levels {
+        if is_negative(cumulative_size) {
+            out.push(Violation::NegativeSize {
+                side,
+                field: "depth cumulative_size",
+                value: cumulative_size,
+            });
+        }
+    }
+    for pair in levels.windows(2) {
+        let ((previous_price, previous_size), (price, cumulative_size)) = (pair[0], pair[1]);
+        if cumulative_size < previous_size {
+            out.push(Violation::NonMonotoneDepth {
+                side,
+                price,
+                cumulative_size,
+                previous_price,
+                previous_size,
+            });
+        }
+    }
+}
+
+/// Every invariant `snap` breaks. `previous_ms` is the offset of the last
+/// snapshot used in the window, and `end_ms` the last offset it accepts.
+pub fn violations(snap: &BookSnapshot, previous_ms: Option<OffsetMs>, end_ms: OffsetMs) -> Vec<Violation> {
+    let mut out = Vec::new();
+    if snap.offset_ms < 0 || snap.offset_ms > end_ms {
+        out.push(Violation::OutOfWindow {
+            offset_ms: snap.offset_ms,
+            end_ms,
+        });
+    }
+    if let Some(previous_ms) = previous_ms.filter(|p| snap.offset_ms < *p) {
+        out.push(Violation::OutOfOrder {
+            offset_ms: snap.offset_ms,
+            previous_ms,
+        });
+    }
+    side_violations(Side::Yes, &snap.yes, &mut out);
+    side_violations(Side::No, &snap.no, &mut out);
+    out
+}
+
+fn repair_side(state: &mut SideState) {
+    let clamp = |v: f64| if is_negative(v) { 0.0 } else { v };
+    state.best_bid_size = state.best_bid_size.map(clamp);
+    state.best_ask_size = state.best_ask_size.map(clamp);
+    state.total_bid_depth = clamp(state.total_bid_depth);
+    state.total_ask_depth = clamp(state.total_ask_depth);
+    // Neither quote of a crossed book can be trusted.
+    if matches!((state.best_bid, state.best_ask), (Some(bid), Some(ask)) if bid > ask) {
+        state.best_bid = None;
+        state.best_bid_size = None;
+        state.best_ask = None;
+        state.best_ask_size = None;
+    }
+    state.depth.sort_by(|a, b| b.price.total_cmp(&a.price));
+    let mut running = 0.0;
+    for level in state.depth.iter_mut() {
+        running = clamp(level.cumulative_size).max(running);
+        level.cumulative_size = running;
+    }
+}
+
+/// `snap` with its repairable violations fixed. Sides without violations
+/// stay shared with `snap`.
+pub fn repair(snap: &BookSnapshot) -> BookSnapshot {
+    let mut repaired = snap.clone();
+    for (side, state) in [(Side::Yes, &mut repaired.yes), (Side::No, &mut repaired.no)] {
+        let mut found = Vec::new();
+        side_violations(side, state, &mut found);
+        if !found.is_empty() {
+            repair_side(Arc::make_mut(state));
+        }
+    }
+    repaired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::PriceLevel;
+
+    fn snapshot(yes: SideState) -> BookSnapshot {
+        BookSnapshot {
+            market_id: "m".into(),
+            offset_ms: OffsetMs::new(1_000),
+            timestamp_ms: 0,
+            yes: Arc::new(yes),
+            no: Arc::new(SideState::default()),
+            reference_price: None,
+            oracle_price: None,
+        }
+    }
+
+    #[test]
+    fn test_violations_are_found_and_repaired() {
+        let level = |price, cumulative_size| PriceLevel { price, cumulative_size };
+        let snap = snapshot(SideState {
+            best_bid: Some(0.55),
+            best_bid_size: Some(-3.0),
+            best_ask: Some(0.50),
+            best_ask_size: Some(10.0),
+            depth: [level(0.49, 100.0), level(0.48, 40.0), level(0.47, 120.0)].into_iter().collect(),
+            total_bid_depth: 120.0,
+            total_ask_depth: 50.0,
+        });
+        let found = violations(&snap, Some(OffsetMs::new(2_000)), OffsetMs::new(300_000));
+        assert_eq!(found.len(), 4, "{:?}", found);
+        assert!(matches!(found[0], Violation::OutOfOrder { .. }));
+        assert!(!found[0].is_repairable());
+        assert_eq!(found[1].to_string(), "YES best_bid_size is -3");
+        assert_eq!(found[2].to_string(), "YES book crossed: best bid 0.55 > best ask 0.5");
+        assert_eq!(found[3].to_string(), "YES cumulative depth 40 at 0.48 is below 100 at 0.49");
+
+        let fixed = repair(&snap);
+        assert!(violations(&fixed, None, OffsetMs::new(300_000)).is_empty());
+        assert_eq!(fixed.yes.best_bid, None);
+        assert_eq!(fixed.yes.depth[1].cumulative_size, 100.0);
+        assert!(Arc::ptr_eq(&fixed.no, &snap.no));
+
+        let late = BookSnapshot {
+            offset_ms: OffsetMs::new(301_000),
+            ..snapshot(SideState::default())
+        };
+        assert_eq!(
+            violations(&late, None, OffsetMs::new(300_000))[0].to_string(),
+            "offset 301000ms is outside the window (0..=300000ms)"
+        );
+    }
+}
@@ -1,6 +1,38 @@
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod backtest;
+#[cfg(feature = "sqlite")]
+pub mod bench;
+pub mod checkpoint;
 pub mod data;
+#[cfg(all(feature = "sqlite", feature = "net"))]
+pub mod doctor;
+pub mod events;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod fill;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod invariants;
+#[cfg(feature = "live")]
+pub mod live;
+#[cfg(feature = "cli")]
+pub mod logging;
+pub mod portfolio;
 pub mod replay;
 pub mod report;
+pub mod rpc;
+pub mod scenarios;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod signals;
 pub mod strategies;
+pub mod sweep;
+pub mod trace;
 pub mod types;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+/// The crate version plus, when built from a git checkout, the commit it was
+/// built from: `0.1.0 (1a2b3c4-dirty)`. Stored with saved runs.
+pub const VERSION: &str = env!("PHANTOMFILL_VERSION");
@@ -0,0 +1,323 @@
+//! Async live pipeline (`live` feature).
+//!
+//! Batch backtests hand the engine a finished window at a time. Live
+//! capture and shadow trading instead see the book as it happens, with
+//! several windows open at once and the outcome only known at resolution.
+//! A [`SnapshotSource`] yields [`LiveEvent`]s as they arrive and a
+//! [`LiveDriver`] routes them through the same
+//! [`ReplayEngine`] steps the batch path uses
+//! ([`begin_window`](ReplayEngine::begin_window),
+//! [`feed`](ReplayEngine::feed),
+//! [`finish_window`](ReplayEngine::finish_window)), so a strategy behaves
+//! identically in both.
+//!
+//! Sources are async so capture tasks, shadow traders and servers can share
+//! one tokio runtime:
+//!
+//! ```no_run
+//! # async fn example(engine: phantomfill::replay::ReplayEngine) -> anyhow::Result<()> {
+//! use phantomfill::live::{channel, LiveDriver, LiveEvent};
+//! use phantomfill::strategies::{BuiltinFactory, StrategyParams};
+//!
+//! let (events, mut source) = channel(1024);
+//! tokio::spawn(async move {
+//!     // A capture task sends LiveEvent::Open / Snapshot / Close here.
+//!     drop(events);
+//! });
+//! let mut driver = LiveDriver::new(engine, BuiltinFactory::new("momentum", StrategyParams::default())?);
+//! driver
+//!     .run(&mut source, |result| println!("{} {:+.2}", result.market_id, result.realistic_pnl))
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::future::Future;
+
+use anyhow::Result;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use crate::replay::{ReplayEngine, WindowState};
+use crate::strategies::{Strategy, StrategyFactory};
+use crate::types::{BookSnapshot, Market, Outcome, WindowResult};
+
+/// One thing that happened in a live feed.
+// Nearly every event is a snapshot; boxing it would cost an allocation per tick.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum LiveEvent {
+    /// A window opened; its snapshots follow.
+    Open(Market),
+    /// A book update for an open window (matched by `market_id`).
+    Snapshot(BookSnapshot),
+    /// A window resolved. `None` means it closed without a usable outcome
+    /// and is dropped, as the batch path skips unresolved markets.
+    Close {
+        market_id: String,
+        outcome: Option<Outcome>,
+    },
+}
+
+/// An async stream of [`LiveEvent`]s.
+pub trait SnapshotSource: Send {
+    /// The next event, or `None` once the source is exhausted.
+    fn next_event(&mut self) -> impl Future<Output = Result<Option<LiveEvent>>> + Send;
+}
+
+/// A [`SnapshotSource`] fed through a tokio channel; see [`channel`].
+#[derive(Debug)]
+pub struct ChannelSource {
+    rx: mpsc::Receiver<LiveEvent>,
+}
+
+/// A sender for producers and the [`ChannelSource`] that receives from it.
+/// The source is exhausted once every sender is dropped.
+pub fn channel(buffer: usize) -> (mpsc::Sender<LiveEvent>, ChannelSource) {
+    let (tx, rx) = mpsc::channel(buffer);
+    (tx, ChannelSource { rx })
+}
+
+impl SnapshotSource for ChannelSource {
+    async fn next_event(&mut self) -> Result<Option<LiveEvent>> {
+        Ok(self.rx.recv().await)
+    }
+}
+
+/// A [`SnapshotSource`] over events already in hand, e.g. a recorded
+/// session replayed through the live path.
+#[derive(Debug)]
+pub struct IterSource<I> {
+    events: I,
+}
+
+impl<I: Iterator<Item = LiveEvent> + Send> IterSource<I> {
+    pub fn new(events: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            events: events.into_iter(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = LiveEvent> + Send> SnapshotSource for IterSource<I> {
+    async fn next_event(&mut self) -> Result<Option<LiveEvent>> {
+        Ok(self.events.next())
+    }
+}
+
+/// The events a finished window would have produced live: open, every
+/// snapshot, then close with the market's outcome.
+pub fn window_events(market: &Market, snapshots: &[BookSnapshot]) -> Vec<LiveEvent> {
+    let mut events = Vec::with_capacity(snapshots.len() + 2);
+    events.push(LiveEvent::Open(market.clone()));
+    events.extend(snapshots.iter().cloned().map(LiveEvent::Snapshot));
+    events.push(LiveEvent::Close {
+        market_id: market.id.clone(),
+        outcome: market.outcome,
+    });
+    events
+}
+
+struct OpenWindow {
+    market: Market,
+    strategy: Box<dyn Strategy>,
+    state: WindowState,
+}
+
+/// Drives a [`ReplayEngine`] from live events, with a fresh strategy per
+/// window and any number of windows open at once.
+pub struct LiveDriver {
+    engine: ReplayEngine,
+    factory: Box<dyn StrategyFactory>,
+    windows: HashMap<String, OpenWindow>,
+}
+
+impl LiveDriver {
+    pub fn new(engine: ReplayEngine, factory: impl StrategyFactory + 'static) -> Self {
+        Self {
+            engine,
+            factory: Box::new(factory),
+            windows: HashMap::new(),
+        }
+    }
+
+    /// Windows opened but not yet closed.
+    pub fn open_windows(&self) -> usize {
+        self.windows.len()
+    }
+
+    /// Apply one event, returning the window's result when it closes one.
+    /// Snapshots and closes for windows that were never opened are ignored.
+    pub fn handle(&mut self, event: LiveEvent) -> Option<WindowResult> {
+        match event {
+            LiveEvent::Open(market) => {
+                let mut strategy = self.factory.build();
+                let state = self.engine.begin_window(&market, strategy.as_mut());
+                let id = market.id.clone();
+                let window = OpenWindow {
+                    market,
+                    strategy,
+                    state,
+                };
+                if self.windows.insert(id.clone(), window).is_some() {
+                    warn!(market_id = %id, "window reopened, discarding the earlier one");
+                }
+                None
+            }
+            LiveEvent::Snapshot(snap) => {
+                match self.windows.get_mut(&*snap.market_id) {
+                    Some(window) => self.engine.feed(&mut window.state, &snap, window.strategy.as_mut()),
+                    None => debug!(market_id = %snap.market_id, "snapshot for unknown window, skipping"),
+                }
+                None
+            }
+            LiveEvent::Close { market_id, outcome } => {
+                let mut window = self.windows.remove(&market_id)?;
+                if window.state.snapshots() == 0 {
+                    debug!(market_id = %market_id, "window closed without snapshots, skipping");
+                    return None;
+                }
+                let Some(outcome) = outcome else {
+                    debug!(market_id = %market_id, "window closed unresolved, skipping");
+                    return None;
+                };
+                window.market.outcome = Some(outcome);
+                Some(self.engine.finish_window(
+                    &window.market,
+                    window.state,
+                    outcome,
+                    window.strategy.as_mut(),
+                ))
+            }
+        }
+    }
+
+    /// Consume `source` until it is exhausted, passing each finished window
+    /// to `on_result`. Returns the number of windows completed; windows still
+    /// open at the end are left in the driver.
+    pub async fn run<S: SnapshotSource>(
+        &mut self,
+        source: &mut S,
+        mut on_result: impl FnMut(WindowResult),
+    ) -> Result<usize> {
+        let mut completed = 0;
+        while let Some(event) = source.next_event().await? {
+            if let Some(result) = self.handle(event) {
+                on_result(result);
+                completed += 1;
+            }
+        }
+        Ok(completed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::{generate_dataset, BenchConfig};
+    use crate::data::ticks_to_snapshots;
+    use crate::fill::{DeLiseConfig, DeLiseFillModel};
+    use crate::replay::ReplayConfig;
+    use crate::strategies::{BuiltinFactory, StrategyParams};
+
+    fn engine() -> ReplayEngine {
+        let fill_model = DeLiseFillModel::new(DeLiseConfig {
+            seed: Some(11),
+            ..DeLiseConfig::default()
+        });
+        ReplayEngine::new(Box::new(fill_model), ReplayConfig::default())
+    }
+
+    fn spread_arb() -> BuiltinFactory {
+        BuiltinFactory::new("spread_arb", StrategyParams::default()).unwrap()
+    }
+
+    fn windows() -> Vec<(Market, Vec<BookSnapshot>)> {
+        let config = BenchConfig {
+            markets: 5,
+            snapshots_per_market: 30,
+            seed: 4,
+        };
+        generate_dataset(&config)
+            .into_iter()
+            .map(|(market, ticks)| {
+                let snapshots = ticks_to_snapshots(&market.id, &ticks);
+                (market, snapshots)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_live_matches_batch() {
+        let windows = windows();
+        let markets: Vec<Market> = windows.iter().map(|(m, _)| m.clone()).collect();
+        let snapshots: HashMap<_, _> = windows.iter().map(|(m, s)| (m.id.clone(), s.clone())).collect();
+        let batch = engine().run_all(&markets, &|id| Ok(snapshots[id].clone()), &spread_arb());
+
+        let events: Vec<LiveEvent> = windows.iter().flat_map(|(m, s)| window_events(m, s)).collect();
+        let mut driver = LiveDriver::new(engine(), spread_arb());
+        let mut live = Vec::new();
+        let completed = driver
+            .run(&mut IterSource::new(events), |result| live.push(result))
+            .await
+            .unwrap();
+
+        assert_eq!(completed, batch.len());
+        for (a, b) in batch.iter().zip(&live) {
+            assert_eq!(a.market_id, b.market_id);
+            assert_eq!(a.filled, b.filled);
+            assert_eq!(a.naive_pnl, b.naive_pnl);
+            assert_eq!(a.realistic_pnl, b.realistic_pnl);
+            assert_eq!(a.ref_price_close, b.ref_price_close);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_channel_source_interleaves_windows() {
+        let windows = windows();
+        let (tx, mut source) = channel(16);
+        let producer = tokio::spawn(async move {
+            for (market, _) in &windows {
+                tx.send(LiveEvent::Open(market.clone())).await.unwrap();
+            }
+            // Round-robin the windows' snapshots, as a capture of concurrent
+            // markets would deliver them.
+            let longest = windows.iter().map(|(_, s)| s.len()).max().unwrap();
+            for i in 0..longest {
+                for (_, snapshots) in &windows {
+                    if let Some(snap) = snapshots.get(i) {
+                        tx.send(LiveEvent::Snapshot(snap.clone())).await.unwrap();
+                    }
+                }
+            }
+            tx.send(LiveEvent::Snapshot(BookSnapshot {
+                market_id: "never-opened".into(),
+                ..windows[0].1[0].clone()
+            }))
+            .await
+            .unwrap();
+            for (i, (market, _)) in windows.iter().enumerate() {
+                let outcome = if i == 0 { None } else { market.outcome };
+                tx.send(LiveEvent::Close {
+                    market_id: market.id.clone(),
+                    outcome,
+                })
+                .await
+                .unwrap();
+            }
+        });
+
+        let mut driver = LiveDriver::new(engine(), spread_arb());
+        let mut ids = Vec::new();
+        let completed = driver
+            .run(&mut source, |result| ids.push(result.market_id))
+            .await
+            .unwrap();
+        producer.await.unwrap();
+
+        assert_eq!(completed, 4);
+        assert_eq!(ids.len(), 4);
+        assert_eq!(driver.open_windows(), 0);
+    }
+}
@@ -0,0 +1,50 @@
+//! Tracing subscriber setup shared by the command-line binaries.
+
+use clap::ValueEnum;
+
+/// Output format for log events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines (default).
+    #[default]
+    Text,
+    /// One JSON object per event, for pipelines and log shippers.
+    Json,
+}
+
+/// Install the global tracing subscriber.
+///
+/// Honors `RUST_LOG` and falls back to `info`. In JSON mode events go to
+/// stderr (keeping stdout free for reports), one line per event with its
+/// structured fields at the top level, e.g.
+/// `{"level":"INFO","message":"processing market","processed":100,...}`.
+/// Per-window results are logged at `debug` level (`RUST_LOG=debug`).
+pub fn init(format: LogFormat) {
+    install(format, format == LogFormat::Json);
+}
+
+/// Like [`init`], but text logs go to stderr too, for commands whose stdout
+/// is a protocol stream (`pf rpc`).
+pub fn init_stderr(format: LogFormat) {
+    install(format, true);
+}
+
+fn install(format: LogFormat, stderr: bool) {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+
+    match format {
+        LogFormat::Text if stderr => tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_writer(std::io::stderr)
+            .init(),
+        LogFormat::Text => tracing_subscriber::fmt().with_env_filter(filter).init(),
+        LogFormat::Json => tracing_subscriber::fmt()
+            .json()
+            .with_env_filter(filter)
+            .flatten_event(true)
+            .with_writer(std::io::stderr)
+            .with_current_span(false)
+            .init(),
+    }
+}
@@ -0,0 +1,384 @@
+//! Portfolio constraints across the windows of a run.
+//!
+//! Each window is replayed on its own, but a real account trades every
+//! window open at the same moment from one pool of cash. With
+//! [`PortfolioLimits`] on the [`ReplayConfig`](crate::replay::ReplayConfig),
+//! the engine keeps a [`Portfolio`] of the cash each finished window
+//! committed over wall-clock time, and refuses an order that would take
+//! more positions, capital or category exposure than the limits allow
+//! alongside the windows still open when it is placed.
+//!
+//! A window's orders commit `price × shares` from their placement until it
+//! closes, less whatever a cancel releases unfilled; sells don't release
+//! anything early. Windows claim capital in the order they run (by open
+//! time), so a window that opens later never crowds out one that opened
+//! earlier, even if it would have traded first. Under
+//! [`ReplayConfig::shared_timeline`](crate::replay::ReplayConfig::shared_timeline)
+//! overlapping windows run side by side instead, and claim it as they
+//! trade.
+//!
+//! [`overlaps`] measures how much of a run's trading time its windows
+//! shared in the first place.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Cash, OrderEvent, OrderId, Price, Shares, WindowResult};
+
+/// How much trading time a run's windows shared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Overlaps {
+    /// Windows whose trading period overlaps at least one other's.
+    pub windows: usize,
+    /// Runs of windows chained together by overlaps, e.g. a 15m window and
+    /// the three 5m windows inside it.
+    pub groups: usize,
+    /// Most windows open at one moment.
+    pub max_concurrent: usize,
+}
+
+/// The [`Overlaps`] among windows open over `spans` of `(open, close)`
+/// times. A window closing as another opens doesn't overlap it.
+pub fn overlaps(spans: impl IntoIterator<Item = (i64, i64)>) -> Overlaps {
+    let mut spans: Vec<(i64, i64)> = spans.into_iter().collect();
+    spans.sort_unstable();
+    let mut result = Overlaps::default();
+    // The current group's size and latest close.
+    let mut group = (0, i64::MIN);
+    let close_group = |result: &mut Overlaps, size: usize| {
+        if size > 1 {
+            result.windows += size;
+            result.groups += 1;
+        }
+    };
+    for &(open, close) in &spans {
+        if open < group.1 {
+            group = (group.0 + 1, group.1.max(close));
+        } else {
+            close_group(&mut result, group.0);
+            group = (1, close);
+        }
+    }
+    close_group(&mut result, group.0);
+
+    // Closes sort before opens at the same time.
+    let mut edges: Vec<(i64, i32)> = spans.iter().flat_map(|&(open, close)| [(open, 1), (close, -1)]).collect();
+    edges.sort_unstable();
+    let mut open = 0;
+    for (_, change) in edges {
+        open += change;
+        result.max_concurrent = result.max_concurrent.max(open as usize);
+    }
+    result
+}
+
+/// Limits on what the windows open at one moment may hold together.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PortfolioLimits {
+    /// Most windows holding orders or shares at once.
+    pub max_positions: Option<usize>,
+    /// Most cash committed at once across all windows, in dollars.
+    pub max_capital: Option<f64>,
+    /// Most cash committed at once within any one category, in dollars.
+    pub max_category_capital: Option<f64>,
+    /// Caps for particular categories, in place of
+    /// [`max_category_capital`](Self::max_category_capital).
+    pub category_caps: BTreeMap<String, f64>,
+}
+
+impl PortfolioLimits {
+    /// Whether any limit is set.
+    pub fn is_active(&self) -> bool {
+        self.max_positions.is_some()
+            || self.max_capital.is_some()
+            || self.max_category_capital.is_some()
+            || !self.category_caps.is_empty()
+    }
+
+    /// The exposure cap for `category`, if it has one.
+    pub fn category_cap(&self, category: &str) -> Option<f64> {
+        self.category_caps.get(category).copied().or(self.max_category_capital)
+    }
+}
+
+/// Parse a category cap like `200` (every category) or `btc=200` (one),
+/// in dollars.
+pub fn parse_category_cap(spec: &str) -> Result<(Option<String>, f64)> {
+    let (category, cap) = match spec.split_once('=') {
+        Some((category, cap)) => (Some(category.trim().to_string()), cap),
+        None => (None, spec),
+    };
+    let cap: f64 = cap.trim().parse().with_context(|| format!("bad category cap `{}`", spec))?;
+    if cap < 0.0 {
+        bail!("category cap `{}` is negative", spec);
+    }
+    Ok((category, cap))
+}
+
+/// What one finished window committed over time.
+#[derive(Debug, Clone)]
+struct Exposure {
+    category: String,
+    /// `(wall-clock ms, cash committed from then on)`, in time order.
+    steps: Vec<(i64, Cash)>,
+}
+
+impl Exposure {
+    fn committed_at(&self, at_ms: i64) -> Cash {
+        match self.steps.partition_point(|&(t, _)| t <= at_ms) {
+            0 => Cash::default(),
+            i => self.steps[i - 1].1,
+        }
+    }
+}
+
+/// The positions a run's finished windows held, by when they closed; see
+/// the [module docs](self).
+#[derive(Debug, Clone, Default)]
+pub struct Portfolio {
+    /// Keyed by close time (wall-clock ms) and then the order recorded.
+    windows: BTreeMap<(i64, usize), Exposure>,
+}
+
+/// The windows open at one moment, as a new order sees them.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OpenExposure {
+    /// Windows with cash committed.
+    pub positions: usize,
+    /// Cash committed across all of them.
+    pub capital: Cash,
+    /// Cash committed by those in the category asked about.
+    pub category_capital: Cash,
+}
+
+impl std::ops::Add for OpenExposure {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            positions: self.positions + other.positions,
+            capital: self.capital + other.capital,
+            category_capital: self.category_capital + other.category_capital,
+        }
+    }
+}
+
+impl Portfolio {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget every window.
+    pub fn clear(&mut self) {
+        self.windows.clear();
+    }
+
+    /// Record the orders of a finished window from its
+    /// [events](WindowResult::events).
+    pub fn record(&mut self, result: &WindowResult) {
+        let open_ms = result.open_ts * 1000;
+        // Price, shares and filled shares of each live buy.
+        let mut orders: BTreeMap<OrderId, (Price, Shares, Shares)> = BTreeMap::new();
+        let mut committed = Cash::default();
+        let mut steps = Vec::new();
+        for event in &result.events {
+            let (offset_ms, change) = match *event {
+                OrderEvent::Placed {
+                    order_id,
+                    price,
+                    shares,
+                    offset_ms,
+                    sell: false,
+                    ..
+                } => {
+                    let (price, shares) = (Price::from_f64(price), Shares::from_f64(shares));
+                    orders.insert(order_id, (price, shares, Shares::default()));
+                    (offset_ms, shares * price)
+                }
+                OrderEvent::PartiallyFilled { order_id, shares, .. } | OrderEvent::Filled { order_id, shares, .. } => {
+                    if let Some(order) = orders.get_mut(&order_id) {
+                        order.2 = order.2 + Shares::from_f64(shares);
+                    }
+                    continue;
+                }
+                OrderEvent::Amended {
+                    order_id,
+                    shares,
+                    offset_ms,
+                    ..
+                } => {
+                    let Some(order) = orders.get_mut(&order_id) else {
+                        continue;
+                    };
+                    let before = order.1 * order.0;
+                    order.1 = Shares::from_f64(shares);
+                    (offset_ms, order.1 * order.0 - before)
+                }
+                OrderEvent::Cancelled { order_id, offset_ms, .. } => {
+                    let Some((price, shares, filled)) = orders.remove(&order_id) else {
+                        continue;
+                    };
+                    (offset_ms, filled * price - shares * price)
+                }
+                _ => continue,
+            };
+            committed += change;
+            let at = open_ms + offset_ms;
+            match steps.last_mut() {
+                Some((t, cash)) if *t == at => *cash = committed,
+                _ => steps.push((at, committed)),
+            }
+        }
+        if steps.is_empty() {
+            return;
+        }
+        let key = (result.close_ts * 1000, self.windows.len());
+        self.windows.insert(
+            key,
+            Exposure {
+                category: result.category.clone(),
+                steps,
+            },
+        );
+    }
+
+    /// What the recorded windows still open at `at_ms` (wall-clock) hold,
+    /// with `category`'s share of it.
+    pub fn open_at(&self, at_ms: i64, category: &str) -> OpenExposure {
+        let mut open = OpenExposure::default();
+        for window in self.windows.range((at_ms + 1, 0)..).map(|(_, w)| w) {
+            let committed = window.committed_at(at_ms);
+            if committed <= Cash::default() {
+                continue;
+            }
+            open.positions += 1;
+            open.capital += committed;
+            if window.category == category {
+                open.category_capital += committed;
+            }
+        }
+        open
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Side;
+
+    fn window(category: &str, open_ts: i64, close_ts: i64, events: Vec<OrderEvent>) -> WindowResult {
+        WindowResult {
+            market_id: format!("{}-{}", category, open_ts),
+            platform: "polymarket".to_string(),
+            category: category.to_string(),
+            open_ts,
+            close_ts,
+            outcome: "YES".to_string(),
+            predicted: None,
+            signal_offset_ms: None,
+            bid_side: None,
+            bid_price: 0.49,
+            shares: 10.0,
+            filled: false,
+            queue_ahead_at_place: 0.0,
+            fill_time_ms: None,
+            correct: false,
+            realistic_pnl: 0.0,
+            naive_pnl: 0.0,
+            ref_price_open: None,
+            ref_price_close: None,
+            aborted: None,
+            script_errors: 0,
+            rejected_orders: 0,
+            rejection: None,
+            stake: 0.0,
+            bankroll: None,
+            events,
+        }
+    }
+
+    fn placed(id: u32, price: f64, shares: f64, offset_ms: i64) -> OrderEvent {
+        OrderEvent::Placed {
+            order_id: OrderId::new(id),
+            side: Side::Yes,
+            price,
+            shares,
+            offset_ms,
+            queue_ahead: 0.0,
+            taker: false,
+            sell: false,
+        }
+    }
+
+    #[test]
+    fn test_open_exposure_follows_placements_cancels_and_closes() {
+        let mut portfolio = Portfolio::new();
+        // $5 from 10s; at 60s a cancel releases the 6 unfilled shares.
+        portfolio.record(&window(
+            "btc",
+            1_000,
+            1_900,
+            vec![
+                placed(1, 0.50, 10.0, 10_000),
+                OrderEvent::PartiallyFilled {
+                    order_id: OrderId::new(1),
+                    side: Side::Yes,
+                    price: 0.50,
+                    shares: 4.0,
+                    remaining: 6.0,
+                    offset_ms: 30_000,
+                },
+                OrderEvent::Cancelled {
+                    order_id: OrderId::new(1),
+                    side: Side::Yes,
+                    offset_ms: 60_000,
+                },
+            ],
+        ));
+        portfolio.record(&window("eth", 1_300, 1_600, vec![placed(1, 0.40, 10.0, 0)]));
+        // Nothing placed: no position.
+        portfolio.record(&window("eth", 1_000, 1_900, vec![]));
+
+        assert_eq!(portfolio.open_at(1_005_000, "btc"), OpenExposure::default());
+        let open = portfolio.open_at(1_030_000, "btc");
+        assert_eq!((open.positions, open.capital.to_f64(), open.category_capital.to_f64()), (1, 5.0, 5.0));
+        let open = portfolio.open_at(1_300_000, "eth");
+        assert_eq!((open.positions, open.capital.to_f64(), open.category_capital.to_f64()), (2, 6.0, 4.0));
+        // The eth window has closed.
+        let open = portfolio.open_at(1_600_000, "eth");
+        assert_eq!((open.positions, open.capital.to_f64(), open.category_capital.to_f64()), (1, 2.0, 0.0));
+
+        portfolio.clear();
+        assert_eq!(portfolio.open_at(1_300_000, "eth"), OpenExposure::default());
+    }
+
+    #[test]
+    fn test_overlaps() {
+        assert_eq!(overlaps([]), Overlaps::default());
+        // Back-to-back 5m windows share no time.
+        let back_to_back = overlaps([(0, 300), (300, 600), (600, 900)]);
+        assert_eq!(back_to_back, Overlaps { windows: 0, groups: 0, max_concurrent: 1 });
+        // A 15m window over three 5m ones, then a separate pair.
+        let nested = overlaps([(0, 900), (0, 300), (300, 600), (600, 900), (1000, 1300), (1200, 1500), (2000, 2300)]);
+        assert_eq!(nested, Overlaps { windows: 6, groups: 2, max_concurrent: 2 });
+    }
+
+    #[test]
+    fn test_category_caps() {
+        assert_eq!(parse_category_cap("200").unwrap(), (None, 200.0));
+        assert_eq!(parse_category_cap(" eth = 50 ").unwrap(), (Some("eth".to_string()), 50.0));
+        assert!(parse_category_cap("eth=lots").is_err());
+        assert!(parse_category_cap("-5").is_err());
+
+        let mut limits = PortfolioLimits::default();
+        assert!(!limits.is_active());
+        limits.max_category_capital = Some(200.0);
+        limits.category_caps.insert("eth".to_string(), 50.0);
+        assert!(limits.is_active());
+        assert_eq!(limits.category_cap("btc"), Some(200.0));
+        assert_eq!(limits.category_cap("eth"), Some(50.0));
+    }
+}
@@ -1,13 +1,83 @@
+use std::borrow::{BorrowMut, Cow};
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+#[cfg(feature = "wasm")]
+use web_time::Instant;
+
+use crate::checkpoint::Checkpoint;
+use crate::events::{EngineEvent, EventBus};
+use crate::fill::model::market_seed;
 use crate::fill::FillModel;
-use crate::strategies::Strategy;
-use crate::types::{Action, BookSnapshot, Market, SimOrder, WindowResult};
-use tracing::{debug, info};
+use crate::invariants::{self, SnapshotCheck};
+use crate::portfolio::{OpenExposure, Portfolio, PortfolioLimits};
+use crate::strategies::{Strategy, StrategyFactory};
+use crate::types::{
+    Action, Bankroll, BookSnapshot, Cash, FeeModel, Latency, Market, OffsetMs, OrderEvent, OrderId, OrderState, Outcome, Price, Shares, Side, SideOrderState, SimOrder,
+    VenueRules, WindowResult,
+};
+use tracing::{debug, info, warn};
 
 /// Configuration for the replay engine.
 #[derive(Debug, Clone)]
 pub struct ReplayConfig {
     pub bid_price: f64,
     pub shares: f64,
+    /// Stop the run after the first window the strategy aborts.
+    pub stop_on_abort: bool,
+    /// Reject bids above this many shares, on top of the venue's own
+    /// [`VenueRules`].
+    pub max_shares: Option<f64>,
+    /// Keep filling resting orders on snapshots between the close and
+    /// [`Market::outcome_ts`]. Strategies are not called after the close
+    /// either way; without this, post-close snapshots are ignored.
+    pub fill_until_resolution: bool,
+    /// Check each snapshot for corrupt book data before using it; see
+    /// [`invariants`].
+    pub snapshot_check: SnapshotCheck,
+    /// Fees and rebates taken out of naive and realistic PnL.
+    pub fees: FeeModel,
+    /// Size orders from a running bankroll instead of the strategy's share
+    /// count; see [`Bankroll`].
+    pub bankroll: Option<Bankroll>,
+    /// Delay every placement, take and cancel by this much; see [`Latency`].
+    /// Actions still in flight when the window closes never arrive.
+    pub latency: Option<Latency>,
+    /// Whether orders fill; see [`EvaluationMode`].
+    pub mode: EvaluationMode,
+    /// Refuse orders that would take the windows open at the same moment
+    /// past these limits; see [`crate::portfolio`].
+    pub portfolio: Option<PortfolioLimits>,
+    /// Run windows that overlap in wall-clock time side by side, feeding
+    /// their snapshots in time order, instead of one after another. Orders
+    /// are then sized from the cash the other open windows haven't
+    /// committed, and each window settles into the bankroll when its last
+    /// snapshot is fed rather than before the next window opens.
+    pub shared_timeline: bool,
+}
+
+/// What a replay measures.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvaluationMode {
+    /// Simulate fills and settle what filled.
+    #[default]
+    Execute,
+    /// Only record what the strategy calls: nothing fills, taker orders
+    /// included, so a window's `predicted` side and `signal_offset_ms`
+    /// describe the signal whether or not it could have been executed.
+    /// The prediction is the one side the strategy's live bids back when
+    /// the window closes (none if it backs both), and the signal time is
+    /// when that last changed, so a strategy that bids both sides and
+    /// cancels the loser signals at the cancel.
+    SignalOnly,
 }
 
 impl Default for ReplayConfig {
@@ -15,29 +85,323 @@ impl Default for ReplayConfig {
         Self {
             bid_price: 0.49,
             shares: 10.0,
+            stop_on_abort: false,
+            max_shares: None,
+            fill_until_resolution: false,
+            snapshot_check: SnapshotCheck::Off,
+            fees: FeeModel::default(),
+            bankroll: None,
+            latency: None,
+            mode: EvaluationMode::Execute,
+            portfolio: None,
+            shared_timeline: false,
+        }
+    }
+}
+
+/// Cooperative cancellation for long runs. Clones share one flag; once it
+/// is set, engines stop before the next market window and return what they
+/// have so far.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// How far a pass over the markets has got.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunProgress {
+    pub markets_done: usize,
+    pub markets_total: usize,
+    pub elapsed: Duration,
+}
+
+impl RunProgress {
+    /// Remaining time at the average pace so far; `None` before the first
+    /// market is done.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.markets_done == 0 {
+            return None;
+        }
+        let remaining = self.markets_total.saturating_sub(self.markets_done) as f64;
+        Some(self.elapsed.mul_f64(remaining / self.markets_done as f64))
+    }
+}
+
+/// Callback for [`ReplayEngine::with_progress`].
+pub type ProgressFn = Arc<dyn Fn(&RunProgress) + Send + Sync>;
+
+/// Summarise the engine's order list into the per-side view strategies see.
+fn order_state(orders: &[SimOrder]) -> OrderState {
+    let mut state = OrderState::default();
+    for order in orders.iter().filter(|o| !o.sell) {
+        let was_cancelled = order.is_cancelled();
+        let filled = !was_cancelled && order.filled;
+        let queue_ahead = if filled || was_cancelled {
+            0.0
+        } else {
+            (order.queue_ahead - order.queue_consumed).max(0.0)
+        };
+        let entry = match order.side {
+            Side::Yes => &mut state.yes,
+            Side::No => &mut state.no,
+        };
+        *entry = SideOrderState {
+            placed: true,
+            cancelled: was_cancelled,
+            filled,
+            price: order.price,
+            shares: order.shares,
+            filled_shares: order.filled_shares,
+            filled_at_ms: order.filled_at_ms,
+            queue_ahead,
+            sold_shares: Shares::default(),
+        };
+    }
+    for order in orders.iter().filter(|o| o.sell) {
+        let entry = match order.side {
+            Side::Yes => &mut state.yes,
+            Side::No => &mut state.no,
+        };
+        entry.sold_shares = entry.sold_shares + order.filled_shares;
+    }
+    state.orders = orders.to_vec();
+    state
+}
+
+/// Turn a sell into the bid fill models see, or back: the other side at
+/// `1 − price`. See [`SimOrder::sell`].
+fn mirror(order: &mut SimOrder) {
+    order.side = order.side.opposite();
+    order.price = Price::ONE - order.price;
+}
+
+/// Orders and signal bookkeeping for one window in progress; see
+/// [`ReplayEngine::begin_window`].
+#[derive(Debug, Default)]
+pub struct WindowState {
+    orders: Vec<SimOrder>,
+    snapshots: usize,
+    prev_offset_ms: OffsetMs,
+    close_offset: OffsetMs,
+    resolution_offset: OffsetMs,
+    signal_offset_ms: Option<OffsetMs>,
+    /// Offset of the last snapshot that passed the invariant check.
+    checked_offset_ms: Option<OffsetMs>,
+    aborted: Option<String>,
+    ref_price_open: Option<f64>,
+    ref_price_close: Option<f64>,
+    rules: VenueRules,
+    rejected_orders: usize,
+    rejection: Option<String>,
+    /// The market's open (wall-clock ms) and category, for
+    /// [`ReplayConfig::portfolio`].
+    open_ms: i64,
+    category: String,
+    /// Actions on their way to the venue under [`ReplayConfig::latency`],
+    /// by arrival time.
+    in_flight: Vec<(OffsetMs, Action)>,
+    /// Draws latency jitter, seeded from the run's seed and the market so
+    /// every run of a window with that seed sees the same delays.
+    jitter: Option<StdRng>,
+    /// Under [`EvaluationMode::SignalOnly`], the side the live bids back
+    /// as of the last actions applied.
+    backed: Option<Side>,
+    /// The orders' lifecycle so far, for [`WindowResult::events`].
+    events: Vec<OrderEvent>,
+}
+
+/// The cash `orders` commit: each live buy's full size and each cancelled
+/// one's fills, counting order `replacing` as cancelled.
+fn committed(orders: &[SimOrder], replacing: Option<usize>) -> Cash {
+    orders.iter().enumerate().filter(|(_, o)| !o.sell).fold(Cash::default(), |total, (i, o)| {
+        let held = if o.is_cancelled() || replacing == Some(i) { o.filled_shares } else { o.shares };
+        total + held * o.price
+    })
+}
+
+/// A window in progress under [`ReplayConfig::shared_timeline`], with the
+/// snapshots it has left.
+struct OpenWindow<'m, P> {
+    market: &'m Market,
+    outcome: Outcome,
+    snapshots: std::vec::IntoIter<BookSnapshot>,
+    strategy: P,
+    state: WindowState,
+}
+
+impl<P> OpenWindow<'_, P> {
+    /// The wall-clock time of the next snapshot, in ms.
+    fn next_wall_ms(&self) -> Option<i64> {
+        let next = self.snapshots.as_slice().first()?;
+        Some(self.market.open_ts.to_millis() + next.offset_ms.ms())
+    }
+}
+
+/// What the windows in `open` other than window `i` hold.
+fn exposure_alongside<P>(open: &[OpenWindow<'_, P>], i: usize) -> OpenExposure {
+    let category = &open[i].market.category;
+    let mut exposure = OpenExposure::default();
+    for (j, other) in open.iter().enumerate() {
+        let committed = committed(&other.state.orders, None);
+        if j == i || committed <= Cash::default() {
+            continue;
+        }
+        exposure.positions += 1;
+        exposure.capital += committed;
+        if other.market.category == *category {
+            exposure.category_capital += committed;
         }
     }
+    exposure
+}
+
+/// The one side live buy orders back, if they back exactly one.
+fn backed_side(orders: &[SimOrder]) -> Option<Side> {
+    let mut live = orders.iter().filter(|o| !o.is_cancelled() && !o.sell).map(|o| o.side);
+    let side = live.next()?;
+    live.all(|s| s == side).then_some(side)
+}
+
+impl WindowState {
+    /// Snapshots fed so far.
+    pub fn snapshots(&self) -> usize {
+        self.snapshots
+    }
+
+    /// Why the strategy stopped, if it aborted.
+    pub fn aborted(&self) -> Option<&str> {
+        self.aborted.as_deref()
+    }
 }
 
 /// The core replay engine. Runs strategies against historical data using
 /// a fill model to simulate realistic order execution.
-pub struct ReplayEngine {
-    fill_model: Box<dyn FillModel>,
+///
+/// The fill model is a type parameter. The default, `Box<dyn FillModel>`,
+/// picks the model at runtime; a concrete model such as
+/// `ReplayEngine<DeLiseFillModel>` is monomorphized, as is
+/// [`run_window`](Self::run_window) for a concrete strategy type, so tight
+/// Monte Carlo sweeps pay no dynamic dispatch per tick.
+pub struct ReplayEngine<F = Box<dyn FillModel>> {
+    fill_model: F,
     config: ReplayConfig,
+    events: Option<EventBus>,
+    cancel: Option<CancelToken>,
+    progress: Option<ProgressFn>,
+    /// The running bankroll with [`ReplayConfig::bankroll`], carried from
+    /// one finished window to the next.
+    bankroll: Cell<Option<Cash>>,
+    /// The finished windows' positions, with [`ReplayConfig::portfolio`].
+    portfolio: RefCell<Portfolio>,
+    /// What the other windows running alongside the one being fed hold,
+    /// under [`ReplayConfig::shared_timeline`].
+    concurrent: Cell<OpenExposure>,
+    /// The run's seed, which latency jitter is drawn from; see
+    /// [`with_seed`](Self::with_seed).
+    seed: u64,
 }
 
-impl ReplayEngine {
-    pub fn new(fill_model: Box<dyn FillModel>, config: ReplayConfig) -> Self {
-        Self { fill_model, config }
+impl<F: FillModel> ReplayEngine<F> {
+    pub fn new(fill_model: F, config: ReplayConfig) -> Self {
+        let engine = Self {
+            fill_model,
+            config,
+            events: None,
+            cancel: None,
+            progress: None,
+            bankroll: Cell::new(None),
+            portfolio: RefCell::new(Portfolio::new()),
+            concurrent: Cell::new(OpenExposure::default()),
+            seed: 0,
+        };
+        engine.start_run();
+        engine
+    }
+
+    /// Draw latency jitter from `seed`, mixed with each market's id like
+    /// a seeded fill model's draws, so runs with different seeds see
+    /// different delays. Without it every run draws from seed 0.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Report orders, fills, finished windows and progress to `events`.
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Stop `run_all*` passes before the next window once `cancel` is set.
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Call `progress` after every market of a `run_all*` pass.
+    pub fn with_progress(mut self, progress: ProgressFn) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// The bankroll after the last finished window, with
+    /// [`ReplayConfig::bankroll`] set.
+    pub fn bankroll(&self) -> Option<f64> {
+        self.bankroll.get().map(Cash::to_f64)
+    }
+
+    /// Start the bankroll over at its starting capital, and the portfolio
+    /// empty.
+    fn start_run(&self) {
+        let start = self.config.bankroll.map(|b| Cash::from_f64(b.starting_capital));
+        self.bankroll.set(start);
+        self.portfolio.borrow_mut().clear();
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(CancelToken::is_cancelled)
+    }
+
+    fn report_progress(&self, markets_done: usize, markets_total: usize, started: Instant) {
+        self.emit(|| EngineEvent::Progress {
+            markets_done,
+            markets_total,
+        });
+        if let Some(ref progress) = self.progress {
+            progress(&RunProgress {
+                markets_done,
+                markets_total,
+                elapsed: started.elapsed(),
+            });
+        }
+    }
+
+    /// Emit an event if a bus is attached; `event` is only built then.
+    fn emit(&self, event: impl FnOnce() -> EngineEvent) {
+        if let Some(ref bus) = self.events {
+            bus.emit(event());
+        }
     }
 
     /// Run a single market window: feed snapshots through the strategy,
     /// simulate fills, compute PnL.
-    pub fn run_window(
+    pub fn run_window<S: Strategy + ?Sized>(
         &self,
         market: &Market,
         snapshots: &[BookSnapshot],
-        strategy: &mut dyn Strategy,
+        strategy: &mut S,
     ) -> Option<WindowResult> {
         if snapshots.is_empty() {
             return None;
@@ -45,161 +409,737 @@ impl ReplayEngine {
 
         let outcome = market.outcome?;
 
-        // Reset strategy and notify market open.
+        let mut state = self.begin_window(market, strategy);
+        for snap in snapshots {
+            self.feed(&mut state, snap, strategy);
+        }
+        Some(self.finish_window(market, state, outcome, strategy))
+    }
+
+    /// Start a window snapshot by snapshot, for sources that deliver the
+    /// book as it happens rather than as a finished slice. Feed it with
+    /// [`feed`](Self::feed) and close it with
+    /// [`finish_window`](Self::finish_window) once the outcome is known;
+    /// [`run_window`](Self::run_window) is exactly these three steps.
+    pub fn begin_window<S: Strategy + ?Sized>(&self, market: &Market, strategy: &mut S) -> WindowState {
+        // Reset strategy and notify market open (on the first snapshot).
         strategy.reset();
-        strategy.on_market_open(&snapshots[0]);
+        strategy.on_market(market);
+        self.fill_model.begin_market(&market.id);
+        let mut rules = VenueRules::for_platform(&market.platform);
+        if let Some(max) = self.config.max_shares {
+            rules.max_shares = Some(Shares::from_f64(max));
+        }
+        WindowState {
+            rules,
+            close_offset: market.close_offset(),
+            resolution_offset: market.resolution_offset(),
+            open_ms: market.open_ts.to_millis(),
+            category: market.category.clone(),
+            jitter: self
+                .config
+                .latency
+                .filter(|l| l.jitter_ms > 0)
+                .map(|_| StdRng::seed_from_u64(market_seed(self.seed, &market.id))),
+            ..WindowState::default()
+        }
+    }
 
-        // Track orders and which have been cancelled.
-        let mut orders: Vec<SimOrder> = Vec::new();
-        let mut cancelled: Vec<bool> = Vec::new();
+    /// Process one snapshot of an open window. Once the strategy aborts,
+    /// further snapshots only update the closing reference price.
+    /// Snapshots after the market's close only fill resting orders, and
+    /// only with [`ReplayConfig::fill_until_resolution`].
+    pub fn feed<S: Strategy + ?Sized>(&self, state: &mut WindowState, snap: &BookSnapshot, strategy: &mut S) {
+        let Some(snap) = self.check_snapshot(state, snap) else {
+            return;
+        };
+        let snap = snap.as_ref();
+        if state.snapshots == 0 {
+            strategy.on_market_open(snap);
+            state.aborted = strategy.abort_reason().map(str::to_string);
+            state.prev_offset_ms = snap.offset_ms;
+            state.ref_price_open = snap.reference_price;
+        }
+        state.snapshots += 1;
+        if snap.offset_ms > state.close_offset {
+            if self.config.fill_until_resolution
+                && snap.offset_ms <= state.resolution_offset
+                && state.aborted.is_none()
+            {
+                self.process_fills(state, snap);
+            }
+            return;
+        }
+        state.ref_price_close = snap.reference_price;
+        if state.aborted.is_some() {
+            return;
+        }
 
-        let mut prev_offset_ms = snapshots[0].offset_ms;
-        let mut signal_offset_ms: Option<i64> = None;
+        // Process fill model BEFORE strategy actions so adverse fills
+        // can happen on the same tick as a cancel (prevents cancel/fill race bias).
+        self.process_fills(state, snap);
+        self.deliver(state, snap);
+        strategy.on_order_update(&order_state(&state.orders));
 
-        for snap in snapshots {
-            // Process fill model BEFORE strategy actions so adverse fills
-            // can happen on the same tick as a cancel (prevents cancel/fill race bias).
-            self.fill_model
-                .process_tick(snap, &mut orders, prev_offset_ms);
-            prev_offset_ms = snap.offset_ms;
+        // Get strategy actions for this tick.
+        let actions = strategy.on_tick(snap);
+
+        if let Some(reason) = strategy.abort_reason() {
+            state.aborted = Some(reason.to_string());
+            return;
+        }
+
+        self.send(state, snap, actions);
+    }
+
+    /// Apply `actions` now, or with [`ReplayConfig::latency`] put them in
+    /// flight until their arrival time.
+    fn send(&self, state: &mut WindowState, snap: &BookSnapshot, actions: Vec<Action>) {
+        let Some(latency) = self.config.latency else {
+            self.apply_actions(state, snap, &actions);
+            return;
+        };
+        for action in actions {
+            let jitter = match state.jitter {
+                Some(ref mut rng) => rng.gen_range(0..=latency.jitter_ms),
+                None => 0,
+            };
+            let delay = latency.ms + jitter;
+            if delay <= 0 {
+                self.apply_actions(state, snap, std::slice::from_ref(&action));
+                continue;
+            }
+            let arrives = snap.offset_ms + delay;
+            let at = state.in_flight.partition_point(|(t, _)| *t <= arrives);
+            state.in_flight.insert(at, (arrives, action));
+        }
+    }
 
-            // Get strategy actions for this tick.
-            let actions = strategy.on_tick(snap);
+    /// Apply the in-flight actions that have arrived by `snap`, in order of
+    /// arrival.
+    fn deliver(&self, state: &mut WindowState, snap: &BookSnapshot) {
+        let arrived = state.in_flight.partition_point(|(t, _)| *t <= snap.offset_ms);
+        if arrived == 0 {
+            return;
+        }
+        let actions: Vec<Action> = state.in_flight.drain(..arrived).map(|(_, a)| a).collect();
+        self.apply_actions(state, snap, &actions);
+    }
 
-            for action in &actions {
-                match action {
-                    Action::PlaceBid {
-                        side,
-                        price,
+    /// Apply [`ReplayConfig::snapshot_check`] to `snap`: the snapshot to
+    /// use, or `None` to drop it.
+    fn check_snapshot<'s>(&self, state: &mut WindowState, snap: &'s BookSnapshot) -> Option<Cow<'s, BookSnapshot>> {
+        let mode = self.config.snapshot_check;
+        if mode == SnapshotCheck::Off {
+            return Some(Cow::Borrowed(snap));
+        }
+        let end_ms = if self.config.fill_until_resolution {
+            state.resolution_offset
+        } else {
+            state.close_offset
+        };
+        let violations = invariants::violations(snap, state.checked_offset_ms, end_ms);
+        if violations.is_empty() {
+            state.checked_offset_ms = Some(snap.offset_ms);
+            return Some(Cow::Borrowed(snap));
+        }
+        let diagnostic = violations.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ");
+        warn!(
+            market_id = %snap.market_id,
+            offset_ms = snap.offset_ms.ms(),
+            violations = %diagnostic,
+            check = ?mode,
+            "invalid snapshot"
+        );
+        match mode {
+            SnapshotCheck::Repair if violations.iter().all(|v| v.is_repairable()) => {
+                state.checked_offset_ms = Some(snap.offset_ms);
+                Some(Cow::Owned(invariants::repair(snap)))
+            }
+            SnapshotCheck::Abort => {
+                state
+                    .aborted
+                    .get_or_insert_with(|| format!("invalid snapshot at {}ms: {}", snap.offset_ms, diagnostic));
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Run the fill model over `snap` and report new fills.
+    fn process_fills(&self, state: &mut WindowState, snap: &BookSnapshot) {
+        if self.config.mode == EvaluationMode::SignalOnly {
+            return;
+        }
+        let filled_before: Vec<Shares> = state.orders.iter().map(|o| o.filled_shares).collect();
+
+        state.orders.iter_mut().filter(|o| o.sell).for_each(mirror);
+        self.fill_model
+            .process_tick(snap, &mut state.orders, state.prev_offset_ms);
+        state.orders.iter_mut().filter(|o| o.sell).for_each(mirror);
+        state.prev_offset_ms = snap.offset_ms;
+        for mut check in self.fill_model.take_checks() {
+            // Sells were checked as their mirror; report the side they sell.
+            if state.orders.iter().any(|o| o.id == check.order_id && o.sell) {
+                check.side = check.side.opposite();
+            }
+            self.emit(|| EngineEvent::FillChecked {
+                market_id: snap.market_id.to_string(),
+                offset_ms: snap.offset_ms.ms(),
+                check,
+            });
+        }
+
+        for (order, &before) in state.orders.iter_mut().zip(&filled_before) {
+            // Models that only set `filled` filled the order in full.
+            if order.filled && order.filled_shares < order.shares {
+                order.filled_shares = order.shares;
+                order.filled_at_ms.get_or_insert(snap.offset_ms);
+            }
+            if order.filled_shares > before {
+                self.emit_fill(&mut state.events, snap, order, order.filled_shares - before);
+            }
+        }
+    }
+
+    /// Record and report that `order` just filled `shares` more on `snap`.
+    fn emit_fill(&self, events: &mut Vec<OrderEvent>, snap: &BookSnapshot, order: &SimOrder, shares: Shares) {
+        if order.filled {
+            events.push(OrderEvent::Filled {
+                order_id: order.id,
+                side: order.side,
+                price: order.price.to_f64(),
+                shares: shares.to_f64(),
+                offset_ms: snap.offset_ms.ms(),
+            });
+            self.emit(|| EngineEvent::OrderFilled {
+                market_id: snap.market_id.to_string(),
+                order_id: order.id,
+                side: order.side,
+                price: order.price.to_f64(),
+                shares: order.shares.to_f64(),
+                offset_ms: snap.offset_ms.ms(),
+            });
+        } else {
+            events.push(OrderEvent::PartiallyFilled {
+                order_id: order.id,
+                side: order.side,
+                price: order.price.to_f64(),
+                shares: shares.to_f64(),
+                remaining: order.remaining().to_f64(),
+                offset_ms: snap.offset_ms.ms(),
+            });
+            self.emit(|| EngineEvent::OrderPartiallyFilled {
+                market_id: snap.market_id.to_string(),
+                order_id: order.id,
+                side: order.side,
+                price: order.price.to_f64(),
+                shares: shares.to_f64(),
+                remaining: order.remaining().to_f64(),
+                offset_ms: snap.offset_ms.ms(),
+            });
+        }
+    }
+
+    /// Apply a strategy's actions for `snap`.
+    fn apply_actions(&self, state: &mut WindowState, snap: &BookSnapshot, actions: &[Action]) {
+        for action in actions {
+            match action {
+                Action::PlaceBid {
+                    side,
+                    price,
+                    shares,
+                } => {
+                    let Some(shares) = self.accepts(state, snap, *side, *price, *shares) else {
+                        continue;
+                    };
+                    let order = self.fill_model.create_order(
+                        *side,
+                        *price,
                         shares,
-                    } => {
-                        // Only allow one order per side (active or already placed).
-                        let already_has = orders
-                            .iter()
-                            .zip(cancelled.iter())
-                            .any(|(o, &c)| o.side == *side && !c);
-                        if already_has {
-                            continue;
-                        }
-                        // Also skip if this side was previously cancelled.
-                        let side_cancelled = orders
-                            .iter()
-                            .zip(cancelled.iter())
-                            .any(|(o, &c)| o.side == *side && c);
-                        if side_cancelled {
-                            continue;
-                        }
-
-                        let order = self.fill_model.create_order(
-                            *side,
-                            *price,
-                            *shares,
-                            snap,
-                            snap.offset_ms,
-                        );
-
-                        if signal_offset_ms.is_none() {
-                            signal_offset_ms = Some(snap.offset_ms);
-                        }
-
-                        orders.push(order);
-                        cancelled.push(false);
-                    }
-                    Action::Cancel { side } => {
-                        // Find unfilled, non-cancelled order on this side and cancel it.
-                        for (idx, order) in orders.iter_mut().enumerate() {
-                            if order.side == *side && !order.filled && !cancelled[idx] {
-                                // Mark as filled so fill_model.process_tick skips it,
-                                // but do NOT set filled_at_ms (distinguishes cancel from real fill).
-                                order.filled = true;
-                                cancelled[idx] = true;
-                                break;
-                            }
-                        }
+                        snap,
+                        snap.offset_ms,
+                    );
+                    self.place(state, snap, order);
+                }
+                Action::TakeAsk {
+                    side,
+                    shares,
+                    max_price,
+                } => {
+                    let Some(shares) = self.accepts(state, snap, *side, *max_price, *shares) else {
+                        continue;
+                    };
+                    let order = match self.config.mode {
+                        EvaluationMode::Execute => self
+                            .fill_model
+                            .take_order(*side, shares, *max_price, snap, snap.offset_ms),
+                        EvaluationMode::SignalOnly => self
+                            .fill_model
+                            .create_order(*side, *max_price, shares, snap, snap.offset_ms),
+                    };
+                    self.place(state, snap, order);
+                }
+                Action::Cancel { side } => {
+                    for order in state.orders.iter_mut().filter(|o| o.side == *side && o.is_open()) {
+                        self.cancel(&mut state.events, snap, order);
                     }
                 }
+                Action::Amend {
+                    side,
+                    new_price,
+                    new_shares,
+                } => self.amend(state, snap, *side, *new_price, *new_shares),
+                Action::Sell { side, price, shares } => self.sell(state, snap, *side, *price, *shares),
             }
         }
+        if self.config.mode == EvaluationMode::SignalOnly {
+            let backed = backed_side(&state.orders);
+            if backed != state.backed {
+                state.backed = backed;
+                state.signal_offset_ms = Some(snap.offset_ms);
+            }
+        }
+    }
 
-        // Compute naive PnL: assumes every non-cancelled PlaceBid fills.
-        let mut naive_pnl = 0.0;
-        for (idx, order) in orders.iter().enumerate() {
-            if cancelled[idx] {
-                continue;
+    /// Cancel `order` on `snap`.
+    fn cancel(&self, events: &mut Vec<OrderEvent>, snap: &BookSnapshot, order: &mut SimOrder) {
+        order.cancelled_at_ms = Some(snap.offset_ms);
+        events.push(OrderEvent::Cancelled {
+            order_id: order.id,
+            side: order.side,
+            offset_ms: snap.offset_ms.ms(),
+        });
+        self.emit(|| EngineEvent::OrderCancelled {
+            market_id: snap.market_id.to_string(),
+            order_id: order.id,
+            side: order.side,
+            offset_ms: snap.offset_ms.ms(),
+        });
+    }
+
+    /// Apply [`Action::Amend`] to the open order on `side`, if there is one.
+    /// Amending to nothing cancels it. A replacement is checked like a new
+    /// bid; if it is refused, the old order keeps resting. Shares the old
+    /// order filled stay with it, at its price.
+    fn amend(&self, state: &mut WindowState, snap: &BookSnapshot, side: Side, price: Price, shares: Shares) {
+        let Some(i) = state.orders.iter().position(|o| o.side == side && !o.sell && o.is_open()) else {
+            return;
+        };
+        if shares <= Shares::default() {
+            self.cancel(&mut state.events, snap, &mut state.orders[i]);
+            return;
+        }
+        let order = &mut state.orders[i];
+        if price == order.price && shares <= order.remaining() {
+            order.shares = order.filled_shares + shares;
+            state.events.push(OrderEvent::Amended {
+                order_id: order.id,
+                side,
+                shares: order.shares.to_f64(),
+                offset_ms: snap.offset_ms.ms(),
+            });
+            self.emit(|| EngineEvent::OrderAmended {
+                market_id: snap.market_id.to_string(),
+                order_id: order.id,
+                side,
+                shares: order.shares.to_f64(),
+                offset_ms: snap.offset_ms.ms(),
+            });
+            return;
+        }
+        let Some(shares) = self.check(state, snap, side, price, shares, Some(i)) else {
+            return;
+        };
+        self.cancel(&mut state.events, snap, &mut state.orders[i]);
+        let order = self.fill_model.create_order(side, price, shares, snap, snap.offset_ms);
+        self.place(state, snap, order);
+    }
+
+    /// Apply [`Action::Sell`]: offer up to `shares` of what `side` holds
+    /// and has not already offered, taking from the bids if `price` crosses
+    /// the best bid and resting on the asks otherwise. Either way the fill
+    /// model simulates it as its [mirror](SimOrder::sell).
+    fn sell(&self, state: &mut WindowState, snap: &BookSnapshot, side: Side, price: Price, shares: Shares) {
+        let held = state.orders.iter().filter(|o| o.side == side).fold(Shares::default(), |held, o| {
+            if !o.sell {
+                held + o.filled_shares
+            } else if o.is_cancelled() {
+                held - o.filled_shares
+            } else {
+                held - o.shares
+            }
+        });
+        let shares = shares.min(held);
+        if shares <= Shares::default() {
+            return;
+        }
+        if let Some(reason) = state.rules.rejection(price, shares) {
+            self.reject(state, snap, side, price, shares, reason);
+            return;
+        }
+        let book = match side {
+            Side::Yes => &snap.yes,
+            Side::No => &snap.no,
+        };
+        let crosses = book.best_bid.is_some_and(|bid| Price::from_f64(bid) >= price);
+        let (mirror_side, mirror_price) = (side.opposite(), Price::ONE - price);
+        let mut order = if crosses {
+            self.fill_model
+                .take_order(mirror_side, shares, mirror_price, snap, snap.offset_ms)
+        } else {
+            self.fill_model
+                .create_order(mirror_side, mirror_price, shares, snap, snap.offset_ms)
+        };
+        mirror(&mut order);
+        order.sell = true;
+        self.place(state, snap, order);
+    }
+
+    /// The shares a new order on `side` may be placed for, if any: one
+    /// order per side per window (including one already cancelled), sized
+    /// from the bankroll if there is one, within the venue's rules.
+    fn accepts(&self, state: &mut WindowState, snap: &BookSnapshot, side: Side, price: Price, shares: Shares) -> Option<Shares> {
+        if state.orders.iter().any(|o| o.side == side && !o.sell) {
+            return None;
+        }
+        self.check(state, snap, side, price, shares, None)
+    }
+
+    /// `shares` sized from the bankroll and checked against the venue's
+    /// rules, for a new order or one replacing order `replacing`. Rule
+    /// breaches and orders the bankroll can't fund are counted and reported.
+    fn check(
+        &self,
+        state: &mut WindowState,
+        snap: &BookSnapshot,
+        side: Side,
+        price: Price,
+        shares: Shares,
+        replacing: Option<usize>,
+    ) -> Option<Shares> {
+        let (shares, rejection) = match self.size(state, price, shares, replacing) {
+            Ok(shares) => {
+                let rejection = state.rules.rejection(price, shares);
+                (shares, rejection.or_else(|| self.portfolio_rejection(state, snap, shares * price, replacing)))
+            }
+            Err(reason) => (shares, Some(reason)),
+        };
+        let Some(reason) = rejection else {
+            return Some(shares);
+        };
+        self.reject(state, snap, side, price, shares, reason);
+        None
+    }
+
+    /// Count and report an order refused for `reason`.
+    fn reject(&self, state: &mut WindowState, snap: &BookSnapshot, side: Side, price: Price, shares: Shares, reason: String) {
+        debug!(market_id = %snap.market_id, side = %side, reason = %reason, "order rejected");
+        self.emit(|| EngineEvent::OrderRejected {
+            market_id: snap.market_id.to_string(),
+            side,
+            price: price.to_f64(),
+            shares: shares.to_f64(),
+            offset_ms: snap.offset_ms.ms(),
+            reason: reason.clone(),
+        });
+        state.rejected_orders += 1;
+        state.rejection.get_or_insert(reason);
+    }
+
+    /// `shares` resized under [`ReplayConfig::bankroll`], against the cash
+    /// the window's earlier orders (counting the order being replaced as
+    /// cancelled) and any windows running alongside it have not already
+    /// committed.
+    fn size(&self, state: &WindowState, price: Price, shares: Shares, replacing: Option<usize>) -> Result<Shares, String> {
+        let (Some(bankroll), Some(equity)) = (self.config.bankroll, self.bankroll.get()) else {
+            return Ok(shares);
+        };
+        let committed = committed(&state.orders, replacing) + self.concurrent.get().capital;
+        bankroll.size(price, shares, equity, equity - committed, state.rules.share_increment)
+    }
+
+    /// Why `cost` more in this window would break
+    /// [`ReplayConfig::portfolio`] alongside the other windows open on
+    /// `snap`, if it would.
+    fn portfolio_rejection(&self, state: &WindowState, snap: &BookSnapshot, cost: Cash, replacing: Option<usize>) -> Option<String> {
+        let limits = self.config.portfolio.as_ref()?;
+        let own = committed(&state.orders, replacing);
+        let open = self
+            .portfolio
+            .borrow()
+            .open_at(state.open_ms + snap.offset_ms.ms(), &state.category)
+            + self.concurrent.get();
+        if let Some(max) = limits.max_positions {
+            if own <= Cash::default() && open.positions >= max {
+                return Some(format!("{} positions already open", open.positions));
+            }
+        }
+        let total = open.capital + own + cost;
+        if let Some(max) = limits.max_capital.filter(|&max| total > Cash::from_f64(max)) {
+            return Some(format!("{:.2} committed across windows, over the {} limit", total.to_f64(), max));
+        }
+        let category = open.category_capital + own + cost;
+        if let Some(max) = limits.category_cap(&state.category).filter(|&max| category > Cash::from_f64(max)) {
+            return Some(format!(
+                "{:.2} committed in {}, over the {} limit",
+                category.to_f64(),
+                state.category,
+                max
+            ));
+        }
+        None
+    }
+
+    /// Number and record a new order. A taker order is filled on the spot
+    /// as far as the asks allow, and the rest cancelled.
+    fn place(&self, state: &mut WindowState, snap: &BookSnapshot, mut order: SimOrder) {
+        order.id = OrderId::new(state.orders.len() as u32 + 1);
+        if state.signal_offset_ms.is_none() {
+            state.signal_offset_ms = Some(snap.offset_ms);
+        }
+        state.events.push(OrderEvent::Placed {
+            order_id: order.id,
+            side: order.side,
+            price: order.price.to_f64(),
+            shares: order.shares.to_f64(),
+            offset_ms: snap.offset_ms.ms(),
+            queue_ahead: order.queue_ahead,
+            taker: order.taker,
+            sell: order.sell,
+        });
+        self.emit(|| EngineEvent::OrderPlaced {
+            market_id: snap.market_id.to_string(),
+            order_id: order.id,
+            side: order.side,
+            price: order.price.to_f64(),
+            shares: order.shares.to_f64(),
+            offset_ms: snap.offset_ms.ms(),
+            queue_ahead: order.queue_ahead,
+            taker: order.taker,
+            sell: order.sell,
+        });
+        if order.taker {
+            if order.filled_shares > Shares::default() {
+                self.emit_fill(&mut state.events, snap, &order, order.filled_shares);
+            }
+            if !order.filled {
+                self.cancel(&mut state.events, snap, &mut order);
             }
-            if outcome.matches_side(order.side) {
-                naive_pnl += order.shares * (1.0 - order.price);
+        }
+        state.orders.push(order);
+    }
+
+    /// Close a window fed through [`feed`](Self::feed): pull anything still
+    /// resting if the strategy aborted (or let it expire at the end of the
+    /// window otherwise), then settle against `outcome`.
+    pub fn finish_window<S: Strategy + ?Sized>(
+        &self,
+        market: &Market,
+        state: WindowState,
+        outcome: Outcome,
+        strategy: &mut S,
+    ) -> WindowResult {
+        let WindowState {
+            mut orders,
+            prev_offset_ms,
+            close_offset,
+            resolution_offset,
+            signal_offset_ms,
+            aborted,
+            ref_price_open,
+            ref_price_close,
+            rejected_orders,
+            rejection,
+            mut events,
+            ..
+        } = state;
+
+        if let Some(ref reason) = aborted {
+            // The strategy can no longer manage its orders: pull anything still resting.
+            for order in orders.iter_mut().filter(|o| o.is_open()) {
+                order.cancelled_at_ms = Some(prev_offset_ms);
+                events.push(OrderEvent::Cancelled {
+                    order_id: order.id,
+                    side: order.side,
+                    offset_ms: prev_offset_ms.ms(),
+                });
+                self.emit(|| EngineEvent::OrderCancelled {
+                    market_id: market.id.clone(),
+                    order_id: order.id,
+                    side: order.side,
+                    offset_ms: prev_offset_ms.ms(),
+                });
+            }
+            warn!(market_id = %market.id, reason = %reason, "strategy aborted window");
+        } else {
+            let end = if self.config.fill_until_resolution {
+                resolution_offset
             } else {
-                naive_pnl -= order.shares * order.price;
+                close_offset
+            };
+            for order in orders.iter().filter(|o| o.is_open()) {
+                events.push(OrderEvent::Expired {
+                    order_id: order.id,
+                    side: order.side,
+                    offset_ms: end.ms(),
+                });
+                self.emit(|| EngineEvent::OrderExpired {
+                    market_id: market.id.clone(),
+                    order_id: order.id,
+                    side: order.side,
+                    offset_ms: end.ms(),
+                });
             }
         }
 
-        // Compute realistic PnL: only orders that actually filled and pass
-        // the adverse selection filter.
-        let mut realistic_pnl = 0.0;
-        for (idx, order) in orders.iter().enumerate() {
-            if cancelled[idx] {
-                continue;
+        // Void markets refund every stake and unresolved ones have not paid
+        // out, so only a settled outcome carries PnL.
+        let settled = outcome.is_settled();
+        let payout = |won: bool| if won { Price::ONE } else { Price::default() };
+
+        // Sells refund the settlement fees their shares were charged as
+        // bought, at the side's average fill price.
+        let entry = |side: Side| {
+            let (shares, cost) = orders
+                .iter()
+                .filter(|o| o.side == side && !o.sell)
+                .fold((Shares::default(), Cash::default()), |(shares, cost), o| {
+                    (shares + o.filled_shares, cost + o.filled_shares * o.price)
+                });
+            if shares > Shares::default() {
+                Price::from_f64(cost.to_f64() / shares.to_f64())
+            } else {
+                Price::default()
             }
-            if !order.filled || order.filled_at_ms.is_none() {
+        };
+
+        // Compute naive PnL: assumes every non-cancelled PlaceBid fills in
+        // full (and a cancelled one keeps what it had filled), net of fees.
+        // A sold share makes its sale price instead of settling.
+        let fees = &self.config.fees;
+        let mut naive_pnl = Cash::default();
+        for order in &orders {
+            if !settled {
                 continue;
             }
-            let is_winner = outcome.matches_side(order.side);
-            if !self.fill_model.adverse_selection_filter(order, is_winner) {
+            let shares = if order.is_cancelled() {
+                order.filled_shares
+            } else {
+                order.shares
+            };
+            let won = outcome.matches_side(order.side);
+            if order.sell {
+                naive_pnl += shares * (order.price - payout(won));
+                naive_pnl -= fees.exit_fees(order.price, shares, order.taker, won, entry(order.side));
                 continue;
             }
-            if is_winner {
-                realistic_pnl += order.shares * (1.0 - order.price);
+            if won {
+                naive_pnl += shares * (Price::ONE - order.price);
             } else {
-                realistic_pnl -= order.shares * order.price;
+                naive_pnl -= shares * order.price;
+            }
+            naive_pnl -= fees.fees(order.price, shares, order.taker, won);
+        }
+
+        // Compute realistic PnL: the shares each order actually filled, if
+        // the fill passes the adverse selection filter. Each order that
+        // filled at all is reported resolved with what it contributed. Sells
+        // are filtered as their mirror, and can only sell shares whose
+        // purchase passed.
+        let mut realistic_pnl = Cash::default();
+        let (mut kept_yes, mut kept_no) = (Shares::default(), Shares::default());
+        for order in &orders {
+            if order.filled_shares <= Shares::default() {
+                continue;
+            }
+            let mut pnl = Cash::default();
+            let is_winner = outcome.matches_side(order.side);
+            let kept = match order.side {
+                Side::Yes => &mut kept_yes,
+                Side::No => &mut kept_no,
+            };
+            let mut survives = |checked: &SimOrder, is_winner| {
+                let survives = self.fill_model.adverse_selection_filter(checked, is_winner);
+                if !survives {
+                    events.push(OrderEvent::AdverseFiltered {
+                        order_id: order.id,
+                        side: order.side,
+                        shares: order.filled_shares.to_f64(),
+                    });
+                }
+                survives
+            };
+            if order.sell {
+                let mut mirrored = order.clone();
+                mirror(&mut mirrored);
+                if settled && survives(&mirrored, !is_winner) {
+                    let shares = order.filled_shares.min(*kept);
+                    *kept = *kept - shares;
+                    pnl += shares * (order.price - payout(is_winner));
+                    pnl -= fees.exit_fees(order.price, shares, order.taker, is_winner, entry(order.side));
+                }
+            } else if settled && survives(order, is_winner) {
+                *kept = *kept + order.filled_shares;
+                if is_winner {
+                    pnl += order.filled_shares * (Price::ONE - order.price);
+                } else {
+                    pnl -= order.filled_shares * order.price;
+                }
+                pnl -= fees.fees(order.price, order.filled_shares, order.taker, is_winner);
             }
+            realistic_pnl += pnl;
+            self.emit(|| EngineEvent::OrderResolved {
+                market_id: market.id.clone(),
+                order_id: order.id,
+                side: order.side,
+                pnl: pnl.to_f64(),
+            });
         }
 
-        // Determine predicted side: first non-cancelled order's side.
-        let predicted = orders
+        let stake = orders
             .iter()
-            .zip(cancelled.iter())
-            .find(|(_, &c)| !c)
-            .map(|(o, _)| o.side);
+            .filter(|o| !o.sell)
+            .fold(Cash::default(), |total, o| total + o.filled_shares * o.price);
+        let bankroll = self.bankroll.get().map(|b| b + realistic_pnl);
+        if bankroll.is_some() {
+            self.bankroll.set(bankroll);
+        }
+
+        // Determine predicted side: first non-cancelled buy's side.
+        let live = || orders.iter().filter(|o| !o.is_cancelled() && !o.sell);
+        let predicted = match self.config.mode {
+            EvaluationMode::Execute => live().next().map(|o| o.side),
+            EvaluationMode::SignalOnly => backed_side(&orders),
+        };
 
         // Correct = any non-cancelled order predicted the winning side.
-        let correct = orders
-            .iter()
-            .zip(cancelled.iter())
-            .any(|(o, &c)| !c && outcome.matches_side(o.side));
+        let correct = match self.config.mode {
+            EvaluationMode::Execute => live().any(|o| outcome.matches_side(o.side)),
+            EvaluationMode::SignalOnly => predicted.is_some_and(|s| outcome.matches_side(s)),
+        };
 
-        // Find the first non-cancelled, actually-filled order for fill metadata.
-        let primary_fill = orders
-            .iter()
-            .zip(cancelled.iter())
-            .find(|(o, &c)| !c && o.filled && o.filled_at_ms.is_some());
+        // Find the first order that filled at all for fill metadata.
+        let primary_fill = orders.iter().find(|o| !o.sell && o.filled_shares > Shares::default());
 
         let (filled, queue_ahead_at_place, fill_time_ms) = match primary_fill {
-            Some((o, _)) => (true, o.queue_ahead, o.filled_at_ms),
+            Some(o) => (true, o.queue_ahead, o.filled_at_ms.map(OffsetMs::ms)),
             None => {
                 // Use queue_ahead from first non-cancelled order if available.
-                let qa = orders
-                    .iter()
-                    .zip(cancelled.iter())
-                    .find(|(_, &c)| !c)
-                    .map(|(o, _)| o.queue_ahead)
-                    .unwrap_or(0.0);
+                let qa = live().next().map(|o| o.queue_ahead).unwrap_or(0.0);
                 (false, qa, None)
             }
         };
 
-        let ref_price_open = snapshots.first().and_then(|s| s.reference_price);
-        let ref_price_close = snapshots.last().and_then(|s| s.reference_price);
-
         let result = WindowResult {
             market_id: market.id.clone(),
             platform: market.platform.to_string(),
             category: market.category.clone(),
-            open_ts: market.open_ts,
-            close_ts: market.close_ts,
+            open_ts: market.open_ts.secs(),
+            close_ts: market.close_ts.secs(),
             outcome: outcome.label().to_string(),
             predicted: predicted.map(|s| s.label().to_string()),
-            signal_offset_ms,
+            signal_offset_ms: signal_offset_ms.map(OffsetMs::ms),
             bid_side: predicted.map(|s| s.label().to_string()),
             bid_price: self.config.bid_price,
             shares: self.config.shares,
@@ -207,10 +1147,17 @@ impl ReplayEngine {
             queue_ahead_at_place,
             fill_time_ms,
             correct,
-            realistic_pnl,
-            naive_pnl,
+            realistic_pnl: realistic_pnl.to_f64(),
+            naive_pnl: naive_pnl.to_f64(),
             ref_price_open,
             ref_price_close,
+            aborted,
+            script_errors: strategy.error_count(),
+            rejected_orders,
+            rejection,
+            stake: stake.to_f64(),
+            bankroll: bankroll.map(Cash::to_f64),
+            events,
         };
 
         debug!(
@@ -218,61 +1165,301 @@ impl ReplayEngine {
             outcome = %outcome,
             predicted = ?predicted,
             correct,
-            naive_pnl,
-            realistic_pnl,
+            naive_pnl = result.naive_pnl,
+            realistic_pnl = result.realistic_pnl,
             filled,
             "window complete"
         );
 
-        Some(result)
+        if self.config.portfolio.is_some() {
+            self.portfolio.borrow_mut().record(&result);
+        }
+        self.emit(|| EngineEvent::WindowCompleted(Box::new(result.clone())));
+
+        result
     }
 
-    /// Run all markets through the replay engine, creating a fresh strategy
-    /// per window.
+    /// Run all markets through the replay engine, building a fresh strategy
+    /// from `strategy` per window. Progress is reported to any
+    /// [`with_progress`](Self::with_progress) callback, and a cancelled
+    /// [`with_cancel`](Self::with_cancel) token ends the pass early with the
+    /// results so far. Under [`ReplayConfig::shared_timeline`], windows that
+    /// overlap run side by side and results come in the order they finish.
     pub fn run_all(
         &self,
         markets: &[Market],
         snapshots_fn: &dyn Fn(&str) -> anyhow::Result<Vec<BookSnapshot>>,
-        strategy_fn: &dyn Fn() -> Box<dyn Strategy>,
+        strategy: &dyn StrategyFactory,
+    ) -> Vec<WindowResult> {
+        self.run_all_with(markets, snapshots_fn, strategy, &mut |_| {})
+    }
+
+    /// Like [`run_all`](Self::run_all), calling `on_window` as each window
+    /// finishes.
+    pub fn run_all_with(
+        &self,
+        markets: &[Market],
+        snapshots_fn: &dyn Fn(&str) -> anyhow::Result<Vec<BookSnapshot>>,
+        strategy: &dyn StrategyFactory,
+        on_window: &mut dyn FnMut(&WindowResult),
+    ) -> Vec<WindowResult> {
+        self.run_markets::<dyn Strategy, _>(markets, snapshots_fn, &|| strategy.build(), on_window)
+    }
+
+    /// Like [`run_all`](Self::run_all) for a strategy type known at compile
+    /// time: windows run through a monomorphized
+    /// [`run_window`](Self::run_window) with no boxing.
+    pub fn run_all_typed<S: Strategy>(
+        &self,
+        markets: &[Market],
+        snapshots_fn: &dyn Fn(&str) -> anyhow::Result<Vec<BookSnapshot>>,
+        strategy_fn: impl Fn() -> S,
+    ) -> Vec<WindowResult> {
+        self.run_markets::<S, S>(markets, snapshots_fn, &strategy_fn, &mut |_| {})
+    }
+
+    fn run_markets<S: Strategy + ?Sized, P: BorrowMut<S>>(
+        &self,
+        markets: &[Market],
+        snapshots_fn: &dyn Fn(&str) -> anyhow::Result<Vec<BookSnapshot>>,
+        strategy_fn: &dyn Fn() -> P,
+        on_window: &mut dyn FnMut(&WindowResult),
     ) -> Vec<WindowResult> {
+        if self.config.shared_timeline {
+            return self.run_shared_timeline(markets, snapshots_fn, strategy_fn, on_window);
+        }
         let mut results = Vec::new();
         let total = markets.len();
+        let started = Instant::now();
+        self.start_run();
 
         for (i, market) in markets.iter().enumerate() {
+            if self.cancelled() {
+                warn!(processed = i, total, "run cancelled");
+                break;
+            }
             if (i + 1) % 100 == 0 || i + 1 == total {
-                info!("processing market {}/{} ({})", i + 1, total, market.id);
+                info!(processed = i + 1, total, market_id = %market.id, "processing market");
             }
 
-            let snapshots = match snapshots_fn(&market.id) {
-                Ok(s) => s,
+            let result = match snapshots_fn(&market.id) {
+                Ok(snapshots) => {
+                    let mut strategy = strategy_fn();
+                    self.run_window(market, &snapshots, strategy.borrow_mut())
+                }
                 Err(e) => {
                     debug!(market_id = %market.id, error = %e, "failed to load snapshots, skipping");
-                    continue;
+                    None
                 }
             };
+            self.report_progress(i + 1, total, started);
 
-            let mut strategy = strategy_fn();
-            if let Some(result) = self.run_window(market, &snapshots, strategy.as_mut()) {
+            if let Some(result) = result {
+                let stop = self.config.stop_on_abort && result.aborted.is_some();
+                on_window(&result);
                 results.push(result);
+                if stop {
+                    warn!(market_id = %market.id, "stopping run after aborted window");
+                    break;
+                }
             }
         }
 
-        info!(
-            "replay complete: {} results from {} markets",
-            results.len(),
-            total
-        );
+        info!(results = results.len(), total, "replay complete");
+
+        results
+    }
+
+    /// [`run_markets`](Self::run_markets) under
+    /// [`ReplayConfig::shared_timeline`]: markets are opened in order of
+    /// their open time, and the earliest snapshot (by wall-clock time) of
+    /// every window open so far is fed next. Only the windows open at one
+    /// moment are held in memory. Results come in the order the windows
+    /// finish.
+    fn run_shared_timeline<S: Strategy + ?Sized, P: BorrowMut<S>>(
+        &self,
+        markets: &[Market],
+        snapshots_fn: &dyn Fn(&str) -> anyhow::Result<Vec<BookSnapshot>>,
+        strategy_fn: &dyn Fn() -> P,
+        on_window: &mut dyn FnMut(&WindowResult),
+    ) -> Vec<WindowResult> {
+        let mut order: Vec<&Market> = markets.iter().collect();
+        order.sort_by_key(|m| m.open_ts);
+        let mut pending = order.into_iter().enumerate().peekable();
+        let mut open: Vec<OpenWindow<'_, P>> = Vec::new();
+        let mut results = Vec::new();
+        let total = markets.len();
+        let started = Instant::now();
+        self.start_run();
+
+        loop {
+            // The open window with the earliest next snapshot, and when.
+            let next = open
+                .iter()
+                .enumerate()
+                .filter_map(|(i, w)| Some((w.next_wall_ms()?, i)))
+                .min();
+            let opens_first = match (pending.peek(), next) {
+                (None, None) => break,
+                (Some(_), None) => true,
+                (Some((_, market)), Some((at, _))) => market.open_ts.to_millis() <= at,
+                (None, Some(_)) => false,
+            };
+            if opens_first {
+                let (i, market) = pending.next().expect("peeked");
+                if self.cancelled() {
+                    warn!(processed = i, total, "run cancelled");
+                    break;
+                }
+                if (i + 1) % 100 == 0 || i + 1 == total {
+                    info!(processed = i + 1, total, market_id = %market.id, "processing market");
+                }
+                match (market.outcome, snapshots_fn(&market.id)) {
+                    (Some(outcome), Ok(snapshots)) if !snapshots.is_empty() => {
+                        let mut strategy = strategy_fn();
+                        let state = self.begin_window(market, strategy.borrow_mut());
+                        open.push(OpenWindow {
+                            market,
+                            outcome,
+                            snapshots: snapshots.into_iter(),
+                            strategy,
+                            state,
+                        });
+                    }
+                    (_, Err(e)) => debug!(market_id = %market.id, error = %e, "failed to load snapshots, skipping"),
+                    _ => {}
+                }
+                self.report_progress(i + 1, total, started);
+                continue;
+            }
+
+            let (_, i) = next.expect("a window is open");
+            self.concurrent.set(exposure_alongside(&open, i));
+            let window = &mut open[i];
+            let snap = window.snapshots.next().expect("window has a next snapshot");
+            self.feed(&mut window.state, &snap, window.strategy.borrow_mut());
+            if window.snapshots.as_slice().is_empty() {
+                let mut window = open.swap_remove(i);
+                self.concurrent.set(OpenExposure::default());
+                let result = self.finish_window(window.market, window.state, window.outcome, window.strategy.borrow_mut());
+                let stop = self.config.stop_on_abort && result.aborted.is_some();
+                on_window(&result);
+                results.push(result);
+                if stop {
+                    warn!(market_id = %window.market.id, "stopping run after aborted window");
+                    break;
+                }
+            }
+        }
+        self.concurrent.set(OpenExposure::default());
+
+        info!(results = results.len(), total, "replay complete");
 
         results
     }
+
+    /// Like [`run_all`](Self::run_all), but skips markets already recorded in
+    /// `checkpoint` and saves progress to `checkpoint_path` every
+    /// `save_every` markets (and once more at the end).
+    ///
+    /// Returns every result in the checkpoint for `markets`, including those
+    /// from earlier interrupted runs; markets outside `markets` are dropped
+    /// from the checkpoint. A cancelled run saves the checkpoint before
+    /// returning, so the same call picks up where it stopped.
+    /// Fails under [`ReplayConfig::shared_timeline`].
+    pub fn run_all_resumable(
+        &self,
+        markets: &[Market],
+        snapshots_fn: &dyn Fn(&str) -> anyhow::Result<Vec<BookSnapshot>>,
+        strategy: &dyn StrategyFactory,
+        checkpoint: &mut Checkpoint,
+        checkpoint_path: &Path,
+        save_every: usize,
+    ) -> anyhow::Result<Vec<WindowResult>> {
+        if self.config.shared_timeline {
+            anyhow::bail!("checkpointed runs replay windows one at a time; the shared timeline isn't supported");
+        }
+        checkpoint.retain_markets(markets);
+        let total = markets.len();
+        let already_done = markets
+            .iter()
+            .filter(|m| checkpoint.is_completed(&m.id))
+            .count();
+        if already_done > 0 {
+            info!(
+                "resuming: {} of {} markets already completed",
+                already_done, total
+            );
+        }
+
+        // Pick the bankroll and portfolio up where the interrupted run left
+        // them.
+        self.start_run();
+        if self.config.bankroll.is_some() {
+            if let Some(bankroll) = checkpoint.results.iter().rev().find_map(|r| r.bankroll) {
+                self.bankroll.set(Some(Cash::from_f64(bankroll)));
+            }
+        }
+        if self.config.portfolio.is_some() {
+            let mut portfolio = self.portfolio.borrow_mut();
+            for result in &checkpoint.results {
+                portfolio.record(result);
+            }
+        }
+
+        let mut since_save = 0usize;
+        let started = Instant::now();
+        for (i, market) in markets.iter().enumerate() {
+            if checkpoint.is_completed(&market.id) {
+                continue;
+            }
+            if self.cancelled() {
+                warn!(processed = i, total, "run cancelled, saving checkpoint");
+                break;
+            }
+            if (i + 1) % 100 == 0 || i + 1 == total {
+                info!(processed = i + 1, total, market_id = %market.id, "processing market");
+            }
+
+            let result = match snapshots_fn(&market.id) {
+                Ok(snapshots) => {
+                    let mut strategy = strategy.build();
+                    self.run_window(market, &snapshots, strategy.as_mut())
+                }
+                Err(e) => {
+                    debug!(market_id = %market.id, error = %e, "failed to load snapshots, skipping");
+                    None
+                }
+            };
+            self.report_progress(i + 1, total, started);
+            let stop = self.config.stop_on_abort
+                && result.as_ref().is_some_and(|r| r.aborted.is_some());
+            checkpoint.record(&market.id, result);
+            if stop {
+                warn!(market_id = %market.id, "stopping run after aborted window");
+                break;
+            }
+
+            since_save += 1;
+            if since_save >= save_every.max(1) {
+                checkpoint.save(checkpoint_path)?;
+                since_save = 0;
+            }
+        }
+        checkpoint.save(checkpoint_path)?;
+
+        info!(results = checkpoint.results.len(), total, "replay complete");
+
+        Ok(checkpoint.results.clone())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::fill::model::FillModel;
-    use crate::strategies::make_test_snap;
-    use crate::types::{Outcome, Platform, Side};
+    use crate::strategies::{make_test_snap, BuiltinFactory, StrategyParams};
+    use crate::types::{FeeCurve, Latency, Outcome, Platform, Shares, Side, UnixSecs};
 
     /// A deterministic fill model for testing: fills every order on the second
     /// tick it sees (simulating immediate queue consumption).
@@ -286,13 +1473,14 @@ mod tests {
         fn create_order(
             &self,
             side: Side,
-            price: f64,
-            shares: f64,
+            price: Price,
+            shares: Shares,
             snap: &BookSnapshot,
-            offset_ms: i64,
+            offset_ms: OffsetMs,
         ) -> SimOrder {
             let _ = snap;
             SimOrder {
+                id: OrderId::default(),
                 side,
                 price,
                 shares,
@@ -300,7 +1488,11 @@ mod tests {
                 queue_ahead: 100.0,
                 queue_consumed: 0.0,
                 filled: false,
+                filled_shares: Shares::default(),
                 filled_at_ms: None,
+                cancelled_at_ms: None,
+                taker: false,
+                sell: false,
             }
         }
 
@@ -308,11 +1500,11 @@ mod tests {
             &self,
             snap: &BookSnapshot,
             orders: &mut [SimOrder],
-            _prev_offset_ms: i64,
+            _prev_offset_ms: OffsetMs,
         ) -> Vec<usize> {
             let mut filled = Vec::new();
             for (i, order) in orders.iter_mut().enumerate() {
-                if order.filled {
+                if !order.is_open() {
                     continue;
                 }
                 // Fill if order was placed before this tick.
@@ -325,8 +1517,37 @@ mod tests {
             filled
         }
 
-        fn adverse_selection_filter(&self, _order: &SimOrder, _is_winner: bool) -> bool {
-            true
+        fn adverse_selection_filter(&self, _order: &SimOrder, _is_winner: bool) -> bool {
+            true
+        }
+    }
+
+    /// Fills like [`AlwaysFillModel`], but the adverse selection filter
+    /// throws out every losing fill.
+    struct FilterLosersModel;
+
+    impl FillModel for FilterLosersModel {
+        fn name(&self) -> &str {
+            "filter-losers"
+        }
+
+        fn create_order(
+            &self,
+            side: Side,
+            price: Price,
+            shares: Shares,
+            snap: &BookSnapshot,
+            offset_ms: OffsetMs,
+        ) -> SimOrder {
+            AlwaysFillModel.create_order(side, price, shares, snap, offset_ms)
+        }
+
+        fn process_tick(&self, snap: &BookSnapshot, orders: &mut [SimOrder], prev_offset_ms: OffsetMs) -> Vec<usize> {
+            AlwaysFillModel.process_tick(snap, orders, prev_offset_ms)
+        }
+
+        fn adverse_selection_filter(&self, _order: &SimOrder, is_winner: bool) -> bool {
+            is_winner
         }
     }
 
@@ -343,12 +1564,13 @@ mod tests {
         fn create_order(
             &self,
             side: Side,
-            price: f64,
-            shares: f64,
+            price: Price,
+            shares: Shares,
             _snap: &BookSnapshot,
-            offset_ms: i64,
+            offset_ms: OffsetMs,
         ) -> SimOrder {
             SimOrder {
+                id: OrderId::default(),
                 side,
                 price,
                 shares,
@@ -356,7 +1578,11 @@ mod tests {
                 queue_ahead: 100.0,
                 queue_consumed: 0.0,
                 filled: false,
+                filled_shares: Shares::default(),
                 filled_at_ms: None,
+                cancelled_at_ms: None,
+                taker: false,
+                sell: false,
             }
         }
 
@@ -364,11 +1590,11 @@ mod tests {
             &self,
             snap: &BookSnapshot,
             orders: &mut [SimOrder],
-            _prev_offset_ms: i64,
+            _prev_offset_ms: OffsetMs,
         ) -> Vec<usize> {
             let mut filled = Vec::new();
             for (i, order) in orders.iter_mut().enumerate() {
-                if order.filled {
+                if !order.is_open() {
                     continue;
                 }
                 if snap.offset_ms >= order.placed_at_ms + self.min_delay_ms {
@@ -396,12 +1622,13 @@ mod tests {
         fn create_order(
             &self,
             side: Side,
-            price: f64,
-            shares: f64,
+            price: Price,
+            shares: Shares,
             _snap: &BookSnapshot,
-            offset_ms: i64,
+            offset_ms: OffsetMs,
         ) -> SimOrder {
             SimOrder {
+                id: OrderId::default(),
                 side,
                 price,
                 shares,
@@ -409,7 +1636,11 @@ mod tests {
                 queue_ahead: 500.0,
                 queue_consumed: 0.0,
                 filled: false,
+                filled_shares: Shares::default(),
                 filled_at_ms: None,
+                cancelled_at_ms: None,
+                taker: false,
+                sell: false,
             }
         }
 
@@ -417,7 +1648,7 @@ mod tests {
             &self,
             _snap: &BookSnapshot,
             _orders: &mut [SimOrder],
-            _prev_offset_ms: i64,
+            _prev_offset_ms: OffsetMs,
         ) -> Vec<usize> {
             Vec::new()
         }
@@ -433,13 +1664,18 @@ mod tests {
             platform: Platform::Polymarket,
             description: "test".to_string(),
             category: "btc".to_string(),
-            open_ts: 1_700_000_000,
-            close_ts: 1_700_000_300,
+            open_ts: UnixSecs::new(1_700_000_000),
+            close_ts: UnixSecs::new(1_700_000_300),
             duration_secs: 300,
             outcome,
+            outcome_ts: None,
         }
     }
 
+    fn spread_arb() -> BuiltinFactory {
+        BuiltinFactory::new("spread_arb", StrategyParams::default()).unwrap()
+    }
+
     fn make_snaps_with_ref(count: usize, oracle_start: f64, oracle_end: f64) -> Vec<BookSnapshot> {
         (0..count)
             .map(|i| {
@@ -491,6 +1727,177 @@ mod tests {
         assert!(result.filled);
     }
 
+    #[test]
+    fn test_fees_come_out_of_both_pnls() {
+        let fees = FeeModel {
+            maker_fee: -0.01,
+            taker_fee: 0.07,
+            curve: FeeCurve::Variance,
+            settlement_fee: 0.001,
+            winner_fee: 0.02,
+        };
+        let config = ReplayConfig {
+            fees,
+            ..ReplayConfig::default()
+        };
+        let engine = ReplayEngine::new(Box::new(AlwaysFillModel), config);
+        let market = make_market(Some(Outcome::Yes));
+        let snaps = make_snaps_with_ref(10, 50000.0, 50100.0);
+        let mut strategy = crate::strategies::spread_arb::NaiveSpreadArb::new(0.49, 10.0);
+
+        let result = engine.run_window(&market, &snaps, &mut strategy).unwrap();
+
+        // Maker rebate of 1% x 0.49 x 0.51 a share on both orders, $0.001 a
+        // share to settle each, and 2% of the YES profit.
+        let rebate = 2.0 * 10.0 * 0.01 * 0.49 * 0.51;
+        let fee = 2.0 * 10.0 * 0.001 + 0.02 * 10.0 * 0.51 - rebate;
+        let expected = 10.0 * (1.0 - 0.49) - 10.0 * 0.49 - fee;
+        assert!((result.naive_pnl - expected).abs() < 1e-9, "naive_pnl={}", result.naive_pnl);
+        assert!((result.realistic_pnl - expected).abs() < 1e-9, "realistic_pnl={}", result.realistic_pnl);
+
+        // Takers pay the 7% curve instead of earning the rebate.
+        let taker = fees.fees(Price::from_f64(0.49), Shares::from_f64(10.0), true, false);
+        assert!((taker.to_f64() - (10.0 * 0.07 * 0.49 * 0.51 + 0.01)).abs() < 1e-9);
+
+        // Selling a winner back refunds the settlement and winner fees its
+        // purchase at 0.49 paid.
+        let exit = fees.exit_fees(Price::from_f64(0.60), Shares::from_f64(10.0), true, true, Price::from_f64(0.49));
+        assert!((exit.to_f64() - (10.0 * 0.07 * 0.60 * 0.40 - 0.01 - 0.02 * 10.0 * 0.51)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bankroll_sizes_orders_and_carries_between_windows() {
+        let config = ReplayConfig {
+            bankroll: Some(Bankroll {
+                starting_capital: 6.0,
+                sizing: crate::types::Sizing::Fixed,
+            }),
+            ..ReplayConfig::default()
+        };
+        let engine = ReplayEngine::new(Box::new(AlwaysFillModel), config);
+        let snaps = make_snaps_with_ref(10, 50000.0, 50100.0);
+        let run = |outcome| {
+            let mut strategy = crate::strategies::spread_arb::NaiveSpreadArb::new(0.49, 10.0);
+            engine.run_window(&make_market(Some(outcome)), &snaps, &mut strategy).unwrap()
+        };
+
+        // $6 buys the 10 YES shares; the $1.10 left is under the minimum NO order.
+        let first = run(Outcome::Yes);
+        assert_eq!(first.rejection.as_deref(), Some("2.24 shares below the 5 minimum"));
+        assert!((first.stake - 4.9).abs() < 1e-9);
+        assert!((first.realistic_pnl - 5.1).abs() < 1e-9);
+        assert_eq!(first.bankroll, Some(11.1));
+
+        // The winnings fund both sides of the next window.
+        let second = run(Outcome::No);
+        assert_eq!(second.rejected_orders, 0);
+        assert!((second.stake - 9.8).abs() < 1e-9);
+        assert_eq!(second.bankroll, Some(11.3));
+        assert_eq!(engine.bankroll(), Some(11.3));
+    }
+
+    #[test]
+    fn test_portfolio_limits_span_overlapping_windows() {
+        // btc 0-300s, eth 100-400s, btc 300-600s; spread_arb commits $9.80 a window.
+        let markets: Vec<Market> = [("btc", 0), ("eth", 100), ("btc", 300)]
+            .iter()
+            .map(|&(category, start)| {
+                let mut m = make_market(Some(Outcome::Yes));
+                m.id = format!("{}-{}", category, start);
+                m.category = category.to_string();
+                m.open_ts = UnixSecs::new(1_700_000_000 + start);
+                m.close_ts = UnixSecs::new(1_700_000_300 + start);
+                m
+            })
+            .collect();
+        let snapshots = |_: &str| Ok(make_snaps_with_ref(10, 50000.0, 50100.0));
+        let run = |limits: PortfolioLimits| {
+            let config = ReplayConfig {
+                portfolio: Some(limits),
+                ..ReplayConfig::default()
+            };
+            let results = ReplayEngine::new(Box::new(AlwaysFillModel), config).run_all(&markets, &snapshots, &spread_arb());
+            results.iter().map(|r| (r.stake, r.rejection.clone())).collect::<Vec<_>>()
+        };
+
+        // One position at a time: eth opens while btc-0 holds one.
+        let results = run(PortfolioLimits {
+            max_positions: Some(1),
+            ..PortfolioLimits::default()
+        });
+        assert!((results[0].0 - 9.8).abs() < 1e-9);
+        assert_eq!(results[1], (0.0, Some("1 positions already open".to_string())));
+        assert!((results[2].0 - 9.8).abs() < 1e-9, "btc-0 has closed and eth holds nothing");
+
+        // $15 in all: eth gets one side, and btc-300 both alongside it.
+        let results = run(PortfolioLimits {
+            max_capital: Some(15.0),
+            ..PortfolioLimits::default()
+        });
+        assert!((results[1].0 - 4.9).abs() < 1e-9);
+        assert_eq!(results[1].1.as_deref(), Some("19.60 committed across windows, over the 15 limit"));
+        assert!((results[2].0 - 9.8).abs() < 1e-9);
+
+        // $5 of btc at once; eth is uncapped.
+        let results = run(PortfolioLimits {
+            category_caps: [("btc".to_string(), 5.0)].into(),
+            ..PortfolioLimits::default()
+        });
+        assert!((results[0].0 - 4.9).abs() < 1e-9);
+        assert_eq!(results[0].1.as_deref(), Some("9.80 committed in btc, over the 5 limit"));
+        assert!((results[1].0 - 9.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_shared_timeline_runs_overlapping_windows_side_by_side() {
+        // "long" runs 0-9s; "short" opens at 2s and has its last snapshot at 4s.
+        let markets: Vec<Market> = [("long", 0), ("short", 2)]
+            .iter()
+            .map(|&(id, start)| {
+                let mut m = make_market(Some(Outcome::Yes));
+                m.id = id.to_string();
+                m.open_ts = UnixSecs::new(1_700_000_000 + start);
+                m.close_ts = UnixSecs::new(1_700_000_300 + start);
+                m
+            })
+            .collect();
+        let snapshots = |id: &str| Ok(make_snaps_with_ref(if id == "long" { 10 } else { 3 }, 50000.0, 50100.0));
+        let run = |capital: f64, shared_timeline: bool, portfolio: Option<PortfolioLimits>| {
+            let config = ReplayConfig {
+                bankroll: Some(Bankroll {
+                    starting_capital: capital,
+                    sizing: crate::types::Sizing::Fixed,
+                }),
+                portfolio,
+                shared_timeline,
+                ..ReplayConfig::default()
+            };
+            ReplayEngine::new(Box::new(AlwaysFillModel), config).run_all(&markets, &snapshots, &spread_arb())
+        };
+
+        // One after another, each window has the whole $12 and commits $9.80.
+        let results = run(12.0, false, None);
+        assert_eq!(results[0].market_id, "long");
+        assert!(results.iter().all(|r| (r.stake - 9.8).abs() < 1e-9));
+
+        // Side by side, "short" only has the $2.20 "long" left, and settles first.
+        let results = run(12.0, true, None);
+        assert_eq!(results[0].market_id, "short");
+        assert_eq!(results[0].stake, 0.0);
+        assert_eq!(results[0].rejected_orders, 2);
+        assert_eq!(results[0].bankroll, Some(12.0));
+        assert!((results[1].stake - 9.8).abs() < 1e-9);
+        assert_eq!(results[1].bankroll, Some(12.2));
+
+        // Portfolio limits count the windows still running too.
+        let limits = PortfolioLimits {
+            max_positions: Some(1),
+            ..PortfolioLimits::default()
+        };
+        let results = run(100.0, true, Some(limits));
+        assert_eq!(results[0].rejection.as_deref(), Some("1 positions already open"));
+    }
+
     #[test]
     fn test_spread_arb_no_wins() {
         let engine = ReplayEngine::new(Box::new(AlwaysFillModel), ReplayConfig::default());
@@ -512,6 +1919,21 @@ mod tests {
     // -----------------------------------------------------------------------
     // Test: momentum strategy (single directional bet)
     // -----------------------------------------------------------------------
+    #[test]
+    fn test_void_and_unresolved_windows_carry_no_pnl() {
+        let engine = ReplayEngine::new(Box::new(AlwaysFillModel), ReplayConfig::default());
+        let snaps = make_snaps_with_ref(10, 50000.0, 50100.0);
+        for outcome in [Outcome::Void, Outcome::Unresolved] {
+            let mut strategy = crate::strategies::spread_arb::NaiveSpreadArb::new(0.49, 10.0);
+            let result = engine
+                .run_window(&make_market(Some(outcome)), &snaps, &mut strategy)
+                .unwrap();
+            assert_eq!(result.outcome, outcome.label());
+            assert!(result.filled && !result.correct);
+            assert_eq!((result.naive_pnl, result.realistic_pnl), (0.0, 0.0));
+        }
+    }
+
     #[test]
     fn test_momentum_single_bet_correct() {
         let engine = ReplayEngine::new(Box::new(AlwaysFillModel), ReplayConfig::default());
@@ -751,7 +2173,7 @@ mod tests {
         let results = engine.run_all(
             &markets,
             &|id| Ok(make_snaps_with_ref(10, 50000.0, if id.contains("2") { 49900.0 } else { 50100.0 })),
-            &|| Box::new(crate::strategies::spread_arb::NaiveSpreadArb::new(0.49, 10.0)),
+            &spread_arb(),
         );
 
         // Third market has no outcome, so only 2 results.
@@ -760,6 +2182,75 @@ mod tests {
         assert_eq!(results[1].market_id, "test-market-2");
     }
 
+    // -----------------------------------------------------------------------
+    // Test: monomorphized engine matches the boxed one
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_run_all_typed_matches_boxed() {
+        use crate::fill::{DeLiseConfig, DeLiseFillModel};
+        use crate::strategies::spread_arb::NaiveSpreadArb;
+
+        let fill_model = || {
+            DeLiseFillModel::new(DeLiseConfig {
+                seed: Some(3),
+                ..DeLiseConfig::default()
+            })
+        };
+        let markets: Vec<Market> = (0..6)
+            .map(|i| {
+                let mut m = make_market(Some(if i % 2 == 0 { Outcome::Yes } else { Outcome::No }));
+                m.id = format!("m{}", i);
+                m
+            })
+            .collect();
+        let snapshots = |_: &str| Ok(make_snaps_with_ref(40, 50000.0, 50100.0));
+
+        let boxed = ReplayEngine::new(Box::new(fill_model()) as Box<dyn FillModel>, ReplayConfig::default())
+            .run_all(&markets, &snapshots, &spread_arb());
+        let typed: ReplayEngine<DeLiseFillModel> = ReplayEngine::new(fill_model(), ReplayConfig::default());
+        let typed = typed.run_all_typed(&markets, &snapshots, || NaiveSpreadArb::new(0.49, 10.0));
+
+        assert_eq!(boxed.len(), typed.len());
+        for (a, b) in boxed.iter().zip(&typed) {
+            assert_eq!(a.filled, b.filled);
+            assert_eq!(a.fill_time_ms, b.fill_time_ms);
+            assert_eq!(a.realistic_pnl, b.realistic_pnl);
+        }
+    }
+
+    #[test]
+    fn test_seeded_market_results_do_not_depend_on_earlier_markets() {
+        use crate::fill::{DeLiseConfig, DeLiseFillModel};
+
+        let engine = || {
+            let fill_model = DeLiseFillModel::new(DeLiseConfig {
+                seed: Some(11),
+                rf: 0.05,
+                ..DeLiseConfig::default()
+            });
+            ReplayEngine::new(Box::new(fill_model) as Box<dyn FillModel>, ReplayConfig::default())
+        };
+        let markets: Vec<Market> = (0..8)
+            .map(|i| {
+                let mut m = make_market(Some(Outcome::Yes));
+                m.id = format!("m{}", i);
+                m
+            })
+            .collect();
+        let snapshots = |_: &str| Ok(make_snaps_with_ref(40, 50000.0, 50100.0));
+
+        let all = engine().run_all(&markets, &snapshots, &spread_arb());
+        let tail = engine().run_all(&markets[5..], &snapshots, &spread_arb());
+        assert_eq!(all.len(), 8);
+        for (a, b) in all[5..].iter().zip(&tail) {
+            assert_eq!(a.market_id, b.market_id);
+            assert_eq!(a.fill_time_ms, b.fill_time_ms);
+            assert_eq!(a.realistic_pnl, b.realistic_pnl);
+        }
+        let fill_times: std::collections::HashSet<_> = all.iter().map(|r| r.fill_time_ms).collect();
+        assert!(fill_times.len() > 1, "markets should draw differently");
+    }
+
     // -----------------------------------------------------------------------
     // Test: run_all handles snapshot load errors gracefully
     // -----------------------------------------------------------------------
@@ -772,12 +2263,62 @@ mod tests {
         let results = engine.run_all(
             &markets,
             &|_id| Err(anyhow::anyhow!("database error")),
-            &|| Box::new(crate::strategies::spread_arb::NaiveSpreadArb::new(0.49, 10.0)),
+            &spread_arb(),
         );
 
         assert!(results.is_empty());
     }
 
+    // -----------------------------------------------------------------------
+    // Test: run_all_resumable skips markets recorded in the checkpoint
+    // -----------------------------------------------------------------------
+    #[test]
+    fn test_run_all_resumable_skips_completed() {
+        let engine = ReplayEngine::new(Box::new(AlwaysFillModel), ReplayConfig::default());
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("run.ckpt");
+
+        let markets: Vec<Market> = (0..3)
+            .map(|i| {
+                let mut m = make_market(Some(Outcome::Yes));
+                m.id = format!("m{}", i);
+                m
+            })
+            .collect();
+
+        // Pretend m0 was finished by an earlier, interrupted run, which also
+        // covered a market this run no longer selects.
+        let mut checkpoint = Checkpoint::new("spread_arb", "abc");
+        checkpoint.record("m0", None);
+        let snaps = make_snaps_with_ref(10, 50000.0, 50100.0);
+        let mut dropped = engine.run_window(&markets[0], &snaps, spread_arb().build().as_mut()).unwrap();
+        dropped.market_id = "gone".to_string();
+        checkpoint.record("gone", Some(dropped));
+
+        let loaded = std::cell::RefCell::new(Vec::new());
+        let results = engine
+            .run_all_resumable(
+                &markets,
+                &|id| {
+                    loaded.borrow_mut().push(id.to_string());
+                    Ok(make_snaps_with_ref(10, 50000.0, 50100.0))
+                },
+                &spread_arb(),
+                &mut checkpoint,
+                &path,
+                1,
+            )
+            .unwrap();
+
+        assert_eq!(*loaded.borrow(), vec!["m1", "m2"]);
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.market_id != "gone"));
+
+        let saved = Checkpoint::load_or_new(&path, "spread_arb", "abc").unwrap();
+        assert_eq!(saved.completed.len(), 3);
+        assert_eq!(saved.results.len(), 2);
+    }
+
     // -----------------------------------------------------------------------
     // Test: NeverFillModel produces zero realistic PnL
     // -----------------------------------------------------------------------
@@ -825,12 +2366,13 @@ mod tests {
         fn create_order(
             &self,
             side: Side,
-            price: f64,
-            shares: f64,
+            price: Price,
+            shares: Shares,
             _snap: &BookSnapshot,
-            offset_ms: i64,
+            offset_ms: OffsetMs,
         ) -> SimOrder {
             SimOrder {
+                id: OrderId::default(),
                 side,
                 price,
                 shares,
@@ -838,7 +2380,11 @@ mod tests {
                 queue_ahead: 0.0,
                 queue_consumed: 0.0,
                 filled: false,
+                filled_shares: Shares::default(),
                 filled_at_ms: None,
+                cancelled_at_ms: None,
+                taker: false,
+                sell: false,
             }
         }
 
@@ -846,11 +2392,11 @@ mod tests {
             &self,
             snap: &BookSnapshot,
             orders: &mut [SimOrder],
-            _prev_offset_ms: i64,
+            _prev_offset_ms: OffsetMs,
         ) -> Vec<usize> {
             let mut filled = Vec::new();
             for (i, order) in orders.iter_mut().enumerate() {
-                if order.filled {
+                if !order.is_open() {
                     continue;
                 }
                 // Non-strict: fills if snap.offset_ms >= placed_at_ms.
@@ -894,8 +2440,8 @@ mod tests {
                 self.placed = true;
                 vec![crate::types::Action::PlaceBid {
                     side: Side::Yes,
-                    price: 0.49,
-                    shares: 10.0,
+                    price: Price::from_f64(0.49),
+                    shares: Shares::from_f64(10.0),
                 }]
             } else {
                 vec![]
@@ -935,6 +2481,129 @@ mod tests {
         );
     }
 
+    /// Takes YES asks on the first tick.
+    struct TakeOnFirstTick {
+        max_price: f64,
+        placed: bool,
+    }
+
+    impl crate::strategies::Strategy for TakeOnFirstTick {
+        fn name(&self) -> &str {
+            "take-on-first-tick"
+        }
+        fn description(&self) -> &str {
+            "takes 800 YES on first tick"
+        }
+        fn on_tick(&mut self, _snap: &BookSnapshot) -> Vec<crate::types::Action> {
+            if std::mem::replace(&mut self.placed, true) {
+                return vec![];
+            }
+            vec![crate::types::Action::TakeAsk {
+                side: Side::Yes,
+                shares: Shares::from_f64(800.0),
+                max_price: Price::from_f64(self.max_price),
+            }]
+        }
+        fn reset(&mut self) {
+            self.placed = false;
+        }
+    }
+
+    #[test]
+    fn test_taker_orders_walk_the_ask_ladder() {
+        let mut snap = make_test_snap(0, Some(50000.0), 500.0, 500.0);
+        // YES asks mirror NO bids: 500 at 0.51, then 300 more at 0.53.
+        Arc::make_mut(&mut snap.no).depth.push(crate::types::PriceLevel {
+            price: 0.47,
+            cumulative_size: 800.0,
+        });
+        let snaps = vec![snap, make_test_snap(1_000, Some(50000.0), 500.0, 500.0)];
+        let run = |max_price| {
+            let bus = EventBus::new();
+            let events = bus.channel();
+            let engine = ReplayEngine::new(Box::new(NeverFillModel), ReplayConfig::default()).with_events(bus);
+            let mut strategy = TakeOnFirstTick { max_price, placed: false };
+            let result = engine
+                .run_window(&make_market(Some(Outcome::Yes)), &snaps, &mut strategy)
+                .unwrap();
+            let records = crate::events::order_records(events.try_iter());
+            (result, records.into_iter().next().unwrap())
+        };
+
+        let (result, order) = run(0.52);
+        assert!(order.taker);
+        // 300 short: the rest is cancelled, the 500 bought still count.
+        assert_eq!((order.filled_shares, order.price, order.cancelled_at_ms), (500.0, 0.51, Some(0)));
+        assert!(result.filled);
+        assert!((result.realistic_pnl - 245.0).abs() < 1e-9);
+
+        let (result, order) = run(0.53);
+        assert_eq!((order.filled_shares, order.price, order.filled_at_ms), (800.0, 0.5175, Some(0)));
+        assert!((result.realistic_pnl - 386.0).abs() < 1e-9);
+
+        let (result, order) = run(0.50);
+        assert_eq!((order.filled_at_ms, order.cancelled_at_ms), (None, Some(0)));
+        assert!(!result.filled && result.naive_pnl == 0.0);
+    }
+
+    #[test]
+    fn test_fills_after_close_only_until_resolution_when_enabled() {
+        let snaps: Vec<BookSnapshot> = [0, 299_000, 330_000, 400_000]
+            .into_iter()
+            .map(|offset| make_test_snap(offset, Some(50000.0), 500.0, 500.0))
+            .collect();
+        let run = |fill_until_resolution, outcome_ts: Option<i64>, min_delay_ms| {
+            let config = ReplayConfig {
+                fill_until_resolution,
+                ..ReplayConfig::default()
+            };
+            let engine = ReplayEngine::new(Box::new(SlowFillModel { min_delay_ms }), config);
+            let market = Market {
+                outcome_ts: outcome_ts.map(UnixSecs::new),
+                ..make_market(Some(Outcome::Yes))
+            };
+            engine
+                .run_window(&market, &snaps, &mut PlaceOnFirstTick::new())
+                .unwrap()
+                .fill_time_ms
+        };
+
+        assert_eq!(run(false, Some(1_700_000_360), 320_000), None);
+        assert_eq!(run(true, None, 320_000), None);
+        assert_eq!(run(true, Some(1_700_000_360), 320_000), Some(330_000));
+        // 400s is past the resolution.
+        assert_eq!(run(true, Some(1_700_000_360), 350_000), None);
+    }
+
+    #[test]
+    fn test_snapshot_check_skips_repairs_or_aborts() {
+        let mut snaps: Vec<BookSnapshot> = [0, 10_000, 20_000]
+            .into_iter()
+            .map(|offset| make_test_snap(offset, Some(50000.0), 500.0, 500.0))
+            .collect();
+        Arc::make_mut(&mut snaps[1].yes).best_bid = Some(0.60);
+        let run = |snapshot_check| {
+            let config = ReplayConfig {
+                snapshot_check,
+                ..ReplayConfig::default()
+            };
+            let engine = ReplayEngine::new(Box::new(SlowFillModel { min_delay_ms: 5_000 }), config);
+            engine
+                .run_window(&make_market(Some(Outcome::Yes)), &snaps, &mut PlaceOnFirstTick::new())
+                .unwrap()
+        };
+
+        assert_eq!(run(SnapshotCheck::Off).fill_time_ms, Some(10_000));
+        assert_eq!(run(SnapshotCheck::Skip).fill_time_ms, Some(20_000));
+        assert_eq!(run(SnapshotCheck::Repair).fill_time_ms, Some(10_000));
+        let aborted = run(SnapshotCheck::Abort);
+        assert_eq!(aborted.fill_time_ms, None);
+        assert_eq!(
+            aborted.aborted.as_deref(),
+            Some("invalid snapshot at 10000ms: YES book crossed: best bid 0.6 > best ask 0.51")
+        );
+    }
+
     // -----------------------------------------------------------------------
     // Regression test: Bug 5 — adverse fill CAN happen on the same tick as
     // a cancel. Since process_tick now runs BEFORE strategy actions (cancels),
@@ -954,12 +2623,13 @@ mod tests {
         fn create_order(
             &self,
             side: Side,
-            price: f64,
-            shares: f64,
+            price: Price,
+            shares: Shares,
             _snap: &BookSnapshot,
-            offset_ms: i64,
+            offset_ms: OffsetMs,
         ) -> SimOrder {
             SimOrder {
+                id: OrderId::default(),
                 side,
                 price,
                 shares,
@@ -967,7 +2637,11 @@ mod tests {
                 queue_ahead: 0.0,
                 queue_consumed: 0.0,
                 filled: false,
+                filled_shares: Shares::default(),
                 filled_at_ms: None,
+                cancelled_at_ms: None,
+                taker: false,
+                sell: false,
             }
         }
 
@@ -975,11 +2649,11 @@ mod tests {
             &self,
             snap: &BookSnapshot,
             orders: &mut [SimOrder],
-            _prev_offset_ms: i64,
+            _prev_offset_ms: OffsetMs,
         ) -> Vec<usize> {
             let mut filled = Vec::new();
             for (i, order) in orders.iter_mut().enumerate() {
-                if !order.filled {
+                if order.is_open() {
                     order.filled = true;
                     order.filled_at_ms = Some(snap.offset_ms);
                     filled.push(i);
@@ -1023,8 +2697,8 @@ mod tests {
                 self.placed = true;
                 vec![crate::types::Action::PlaceBid {
                     side: Side::Yes,
-                    price: 0.49,
-                    shares: 10.0,
+                    price: Price::from_f64(0.49),
+                    shares: Shares::from_f64(10.0),
                 }]
             } else if !self.cancelled {
                 self.cancelled = true;
@@ -1078,4 +2752,419 @@ mod tests {
             "filled YES order in YES-outcome market should yield positive realistic PnL"
         );
     }
+
+    #[test]
+    fn test_latency_delays_placements_and_cancels() {
+        let market = make_market(Some(Outcome::Yes));
+        let snaps: Vec<BookSnapshot> = (0..6)
+            .map(|i| make_test_snap(i * 100, Some(50000.0), 500.0, 500.0))
+            .collect();
+        let run = |latency| {
+            let config = ReplayConfig {
+                latency: Some(latency),
+                ..ReplayConfig::default()
+            };
+            let engine = ReplayEngine::new(Box::new(AlwaysFillModel), config);
+            engine.run_window(&market, &snaps, &mut PlaceThenCancelStrategy::new()).unwrap()
+        };
+
+        // The bid sent at 0 lands on the 300 snapshot and fills on the next;
+        // the cancel sent at 100 arrives at 400, too late.
+        let result = run(Latency { ms: 250, jitter_ms: 0 });
+        assert_eq!(result.signal_offset_ms, Some(300));
+        assert_eq!(result.fill_time_ms, Some(400));
+
+        // Jitter draws the same delays for a market every time.
+        let jittered = run(Latency { ms: 250, jitter_ms: 100 });
+        assert!(matches!(jittered.signal_offset_ms, Some(300 | 400)));
+        assert_eq!(jittered.signal_offset_ms, run(Latency { ms: 250, jitter_ms: 100 }).signal_offset_ms);
+
+        // ...for one seed; other seeds draw other delays.
+        let config = ReplayConfig {
+            latency: Some(Latency { ms: 0, jitter_ms: 1_000 }),
+            ..ReplayConfig::default()
+        };
+        let offsets: std::collections::HashSet<_> = (0..8)
+            .map(|seed| {
+                let engine = ReplayEngine::new(Box::new(AlwaysFillModel), config.clone()).with_seed(seed);
+                engine.run_window(&market, &snaps, &mut PlaceThenCancelStrategy::new()).unwrap().signal_offset_ms
+            })
+            .collect();
+        assert!(offsets.len() > 1, "{:?}", offsets);
+
+        // Actions still in flight at the close never arrive.
+        let late = run(Latency { ms: 10_000, jitter_ms: 0 });
+        assert_eq!(late.signal_offset_ms, None);
+        assert!(!late.filled);
+    }
+
+    /// Strategy that sends a fixed list of actions on each tick.
+    struct ActionsPerTick {
+        ticks: Vec<Vec<crate::types::Action>>,
+        tick: usize,
+    }
+
+    impl crate::strategies::Strategy for ActionsPerTick {
+        fn name(&self) -> &str {
+            "actions-per-tick"
+        }
+        fn description(&self) -> &str {
+            "sends the next tick's actions"
+        }
+        fn on_tick(&mut self, _snap: &BookSnapshot) -> Vec<crate::types::Action> {
+            self.tick += 1;
+            self.ticks.get(self.tick - 1).cloned().unwrap_or_default()
+        }
+        fn reset(&mut self) {
+            self.tick = 0;
+        }
+    }
+
+    #[test]
+    fn test_amend_keeps_priority_only_when_shrinking_in_place() {
+        use crate::types::Action;
+        let amend = |price, shares| Action::Amend {
+            side: Side::Yes,
+            new_price: Price::from_f64(price),
+            new_shares: Shares::from_f64(shares),
+        };
+        let snaps: Vec<BookSnapshot> = (0..5)
+            .map(|i| make_test_snap(i * 100, Some(50000.0), 500.0, 500.0))
+            .collect();
+        let bus = EventBus::new();
+        let events = bus.channel();
+        let engine = ReplayEngine::new(Box::new(NeverFillModel), ReplayConfig::default()).with_events(bus);
+        let mut strategy = ActionsPerTick {
+            ticks: vec![
+                vec![Action::PlaceBid {
+                    side: Side::Yes,
+                    price: Price::from_f64(0.49),
+                    shares: Shares::from_f64(10.0),
+                }],
+                vec![amend(0.49, 6.0)],
+                vec![amend(0.50, 6.0)],
+                vec![amend(0.50, 0.0)],
+                vec![amend(0.51, 5.0)],
+            ],
+            tick: 0,
+        };
+        let result = engine
+            .run_window(&make_market(Some(Outcome::Yes)), &snaps, &mut strategy)
+            .unwrap();
+        let records = crate::events::order_records(events.try_iter());
+        let summary: Vec<_> = records
+            .iter()
+            .map(|r| (r.order_id.get(), r.price, r.shares, r.placed_at_ms, r.cancelled_at_ms))
+            .collect();
+        let log: Vec<_> = result
+            .events
+            .iter()
+            .map(|e| serde_json::to_value(e).unwrap()["type"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(log, ["placed", "amended", "cancelled", "placed", "cancelled"]);
+        assert_eq!(
+            summary,
+            vec![
+                // Shrunk in place at 100, then repriced to the back of the queue.
+                (1, 0.49, 6.0, 0, Some(200)),
+                // Amended to nothing: cancelled, and nothing left to amend.
+                (2, 0.50, 6.0, 200, Some(300)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_window_events_log_each_order() {
+        use crate::types::{Action, OrderEvent};
+        let snaps: Vec<BookSnapshot> = (0..3)
+            .map(|i| make_test_snap(i * 100, Some(50000.0), 500.0, 500.0))
+            .collect();
+        let bid = |side| Action::PlaceBid {
+            side,
+            price: Price::from_f64(0.49),
+            shares: Shares::from_f64(10.0),
+        };
+        let engine = ReplayEngine::new(Box::new(FilterLosersModel), ReplayConfig::default());
+        let mut strategy = ActionsPerTick {
+            ticks: vec![vec![bid(Side::Yes), bid(Side::No)]],
+            tick: 0,
+        };
+        let result = engine
+            .run_window(&make_market(Some(Outcome::Yes)), &snaps, &mut strategy)
+            .unwrap();
+
+        let placed = |id, side| OrderEvent::Placed {
+            order_id: OrderId::new(id),
+            side,
+            price: 0.49,
+            shares: 10.0,
+            offset_ms: 0,
+            queue_ahead: 100.0,
+            taker: false,
+            sell: false,
+        };
+        let filled = |id, side| OrderEvent::Filled {
+            order_id: OrderId::new(id),
+            side,
+            price: 0.49,
+            shares: 10.0,
+            offset_ms: 100,
+        };
+        assert_eq!(
+            result.events,
+            [
+                placed(1, Side::Yes),
+                placed(2, Side::No),
+                filled(1, Side::Yes),
+                filled(2, Side::No),
+                // The losing NO fill doesn't survive settlement.
+                OrderEvent::AdverseFiltered {
+                    order_id: OrderId::new(2),
+                    side: Side::No,
+                    shares: 10.0,
+                },
+            ]
+        );
+        assert!((result.realistic_pnl - 5.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sell_exits_a_filled_position() {
+        use crate::types::Action;
+        let sell = |price, shares| Action::Sell {
+            side: Side::Yes,
+            price: Price::from_f64(price),
+            shares: Shares::from_f64(shares),
+        };
+        let snaps: Vec<BookSnapshot> = (0..4)
+            .map(|i| make_test_snap(i * 100, Some(50000.0), 500.0, 500.0))
+            .collect();
+        let run = |outcome, exit: Vec<Action>| {
+            let bus = EventBus::new();
+            let events = bus.channel();
+            let engine = ReplayEngine::new(Box::new(AlwaysFillModel), ReplayConfig::default()).with_events(bus);
+            let mut strategy = ActionsPerTick {
+                ticks: vec![
+                    vec![
+                        Action::PlaceBid {
+                            side: Side::Yes,
+                            price: Price::from_f64(0.49),
+                            shares: Shares::from_f64(10.0),
+                        },
+                        // Nothing held yet.
+                        sell(0.60, 10.0),
+                    ],
+                    exit,
+                ],
+                tick: 0,
+            };
+            let result = engine
+                .run_window(&make_market(Some(outcome)), &snaps, &mut strategy)
+                .unwrap();
+            (result, crate::events::order_records(events.try_iter()))
+        };
+
+        // A resting exit at 0.60, capped at the 10 held; the second has
+        // nothing left to offer. Locked in whichever side wins.
+        for outcome in [Outcome::Yes, Outcome::No] {
+            let (result, records) = run(outcome, vec![sell(0.60, 20.0), sell(0.70, 5.0)]);
+            assert!((result.realistic_pnl - 1.1).abs() < 1e-9, "{outcome:?}: {}", result.realistic_pnl);
+            assert!((result.naive_pnl - 1.1).abs() < 1e-9);
+            assert!((result.stake - 4.9).abs() < 1e-9);
+            assert_eq!(records.len(), 2);
+            let exit = &records[1];
+            assert!(exit.sell && !exit.taker);
+            assert_eq!((exit.price, exit.filled_shares, exit.filled_at_ms), (0.60, 10.0, Some(200)));
+        }
+
+        // Below the best bid: sold at once at 0.49 (YES bids are NO asks).
+        let (result, records) = run(Outcome::Yes, vec![sell(0.40, 6.0)]);
+        assert!(records[1].sell && records[1].taker);
+        assert_eq!((records[1].price, records[1].filled_at_ms), (0.49, Some(100)));
+        assert!((result.realistic_pnl - (5.1 - 6.0 * 0.51)).abs() < 1e-9);
+    }
+
+    /// Strategy that places a YES bid on the first tick and records the
+    /// order state it is shown on every tick.
+    struct RecordOrdersStrategy {
+        seen: Vec<OrderState>,
+    }
+
+    impl crate::strategies::Strategy for RecordOrdersStrategy {
+        fn name(&self) -> &str {
+            "record-orders"
+        }
+        fn description(&self) -> &str {
+            "places YES at tick 0 and records order updates"
+        }
+        fn on_order_update(&mut self, orders: &OrderState) {
+            self.seen.push(orders.clone());
+        }
+        fn on_tick(&mut self, _snap: &BookSnapshot) -> Vec<crate::types::Action> {
+            if self.seen.len() == 1 {
+                vec![crate::types::Action::PlaceBid {
+                    side: Side::Yes,
+                    price: Price::from_f64(0.49),
+                    shares: Shares::from_f64(10.0),
+                }]
+            } else {
+                vec![]
+            }
+        }
+        fn reset(&mut self) {
+            self.seen.clear();
+        }
+    }
+
+    #[test]
+    fn test_strategy_sees_order_state_after_fills() {
+        let engine = ReplayEngine::new(Box::new(AlwaysFillModel), ReplayConfig::default());
+        let market = make_market(Some(Outcome::Yes));
+        let snaps = make_snaps_with_ref(3, 50000.0, 50100.0);
+
+        let mut strategy = RecordOrdersStrategy { seen: Vec::new() };
+        engine.run_window(&market, &snaps, &mut strategy).unwrap();
+
+        assert_eq!(strategy.seen.len(), 3);
+        assert_eq!(strategy.seen[0], OrderState::default());
+        let yes = &strategy.seen[1].yes;
+        assert!(yes.placed && yes.filled && !yes.cancelled);
+        assert_eq!(yes.filled_at_ms, Some(OffsetMs::new(1000)));
+        assert_eq!(yes.price, Price::from_f64(0.49));
+        assert!(!strategy.seen[1].no.placed);
+    }
+
+    #[test]
+    fn test_order_state_distinguishes_cancel_from_fill() {
+        let order = |side, filled_at_ms: Option<OffsetMs>| SimOrder {
+            id: OrderId::default(),
+            side,
+            price: Price::from_f64(0.49),
+            shares: Shares::from_f64(10.0),
+            placed_at_ms: OffsetMs::new(0),
+            queue_ahead: 100.0,
+            queue_consumed: 30.0,
+            filled: filled_at_ms.is_some(),
+            filled_shares: Shares::default(),
+            filled_at_ms,
+            cancelled_at_ms: None,
+            taker: false,
+            sell: false,
+        };
+        // Cancelled YES, resting NO.
+        let mut yes = order(Side::Yes, None);
+        yes.cancelled_at_ms = Some(OffsetMs::new(5_000));
+        let state = order_state(&[yes, order(Side::No, None)]);
+
+        assert!(state.yes.cancelled && !state.yes.filled);
+        assert_eq!(state.yes.queue_ahead, 0.0);
+        assert!(state.no.placed && !state.no.filled);
+        assert!((state.side(Side::No).queue_ahead - 70.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_bids_breaking_venue_rules_are_rejected() {
+        let snaps = make_snaps_with_ref(5, 50000.0, 50100.0);
+        let run = |market: &Market, config: ReplayConfig, price: f64, shares: f64| {
+            let engine = ReplayEngine::new(Box::new(AlwaysFillModel), config);
+            let mut strategy = crate::strategies::spread_arb::NaiveSpreadArb::new(price, shares);
+            engine.run_window(market, &snaps, &mut strategy).unwrap()
+        };
+        let polymarket = make_market(Some(Outcome::Yes));
+
+        let result = run(&polymarket, ReplayConfig::default(), 0.495, 10.0);
+        assert_eq!(result.rejected_orders, 2);
+        assert_eq!(result.rejection.as_deref(), Some("price 0.495 off the 0.01 tick"));
+        assert!(result.bid_side.is_none() && !result.filled);
+        assert_eq!(result.naive_pnl, 0.0);
+
+        // Tenth-of-a-cent prices are fine near the ends of the range.
+        assert_eq!(run(&polymarket, ReplayConfig::default(), 0.035, 10.0).rejected_orders, 0);
+
+        let capped = ReplayConfig {
+            max_shares: Some(100.0),
+            ..ReplayConfig::default()
+        };
+        let result = run(&polymarket, capped, 0.49, 250.0);
+        assert_eq!(result.rejection.as_deref(), Some("250 shares above the 100 maximum"));
+
+        let kalshi = Market {
+            platform: Platform::Kalshi,
+            ..make_market(Some(Outcome::Yes))
+        };
+        let result = run(&kalshi, ReplayConfig::default(), 0.49, 2.5);
+        assert_eq!(result.rejection.as_deref(), Some("2.5 shares not a multiple of 1"));
+        assert_eq!(run(&kalshi, ReplayConfig::default(), 0.49, 3.0).rejected_orders, 0);
+    }
+
+    #[test]
+    #[cfg(feature = "scripting")]
+    fn test_aborted_script_stops_window_and_cancels_orders() {
+        let source = r#"
+let ticks = 0;
+fn on_tick(snap) {
+    ticks += 1;
+    if ticks == 1 { return [bid("yes", BID_PRICE, SHARES)]; }
+    loop {}
+}
+fn on_reset() { ticks = 0; }
+"#;
+        let mut strategy =
+            crate::strategies::scripted::RhaiStrategy::from_source("loop", source, 10.0, 0.49)
+                .unwrap();
+        // The order would fill on tick 2 if it were still resting.
+        let engine = ReplayEngine::new(
+            Box::new(SlowFillModel { min_delay_ms: 2000 }),
+            ReplayConfig::default(),
+        );
+        let market = make_market(Some(Outcome::Yes));
+        let snaps = make_snaps_with_ref(5, 50000.0, 50100.0);
+
+        let result = engine.run_window(&market, &snaps, &mut strategy).unwrap();
+        assert!(result.aborted.unwrap().contains("operation limit"));
+        assert!(!result.filled);
+        assert_eq!(result.naive_pnl, 0.0);
+        assert_eq!(result.realistic_pnl, 0.0);
+    }
+
+    #[test]
+    #[cfg(feature = "scripting")]
+    fn test_script_errors_counted_or_stop_strict_run() {
+        let source = r#"
+fn on_tick(snap) {
+    if snap.offset_ms == 0 { let x = snap.missing + 1; }
+    []
+}
+fn on_reset() {}
+"#;
+        let make = |strict: bool| -> Box<dyn Strategy> {
+            Box::new(
+                crate::strategies::scripted::RhaiStrategy::from_source("err", source, 10.0, 0.49)
+                    .unwrap()
+                    .with_strict(strict),
+            )
+        };
+        let markets = vec![make_market(Some(Outcome::Yes)), {
+            let mut m = make_market(Some(Outcome::No));
+            m.id = "test-market-2".to_string();
+            m
+        }];
+        let snaps = |_: &str| Ok(make_snaps_with_ref(5, 50000.0, 50100.0));
+
+        let engine = ReplayEngine::new(Box::new(AlwaysFillModel), ReplayConfig::default());
+        let results = engine.run_all(&markets, &snaps, &crate::strategies::FnFactory::new(|| make(false)));
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.script_errors == 1 && r.aborted.is_none()));
+
+        let engine = ReplayEngine::new(
+            Box::new(AlwaysFillModel),
+            ReplayConfig {
+                stop_on_abort: true,
+                ..ReplayConfig::default()
+            },
+        );
+        let results = engine.run_all(&markets, &snaps, &crate::strategies::FnFactory::new(|| make(true)));
+        assert_eq!(results.len(), 1);
+        assert!(results[0].aborted.as_deref().unwrap().starts_with("on_tick:"));
+    }
 }
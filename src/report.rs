@@ -1,11 +1,29 @@
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 use anyhow::{Context, Result};
+use serde::Serialize;
 
-use crate::types::WindowResult;
+use crate::data::{DataStore, ResultFilter};
+use crate::portfolio::{overlaps, Overlaps};
+use crate::strategies::DEFAULT_SIGNAL_OFFSET_MS;
+use crate::types::{Outcome, WindowResult};
+
+/// Upper bounds (exclusive) of the [`Report::fill_time_ms`] histogram
+/// buckets, in ms from the open; the last bucket is open-ended.
+pub const FILL_TIME_BUCKETS_MS: &[f64] = &[15_000.0, 30_000.0, 60_000.0, 90_000.0, 120_000.0, 180_000.0, 300_000.0, 900_000.0];
+
+/// Upper bounds (exclusive) of the [`Report::queue_ahead`] histogram
+/// buckets, in shares; the last bucket is open-ended.
+pub const QUEUE_AHEAD_BUCKETS: &[f64] = &[10.0, 50.0, 100.0, 250.0, 500.0, 1_000.0, 2_500.0, 5_000.0];
+
+/// Upper bounds (exclusive) of the [`Report::fill_by_placement`] buckets:
+/// when the order was placed, in ms after the signal (negative before
+/// it). The last bucket is open-ended.
+pub const PLACEMENT_BUCKETS_MS: &[i64] = &[-60_000, -30_000, 0, 30_000, 60_000, 120_000];
 
 /// Summary of multiple Monte Carlo runs with confidence intervals.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct MonteCarloSummary {
     pub runs: usize,
     pub seed: Option<u64>,
@@ -152,6 +170,32 @@ impl MonteCarloSummary {
         println!("{}", "=".repeat(55));
         println!();
     }
+
+    /// Print one row per strategy comparing Monte Carlo summaries.
+    pub fn print_comparison(summaries: &[MonteCarloSummary]) {
+        println!();
+        println!("{}", "=".repeat(97));
+        println!("  Strategy comparison ({} runs each)", summaries.first().map_or(0, |s| s.runs));
+        println!("{}", "=".repeat(97));
+        println!(
+            "  {:<36} {:>6} {:>6} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            "strategy", "fill%", "WR%", "naive", "real med", "p5", "p95", "gap med"
+        );
+        for s in summaries {
+            println!(
+                "  {:<36.36} {:>6.1} {:>6.1} {:>+9.2} {:>+9.2} {:>+9.2} {:>+9.2} {:>9.2}",
+                s.reports[0].strategy_name,
+                s.fill_rate_mean * 100.0,
+                s.win_rate_mean * 100.0,
+                s.naive_total_pnl,
+                s.realistic_pnl_median,
+                s.realistic_pnl_p5,
+                s.realistic_pnl_p95,
+                s.phantom_gap_median
+            );
+        }
+        println!();
+    }
 }
 
 /// Compute a percentile from a sorted slice using nearest-rank.
@@ -167,8 +211,170 @@ fn percentile(sorted: &[f64], pct: f64) -> f64 {
     sorted[lo] * (1.0 - frac) + sorted[hi] * frac
 }
 
+/// Percentiles and a histogram of one per-window quantity. Averages hide
+/// the long tail: a strategy whose median fill comes 20s in can still wait
+/// out most of the window for a tenth of them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+    /// Upper bounds (exclusive) of the histogram buckets.
+    pub bounds: Vec<f64>,
+    /// Values per bucket, plus the open-ended last one.
+    pub histogram: Vec<usize>,
+}
+
+impl Distribution {
+    /// Summarize `values`, bucketed by `bounds`.
+    pub fn new(values: impl IntoIterator<Item = f64>, bounds: &[f64]) -> Self {
+        let mut sorted: Vec<f64> = values.into_iter().collect();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let mut histogram = vec![0; bounds.len() + 1];
+        for &v in &sorted {
+            histogram[bounds.iter().position(|&bound| v < bound).unwrap_or(bounds.len())] += 1;
+        }
+        let at = |pct| if sorted.is_empty() { 0.0 } else { percentile(&sorted, pct) };
+        Self {
+            count: sorted.len(),
+            p50: at(50.0),
+            p90: at(90.0),
+            p99: at(99.0),
+            max: sorted.last().copied().unwrap_or(0.0),
+            bounds: bounds.to_vec(),
+            histogram,
+        }
+    }
+
+    /// Print the histogram, one bar per bucket, labelling bounds with
+    /// `label`.
+    fn print_histogram(&self, label: impl Fn(f64) -> String) {
+        let widest = self.histogram.iter().copied().max().unwrap_or(0).max(1);
+        for (i, &count) in self.histogram.iter().enumerate() {
+            let range = match i {
+                0 => format!("< {}", label(self.bounds[0])),
+                i if i == self.bounds.len() => format!(">= {}", label(self.bounds[i - 1])),
+                i => format!("{}-{}", label(self.bounds[i - 1]), label(self.bounds[i])),
+            };
+            let bar = "#".repeat((count * 30).div_ceil(widest));
+            println!("{}", format!("    {:>11} {:>6}  {}", range, count, bar).trim_end());
+        }
+    }
+}
+
+/// Orders placed in one range of time relative to the signal, and how many
+/// filled.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlacementBucket {
+    /// When the orders were placed, in ms after the signal (negative
+    /// before it); `None` for the open ends.
+    pub from_ms: Option<i64>,
+    pub to_ms: Option<i64>,
+    pub orders: usize,
+    pub fills: usize,
+}
+
+impl PlacementBucket {
+    pub fn fill_rate(&self) -> f64 {
+        if self.orders > 0 {
+            self.fills as f64 / self.orders as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Fill rates of `traded` windows by when each placed its first order
+/// relative to the signal at `signal_offset_ms`, over
+/// [`PLACEMENT_BUCKETS_MS`].
+fn fill_by_placement(traded: &[&WindowResult], signal_offset_ms: i64) -> Vec<PlacementBucket> {
+    let bounds = PLACEMENT_BUCKETS_MS;
+    let mut buckets: Vec<PlacementBucket> = (0..=bounds.len())
+        .map(|i| PlacementBucket {
+            from_ms: i.checked_sub(1).map(|j| bounds[j]),
+            to_ms: bounds.get(i).copied(),
+            orders: 0,
+            fills: 0,
+        })
+        .collect();
+    for r in traded {
+        let Some(placed) = r.signal_offset_ms else {
+            continue;
+        };
+        let relative = placed - signal_offset_ms;
+        let bucket = &mut buckets[bounds.iter().position(|&bound| relative < bound).unwrap_or(bounds.len())];
+        bucket.orders += 1;
+        bucket.fills += r.filled as usize;
+    }
+    buckets
+}
+
+/// How the bankroll of a run sized from one (see
+/// [`Bankroll`](crate::types::Bankroll)) moved over its windows.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BankrollSummary {
+    pub starting: f64,
+    pub terminal: f64,
+    pub peak: f64,
+    /// Largest fall from a running peak, in dollars and as a fraction of
+    /// that peak.
+    pub max_drawdown: f64,
+    pub max_drawdown_pct: f64,
+    /// The bankroll before the first window and after each one.
+    pub equity_curve: Vec<f64>,
+}
+
+impl BankrollSummary {
+    /// The bankroll path of `results`, or `None` if they were not sized
+    /// from one.
+    pub fn from_results(results: &[WindowResult]) -> Option<Self> {
+        let sized: Vec<&WindowResult> = results.iter().filter(|r| r.bankroll.is_some()).collect();
+        let first = sized.first()?;
+        let starting = first.bankroll? - first.realistic_pnl;
+        let mut equity_curve = vec![starting];
+        equity_curve.extend(sized.iter().filter_map(|r| r.bankroll));
+
+        let (mut peak, mut max_drawdown, mut max_drawdown_pct) = (starting, 0.0, 0.0);
+        for &equity in &equity_curve {
+            peak = f64::max(peak, equity);
+            if peak - equity > max_drawdown {
+                max_drawdown = peak - equity;
+                max_drawdown_pct = if peak > 0.0 { max_drawdown / peak } else { 0.0 };
+            }
+        }
+        Some(Self {
+            starting,
+            terminal: *equity_curve.last()?,
+            peak,
+            max_drawdown,
+            max_drawdown_pct,
+            equity_curve,
+        })
+    }
+
+    /// The equity curve as a one-line sparkline at most `width` characters
+    /// wide.
+    pub fn sparkline(&self, width: usize) -> String {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let curve = &self.equity_curve;
+        let width = width.clamp(1, curve.len());
+        let points: Vec<f64> = (0..width).map(|i| curve[i * (curve.len() - 1) / (width - 1).max(1)]).collect();
+        let lo = points.iter().copied().fold(f64::INFINITY, f64::min);
+        let hi = points.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        points
+            .iter()
+            .map(|&v| {
+                let level = if hi > lo { (v - lo) / (hi - lo) * 7.0 } else { 0.0 };
+                BARS[level.round() as usize]
+            })
+            .collect()
+    }
+}
+
 /// Summary report computed from a backtest run.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Report {
     pub strategy_name: String,
     pub fill_model_name: String,
@@ -179,6 +385,20 @@ pub struct Report {
     pub fills: usize,
     pub correct: usize,
     pub skipped: usize,
+    /// Windows the strategy aborted (e.g. script budget exceeded).
+    pub aborted: usize,
+    /// Script runtime errors swallowed across all windows.
+    pub script_errors: usize,
+    /// Windows with at least one swallowed script error.
+    pub error_windows: usize,
+    /// Windows whose market was voided; stakes are refunded.
+    pub void: usize,
+    /// Windows that had not resolved when the data was exported.
+    pub unresolved: usize,
+    /// Bids refused under the venue's order rules.
+    pub rejected_orders: usize,
+    /// How much trading time the windows shared.
+    pub overlaps: Overlaps,
 
     // Rates
     pub fill_rate: f64,
@@ -195,21 +415,61 @@ pub struct Report {
     // Queue stats
     pub avg_queue_ahead: f64,
     pub avg_fill_time_ms: f64,
+    /// Queue ahead at placement over traded windows, in shares.
+    pub queue_ahead: Distribution,
+    /// Fill time over filled windows, in ms from the open.
+    pub fill_time_ms: Distribution,
+    /// The signal [`fill_by_placement`](Self::fill_by_placement) is
+    /// measured from, in ms from the open.
+    pub signal_offset_ms: i64,
+    /// Fill rate by when each traded window's first order was placed
+    /// relative to the signal at [`signal_offset_ms`](Self::signal_offset_ms).
+    pub fill_by_placement: Vec<PlacementBucket>,
+
+    /// Equity curve and drawdown, for runs sized from a bankroll.
+    pub bankroll: Option<BankrollSummary>,
 }
 
 impl Report {
-    /// Build a report from backtest results.
+    /// Build a report from backtest results, placing orders against the
+    /// signal at [`DEFAULT_SIGNAL_OFFSET_MS`].
     pub fn from_results(
         results: &[WindowResult],
         strategy_name: &str,
         fill_model_name: &str,
+    ) -> Self {
+        Self::from_results_with_signal(results, strategy_name, fill_model_name, DEFAULT_SIGNAL_OFFSET_MS)
+    }
+
+    /// Like [`from_results`](Self::from_results), with the run's configured
+    /// signal offset (see [`StrategySpec::signal_offset_ms`]) for
+    /// [`fill_by_placement`](Self::fill_by_placement).
+    ///
+    /// [`StrategySpec::signal_offset_ms`]: crate::strategies::StrategySpec::signal_offset_ms
+    pub fn from_results_with_signal(
+        results: &[WindowResult],
+        strategy_name: &str,
+        fill_model_name: &str,
+        signal_offset_ms: i64,
     ) -> Self {
         let total_windows = results.len();
 
+        // Void and unresolved windows paid nothing out either way, so they
+        // are counted on their own and left out of the trade stats.
+        let (settled, unsettled): (Vec<&WindowResult>, Vec<&WindowResult>) =
+            results.iter().partition(|r| r.is_settled());
+        let void = unsettled.iter().filter(|r| r.outcome == Outcome::Void.label()).count();
+        let unresolved = unsettled.len() - void;
+
         // A trade was taken if bid_side is set (strategy placed an order).
-        let traded: Vec<&WindowResult> = results.iter().filter(|r| r.bid_side.is_some()).collect();
+        let traded: Vec<&WindowResult> = settled.iter().copied().filter(|r| r.bid_side.is_some()).collect();
         let trades_taken = traded.len();
-        let skipped = total_windows - trades_taken;
+        let skipped = settled.len() - trades_taken;
+        let aborted = results.iter().filter(|r| r.aborted.is_some()).count();
+        let script_errors = results.iter().map(|r| r.script_errors).sum();
+        let error_windows = results.iter().filter(|r| r.script_errors > 0).count();
+        let rejected_orders = results.iter().map(|r| r.rejected_orders).sum();
+        let overlaps = overlaps(results.iter().map(|r| (r.open_ts, r.close_ts)));
 
         let fills = traded.iter().filter(|r| r.filled).count();
         // "correct" in naive sense: predicted the winner regardless of fill.
@@ -273,6 +533,13 @@ impl Report {
             fills,
             correct: realistic_correct,
             skipped,
+            aborted,
+            script_errors,
+            error_windows,
+            void,
+            unresolved,
+            rejected_orders,
+            overlaps,
             fill_rate,
             naive_win_rate,
             realistic_win_rate,
@@ -283,9 +550,30 @@ impl Report {
             avg_realistic_pnl,
             avg_queue_ahead,
             avg_fill_time_ms,
+            queue_ahead: Distribution::new(traded.iter().map(|r| r.queue_ahead_at_place), QUEUE_AHEAD_BUCKETS),
+            fill_time_ms: Distribution::new(fill_times, FILL_TIME_BUCKETS_MS),
+            signal_offset_ms,
+            fill_by_placement: fill_by_placement(&traded, signal_offset_ms),
+            bankroll: BankrollSummary::from_results(results),
         }
     }
 
+    /// Recompute the report of a persisted run (see
+    /// [`DataStore::insert_results`]) over the results passing `filter`,
+    /// without re-simulating.
+    pub fn from_store(store: &dyn DataStore, run_id: i64, filter: &ResultFilter) -> Result<Self> {
+        let run = store
+            .load_run(run_id)?
+            .with_context(|| format!("no stored run with id {}", run_id))?;
+        let results = store.load_results(run_id, filter)?;
+        Ok(Self::from_results_with_signal(
+            &results,
+            &run.meta.strategy.name,
+            &run.meta.fill_model,
+            run.meta.strategy.signal_offset_ms(),
+        ))
+    }
+
     /// Print a formatted text report to stdout.
     pub fn print(&self) {
         let pct = |n: usize, d: usize| -> f64 {
@@ -331,6 +619,50 @@ impl Report {
             self.skipped,
             pct(self.skipped, self.total_windows)
         );
+        if self.void > 0 {
+            println!(
+                "  Void:         {}    ({:.1}%, stakes refunded)",
+                self.void,
+                pct(self.void, self.total_windows)
+            );
+        }
+        if self.unresolved > 0 {
+            println!(
+                "  Unresolved:   {}    ({:.1}%, not settled at export)",
+                self.unresolved,
+                pct(self.unresolved, self.total_windows)
+            );
+        }
+        if self.aborted > 0 {
+            println!(
+                "  Aborted:      {}    ({:.1}%)",
+                self.aborted,
+                pct(self.aborted, self.total_windows)
+            );
+        }
+        if self.script_errors > 0 {
+            println!(
+                "  Script errors: {} in {} windows    ({:.1}%; see log, or --strict-scripts)",
+                self.script_errors,
+                self.error_windows,
+                pct(self.error_windows, self.total_windows)
+            );
+        }
+        if self.rejected_orders > 0 {
+            println!(
+                "  Rejected bids: {}    (off-tick, out of range, mis-sized or over portfolio limits; see the rejection column)",
+                self.rejected_orders
+            );
+        }
+        if self.overlaps.windows > 0 {
+            println!(
+                "  Overlapping:  {}    ({:.1}%, {} groups, up to {} open at once; see --shared-timeline)",
+                self.overlaps.windows,
+                pct(self.overlaps.windows, self.total_windows),
+                self.overlaps.groups,
+                self.overlaps.max_concurrent
+            );
+        }
 
         println!();
         println!("  --- PnL {}",  "-".repeat(45));
@@ -356,6 +688,24 @@ impl Report {
             self.avg_realistic_pnl
         );
 
+        if let Some(ref b) = self.bankroll {
+            println!();
+            println!("  --- Bankroll {}", "-".repeat(40));
+            println!("  Starting:        {:.2}", b.starting);
+            println!(
+                "  Terminal:        {:.2}   ({:+.1}%)",
+                b.terminal,
+                if b.starting > 0.0 { (b.terminal / b.starting - 1.0) * 100.0 } else { 0.0 }
+            );
+            println!(
+                "  Max drawdown:    {:.2}   ({:.1}% from peak {:.2})",
+                b.max_drawdown,
+                b.max_drawdown_pct * 100.0,
+                b.peak
+            );
+            println!("  Equity:          {}", b.sparkline(36));
+        }
+
         println!();
         println!("  --- Queue Stats {}", "-".repeat(37));
         println!(
@@ -366,6 +716,52 @@ impl Report {
             "  Avg fill time:    {:.0} ms",
             self.avg_fill_time_ms
         );
+        // Values and histogram bounds of each distribution, as text.
+        type Format<'a> = &'a dyn Fn(f64) -> String;
+        let secs = |ms: f64| format!("{}s", ms / 1000.0);
+        let tenths = |ms: f64| format!("{:.1}s", ms / 1000.0);
+        let shares = |n: f64| format!("{:.0}", n);
+        let distributions: [(&str, &Distribution, Format, Format); 2] = [
+            ("Queue ahead (shares)", &self.queue_ahead, &shares, &shares),
+            ("Fill time", &self.fill_time_ms, &tenths, &secs),
+        ];
+        for (name, d, value, bound) in distributions {
+            if d.count == 0 {
+                continue;
+            }
+            println!();
+            println!(
+                "  {}:  p50 {}  p90 {}  p99 {}  max {}",
+                name,
+                value(d.p50),
+                value(d.p90),
+                value(d.p99),
+                value(d.max)
+            );
+            d.print_histogram(bound);
+        }
+
+        if self.fill_by_placement.iter().any(|b| b.orders > 0) {
+            println!();
+            println!(
+                "  Fill rate by placement vs the signal at {}:",
+                secs(self.signal_offset_ms as f64)
+            );
+            let at = |ms: i64| match ms {
+                0 => "0s".to_string(),
+                ms => format!("{:+}s", ms as f64 / 1000.0),
+            };
+            for b in &self.fill_by_placement {
+                let range = match (b.from_ms, b.to_ms) {
+                    (None, Some(to)) => format!("< {}", at(to)),
+                    (Some(from), None) => format!(">= {}", at(from)),
+                    (Some(from), Some(to)) => format!("{} to {}", at(from), at(to)),
+                    (None, None) => "any".to_string(),
+                };
+                let rate = if b.orders > 0 { format!("{:.1}%", b.fill_rate() * 100.0) } else { "-".to_string() };
+                println!("    {:>13} {:>6} orders {:>6} fills  {:>6}", range, b.orders, b.fills, rate);
+            }
+        }
 
         println!();
         println!(
@@ -375,13 +771,53 @@ impl Report {
         println!();
     }
 
-    /// Export all WindowResult rows to a CSV file.
+    /// Print one row per strategy comparing single-run reports.
+    pub fn print_comparison(reports: &[Report]) {
+        println!();
+        println!("{}", "=".repeat(87));
+        println!("  Strategy comparison");
+        println!("{}", "=".repeat(87));
+        println!(
+            "  {:<36} {:>6} {:>6} {:>6} {:>9} {:>9} {:>9}",
+            "strategy", "trades", "fill%", "WR%", "naive", "realistic", "gap"
+        );
+        for r in reports {
+            println!(
+                "  {:<36.36} {:>6} {:>6.1} {:>6.1} {:>+9.2} {:>+9.2} {:>9.2}",
+                r.strategy_name,
+                r.trades_taken,
+                r.fill_rate * 100.0,
+                r.realistic_win_rate * 100.0,
+                r.naive_total_pnl,
+                r.realistic_total_pnl,
+                r.phantom_fill_gap
+            );
+        }
+        println!();
+    }
+
+    /// Export all WindowResult rows to a CSV file. Each window's order
+    /// [`events`](WindowResult::events) go in the last column, as a JSON
+    /// array.
     pub fn export_csv(results: &[WindowResult], path: &Path) -> Result<()> {
+        #[derive(Serialize)]
+        struct Events {
+            events: String,
+        }
+
         let mut wtr = csv::Writer::from_path(path)
             .with_context(|| format!("failed to create CSV at {}", path.display()))?;
 
         for r in results {
-            wtr.serialize(r)
+            let events = Events {
+                events: serde_json::to_string(&r.events)?,
+            };
+            // CSV can't nest the list, so the row goes without it.
+            let row = WindowResult {
+                events: Vec::new(),
+                ..r.clone()
+            };
+            wtr.serialize((row, events))
                 .with_context(|| format!("failed to write CSV row for {}", r.market_id))?;
         }
 
@@ -390,9 +826,146 @@ impl Report {
     }
 }
 
+/// Realistic PnL differences smaller than this count as equal.
+const PNL_EPSILON: f64 = 1e-9;
+
+/// One window whose fill status or realistic PnL differs between two runs.
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowDiff {
+    pub market_id: String,
+    pub open_ts: i64,
+    pub filled_a: bool,
+    pub filled_b: bool,
+    pub pnl_a: f64,
+    pub pnl_b: f64,
+}
+
+impl WindowDiff {
+    /// Run B's PnL minus run A's.
+    pub fn pnl_delta(&self) -> f64 {
+        self.pnl_b - self.pnl_a
+    }
+}
+
+/// Two runs compared window by window (`pf runs diff`), matching windows by
+/// market id.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunDiff {
+    /// Windows present in both runs.
+    pub matched: usize,
+    /// Matched windows that diverged, largest PnL difference first.
+    pub diverged: Vec<WindowDiff>,
+    /// Windows only run A has, and only run B has.
+    pub only_a: Vec<String>,
+    pub only_b: Vec<String>,
+    /// Realistic PnL over the matched windows.
+    pub pnl_a: f64,
+    pub pnl_b: f64,
+}
+
+impl RunDiff {
+    pub fn new(a: &[WindowResult], b: &[WindowResult]) -> Self {
+        let by_market: HashMap<&str, &WindowResult> =
+            b.iter().map(|r| (r.market_id.as_str(), r)).collect();
+        let in_a: HashSet<&str> = a.iter().map(|r| r.market_id.as_str()).collect();
+
+        let mut diff = RunDiff {
+            matched: 0,
+            diverged: Vec::new(),
+            only_a: Vec::new(),
+            only_b: b
+                .iter()
+                .filter(|r| !in_a.contains(r.market_id.as_str()))
+                .map(|r| r.market_id.clone())
+                .collect(),
+            pnl_a: 0.0,
+            pnl_b: 0.0,
+        };
+        for ra in a {
+            let Some(rb) = by_market.get(ra.market_id.as_str()) else {
+                diff.only_a.push(ra.market_id.clone());
+                continue;
+            };
+            diff.matched += 1;
+            diff.pnl_a += ra.realistic_pnl;
+            diff.pnl_b += rb.realistic_pnl;
+            if ra.filled != rb.filled || (ra.realistic_pnl - rb.realistic_pnl).abs() > PNL_EPSILON {
+                diff.diverged.push(WindowDiff {
+                    market_id: ra.market_id.clone(),
+                    open_ts: ra.open_ts,
+                    filled_a: ra.filled,
+                    filled_b: rb.filled,
+                    pnl_a: ra.realistic_pnl,
+                    pnl_b: rb.realistic_pnl,
+                });
+            }
+        }
+        diff.diverged
+            .sort_by(|x, y| y.pnl_delta().abs().total_cmp(&x.pnl_delta().abs()));
+        diff
+    }
+
+    /// Compare persisted runs `run_a` and `run_b` (see
+    /// [`DataStore::insert_results`]).
+    pub fn from_store(store: &dyn DataStore, run_a: i64, run_b: i64) -> Result<Self> {
+        let a = store.load_results(run_a, &ResultFilter::default())?;
+        let b = store.load_results(run_b, &ResultFilter::default())?;
+        Ok(Self::new(&a, &b))
+    }
+
+    /// Matched windows filled in one run but not the other: (A only, B only).
+    pub fn fill_flips(&self) -> (usize, usize) {
+        let a_only = self.diverged.iter().filter(|d| d.filled_a && !d.filled_b).count();
+        let b_only = self.diverged.iter().filter(|d| !d.filled_a && d.filled_b).count();
+        (a_only, b_only)
+    }
+
+    /// Print the summary and the `top` largest divergences.
+    pub fn print(&self, top: usize) {
+        let (a_only, b_only) = self.fill_flips();
+        println!("  Matched windows:  {}", self.matched);
+        if !self.only_a.is_empty() || !self.only_b.is_empty() {
+            println!("  Unmatched:        {} only in A, {} only in B", self.only_a.len(), self.only_b.len());
+        }
+        println!("  Diverged:         {}", self.diverged.len());
+        println!("  Fill flips:       {} filled only in A, {} filled only in B", a_only, b_only);
+        println!(
+            "  Realistic PnL:    A {:+.2}, B {:+.2} ({:+.2})",
+            self.pnl_a,
+            self.pnl_b,
+            self.pnl_b - self.pnl_a
+        );
+        if self.diverged.is_empty() {
+            return;
+        }
+
+        println!();
+        println!(
+            "  {:<40} {:>6} {:>6} {:>9} {:>9} {:>9}",
+            "market", "fill A", "fill B", "PnL A", "PnL B", "delta"
+        );
+        let yes_no = |filled: bool| if filled { "yes" } else { "no" };
+        for d in self.diverged.iter().take(top) {
+            println!(
+                "  {:<40.40} {:>6} {:>6} {:>+9.2} {:>+9.2} {:>+9.2}",
+                d.market_id,
+                yes_no(d.filled_a),
+                yes_no(d.filled_b),
+                d.pnl_a,
+                d.pnl_b,
+                d.pnl_delta()
+            );
+        }
+        if self.diverged.len() > top {
+            println!("  ... and {} more", self.diverged.len() - top);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::{OrderEvent, OrderId, Side};
 
     fn make_result(
         bid_side: Option<&str>,
@@ -423,9 +996,88 @@ mod tests {
             naive_pnl,
             ref_price_open: Some(66000.0),
             ref_price_close: Some(66100.0),
+            aborted: None,
+            script_errors: 0,
+            rejected_orders: 0,
+            rejection: None,
+            stake: 0.0,
+            bankroll: None,
+            events: Vec::new(),
         }
     }
 
+    #[test]
+    fn test_report_from_store() {
+        use crate::data::{MemoryStore, RunMeta};
+        use crate::strategies::StrategySpec;
+
+        let mut eth = make_result(Some("YES"), true, true, 5.1, 5.1, 100.0, Some(2000));
+        eth.category = "eth".to_string();
+        eth.open_ts = 5000;
+        eth.close_ts = 5300;
+        let results = vec![
+            make_result(Some("YES"), true, false, -4.9, -4.9, 50.0, Some(1000)),
+            eth,
+            make_result(None, false, false, 0.0, 0.0, 0.0, None),
+        ];
+        let store = MemoryStore::new();
+        let spec = StrategySpec {
+            name: "momentum".to_string(),
+            params: Default::default(),
+        };
+        let run_id = store.insert_results(&RunMeta::new(spec, "delise", Some(1)), &results).unwrap();
+
+        let report = Report::from_store(&store, run_id, &ResultFilter::default()).unwrap();
+        assert_eq!(report.strategy_name, "momentum");
+        assert_eq!(report.fill_model_name, "delise");
+        assert_eq!(report.total_windows, 3);
+        assert!((report.realistic_total_pnl - 0.2).abs() < 1e-9);
+
+        let filter = ResultFilter {
+            category: Some("btc".to_string()),
+            ..ResultFilter::default()
+        };
+        let btc = Report::from_store(&store, run_id, &filter).unwrap();
+        assert_eq!(btc.total_windows, 2);
+        assert_eq!(btc.fills, 1);
+        let late = ResultFilter {
+            min_ts: Some(2000),
+            ..ResultFilter::default()
+        };
+        assert_eq!(Report::from_store(&store, run_id, &late).unwrap().fills, 1);
+        assert!(Report::from_store(&store, run_id + 1, &filter).is_err());
+    }
+
+    #[test]
+    fn test_run_diff() {
+        let result = |id: &str, filled: bool, pnl: f64| WindowResult {
+            market_id: id.to_string(),
+            ..make_result(Some("YES"), filled, pnl > 0.0, pnl, pnl, 10.0, None)
+        };
+        let a = vec![
+            result("same", true, 5.1),
+            result("flip", true, 5.1),
+            result("worse", true, 5.1),
+            result("gone", false, 0.0),
+        ];
+        let b = vec![
+            result("same", true, 5.1),
+            result("flip", false, 0.0),
+            result("worse", true, -4.9),
+            result("new", true, 5.1),
+        ];
+
+        let diff = RunDiff::new(&a, &b);
+        assert_eq!(diff.matched, 3);
+        let ids: Vec<&str> = diff.diverged.iter().map(|d| d.market_id.as_str()).collect();
+        assert_eq!(ids, ["worse", "flip"]);
+        assert!((diff.diverged[0].pnl_delta() + 10.0).abs() < 1e-9);
+        assert_eq!(diff.fill_flips(), (1, 0));
+        assert_eq!((diff.only_a.as_slice(), diff.only_b.as_slice()), (&["gone".to_string()][..], &["new".to_string()][..]));
+        assert!((diff.pnl_a - 15.3).abs() < 1e-9);
+        assert!((diff.pnl_b - 0.2).abs() < 1e-9);
+    }
+
     #[test]
     fn test_empty_results() {
         let report = Report::from_results(&[], "test", "delise");
@@ -451,6 +1103,23 @@ mod tests {
         assert_eq!(report.fill_rate, 0.0);
     }
 
+    #[test]
+    fn test_void_and_unresolved_windows_counted_apart() {
+        let mut void = make_result(Some("YES"), true, false, 0.0, 0.0, 200.0, Some(45000));
+        void.outcome = "VOID".to_string();
+        let mut unresolved = make_result(None, false, false, 0.0, 0.0, 0.0, None);
+        unresolved.outcome = "UNRESOLVED".to_string();
+        let results = vec![
+            make_result(Some("YES"), true, true, 0.51, 0.51, 200.0, Some(45000)),
+            void,
+            unresolved,
+        ];
+        let report = Report::from_results(&results, "test", "delise");
+        assert_eq!((report.total_windows, report.void, report.unresolved), (3, 1, 1));
+        assert_eq!((report.trades_taken, report.skipped, report.fills), (1, 0, 1));
+        assert_eq!(report.realistic_win_rate, 1.0);
+    }
+
     #[test]
     fn test_basic_counts() {
         let results = vec![
@@ -510,12 +1179,69 @@ mod tests {
         assert!((report.avg_fill_time_ms - 45000.0).abs() < 1e-9);
     }
 
+    #[test]
+    fn test_fill_time_and_queue_distributions() {
+        // Nine fills between 10s and 50s in, and one that waited until 280s.
+        let mut results: Vec<WindowResult> = (1..=10)
+            .map(|i| {
+                let fill_time = if i == 10 { 280_000 } else { i * 5_000 + 5_000 };
+                make_result(Some("YES"), true, true, 0.51, 0.51, i as f64 * 100.0, Some(fill_time))
+            })
+            .collect();
+        results.push(make_result(Some("YES"), false, true, 0.0, 0.0, 6_000.0, None));
+        for (i, r) in results.iter_mut().enumerate() {
+            // Placed 45s before the signal, on it, or 45s after.
+            r.signal_offset_ms = Some(DEFAULT_SIGNAL_OFFSET_MS + (i as i64 % 3 - 1) * 45_000);
+        }
+        let report = Report::from_results(&results, "test", "delise");
+
+        let fill = &report.fill_time_ms;
+        assert_eq!(fill.count, 10);
+        assert!((fill.p50 - 32_500.0).abs() < 1e-9);
+        assert!(fill.p90 > 50_000.0 && fill.p99 > fill.p90);
+        assert_eq!(fill.max, 280_000.0);
+        assert_eq!(fill.histogram, vec![1, 3, 5, 0, 0, 0, 1, 0, 0]);
+
+        let queue = &report.queue_ahead;
+        assert_eq!(queue.count, 11);
+        assert_eq!(queue.histogram, vec![0, 0, 0, 2, 2, 5, 1, 0, 1]);
+
+        let by_placement: Vec<_> = report
+            .fill_by_placement
+            .iter()
+            .map(|b| (b.from_ms, b.orders, b.fills))
+            .collect();
+        assert_eq!(
+            by_placement,
+            vec![
+                (None, 0, 0),
+                (Some(-60_000), 4, 4),
+                (Some(-30_000), 0, 0),
+                (Some(0), 4, 3),
+                (Some(30_000), 3, 3),
+                (Some(60_000), 0, 0),
+                (Some(120_000), 0, 0),
+            ]
+        );
+
+        // Measured from a later signal, every order went in before it.
+        let later = Report::from_results_with_signal(&results, "test", "delise", DEFAULT_SIGNAL_OFFSET_MS + 90_000);
+        assert_eq!(later.signal_offset_ms, 180_000);
+        let by_placement: Vec<_> = later.fill_by_placement.iter().map(|b| (b.orders, b.fills)).collect();
+        assert_eq!(by_placement, vec![(8, 7), (3, 3), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0)]);
+    }
+
     #[test]
     fn test_export_csv_roundtrip() {
-        let results = vec![
+        let mut results = vec![
             make_result(Some("YES"), true, true, 0.51, 0.51, 200.0, Some(30000)),
             make_result(Some("NO"), false, false, -0.49, 0.0, 300.0, None),
         ];
+        results[0].events.push(OrderEvent::Cancelled {
+            order_id: OrderId::new(1),
+            side: Side::Yes,
+            offset_ms: 1500,
+        });
 
         let dir = std::env::temp_dir().join("phantomfill_test_csv");
         let _ = std::fs::create_dir_all(&dir);
@@ -531,6 +1257,9 @@ mod tests {
         assert!(lines[0].contains("market_id"));
         assert!(lines[0].contains("naive_pnl"));
         assert!(lines[0].contains("realistic_pnl"));
+        assert!(lines[0].ends_with(",events"));
+        assert!(lines[1].ends_with(r#","[{""type"":""cancelled"",""order_id"":1,""side"":""Yes"",""offset_ms"":1500}]""#), "{}", lines[1]);
+        assert!(lines[2].ends_with(",[]"));
 
         // Cleanup
         let _ = std::fs::remove_file(&path);
@@ -546,6 +1275,43 @@ mod tests {
         report.print();
     }
 
+    #[test]
+    fn test_bankroll_summary() {
+        let mut results = vec![
+            make_result(Some("YES"), true, true, 5.0, 5.0, 0.0, None),
+            make_result(Some("YES"), true, false, -10.0, -10.0, 0.0, None),
+            make_result(None, false, false, 0.0, 0.0, 0.0, None),
+            make_result(Some("YES"), true, true, 20.0, 20.0, 0.0, None),
+        ];
+        assert!(Report::from_results(&results, "test", "delise").bankroll.is_none());
+        for (r, equity) in results.iter_mut().zip([105.0, 95.0, 95.0, 115.0]) {
+            r.bankroll = Some(equity);
+        }
+        let b = Report::from_results(&results, "test", "delise").bankroll.unwrap();
+        assert_eq!(b.equity_curve, [100.0, 105.0, 95.0, 95.0, 115.0]);
+        assert_eq!((b.starting, b.terminal, b.peak), (100.0, 115.0, 115.0));
+        assert_eq!(b.max_drawdown, 10.0);
+        assert!((b.max_drawdown_pct - 10.0 / 105.0).abs() < 1e-9);
+        assert_eq!(b.sparkline(80), "▃▅▁▁█");
+    }
+
+    #[test]
+    fn test_overlapping_windows_counted() {
+        // Back to back: 1000-1300, 1300-1600, 1600-1900.
+        let mut results: Vec<WindowResult> = (0..3)
+            .map(|i| WindowResult {
+                open_ts: 1000 + i * 300,
+                close_ts: 1300 + i * 300,
+                ..make_result(Some("YES"), true, true, 5.0, 5.0, 0.0, None)
+            })
+            .collect();
+        assert_eq!(Report::from_results(&results, "test", "delise").overlaps.windows, 0);
+        results[1].open_ts = 1100;
+        results[2].open_ts = 1400;
+        let overlaps = Report::from_results(&results, "test", "delise").overlaps;
+        assert_eq!(overlaps, Overlaps { windows: 3, groups: 1, max_concurrent: 2 });
+    }
+
     #[test]
     fn test_report_names() {
         let report = Report::from_results(&[], "my_strat", "my_model");
@@ -566,6 +1332,13 @@ mod tests {
             fills: 80,
             correct: 70,
             skipped: 5,
+            aborted: 0,
+            script_errors: 0,
+            error_windows: 0,
+            void: 0,
+            unresolved: 0,
+            rejected_orders: 0,
+            overlaps: Overlaps::default(),
             fill_rate,
             naive_win_rate: 0.9,
             realistic_win_rate: win_rate,
@@ -576,6 +1349,11 @@ mod tests {
             avg_realistic_pnl: realistic / 95.0,
             avg_queue_ahead: 200.0,
             avg_fill_time_ms: 45000.0,
+            queue_ahead: Distribution::default(),
+            fill_time_ms: Distribution::default(),
+            signal_offset_ms: DEFAULT_SIGNAL_OFFSET_MS,
+            fill_by_placement: Vec::new(),
+            bankroll: None,
         }
     }
 
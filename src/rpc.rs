@@ -0,0 +1,271 @@
+//! JSON-RPC over stdio: `pf rpc`.
+//!
+//! Editors, Electron frontends and other languages can drive backtests by
+//! spawning `pf rpc --db hf.db` and exchanging newline-delimited
+//! [JSON-RPC 2.0](https://www.jsonrpc.org/specification) messages on its
+//! stdin/stdout, with no Rust linking and no HTTP server. Requests are
+//! handled one at a time, in order; logs go to stderr.
+//!
+//! | Method            | Params                                                      | Result                             |
+//! |-------------------|-------------------------------------------------------------|------------------------------------|
+//! | `list_markets`    | `platform`, `category`, `duration_secs`, `min_ts`, `max_ts` | Matching markets, by open time     |
+//! | `list_strategies` | none                                                        | Built-in strategy names            |
+//! | `run_backtest`    | A [`BacktestConfig`]                                        | The [`RunOutput`]                  |
+//! | `stream_results`  | A [`BacktestConfig`]                                        | `strategy`, `report` and `summary` |
+//!
+//! `stream_results` first sends one `window_result` notification per
+//! finished window, `{"request_id", "run", "result"}`, then its response:
+//!
+//! ```text
+//! -> {"jsonrpc": "2.0", "id": 1, "method": "stream_results", "params": {"strategy": "momentum", "runs": 2}}
+//! <- {"jsonrpc": "2.0", "method": "window_result", "params": {"request_id": 1, "run": 0, "result": {...}}}
+//! <- ...
+//! <- {"jsonrpc": "2.0", "id": 1, "result": {"strategy": {...}, "report": {...}, "summary": {...}}}
+//! ```
+
+use std::io::{BufRead, Write};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::backtest::BacktestConfig;
+#[cfg(doc)]
+use crate::backtest::RunOutput;
+use crate::data::{DataStore, MarketFilter};
+use crate::strategies::list_strategies;
+use crate::types::Platform;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// A well-formed request that failed (e.g. the backtest errored).
+const SERVER_ERROR: i64 = -32000;
+
+/// `list_markets` params.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FilterParams {
+    platform: Option<Platform>,
+    category: Option<String>,
+    duration_secs: Option<i64>,
+    min_ts: Option<i64>,
+    max_ts: Option<i64>,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+fn params<T: for<'de> Deserialize<'de> + Default>(params: Option<Value>) -> Result<T, RpcError> {
+    match params {
+        None | Some(Value::Null) => Ok(T::default()),
+        Some(value) => serde_json::from_value(value).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string())),
+    }
+}
+
+fn server_error(err: anyhow::Error) -> RpcError {
+    RpcError::new(SERVER_ERROR, format!("{:#}", err))
+}
+
+/// Handle one request line. `notify` receives any notifications sent while
+/// it runs; the return value is the response, or `None` for JSON-RPC
+/// notifications (requests without an `id`), which get no reply.
+pub fn handle_line(store: &dyn DataStore, line: &str, notify: &mut dyn FnMut(Value)) -> Option<Value> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => return Some(error_response(Value::Null, RpcError::new(PARSE_ERROR, e.to_string()))),
+    };
+    let id = request.get("id").cloned();
+    let method = request.get("method").and_then(Value::as_str);
+    let Some(method) = method.filter(|_| request.get("jsonrpc") == Some(&json!("2.0"))) else {
+        return Some(error_response(
+            id.unwrap_or(Value::Null),
+            RpcError::new(INVALID_REQUEST, "expected a JSON-RPC 2.0 request with a method"),
+        ));
+    };
+    let request_id = id.clone().unwrap_or(Value::Null);
+
+    let result = dispatch(store, method, request.get("params").cloned(), &request_id, notify);
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => error_response(id, err),
+    })
+}
+
+fn dispatch(
+    store: &dyn DataStore,
+    method: &str,
+    raw: Option<Value>,
+    request_id: &Value,
+    notify: &mut dyn FnMut(Value),
+) -> Result<Value, RpcError> {
+    match method {
+        "list_markets" => {
+            let filter: FilterParams = params(raw)?;
+            let markets = store
+                .list_markets(&MarketFilter {
+                    platform: filter.platform,
+                    category: filter.category,
+                    duration_secs: filter.duration_secs,
+                    min_ts: filter.min_ts,
+                    max_ts: filter.max_ts,
+                })
+                .map_err(server_error)?;
+            Ok(json!(markets))
+        }
+        "list_strategies" => {
+            let strategies: Vec<Value> = list_strategies()
+                .into_iter()
+                .map(|(name, description)| json!({ "name": name, "description": description }))
+                .collect();
+            Ok(json!(strategies))
+        }
+        "run_backtest" => {
+            let config: BacktestConfig = params(raw)?;
+            let output = config.run(store).map_err(server_error)?;
+            Ok(json!(output))
+        }
+        "stream_results" => {
+            let config: BacktestConfig = params(raw)?;
+            let output = config
+                .run_streaming(store, |run, result| {
+                    notify(json!({
+                        "jsonrpc": "2.0",
+                        "method": "window_result",
+                        "params": { "request_id": request_id, "run": run, "result": result },
+                    }))
+                })
+                .map_err(server_error)?;
+            Ok(json!({
+                "strategy": output.strategy,
+                "report": output.report,
+                "summary": output.summary,
+            }))
+        }
+        _ => Err(RpcError::new(METHOD_NOT_FOUND, format!("unknown method `{}`", method))),
+    }
+}
+
+fn error_response(id: Value, err: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": err.code, "message": err.message },
+    })
+}
+
+fn write_message(output: &mut dyn Write, message: &Value) -> Result<()> {
+    writeln!(output, "{}", message)?;
+    output.flush().context("failed to write response")
+}
+
+/// Answer requests from `input` on `output` until `input` is exhausted.
+/// Notifications are written as they happen; blank lines are skipped.
+pub fn serve(store: &dyn DataStore, input: impl BufRead, mut output: impl Write) -> Result<()> {
+    for line in input.lines() {
+        let line = line.context("failed to read request")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut write_error = None;
+        let response = handle_line(store, &line, &mut |message| {
+            if write_error.is_none() {
+                write_error = write_message(&mut output, &message).err();
+            }
+        });
+        if let Some(err) = write_error {
+            return Err(err);
+        }
+        if let Some(response) = response {
+            write_message(&mut output, &response)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::{generate_dataset, BenchConfig};
+    use crate::data::MemoryStore;
+
+    fn store() -> MemoryStore {
+        let store = MemoryStore::new();
+        let config = BenchConfig {
+            markets: 4,
+            snapshots_per_market: 20,
+            seed: 8,
+        };
+        for (market, ticks) in generate_dataset(&config) {
+            store.insert_market(&market).unwrap();
+            store.insert_ticks(&ticks).unwrap();
+        }
+        store
+    }
+
+    fn session(input: &str) -> Vec<Value> {
+        let mut output = Vec::new();
+        serve(&store(), input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_methods_and_streaming() {
+        let messages = session(concat!(
+            r#"{"jsonrpc": "2.0", "id": 1, "method": "list_markets"}"#,
+            "\n\n",
+            r#"{"jsonrpc": "2.0", "id": "s", "method": "stream_results", "params": {"strategy": "spread_arb", "runs": 2, "seed": 3}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "run_backtest", "params": {"strategy": "spread_arb", "limit": 2}}"#,
+            "\n",
+        ));
+        assert_eq!(messages[0]["id"], 1);
+        assert_eq!(messages[0]["result"].as_array().unwrap().len(), 4);
+
+        let windows = &messages[1..9];
+        assert!(windows.iter().all(|m| m["method"] == "window_result" && m["params"]["request_id"] == "s"));
+        assert_eq!(windows[4]["params"]["run"], 1);
+        assert_eq!(messages[9]["id"], "s");
+        assert_eq!(messages[9]["result"]["summary"]["runs"], 2);
+
+        assert_eq!(messages[10]["result"]["results"].as_array().unwrap().len(), 2);
+        assert_eq!(messages.len(), 11);
+    }
+
+    #[test]
+    fn test_errors() {
+        let messages = session(concat!(
+            "not json\n",
+            r#"{"id": 1, "method": "list_markets"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "nope"}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "run_backtest", "params": {"shars": 1}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "run_backtest", "params": {"strategy": "nope"}}"#,
+            "\n",
+            r#"{"jsonrpc": "2.0", "method": "list_strategies"}"#,
+            "\n",
+        ));
+        let codes: Vec<i64> = messages.iter().map(|m| m["error"]["code"].as_i64().unwrap()).collect();
+        assert_eq!(codes, vec![PARSE_ERROR, INVALID_REQUEST, METHOD_NOT_FOUND, INVALID_PARAMS, SERVER_ERROR]);
+        assert!(messages[4]["error"]["message"].as_str().unwrap().contains("unknown strategy"));
+    }
+}
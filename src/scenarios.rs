@@ -0,0 +1,460 @@
+//! Synthetic market scenarios for testing strategies without a database.
+//!
+//! Each scenario is a single 5-minute window with a hand-shaped book and
+//! oracle path (flat, strong moves, an adverse sweep, an empty book). A
+//! strategy is replayed through the normal engine and fill model while its
+//! actions are recorded, so `pf script test` can check them against
+//! [`Expectation`]s plus a few invariants every strategy should hold.
+
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use smallvec::smallvec;
+
+use crate::fill::{DeLiseConfig, DeLiseFillModel};
+use crate::replay::{ReplayConfig, ReplayEngine};
+use crate::strategies::recorder::Recorder;
+use crate::strategies::Strategy;
+use crate::types::{
+    Action, BookSnapshot, Market, MarketId, OffsetMs, Outcome, Platform, PriceLevel, Shares, Side,
+    SideState, UnixSecs, VenueRules, WindowResult,
+};
+
+const OPEN_TS: UnixSecs = UnixSecs::new(1_700_000_000);
+const DURATION_SECS: i64 = 300;
+const ORACLE_OPEN: f64 = 50_000.0;
+
+/// A named synthetic market window.
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub market: Market,
+    pub snapshots: Vec<BookSnapshot>,
+}
+
+/// YES/NO best bids, resting size and oracle price at one tick.
+struct TickShape {
+    yes_bid: Option<f64>,
+    no_bid: Option<f64>,
+    size: f64,
+    oracle: f64,
+}
+
+/// Build a one-tick-per-second scenario from a shape function of the
+/// elapsed fraction of the window.
+fn build(
+    name: &'static str,
+    description: &'static str,
+    outcome: Outcome,
+    shape: impl Fn(f64) -> TickShape,
+) -> Scenario {
+    let id = format!("scenario-{}", name);
+    let market = Market {
+        id: id.clone(),
+        platform: Platform::Polymarket,
+        description: description.to_string(),
+        category: "btc".to_string(),
+        open_ts: OPEN_TS,
+        close_ts: OPEN_TS + DURATION_SECS,
+        duration_secs: DURATION_SECS,
+        outcome: Some(outcome),
+        outcome_ts: None,
+    };
+
+    let market_id = MarketId::from(id);
+    let snapshots = (0..DURATION_SECS)
+        .map(|sec| {
+            let offset_ms = OffsetMs::from_secs(sec);
+            let t = sec as f64 / DURATION_SECS as f64;
+            let s = shape(t);
+            BookSnapshot {
+                market_id: market_id.clone(),
+                offset_ms,
+                timestamp_ms: OPEN_TS.at(offset_ms),
+                yes: Arc::new(side_state(s.yes_bid, s.no_bid, s.size)),
+                no: Arc::new(side_state(s.no_bid, s.yes_bid, s.size)),
+                reference_price: Some(s.oracle),
+                oracle_price: Some(s.oracle),
+            }
+        })
+        .collect();
+
+    Scenario {
+        name,
+        description,
+        market,
+        snapshots,
+    }
+}
+
+/// One side of the book: two bid levels, with the ask implied by the
+/// opposite side's best bid.
+fn side_state(bid: Option<f64>, other_bid: Option<f64>, size: f64) -> SideState {
+    let Some(bid) = bid else {
+        return SideState::default();
+    };
+    let round = |p: f64| (p * 100.0).round() / 100.0;
+    SideState {
+        best_bid: Some(bid),
+        best_bid_size: Some(size),
+        best_ask: other_bid.map(|b| round(1.0 - b)),
+        best_ask_size: other_bid.map(|_| size),
+        depth: smallvec![
+            PriceLevel {
+                price: bid,
+                cumulative_size: size,
+            },
+            PriceLevel {
+                price: round(bid - 0.01),
+                cumulative_size: size * 3.0,
+            },
+        ],
+        total_bid_depth: size * 3.0,
+        total_ask_depth: if other_bid.is_some() { size * 3.0 } else { 0.0 },
+    }
+}
+
+/// The built-in scenario library, in display order.
+pub fn library() -> Vec<Scenario> {
+    vec![
+        build(
+            "flat",
+            "balanced book at 0.49/0.49, oracle unchanged",
+            Outcome::Yes,
+            |_| TickShape {
+                yes_bid: Some(0.49),
+                no_bid: Some(0.49),
+                size: 200.0,
+                oracle: ORACLE_OPEN,
+            },
+        ),
+        build(
+            "up_move",
+            "oracle rallies ~60 bps, YES bid climbs to 0.89",
+            Outcome::Yes,
+            |t| {
+                let yes = ((0.49 + 0.40 * t) * 100.0).round() / 100.0;
+                TickShape {
+                    yes_bid: Some(yes),
+                    no_bid: Some(((0.98 - yes) * 100.0).round() / 100.0),
+                    size: 200.0,
+                    oracle: ORACLE_OPEN * (1.0 + 0.006 * t),
+                }
+            },
+        ),
+        build(
+            "down_move",
+            "oracle sells off ~60 bps, NO bid climbs to 0.89",
+            Outcome::No,
+            |t| {
+                let no = ((0.49 + 0.40 * t) * 100.0).round() / 100.0;
+                TickShape {
+                    yes_bid: Some(((0.98 - no) * 100.0).round() / 100.0),
+                    no_bid: Some(no),
+                    size: 200.0,
+                    oracle: ORACLE_OPEN * (1.0 - 0.006 * t),
+                }
+            },
+        ),
+        build(
+            "adverse_sweep",
+            "flat until T+150s, then YES bids are swept to 0.20 and NO wins",
+            Outcome::No,
+            |t| {
+                if t < 0.5 {
+                    TickShape {
+                        yes_bid: Some(0.49),
+                        no_bid: Some(0.49),
+                        size: 200.0,
+                        oracle: ORACLE_OPEN,
+                    }
+                } else {
+                    TickShape {
+                        yes_bid: Some(0.20),
+                        no_bid: Some(0.78),
+                        size: 50.0,
+                        oracle: ORACLE_OPEN * 0.996,
+                    }
+                }
+            },
+        ),
+        build(
+            "empty_book",
+            "no bids or asks on either side, oracle unchanged",
+            Outcome::Yes,
+            |_| TickShape {
+                yes_bid: None,
+                no_bid: None,
+                size: 0.0,
+                oracle: ORACLE_OPEN,
+            },
+        ),
+    ]
+}
+
+/// Look up scenarios by name; an empty list selects the whole library.
+pub fn select(names: &[String]) -> Result<Vec<Scenario>> {
+    let all = library();
+    if names.is_empty() {
+        return Ok(all);
+    }
+    names
+        .iter()
+        .map(|name| match all.iter().find(|s| s.name == name) {
+            Some(s) => Ok(s.clone()),
+            None => {
+                let known: Vec<&str> = all.iter().map(|s| s.name).collect();
+                bail!("unknown scenario '{}'. available: {}", name, known.join(", "))
+            }
+        })
+        .collect()
+}
+
+/// What a strategy is expected to do in a scenario.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expectation {
+    /// No actions at all.
+    None,
+    /// At least one action of any kind.
+    Any,
+    /// At least one bid, optionally on a given side.
+    Bid(Option<Side>),
+    /// At least one cancel, optionally on a given side.
+    Cancel(Option<Side>),
+}
+
+impl Expectation {
+    /// Parse `none`, `any`, `bid`, `bid:yes`, `cancel:no`, ...
+    pub fn parse(raw: &str) -> Result<Self> {
+        let raw = raw.trim().to_ascii_lowercase();
+        let (kind, side) = match raw.split_once(':') {
+            Some((kind, side)) => (kind, Some(side)),
+            None => (raw.as_str(), None),
+        };
+        let side = match side {
+            None => None,
+            Some("yes") => Some(Side::Yes),
+            Some("no") => Some(Side::No),
+            Some(other) => bail!("unknown side '{}' in expectation (use yes or no)", other),
+        };
+        match (kind, side) {
+            ("none", None) => Ok(Self::None),
+            ("any", None) => Ok(Self::Any),
+            ("bid", side) => Ok(Self::Bid(side)),
+            ("cancel", side) => Ok(Self::Cancel(side)),
+            _ => bail!(
+                "invalid expectation '{}' (use none, any, bid[:side] or cancel[:side])",
+                raw
+            ),
+        }
+    }
+
+    fn is_met(&self, actions: &[(OffsetMs, Action)]) -> bool {
+        let side_matches = |want: &Option<Side>, got: &Side| want.is_none_or(|w| w == *got);
+        match self {
+            Self::None => actions.is_empty(),
+            Self::Any => !actions.is_empty(),
+            Self::Bid(want) => actions
+                .iter()
+                .any(|(_, a)| matches!(a, Action::PlaceBid { side, .. } if side_matches(want, side))),
+            Self::Cancel(want) => actions
+                .iter()
+                .any(|(_, a)| matches!(a, Action::Cancel { side } if side_matches(want, side))),
+        }
+    }
+}
+
+impl std::fmt::Display for Expectation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let with_side = |kind: &str, side: &Option<Side>| match side {
+            Some(s) => format!("{}:{}", kind, s.label().to_ascii_lowercase()),
+            None => kind.to_string(),
+        };
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Any => write!(f, "any"),
+            Self::Bid(side) => write!(f, "{}", with_side("bid", side)),
+            Self::Cancel(side) => write!(f, "{}", with_side("cancel", side)),
+        }
+    }
+}
+
+/// Parse a `SCENARIO=EXPECTATION` flag, checking the scenario exists.
+pub fn parse_expect(raw: &str) -> Result<(String, Expectation)> {
+    let Some((name, expectation)) = raw.split_once('=') else {
+        bail!("expectation `{}` must be SCENARIO=EXPECTATION", raw);
+    };
+    let name = name.trim();
+    if !library().iter().any(|s| s.name == name) {
+        bail!("unknown scenario '{}' in expectation `{}`", name, raw);
+    }
+    Ok((name.to_string(), Expectation::parse(expectation)?))
+}
+
+/// A strategy's behaviour in one scenario.
+#[derive(Debug)]
+pub struct ScenarioRun {
+    pub scenario: &'static str,
+    /// Every action returned, with the offset (ms) of the tick that emitted it.
+    pub actions: Vec<(OffsetMs, Action)>,
+    pub result: Option<WindowResult>,
+    /// Invariant violations and unmet expectations; empty means passed.
+    pub failures: Vec<String>,
+}
+
+impl ScenarioRun {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Replay `scenario` through `strategy` with a seeded DeLise fill model and
+/// check the recorded actions against `expectations`.
+pub fn run_scenario(
+    scenario: &Scenario,
+    strategy: &mut dyn Strategy,
+    config: ReplayConfig,
+    seed: u64,
+    expectations: &[Expectation],
+) -> ScenarioRun {
+    let max_shares = config.max_shares;
+    let engine = ReplayEngine::new(
+        Box::new(DeLiseFillModel::new(DeLiseConfig {
+            seed: Some(seed),
+            ..DeLiseConfig::default()
+        })),
+        config,
+    )
+    .with_seed(seed);
+    let mut actions = Vec::new();
+    let mut recorder = Recorder::new(strategy, |snap, list| {
+        actions.extend(list.iter().map(|a| (snap.offset_ms, a.clone())));
+    });
+    let result = engine.run_window(&scenario.market, &scenario.snapshots, &mut recorder);
+
+    let mut failures = Vec::new();
+    if let Some(reason) = result.as_ref().and_then(|r| r.aborted.as_deref()) {
+        failures.push(format!("aborted: {}", reason));
+    }
+    if let Some(errors) = result.as_ref().map(|r| r.script_errors).filter(|&n| n > 0) {
+        failures.push(format!("{} script error(s)", errors));
+    }
+    let mut rules = VenueRules::for_platform(&scenario.market.platform);
+    rules.max_shares = max_shares.map(Shares::from_f64);
+    for (offset_ms, action) in &actions {
+        if let Action::PlaceBid { price, shares, .. } = action {
+            if let Some(reason) = rules.rejection(*price, *shares) {
+                failures.push(format!("bid rejected at {}ms: {}", offset_ms, reason));
+            }
+        }
+    }
+    for expectation in expectations {
+        if !expectation.is_met(&actions) {
+            failures.push(format!("expected {}", expectation));
+        }
+    }
+
+    ScenarioRun {
+        scenario: scenario.name,
+        actions,
+        result,
+        failures,
+    }
+}
+
+/// Short human-readable list of actions, e.g. `bid YES 0.49x10 @12s`.
+pub fn summarize_actions(actions: &[(OffsetMs, Action)], max: usize) -> String {
+    if actions.is_empty() {
+        return "no actions".to_string();
+    }
+    let mut parts: Vec<String> = actions
+        .iter()
+        .take(max)
+        .map(|(offset_ms, action)| match action {
+            Action::PlaceBid {
+                side,
+                price,
+                shares,
+            } => format!("bid {} {}x{} @{}s", side, price, shares, offset_ms.ms() / 1000),
+            Action::Cancel { side } => format!("cancel {} @{}s", side, offset_ms.ms() / 1000),
+            Action::TakeAsk {
+                side,
+                shares,
+                max_price,
+            } => format!("take {} <={}x{} @{}s", side, max_price, shares, offset_ms.ms() / 1000),
+            Action::Amend {
+                side,
+                new_price,
+                new_shares,
+            } => format!("amend {} {}x{} @{}s", side, new_price, new_shares, offset_ms.ms() / 1000),
+            Action::Sell { side, price, shares } => {
+                format!("sell {} {}x{} @{}s", side, price, shares, offset_ms.ms() / 1000)
+            }
+        })
+        .collect();
+    if actions.len() > max {
+        parts.push(format!("+{} more", actions.len() - max));
+    }
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::{create_strategy, StrategyParams};
+
+    #[test]
+    fn test_library_shapes() {
+        let scenarios = library();
+        assert_eq!(scenarios.len(), 5);
+        for s in &scenarios {
+            assert_eq!(s.snapshots.len(), DURATION_SECS as usize);
+            assert!(s.snapshots.windows(2).all(|w| w[0].offset_ms < w[1].offset_ms));
+        }
+        let empty = scenarios.iter().find(|s| s.name == "empty_book").unwrap();
+        assert!(empty
+            .snapshots
+            .iter()
+            .all(|s| s.yes.best_bid.is_none() && s.no.depth.is_empty()));
+        let up = scenarios.iter().find(|s| s.name == "up_move").unwrap();
+        assert!(up.snapshots.last().unwrap().yes.best_bid.unwrap() > 0.85);
+    }
+
+    #[test]
+    fn test_expectation_parse() {
+        assert_eq!(Expectation::parse("none").unwrap(), Expectation::None);
+        assert_eq!(Expectation::parse("BID:yes").unwrap(), Expectation::Bid(Some(Side::Yes)));
+        assert_eq!(Expectation::parse("cancel").unwrap(), Expectation::Cancel(None));
+        assert!(Expectation::parse("bid:maybe").is_err());
+        assert!(Expectation::parse("none:yes").is_err());
+        assert_eq!(Expectation::parse("cancel:no").unwrap().to_string(), "cancel:no");
+
+        let (name, exp) = parse_expect("up_move=bid:yes").unwrap();
+        assert_eq!((name.as_str(), exp), ("up_move", Expectation::Bid(Some(Side::Yes))));
+        assert!(parse_expect("sideways=none").is_err());
+        assert!(parse_expect("flat").is_err());
+    }
+
+    #[test]
+    fn test_run_scenario_records_and_checks() {
+        let scenarios = library();
+        let flat = &scenarios[0];
+        let mut strategy = create_strategy("spread_arb", &StrategyParams::default()).unwrap();
+
+        let run = run_scenario(
+            flat,
+            strategy.as_mut(),
+            ReplayConfig::default(),
+            7,
+            &[Expectation::Bid(Some(Side::Yes)), Expectation::Bid(Some(Side::No))],
+        );
+        assert!(run.passed(), "{:?}", run.failures);
+        assert!(run.actions.len() >= 2);
+        assert!(run.result.is_some());
+
+        let config = ReplayConfig::default();
+        let run = run_scenario(flat, strategy.as_mut(), config, 7, &[Expectation::None]);
+        assert_eq!(run.failures, vec!["expected none".to_string()]);
+    }
+}
@@ -0,0 +1,543 @@
+//! Shared backtest server (`server` feature): `pf serve`.
+//!
+//! A small JSON-over-HTTP API in front of one native database, so a team can
+//! share the machine that holds it. Submitted runs go into a FIFO queue and
+//! a single worker executes them one at a time, so runs never compete for
+//! the disk or skew each other's timings. A run that panics is marked
+//! failed and the worker moves on. Only the newest finished jobs (100 by
+//! default) are kept; older ones are dropped along with their results.
+//!
+//! | Method | Path                      | Description                                  |
+//! |--------|---------------------------|----------------------------------------------|
+//! | POST   | `/runs`                   | Submit a [`BacktestConfig`]; returns the job |
+//! | GET    | `/runs`                   | All jobs, newest first                       |
+//! | GET    | `/runs/{id}`              | One job's status and progress                |
+//! | GET    | `/runs/{id}/results`      | Report, per-window results and summary       |
+//! | GET    | `/datasets`               | Markets in the database, grouped             |
+//! | GET    | `/markets/{id}/snapshots` | A market's book as a [`SnapshotSet`]         |
+
+use std::collections::{BTreeMap, VecDeque};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::backtest::{BacktestConfig, RunOutput};
+use crate::data::snapshots::SnapshotSet;
+use crate::data::{ticks_to_snapshots, DataStore, MarketFilter, SqliteStore};
+use crate::strategies::is_known_strategy;
+use crate::types::{Platform, UnixSecs};
+
+/// Lifecycle of a submitted run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+/// Public view of a job, as returned by the API.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: u64,
+    pub status: JobStatus,
+    /// Strategy name, or the script path/`"script"` for scripted runs.
+    pub strategy: String,
+    pub runs_total: usize,
+    pub runs_done: usize,
+    /// Unix seconds.
+    pub submitted_at: i64,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+    pub error: Option<String>,
+}
+
+struct Job {
+    info: JobInfo,
+    config: BacktestConfig,
+    output: Option<Arc<RunOutput>>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    jobs: Vec<Job>,
+    pending: VecDeque<u64>,
+    next_id: u64,
+}
+
+/// Finished jobs a [`JobQueue`] keeps by default.
+pub const MAX_FINISHED_JOBS: usize = 100;
+
+/// FIFO queue of submitted runs, shared between the HTTP thread and the
+/// worker.
+pub struct JobQueue {
+    state: Mutex<QueueState>,
+    wake: Condvar,
+    max_finished: usize,
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self {
+            state: Mutex::default(),
+            wake: Condvar::new(),
+            max_finished: MAX_FINISHED_JOBS,
+        }
+    }
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep at most `n` finished jobs; the oldest are evicted first.
+    pub fn with_max_finished(mut self, n: usize) -> Self {
+        self.max_finished = n;
+        self
+    }
+
+    /// Queue a run and return its job id.
+    pub fn submit(&self, config: BacktestConfig) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let id = state.next_id;
+        let strategy = match (&config.script_source, &config.script) {
+            (Some(_), _) => "script".to_string(),
+            (None, Some(path)) => path.display().to_string(),
+            (None, None) => config.strategy.clone(),
+        };
+        state.jobs.push(Job {
+            info: JobInfo {
+                id,
+                status: JobStatus::Queued,
+                strategy,
+                runs_total: config.runs,
+                runs_done: 0,
+                submitted_at: now(),
+                started_at: None,
+                finished_at: None,
+                error: None,
+            },
+            config,
+            output: None,
+        });
+        state.pending.push_back(id);
+        self.wake.notify_one();
+        id
+    }
+
+    pub fn info(&self, id: u64) -> Option<JobInfo> {
+        self.with_job(id, |job| job.info.clone())
+    }
+
+    /// All jobs, newest first.
+    pub fn list(&self) -> Vec<JobInfo> {
+        let state = self.state.lock().unwrap();
+        state.jobs.iter().rev().map(|j| j.info.clone()).collect()
+    }
+
+    /// Output of a finished job.
+    pub fn output(&self, id: u64) -> Option<Arc<RunOutput>> {
+        self.with_job(id, |job| job.output.clone()).flatten()
+    }
+
+    /// Block until a job is queued, run it against `store`, and record the
+    /// outcome. Returns the job id.
+    pub fn run_next(&self, store: &dyn DataStore) -> u64 {
+        let (id, config) = {
+            let mut state = self.state.lock().unwrap();
+            let id = loop {
+                match state.pending.pop_front() {
+                    Some(id) => break id,
+                    None => state = self.wake.wait(state).unwrap(),
+                }
+            };
+            let job = state.jobs.iter_mut().find(|j| j.info.id == id).unwrap();
+            job.info.status = JobStatus::Running;
+            job.info.started_at = Some(now());
+            (id, job.config.clone())
+        };
+        info!(job = id, "run started");
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            config.run_with(store, |i, _| {
+                self.with_job(id, |job| job.info.runs_done = i + 1);
+                Ok(())
+            })
+        }))
+        .unwrap_or_else(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            Err(anyhow!("run panicked: {}", message))
+        });
+
+        self.with_job(id, |job| {
+            job.info.finished_at = Some(now());
+            match result {
+                Ok(output) => {
+                    job.info.status = JobStatus::Done;
+                    job.output = Some(Arc::new(output));
+                }
+                Err(err) => {
+                    warn!(job = id, "run failed: {:#}", err);
+                    job.info.status = JobStatus::Failed;
+                    job.info.error = Some(format!("{:#}", err));
+                }
+            }
+        });
+        self.evict_finished();
+        info!(job = id, "run finished");
+        id
+    }
+
+    /// Drop the oldest finished jobs beyond `max_finished`.
+    fn evict_finished(&self) {
+        let mut state = self.state.lock().unwrap();
+        let finished = |job: &Job| matches!(job.info.status, JobStatus::Done | JobStatus::Failed);
+        let mut excess = state
+            .jobs
+            .iter()
+            .filter(|j| finished(j))
+            .count()
+            .saturating_sub(self.max_finished);
+        state.jobs.retain(|job| {
+            if excess > 0 && finished(job) {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+
+    fn with_job<T>(&self, id: u64, f: impl FnOnce(&mut Job) -> T) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        state.jobs.iter_mut().find(|j| j.info.id == id).map(f)
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// Markets sharing a platform, category and window length.
+#[derive(Debug, Serialize)]
+pub struct Dataset {
+    pub platform: Platform,
+    pub category: String,
+    pub duration_secs: i64,
+    pub markets: usize,
+    /// Markets with a known outcome.
+    pub resolved: usize,
+    pub first_open_ts: UnixSecs,
+    pub last_close_ts: UnixSecs,
+}
+
+/// Group the store's markets into [`Dataset`]s.
+pub fn list_datasets(store: &dyn DataStore) -> Result<Vec<Dataset>> {
+    let mut groups: BTreeMap<(String, String, i64), Dataset> = BTreeMap::new();
+    for m in store.list_markets(&MarketFilter::default())? {
+        let key = (m.platform.to_string(), m.category.clone(), m.duration_secs);
+        let group = groups.entry(key).or_insert_with(|| Dataset {
+            platform: m.platform,
+            category: m.category.clone(),
+            duration_secs: m.duration_secs,
+            markets: 0,
+            resolved: 0,
+            first_open_ts: m.open_ts,
+            last_close_ts: m.close_ts,
+        });
+        group.markets += 1;
+        group.resolved += m.outcome.is_some() as usize;
+        group.first_open_ts = group.first_open_ts.min(m.open_ts);
+        group.last_close_ts = group.last_close_ts.max(m.close_ts);
+    }
+    Ok(groups.into_values().collect())
+}
+
+/// Handle one API request. Returns the HTTP status and JSON body.
+pub fn route(
+    queue: &JobQueue,
+    store: &dyn DataStore,
+    method: &str,
+    path: &str,
+    body: &str,
+) -> (u16, Value) {
+    let path = path.split('?').next().unwrap_or("").trim_end_matches('/');
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let error = |status: u16, message: String| (status, json!({ "error": message }));
+
+    match (method, segments.as_slice()) {
+        ("POST", ["runs"]) => {
+            let body = if body.trim().is_empty() { "{}" } else { body };
+            let config: BacktestConfig = match serde_json::from_str(body) {
+                Ok(config) => config,
+                Err(e) => return error(400, format!("invalid run spec: {}", e)),
+            };
+            if config.script.is_none()
+                && config.script_source.is_none()
+                && !is_known_strategy(&config.strategy)
+            {
+                return error(400, format!("unknown strategy `{}`", config.strategy));
+            }
+            if config.runs == 0 {
+                return error(400, "runs must be at least 1".to_string());
+            }
+            let id = queue.submit(config);
+            (202, json!(queue.info(id)))
+        }
+        ("GET", ["runs"]) => (200, json!(queue.list())),
+        ("GET", ["runs", id]) => match id.parse().ok().and_then(|id| queue.info(id)) {
+            Some(info) => (200, json!(info)),
+            None => error(404, format!("no run {}", id)),
+        },
+        ("GET", ["runs", id, "results"]) => {
+            let Some(info) = id.parse().ok().and_then(|id| queue.info(id)) else {
+                return error(404, format!("no run {}", id));
+            };
+            match (info.status, queue.output(info.id)) {
+                (JobStatus::Done, Some(output)) => (200, json!(*output)),
+                (JobStatus::Failed, _) => error(409, info.error.unwrap_or_default()),
+                (JobStatus::Queued, _) => error(409, format!("run {} is still queued", id)),
+                _ => error(409, format!("run {} is still running", id)),
+            }
+        }
+        ("GET", ["datasets"]) => match list_datasets(store) {
+            Ok(datasets) => (200, json!(datasets)),
+            Err(e) => error(500, format!("{:#}", e)),
+        },
+        ("GET", ["markets", id, "snapshots"]) => match store.load_ticks(id) {
+            Ok(ticks) if ticks.is_empty() => error(404, format!("no snapshots for market {}", id)),
+            Ok(ticks) => (200, json!(SnapshotSet::new(*id, ticks_to_snapshots(id, &ticks)))),
+            Err(e) => error(500, format!("{:#}", e)),
+        },
+        (_, ["runs", ..]) | (_, ["datasets"]) | (_, ["markets", _, "snapshots"]) => {
+            error(405, format!("{} not allowed on {}", method, path))
+        }
+        _ => error(404, format!("no route for {}", path)),
+    }
+}
+
+/// Serve the API for the native database at `db` on `addr` until the
+/// process is stopped.
+pub fn serve(db: &Path, addr: &str) -> Result<()> {
+    // Fail fast on a bad path before binding the port.
+    let store = SqliteStore::open(db).with_context(|| format!("failed to open {}", db.display()))?;
+    let queue = Arc::new(JobQueue::new());
+
+    let worker_queue = Arc::clone(&queue);
+    let worker_db = db.to_path_buf();
+    std::thread::Builder::new()
+        .name("pf-worker".into())
+        .spawn(move || -> Result<()> {
+            let store = SqliteStore::open(&worker_db)?;
+            loop {
+                worker_queue.run_next(&store);
+            }
+        })?;
+
+    let server = tiny_http::Server::http(addr)
+        .map_err(|e| anyhow::anyhow!("failed to listen on {}: {}", addr, e))?;
+    info!("serving {} on http://{}", db.display(), addr);
+
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        let (status, value) = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => route(
+                &queue,
+                &store,
+                request.method().as_str(),
+                request.url(),
+                &body,
+            ),
+            Err(e) => (400, json!({ "error": format!("unreadable body: {}", e) })),
+        };
+        let response = tiny_http::Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(
+                tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                    .expect("static header is valid"),
+            );
+        if let Err(e) = request.respond(response) {
+            warn!("failed to send response: {}", e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bench::{generate_dataset, BenchConfig};
+    use crate::data::{ResultFilter, RunMeta, StoredRun};
+    use crate::types::{BookTick, Market, WindowResult};
+
+    fn seeded_store() -> SqliteStore {
+        let store = SqliteStore::in_memory().unwrap();
+        store.init().unwrap();
+        let config = BenchConfig {
+            markets: 4,
+            snapshots_per_market: 20,
+            seed: 2,
+        };
+        for (market, ticks) in generate_dataset(&config) {
+            store.insert_market(&market).unwrap();
+            store.insert_ticks(&ticks).unwrap();
+        }
+        store
+    }
+
+    #[test]
+    fn test_submit_run_and_fetch_results() {
+        let store = seeded_store();
+        let queue = JobQueue::new();
+
+        let spec = r#"{"strategy": "spread_arb", "runs": 2, "seed": 1}"#;
+        let (status, job) = route(&queue, &store, "POST", "/runs", spec);
+        assert_eq!(status, 202);
+        assert_eq!(job["status"], "queued");
+        assert_eq!(job["runs_total"], 2);
+        let id = job["id"].as_u64().unwrap();
+
+        let (status, _) = route(&queue, &store, "GET", &format!("/runs/{}/results", id), "");
+        assert_eq!(status, 409);
+
+        assert_eq!(queue.run_next(&store), id);
+        let (status, job) = route(&queue, &store, "GET", &format!("/runs/{}", id), "");
+        assert_eq!(status, 200);
+        assert_eq!(job["status"], "done");
+        assert_eq!(job["runs_done"], 2);
+
+        let (status, output) = route(&queue, &store, "GET", &format!("/runs/{}/results/", id), "");
+        assert_eq!(status, 200);
+        assert_eq!(output["results"].as_array().unwrap().len(), 4);
+        assert_eq!(output["summary"]["runs"], 2);
+
+        let (_, jobs) = route(&queue, &store, "GET", "/runs", "");
+        assert_eq!(jobs.as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_failures_and_bad_requests() {
+        let store = seeded_store();
+        let queue = JobQueue::new();
+
+        assert_eq!(route(&queue, &store, "POST", "/runs", "{oops").0, 400);
+        assert_eq!(route(&queue, &store, "POST", "/runs", r#"{"strategy": "nope"}"#).0, 400);
+        assert_eq!(route(&queue, &store, "GET", "/runs/99", "").0, 404);
+        assert_eq!(route(&queue, &store, "DELETE", "/runs/1", "").0, 405);
+        assert_eq!(route(&queue, &store, "GET", "/nowhere", "").0, 404);
+
+        let (_, job) = route(&queue, &store, "POST", "/runs", r#"{"script": "/missing.rhai"}"#);
+        let id = job["id"].as_u64().unwrap();
+        queue.run_next(&store);
+        let info = queue.info(id).unwrap();
+        assert_eq!(info.status, JobStatus::Failed);
+        let (status, body) = route(&queue, &store, "GET", &format!("/runs/{}/results", id), "");
+        assert_eq!(status, 409);
+        assert!(body["error"].as_str().unwrap().contains("failed to read script"));
+    }
+
+    /// Delegates to a seeded store but panics when asked for ticks.
+    struct PanickingStore(SqliteStore);
+
+    impl DataStore for PanickingStore {
+        fn init(&self) -> Result<()> {
+            self.0.init()
+        }
+        fn insert_market(&self, market: &Market) -> Result<()> {
+            self.0.insert_market(market)
+        }
+        fn insert_ticks(&self, ticks: &[BookTick]) -> Result<usize> {
+            self.0.insert_ticks(ticks)
+        }
+        fn list_markets(&self, filter: &MarketFilter) -> Result<Vec<Market>> {
+            self.0.list_markets(filter)
+        }
+        fn load_ticks(&self, _market_id: &str) -> Result<Vec<BookTick>> {
+            panic!("disk on fire")
+        }
+        fn insert_results(&self, meta: &RunMeta, results: &[WindowResult]) -> Result<i64> {
+            self.0.insert_results(meta, results)
+        }
+        fn list_runs(&self) -> Result<Vec<StoredRun>> {
+            self.0.list_runs()
+        }
+        fn load_results(&self, run_id: i64, filter: &ResultFilter) -> Result<Vec<WindowResult>> {
+            self.0.load_results(run_id, filter)
+        }
+    }
+
+    #[test]
+    fn test_panicking_run_fails_and_queue_moves_on() {
+        let store = seeded_store();
+        let broken = PanickingStore(seeded_store());
+        let queue = JobQueue::new();
+
+        let first = queue.submit(BacktestConfig::default());
+        let second = queue.submit(BacktestConfig::default());
+        assert_eq!(queue.run_next(&broken), first);
+        let info = queue.info(first).unwrap();
+        assert_eq!(info.status, JobStatus::Failed);
+        assert!(info.error.unwrap().contains("run panicked: disk on fire"));
+
+        assert_eq!(queue.run_next(&store), second);
+        assert_eq!(queue.info(second).unwrap().status, JobStatus::Done);
+    }
+
+    #[test]
+    fn test_finished_jobs_are_evicted_oldest_first() {
+        let store = seeded_store();
+        let queue = JobQueue::new().with_max_finished(1);
+
+        let first = queue.submit(BacktestConfig::default());
+        let second = queue.submit(BacktestConfig::default());
+        let queued = queue.submit(BacktestConfig::default());
+        queue.run_next(&store);
+        assert!(queue.info(first).is_some());
+        queue.run_next(&store);
+        assert_eq!(route(&queue, &store, "GET", &format!("/runs/{}", first), "").0, 404);
+        assert!(queue.output(second).is_some());
+        assert_eq!(queue.info(queued).unwrap().status, JobStatus::Queued);
+        assert_eq!(queue.list().len(), 2);
+    }
+
+    #[test]
+    fn test_list_datasets_groups_markets() {
+        let store = seeded_store();
+        let (status, datasets) = route(&JobQueue::new(), &store, "GET", "/datasets", "");
+        assert_eq!(status, 200);
+        let total: u64 = datasets
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| d["markets"].as_u64().unwrap())
+            .sum();
+        assert_eq!(total, 4);
+    }
+
+    #[test]
+    fn test_market_snapshots_use_wire_schema() {
+        let store = seeded_store();
+        let queue = JobQueue::new();
+        let id = store.list_markets(&MarketFilter::default()).unwrap()[0].id.clone();
+
+        let (status, body) = route(&queue, &store, "GET", &format!("/markets/{}/snapshots", id), "");
+        assert_eq!(status, 200);
+        let set = SnapshotSet::from_json(&body.to_string()).unwrap();
+        assert_eq!(set.market_id, id);
+        assert_eq!(set.snapshots.len(), 20);
+
+        assert_eq!(route(&queue, &store, "GET", "/markets/missing/snapshots", "").0, 404);
+        assert_eq!(route(&queue, &store, "POST", &format!("/markets/{}/snapshots", id), "").0, 405);
+    }
+}
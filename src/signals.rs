@@ -0,0 +1,436 @@
+//! Signal-only evaluation (`pf signals`).
+//!
+//! A backtest answers two questions at once: is the strategy's call right,
+//! and could it have been filled at its price. A good signal behind a
+//! queue it never reaches looks like a bad strategy. [`evaluate`] replays
+//! windows under [`EvaluationMode::SignalOnly`], where nothing fills, and
+//! records per window the side the strategy called, when, and how strong
+//! its signal was
+//! ([`signal_strength`](crate::strategies::Strategy::signal_strength)).
+//! [`SignalReport`] scores the calls overall and by strength bucket, next
+//! to the probability the market put on the called side at the time, so a
+//! bucket that is right 60% of the time on sides the book priced at 55c
+//! shows an edge and one priced at 65c doesn't.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::fill::{DeLiseConfig, DeLiseFillModel, FillModel};
+use crate::replay::{EvaluationMode, ReplayConfig, ReplayEngine};
+use crate::strategies::StrategyFactory;
+use crate::types::{BookSnapshot, Market, Outcome, Side};
+
+/// What a strategy called in one window.
+#[derive(Debug, Clone)]
+pub struct WindowSignal {
+    pub market_id: String,
+    pub outcome: Outcome,
+    /// The one side the strategy backed at the close, if any.
+    pub predicted: Option<Side>,
+    /// When it settled on that side, in ms from the open.
+    pub signal_offset_ms: Option<i64>,
+    pub strength: Option<f64>,
+    /// Mid price of the called side at the signal: the market's own
+    /// probability that the call is right.
+    pub implied: Option<f64>,
+}
+
+impl WindowSignal {
+    /// Whether the call matched the outcome; `None` without a call.
+    pub fn correct(&self) -> Option<bool> {
+        Some(self.outcome.matches_side(self.predicted?))
+    }
+}
+
+/// Replay each resolved market in `markets` with a fresh strategy from
+/// `factory`, filling nothing. `config` is used as given apart from its
+/// mode; latency still delays when a call lands. Void, unresolved and
+/// empty markets are skipped.
+pub fn evaluate(
+    markets: &[Market],
+    snapshots: &dyn Fn(&str) -> Result<Vec<BookSnapshot>>,
+    factory: &dyn StrategyFactory,
+    config: ReplayConfig,
+) -> Result<Vec<WindowSignal>> {
+    // The fill model only prices orders here; none of them fill.
+    let engine = ReplayEngine::new(
+        Box::new(DeLiseFillModel::new(DeLiseConfig::default())) as Box<dyn FillModel>,
+        ReplayConfig {
+            mode: EvaluationMode::SignalOnly,
+            ..config
+        },
+    );
+
+    let mut signals = Vec::with_capacity(markets.len());
+    for market in markets {
+        let Some(outcome) = market.outcome.filter(Outcome::is_settled) else {
+            continue;
+        };
+        let snaps = snapshots(&market.id)?;
+        if snaps.is_empty() {
+            continue;
+        }
+        let mut strategy = factory.build();
+        let mut state = engine.begin_window(market, strategy.as_mut());
+        for snap in &snaps {
+            engine.feed(&mut state, snap, strategy.as_mut());
+        }
+        let result = engine.finish_window(market, state, outcome, strategy.as_mut());
+
+        let predicted = [Side::Yes, Side::No]
+            .into_iter()
+            .find(|s| result.predicted.as_deref() == Some(s.label()));
+        let signal_offset_ms = predicted.and(result.signal_offset_ms);
+        let implied = match (predicted, signal_offset_ms) {
+            (Some(side), Some(at)) => snaps
+                .iter()
+                .take_while(|s| s.offset_ms.ms() <= at)
+                .last()
+                .and_then(|snap| snap.implied_prob(side)),
+            _ => None,
+        };
+        signals.push(WindowSignal {
+            market_id: market.id.clone(),
+            outcome,
+            predicted,
+            signal_offset_ms,
+            strength: predicted.and(strategy.signal_strength()),
+            implied,
+        });
+    }
+    Ok(signals)
+}
+
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    market_id: &'a str,
+    outcome: &'a str,
+    predicted: Option<&'a str>,
+    correct: Option<bool>,
+    signal_offset_ms: Option<i64>,
+    strength: Option<f64>,
+    implied: Option<f64>,
+}
+
+/// Write `signals` to a CSV file, one row per window.
+pub fn export_csv(signals: &[WindowSignal], path: &Path) -> Result<()> {
+    let mut wtr =
+        csv::Writer::from_path(path).with_context(|| format!("failed to create CSV at {}", path.display()))?;
+    for s in signals {
+        wtr.serialize(CsvRow {
+            market_id: &s.market_id,
+            outcome: s.outcome.label(),
+            predicted: s.predicted.as_ref().map(Side::label),
+            correct: s.correct(),
+            signal_offset_ms: s.signal_offset_ms,
+            strength: s.strength,
+            implied: s.implied,
+        })
+        .with_context(|| format!("failed to write CSV row for {}", s.market_id))?;
+    }
+    wtr.flush().context("failed to flush CSV")?;
+    Ok(())
+}
+
+/// Calls, and how many were right, over some group of windows.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallStats {
+    pub calls: usize,
+    pub correct: usize,
+    /// Mean [`WindowSignal::implied`] over the calls that have one.
+    pub implied: Option<f64>,
+}
+
+impl CallStats {
+    fn new<'a>(signals: impl IntoIterator<Item = &'a WindowSignal>) -> Self {
+        let (mut calls, mut correct, mut implied, mut priced) = (0, 0, 0.0, 0);
+        for signal in signals {
+            let Some(right) = signal.correct() else {
+                continue;
+            };
+            calls += 1;
+            correct += usize::from(right);
+            if let Some(p) = signal.implied {
+                implied += p;
+                priced += 1;
+            }
+        }
+        Self {
+            calls,
+            correct,
+            implied: (priced > 0).then(|| implied / priced as f64),
+        }
+    }
+
+    /// Share of calls that were right; 0 without calls.
+    pub fn accuracy(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.correct as f64 / self.calls as f64
+        }
+    }
+}
+
+/// Calls whose strength falls in `min..=max`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrengthBucket {
+    pub min: f64,
+    pub max: f64,
+    pub stats: CallStats,
+}
+
+/// Accuracy of a strategy's calls; see the [module docs](self).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignalReport {
+    /// Resolved windows evaluated.
+    pub windows: usize,
+    pub overall: CallStats,
+    /// Equal-count buckets of the calls with a strength, weakest first.
+    pub buckets: Vec<StrengthBucket>,
+    /// Calls the strategy didn't rate.
+    pub unrated: CallStats,
+    /// Median signal time of the calls, in ms from the open.
+    pub median_signal_ms: Option<i64>,
+}
+
+impl SignalReport {
+    /// Score `signals`, splitting the rated calls into up to `buckets`
+    /// groups of (nearly) equal size by strength.
+    pub fn new(signals: &[WindowSignal], buckets: usize) -> Self {
+        let calls: Vec<&WindowSignal> = signals.iter().filter(|s| s.predicted.is_some()).collect();
+        let mut rated: Vec<&WindowSignal> = calls.iter().copied().filter(|s| s.strength.is_some()).collect();
+        rated.sort_by(|a, b| a.strength.partial_cmp(&b.strength).unwrap_or(std::cmp::Ordering::Equal));
+
+        let n = buckets.clamp(1, rated.len().max(1));
+        let strength = |s: &WindowSignal| s.strength.unwrap_or_default();
+        let buckets = (0..n)
+            .map(|i| &rated[i * rated.len() / n..(i + 1) * rated.len() / n])
+            .filter(|group| !group.is_empty())
+            .map(|group| StrengthBucket {
+                min: strength(group[0]),
+                max: strength(group[group.len() - 1]),
+                stats: CallStats::new(group.iter().copied()),
+            })
+            .collect();
+
+        let mut times: Vec<i64> = calls.iter().filter_map(|s| s.signal_offset_ms).collect();
+        times.sort_unstable();
+        Self {
+            windows: signals.len(),
+            overall: CallStats::new(calls.iter().copied()),
+            buckets,
+            unrated: CallStats::new(calls.iter().copied().filter(|s| s.strength.is_none())),
+            median_signal_ms: times.get(times.len() / 2).copied(),
+        }
+    }
+
+    /// Share of windows the strategy made a call in.
+    pub fn coverage(&self) -> f64 {
+        if self.windows == 0 {
+            0.0
+        } else {
+            self.overall.calls as f64 / self.windows as f64
+        }
+    }
+
+    pub fn print(&self) {
+        let pct = |p: f64| format!("{:.1}%", p * 100.0);
+        let implied = |s: &CallStats| s.implied.map_or_else(|| "-".to_string(), pct);
+        println!(
+            "  Windows:  {} ({} with a call, {} coverage)",
+            self.windows,
+            self.overall.calls,
+            pct(self.coverage())
+        );
+        if self.overall.calls == 0 {
+            return;
+        }
+        println!(
+            "  Accuracy: {} ({}/{}), market implied {}",
+            pct(self.overall.accuracy()),
+            self.overall.correct,
+            self.overall.calls,
+            implied(&self.overall)
+        );
+        if let Some(ms) = self.median_signal_ms {
+            println!("  Signal:   median {:.1}s after the open", ms as f64 / 1000.0);
+        }
+        if self.buckets.is_empty() {
+            println!("  (the strategy doesn't rate its signals)");
+            return;
+        }
+
+        println!();
+        println!("  By signal strength:");
+        println!("    {:>17} {:>6} {:>9} {:>8} {:>7}", "strength", "calls", "accuracy", "implied", "edge");
+        let row = |label: String, stats: &CallStats| {
+            let edge = stats
+                .implied
+                .map_or_else(|| "-".to_string(), |p| format!("{:+.1}", (stats.accuracy() - p) * 100.0));
+            println!(
+                "    {:>17} {:>6} {:>9} {:>8} {:>7}",
+                label,
+                stats.calls,
+                pct(stats.accuracy()),
+                implied(stats),
+                edge
+            );
+        };
+        for bucket in &self.buckets {
+            row(format!("{:.2}-{:.2}", bucket.min, bucket.max), &bucket.stats);
+        }
+        if self.unrated.calls > 0 {
+            row("unrated".to_string(), &self.unrated);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use crate::strategies::momentum::MomentumSignal;
+    use crate::strategies::post_cancel::PostBothCancelLoser;
+    use crate::strategies::{FnFactory, Strategy};
+    use crate::types::{OffsetMs, Platform, SideState, UnixSecs};
+
+    fn market(id: &str, outcome: Outcome) -> Market {
+        Market {
+            id: id.into(),
+            platform: Platform::Polymarket,
+            description: String::new(),
+            category: "btc".into(),
+            open_ts: UnixSecs::new(0),
+            close_ts: UnixSecs::new(300),
+            duration_secs: 300,
+            outcome: Some(outcome),
+            outcome_ts: None,
+        }
+    }
+
+    /// No asks and no bids at our price, so nothing could fill anyway.
+    fn snap(id: &str, offset_ms: i64, oracle: f64) -> BookSnapshot {
+        let side = Arc::new(SideState {
+            best_bid: Some(0.40),
+            best_bid_size: Some(100.0),
+            best_ask: Some(0.60),
+            best_ask_size: Some(100.0),
+            depth: Default::default(),
+            total_bid_depth: 100.0,
+            total_ask_depth: 100.0,
+        });
+        BookSnapshot {
+            market_id: id.into(),
+            offset_ms: OffsetMs::new(offset_ms),
+            timestamp_ms: offset_ms,
+            yes: side.clone(),
+            no: side,
+            reference_price: None,
+            oracle_price: Some(oracle),
+        }
+    }
+
+    fn signal(predicted: Option<Side>, outcome: Outcome, strength: Option<f64>) -> WindowSignal {
+        WindowSignal {
+            market_id: String::new(),
+            outcome,
+            predicted,
+            signal_offset_ms: predicted.map(|_| 1000),
+            strength,
+            implied: predicted.map(|_| 0.5),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_records_calls() {
+        let markets = vec![
+            market("up", Outcome::Yes),
+            market("down", Outcome::Yes),
+            market("flat", Outcome::No),
+            market("void", Outcome::Void),
+        ];
+        let snapshots = |id: &str| -> Result<Vec<BookSnapshot>> {
+            let end = match id {
+                "up" => 100_200.0,
+                "down" => 99_700.0,
+                _ => 100_001.0,
+            };
+            Ok(vec![snap(id, 0, 100_000.0), snap(id, 60_000, end), snap(id, 90_000, end)])
+        };
+        let factory = FnFactory::new(|| -> Box<dyn Strategy> { Box::new(PostBothCancelLoser::new(0.49, 10.0, 5.0, 60_000)) });
+        let signals = evaluate(&markets, &snapshots, &factory, ReplayConfig::default()).unwrap();
+
+        let calls: Vec<_> = signals
+            .iter()
+            .map(|s| (s.market_id.as_str(), s.predicted, s.signal_offset_ms, s.correct()))
+            .collect();
+        // Both bids go up at the open; the call is the cancel of the loser.
+        assert_eq!(
+            calls,
+            vec![
+                ("up", Some(Side::Yes), Some(60_000), Some(true)),
+                ("down", Some(Side::No), Some(60_000), Some(false)),
+                ("flat", None, None, None),
+            ]
+        );
+        assert!((signals[0].strength.unwrap() - 20.0).abs() < 1e-9);
+        assert_eq!(signals[0].implied, Some(0.5));
+        assert_eq!(signals[2].strength, None);
+    }
+
+    #[test]
+    fn test_nothing_fills() {
+        // A bid through the asks would fill at once under execution.
+        let snapshots = vec![snap("up", 0, 100_000.0), snap("up", 60_000, 100_200.0)];
+        let factory = FnFactory::new(|| -> Box<dyn Strategy> { Box::new(MomentumSignal::new(0.99, 10.0, 5.0, 60_000)) });
+        let engine = ReplayEngine::new(
+            Box::new(DeLiseFillModel::new(DeLiseConfig::default())) as Box<dyn FillModel>,
+            ReplayConfig {
+                mode: EvaluationMode::SignalOnly,
+                ..ReplayConfig::default()
+            },
+        );
+        let mut strategy = factory.build();
+        let result = engine
+            .run_window(&market("up", Outcome::Yes), &snapshots, strategy.as_mut())
+            .unwrap();
+        assert!(!result.filled);
+        assert_eq!(result.realistic_pnl, 0.0);
+        assert_eq!(result.predicted.as_deref(), Some("YES"));
+        assert!(result.correct);
+    }
+
+    #[test]
+    fn test_report_buckets() {
+        let signals = vec![
+            signal(Some(Side::Yes), Outcome::Yes, Some(30.0)),
+            signal(Some(Side::Yes), Outcome::No, Some(5.0)),
+            signal(Some(Side::No), Outcome::No, Some(20.0)),
+            signal(Some(Side::No), Outcome::Yes, Some(10.0)),
+            signal(Some(Side::Yes), Outcome::Yes, None),
+            signal(None, Outcome::Yes, None),
+        ];
+        let report = SignalReport::new(&signals, 2);
+        assert_eq!(report.windows, 6);
+        assert_eq!((report.overall.calls, report.overall.correct), (5, 3));
+        assert!((report.coverage() - 5.0 / 6.0).abs() < 1e-12);
+        assert_eq!(report.median_signal_ms, Some(1000));
+
+        let buckets: Vec<_> = report
+            .buckets
+            .iter()
+            .map(|b| (b.min, b.max, b.stats.calls, b.stats.correct))
+            .collect();
+        assert_eq!(buckets, vec![(5.0, 10.0, 2, 0), (20.0, 30.0, 2, 2)]);
+        assert_eq!((report.unrated.calls, report.unrated.correct), (1, 1));
+        assert_eq!(report.overall.implied, Some(0.5));
+
+        // More buckets than calls: one per call.
+        assert_eq!(SignalReport::new(&signals, 10).buckets.len(), 4);
+        assert!(SignalReport::new(&[], 5).buckets.is_empty());
+    }
+}
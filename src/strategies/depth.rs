@@ -1,5 +1,5 @@
 use crate::strategies::Strategy;
-use crate::types::{Action, BookSnapshot, Side};
+use crate::types::{Action, BookSnapshot, Price, Shares, Side};
 
 /// Depth + momentum strategy.
 ///
@@ -10,22 +10,25 @@ use crate::types::{Action, BookSnapshot, Side};
 ///
 /// Higher selectivity = fewer trades but (theoretically) higher accuracy.
 pub struct DepthMomentum {
-    bid_price: f64,
-    shares: f64,
+    bid_price: Price,
+    shares: Shares,
     min_bps: f64,
     signal_offset_ms: i64,
     open_oracle: Option<f64>,
+    /// |momentum| in bps behind the side this window backed.
+    signal_bps: Option<f64>,
     acted: bool,
 }
 
 impl DepthMomentum {
     pub fn new(bid_price: f64, shares: f64, min_bps: f64, signal_offset_ms: i64) -> Self {
         Self {
-            bid_price,
-            shares,
+            bid_price: Price::from_f64(bid_price),
+            shares: Shares::from_f64(shares),
             min_bps,
             signal_offset_ms,
             open_oracle: None,
+            signal_bps: None,
             acted: false,
         }
     }
@@ -69,8 +72,8 @@ impl Strategy for DepthMomentum {
 
         // Check depth agreement: the predicted winner side should have
         // more bid depth (more people betting on it)
-        let yes_depth = snap.yes.bid_depth_at(self.bid_price);
-        let no_depth = snap.no.bid_depth_at(self.bid_price);
+        let yes_depth = snap.yes.bid_depth_at(self.bid_price.to_f64());
+        let no_depth = snap.no.bid_depth_at(self.bid_price.to_f64());
 
         let depth_side = if yes_depth > no_depth {
             Side::Yes
@@ -84,6 +87,7 @@ impl Strategy for DepthMomentum {
         if momentum_side != depth_side {
             return vec![];
         }
+        self.signal_bps = Some(momentum_bps.abs());
 
         vec![Action::PlaceBid {
             side: momentum_side,
@@ -95,6 +99,11 @@ impl Strategy for DepthMomentum {
     fn reset(&mut self) {
         self.open_oracle = None;
         self.acted = false;
+        self.signal_bps = None;
+    }
+
+    fn signal_strength(&self) -> Option<f64> {
+        self.signal_bps
     }
 }
 
@@ -0,0 +1,310 @@
+//! Strategy factories.
+//!
+//! The engine builds a fresh strategy for every market window. A
+//! [`StrategyFactory`] does the building and also describes what it builds,
+//! so multi-run, parallel and sweep code can record (and serialize) which
+//! configuration produced which results via [`StrategySpec`].
+
+use std::collections::BTreeMap;
+#[cfg(feature = "scripting")]
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::strategies::{create_strategy, Strategy, StrategyParams, DEFAULT_SIGNAL_OFFSET_MS};
+
+/// Builds one strategy instance per market window.
+///
+/// Factories are shared across threads by parallel runs, so they must be
+/// `Send + Sync`; the concrete factories here are also `Clone`.
+pub trait StrategyFactory: Send + Sync {
+    /// Name of the strategies this factory builds.
+    fn name(&self) -> &str;
+
+    /// The configuration the strategies are built with.
+    fn params(&self) -> BTreeMap<String, Value>;
+
+    /// A fresh strategy for one window.
+    fn build(&self) -> Box<dyn Strategy>;
+
+    /// [`name`](Self::name) and [`params`](Self::params) as data.
+    fn spec(&self) -> StrategySpec {
+        StrategySpec {
+            name: self.name().to_string(),
+            params: self.params(),
+        }
+    }
+}
+
+impl<T: StrategyFactory + ?Sized> StrategyFactory for &T {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn params(&self) -> BTreeMap<String, Value> {
+        (**self).params()
+    }
+
+    fn build(&self) -> Box<dyn Strategy> {
+        (**self).build()
+    }
+}
+
+impl<T: StrategyFactory + ?Sized> StrategyFactory for Box<T> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn params(&self) -> BTreeMap<String, Value> {
+        (**self).params()
+    }
+
+    fn build(&self) -> Box<dyn Strategy> {
+        (**self).build()
+    }
+}
+
+impl<T: StrategyFactory + ?Sized> StrategyFactory for Arc<T> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn params(&self) -> BTreeMap<String, Value> {
+        (**self).params()
+    }
+
+    fn build(&self) -> Box<dyn Strategy> {
+        (**self).build()
+    }
+}
+
+/// Which strategy configuration produced a set of results.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategySpec {
+    pub name: String,
+    pub params: BTreeMap<String, Value>,
+}
+
+impl StrategySpec {
+    /// The `signal_offset_ms` param, for strategies that take one, or
+    /// [`DEFAULT_SIGNAL_OFFSET_MS`].
+    pub fn signal_offset_ms(&self) -> i64 {
+        self.params
+            .get("signal_offset_ms")
+            .and_then(Value::as_i64)
+            .unwrap_or(DEFAULT_SIGNAL_OFFSET_MS)
+    }
+}
+
+/// A built-in strategy by name (see [`create_strategy`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BuiltinFactory {
+    name: String,
+    #[serde(flatten)]
+    params: StrategyParams,
+}
+
+impl BuiltinFactory {
+    /// Fails for names [`create_strategy`] does not know.
+    pub fn new(name: &str, params: StrategyParams) -> Result<Self> {
+        create_strategy(name, &params).with_context(|| format!("unknown strategy `{}`", name))?;
+        Ok(Self {
+            name: name.to_string(),
+            params,
+        })
+    }
+}
+
+impl StrategyFactory for BuiltinFactory {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The params [`param_keys`](crate::strategies::param_keys) lists for
+    /// the strategy.
+    fn params(&self) -> BTreeMap<String, Value> {
+        self.params.to_map(&self.name)
+    }
+
+    fn build(&self) -> Box<dyn Strategy> {
+        create_strategy(&self.name, &self.params).expect("strategy name checked in new")
+    }
+}
+
+/// A Rhai script, compiled once up front. Every window starts a fresh
+/// instance from the compiled script, so no script state leaks between
+/// windows.
+#[cfg(feature = "scripting")]
+#[derive(Debug, Clone)]
+pub struct ScriptFactory {
+    name: String,
+    script: crate::strategies::scripted::CompiledScript,
+    path: Option<String>,
+    shares: f64,
+    bid_price: f64,
+    params: Vec<(String, rhai::Dynamic)>,
+}
+
+#[cfg(feature = "scripting")]
+impl ScriptFactory {
+    /// A script from source; `name` is used unless the script names itself.
+    /// `params` are `KEY=VALUE` constants (see
+    /// [`parse_script_param`](crate::strategies::scripted::parse_script_param)).
+    pub fn from_source(name: &str, source: &str, shares: f64, bid_price: f64, params: &[String]) -> Result<Self> {
+        let params = params
+            .iter()
+            .map(|p| crate::strategies::scripted::parse_script_param(p))
+            .collect::<Result<Vec<_>>>()?;
+        let script = crate::strategies::scripted::CompiledScript::new(name, source, shares, bid_price, &params)?;
+        let strategy = crate::strategies::scripted::RhaiStrategy::from_compiled(&script);
+        if let Some(error) = strategy.abort_reason() {
+            anyhow::bail!("{}", error);
+        }
+        Ok(Self {
+            name: strategy.name().to_string(),
+            script,
+            path: None,
+            shares,
+            bid_price,
+            params,
+        })
+    }
+
+    /// A `.rhai` file, read once.
+    pub fn from_file(path: &Path, shares: f64, bid_price: f64, params: &[String]) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read script: {}", path.display()))?;
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "unknown".to_string());
+        let mut factory = Self::from_source(&name, &source, shares, bid_price, params)
+            .with_context(|| format!("failed to load script: {}", path.display()))?;
+        factory.path = Some(path.display().to_string());
+        Ok(factory)
+    }
+}
+
+#[cfg(feature = "scripting")]
+impl StrategyFactory for ScriptFactory {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn params(&self) -> BTreeMap<String, Value> {
+        let mut params = BTreeMap::from([
+            ("bid_price".to_string(), self.bid_price.into()),
+            ("shares".to_string(), self.shares.into()),
+        ]);
+        if let Some(ref path) = self.path {
+            params.insert("script".to_string(), path.clone().into());
+        }
+        for (key, value) in &self.params {
+            let value = if let Ok(i) = value.as_int() {
+                i.into()
+            } else if let Ok(f) = value.as_float() {
+                f.into()
+            } else if let Ok(b) = value.as_bool() {
+                b.into()
+            } else {
+                value.to_string().into()
+            };
+            params.insert(key.clone(), value);
+        }
+        params
+    }
+
+    fn build(&self) -> Box<dyn Strategy> {
+        Box::new(crate::strategies::scripted::RhaiStrategy::from_compiled(&self.script))
+    }
+}
+
+/// Any closure returning a strategy, for strategies the other factories do
+/// not cover (plugins, Python, hand-built instances).
+#[derive(Clone)]
+pub struct FnFactory<F> {
+    name: String,
+    params: BTreeMap<String, Value>,
+    build: F,
+}
+
+impl<F: Fn() -> Box<dyn Strategy> + Send + Sync> FnFactory<F> {
+    /// Named after the strategy `build` returns (it is called once here).
+    pub fn new(build: F) -> Self {
+        let name = build().name().to_string();
+        Self {
+            name,
+            params: BTreeMap::new(),
+            build,
+        }
+    }
+
+    /// Record `params` as the configuration behind `build`.
+    pub fn with_params(mut self, params: BTreeMap<String, Value>) -> Self {
+        self.params = params;
+        self
+    }
+}
+
+impl<F> std::fmt::Debug for FnFactory<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FnFactory")
+            .field("name", &self.name)
+            .field("params", &self.params)
+            .finish()
+    }
+}
+
+impl<F: Fn() -> Box<dyn Strategy> + Send + Sync> StrategyFactory for FnFactory<F> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn params(&self) -> BTreeMap<String, Value> {
+        self.params.clone()
+    }
+
+    fn build(&self) -> Box<dyn Strategy> {
+        (self.build)()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_factory_spec_round_trips() {
+        assert!(BuiltinFactory::new("nope", StrategyParams::default()).is_err());
+
+        let factory = BuiltinFactory::new("spread_arb", StrategyParams::default()).unwrap();
+        assert_eq!(factory.build().name(), "spread_arb");
+        let shared: Arc<dyn StrategyFactory> = Arc::new(factory.clone());
+        assert_eq!(shared.spec(), factory.spec());
+
+        let json = serde_json::to_string(&factory.spec()).unwrap();
+        let spec: StrategySpec = serde_json::from_str(&json).unwrap();
+        assert_eq!(spec.name, "spread_arb");
+        assert_eq!(spec.params["bid_price"], 0.49);
+
+        let custom = FnFactory::new(|| factory.build()).with_params(spec.params.clone());
+        assert_eq!(custom.spec(), spec);
+    }
+
+    #[cfg(feature = "scripting")]
+    #[test]
+    fn test_script_factory_records_params() {
+        let source = "fn on_tick(snap) { [] }\nfn on_reset() {}";
+        assert!(ScriptFactory::from_source("s", source, 10.0, 0.49, &["bad".into()]).is_err());
+
+        let factory =
+            ScriptFactory::from_source("s", source, 10.0, 0.49, &["lookback=3".into(), "mode=fast".into()]).unwrap();
+        let params = factory.params();
+        assert_eq!(params["lookback"], 3);
+        assert_eq!(params["mode"], "fast");
+        assert_eq!(factory.build().name(), factory.name());
+    }
+}
@@ -2,7 +2,7 @@ use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 
 use crate::strategies::Strategy;
-use crate::types::{Action, BookSnapshot, Market, Outcome, Side};
+use crate::types::{Action, BookSnapshot, Market, Outcome, Price, Shares, Side, UnixSecs};
 
 /// Direction of a candle / market outcome (local to fade logic).
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -40,7 +40,7 @@ pub fn compute_fade_signals(
     for ((_cat, duration), mut group) in groups {
         group.sort_by_key(|m| m.open_ts);
 
-        let mut history: VecDeque<(i64, CandleDir)> = VecDeque::new();
+        let mut history: VecDeque<(UnixSecs, CandleDir)> = VecDeque::new();
         let max_history = max_streak + 5;
 
         for i in 0..group.len() {
@@ -48,7 +48,7 @@ pub fn compute_fade_signals(
             let dir = match market.outcome {
                 Some(Outcome::Yes) => CandleDir::Up,
                 Some(Outcome::No) => CandleDir::Down,
-                None => continue,
+                Some(Outcome::Void | Outcome::Unresolved) | None => continue,
             };
 
             history.push_back((market.open_ts, dir));
@@ -58,7 +58,7 @@ pub fn compute_fade_signals(
 
             // Count consecutive same-direction from the end.
             let mut streak = 0usize;
-            let mut prev_ts: Option<i64> = None;
+            let mut prev_ts: Option<UnixSecs> = None;
 
             for &(ts, d) in history.iter().rev() {
                 if d != dir {
@@ -95,8 +95,8 @@ pub fn compute_fade_signals(
 /// Uses pre-computed signals from [`compute_fade_signals`]. On market open, looks up whether
 /// this window has a fade signal. If yes, places a single bid on the fade side at T=0.
 pub struct FadeMomentum {
-    bid_price: f64,
-    shares: f64,
+    bid_price: Price,
+    shares: Shares,
     signals: Arc<HashMap<String, Side>>,
     current_signal: Option<Side>,
     acted: bool,
@@ -105,8 +105,8 @@ pub struct FadeMomentum {
 impl FadeMomentum {
     pub fn new(bid_price: f64, shares: f64, signals: Arc<HashMap<String, Side>>) -> Self {
         Self {
-            bid_price,
-            shares,
+            bid_price: Price::from_f64(bid_price),
+            shares: Shares::from_f64(shares),
             signals,
             current_signal: None,
             acted: false,
@@ -124,7 +124,7 @@ impl Strategy for FadeMomentum {
     }
 
     fn on_market_open(&mut self, snap: &BookSnapshot) {
-        self.current_signal = self.signals.get(&snap.market_id).cloned();
+        self.current_signal = self.signals.get(&*snap.market_id).cloned();
     }
 
     fn on_tick(&mut self, _snap: &BookSnapshot) -> Vec<Action> {
@@ -166,10 +166,11 @@ mod tests {
             platform: Platform::Polymarket,
             description: String::new(),
             category: category.to_string(),
-            open_ts,
-            close_ts: open_ts + duration,
+            open_ts: UnixSecs::new(open_ts),
+            close_ts: UnixSecs::new(open_ts + duration),
             duration_secs: duration,
             outcome: Some(outcome),
+            outcome_ts: None,
         }
     }
 
@@ -294,8 +295,8 @@ mod tests {
         match &actions[0] {
             Action::PlaceBid { side, price, shares } => {
                 assert_eq!(*side, Side::No);
-                assert!((price - 0.49).abs() < 0.001);
-                assert!((shares - 25.0).abs() < 0.001);
+                assert_eq!(*price, Price::from_f64(0.49));
+                assert_eq!(*shares, Shares::from_f64(25.0));
             }
             _ => panic!("expected PlaceBid"),
         }
@@ -1,35 +1,75 @@
 use crate::strategies::Strategy;
-use crate::types::{Action, BookSnapshot, Side};
+use crate::types::{Action, BookSnapshot, OrderState, Price, Shares, Side};
 
 /// "Gabagool" combined-price arb: buy YES and NO at different times when
 /// their combined best_bid < $1.00.
 ///
 /// Named after the famous Polymarket bot gabagool22 (86% WR, $246K profit).
-/// The strategy watches both sides' best_bid prices. When yes_bid + no_bid < 1.0,
-/// it buys whichever side is cheaper first, then waits for the other side to
-/// become cheap enough and buys that too. If both legs fill, you profit the
-/// difference (1.0 - yes_bid - no_bid) per share regardless of outcome.
+/// The strategy watches both sides' best_bid prices. When yes_bid + no_bid
+/// drops below `max_combined`, it bids the cheaper side. Once that first leg
+/// fills, it waits for the other side to become cheap enough -- first-leg
+/// price plus the other side's bid at most `max_completion_combined` -- and
+/// bids it for the shares the first leg got. If both legs fill, you profit
+/// the difference (1.0 - yes_price - no_price) per share regardless of
+/// outcome.
+///
+/// A pair that isn't complete `completion_window_ms` after the first fill
+/// is unwound: the open bids are cancelled and the first-leg shares the
+/// second leg didn't match are sold at the best bid.
 ///
 /// Unlike spread_arb (which bids both at the same price simultaneously),
 /// gabagool exploits temporal price dislocations — moments when the two sides
 /// are briefly mispriced relative to each other.
 pub struct Gabagool {
-    shares: f64,
+    shares: Shares,
     /// Maximum combined price to trigger (e.g., 0.995 = need at least $0.005 edge).
     max_combined: f64,
-    /// Track which sides we've already bid on.
-    yes_placed: bool,
-    no_placed: bool,
+    /// Maximum first-leg price plus second-leg bid to complete the pair at.
+    max_completion_combined: f64,
+    /// How long to wait for the second leg after the first fills.
+    completion_window_ms: i64,
+    /// The first leg's side and price, once bid.
+    first: Option<(Side, Price)>,
+    second_placed: bool,
+    /// Gave up on the pair and sold the first leg.
+    unwound: bool,
+    orders: OrderState,
 }
 
 impl Gabagool {
-    pub fn new(shares: f64, max_combined: f64) -> Self {
+    pub fn new(shares: f64, max_combined: f64, max_completion_combined: f64, completion_window_ms: i64) -> Self {
         Self {
-            shares,
+            shares: Shares::from_f64(shares),
             max_combined,
-            yes_placed: false,
-            no_placed: false,
+            max_completion_combined,
+            completion_window_ms,
+            first: None,
+            second_placed: false,
+            unwound: false,
+            orders: OrderState::default(),
+        }
+    }
+
+    /// Bid the cheaper side once the combined bid is below `max_combined`.
+    fn enter(&mut self, snap: &BookSnapshot) -> Vec<Action> {
+        let (Some(yes_bid), Some(no_bid)) = (snap.yes.best_bid, snap.no.best_bid) else {
+            return vec![];
+        };
+        if yes_bid + no_bid >= self.max_combined {
+            return vec![];
         }
+        let (side, bid) = if yes_bid <= no_bid {
+            (Side::Yes, yes_bid)
+        } else {
+            (Side::No, no_bid)
+        };
+        let price = Price::from_f64(bid);
+        self.first = Some((side, price));
+        vec![Action::PlaceBid {
+            side,
+            price,
+            shares: self.shares,
+        }]
     }
 }
 
@@ -39,159 +79,265 @@ impl Strategy for Gabagool {
     }
 
     fn description(&self) -> &str {
-        "Gabagool combined-price arb: buy YES+NO at different times when combined bid < $1.00"
+        "Gabagool combined-price arb: buy YES+NO at different times when combined bid < $1.00, unwinding unpaired legs"
+    }
+
+    fn on_order_update(&mut self, orders: &OrderState) {
+        self.orders = orders.clone();
     }
 
     fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action> {
-        if self.yes_placed && self.no_placed {
+        if self.unwound {
             return vec![];
         }
+        let Some((side, price)) = self.first else {
+            return self.enter(snap);
+        };
 
-        let yes_bid = snap.yes.best_bid.unwrap_or(0.0);
-        let no_bid = snap.no.best_bid.unwrap_or(0.0);
-        let combined = yes_bid + no_bid;
-
-        // Only act when there's a combined discount.
-        if combined >= self.max_combined {
+        // Nothing to pair until the first leg fills.
+        let first = self.orders.side(side);
+        let Some(filled_at) = first.filled_at_ms else {
+            return vec![];
+        };
+        let other = side.opposite();
+        let matched = self.orders.side(other).filled_shares;
+        let held = first.filled_shares;
+        if matched >= held {
             return vec![];
         }
 
-        let mut actions = vec![];
-
-        // Buy the cheaper side first (or both if both are available).
-        if !self.yes_placed && !self.no_placed {
-            // First entry: buy the cheaper side.
-            if yes_bid <= no_bid && yes_bid > 0.0 {
-                self.yes_placed = true;
-                actions.push(Action::PlaceBid {
-                    side: Side::Yes,
-                    price: yes_bid,
-                    shares: self.shares,
-                });
-            } else if no_bid > 0.0 {
-                self.no_placed = true;
-                actions.push(Action::PlaceBid {
-                    side: Side::No,
-                    price: no_bid,
-                    shares: self.shares,
-                });
+        if snap.offset_ms >= filled_at.ms() + self.completion_window_ms {
+            self.unwound = true;
+            let mut actions = vec![];
+            if self.second_placed {
+                actions.push(Action::Cancel { side: other });
             }
-        }
-
-        // Second entry: buy the other side if still cheap enough.
-        if self.yes_placed && !self.no_placed && no_bid > 0.0 {
-            // We already bought YES at some price. Now buy NO if combined is still good.
-            self.no_placed = true;
-            actions.push(Action::PlaceBid {
-                side: Side::No,
-                price: no_bid,
-                shares: self.shares,
-            });
-        } else if self.no_placed && !self.yes_placed && yes_bid > 0.0 {
-            self.yes_placed = true;
-            actions.push(Action::PlaceBid {
-                side: Side::Yes,
-                price: yes_bid,
-                shares: self.shares,
+            if !first.filled {
+                actions.push(Action::Cancel { side });
+            }
+            // At or below the best bid a sell takes at once; with no bids
+            // it rests on the asks at the entry price.
+            let exit = snap.side(side).best_bid.map_or(price, Price::from_f64);
+            actions.push(Action::Sell {
+                side,
+                price: exit,
+                shares: held - matched,
             });
+            return actions;
         }
 
+        if self.second_placed {
+            return vec![];
+        }
+        let Some(other_bid) = snap.side(other).best_bid else {
+            return vec![];
+        };
+        let other_price = Price::from_f64(other_bid);
+        if price.units() + other_price.units() > Price::from_f64(self.max_completion_combined).units() {
+            return vec![];
+        }
+        self.second_placed = true;
+        let mut actions = vec![];
+        // Pair only what filled: stop the first leg from growing past it.
+        if !first.filled {
+            actions.push(Action::Cancel { side });
+        }
+        actions.push(Action::PlaceBid {
+            side: other,
+            price: other_price,
+            shares: held,
+        });
         actions
     }
 
     fn reset(&mut self) {
-        self.yes_placed = false;
-        self.no_placed = false;
+        self.first = None;
+        self.second_placed = false;
+        self.unwound = false;
+        self.orders = OrderState::default();
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{PriceLevel, SideState};
+    use std::sync::Arc;
+    use crate::types::{OffsetMs, PriceLevel, SideOrderState, SideState};
+    use smallvec::smallvec;
 
     fn make_snap(offset_ms: i64, yes_bid: f64, no_bid: f64) -> BookSnapshot {
         BookSnapshot {
-            market_id: "test".to_string(),
-            offset_ms,
+            market_id: "test".into(),
+            offset_ms: OffsetMs::new(offset_ms),
             timestamp_ms: 1_700_000_000_000 + offset_ms,
-            yes: SideState {
+            yes: Arc::new(SideState {
                 best_bid: Some(yes_bid),
                 best_bid_size: Some(500.0),
                 best_ask: Some(yes_bid + 0.01),
                 best_ask_size: Some(100.0),
-                depth: vec![PriceLevel {
+                depth: smallvec![PriceLevel {
                     price: yes_bid,
                     cumulative_size: 500.0,
                 }],
                 total_bid_depth: 500.0,
                 total_ask_depth: 100.0,
-            },
-            no: SideState {
+            }),
+            no: Arc::new(SideState {
                 best_bid: Some(no_bid),
                 best_bid_size: Some(500.0),
                 best_ask: Some(no_bid + 0.01),
                 best_ask_size: Some(100.0),
-                depth: vec![PriceLevel {
+                depth: smallvec![PriceLevel {
                     price: no_bid,
                     cumulative_size: 500.0,
                 }],
                 total_bid_depth: 500.0,
                 total_ask_depth: 100.0,
-            },
+            }),
             reference_price: None,
             oracle_price: None,
         }
     }
 
+    /// The first leg on `side` at `price`, with `filled` of 10 shares
+    /// filled at 1s.
+    fn first_leg(side: Side, price: f64, filled: f64) -> OrderState {
+        let mut orders = OrderState::default();
+        let leg = SideOrderState {
+            placed: true,
+            filled: filled >= 10.0,
+            price: Price::from_f64(price),
+            shares: Shares::from_f64(10.0),
+            filled_shares: Shares::from_f64(filled),
+            filled_at_ms: Some(OffsetMs::new(1000)),
+            ..Default::default()
+        };
+        match side {
+            Side::Yes => orders.yes = leg,
+            Side::No => orders.no = leg,
+        }
+        orders
+    }
+
     #[test]
     fn buys_cheaper_side_first() {
-        let mut strat = Gabagool::new(10.0, 0.99);
-        let snap = make_snap(0, 0.48, 0.50); // combined 0.98 < 0.99
+        let mut strat = Gabagool::new(10.0, 0.99, 1.0, 60_000);
+        let snap = make_snap(0, 0.50, 0.48); // combined 0.98 < 0.99
         let actions = strat.on_tick(&snap);
-        // Should buy YES (cheaper) first, then NO on same tick.
-        assert_eq!(actions.len(), 2);
+        // Only the cheaper side (NO) until it fills.
+        assert_eq!(actions.len(), 1);
         match &actions[0] {
-            Action::PlaceBid { side, price, .. } => {
-                assert_eq!(*side, Side::Yes);
-                assert!((price - 0.48).abs() < f64::EPSILON);
-            }
-            _ => panic!("expected PlaceBid YES"),
-        }
-        match &actions[1] {
             Action::PlaceBid { side, price, .. } => {
                 assert_eq!(*side, Side::No);
-                assert!((price - 0.50).abs() < f64::EPSILON);
+                assert_eq!(*price, Price::from_f64(0.48));
             }
             _ => panic!("expected PlaceBid NO"),
         }
+        assert!(strat.on_tick(&make_snap(500, 0.45, 0.48)).is_empty());
     }
 
     #[test]
     fn skips_when_combined_too_high() {
-        let mut strat = Gabagool::new(10.0, 0.99);
+        let mut strat = Gabagool::new(10.0, 0.99, 1.0, 60_000);
         let snap = make_snap(0, 0.50, 0.50); // combined 1.00 >= 0.99
         let actions = strat.on_tick(&snap);
         assert!(actions.is_empty());
     }
 
     #[test]
-    fn no_double_placement() {
-        let mut strat = Gabagool::new(10.0, 0.99);
-        let snap1 = make_snap(0, 0.48, 0.50);
-        strat.on_tick(&snap1);
-        let snap2 = make_snap(1000, 0.47, 0.49);
-        let actions = strat.on_tick(&snap2);
-        assert!(actions.is_empty()); // already placed both
+    fn completes_only_while_the_pair_is_cheap() {
+        let mut strat = Gabagool::new(10.0, 0.99, 1.0, 60_000);
+        strat.on_tick(&make_snap(0, 0.48, 0.50));
+        strat.on_order_update(&first_leg(Side::Yes, 0.48, 10.0));
+        // 0.48 + 0.53 > 1.00: the discount is gone, keep waiting.
+        assert!(strat.on_tick(&make_snap(2000, 0.46, 0.53)).is_empty());
+        let actions = strat.on_tick(&make_snap(3000, 0.47, 0.52));
+        assert_eq!(
+            actions,
+            vec![Action::PlaceBid {
+                side: Side::No,
+                price: Price::from_f64(0.52),
+                shares: Shares::from_f64(10.0),
+            }]
+        );
+        assert!(strat.on_tick(&make_snap(4000, 0.47, 0.50)).is_empty());
+    }
+
+    #[test]
+    fn pairs_only_the_filled_shares() {
+        let mut strat = Gabagool::new(10.0, 0.99, 1.0, 60_000);
+        strat.on_tick(&make_snap(0, 0.48, 0.50));
+        strat.on_order_update(&first_leg(Side::Yes, 0.48, 4.0));
+        let actions = strat.on_tick(&make_snap(2000, 0.48, 0.50));
+        assert_eq!(
+            actions,
+            vec![
+                Action::Cancel { side: Side::Yes },
+                Action::PlaceBid {
+                    side: Side::No,
+                    price: Price::from_f64(0.50),
+                    shares: Shares::from_f64(4.0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unwinds_when_the_second_leg_never_comes() {
+        let mut strat = Gabagool::new(10.0, 0.99, 1.0, 60_000);
+        strat.on_tick(&make_snap(0, 0.48, 0.50));
+        strat.on_order_update(&first_leg(Side::Yes, 0.48, 10.0));
+        assert!(strat.on_tick(&make_snap(60_000, 0.40, 0.70)).is_empty());
+        let actions = strat.on_tick(&make_snap(61_000, 0.40, 0.70));
+        assert_eq!(
+            actions,
+            vec![Action::Sell {
+                side: Side::Yes,
+                price: Price::from_f64(0.40),
+                shares: Shares::from_f64(10.0),
+            }]
+        );
+        assert!(strat.on_tick(&make_snap(62_000, 0.30, 0.50)).is_empty());
+    }
+
+    #[test]
+    fn unwinds_the_unmatched_part_of_a_resting_pair() {
+        let mut strat = Gabagool::new(10.0, 0.99, 1.0, 60_000);
+        strat.on_tick(&make_snap(0, 0.48, 0.50));
+        strat.on_order_update(&first_leg(Side::Yes, 0.48, 10.0));
+        strat.on_tick(&make_snap(2000, 0.48, 0.51));
+        let mut orders = first_leg(Side::Yes, 0.48, 10.0);
+        orders.no = SideOrderState {
+            placed: true,
+            price: Price::from_f64(0.51),
+            shares: Shares::from_f64(10.0),
+            filled_shares: Shares::from_f64(6.0),
+            filled_at_ms: Some(OffsetMs::new(30_000)),
+            ..Default::default()
+        };
+        strat.on_order_update(&orders);
+        let actions = strat.on_tick(&make_snap(61_000, 0.45, 0.53));
+        assert_eq!(
+            actions,
+            vec![
+                Action::Cancel { side: Side::No },
+                Action::Sell {
+                    side: Side::Yes,
+                    price: Price::from_f64(0.45),
+                    shares: Shares::from_f64(4.0),
+                },
+            ]
+        );
     }
 
     #[test]
     fn reset_allows_replay() {
-        let mut strat = Gabagool::new(10.0, 0.99);
+        let mut strat = Gabagool::new(10.0, 0.99, 1.0, 60_000);
         let snap = make_snap(0, 0.48, 0.50);
         strat.on_tick(&snap);
+        strat.on_order_update(&first_leg(Side::Yes, 0.48, 10.0));
         strat.reset();
         let actions = strat.on_tick(&snap);
-        assert_eq!(actions.len(), 2);
+        assert_eq!(actions.len(), 1);
     }
 }
@@ -0,0 +1,209 @@
+//! Stateful indicators for strategies, also registered for Rhai scripts.
+//!
+//! Each [`Indicator`] is fed one value per tick with [`Indicator::update`]
+//! and exposes its current value plus a `ready` flag once enough samples
+//! have been seen.
+
+use std::collections::VecDeque;
+
+use rhai::{Engine, EvalAltResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Sma,
+    Ema,
+    RollingStd,
+    Max,
+    Min,
+    BpsSinceOpen,
+}
+
+#[derive(Debug, Clone)]
+pub struct Indicator {
+    kind: Kind,
+    period: usize,
+    window: VecDeque<f64>,
+    count: usize,
+    value: Option<f64>,
+    first: Option<f64>,
+}
+
+impl Indicator {
+    fn new(kind: Kind, period: usize) -> Self {
+        Self {
+            kind,
+            period,
+            window: VecDeque::with_capacity(period),
+            count: 0,
+            value: None,
+            first: None,
+        }
+    }
+
+    /// Simple moving average over the last `period` values.
+    pub fn sma(period: usize) -> Self {
+        Self::new(Kind::Sma, period.max(1))
+    }
+
+    /// Exponential moving average with `alpha = 2 / (period + 1)`, seeded
+    /// with the first value.
+    pub fn ema(period: usize) -> Self {
+        Self::new(Kind::Ema, period.max(1))
+    }
+
+    /// Population standard deviation over the last `period` values.
+    pub fn rolling_std(period: usize) -> Self {
+        Self::new(Kind::RollingStd, period.max(1))
+    }
+
+    /// Highest value seen so far.
+    pub fn max_so_far() -> Self {
+        Self::new(Kind::Max, 1)
+    }
+
+    /// Lowest value seen so far.
+    pub fn min_so_far() -> Self {
+        Self::new(Kind::Min, 1)
+    }
+
+    /// Change in basis points relative to the first value seen.
+    pub fn bps_since_open() -> Self {
+        Self::new(Kind::BpsSinceOpen, 1)
+    }
+
+    /// Feed the next value and return the updated indicator value.
+    pub fn update(&mut self, x: f64) -> f64 {
+        self.count += 1;
+        if matches!(self.kind, Kind::Sma | Kind::RollingStd) {
+            if self.window.len() == self.period {
+                self.window.pop_front();
+            }
+            self.window.push_back(x);
+        }
+
+        let next = match self.kind {
+            Kind::Sma => Some(self.window.iter().sum::<f64>() / self.window.len() as f64),
+            Kind::Ema => {
+                let alpha = 2.0 / (self.period as f64 + 1.0);
+                Some(match self.value {
+                    Some(prev) => alpha * x + (1.0 - alpha) * prev,
+                    None => x,
+                })
+            }
+            Kind::RollingStd => {
+                let n = self.window.len() as f64;
+                let mean = self.window.iter().sum::<f64>() / n;
+                let var = self.window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+                Some(var.sqrt())
+            }
+            Kind::Max => Some(self.value.map_or(x, |v| v.max(x))),
+            Kind::Min => Some(self.value.map_or(x, |v| v.min(x))),
+            Kind::BpsSinceOpen => {
+                let open = *self.first.get_or_insert(x);
+                (open != 0.0).then(|| (x - open) / open * 10_000.0)
+            }
+        };
+        self.value = next;
+        self.value()
+    }
+
+    /// Current value (0.0 before any usable input).
+    pub fn value(&self) -> f64 {
+        self.value.unwrap_or(0.0)
+    }
+
+    /// Whether `period` values have been seen.
+    pub fn ready(&self) -> bool {
+        self.value.is_some() && self.count >= self.period
+    }
+
+    /// Clear all state, keeping the indicator type and period.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.kind, self.period);
+    }
+}
+
+fn checked_period(period: i64) -> Result<usize, Box<EvalAltResult>> {
+    if period < 1 {
+        return Err(format!("indicator period must be >= 1, got {}", period).into());
+    }
+    Ok(period as usize)
+}
+
+/// Register the indicator constructors and methods on a Rhai engine.
+///
+/// Scripts use them as `let fast = sma(5); ... fast.update(snap.oracle_price);`
+/// and read `fast.value` / `fast.ready`.
+pub fn register(engine: &mut Engine) {
+    engine
+        .register_type_with_name::<Indicator>("Indicator")
+        .register_fn("sma", |n: i64| checked_period(n).map(Indicator::sma))
+        .register_fn("ema", |n: i64| checked_period(n).map(Indicator::ema))
+        .register_fn("rolling_std", |n: i64| {
+            checked_period(n).map(Indicator::rolling_std)
+        })
+        .register_fn("max_so_far", Indicator::max_so_far)
+        .register_fn("min_so_far", Indicator::min_so_far)
+        .register_fn("bps_since_open", Indicator::bps_since_open)
+        .register_fn("update", Indicator::update)
+        .register_fn("update", |ind: &mut Indicator, x: i64| ind.update(x as f64))
+        .register_fn("reset", Indicator::reset)
+        .register_get("value", |ind: &mut Indicator| ind.value())
+        .register_get("ready", |ind: &mut Indicator| ind.ready());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(ind: &mut Indicator, xs: &[f64]) -> f64 {
+        xs.iter().map(|&x| ind.update(x)).last().unwrap()
+    }
+
+    #[test]
+    fn test_sma_rolls_window() {
+        let mut sma = Indicator::sma(3);
+        assert_eq!(sma.update(1.0), 1.0);
+        assert!(!sma.ready());
+        assert_eq!(feed(&mut sma, &[2.0, 3.0]), 2.0);
+        assert!(sma.ready());
+        assert_eq!(sma.update(7.0), 4.0);
+    }
+
+    #[test]
+    fn test_ema_and_std() {
+        let mut ema = Indicator::ema(3);
+        // alpha = 0.5
+        assert_eq!(feed(&mut ema, &[10.0, 20.0]), 15.0);
+
+        let mut std = Indicator::rolling_std(2);
+        assert_eq!(feed(&mut std, &[1.0, 3.0]), 1.0);
+        assert_eq!(std.update(3.0), 0.0);
+    }
+
+    #[test]
+    fn test_extremes_and_bps() {
+        let mut hi = Indicator::max_so_far();
+        let mut lo = Indicator::min_so_far();
+        assert_eq!(feed(&mut hi, &[2.0, 5.0, 3.0]), 5.0);
+        assert_eq!(feed(&mut lo, &[2.0, 5.0, 1.0]), 1.0);
+
+        let mut bps = Indicator::bps_since_open();
+        assert_eq!(feed(&mut bps, &[50_000.0, 50_050.0]), 10.0);
+
+        bps.reset();
+        assert_eq!(bps.value(), 0.0);
+        assert!(!bps.ready());
+    }
+
+    #[test]
+    fn test_registered_in_rhai() {
+        let mut engine = Engine::new();
+        register(&mut engine);
+        let v: f64 = engine
+            .eval("let s = sma(2); s.update(1); s.update(3.0); if s.ready { s.value } else { -1.0 }")
+            .unwrap();
+        assert_eq!(v, 2.0);
+        assert!(engine.eval::<f64>("let s = sma(0); s.value").is_err());
+    }
+}
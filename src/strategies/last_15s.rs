@@ -1,5 +1,5 @@
 use crate::strategies::Strategy;
-use crate::types::{Action, BookSnapshot, Side};
+use crate::types::{Action, BookSnapshot, Market, Price, Shares, Side};
 
 /// "Last 15 Seconds" strategy: wait until the final 15 seconds of a market
 /// window, then buy whichever side has a best_bid >= the threshold (default 0.98).
@@ -13,24 +13,27 @@ use crate::types::{Action, BookSnapshot, Side};
 /// depth ahead of you. This strategy exists to demonstrate phantom fills.
 pub struct Last15Seconds {
     /// Price to bid at (uses the observed best_bid, not a fixed price).
-    shares: f64,
+    shares: Shares,
     /// Minimum best_bid to trigger entry.
     min_bid: f64,
     /// How many ms before market close to start looking (default 15_000).
     trigger_before_close_ms: i64,
-    /// Market duration in ms (set on market open from close_ts - open_ts context,
-    /// or defaults to 900_000 for 15m markets).
+    /// Window length in ms when the market's own isn't known (e.g. the
+    /// strategy is driven without [`Strategy::on_market`]).
     window_duration_ms: i64,
+    /// The current market's window length in ms, from its metadata.
+    market_duration_ms: Option<i64>,
     acted: bool,
 }
 
 impl Last15Seconds {
-    pub fn new(shares: f64, min_bid: f64, window_duration_ms: i64) -> Self {
+    pub fn new(shares: f64, min_bid: f64, trigger_before_close_ms: i64, window_duration_ms: i64) -> Self {
         Self {
-            shares,
+            shares: Shares::from_f64(shares),
             min_bid,
-            trigger_before_close_ms: 15_000,
+            trigger_before_close_ms,
             window_duration_ms,
+            market_duration_ms: None,
             acted: false,
         }
     }
@@ -45,12 +48,17 @@ impl Strategy for Last15Seconds {
         "Last 15 Seconds: buy the side bid at 98c+ in the final 15 seconds"
     }
 
+    fn on_market(&mut self, market: &Market) {
+        self.market_duration_ms = (market.duration_secs > 0).then_some(market.duration_secs * 1000);
+    }
+
     fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action> {
         if self.acted {
             return vec![];
         }
 
-        let trigger_offset = self.window_duration_ms - self.trigger_before_close_ms;
+        let duration_ms = self.market_duration_ms.unwrap_or(self.window_duration_ms);
+        let trigger_offset = duration_ms - self.trigger_before_close_ms;
         if snap.offset_ms < trigger_offset {
             return vec![];
         }
@@ -72,50 +80,53 @@ impl Strategy for Last15Seconds {
 
         vec![Action::PlaceBid {
             side,
-            price,
+            price: Price::from_f64(price),
             shares: self.shares,
         }]
     }
 
     fn reset(&mut self) {
         self.acted = false;
+        self.market_duration_ms = None;
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{PriceLevel, SideState};
+    use std::sync::Arc;
+    use crate::types::{OffsetMs, Platform, PriceLevel, SideState, UnixSecs};
+    use smallvec::smallvec;
 
     fn make_snap(offset_ms: i64, yes_bid: f64, no_bid: f64) -> BookSnapshot {
         BookSnapshot {
-            market_id: "test".to_string(),
-            offset_ms,
+            market_id: "test".into(),
+            offset_ms: OffsetMs::new(offset_ms),
             timestamp_ms: 1_700_000_000_000 + offset_ms,
-            yes: SideState {
+            yes: Arc::new(SideState {
                 best_bid: Some(yes_bid),
                 best_bid_size: Some(500.0),
                 best_ask: Some(yes_bid + 0.01),
                 best_ask_size: Some(100.0),
-                depth: vec![PriceLevel {
+                depth: smallvec![PriceLevel {
                     price: yes_bid,
                     cumulative_size: 500.0,
                 }],
                 total_bid_depth: 500.0,
                 total_ask_depth: 100.0,
-            },
-            no: SideState {
+            }),
+            no: Arc::new(SideState {
                 best_bid: Some(no_bid),
                 best_bid_size: Some(500.0),
                 best_ask: Some(no_bid + 0.01),
                 best_ask_size: Some(100.0),
-                depth: vec![PriceLevel {
+                depth: smallvec![PriceLevel {
                     price: no_bid,
                     cumulative_size: 500.0,
                 }],
                 total_bid_depth: 500.0,
                 total_ask_depth: 100.0,
-            },
+            }),
             reference_price: None,
             oracle_price: None,
         }
@@ -123,7 +134,7 @@ mod tests {
 
     #[test]
     fn no_action_before_trigger_window() {
-        let mut strat = Last15Seconds::new(10.0, 0.98, 900_000);
+        let mut strat = Last15Seconds::new(10.0, 0.98, 15_000, 900_000);
         let snap = make_snap(800_000, 0.99, 0.01); // 100s before close
         let actions = strat.on_tick(&snap);
         assert!(actions.is_empty());
@@ -131,15 +142,15 @@ mod tests {
 
     #[test]
     fn buys_yes_when_bid_high_in_last_15s() {
-        let mut strat = Last15Seconds::new(10.0, 0.98, 900_000);
+        let mut strat = Last15Seconds::new(10.0, 0.98, 15_000, 900_000);
         let snap = make_snap(886_000, 0.99, 0.01); // 14s before close
         let actions = strat.on_tick(&snap);
         assert_eq!(actions.len(), 1);
         match &actions[0] {
             Action::PlaceBid { side, price, shares } => {
                 assert_eq!(*side, Side::Yes);
-                assert!((price - 0.99).abs() < f64::EPSILON);
-                assert!((shares - 10.0).abs() < f64::EPSILON);
+                assert_eq!(*price, Price::from_f64(0.99));
+                assert_eq!(*shares, Shares::from_f64(10.0));
             }
             _ => panic!("expected PlaceBid"),
         }
@@ -147,7 +158,7 @@ mod tests {
 
     #[test]
     fn buys_no_when_no_side_bid_high() {
-        let mut strat = Last15Seconds::new(10.0, 0.98, 900_000);
+        let mut strat = Last15Seconds::new(10.0, 0.98, 15_000, 900_000);
         let snap = make_snap(886_000, 0.01, 0.99);
         let actions = strat.on_tick(&snap);
         assert_eq!(actions.len(), 1);
@@ -159,7 +170,7 @@ mod tests {
 
     #[test]
     fn skips_when_no_side_meets_threshold() {
-        let mut strat = Last15Seconds::new(10.0, 0.98, 900_000);
+        let mut strat = Last15Seconds::new(10.0, 0.98, 15_000, 900_000);
         let snap = make_snap(886_000, 0.50, 0.50);
         let actions = strat.on_tick(&snap);
         assert!(actions.is_empty());
@@ -167,7 +178,7 @@ mod tests {
 
     #[test]
     fn acts_only_once() {
-        let mut strat = Last15Seconds::new(10.0, 0.98, 900_000);
+        let mut strat = Last15Seconds::new(10.0, 0.98, 15_000, 900_000);
         let snap1 = make_snap(886_000, 0.99, 0.01);
         strat.on_tick(&snap1);
         let snap2 = make_snap(890_000, 0.99, 0.01);
@@ -175,9 +186,37 @@ mod tests {
         assert!(actions.is_empty());
     }
 
+    #[test]
+    fn triggers_from_the_market_duration() {
+        let market = |duration_secs| Market {
+            id: "test".into(),
+            platform: Platform::Polymarket,
+            description: String::new(),
+            category: "btc".into(),
+            open_ts: UnixSecs::new(1_700_000_000),
+            close_ts: UnixSecs::new(1_700_000_000 + duration_secs),
+            duration_secs,
+            outcome: None,
+            outcome_ts: None,
+        };
+        let mut strat = Last15Seconds::new(10.0, 0.98, 15_000, 900_000);
+        strat.on_market(&market(300));
+        assert!(strat.on_tick(&make_snap(280_000, 0.99, 0.01)).is_empty());
+        assert_eq!(strat.on_tick(&make_snap(286_000, 0.99, 0.01)).len(), 1);
+
+        strat.reset();
+        strat.on_market(&market(3600));
+        assert!(strat.on_tick(&make_snap(886_000, 0.99, 0.01)).is_empty());
+        assert_eq!(strat.on_tick(&make_snap(3_586_000, 0.99, 0.01)).len(), 1);
+
+        // Without metadata it falls back to window_duration_ms.
+        strat.reset();
+        assert_eq!(strat.on_tick(&make_snap(886_000, 0.99, 0.01)).len(), 1);
+    }
+
     #[test]
     fn reset_allows_replay() {
-        let mut strat = Last15Seconds::new(10.0, 0.98, 900_000);
+        let mut strat = Last15Seconds::new(10.0, 0.98, 15_000, 900_000);
         let snap = make_snap(886_000, 0.99, 0.01);
         strat.on_tick(&snap);
         strat.reset();
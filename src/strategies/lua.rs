@@ -0,0 +1,722 @@
+//! Strategies written in Lua 5.4, loaded through mlua (`lua` feature).
+//!
+//! Mirrors the Rhai backend: scripts define `on_tick(snap)` and `on_reset()`
+//! (plus optional `on_market_open(snap)`), see `SHARES`, `BID_PRICE` and any
+//! script parameters as globals, and get the same helpers -- `bid`, `cancel`,
+//! the bid/ask `*_depth_at` lookups, `history`/`oracle_ago`, the timing
+//! helpers, `log`/`debug` and the indicator constructors. `snap` is a table with the same keys as the
+//! Rhai snap map; values that are `()` in Rhai are `nil` here.
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::{bail, Context};
+use mlua::{Function, HookTriggers, Lua, Table, UserData, Value};
+use rhai::{Dynamic, Map};
+
+use crate::strategies::indicators::Indicator;
+use crate::strategies::scripted::{
+    ask_depth_at_levels, depth_at_levels, snap_to_dynamic, window_elapsed_fraction,
+    window_remaining_ms, LogContext, Schedule, ScriptLimits, SnapshotHistory, DEFAULT_HISTORY_LEN,
+};
+use crate::strategies::Strategy;
+use crate::types::{Action, BookSnapshot, Market, OrderState, Price, Shares, Side};
+
+/// VM instructions between budget checks.
+const HOOK_INTERVAL: u32 = 1000;
+
+/// Budget state shared with the instruction hook.
+#[derive(Default)]
+struct Budget {
+    armed: bool,
+    ops: u64,
+    max_ops: u64,
+    deadline: Option<Instant>,
+    tripped: Option<&'static str>,
+}
+
+/// A strategy loaded from a Lua script file.
+pub struct LuaStrategy {
+    lua: Lua,
+    name: String,
+    script_path: String,
+    has_on_market_open: bool,
+    orders: OrderState,
+    market: Option<Market>,
+    limits: ScriptLimits,
+    budget: Arc<Mutex<Budget>>,
+    history: SnapshotHistory,
+    schedule: Schedule,
+    log_context: LogContext,
+    strict: bool,
+    errors: usize,
+    aborted: Option<String>,
+    /// Why the script failed to load in this instance; every window
+    /// aborts with it.
+    load_error: Option<String>,
+}
+
+impl std::fmt::Debug for LuaStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LuaStrategy")
+            .field("name", &self.name)
+            .field("script_path", &self.script_path)
+            .finish()
+    }
+}
+
+impl LuaStrategy {
+    /// Load a strategy from a `.lua` file, with extra global constants
+    /// (see [`parse_script_param`](crate::strategies::scripted::parse_script_param)).
+    pub fn from_file_with_params(
+        path: &Path,
+        shares: f64,
+        bid_price: f64,
+        params: &[(String, Dynamic)],
+    ) -> anyhow::Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read script: {}", path.display()))?;
+
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let mut strategy = Self::from_source_with_params(&name, &source, shares, bid_price, params)
+            .with_context(|| format!("failed to load script: {}", path.display()))?;
+        strategy.script_path = path.display().to_string();
+        Ok(strategy)
+    }
+
+    /// Load a strategy from source code (useful for testing).
+    pub fn from_source(
+        name: &str,
+        source: &str,
+        shares: f64,
+        bid_price: f64,
+    ) -> anyhow::Result<Self> {
+        Self::from_source_with_params(name, source, shares, bid_price, &[])
+    }
+
+    /// Load a strategy from source code with extra global constants.
+    pub fn from_source_with_params(
+        name: &str,
+        source: &str,
+        shares: f64,
+        bid_price: f64,
+        params: &[(String, Dynamic)],
+    ) -> anyhow::Result<Self> {
+        let mut strategy = Self::new(name);
+        strategy.load(source, shares, bid_price, params)?;
+        Ok(strategy)
+    }
+
+    /// Like [`from_source_with_params`](Self::from_source_with_params), but
+    /// a script that fails to load still gives an instance, one that aborts
+    /// every window with the error.
+    pub fn from_source_or_aborted(
+        name: &str,
+        source: &str,
+        shares: f64,
+        bid_price: f64,
+        params: &[(String, Dynamic)],
+    ) -> Self {
+        let mut strategy = Self::new(name);
+        if let Err(e) = strategy.load(source, shares, bid_price, params) {
+            strategy.load_error = Some(format!("{:#}", e));
+            strategy.aborted = strategy.load_error.clone();
+        }
+        strategy
+    }
+
+    /// A VM with the execution guard installed and no script loaded.
+    fn new(name: &str) -> Self {
+        let lua = Lua::new();
+        let limits = ScriptLimits::default();
+        let budget = Arc::new(Mutex::new(Budget {
+            max_ops: limits.max_operations,
+            ..Budget::default()
+        }));
+
+        // Guard against runaway scripts; the budget is armed per callback.
+        let hook_budget = budget.clone();
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(HOOK_INTERVAL),
+            move |_, _| {
+                let mut b = hook_budget.lock().unwrap();
+                if !b.armed {
+                    return Ok(());
+                }
+                b.ops += u64::from(HOOK_INTERVAL);
+                if b.max_ops > 0 && b.ops > b.max_ops {
+                    b.tripped = Some("operation limit exceeded");
+                } else if b.deadline.is_some_and(|d| Instant::now() >= d) {
+                    b.tripped = Some("timed out");
+                }
+                match b.tripped {
+                    Some(reason) => Err(mlua::Error::RuntimeError(reason.to_string())),
+                    None => Ok(()),
+                }
+            },
+        );
+
+        Self {
+            lua,
+            name: name.to_string(),
+            script_path: name.to_string(),
+            has_on_market_open: false,
+            orders: OrderState::default(),
+            market: None,
+            limits,
+            budget,
+            history: SnapshotHistory::new(DEFAULT_HISTORY_LEN),
+            schedule: Schedule::default(),
+            log_context: LogContext::default(),
+            strict: false,
+            errors: 0,
+            aborted: None,
+            load_error: None,
+        }
+    }
+
+    /// Register the helpers and constants, and run `source`'s top level.
+    fn load(&mut self, source: &str, shares: f64, bid_price: f64, params: &[(String, Dynamic)]) -> anyhow::Result<()> {
+        let lua = &self.lua;
+        register_helpers(lua, &self.name, &self.history, &self.schedule, &self.log_context)
+            .map_err(|e| anyhow::anyhow!("failed to register helpers: {}", e))?;
+
+        let globals = lua.globals();
+        globals.set("SHARES", shares)?;
+        globals.set("BID_PRICE", bid_price)?;
+        for (key, value) in params {
+            if key == "SHARES" || key == "BID_PRICE" {
+                bail!("script parameter `{}` is reserved; use --shares/--bid-price", key);
+            }
+            globals.set(key.as_str(), dynamic_to_lua(lua, value)?)?;
+        }
+
+        // Run the top-level chunk once to define functions and global state.
+        arm(&self.budget, &self.limits);
+        let init = lua.load(source).set_name(self.name.as_str()).exec();
+        let tripped = disarm(&self.budget);
+        if let Err(e) = init {
+            match tripped {
+                Some(reason) => bail!("initialization error: {}", reason),
+                None => bail!("{}", e),
+            }
+        }
+
+        let has_fn = |f: &str| matches!(globals.get::<_, Value>(f), Ok(Value::Function(_)));
+        if !has_fn("on_tick") {
+            bail!("script must define an `on_tick(snap)` function");
+        }
+        if !has_fn("on_reset") {
+            bail!("script must define an `on_reset()` function");
+        }
+        self.has_on_market_open = has_fn("on_market_open");
+        Ok(())
+    }
+
+    /// Replace the default execution budget. `max_operations` counts Lua VM
+    /// instructions; call depth is bounded by Lua's own stack limit.
+    pub fn with_limits(self, limits: ScriptLimits) -> Self {
+        self.budget.lock().unwrap().max_ops = limits.max_operations;
+        Self { limits, ..self }
+    }
+
+    /// In strict mode any script runtime error aborts the window.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Keep the last `len` snapshots for `history(i)` / `oracle_ago(s)`.
+    pub fn with_history(self, len: usize) -> Self {
+        self.history.set_capacity(len);
+        self
+    }
+
+    /// Call a global script function under the execution budget, parsing its
+    /// return value as actions. Error handling matches the Rhai backend.
+    fn call(&mut self, fn_name: &str, snap: Option<&Dynamic>) -> Vec<Action> {
+        if self.aborted.is_some() {
+            return vec![];
+        }
+
+        arm(&self.budget, &self.limits);
+        let result = (|| -> mlua::Result<Vec<Action>> {
+            let func: Function = self.lua.globals().get(fn_name)?;
+            let ret: Value = match snap {
+                Some(map) => func.call(dynamic_to_lua(&self.lua, map)?)?,
+                None => func.call(())?,
+            };
+            Ok(parse_actions(ret))
+        })();
+        let tripped = disarm(&self.budget);
+
+        match result {
+            Ok(actions) => actions,
+            Err(e) => {
+                if let Some(reason) = tripped {
+                    // Reported by the engine when it stops the window.
+                    self.aborted = Some(format!("{}: {}", fn_name, reason));
+                } else {
+                    self.errors += 1;
+                    if self.strict {
+                        self.aborted = Some(format!("{}: {}", fn_name, e));
+                    } else {
+                        tracing::warn!(script = %self.name, "{} error: {}", fn_name, e);
+                    }
+                }
+                vec![]
+            }
+        }
+    }
+}
+
+fn arm(budget: &Mutex<Budget>, limits: &ScriptLimits) {
+    let mut b = budget.lock().unwrap();
+    b.armed = true;
+    b.ops = 0;
+    b.deadline = Some(Instant::now() + limits.timeout);
+    b.tripped = None;
+}
+
+/// Stop budgeting and return the limit that was hit, if any.
+fn disarm(budget: &Mutex<Budget>) -> Option<&'static str> {
+    let mut b = budget.lock().unwrap();
+    b.armed = false;
+    b.deadline = None;
+    b.tripped.take()
+}
+
+impl Strategy for LuaStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.script_path
+    }
+
+    fn on_market(&mut self, market: &Market) {
+        self.market = Some(market.clone());
+    }
+
+    fn on_market_open(&mut self, snap: &BookSnapshot) {
+        if !self.has_on_market_open {
+            return;
+        }
+        self.log_context.set(snap);
+        self.schedule.set(snap.offset_ms.ms());
+        let snap_map = snap_to_dynamic(snap, &self.orders, self.market.as_ref());
+        self.call("on_market_open", Some(&snap_map));
+    }
+
+    fn on_order_update(&mut self, orders: &OrderState) {
+        self.orders = orders.clone();
+    }
+
+    fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action> {
+        self.log_context.set(snap);
+        self.schedule.set(snap.offset_ms.ms());
+        let snap_map = snap_to_dynamic(snap, &self.orders, self.market.as_ref());
+        self.history.push(snap, &snap_map);
+        self.call("on_tick", Some(&snap_map))
+    }
+
+    fn reset(&mut self) {
+        self.orders = OrderState::default();
+        self.history.clear();
+        self.schedule.clear();
+        self.errors = 0;
+        self.aborted = self.load_error.clone();
+        self.call("on_reset", None);
+    }
+
+    fn abort_reason(&self) -> Option<&str> {
+        self.aborted.as_deref()
+    }
+
+    fn error_count(&self) -> usize {
+        self.errors
+    }
+}
+
+impl UserData for Indicator {
+    fn add_fields<'lua, F: mlua::UserDataFields<'lua, Self>>(fields: &mut F) {
+        fields.add_field_method_get("value", |_, ind| Ok(ind.value()));
+        fields.add_field_method_get("ready", |_, ind| Ok(ind.ready()));
+    }
+
+    fn add_methods<'lua, M: mlua::UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method_mut("update", |_, ind, x: f64| Ok(ind.update(x)));
+        methods.add_method_mut("reset", |_, ind, ()| {
+            ind.reset();
+            Ok(())
+        });
+    }
+}
+
+fn checked_period(period: i64) -> mlua::Result<usize> {
+    if period < 1 {
+        return Err(mlua::Error::RuntimeError(format!(
+            "indicator period must be >= 1, got {}",
+            period
+        )));
+    }
+    Ok(period as usize)
+}
+
+/// Register the same helper functions the Rhai backend provides.
+fn register_helpers(
+    lua: &Lua,
+    name: &str,
+    history: &SnapshotHistory,
+    schedule: &Schedule,
+    log_context: &LogContext,
+) -> mlua::Result<()> {
+    let globals = lua.globals();
+
+    globals.set(
+        "bid",
+        lua.create_function(|lua, (side, price, shares): (String, f64, f64)| {
+            let t = lua.create_table()?;
+            t.set("type", "bid")?;
+            t.set("side", side)?;
+            t.set("price", price)?;
+            t.set("shares", shares)?;
+            Ok(t)
+        })?,
+    )?;
+    globals.set(
+        "take",
+        lua.create_function(|lua, (side, shares, max_price): (String, f64, f64)| {
+            let t = lua.create_table()?;
+            t.set("type", "take")?;
+            t.set("side", side)?;
+            t.set("shares", shares)?;
+            t.set("max_price", max_price)?;
+            Ok(t)
+        })?,
+    )?;
+    globals.set(
+        "amend",
+        lua.create_function(|lua, (side, price, shares): (String, f64, f64)| {
+            let t = lua.create_table()?;
+            t.set("type", "amend")?;
+            t.set("side", side)?;
+            t.set("price", price)?;
+            t.set("shares", shares)?;
+            Ok(t)
+        })?,
+    )?;
+    globals.set(
+        "sell",
+        lua.create_function(|lua, (side, price, shares): (String, f64, f64)| {
+            let t = lua.create_table()?;
+            t.set("type", "sell")?;
+            t.set("side", side)?;
+            t.set("price", price)?;
+            t.set("shares", shares)?;
+            Ok(t)
+        })?,
+    )?;
+    globals.set(
+        "cancel",
+        lua.create_function(|lua, side: String| {
+            let t = lua.create_table()?;
+            t.set("type", "cancel")?;
+            t.set("side", side)?;
+            Ok(t)
+        })?,
+    )?;
+
+    globals.set(
+        "yes_depth_at",
+        lua.create_function(|_, (snap, price): (Table, f64)| depth_at(&snap, "yes_depth", price))?,
+    )?;
+    globals.set(
+        "no_depth_at",
+        lua.create_function(|_, (snap, price): (Table, f64)| depth_at(&snap, "no_depth", price))?,
+    )?;
+
+    globals.set(
+        "yes_ask_depth_at",
+        lua.create_function(|_, (snap, price): (Table, f64)| {
+            Ok(ask_depth_at_levels(&depth_levels(&snap, "yes_ask_depth")?, price))
+        })?,
+    )?;
+    globals.set(
+        "no_ask_depth_at",
+        lua.create_function(|_, (snap, price): (Table, f64)| {
+            Ok(ask_depth_at_levels(&depth_levels(&snap, "no_ask_depth")?, price))
+        })?,
+    )?;
+
+    let h = history.clone();
+    globals.set(
+        "history",
+        lua.create_function(move |lua, i: i64| dynamic_to_lua(lua, &h.get(i)))?,
+    )?;
+    let h = history.clone();
+    globals.set(
+        "oracle_ago",
+        lua.create_function(move |lua, secs: f64| dynamic_to_lua(lua, &h.oracle_ago(secs)))?,
+    )?;
+
+    globals.set(
+        "remaining_ms",
+        lua.create_function(|_, snap: Table| {
+            Ok(window_remaining_ms(snap.get("duration_ms")?, snap.get("offset_ms")?))
+        })?,
+    )?;
+    globals.set(
+        "elapsed_fraction",
+        lua.create_function(|_, snap: Table| {
+            Ok(window_elapsed_fraction(snap.get("duration_ms")?, snap.get("offset_ms")?))
+        })?,
+    )?;
+    let sch = schedule.clone();
+    globals.set(
+        "schedule",
+        lua.create_function(move |_, target: f64| Ok(sch.fire(target.round() as i64)))?,
+    )?;
+
+    for (fn_name, level) in [("log", tracing::Level::INFO), ("debug", tracing::Level::DEBUG)] {
+        let (ctx, script) = (log_context.clone(), name.to_string());
+        globals.set(
+            fn_name,
+            lua.create_function(move |_, msg: Value| {
+                let text = match msg {
+                    Value::String(s) => s.to_string_lossy().into_owned(),
+                    other => format!("{:?}", other),
+                };
+                ctx.log(&script, level, &text);
+                Ok(())
+            })?,
+        )?;
+    }
+
+    globals.set(
+        "sma",
+        lua.create_function(|_, n: i64| checked_period(n).map(Indicator::sma))?,
+    )?;
+    globals.set(
+        "ema",
+        lua.create_function(|_, n: i64| checked_period(n).map(Indicator::ema))?,
+    )?;
+    globals.set(
+        "rolling_std",
+        lua.create_function(|_, n: i64| checked_period(n).map(Indicator::rolling_std))?,
+    )?;
+    globals.set(
+        "max_so_far",
+        lua.create_function(|_, ()| Ok(Indicator::max_so_far()))?,
+    )?;
+    globals.set(
+        "min_so_far",
+        lua.create_function(|_, ()| Ok(Indicator::min_so_far()))?,
+    )?;
+    globals.set(
+        "bps_since_open",
+        lua.create_function(|_, ()| Ok(Indicator::bps_since_open()))?,
+    )?;
+    Ok(())
+}
+
+/// Look up cumulative bid depth at a price from a snap's depth array.
+fn depth_at(snap: &Table, key: &str, price: f64) -> mlua::Result<f64> {
+    Ok(depth_at_levels(&depth_levels(snap, key)?, price))
+}
+
+/// Extract `(price, size)` pairs from a depth array in a snap table.
+fn depth_levels(snap: &Table, key: &str) -> mlua::Result<Vec<(f64, f64)>> {
+    Ok(match snap.get::<_, Option<Table>>(key)? {
+        Some(arr) => arr
+            .sequence_values::<Table>()
+            .filter_map(|level| {
+                let level = level.ok()?;
+                Some((level.get("price").ok()?, level.get("size").ok()?))
+            })
+            .collect(),
+        None => vec![],
+    })
+}
+
+/// Convert a Rhai value (snap maps, params, history entries) into Lua.
+fn dynamic_to_lua<'lua>(lua: &'lua Lua, value: &Dynamic) -> mlua::Result<Value<'lua>> {
+    if value.is_unit() {
+        return Ok(Value::Nil);
+    }
+    if let Ok(f) = value.as_float() {
+        return Ok(Value::Number(f));
+    }
+    if let Ok(i) = value.as_int() {
+        return Ok(Value::Integer(i));
+    }
+    if let Ok(b) = value.as_bool() {
+        return Ok(Value::Boolean(b));
+    }
+    if let Some(map) = value.read_lock::<Map>() {
+        let t = lua.create_table()?;
+        for (key, v) in map.iter() {
+            t.set(key.as_str(), dynamic_to_lua(lua, v)?)?;
+        }
+        return Ok(Value::Table(t));
+    }
+    if let Some(arr) = value.read_lock::<rhai::Array>() {
+        let t = lua.create_table()?;
+        for v in arr.iter() {
+            t.push(dynamic_to_lua(lua, v)?)?;
+        }
+        return Ok(Value::Table(t));
+    }
+    Ok(Value::String(lua.create_string(value.to_string())?))
+}
+
+/// Parse the table returned by `on_tick`; malformed entries are skipped.
+fn parse_actions(value: Value) -> Vec<Action> {
+    let Value::Table(list) = value else {
+        return vec![];
+    };
+    list.sequence_values::<Table>()
+        .filter_map(|item| parse_one_action(&item.ok()?))
+        .collect()
+}
+
+fn parse_one_action(t: &Table) -> Option<Action> {
+    let action_type: String = t.get("type").ok()?;
+    let side: String = t.get("side").ok()?;
+
+    let side = match side.to_ascii_lowercase().as_str() {
+        "yes" => Side::Yes,
+        "no" => Side::No,
+        _ => return None,
+    };
+
+    match action_type.as_str() {
+        "bid" => Some(Action::PlaceBid {
+            side,
+            price: Price::from_f64(t.get("price").ok()?),
+            shares: Shares::from_f64(t.get("shares").ok()?),
+        }),
+        "take" => Some(Action::TakeAsk {
+            side,
+            shares: Shares::from_f64(t.get("shares").ok()?),
+            max_price: Price::from_f64(t.get("max_price").ok()?),
+        }),
+        "cancel" => Some(Action::Cancel { side }),
+        "amend" => Some(Action::Amend {
+            side,
+            new_price: Price::from_f64(t.get("price").ok()?),
+            new_shares: Shares::from_f64(t.get("shares").ok()?),
+        }),
+        "sell" => Some(Action::Sell {
+            side,
+            price: Price::from_f64(t.get("price").ok()?),
+            shares: Shares::from_f64(t.get("shares").ok()?),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::make_test_snap;
+
+    const ONE_SHOT: &str = r#"
+local acted = false
+local fast = sma(2)
+
+function on_tick(snap)
+    fast:update(snap.oracle_price)
+    log("tick " .. snap.offset_ms)
+    if acted or snap.orders.yes.placed or snap.orders.yes.fill_time_ms ~= nil then
+        return {}
+    end
+    acted = true
+    return {
+        bid("yes", snap.yes_bid, SHARES),
+        bid("no", MY_PRICE, yes_depth_at(snap, 0.49) + no_ask_depth_at(snap, 0.51)),
+        cancel("no"),
+    }
+end
+
+function on_reset()
+    acted = false
+    fast:reset()
+end
+"#;
+
+    #[test]
+    fn test_lua_strategy_mirrors_rhai_helpers() {
+        let params = [("MY_PRICE".to_string(), Dynamic::from(0.45))];
+        let mut strat =
+            LuaStrategy::from_source_with_params("one_shot", ONE_SHOT, 10.0, 0.49, &params)
+                .unwrap();
+        let snap = make_test_snap(0, Some(50000.0), 500.0, 500.0);
+
+        let actions = strat.on_tick(&snap);
+        assert_eq!(actions.len(), 3);
+        assert!(matches!(
+            actions[0],
+            Action::PlaceBid { side: Side::Yes, price, shares } if price == Price::from_f64(0.49) && shares == Shares::from_f64(10.0)
+        ));
+        assert!(matches!(
+            actions[1],
+            Action::PlaceBid { side: Side::No, price, shares } if price == Price::from_f64(0.45) && shares == Shares::from_f64(1000.0)
+        ));
+        assert!(matches!(actions[2], Action::Cancel { side: Side::No }));
+        assert!(strat.on_tick(&snap).is_empty());
+
+        strat.reset();
+        assert_eq!(strat.on_tick(&snap).len(), 3);
+    }
+
+    #[test]
+    fn test_lua_history_and_errors() {
+        let source = r#"
+function on_tick(snap)
+    local prev = history(1)
+    if prev == nil then return {} end
+    if snap.offset_ms == 2000 then error("boom") end
+    if not schedule(1000) or remaining_ms(snap) ~= 0 then return {} end
+    return { bid("yes", prev.yes_bid, SHARES) }
+end
+function on_reset() end
+"#;
+        let mut strat = LuaStrategy::from_source("hist", source, 10.0, 0.49).unwrap();
+        assert!(strat.on_tick(&make_test_snap(0, Some(1.0), 500.0, 500.0)).is_empty());
+        assert_eq!(strat.on_tick(&make_test_snap(1000, Some(1.0), 500.0, 500.0)).len(), 1);
+        assert!(strat.on_tick(&make_test_snap(1500, Some(1.0), 500.0, 500.0)).is_empty());
+        assert!(strat.on_tick(&make_test_snap(2000, Some(1.0), 500.0, 500.0)).is_empty());
+        assert_eq!(strat.error_count(), 1);
+        assert!(strat.abort_reason().is_none());
+
+        let mut strict = LuaStrategy::from_source("hist", source, 10.0, 0.49)
+            .unwrap()
+            .with_strict(true);
+        strict.on_tick(&make_test_snap(1000, Some(1.0), 500.0, 500.0));
+        strict.on_tick(&make_test_snap(2000, Some(1.0), 500.0, 500.0));
+        assert!(strict.abort_reason().unwrap().contains("boom"));
+    }
+
+    #[test]
+    fn test_lua_budget_and_load_errors() {
+        let source = "function on_tick(snap) while true do end end\nfunction on_reset() end";
+        let mut strat = LuaStrategy::from_source("loop", source, 10.0, 0.49).unwrap();
+        let snap = make_test_snap(0, Some(50000.0), 500.0, 500.0);
+        assert!(strat.on_tick(&snap).is_empty());
+        assert!(strat.abort_reason().unwrap().contains("operation limit"));
+        strat.reset();
+        assert!(strat.abort_reason().is_none());
+
+        let err = LuaStrategy::from_source("x", "function on_reset() end", 10.0, 0.49).unwrap_err();
+        assert!(err.to_string().contains("on_tick"));
+        let err = LuaStrategy::from_source("x", "while true do end", 10.0, 0.49).unwrap_err();
+        assert!(err.to_string().contains("operation limit"), "{}", err);
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::VecDeque;
+
+use crate::strategies::Strategy;
+use crate::types::{Action, BookSnapshot, Market, Price, Shares, Side};
+
+/// Late-window mean reversion: bid into a side whose best bid collapses in
+/// the final seconds of a window.
+///
+/// Panic flow near the close tends to hit one side of the book: its best
+/// bid drops sharply while the other side's bid doesn't rise to match, so
+/// YES bid + NO bid falls. Inside the last `trigger_before_close_ms` of
+/// the window, when a side's best bid is at least `min_drop_bps` below its
+/// high over the last `drop_lookback_ms` and the combined bid fell with
+/// it, the strategy joins the collapsed bid once, expecting it to recover.
+///
+/// Whether those bids ever fill is exactly what the fill model decides, so
+/// this is a stress test as much as a strategy.
+pub struct MeanRevert {
+    shares: Shares,
+    /// Least drop of a best bid from its recent high, in bps.
+    min_drop_bps: f64,
+    /// How far back the recent high reaches, in ms.
+    drop_lookback_ms: i64,
+    /// How long before the close to start looking, in ms.
+    trigger_before_close_ms: i64,
+    /// Window length in ms when the market's own isn't known.
+    window_duration_ms: i64,
+    /// The current market's window length in ms, from its metadata.
+    market_duration_ms: Option<i64>,
+    /// `(offset_ms, yes bid, no bid)` over the lookback, oldest first.
+    history: VecDeque<(i64, f64, f64)>,
+    /// Drop in bps behind the bid this window placed.
+    drop_bps: Option<f64>,
+    acted: bool,
+}
+
+impl MeanRevert {
+    pub fn new(
+        shares: f64,
+        min_drop_bps: f64,
+        drop_lookback_ms: i64,
+        trigger_before_close_ms: i64,
+        window_duration_ms: i64,
+    ) -> Self {
+        Self {
+            shares: Shares::from_f64(shares),
+            min_drop_bps,
+            drop_lookback_ms,
+            trigger_before_close_ms,
+            window_duration_ms,
+            market_duration_ms: None,
+            history: VecDeque::new(),
+            drop_bps: None,
+            acted: false,
+        }
+    }
+
+    /// The drop in bps on `side` from its high over the lookback, if the
+    /// other side's bid rose by less than it fell since then.
+    fn one_sided_drop(&self, side: Side, bid: f64, other_bid: f64) -> Option<f64> {
+        let pick = |&(_, yes, no): &(i64, f64, f64)| match side {
+            Side::Yes => (yes, no),
+            Side::No => (no, yes),
+        };
+        let (high, other_then) = self
+            .history
+            .iter()
+            .map(pick)
+            .max_by(|a, b| a.0.total_cmp(&b.0))?;
+        if high <= 0.0 || other_bid - other_then >= high - bid {
+            return None;
+        }
+        Some((high - bid) / high * 10_000.0)
+    }
+}
+
+impl Strategy for MeanRevert {
+    fn name(&self) -> &str {
+        "mean_revert"
+    }
+
+    fn description(&self) -> &str {
+        "Late-window mean reversion: bid into a side whose bid collapses one-sidedly near the close"
+    }
+
+    fn on_market(&mut self, market: &Market) {
+        self.market_duration_ms = (market.duration_secs > 0).then_some(market.duration_secs * 1000);
+    }
+
+    fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action> {
+        if self.acted {
+            return vec![];
+        }
+        let (Some(yes_bid), Some(no_bid)) = (snap.yes.best_bid, snap.no.best_bid) else {
+            return vec![];
+        };
+        let now = snap.offset_ms.ms();
+        while self.history.front().is_some_and(|&(t, _, _)| now - t > self.drop_lookback_ms) {
+            self.history.pop_front();
+        }
+
+        let duration_ms = self.market_duration_ms.unwrap_or(self.window_duration_ms);
+        let mut actions = vec![];
+        if now >= duration_ms - self.trigger_before_close_ms {
+            let drops = [
+                (Side::Yes, yes_bid, self.one_sided_drop(Side::Yes, yes_bid, no_bid)),
+                (Side::No, no_bid, self.one_sided_drop(Side::No, no_bid, yes_bid)),
+            ];
+            let best = drops
+                .into_iter()
+                .filter_map(|(side, bid, drop)| Some((side, bid, drop?)))
+                .filter(|&(_, _, drop)| drop >= self.min_drop_bps)
+                .max_by(|a, b| a.2.total_cmp(&b.2));
+            if let Some((side, bid, drop)) = best {
+                self.acted = true;
+                self.drop_bps = Some(drop);
+                actions.push(Action::PlaceBid {
+                    side,
+                    price: Price::from_f64(bid),
+                    shares: self.shares,
+                });
+            }
+        }
+        self.history.push_back((now, yes_bid, no_bid));
+        actions
+    }
+
+    fn reset(&mut self) {
+        self.market_duration_ms = None;
+        self.history.clear();
+        self.drop_bps = None;
+        self.acted = false;
+    }
+
+    fn signal_strength(&self) -> Option<f64> {
+        self.drop_bps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::make_test_snap;
+    use crate::types::SideState;
+    use std::sync::Arc;
+
+    fn snap(offset_ms: i64, yes_bid: f64, no_bid: f64) -> BookSnapshot {
+        let mut snap = make_test_snap(offset_ms, None, 500.0, 500.0);
+        snap.yes = Arc::new(SideState { best_bid: Some(yes_bid), ..(*snap.yes).clone() });
+        snap.no = Arc::new(SideState { best_bid: Some(no_bid), ..(*snap.no).clone() });
+        snap
+    }
+
+    fn make_strat() -> MeanRevert {
+        // 1000 bps within 5s, in the last 15s of a 60s window.
+        MeanRevert::new(10.0, 1_000.0, 5_000, 15_000, 60_000)
+    }
+
+    #[test]
+    fn bids_into_a_one_sided_collapse() {
+        let mut strat = make_strat();
+        assert!(strat.on_tick(&snap(44_000, 0.60, 0.38)).is_empty());
+        let actions = strat.on_tick(&snap(47_000, 0.50, 0.40));
+        assert_eq!(
+            actions,
+            vec![Action::PlaceBid {
+                side: Side::Yes,
+                price: Price::from_f64(0.50),
+                shares: Shares::from_f64(10.0),
+            }]
+        );
+        assert!((strat.signal_strength().unwrap() - 1_666.7).abs() < 0.1);
+        assert!(strat.on_tick(&snap(48_000, 0.40, 0.40)).is_empty(), "acts once");
+
+        strat.reset();
+        assert_eq!(strat.signal_strength(), None);
+    }
+
+    #[test]
+    fn ignores_repricing_and_early_or_slow_drops() {
+        // The other side absorbed the move: the market repriced, no panic.
+        let mut strat = make_strat();
+        strat.on_tick(&snap(44_000, 0.60, 0.38));
+        assert!(strat.on_tick(&snap(47_000, 0.50, 0.48)).is_empty());
+
+        // Same collapse before the last 15s.
+        let mut strat = make_strat();
+        strat.on_tick(&snap(30_000, 0.60, 0.38));
+        assert!(strat.on_tick(&snap(33_000, 0.50, 0.40)).is_empty());
+
+        // The high is older than the 5s lookback.
+        let mut strat = make_strat();
+        strat.on_tick(&snap(40_000, 0.60, 0.38));
+        strat.on_tick(&snap(46_000, 0.56, 0.38));
+        assert!(strat.on_tick(&snap(47_000, 0.54, 0.38)).is_empty());
+    }
+
+    #[test]
+    fn final_window_follows_the_market_duration() {
+        let mut strat = make_strat();
+        strat.on_market(&Market {
+            id: "test".into(),
+            platform: crate::types::Platform::Polymarket,
+            description: String::new(),
+            category: "btc".into(),
+            open_ts: crate::types::UnixSecs::new(1_700_000_000),
+            close_ts: crate::types::UnixSecs::new(1_700_000_300),
+            duration_secs: 300,
+            outcome: None,
+            outcome_ts: None,
+        });
+        strat.on_tick(&snap(44_000, 0.38, 0.60));
+        assert!(strat.on_tick(&snap(47_000, 0.40, 0.50)).is_empty());
+        strat.on_tick(&snap(288_000, 0.38, 0.60));
+        let actions = strat.on_tick(&snap(290_000, 0.40, 0.50));
+        assert!(matches!(actions[..], [Action::PlaceBid { side: Side::No, .. }]), "{:?}", actions);
+    }
+}
@@ -1,13 +1,34 @@
 pub mod depth;
+pub mod factory;
 pub mod fade;
 pub mod gabagool;
+#[cfg(feature = "scripting")]
+pub mod indicators;
+#[cfg(feature = "lua")]
+pub mod lua;
 pub mod last_15s;
+pub mod mean_revert;
 pub mod momentum;
+pub mod params;
+#[cfg(feature = "plugins")]
+pub mod plugin;
 pub mod post_cancel;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod recorder;
+pub mod regime;
+#[cfg(feature = "scripting")]
 pub mod scripted;
+pub mod sniper;
 pub mod spread_arb;
+pub mod subprocess;
 
-use crate::types::{Action, BookSnapshot};
+#[cfg(feature = "scripting")]
+pub use factory::ScriptFactory;
+pub use factory::{BuiltinFactory, FnFactory, StrategyFactory, StrategySpec};
+pub use params::{param_keys, StrategyParams};
+
+use crate::types::{Action, BookSnapshot, Market, OrderState};
 
 /// Trait for trading strategies.
 ///
@@ -18,40 +39,91 @@ pub trait Strategy: Send {
     fn name(&self) -> &str;
     fn description(&self) -> &str;
 
+    /// Called at the start of each market window, before `on_market_open`,
+    /// with the window's metadata.
+    fn on_market(&mut self, _market: &Market) {}
+
     /// Called once on the first snapshot of a market window.
     fn on_market_open(&mut self, _snap: &BookSnapshot) {}
 
+    /// Called before each `on_tick` with the current simulated order state
+    /// (fills for this tick already applied).
+    fn on_order_update(&mut self, _orders: &OrderState) {}
+
     /// Called on each tick. Returns a list of actions to execute.
     fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action>;
 
     /// Reset internal state between market windows.
     fn reset(&mut self);
+
+    /// Set when the strategy can no longer run (e.g. a script blew its
+    /// execution budget). The engine then cancels resting orders and stops
+    /// the window.
+    fn abort_reason(&self) -> Option<&str> {
+        None
+    }
+
+    /// Runtime errors the strategy recovered from in the current window
+    /// (e.g. a script callback that raised and was treated as a no-op).
+    fn error_count(&self) -> usize {
+        0
+    }
+
+    /// How strong the signal behind this window's call was, in the
+    /// strategy's own units (the momentum strategies report the oracle
+    /// move in bps); `None` before it calls a side or if it doesn't rate
+    /// its signals. `pf signals` groups accuracy by it.
+    fn signal_strength(&self) -> Option<f64> {
+        None
+    }
 }
 
-/// Create a strategy by name with the given parameters.
-pub fn create_strategy(
-    name: &str,
-    bid_price: f64,
-    shares: f64,
-    min_bps: f64,
-) -> Option<Box<dyn Strategy>> {
+/// When the signal strategies (momentum, post_cancel, depth) read their
+/// signal, in ms from the open.
+pub const DEFAULT_SIGNAL_OFFSET_MS: i64 = 90_000;
+
+/// Create a strategy by name from `params` (see [`param_keys`] for the
+/// ones each reads). `fade` needs signals computed over the whole market
+/// history first, so it isn't built here; see [`fade::compute_fade_signals`].
+pub fn create_strategy(name: &str, params: &StrategyParams) -> Option<Box<dyn Strategy>> {
+    let StrategyParams {
+        bid_price,
+        shares,
+        min_bps,
+        signal_offset_ms,
+        ..
+    } = *params;
     match name {
         "spread_arb" => Some(Box::new(spread_arb::NaiveSpreadArb::new(bid_price, shares))),
         "momentum" => Some(Box::new(momentum::MomentumSignal::new(
-            bid_price, shares, min_bps, 90_000,
+            bid_price, shares, min_bps, signal_offset_ms,
         ))),
         "post_cancel" => Some(Box::new(post_cancel::PostBothCancelLoser::new(
-            bid_price, shares, min_bps, 90_000,
+            bid_price, shares, min_bps, signal_offset_ms,
         ))),
         "depth" => Some(Box::new(depth::DepthMomentum::new(
-            bid_price, shares, min_bps, 90_000,
+            bid_price, shares, min_bps, signal_offset_ms,
         ))),
         "last_15s" => Some(Box::new(last_15s::Last15Seconds::new(
-            shares, 0.98, 900_000,
+            shares,
+            params.min_bid,
+            params.trigger_before_close_ms,
+            params.window_duration_ms,
         ))),
         "gabagool" => Some(Box::new(gabagool::Gabagool::new(
-            shares, 0.99,
+            shares,
+            params.max_combined,
+            params.max_completion_combined,
+            params.completion_window_ms,
+        ))),
+        "mean_revert" => Some(Box::new(mean_revert::MeanRevert::new(
+            shares,
+            params.min_drop_bps,
+            params.drop_lookback_ms,
+            params.trigger_before_close_ms,
+            params.window_duration_ms,
         ))),
+        "sniper" => Some(Box::new(sniper::QueueSniper::new(bid_price, shares, params.max_queue_ahead))),
         _ => None,
     }
 }
@@ -65,7 +137,9 @@ pub fn list_strategies() -> Vec<(&'static str, &'static str)> {
         ("depth", "Depth + momentum: like momentum but also requires orderbook depth agreement"),
         ("fade", "Fade momentum: bet against streaks of consecutive same-direction candles"),
         ("last_15s", "Last 15 Seconds: buy the side bid at 98c+ in the final 15 seconds"),
-        ("gabagool", "Gabagool combined-price arb: buy YES+NO at different times when combined bid < $1.00"),
+        ("gabagool", "Gabagool combined-price arb: buy YES+NO at different times when combined bid < $1.00, unwinding unpaired legs"),
+        ("mean_revert", "Late-window mean reversion: bid into a side whose bid collapses one-sidedly near the close"),
+        ("sniper", "Queue-aware sniper: bid each side only while the estimated queue ahead is short"),
     ]
 }
 
@@ -81,36 +155,37 @@ pub(crate) fn make_test_snap(
     yes_depth: f64,
     no_depth: f64,
 ) -> BookSnapshot {
-    use crate::types::{PriceLevel, SideState};
+    use crate::types::{OffsetMs, PriceLevel, SideState};
+    use std::sync::Arc;
 
     BookSnapshot {
-        market_id: "test-market".to_string(),
-        offset_ms,
+        market_id: "test-market".into(),
+        offset_ms: OffsetMs::new(offset_ms),
         timestamp_ms: 1_700_000_000_000 + offset_ms,
-        yes: SideState {
+        yes: Arc::new(SideState {
             best_bid: Some(0.49),
             best_bid_size: Some(yes_depth),
             best_ask: Some(0.51),
             best_ask_size: Some(100.0),
-            depth: vec![PriceLevel {
+            depth: smallvec::smallvec![PriceLevel {
                 price: 0.49,
                 cumulative_size: yes_depth,
             }],
             total_bid_depth: yes_depth,
             total_ask_depth: 100.0,
-        },
-        no: SideState {
+        }),
+        no: Arc::new(SideState {
             best_bid: Some(0.49),
             best_bid_size: Some(no_depth),
             best_ask: Some(0.51),
             best_ask_size: Some(100.0),
-            depth: vec![PriceLevel {
+            depth: smallvec::smallvec![PriceLevel {
                 price: 0.49,
                 cumulative_size: no_depth,
             }],
             total_bid_depth: no_depth,
             total_ask_depth: 100.0,
-        },
+        }),
         reference_price: None,
         oracle_price,
     }
@@ -1,5 +1,5 @@
 use crate::strategies::Strategy;
-use crate::types::{Action, BookSnapshot, Side};
+use crate::types::{Action, BookSnapshot, Price, Shares, Side};
 
 /// Momentum signal strategy: wait for oracle price movement, then bet on
 /// the predicted winner.
@@ -8,22 +8,25 @@ use crate::types::{Action, BookSnapshot, Side};
 /// momentum_bps = (current - open) / open * 10000. If strong enough,
 /// places a single bid on the predicted winning side.
 pub struct MomentumSignal {
-    bid_price: f64,
-    shares: f64,
+    bid_price: Price,
+    shares: Shares,
     min_bps: f64,
     signal_offset_ms: i64,
     open_oracle: Option<f64>,
+    /// |momentum| in bps behind the side this window backed.
+    signal_bps: Option<f64>,
     acted: bool,
 }
 
 impl MomentumSignal {
     pub fn new(bid_price: f64, shares: f64, min_bps: f64, signal_offset_ms: i64) -> Self {
         Self {
-            bid_price,
-            shares,
+            bid_price: Price::from_f64(bid_price),
+            shares: Shares::from_f64(shares),
             min_bps,
             signal_offset_ms,
             open_oracle: None,
+            signal_bps: None,
             acted: false,
         }
     }
@@ -68,6 +71,7 @@ impl Strategy for MomentumSignal {
         } else {
             Side::No
         };
+        self.signal_bps = Some(momentum_bps.abs());
 
         vec![Action::PlaceBid {
             side,
@@ -79,6 +83,11 @@ impl Strategy for MomentumSignal {
     fn reset(&mut self) {
         self.open_oracle = None;
         self.acted = false;
+        self.signal_bps = None;
+    }
+
+    fn signal_strength(&self) -> Option<f64> {
+        self.signal_bps
     }
 }
 
@@ -114,6 +123,9 @@ mod tests {
             Action::PlaceBid { side, .. } => assert_eq!(*side, Side::Yes),
             _ => panic!("expected PlaceBid"),
         }
+        assert!((strat.signal_strength().unwrap() - 40.0).abs() < 1e-9);
+        strat.reset();
+        assert_eq!(strat.signal_strength(), None);
     }
 
     #[test]
@@ -143,6 +155,7 @@ mod tests {
         let snap = make_test_snap(90_000, Some(50025.0), 500.0, 500.0);
         let actions = strat.on_tick(&snap);
         assert!(actions.is_empty());
+        assert_eq!(strat.signal_strength(), None);
     }
 
     #[test]
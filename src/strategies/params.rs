@@ -0,0 +1,221 @@
+//! Settings for the built-in strategies.
+//!
+//! Every built-in strategy is built from one [`StrategyParams`]: the bid
+//! price and size most of them share, plus the knobs only some of them
+//! have (the last_15s trigger, gabagool's combined-price ceilings and
+//! completion window, fade's streak lengths, mean_revert's drop size,
+//! sniper's queue limit). [`param_keys`] lists which keys a strategy reads, and
+//! [`StrategyParams::apply`] sets them from `KEY=VALUE` strings such as
+//! `pf run --param`.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::DEFAULT_SIGNAL_OFFSET_MS;
+
+/// Settings for [`create_strategy`](super::create_strategy); each strategy
+/// reads the subset [`param_keys`] lists and ignores the rest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StrategyParams {
+    /// Limit price for strategies that bid a fixed price.
+    pub bid_price: f64,
+    pub shares: f64,
+    /// Oracle move from the open, in basis points, that counts as a signal.
+    pub min_bps: f64,
+    /// When the signal strategies read their signal, in ms from the open.
+    pub signal_offset_ms: i64,
+    /// last_15s: lowest best bid worth buying.
+    pub min_bid: f64,
+    /// last_15s, mean_revert: how long before the close to start looking,
+    /// in ms.
+    pub trigger_before_close_ms: i64,
+    /// last_15s, mean_revert: window length, in ms, for markets whose metadata doesn't
+    /// give one.
+    pub window_duration_ms: i64,
+    /// gabagool: highest combined YES + NO bid worth buying.
+    pub max_combined: f64,
+    /// gabagool: highest first-leg price plus second-leg bid worth
+    /// completing the pair at.
+    pub max_completion_combined: f64,
+    /// gabagool: how long after the first leg fills to wait for the
+    /// second before unwinding, in ms.
+    pub completion_window_ms: i64,
+    /// fade: shortest streak of same-direction windows to fade.
+    pub min_streak: usize,
+    /// fade: longest streak to fade.
+    pub max_streak: usize,
+    /// mean_revert: least fall of a best bid from its recent high, in bps.
+    pub min_drop_bps: f64,
+    /// mean_revert: how far back the recent high reaches, in ms.
+    pub drop_lookback_ms: i64,
+    /// sniper: most shares ahead in the queue worth joining behind.
+    pub max_queue_ahead: f64,
+}
+
+impl Default for StrategyParams {
+    fn default() -> Self {
+        Self {
+            bid_price: 0.49,
+            shares: 10.0,
+            min_bps: 5.0,
+            signal_offset_ms: DEFAULT_SIGNAL_OFFSET_MS,
+            min_bid: 0.98,
+            trigger_before_close_ms: 15_000,
+            window_duration_ms: 900_000,
+            max_combined: 0.99,
+            max_completion_combined: 1.0,
+            completion_window_ms: 60_000,
+            min_streak: 3,
+            max_streak: 6,
+            min_drop_bps: 1_000.0,
+            drop_lookback_ms: 5_000,
+            max_queue_ahead: 100.0,
+        }
+    }
+}
+
+/// The [`StrategyParams`] keys strategy `name` reads; empty for names that
+/// aren't built in.
+pub fn param_keys(name: &str) -> &'static [&'static str] {
+    match name {
+        "spread_arb" => &["bid_price", "shares"],
+        "momentum" | "post_cancel" | "depth" => &["bid_price", "shares", "min_bps", "signal_offset_ms"],
+        "fade" => &["bid_price", "shares", "min_streak", "max_streak"],
+        "last_15s" => &["shares", "min_bid", "trigger_before_close_ms", "window_duration_ms"],
+        "gabagool" => &["shares", "max_combined", "max_completion_combined", "completion_window_ms"],
+        "mean_revert" => &[
+            "shares",
+            "min_drop_bps",
+            "drop_lookback_ms",
+            "trigger_before_close_ms",
+            "window_duration_ms",
+        ],
+        "sniper" => &["bid_price", "shares", "max_queue_ahead"],
+        _ => &[],
+    }
+}
+
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    value
+        .trim()
+        .parse()
+        .with_context(|| format!("bad value `{}` for param `{}`", value, key))
+}
+
+impl StrategyParams {
+    /// Set `key` from its string form.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "bid_price" => self.bid_price = parse(key, value)?,
+            "shares" => self.shares = parse(key, value)?,
+            "min_bps" => self.min_bps = parse(key, value)?,
+            "signal_offset_ms" => self.signal_offset_ms = parse(key, value)?,
+            "min_bid" => self.min_bid = parse(key, value)?,
+            "trigger_before_close_ms" => self.trigger_before_close_ms = parse(key, value)?,
+            "window_duration_ms" => self.window_duration_ms = parse(key, value)?,
+            "max_combined" => self.max_combined = parse(key, value)?,
+            "max_completion_combined" => self.max_completion_combined = parse(key, value)?,
+            "completion_window_ms" => self.completion_window_ms = parse(key, value)?,
+            "min_streak" => self.min_streak = parse(key, value)?,
+            "max_streak" => self.max_streak = parse(key, value)?,
+            "min_drop_bps" => self.min_drop_bps = parse(key, value)?,
+            "drop_lookback_ms" => self.drop_lookback_ms = parse(key, value)?,
+            "max_queue_ahead" => self.max_queue_ahead = parse(key, value)?,
+            _ => bail!("unknown strategy param `{}`", key),
+        }
+        Ok(())
+    }
+
+    /// The value of `key`, if it's a param.
+    pub fn get(&self, key: &str) -> Option<Value> {
+        Some(match key {
+            "bid_price" => self.bid_price.into(),
+            "shares" => self.shares.into(),
+            "min_bps" => self.min_bps.into(),
+            "signal_offset_ms" => self.signal_offset_ms.into(),
+            "min_bid" => self.min_bid.into(),
+            "trigger_before_close_ms" => self.trigger_before_close_ms.into(),
+            "window_duration_ms" => self.window_duration_ms.into(),
+            "max_combined" => self.max_combined.into(),
+            "max_completion_combined" => self.max_completion_combined.into(),
+            "completion_window_ms" => self.completion_window_ms.into(),
+            "min_streak" => self.min_streak.into(),
+            "max_streak" => self.max_streak.into(),
+            "min_drop_bps" => self.min_drop_bps.into(),
+            "drop_lookback_ms" => self.drop_lookback_ms.into(),
+            "max_queue_ahead" => self.max_queue_ahead.into(),
+            _ => return None,
+        })
+    }
+
+    /// Apply `KEY=VALUE` assignments for `strategy`, rejecting keys it
+    /// doesn't read.
+    pub fn apply(&mut self, strategy: &str, assignments: &[String]) -> Result<()> {
+        let keys = param_keys(strategy);
+        for assignment in assignments {
+            let (key, value) = assignment
+                .split_once('=')
+                .with_context(|| format!("expected KEY=VALUE, got `{}`", assignment))?;
+            let key = key.trim();
+            if !keys.contains(&key) {
+                bail!(
+                    "{} has no param `{}`; it takes {}",
+                    strategy,
+                    key,
+                    keys.join(", ")
+                );
+            }
+            self.set(key, value)?;
+        }
+        Ok(())
+    }
+
+    /// The params `strategy` reads, by key.
+    pub fn to_map(&self, strategy: &str) -> BTreeMap<String, Value> {
+        param_keys(strategy)
+            .iter()
+            .filter_map(|&key| Some((key.to_string(), self.get(key)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let mut params = StrategyParams::default();
+        params
+            .apply("last_15s", &["min_bid=0.95".to_string(), " trigger_before_close_ms = 30000".to_string()])
+            .unwrap();
+        assert_eq!(params.min_bid, 0.95);
+        assert_eq!(params.trigger_before_close_ms, 30_000);
+
+        let err = params.apply("momentum", &["max_combined=0.98".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("momentum has no param `max_combined`"), "{}", err);
+        assert!(params.apply("gabagool", &["max_combined=lots".to_string()]).is_err());
+        assert!(params.apply("gabagool", &["max_combined".to_string()]).is_err());
+        assert_eq!(params.max_combined, 0.99);
+    }
+
+    #[test]
+    fn test_every_key_round_trips() {
+        let params = StrategyParams::default();
+        for name in ["spread_arb", "momentum", "post_cancel", "depth", "fade", "last_15s", "gabagool", "mean_revert", "sniper"] {
+            for (key, value) in params.to_map(name) {
+                let mut copy = params.clone();
+                copy.set(&key, &value.to_string()).unwrap();
+                assert_eq!(copy, params, "{}", key);
+            }
+        }
+    }
+}
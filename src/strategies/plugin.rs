@@ -0,0 +1,277 @@
+//! Compiled Rust strategies loaded from shared libraries (`plugins` feature).
+//!
+//! A plugin is a `cdylib` crate that depends on `phantomfill` (with the
+//! `plugins` feature) and exports a constructor with [`declare_plugin!`]:
+//!
+//! ```ignore
+//! phantomfill::declare_plugin!(|params| Box::new(MyStrategy::new(params.shares)));
+//! ```
+//!
+//! Rust has no stable ABI, so a plugin must be built with the same rustc and
+//! phantomfill version as the `pf` that loads it. [`PluginLibrary::open`]
+//! checks both through plain C symbols before touching any Rust types.
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use libloading::Library;
+
+use crate::strategies::Strategy;
+use crate::types::{Action, BookSnapshot, Market, OrderState};
+
+/// Bumped whenever the exported symbols or their signatures change.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// phantomfill and rustc versions the crate was built with, NUL-terminated.
+/// Host and plugin must agree exactly.
+pub const BUILD_ID: &str = concat!(
+    "phantomfill ",
+    env!("CARGO_PKG_VERSION"),
+    "; ",
+    env!("PHANTOMFILL_RUSTC_VERSION"),
+    "\0"
+);
+
+const ABI_SYMBOL: &[u8] = b"phantomfill_plugin_abi_version";
+const BUILD_ID_SYMBOL: &[u8] = b"phantomfill_plugin_build_id";
+const CREATE_SYMBOL: &[u8] = b"phantomfill_plugin_create";
+
+type CreateFn = unsafe extern "C" fn(*const PluginParams) -> *mut Box<dyn Strategy>;
+
+/// Settings handed to a plugin's constructor.
+#[derive(Debug, Clone, Default)]
+pub struct PluginParams {
+    pub shares: f64,
+    pub bid_price: f64,
+    /// `--plugin-param KEY=VALUE` pairs, in order.
+    pub params: Vec<(String, String)>,
+}
+
+impl PluginParams {
+    /// Value of the last `KEY=VALUE` parameter with this key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Export a strategy constructor from a plugin crate.
+///
+/// Takes a `fn(&PluginParams) -> Box<dyn Strategy>` (a non-capturing closure
+/// works) and defines the C symbols [`PluginLibrary`] looks for. A panic in
+/// the constructor is reported to the host as a load error.
+#[macro_export]
+macro_rules! declare_plugin {
+    ($create:expr) => {
+        #[no_mangle]
+        pub extern "C" fn phantomfill_plugin_abi_version() -> u32 {
+            $crate::strategies::plugin::PLUGIN_ABI_VERSION
+        }
+
+        #[no_mangle]
+        pub extern "C" fn phantomfill_plugin_build_id() -> *const ::std::os::raw::c_char {
+            $crate::strategies::plugin::BUILD_ID.as_ptr().cast()
+        }
+
+        /// # Safety
+        ///
+        /// `params` must point to a live `PluginParams`.
+        #[no_mangle]
+        pub unsafe extern "C" fn phantomfill_plugin_create(
+            params: *const $crate::strategies::plugin::PluginParams,
+        ) -> *mut ::std::boxed::Box<dyn $crate::strategies::Strategy> {
+            let create: fn(
+                &$crate::strategies::plugin::PluginParams,
+            ) -> ::std::boxed::Box<dyn $crate::strategies::Strategy> = $create;
+            let params = &*params;
+            match ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| create(params))) {
+                Ok(strategy) => ::std::boxed::Box::into_raw(::std::boxed::Box::new(strategy)),
+                Err(_) => ::std::ptr::null_mut(),
+            }
+        }
+    };
+}
+
+/// An opened, version-checked plugin library.
+#[derive(Clone)]
+pub struct PluginLibrary {
+    lib: Arc<Library>,
+    path: PathBuf,
+}
+
+impl std::fmt::Debug for PluginLibrary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PluginLibrary")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl PluginLibrary {
+    /// Load a shared library and check it was built against this exact
+    /// phantomfill and rustc.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        // A bare file name would make dlopen search the library path instead.
+        let resolved = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        // SAFETY: loading runs the library's initializers; plugins are
+        // trusted code the user asked to load.
+        let lib = unsafe { Library::new(&resolved) }
+            .with_context(|| format!("failed to load plugin: {}", path.display()))?;
+
+        // SAFETY: both symbols are plain C functions with these signatures in
+        // every ABI version.
+        let (abi, build_id) = unsafe {
+            let abi = lib
+                .get::<extern "C" fn() -> u32>(ABI_SYMBOL)
+                .with_context(|| {
+                    format!(
+                        "{} is not a phantomfill plugin (no declare_plugin! exports)",
+                        path.display()
+                    )
+                })?();
+            let build_id = lib
+                .get::<extern "C" fn() -> *const c_char>(BUILD_ID_SYMBOL)
+                .with_context(|| format!("{}: missing plugin build id", path.display()))?();
+            (abi, CStr::from_ptr(build_id).to_string_lossy().into_owned())
+        };
+        check_compatible(abi, &build_id)
+            .with_context(|| format!("incompatible plugin: {}", path.display()))?;
+
+        // SAFETY: signature guaranteed by the matching ABI version.
+        unsafe { lib.get::<CreateFn>(CREATE_SYMBOL) }
+            .with_context(|| format!("{}: missing plugin constructor", path.display()))?;
+
+        Ok(Self {
+            lib: Arc::new(lib),
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Construct a new strategy instance from the plugin.
+    pub fn create(&self, params: &PluginParams) -> anyhow::Result<Box<dyn Strategy>> {
+        // SAFETY: `open` verified the ABI version and that host and plugin
+        // share a compiler and phantomfill version, so the Rust types on
+        // either side of the call have the same layout and allocator.
+        let inner = unsafe {
+            let create = self.lib.get::<CreateFn>(CREATE_SYMBOL)?;
+            let raw = create(params);
+            if raw.is_null() {
+                bail!("plugin constructor panicked: {}", self.path.display());
+            }
+            *Box::from_raw(raw)
+        };
+        Ok(Box::new(PluginStrategy {
+            inner,
+            _lib: self.lib.clone(),
+        }))
+    }
+}
+
+/// Check a plugin's exported versions against the host's.
+fn check_compatible(abi: u32, build_id: &str) -> anyhow::Result<()> {
+    if abi != PLUGIN_ABI_VERSION {
+        bail!(
+            "plugin ABI version {} does not match pf's {}; rebuild the plugin",
+            abi,
+            PLUGIN_ABI_VERSION
+        );
+    }
+    let host = BUILD_ID.trim_end_matches('\0');
+    if build_id != host {
+        bail!(
+            "plugin was built with `{}` but pf with `{}`; rebuild the plugin with the same toolchain and phantomfill version",
+            build_id,
+            host
+        );
+    }
+    Ok(())
+}
+
+/// A plugin strategy that keeps its library loaded for as long as it lives.
+/// Field order matters: the strategy (whose code lives in the library) must
+/// drop before the library handle.
+struct PluginStrategy {
+    inner: Box<dyn Strategy>,
+    _lib: Arc<Library>,
+}
+
+impl Strategy for PluginStrategy {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn on_market(&mut self, market: &Market) {
+        self.inner.on_market(market);
+    }
+
+    fn on_market_open(&mut self, snap: &BookSnapshot) {
+        self.inner.on_market_open(snap);
+    }
+
+    fn on_order_update(&mut self, orders: &OrderState) {
+        self.inner.on_order_update(orders);
+    }
+
+    fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action> {
+        self.inner.on_tick(snap)
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn abort_reason(&self) -> Option<&str> {
+        self.inner.abort_reason()
+    }
+
+    fn error_count(&self) -> usize {
+        self.inner.error_count()
+    }
+
+    fn signal_strength(&self) -> Option<f64> {
+        self.inner.signal_strength()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_compatible() {
+        let host = BUILD_ID.trim_end_matches('\0');
+        assert!(host.starts_with("phantomfill "));
+        assert!(check_compatible(PLUGIN_ABI_VERSION, host).is_ok());
+
+        let err = check_compatible(PLUGIN_ABI_VERSION + 1, host).unwrap_err();
+        assert!(err.to_string().contains("ABI version"));
+        let err = check_compatible(PLUGIN_ABI_VERSION, "phantomfill 0.0.1; rustc 1.0.0").unwrap_err();
+        assert!(err.to_string().contains("rebuild the plugin"));
+    }
+
+    #[test]
+    fn test_open_rejects_non_plugins() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("not_a_plugin.so");
+        std::fs::write(&path, b"not an ELF file").unwrap();
+        let err = PluginLibrary::open(&path).unwrap_err();
+        assert!(format!("{:#}", err).contains("failed to load plugin"));
+
+        let params = PluginParams {
+            params: vec![("K".into(), "1".into()), ("K".into(), "2".into())],
+            ..PluginParams::default()
+        };
+        assert_eq!(params.get("K"), Some("2"));
+        assert_eq!(params.get("missing"), None);
+    }
+}
@@ -1,5 +1,5 @@
 use crate::strategies::Strategy;
-use crate::types::{Action, BookSnapshot, Side};
+use crate::types::{Action, BookSnapshot, Price, Shares, Side};
 
 /// Post both + cancel loser strategy.
 ///
@@ -10,11 +10,13 @@ use crate::types::{Action, BookSnapshot, Side};
 ///
 /// This is the consensus "best viable" strategy from expert analysis.
 pub struct PostBothCancelLoser {
-    bid_price: f64,
-    shares: f64,
+    bid_price: Price,
+    shares: Shares,
     min_bps: f64,
     signal_offset_ms: i64,
     open_oracle: Option<f64>,
+    /// |momentum| in bps behind the side this window backed.
+    signal_bps: Option<f64>,
     placed: bool,
     signal_acted: bool,
 }
@@ -22,11 +24,12 @@ pub struct PostBothCancelLoser {
 impl PostBothCancelLoser {
     pub fn new(bid_price: f64, shares: f64, min_bps: f64, signal_offset_ms: i64) -> Self {
         Self {
-            bid_price,
-            shares,
+            bid_price: Price::from_f64(bid_price),
+            shares: Shares::from_f64(shares),
             min_bps,
             signal_offset_ms,
             open_oracle: None,
+            signal_bps: None,
             placed: false,
             signal_acted: false,
         }
@@ -89,6 +92,7 @@ impl Strategy for PostBothCancelLoser {
             actions.push(Action::Cancel { side: Side::No });
         } else {
             // Cancel the predicted loser
+            self.signal_bps = Some(momentum_bps.abs());
             let loser = if momentum_bps > 0.0 {
                 Side::No
             } else {
@@ -104,6 +108,11 @@ impl Strategy for PostBothCancelLoser {
         self.open_oracle = None;
         self.placed = false;
         self.signal_acted = false;
+        self.signal_bps = None;
+    }
+
+    fn signal_strength(&self) -> Option<f64> {
+        self.signal_bps
     }
 }
 
@@ -166,6 +175,7 @@ mod tests {
             Action::Cancel { side } => assert_eq!(*side, Side::Yes),
             _ => panic!("expected Cancel"),
         }
+        assert!((strat.signal_strength().unwrap() - 40.0).abs() < 1e-9);
     }
 
     #[test]
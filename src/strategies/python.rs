@@ -0,0 +1,329 @@
+//! Strategies written in Python, loaded through PyO3 (`python` feature).
+//!
+//! A strategy file defines one class with `on_tick(self, snap)` returning a
+//! list of actions and `reset(self)`; `on_market_open(self, snap)` is
+//! optional. `snap` is a dict with the same keys as the Rhai snap map, and
+//! the module sees `SHARES`, `BID_PRICE`, `bid(side, price, shares)` and
+//! `cancel(side)` as globals.
+
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use pyo3::IntoPyObjectExt;
+use rhai::{Dynamic, Map};
+
+use crate::strategies::scripted::snap_to_dynamic;
+use crate::strategies::Strategy;
+use crate::types::{Action, BookSnapshot, Market, OrderState, Price, Shares, Side};
+
+/// Helpers injected into the strategy module before it runs.
+const PRELUDE: &str = r#"
+def bid(side, price, shares):
+    return {"type": "bid", "side": side, "price": float(price), "shares": float(shares)}
+
+def take(side, shares, max_price):
+    return {"type": "take", "side": side, "shares": float(shares), "max_price": float(max_price)}
+
+def cancel(side):
+    return {"type": "cancel", "side": side}
+
+def amend(side, price, shares):
+    return {"type": "amend", "side": side, "price": float(price), "shares": float(shares)}
+
+def sell(side, price, shares):
+    return {"type": "sell", "side": side, "price": float(price), "shares": float(shares)}
+"#;
+
+/// A strategy backed by an instance of a Python class.
+pub struct PyStrategy {
+    instance: Py<PyAny>,
+    name: String,
+    script_path: String,
+    has_on_market_open: bool,
+    orders: OrderState,
+    market: Option<Market>,
+    errors: usize,
+}
+
+impl std::fmt::Debug for PyStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PyStrategy")
+            .field("name", &self.name)
+            .field("script_path", &self.script_path)
+            .finish()
+    }
+}
+
+impl PyStrategy {
+    /// Load a strategy from a `.py` file.
+    pub fn from_file(path: &Path, shares: f64, bid_price: f64) -> anyhow::Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read Python strategy: {}", path.display()))?;
+
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let mut strategy = Self::from_source(&name, &source, shares, bid_price)
+            .with_context(|| format!("failed to load Python strategy: {}", path.display()))?;
+        strategy.script_path = path.display().to_string();
+        Ok(strategy)
+    }
+
+    /// Load a strategy from source code (useful for testing).
+    pub fn from_source(
+        name: &str,
+        source: &str,
+        shares: f64,
+        bid_price: f64,
+    ) -> anyhow::Result<Self> {
+        Python::attach(|py| {
+            let load = || -> PyResult<Vec<Bound<'_, PyAny>>> {
+                let builtins = py.import("builtins")?;
+                let globals = PyDict::new(py);
+                globals.set_item("__name__", name)?;
+                globals.set_item("SHARES", shares)?;
+                globals.set_item("BID_PRICE", bid_price)?;
+                builtins.call_method1("exec", (PRELUDE, &globals))?;
+
+                let code = builtins.call_method1("compile", (source, name, "exec"))?;
+                builtins.call_method1("exec", (code, &globals))?;
+
+                // Strategy classes: defined in this file, with on_tick and reset.
+                let mut classes = Vec::new();
+                for (_, value) in globals.iter() {
+                    let is_class = value.is_instance_of::<pyo3::types::PyType>();
+                    let is_local = value
+                        .getattr("__module__")
+                        .and_then(|m| m.extract::<String>())
+                        .is_ok_and(|m| m == name);
+                    if is_class && is_local && value.hasattr("on_tick")? && value.hasattr("reset")? {
+                        classes.push(value);
+                    }
+                }
+                Ok(classes)
+            };
+            let classes = load().map_err(|e| anyhow::anyhow!(describe_error(py, &e)))?;
+
+            let class = match classes.as_slice() {
+                [class] => class,
+                [] => bail!("Python strategy must define a class with `on_tick(self, snap)` and `reset(self)`"),
+                _ => bail!("Python strategy defines {} strategy classes; expected one", classes.len()),
+            };
+            let instance = class
+                .call0()
+                .map_err(|e| anyhow::anyhow!("initialization error: {}", describe_error(py, &e)))?;
+            let has_on_market_open = instance.hasattr("on_market_open")?;
+
+            Ok(Self {
+                instance: instance.unbind(),
+                name: name.to_string(),
+                script_path: name.to_string(),
+                has_on_market_open,
+                orders: OrderState::default(),
+                market: None,
+                errors: 0,
+            })
+        })
+    }
+
+    /// Call a method on the strategy instance; errors are logged, counted
+    /// and treated as returning nothing.
+    fn call(&mut self, method: &str, snap: Option<&BookSnapshot>) -> Vec<Action> {
+        let snap_map = snap.map(|s| snap_to_dynamic(s, &self.orders, self.market.as_ref()));
+        Python::attach(|py| {
+            let instance = self.instance.bind(py);
+            let result = match snap_map {
+                Some(ref map) => dynamic_to_py(py, map)
+                    .and_then(|arg| instance.call_method1(method, (arg,))),
+                None => instance.call_method0(method),
+            };
+            match result {
+                Ok(value) => parse_actions(&value),
+                Err(e) => {
+                    self.errors += 1;
+                    tracing::warn!(script = %self.name, "{} error: {}", method, describe_error(py, &e));
+                    vec![]
+                }
+            }
+        })
+    }
+}
+
+impl Strategy for PyStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.script_path
+    }
+
+    fn on_market(&mut self, market: &Market) {
+        self.market = Some(market.clone());
+    }
+
+    fn on_market_open(&mut self, snap: &BookSnapshot) {
+        if self.has_on_market_open {
+            self.call("on_market_open", Some(snap));
+        }
+    }
+
+    fn on_order_update(&mut self, orders: &OrderState) {
+        self.orders = orders.clone();
+    }
+
+    fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action> {
+        self.call("on_tick", Some(snap))
+    }
+
+    fn reset(&mut self) {
+        self.orders = OrderState::default();
+        self.errors = 0;
+        self.call("reset", None);
+    }
+
+    fn error_count(&self) -> usize {
+        self.errors
+    }
+}
+
+/// Render a Python exception with its traceback, if any.
+fn describe_error(py: Python<'_>, err: &PyErr) -> String {
+    match err.traceback(py).and_then(|tb| tb.format().ok()) {
+        Some(tb) => format!("{}{}", tb, err),
+        None => err.to_string(),
+    }
+}
+
+/// Convert a Rhai snap map (see `snap_to_dynamic`) into Python objects.
+fn dynamic_to_py<'py>(py: Python<'py>, value: &Dynamic) -> PyResult<Bound<'py, PyAny>> {
+    if value.is_unit() {
+        return Ok(py.None().into_bound(py));
+    }
+    if let Ok(f) = value.as_float() {
+        return f.into_bound_py_any(py);
+    }
+    if let Ok(i) = value.as_int() {
+        return i.into_bound_py_any(py);
+    }
+    if let Ok(b) = value.as_bool() {
+        return b.into_bound_py_any(py);
+    }
+    if let Some(map) = value.read_lock::<Map>() {
+        let dict = PyDict::new(py);
+        for (key, v) in map.iter() {
+            dict.set_item(key.as_str(), dynamic_to_py(py, v)?)?;
+        }
+        return Ok(dict.into_any());
+    }
+    if let Some(arr) = value.read_lock::<rhai::Array>() {
+        let items = arr
+            .iter()
+            .map(|v| dynamic_to_py(py, v))
+            .collect::<PyResult<Vec<_>>>()?;
+        return Ok(PyList::new(py, items)?.into_any());
+    }
+    value.to_string().into_bound_py_any(py)
+}
+
+/// Parse the list returned by `on_tick`; malformed entries are skipped.
+fn parse_actions(value: &Bound<'_, PyAny>) -> Vec<Action> {
+    let Ok(list) = value.cast::<PyList>() else {
+        return vec![];
+    };
+    list.iter().filter_map(|item| parse_one_action(&item)).collect()
+}
+
+fn parse_one_action(item: &Bound<'_, PyAny>) -> Option<Action> {
+    let dict = item.cast::<PyDict>().ok()?;
+    let get_str = |key: &str| -> Option<String> { dict.get_item(key).ok()??.extract().ok() };
+    let get_f64 = |key: &str| -> Option<f64> { dict.get_item(key).ok()??.extract().ok() };
+
+    let side = match get_str("side")?.to_ascii_lowercase().as_str() {
+        "yes" => Side::Yes,
+        "no" => Side::No,
+        _ => return None,
+    };
+
+    match get_str("type")?.as_str() {
+        "bid" => Some(Action::PlaceBid {
+            side,
+            price: Price::from_f64(get_f64("price")?),
+            shares: Shares::from_f64(get_f64("shares")?),
+        }),
+        "take" => Some(Action::TakeAsk {
+            side,
+            shares: Shares::from_f64(get_f64("shares")?),
+            max_price: Price::from_f64(get_f64("max_price")?),
+        }),
+        "cancel" => Some(Action::Cancel { side }),
+        "amend" => Some(Action::Amend {
+            side,
+            new_price: Price::from_f64(get_f64("price")?),
+            new_shares: Shares::from_f64(get_f64("shares")?),
+        }),
+        "sell" => Some(Action::Sell {
+            side,
+            price: Price::from_f64(get_f64("price")?),
+            shares: Shares::from_f64(get_f64("shares")?),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::make_test_snap;
+
+    const SOURCE: &str = r#"
+class OneShot:
+    def __init__(self):
+        self.acted = False
+
+    def on_tick(self, snap):
+        if self.acted or snap["orders"]["yes"]["placed"]:
+            return []
+        self.acted = True
+        return [bid("yes", snap["yes_bid"], SHARES), cancel("no")]
+
+    def reset(self):
+        self.acted = False
+"#;
+
+    #[test]
+    fn test_python_strategy_ticks_and_resets() {
+        let mut strat = PyStrategy::from_source("one_shot", SOURCE, 10.0, 0.49).unwrap();
+        let snap = make_test_snap(0, Some(50000.0), 500.0, 500.0);
+
+        let actions = strat.on_tick(&snap);
+        assert_eq!(actions.len(), 2);
+        assert!(matches!(
+            actions[0],
+            Action::PlaceBid { side: Side::Yes, price, shares } if price == Price::from_f64(0.49) && shares == Shares::from_f64(10.0)
+        ));
+        assert!(matches!(actions[1], Action::Cancel { side: Side::No }));
+        assert!(strat.on_tick(&snap).is_empty());
+
+        strat.reset();
+        assert_eq!(strat.on_tick(&snap).len(), 2);
+    }
+
+    #[test]
+    fn test_python_errors_counted_and_load_errors_reported() {
+        let source = "class Bad:\n    def on_tick(self, snap):\n        return snap['nope']\n    def reset(self):\n        pass\n";
+        let mut strat = PyStrategy::from_source("bad", source, 10.0, 0.49).unwrap();
+        let snap = make_test_snap(0, Some(50000.0), 500.0, 500.0);
+        assert!(strat.on_tick(&snap).is_empty());
+        assert_eq!(strat.error_count(), 1);
+
+        let err = PyStrategy::from_source("none", "x = 1\n", 10.0, 0.49).unwrap_err();
+        assert!(err.to_string().contains("must define a class"));
+        let err = PyStrategy::from_source("syntax", "class (:\n", 10.0, 0.49).unwrap_err();
+        assert!(err.to_string().contains("SyntaxError"), "{}", err);
+    }
+}
@@ -0,0 +1,87 @@
+//! A pass-through strategy wrapper that reports what the strategy does.
+//!
+//! [`Recorder`] forwards every call to the strategy it wraps and hands each
+//! tick's snapshot and returned actions to a callback, which is how
+//! `pf run --trace-market` logs a window and `pf script test` checks a
+//! scenario.
+
+use crate::strategies::Strategy;
+use crate::types::{Action, BookSnapshot, Market, OrderState};
+
+/// Passes calls through to `inner`, calling `on_tick` with every tick's
+/// snapshot and the actions the strategy returned for it (possibly none).
+pub struct Recorder<'a, F> {
+    inner: &'a mut dyn Strategy,
+    on_tick: F,
+}
+
+impl<'a, F: FnMut(&BookSnapshot, &[Action])> Recorder<'a, F> {
+    pub fn new(inner: &'a mut dyn Strategy, on_tick: F) -> Self {
+        Self { inner, on_tick }
+    }
+}
+
+impl<F: FnMut(&BookSnapshot, &[Action]) + Send> Strategy for Recorder<'_, F> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn on_market(&mut self, market: &Market) {
+        self.inner.on_market(market);
+    }
+
+    fn on_market_open(&mut self, snap: &BookSnapshot) {
+        self.inner.on_market_open(snap);
+    }
+
+    fn on_order_update(&mut self, orders: &OrderState) {
+        self.inner.on_order_update(orders);
+    }
+
+    fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action> {
+        let actions = self.inner.on_tick(snap);
+        (self.on_tick)(snap, &actions);
+        actions
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn abort_reason(&self) -> Option<&str> {
+        self.inner.abort_reason()
+    }
+
+    fn error_count(&self) -> usize {
+        self.inner.error_count()
+    }
+
+    fn signal_strength(&self) -> Option<f64> {
+        self.inner.signal_strength()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::{create_strategy, make_test_snap, StrategyParams};
+
+    #[test]
+    fn test_recorder_passes_actions_through() {
+        let mut inner = create_strategy("spread_arb", &StrategyParams::default()).unwrap();
+        let mut seen = Vec::new();
+        let mut recorder = Recorder::new(inner.as_mut(), |snap, actions| {
+            seen.push((snap.offset_ms.ms(), actions.len()));
+        });
+        assert_eq!(recorder.name(), "spread_arb");
+
+        let first = recorder.on_tick(&make_test_snap(0, None, 100.0, 100.0));
+        let second = recorder.on_tick(&make_test_snap(1_000, None, 100.0, 100.0));
+        assert_eq!(seen, vec![(0, first.len()), (1_000, second.len())]);
+        assert!(!first.is_empty());
+    }
+}
@@ -0,0 +1,265 @@
+//! Regime filters: run a strategy only in the market conditions it was
+//! built for.
+//!
+//! [`RegimeFilter`] wraps any strategy and drops its entries (bids and
+//! takes) outside a range of UTC hours, or while the oracle has been too
+//! quiet before the signal. Cancels, amends and sells always pass, so a
+//! position the filter let in can still be managed.
+
+use anyhow::{bail, Context, Result};
+
+use crate::strategies::Strategy;
+use crate::types::{Action, BookSnapshot, Market, OrderState};
+
+/// When [`RegimeFilter`] lets entries through.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RegimeConfig {
+    /// UTC hours `(start, end)`: entries are allowed from `start:00` up to
+    /// `end:00`. Wraps past midnight when `start > end`, e.g. `(22, 4)`.
+    pub hours: Option<(u32, u32)>,
+    /// Least realized volatility of the oracle, in bps, between the open
+    /// and `signal_offset_ms`. Entries wait for the signal time while set.
+    pub min_realized_vol_bps: Option<f64>,
+    /// Where the volatility measurement ends, in ms from the open.
+    pub signal_offset_ms: i64,
+}
+
+impl RegimeConfig {
+    /// Whether the config filters anything.
+    pub fn is_active(&self) -> bool {
+        self.hours.is_some() || self.min_realized_vol_bps.is_some()
+    }
+
+    /// Whether an entry at `hour` (UTC) is inside [`hours`](Self::hours).
+    pub fn allows_hour(&self, hour: u32) -> bool {
+        match self.hours {
+            None => true,
+            Some((start, end)) if start <= end => start <= hour && hour < end,
+            Some((start, end)) => hour >= start || hour < end,
+        }
+    }
+}
+
+/// Parse an hour range like `"13-21"` (13:00 to 21:00 UTC), `"22-4"` or
+/// `"0-24"`.
+pub fn parse_hours(range: &str) -> Result<(u32, u32)> {
+    let (start, end) = range
+        .split_once('-')
+        .with_context(|| format!("expected an hour range like 13-21, got `{}`", range))?;
+    let hour = |h: &str, max: u32| -> Result<u32> {
+        let h: u32 = h.trim().parse().with_context(|| format!("bad hour `{}`", h.trim()))?;
+        if h > max {
+            bail!("hour {} is past {}", h, max);
+        }
+        Ok(h)
+    };
+    let (start, end) = (hour(start, 23)?, hour(end, 24)?);
+    if start == end {
+        bail!("hour range `{}` is empty", range);
+    }
+    Ok((start, end))
+}
+
+/// Realized volatility of `prices`, in bps: the square root of the summed
+/// squared log returns between consecutive prices. Unlike the standard
+/// deviation of tick returns it doesn't grow with how often the oracle
+/// was sampled.
+pub fn realized_vol_bps(prices: &[f64]) -> f64 {
+    let variance: f64 = prices
+        .windows(2)
+        .filter(|w| w[0] > 0.0 && w[1] > 0.0)
+        .map(|w| (w[1] / w[0]).ln().powi(2))
+        .sum();
+    variance.sqrt() * 10_000.0
+}
+
+/// A strategy whose entries only go through in the regime `config`
+/// describes; see the [module docs](self).
+pub struct RegimeFilter {
+    inner: Box<dyn Strategy>,
+    config: RegimeConfig,
+    /// Oracle prices up to the signal time, one per change.
+    oracle: Vec<f64>,
+    /// Entries dropped this window.
+    suppressed: usize,
+}
+
+impl RegimeFilter {
+    pub fn new(inner: Box<dyn Strategy>, config: RegimeConfig) -> Self {
+        Self {
+            inner,
+            config,
+            oracle: Vec::new(),
+            suppressed: 0,
+        }
+    }
+
+    /// Entries dropped in the current window.
+    pub fn suppressed(&self) -> usize {
+        self.suppressed
+    }
+
+    /// Whether an entry on `snap` is in the regime.
+    fn allows(&self, snap: &BookSnapshot) -> bool {
+        if self.config.hours.is_some() {
+            let Some(time) = chrono::DateTime::from_timestamp_millis(snap.timestamp_ms) else {
+                return false;
+            };
+            if !self.config.allows_hour(chrono::Timelike::hour(&time)) {
+                return false;
+            }
+        }
+        match self.config.min_realized_vol_bps {
+            Some(min) => snap.offset_ms >= self.config.signal_offset_ms && realized_vol_bps(&self.oracle) >= min,
+            None => true,
+        }
+    }
+}
+
+impl Strategy for RegimeFilter {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn on_market(&mut self, market: &Market) {
+        self.inner.on_market(market);
+    }
+
+    fn on_market_open(&mut self, snap: &BookSnapshot) {
+        self.inner.on_market_open(snap);
+    }
+
+    fn on_order_update(&mut self, orders: &OrderState) {
+        self.inner.on_order_update(orders);
+    }
+
+    fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action> {
+        if snap.offset_ms <= self.config.signal_offset_ms {
+            if let Some(price) = snap.oracle_price {
+                if self.oracle.last() != Some(&price) {
+                    self.oracle.push(price);
+                }
+            }
+        }
+        let mut actions = self.inner.on_tick(snap);
+        if !actions.is_empty() && !self.allows(snap) {
+            let before = actions.len();
+            actions.retain(|a| !matches!(a, Action::PlaceBid { .. } | Action::TakeAsk { .. }));
+            self.suppressed += before - actions.len();
+        }
+        actions
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.oracle.clear();
+        self.suppressed = 0;
+    }
+
+    fn abort_reason(&self) -> Option<&str> {
+        self.inner.abort_reason()
+    }
+
+    fn error_count(&self) -> usize {
+        self.inner.error_count()
+    }
+
+    fn signal_strength(&self) -> Option<f64> {
+        self.inner.signal_strength()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::make_test_snap;
+    use crate::strategies::spread_arb::NaiveSpreadArb;
+    use crate::types::Side;
+
+    /// Bids YES on every tick.
+    struct AlwaysBid;
+
+    impl Strategy for AlwaysBid {
+        fn name(&self) -> &str {
+            "always"
+        }
+
+        fn description(&self) -> &str {
+            ""
+        }
+
+        fn on_tick(&mut self, _snap: &BookSnapshot) -> Vec<Action> {
+            vec![
+                Action::Cancel { side: Side::No },
+                Action::PlaceBid {
+                    side: Side::Yes,
+                    price: crate::types::Price::from_f64(0.49),
+                    shares: crate::types::Shares::from_f64(10.0),
+                },
+            ]
+        }
+
+        fn reset(&mut self) {}
+    }
+
+    #[test]
+    fn test_parse_hours() {
+        assert_eq!(parse_hours("13-21").unwrap(), (13, 21));
+        assert_eq!(parse_hours(" 22 - 4 ").unwrap(), (22, 4));
+        assert_eq!(parse_hours("0-24").unwrap(), (0, 24));
+        assert!(RegimeConfig { hours: Some((0, 24)), ..Default::default() }.allows_hour(23));
+        assert!(parse_hours("13").is_err());
+        assert!(parse_hours("13-25").is_err());
+        assert!(parse_hours("5-5").is_err());
+
+        let night = RegimeConfig { hours: Some((22, 4)), ..Default::default() };
+        assert!(night.allows_hour(23) && night.allows_hour(3));
+        assert!(!night.allows_hour(4) && !night.allows_hour(12));
+    }
+
+    #[test]
+    fn test_hours_filter_entries_only() {
+        // make_test_snap's clock starts at 2023-11-14 22:13:20 UTC.
+        let config = RegimeConfig { hours: Some((13, 21)), ..Default::default() };
+        let mut filter = RegimeFilter::new(Box::new(AlwaysBid), config);
+        let actions = filter.on_tick(&make_test_snap(0, Some(100.0), 500.0, 500.0));
+        assert!(matches!(actions[..], [Action::Cancel { .. }]), "{:?}", actions);
+        assert_eq!(filter.suppressed(), 1);
+
+        let config = RegimeConfig { hours: Some((22, 23)), ..Default::default() };
+        let mut filter = RegimeFilter::new(Box::new(NaiveSpreadArb::new(0.49, 10.0)), config);
+        assert_eq!(filter.on_tick(&make_test_snap(0, Some(100.0), 500.0, 500.0)).len(), 2);
+        filter.reset();
+        assert_eq!(filter.suppressed(), 0);
+    }
+
+    #[test]
+    fn test_volatility_filter_waits_for_the_signal() {
+        let config = RegimeConfig {
+            min_realized_vol_bps: Some(15.0),
+            signal_offset_ms: 2_000,
+            ..Default::default()
+        };
+        let run = |oracle: [f64; 4]| {
+            let mut filter = RegimeFilter::new(Box::new(AlwaysBid), config);
+            let bids: Vec<bool> = oracle
+                .iter()
+                .enumerate()
+                .map(|(i, &px)| {
+                    let actions = filter.on_tick(&make_test_snap(i as i64 * 1_000, Some(px), 500.0, 500.0));
+                    actions.iter().any(|a| matches!(a, Action::PlaceBid { .. }))
+                })
+                .collect();
+            bids
+        };
+        // 10 bps then 15 bps: realized vol 18 bps by the signal at 2s.
+        assert_eq!(run([100.0, 100.1, 99.95, 90.0]), vec![false, false, true, true]);
+        // 10 bps then nothing; the move after the signal doesn't count.
+        assert_eq!(run([100.0, 100.1, 100.1, 90.0]), vec![false, false, false, false]);
+        assert!((realized_vol_bps(&[100.0, 100.1, 99.95]) - 18.0).abs() < 0.1);
+    }
+}
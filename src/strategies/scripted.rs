@@ -1,18 +1,441 @@
+use std::collections::{HashSet, VecDeque};
 use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+#[cfg(feature = "wasm")]
+use web_time::Instant;
 
 use anyhow::{bail, Context};
-use rhai::{Dynamic, Engine, Map, Scope, AST};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FuncArgs, FuncRegistration, Map, Scope, AST};
+
+use crate::strategies::{indicators, Strategy};
+use crate::types::{
+    Action, BookSnapshot, Market, MarketId, OffsetMs, OrderState, Price, PriceLevel, Shares, Side, SideOrderState,
+    SideState, SimOrder,
+};
+
+/// Execution budget for each script callback.
+///
+/// A callback that exceeds any limit is aborted and the strategy reports an
+/// [`abort_reason`](Strategy::abort_reason), so the engine stops the window
+/// instead of hanging on a runaway script.
+#[derive(Debug, Clone)]
+pub struct ScriptLimits {
+    /// Maximum Rhai operations per callback (0 = unlimited).
+    pub max_operations: u64,
+    /// Maximum function call nesting depth.
+    pub max_call_levels: usize,
+    /// Wall-clock budget per callback.
+    pub timeout: Duration,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            max_operations: 1_000_000,
+            max_call_levels: 64,
+            timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Deadline shared with the engine's progress callback.
+#[derive(Clone, Default)]
+struct Deadline(Arc<Mutex<Option<Instant>>>);
+
+impl Deadline {
+    fn arm(&self, timeout: Duration) {
+        *self.0.lock().unwrap() = Some(Instant::now() + timeout);
+    }
+
+    fn disarm(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+
+    fn expired(&self) -> bool {
+        self.0.lock().unwrap().is_some_and(|d| Instant::now() >= d)
+    }
+}
+
+/// Market and offset of the callback in progress, attached to script log lines.
+#[derive(Clone, Default)]
+pub(crate) struct LogContext(Arc<Mutex<(MarketId, i64)>>);
+
+impl LogContext {
+    pub(crate) fn set(&self, snap: &BookSnapshot) {
+        let mut guard = self.0.lock().unwrap();
+        guard.0 = snap.market_id.clone();
+        guard.1 = snap.offset_ms.ms();
+    }
+
+    pub(crate) fn log(&self, script: &str, level: tracing::Level, msg: &str) {
+        let guard = self.0.lock().unwrap();
+        let (market, offset_ms) = (&guard.0, guard.1);
+        if level == tracing::Level::DEBUG {
+            tracing::debug!(script, market = %market, offset_ms, "{}", msg);
+        } else {
+            tracing::info!(script, market = %market, offset_ms, "{}", msg);
+        }
+    }
+}
+
+/// Default number of past snapshots kept for `history(i)` / `oracle_ago(s)`.
+pub const DEFAULT_HISTORY_LEN: usize = 300;
+
+struct HistoryEntry {
+    timestamp_ms: i64,
+    oracle_price: Option<f64>,
+    snap: Dynamic,
+}
+
+/// Ring buffer of the most recent script snapshots, shared with the helper
+/// functions registered on the engine.
+#[derive(Clone)]
+pub(crate) struct SnapshotHistory(Arc<Mutex<(usize, VecDeque<HistoryEntry>)>>);
+
+impl SnapshotHistory {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self(Arc::new(Mutex::new((capacity, VecDeque::with_capacity(capacity)))))
+    }
+
+    pub(crate) fn set_capacity(&self, capacity: usize) {
+        let mut guard = self.0.lock().unwrap();
+        let (cap, buf) = &mut *guard;
+        *cap = capacity;
+        while buf.len() > capacity {
+            buf.pop_back();
+        }
+    }
+
+    pub(crate) fn push(&self, snap: &BookSnapshot, map: &Dynamic) {
+        let mut guard = self.0.lock().unwrap();
+        let (cap, buf) = &mut *guard;
+        if *cap == 0 {
+            return;
+        }
+        if buf.len() == *cap {
+            buf.pop_back();
+        }
+        buf.push_front(HistoryEntry {
+            timestamp_ms: snap.timestamp_ms,
+            oracle_price: snap.oracle_price,
+            snap: map.clone(),
+        });
+    }
+
+    pub(crate) fn clear(&self) {
+        self.0.lock().unwrap().1.clear();
+    }
+
+    /// Snapshot `ticks_ago` ticks back (0 = current), or `()` if not held.
+    pub(crate) fn get(&self, ticks_ago: i64) -> Dynamic {
+        let guard = self.0.lock().unwrap();
+        usize::try_from(ticks_ago)
+            .ok()
+            .and_then(|i| guard.1.get(i))
+            .map_or(Dynamic::UNIT, |e| e.snap.clone())
+    }
+
+    /// Oracle price as of `seconds` before the current tick, or `()` if the
+    /// buffer does not reach back that far or the oracle was absent.
+    pub(crate) fn oracle_ago(&self, seconds: f64) -> Dynamic {
+        let guard = self.0.lock().unwrap();
+        let Some(now) = guard.1.front() else {
+            return Dynamic::UNIT;
+        };
+        let target = now.timestamp_ms - (seconds * 1000.0).round() as i64;
+        guard
+            .1
+            .iter()
+            .find(|e| e.timestamp_ms <= target)
+            .and_then(|e| e.oracle_price)
+            .map_or(Dynamic::UNIT, Dynamic::from)
+    }
+}
+
+/// Offsets already fired by `schedule(offset_ms)` in the current window,
+/// plus the offset of the tick in progress.
+#[derive(Clone, Default)]
+pub(crate) struct Schedule(Arc<Mutex<(i64, HashSet<i64>)>>);
+
+impl Schedule {
+    pub(crate) fn set(&self, offset_ms: i64) {
+        self.0.lock().unwrap().0 = offset_ms;
+    }
+
+    pub(crate) fn clear(&self) {
+        let mut guard = self.0.lock().unwrap();
+        guard.0 = 0;
+        guard.1.clear();
+    }
+
+    /// True on the first call at or after `target_ms` in this window.
+    pub(crate) fn fire(&self, target_ms: i64) -> bool {
+        let mut guard = self.0.lock().unwrap();
+        let (now, fired) = &mut *guard;
+        *now >= target_ms && fired.insert(target_ms)
+    }
+}
+
+/// The `state` map carried across windows for scripts that opt in with
+/// `let PERSIST_STATE = true;`.
+///
+/// The engine builds a fresh strategy per window, so every instance created
+/// for one run must be given the same handle; clones share the same map.
+#[derive(Clone)]
+pub struct PersistentState(Dynamic);
+
+impl Default for PersistentState {
+    fn default() -> Self {
+        Self(Dynamic::from_map(Map::new()).into_shared())
+    }
+}
+
+impl std::fmt::Debug for PersistentState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("PersistentState").field(&self.0.to_string()).finish()
+    }
+}
+
+impl PersistentState {
+    /// Forget everything, e.g. between Monte Carlo runs.
+    pub fn clear(&self) {
+        let mut shared = self.0.clone();
+        if let Some(mut value) = shared.write_lock::<Dynamic>() {
+            *value = Dynamic::from_map(Map::new());
+        };
+    }
+}
+
+/// A Rhai script compiled once, for any number of [`RhaiStrategy`]
+/// instances to start from (see [`RhaiStrategy::from_compiled`]) without
+/// reading or parsing it again.
+#[derive(Debug, Clone)]
+pub struct CompiledScript {
+    name: String,
+    ast: AST,
+    shares: f64,
+    bid_price: f64,
+    params: Vec<(String, Dynamic)>,
+}
+
+impl CompiledScript {
+    /// Compile `source` and check it defines the required callbacks. The
+    /// top level doesn't run until an instance starts.
+    pub fn new(name: &str, source: &str, shares: f64, bid_price: f64, params: &[(String, Dynamic)]) -> anyhow::Result<Self> {
+        if let Some((key, _)) = params.iter().find(|(key, _)| key == "SHARES" || key == "BID_PRICE") {
+            bail!("script parameter `{}` is reserved; use --shares/--bid-price", key);
+        }
+        let ast = ScriptEngine::new(name)
+            .engine
+            .compile(source)
+            .map_err(|e| anyhow::anyhow!("compile error: {}", e))?;
+        let script = Self {
+            name: name.to_string(),
+            ast,
+            shares,
+            bid_price,
+            params: params.to_vec(),
+        };
+
+        // Verify required functions exist
+        if !script.has_fn("on_tick") {
+            bail!("script must define an `on_tick(snap)` function");
+        }
+        if !script.has_fn("on_reset") {
+            bail!("script must define an `on_reset()` function");
+        }
+        Ok(script)
+    }
+
+    /// Compile a `.rhai` file, read once.
+    pub fn from_file(path: &Path, shares: f64, bid_price: f64, params: &[(String, Dynamic)]) -> anyhow::Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read script: {}", path.display()))?;
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "unknown".to_string());
+        Self::new(&name, &source, shares, bid_price, params)
+            .with_context(|| format!("failed to load script: {}", path.display()))
+    }
 
-use crate::strategies::Strategy;
-use crate::types::{Action, BookSnapshot, Side};
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn has_fn(&self, name: &str) -> bool {
+        self.ast.iter_functions().any(|f| f.name == name)
+    }
+}
+
+/// An engine with every script helper registered, and the handles the
+/// helpers share with the strategy running on it.
+struct ScriptEngine {
+    engine: Engine,
+    deadline: Deadline,
+    history: SnapshotHistory,
+    schedule: Schedule,
+    log_context: LogContext,
+}
+
+impl ScriptEngine {
+    fn new(name: &str) -> Self {
+        let mut engine = Engine::new();
+        engine.set_optimization_level(rhai::OptimizationLevel::Full);
+
+        // Guard against runaway scripts; the deadline is armed per callback.
+        let limits = ScriptLimits::default();
+        let deadline = Deadline::default();
+        engine.set_max_operations(limits.max_operations);
+        engine.set_max_call_levels(limits.max_call_levels);
+        let progress_deadline = deadline.clone();
+        engine.on_progress(move |ops| {
+            if ops % 1024 == 0 && progress_deadline.expired() {
+                Some("timeout".into())
+            } else {
+                None
+            }
+        });
+
+        // Register helper: bid(side, price, shares) -> action map
+        engine.register_fn("bid", |side: &str, price: f64, shares: f64| -> Dynamic {
+            let mut map = Map::new();
+            map.insert("type".into(), "bid".into());
+            map.insert("side".into(), Dynamic::from(side.to_string()));
+            map.insert("price".into(), Dynamic::from(price));
+            map.insert("shares".into(), Dynamic::from(shares));
+            Dynamic::from(map)
+        });
+
+        // Register helper: take(side, shares, max_price) -> action map
+        engine.register_fn("take", |side: &str, shares: f64, max_price: f64| -> Dynamic {
+            let mut map = Map::new();
+            map.insert("type".into(), "take".into());
+            map.insert("side".into(), Dynamic::from(side.to_string()));
+            map.insert("shares".into(), Dynamic::from(shares));
+            map.insert("max_price".into(), Dynamic::from(max_price));
+            Dynamic::from(map)
+        });
+
+        // Register helper: amend(side, price, shares) -> action map
+        engine.register_fn("amend", |side: &str, price: f64, shares: f64| -> Dynamic {
+            let mut map = Map::new();
+            map.insert("type".into(), "amend".into());
+            map.insert("side".into(), Dynamic::from(side.to_string()));
+            map.insert("price".into(), Dynamic::from(price));
+            map.insert("shares".into(), Dynamic::from(shares));
+            Dynamic::from(map)
+        });
+
+        // Register helper: sell(side, price, shares) -> action map
+        engine.register_fn("sell", |side: &str, price: f64, shares: f64| -> Dynamic {
+            let mut map = Map::new();
+            map.insert("type".into(), "sell".into());
+            map.insert("side".into(), Dynamic::from(side.to_string()));
+            map.insert("price".into(), Dynamic::from(price));
+            map.insert("shares".into(), Dynamic::from(shares));
+            Dynamic::from(map)
+        });
+
+        // Register helper: cancel(side) -> action map
+        engine.register_fn("cancel", |side: &str| -> Dynamic {
+            let mut map = Map::new();
+            map.insert("type".into(), "cancel".into());
+            map.insert("side".into(), Dynamic::from(side.to_string()));
+            Dynamic::from(map)
+        });
+
+        // Register depth_at helpers
+        engine.register_fn("yes_depth_at", yes_depth_at);
+        engine.register_fn("no_depth_at", no_depth_at);
+        engine.register_fn("yes_ask_depth_at", yes_ask_depth_at);
+        engine.register_fn("no_ask_depth_at", no_ask_depth_at);
+        register_price_helpers(&mut engine, |snap: &mut Map| quotes_from_map(snap));
+
+        // Register the snapshot handle passed to on_tick / on_market_open,
+        // with handle overloads of the depth and timing helpers below.
+        ScriptSnapshot::register(&mut engine);
+
+        // Register history helpers: history(ticks_ago), oracle_ago(seconds).
+        // Marked volatile so full optimization doesn't fold constant-argument
+        // calls at compile time.
+        let history = SnapshotHistory::new(DEFAULT_HISTORY_LEN);
+        let h = history.clone();
+        FuncRegistration::new("history")
+            .with_volatility(true)
+            .register_into_engine(&mut engine, move |i: i64| h.get(i));
+        let h = history.clone();
+        FuncRegistration::new("oracle_ago")
+            .with_volatility(true)
+            .register_into_engine(&mut engine, move |secs: f64| h.oracle_ago(secs));
+        let h = history.clone();
+        FuncRegistration::new("oracle_ago")
+            .with_volatility(true)
+            .register_into_engine(&mut engine, move |secs: i64| h.oracle_ago(secs as f64));
+
+        // Register the once-per-window trigger schedule(offset_ms); the
+        // timing helpers remaining_ms(snap) and elapsed_fraction(snap) come
+        // with the snapshot handle above.
+        let schedule = Schedule::default();
+        let sch = schedule.clone();
+        FuncRegistration::new("schedule")
+            .with_volatility(true)
+            .register_into_engine(&mut engine, move |target: i64| sch.fire(target));
+        let sch = schedule.clone();
+        FuncRegistration::new("schedule")
+            .with_volatility(true)
+            .register_into_engine(&mut engine, move |target: f64| sch.fire(target.round() as i64));
+
+        // Route log(msg) to info and Rhai's built-in debug(msg) to debug.
+        let log_context = LogContext::default();
+        let (ctx, script) = (log_context.clone(), name.to_string());
+        FuncRegistration::new("log")
+            .with_volatility(true)
+            .register_into_engine(&mut engine, move |msg: &str| {
+                ctx.log(&script, tracing::Level::INFO, msg)
+            });
+        let (ctx, script) = (log_context.clone(), name.to_string());
+        engine.on_debug(move |msg, _, _| ctx.log(&script, tracing::Level::DEBUG, msg));
+
+        // Register indicator stdlib: sma, ema, rolling_std, max_so_far, ...
+        indicators::register(&mut engine);
+
+        Self {
+            engine,
+            deadline,
+            history,
+            schedule,
+            log_context,
+        }
+    }
+}
 
 /// A strategy loaded from a Rhai script file.
 ///
 /// Scripts must define `on_tick(snap)` and `on_reset()` functions.
-/// An optional `on_market_open(snap)` function is called once per window.
+/// An optional `on_market_open(snap)` function is called once per window,
+/// and an optional `on_fill(order)` each time an order fills some shares;
+/// fills are reported just before the tick's `on_tick` call, and the actions
+/// `on_fill` returns are queued ahead of that tick's own actions.
 ///
 /// The script receives `SHARES` and `BID_PRICE` as global constants and
 /// can use `bid(side, price, shares)` and `cancel(side)` helper functions.
+/// The snapshot passed to `on_tick` carries the script's own order
+/// state under `snap.orders.yes` / `snap.orders.no`, with every order of the
+/// window in `snap.orders.all`, and both callbacks see
+/// the window's metadata (`snap.market_id`, `snap.duration_ms`, ...).
+/// Scripts also get a `state` map, emptied on every reset unless the script
+/// sets `PERSIST_STATE = true` and is given a [`PersistentState`].
+/// Past snapshots of the current window are available through
+/// `history(ticks_ago)` and `oracle_ago(seconds)`, timing through
+/// `remaining_ms(snap)`, `elapsed_fraction(snap)` and `schedule(offset_ms)`,
+/// and `log(msg)` /
+/// `debug(msg)` write to the tracing log tagged with the script, market and
+/// offset.
 pub struct RhaiStrategy {
     engine: Engine,
     ast: AST,
@@ -20,6 +443,23 @@ pub struct RhaiStrategy {
     name: String,
     script_path: String,
     has_on_market_open: bool,
+    has_on_fill: bool,
+    orders: OrderState,
+    /// Actions returned by `on_fill`, queued ahead of the tick's own.
+    pending: Vec<Action>,
+    market: Option<Arc<Market>>,
+    limits: ScriptLimits,
+    deadline: Deadline,
+    history: SnapshotHistory,
+    schedule: Schedule,
+    log_context: LogContext,
+    strict: bool,
+    persist: bool,
+    errors: usize,
+    aborted: Option<String>,
+    /// Why the script's top level failed in this instance; every window
+    /// aborts with it.
+    load_error: Option<String>,
 }
 
 impl std::fmt::Debug for RhaiStrategy {
@@ -34,6 +474,17 @@ impl std::fmt::Debug for RhaiStrategy {
 impl RhaiStrategy {
     /// Load a strategy from a `.rhai` file.
     pub fn from_file(path: &Path, shares: f64, bid_price: f64) -> anyhow::Result<Self> {
+        Self::from_file_with_params(path, shares, bid_price, &[])
+    }
+
+    /// Load a strategy from a `.rhai` file, pushing `params` into the script
+    /// scope as extra constants (see [`parse_script_param`]).
+    pub fn from_file_with_params(
+        path: &Path,
+        shares: f64,
+        bid_price: f64,
+        params: &[(String, Dynamic)],
+    ) -> anyhow::Result<Self> {
         let source = std::fs::read_to_string(path)
             .with_context(|| format!("failed to read script: {}", path.display()))?;
 
@@ -44,7 +495,7 @@ impl RhaiStrategy {
 
         let script_path = path.display().to_string();
 
-        Self::from_source(&name, &source, shares, bid_price)
+        Self::from_source_with_params(&name, &source, shares, bid_price, params)
             .with_context(|| format!("failed to load script: {}", script_path))
     }
 
@@ -55,66 +506,167 @@ impl RhaiStrategy {
         shares: f64,
         bid_price: f64,
     ) -> anyhow::Result<Self> {
-        let mut engine = Engine::new();
-        engine.set_optimization_level(rhai::OptimizationLevel::Full);
+        Self::from_source_with_params(name, source, shares, bid_price, &[])
+    }
 
-        // Register helper: bid(side, price, shares) -> action map
-        engine.register_fn("bid", |side: &str, price: f64, shares: f64| -> Dynamic {
-            let mut map = Map::new();
-            map.insert("type".into(), "bid".into());
-            map.insert("side".into(), Dynamic::from(side.to_string()));
-            map.insert("price".into(), Dynamic::from(price));
-            map.insert("shares".into(), Dynamic::from(shares));
-            Dynamic::from(map)
-        });
+    /// Load a strategy from source code with extra scope constants.
+    pub fn from_source_with_params(
+        name: &str,
+        source: &str,
+        shares: f64,
+        bid_price: f64,
+        params: &[(String, Dynamic)],
+    ) -> anyhow::Result<Self> {
+        let strategy = Self::from_compiled(&CompiledScript::new(name, source, shares, bid_price, params)?);
+        if let Some(ref error) = strategy.load_error {
+            bail!("{}", error);
+        }
+        Ok(strategy)
+    }
 
-        // Register helper: cancel(side) -> action map
-        engine.register_fn("cancel", |side: &str| -> Dynamic {
-            let mut map = Map::new();
-            map.insert("type".into(), "cancel".into());
-            map.insert("side".into(), Dynamic::from(side.to_string()));
-            Dynamic::from(map)
-        });
+    /// A fresh instance of an already compiled script, which runs its top
+    /// level again so no script state leaks between instances. If that
+    /// fails this time, the instance aborts every window with the error.
+    pub fn from_compiled(script: &CompiledScript) -> Self {
+        let ScriptEngine {
+            engine,
+            deadline,
+            history,
+            schedule,
+            log_context,
+        } = ScriptEngine::new(&script.name);
+        let limits = ScriptLimits::default();
 
-        // Register depth_at helpers
-        engine.register_fn("yes_depth_at", yes_depth_at);
-        engine.register_fn("no_depth_at", no_depth_at);
+        // Set up scope with constants
+        let mut scope = Scope::new();
+        scope.push_constant("SHARES", script.shares);
+        scope.push_constant("BID_PRICE", script.bid_price);
+        for (key, value) in &script.params {
+            scope.push_constant_dynamic(key.clone(), value.clone());
+        }
+        scope.push("state", Map::new());
 
-        // Compile the script
-        let ast = engine
-            .compile(source)
-            .map_err(|e| anyhow::anyhow!("compile error: {}", e))?;
+        // Run the top-level script once to initialize any global state
+        deadline.arm(limits.timeout);
+        let init = engine.run_ast_with_scope(&mut scope, &script.ast);
+        deadline.disarm();
+        let load_error = init
+            .err()
+            .map(|e| format!("initialization error: {}", describe_error(&e)));
+        let persist = scope.get_value::<bool>("PERSIST_STATE").unwrap_or(false);
+
+        Self {
+            engine,
+            ast: script.ast.clone(),
+            scope,
+            name: script.name.clone(),
+            script_path: script.name.clone(),
+            has_on_market_open: script.has_fn("on_market_open"),
+            has_on_fill: script.has_fn("on_fill"),
+            orders: OrderState::default(),
+            pending: Vec::new(),
+            market: None,
+            limits,
+            deadline,
+            history,
+            schedule,
+            log_context,
+            strict: false,
+            persist,
+            errors: 0,
+            aborted: load_error.clone(),
+            load_error,
+        }
+    }
 
-        // Verify required functions exist
-        let fn_names: Vec<String> = ast.iter_functions().map(|f| f.name.to_string()).collect();
+    /// Replace the default execution budget.
+    pub fn with_limits(mut self, limits: ScriptLimits) -> Self {
+        self.engine.set_max_operations(limits.max_operations);
+        self.engine.set_max_call_levels(limits.max_call_levels);
+        self.limits = limits;
+        self
+    }
 
-        if !fn_names.iter().any(|n| n == "on_tick") {
-            bail!("script must define an `on_tick(snap)` function");
-        }
-        if !fn_names.iter().any(|n| n == "on_reset") {
-            bail!("script must define an `on_reset()` function");
+    /// In strict mode any script runtime error aborts the window, instead of
+    /// being logged and counted.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Whether the script opted into `PERSIST_STATE`.
+    pub fn persists_state(&self) -> bool {
+        self.persist
+    }
+
+    /// Back the script's `state` map with `state` if the script opted in
+    /// with `PERSIST_STATE = true`; otherwise a no-op.
+    pub fn with_persistent_state(mut self, state: &PersistentState) -> Self {
+        if self.persist {
+            self.scope.set_value("state", state.0.clone());
         }
+        self
+    }
 
-        let has_on_market_open = fn_names.iter().any(|n| n == "on_market_open");
+    /// Keep the last `len` snapshots for `history(i)` / `oracle_ago(s)`
+    /// (0 disables the buffer).
+    pub fn with_history(self, len: usize) -> Self {
+        self.history.set_capacity(len);
+        self
+    }
 
-        // Set up scope with constants
-        let mut scope = Scope::new();
-        scope.push_constant("SHARES", shares);
-        scope.push_constant("BID_PRICE", bid_price);
+    /// Call a script function under the execution budget. Budget violations
+    /// (and, in strict mode, any error) abort the strategy until the next
+    /// reset; other script errors are logged, counted and the callback is
+    /// treated as returning nothing.
+    fn call(&mut self, fn_name: &str, args: impl FuncArgs) -> Option<Dynamic> {
+        if self.aborted.is_some() {
+            return None;
+        }
 
-        // Run the top-level script once to initialize any global state
-        engine
-            .run_ast_with_scope(&mut scope, &ast)
-            .map_err(|e| anyhow::anyhow!("initialization error: {}", e))?;
+        self.deadline.arm(self.limits.timeout);
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(&mut self.scope, &self.ast, fn_name, args);
+        self.deadline.disarm();
+
+        match result {
+            Ok(value) => Some(value),
+            Err(e) if is_budget_violation(&e) => {
+                // Reported by the engine when it stops the window.
+                self.aborted = Some(format!("{}: {}", fn_name, describe_error(&e)));
+                None
+            }
+            Err(e) => {
+                self.errors += 1;
+                if self.strict {
+                    self.aborted = Some(format!("{}: {}", fn_name, describe_error(&e)));
+                } else {
+                    tracing::warn!(script = %self.name, "{} error: {}", fn_name, e);
+                }
+                None
+            }
+        }
+    }
+}
 
-        Ok(Self {
-            engine,
-            ast,
-            scope,
-            name: name.to_string(),
-            script_path: name.to_string(),
-            has_on_market_open,
-        })
+/// Whether a script error came from the execution guard rather than the
+/// script's own logic.
+fn is_budget_violation(err: &EvalAltResult) -> bool {
+    matches!(
+        err.unwrap_inner(),
+        EvalAltResult::ErrorTooManyOperations(..)
+            | EvalAltResult::ErrorStackOverflow(..)
+            | EvalAltResult::ErrorTerminated(..)
+    )
+}
+
+fn describe_error(err: &EvalAltResult) -> String {
+    match err.unwrap_inner() {
+        EvalAltResult::ErrorTooManyOperations(..) => "operation limit exceeded".to_string(),
+        EvalAltResult::ErrorStackOverflow(..) => "call depth limit exceeded".to_string(),
+        EvalAltResult::ErrorTerminated(..) => "timed out".to_string(),
+        _ => err.to_string(),
     }
 }
 
@@ -127,79 +679,180 @@ impl Strategy for RhaiStrategy {
         &self.script_path
     }
 
+    fn on_market(&mut self, market: &Market) {
+        self.market = Some(Arc::new(market.clone()));
+    }
+
     fn on_market_open(&mut self, snap: &BookSnapshot) {
         if !self.has_on_market_open {
             return;
         }
-        let snap_map = snap_to_dynamic(snap);
-        if let Err(e) = self.engine.call_fn::<Dynamic>(
-            &mut self.scope,
-            &self.ast,
-            "on_market_open",
-            (snap_map,),
-        ) {
-            tracing::warn!(script = %self.name, "on_market_open error: {}", e);
-        }
+        self.log_context.set(snap);
+        self.schedule.set(snap.offset_ms.ms());
+        let handle = ScriptSnapshot::new(snap, &self.orders, self.market.as_ref());
+        self.call("on_market_open", (Dynamic::from(handle),));
     }
 
-    fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action> {
-        let snap_map = snap_to_dynamic(snap);
-        match self
-            .engine
-            .call_fn::<Dynamic>(&mut self.scope, &self.ast, "on_tick", (snap_map,))
-        {
-            Ok(result) => parse_actions(result),
-            Err(e) => {
-                tracing::warn!(script = %self.name, "on_tick error: {}", e);
-                vec![]
+    fn on_order_update(&mut self, orders: &OrderState) {
+        let previous = std::mem::replace(&mut self.orders, orders.clone());
+        if !self.has_on_fill {
+            return;
+        }
+        for side in [Side::Yes, Side::No] {
+            let (before, now) = (previous.side(side), orders.side(side));
+            if now.filled_shares <= before.filled_shares {
+                continue;
+            }
+            let mut order = side_order_to_map(side, now);
+            order.insert(
+                "fill_shares".into(),
+                Dynamic::from((now.filled_shares - before.filled_shares).to_f64()),
+            );
+            if let Some(actions) = self.call("on_fill", (Dynamic::from(order),)) {
+                self.pending.extend(parse_actions(actions));
             }
         }
     }
 
+    fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action> {
+        self.log_context.set(snap);
+        self.schedule.set(snap.offset_ms.ms());
+        let handle = Dynamic::from(ScriptSnapshot::new(snap, &self.orders, self.market.as_ref()));
+        self.history.push(snap, &handle);
+        let mut actions = std::mem::take(&mut self.pending);
+        actions.extend(self.call("on_tick", (handle,)).map(parse_actions).unwrap_or_default());
+        actions
+    }
+
     fn reset(&mut self) {
-        if let Err(e) =
-            self.engine
-                .call_fn::<Dynamic>(&mut self.scope, &self.ast, "on_reset", ())
-        {
-            tracing::warn!(script = %self.name, "on_reset error: {}", e);
+        self.orders = OrderState::default();
+        self.pending.clear();
+        self.history.clear();
+        self.schedule.clear();
+        if !self.persist {
+            self.scope.set_value("state", Map::new());
         }
+        self.errors = 0;
+        self.aborted = self.load_error.clone();
+        self.call("on_reset", ());
+    }
+
+    fn abort_reason(&self) -> Option<&str> {
+        self.aborted.as_deref()
+    }
+
+    fn error_count(&self) -> usize {
+        self.errors
     }
 }
 
-/// Convert a BookSnapshot into a Rhai Dynamic map.
-fn snap_to_dynamic(snap: &BookSnapshot) -> Dynamic {
-    let mut map = Map::new();
+/// Parse a `key=value` script parameter.
+///
+/// The value becomes an integer, float or bool constant when it parses as
+/// one, and a string otherwise. Keys must be valid Rhai identifiers.
+pub fn parse_script_param(raw: &str) -> anyhow::Result<(String, Dynamic)> {
+    let (key, value) = raw
+        .split_once('=')
+        .with_context(|| format!("script parameter `{}` must be key=value", raw))?;
+    let key = key.trim();
+
+    let valid_ident = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_ident {
+        bail!("script parameter name `{}` is not a valid identifier", key);
+    }
 
-    // Yes side
-    map.insert(
-        "yes_bid".into(),
-        Dynamic::from(snap.yes.best_bid.unwrap_or(0.0)),
-    );
-    map.insert(
-        "yes_ask".into(),
-        Dynamic::from(snap.yes.best_ask.unwrap_or(0.0)),
-    );
-    map.insert(
-        "yes_bid_size".into(),
-        Dynamic::from(snap.yes.best_bid_size.unwrap_or(0.0)),
-    );
-    map.insert(
-        "yes_ask_size".into(),
-        Dynamic::from(snap.yes.best_ask_size.unwrap_or(0.0)),
-    );
-    map.insert(
-        "yes_total_bid_depth".into(),
-        Dynamic::from(snap.yes.total_bid_depth),
-    );
-    map.insert(
-        "yes_total_ask_depth".into(),
-        Dynamic::from(snap.yes.total_ask_depth),
-    );
+    let value = value.trim();
+    let dynamic = if let Ok(i) = value.parse::<i64>() {
+        Dynamic::from(i)
+    } else if let Ok(f) = value.parse::<f64>() {
+        Dynamic::from(f)
+    } else if let Ok(b) = value.parse::<bool>() {
+        Dynamic::from(b)
+    } else {
+        Dynamic::from(value.to_string())
+    };
+    Ok((key.to_string(), dynamic))
+}
+
+/// The fields of the snapshot seen by scripts, in map order.
+pub(crate) const SNAP_FIELDS: &[&str] = &[
+    "yes_bid",
+    "yes_ask",
+    "yes_bid_size",
+    "yes_ask_size",
+    "yes_total_bid_depth",
+    "yes_total_ask_depth",
+    "yes_depth",
+    "no_bid",
+    "no_ask",
+    "no_bid_size",
+    "no_ask_size",
+    "no_total_bid_depth",
+    "no_total_ask_depth",
+    "no_depth",
+    "yes_ask_depth",
+    "no_ask_depth",
+    "offset_ms",
+    "timestamp_ms",
+    "oracle_price",
+    "market_id",
+    "category",
+    "duration_ms",
+    "open_ts",
+    "close_ts",
+    "orders",
+];
+
+/// One field of the script-facing snapshot, or `None` if `key` is not one
+/// of [`SNAP_FIELDS`]. Market metadata is empty/zero when the strategy runs
+/// outside the engine.
+fn snap_field(snap: &BookSnapshot, orders: &OrderState, market: Option<&Market>, key: &str) -> Option<Dynamic> {
+    let value = match key {
+        "yes_bid" => Dynamic::from(snap.yes.best_bid.unwrap_or(0.0)),
+        "yes_ask" => Dynamic::from(snap.yes.best_ask.unwrap_or(0.0)),
+        "yes_bid_size" => Dynamic::from(snap.yes.best_bid_size.unwrap_or(0.0)),
+        "yes_ask_size" => Dynamic::from(snap.yes.best_ask_size.unwrap_or(0.0)),
+        "yes_total_bid_depth" => Dynamic::from(snap.yes.total_bid_depth),
+        "yes_total_ask_depth" => Dynamic::from(snap.yes.total_ask_depth),
+        "yes_depth" => levels_to_dynamic(&snap.yes.depth),
+        "no_bid" => Dynamic::from(snap.no.best_bid.unwrap_or(0.0)),
+        "no_ask" => Dynamic::from(snap.no.best_ask.unwrap_or(0.0)),
+        "no_bid_size" => Dynamic::from(snap.no.best_bid_size.unwrap_or(0.0)),
+        "no_ask_size" => Dynamic::from(snap.no.best_ask_size.unwrap_or(0.0)),
+        "no_total_bid_depth" => Dynamic::from(snap.no.total_bid_depth),
+        "no_total_ask_depth" => Dynamic::from(snap.no.total_ask_depth),
+        "no_depth" => levels_to_dynamic(&snap.no.depth),
+        // Ask ladders, mirrored from the opposite side's bids
+        "yes_ask_depth" => levels_to_dynamic(&snap.ask_depth(Side::Yes)),
+        "no_ask_depth" => levels_to_dynamic(&snap.ask_depth(Side::No)),
+        "offset_ms" => Dynamic::from(snap.offset_ms.ms()),
+        "timestamp_ms" => Dynamic::from(snap.timestamp_ms),
+        "oracle_price" => Dynamic::from(snap.oracle_price.unwrap_or(0.0)),
+        "market_id" => Dynamic::from(market.map(|m| m.id.clone()).unwrap_or_default()),
+        "category" => Dynamic::from(market.map(|m| m.category.clone()).unwrap_or_default()),
+        "duration_ms" => Dynamic::from(market.map(|m| m.duration_secs * 1000).unwrap_or(0)),
+        "open_ts" => Dynamic::from(market.map(|m| m.open_ts.secs()).unwrap_or(0)),
+        "close_ts" => Dynamic::from(market.map(|m| m.close_ts.secs()).unwrap_or(0)),
+        "orders" => {
+            let mut orders_map = Map::new();
+            orders_map.insert("yes".into(), side_order_to_map(Side::Yes, &orders.yes).into());
+            orders_map.insert("no".into(), side_order_to_map(Side::No, &orders.no).into());
+            let all: Array = orders.orders.iter().map(|o| Dynamic::from(sim_order_to_map(o))).collect();
+            orders_map.insert("all".into(), all.into());
+            Dynamic::from(orders_map)
+        }
+        _ => return None,
+    };
+    Some(value)
+}
 
-    // Store depth arrays for depth_at lookups
-    let yes_depth: Vec<Dynamic> = snap
-        .yes
-        .depth
+/// A depth ladder as an array of `#{price, size}` maps.
+fn levels_to_dynamic(levels: &[PriceLevel]) -> Dynamic {
+    let levels: Array = levels
         .iter()
         .map(|l| {
             let mut lm = Map::new();
@@ -208,56 +861,202 @@ fn snap_to_dynamic(snap: &BookSnapshot) -> Dynamic {
             Dynamic::from(lm)
         })
         .collect();
-    map.insert("yes_depth".into(), Dynamic::from(yes_depth));
-
-    // No side
-    map.insert(
-        "no_bid".into(),
-        Dynamic::from(snap.no.best_bid.unwrap_or(0.0)),
-    );
-    map.insert(
-        "no_ask".into(),
-        Dynamic::from(snap.no.best_ask.unwrap_or(0.0)),
-    );
-    map.insert(
-        "no_bid_size".into(),
-        Dynamic::from(snap.no.best_bid_size.unwrap_or(0.0)),
-    );
-    map.insert(
-        "no_ask_size".into(),
-        Dynamic::from(snap.no.best_ask_size.unwrap_or(0.0)),
-    );
-    map.insert(
-        "no_total_bid_depth".into(),
-        Dynamic::from(snap.no.total_bid_depth),
-    );
-    map.insert(
-        "no_total_ask_depth".into(),
-        Dynamic::from(snap.no.total_ask_depth),
-    );
+    Dynamic::from(levels)
+}
 
-    let no_depth: Vec<Dynamic> = snap
-        .no
-        .depth
+/// Convert a BookSnapshot (plus the strategy's order state and the window's
+/// market metadata) into a Rhai Dynamic map with every [`SNAP_FIELDS`] key.
+pub(crate) fn snap_to_dynamic(snap: &BookSnapshot, orders: &OrderState, market: Option<&Market>) -> Dynamic {
+    let map: Map = SNAP_FIELDS
         .iter()
-        .map(|l| {
-            let mut lm = Map::new();
-            lm.insert("price".into(), Dynamic::from(l.price));
-            lm.insert("size".into(), Dynamic::from(l.cumulative_size));
-            Dynamic::from(lm)
+        .map(|&key| {
+            let value = snap_field(snap, orders, market, key).expect("every listed field is known");
+            (key.into(), value)
         })
         .collect();
-    map.insert("no_depth".into(), Dynamic::from(no_depth));
+    Dynamic::from(map)
+}
+
+/// The snapshot handed to Rhai callbacks.
+///
+/// Filling a [`snap_to_dynamic`] map, with a map per depth level, on every
+/// tick dominated the cost of scripted strategies. The handle shares the
+/// snapshot instead and converts a field only when the script reads it.
+/// Scripts use it like a read-only map: `snap.yes_bid`, `snap["yes_bid"]`,
+/// `"yes_bid" in snap` and `snap.keys()` work as before, unknown keys read
+/// as `()`, and `snap.to_map()` returns a full map copy.
+#[derive(Clone)]
+pub(crate) struct ScriptSnapshot(Arc<ScriptSnapshotData>);
+
+struct ScriptSnapshotData {
+    snap: BookSnapshot,
+    orders: OrderState,
+    market: Option<Arc<Market>>,
+}
+
+impl ScriptSnapshot {
+    fn new(snap: &BookSnapshot, orders: &OrderState, market: Option<&Arc<Market>>) -> Self {
+        Self(Arc::new(ScriptSnapshotData {
+            snap: snap.clone(),
+            orders: orders.clone(),
+            market: market.cloned(),
+        }))
+    }
+
+    fn get(&self, key: &str) -> Option<Dynamic> {
+        let data = &*self.0;
+        snap_field(&data.snap, &data.orders, data.market.as_deref(), key)
+    }
+
+    fn to_map(&self) -> Dynamic {
+        let data = &*self.0;
+        snap_to_dynamic(&data.snap, &data.orders, data.market.as_deref())
+    }
+
+    fn duration_ms(&self) -> i64 {
+        self.0.market.as_ref().map_or(0, |m| m.duration_secs * 1000)
+    }
 
-    // Metadata
-    map.insert("offset_ms".into(), Dynamic::from(snap.offset_ms));
-    map.insert("timestamp_ms".into(), Dynamic::from(snap.timestamp_ms));
+    /// Register the type, its fields and its map-like functions.
+    fn register(engine: &mut Engine) {
+        engine.register_type_with_name::<ScriptSnapshot>("Snapshot");
+        for &key in SNAP_FIELDS {
+            engine.register_get(key, move |s: &mut ScriptSnapshot| s.get(key).unwrap_or(Dynamic::UNIT));
+        }
+        engine.register_indexer_get(|s: &mut ScriptSnapshot, key: &str| s.get(key).unwrap_or(Dynamic::UNIT));
+        engine.register_fn("contains", |_: &mut ScriptSnapshot, key: &str| SNAP_FIELDS.contains(&key));
+        engine.register_fn("keys", |_: &mut ScriptSnapshot| -> Array {
+            SNAP_FIELDS.iter().map(|&k| Dynamic::from(k.to_string())).collect()
+        });
+        engine.register_fn("to_map", |s: &mut ScriptSnapshot| s.to_map());
+        engine.register_fn("to_string", |s: &mut ScriptSnapshot| s.to_map().to_string());
+        engine.register_fn("to_debug", |s: &mut ScriptSnapshot| format!("{:?}", s.to_map()));
+
+        engine.register_fn("yes_depth_at", |s: &mut ScriptSnapshot, price: f64| {
+            depth_at_levels(&price_levels(&s.0.snap.yes.depth), price)
+        });
+        engine.register_fn("no_depth_at", |s: &mut ScriptSnapshot, price: f64| {
+            depth_at_levels(&price_levels(&s.0.snap.no.depth), price)
+        });
+        engine.register_fn("yes_ask_depth_at", |s: &mut ScriptSnapshot, price: f64| {
+            s.0.snap.ask_depth_at(Side::Yes, price)
+        });
+        engine.register_fn("no_ask_depth_at", |s: &mut ScriptSnapshot, price: f64| {
+            s.0.snap.ask_depth_at(Side::No, price)
+        });
+        register_price_helpers(engine, |s: &mut ScriptSnapshot| s.0.snap.clone());
+        engine.register_fn("remaining_ms", |s: &mut ScriptSnapshot| {
+            window_remaining_ms(s.duration_ms(), s.0.snap.offset_ms.ms())
+        });
+        engine.register_fn("elapsed_fraction", |s: &mut ScriptSnapshot| {
+            window_elapsed_fraction(s.duration_ms(), s.0.snap.offset_ms.ms())
+        });
+    }
+}
+
+/// Register the mid, implied-probability and spread helpers for snapshots of
+/// type `T`, which `book` turns into a [`BookSnapshot`]. Each returns `()`
+/// when the book lacks a price it needs, or for a side other than yes/no.
+fn register_price_helpers<T: Clone + Send + Sync + 'static>(engine: &mut Engine, book: fn(&mut T) -> BookSnapshot) {
+    let price = |p: Option<f64>| p.map_or(Dynamic::UNIT, Dynamic::from);
+    engine.register_fn("yes_mid", move |s: &mut T| price(book(s).yes_mid()));
+    engine.register_fn("no_mid", move |s: &mut T| price(book(s).no_mid()));
+    engine.register_fn("combined_bid", move |s: &mut T| price(book(s).combined_bid()));
+    engine.register_fn("implied_prob", move |s: &mut T, side: &str| {
+        price(script_side(side).and_then(|side| book(s).implied_prob(side)))
+    });
+    engine.register_fn("spread_bps", move |s: &mut T, side: &str| {
+        price(script_side(side).and_then(|side| book(s).spread_bps(side)))
+    });
+}
+
+/// The top of book held in a snapshot map (e.g. from `history(i)`), where
+/// a missing price reads as 0.0.
+fn quotes_from_map(snap: &Map) -> BookSnapshot {
+    let price = |key: &str| {
+        snap.get(key)
+            .and_then(|v| v.as_float().ok())
+            .filter(|&p| p > 0.0)
+    };
+    let side = |prefix: &str| SideState {
+        best_bid: price(&format!("{}_bid", prefix)),
+        best_ask: price(&format!("{}_ask", prefix)),
+        ..Default::default()
+    };
+    BookSnapshot {
+        market_id: MarketId::default(),
+        offset_ms: OffsetMs::new(map_int(snap, "offset_ms")),
+        timestamp_ms: map_int(snap, "timestamp_ms"),
+        yes: Arc::new(side("yes")),
+        no: Arc::new(side("no")),
+        reference_price: None,
+        oracle_price: None,
+    }
+}
+
+fn price_levels(levels: &[PriceLevel]) -> Vec<(f64, f64)> {
+    levels.iter().map(|l| (l.price, l.cumulative_size)).collect()
+}
+
+/// Convert one side's order state into a Rhai map. `filled_at_ms` (and its
+/// older name `fill_time_ms`) is `()` until the order fills.
+fn side_order_to_map(side: Side, state: &SideOrderState) -> Map {
+    let mut map = Map::new();
+    map.insert("side".into(), Dynamic::from(side.label().to_lowercase()));
+    map.insert("placed".into(), Dynamic::from(state.placed));
+    map.insert("cancelled".into(), Dynamic::from(state.cancelled));
+    map.insert("filled".into(), Dynamic::from(state.filled));
+    map.insert("price".into(), Dynamic::from(state.price.to_f64()));
+    map.insert("shares".into(), Dynamic::from(state.shares.to_f64()));
+    map.insert("filled_shares".into(), Dynamic::from(state.filled_shares.to_f64()));
     map.insert(
-        "oracle_price".into(),
-        Dynamic::from(snap.oracle_price.unwrap_or(0.0)),
+        "remaining".into(),
+        Dynamic::from((state.shares - state.filled_shares).to_f64()),
     );
+    let filled_at_ms = offset_or_unit(state.filled_at_ms);
+    map.insert("filled_at_ms".into(), filled_at_ms.clone());
+    map.insert("fill_time_ms".into(), filled_at_ms);
+    map.insert("queue_ahead".into(), Dynamic::from(state.queue_ahead));
+    map.insert("sold_shares".into(), Dynamic::from(state.sold_shares.to_f64()));
+    map
+}
 
-    Dynamic::from(map)
+/// Convert one order into a Rhai map for `snap.orders.all`. The offsets
+/// are `()` until the order fills or is cancelled.
+fn sim_order_to_map(order: &SimOrder) -> Map {
+    let mut map = Map::new();
+    map.insert("id".into(), Dynamic::from(order.id.get() as i64));
+    map.insert("side".into(), Dynamic::from(order.side.label().to_lowercase()));
+    map.insert("sell".into(), Dynamic::from(order.sell));
+    map.insert("price".into(), Dynamic::from(order.price.to_f64()));
+    map.insert("shares".into(), Dynamic::from(order.shares.to_f64()));
+    map.insert("filled_shares".into(), Dynamic::from(order.filled_shares.to_f64()));
+    map.insert("filled_at_ms".into(), offset_or_unit(order.filled_at_ms));
+    map.insert("cancelled_at_ms".into(), offset_or_unit(order.cancelled_at_ms));
+    map
+}
+
+fn offset_or_unit(offset: Option<OffsetMs>) -> Dynamic {
+    offset.map(|t| Dynamic::from(t.ms())).unwrap_or(Dynamic::UNIT)
+}
+
+fn map_int(snap: &Map, key: &str) -> i64 {
+    snap.get(key).and_then(|v| v.as_int().ok()).unwrap_or(0)
+}
+
+/// Milliseconds left in the window (0 once closed, or if the duration is
+/// unknown).
+pub(crate) fn window_remaining_ms(duration_ms: i64, offset_ms: i64) -> i64 {
+    (duration_ms - offset_ms).max(0)
+}
+
+/// Fraction of the window elapsed, clamped to `[0, 1]` (0.0 if the
+/// duration is unknown).
+pub(crate) fn window_elapsed_fraction(duration_ms: i64, offset_ms: i64) -> f64 {
+    if duration_ms <= 0 {
+        return 0.0;
+    }
+    (offset_ms as f64 / duration_ms as f64).clamp(0.0, 1.0)
 }
 
 /// Look up cumulative depth at a price from the yes_depth array in a snap map.
@@ -270,19 +1069,31 @@ fn no_depth_at(snap: Map, price: f64) -> f64 {
     depth_at_inner(&snap, "no_depth", price)
 }
 
+/// Look up cumulative ask depth at a price from the yes_ask_depth array.
+fn yes_ask_depth_at(snap: Map, price: f64) -> f64 {
+    ask_depth_at_levels(&depth_levels(&snap, "yes_ask_depth"), price)
+}
+
+/// Look up cumulative ask depth at a price from the no_ask_depth array.
+fn no_ask_depth_at(snap: Map, price: f64) -> f64 {
+    ask_depth_at_levels(&depth_levels(&snap, "no_ask_depth"), price)
+}
+
 fn depth_at_inner(snap: &Map, key: &str, price: f64) -> f64 {
-    const EPSILON: f64 = 1e-9;
+    depth_at_levels(&depth_levels(snap, key), price)
+}
 
+/// Extract `(price, size)` pairs from a depth array in a snap map.
+fn depth_levels(snap: &Map, key: &str) -> Vec<(f64, f64)> {
     let depth_arr = match snap.get(key) {
         Some(d) => match d.clone().into_array() {
             Ok(arr) => arr,
-            Err(_) => return 0.0,
+            Err(_) => return vec![],
         },
-        None => return 0.0,
+        None => return vec![],
     };
 
-    // Extract (price, size) from each level map
-    let levels: Vec<(f64, f64)> = depth_arr
+    depth_arr
         .iter()
         .filter_map(|level| {
             let map: Map = level.clone().try_cast()?;
@@ -290,10 +1101,16 @@ fn depth_at_inner(snap: &Map, key: &str, price: f64) -> f64 {
             let ls = map.get("size")?.as_float().ok()?;
             Some((lp, ls))
         })
-        .collect();
+        .collect()
+}
+
+/// Cumulative size at `price` from `(price, size)` levels: the exact level
+/// if present, else the nearest level above it, else 0.0.
+pub(crate) fn depth_at_levels(levels: &[(f64, f64)], price: f64) -> f64 {
+    const EPSILON: f64 = 1e-9;
 
     // Exact match first
-    for &(lp, ls) in &levels {
+    for &(lp, ls) in levels {
         if (lp - price).abs() < EPSILON {
             return ls;
         }
@@ -301,7 +1118,7 @@ fn depth_at_inner(snap: &Map, key: &str, price: f64) -> f64 {
 
     // Fallback: nearest level at or above
     let mut best: Option<(f64, f64)> = None;
-    for &(lp, ls) in &levels {
+    for &(lp, ls) in levels {
         if lp >= price {
             match best {
                 Some((bp, _)) if lp < bp => best = Some((lp, ls)),
@@ -314,6 +1131,21 @@ fn depth_at_inner(snap: &Map, key: &str, price: f64) -> f64 {
     best.map(|(_, s)| s).unwrap_or(0.0)
 }
 
+/// Cumulative ask size at `price` from `(price, size)` ask levels: the exact
+/// level if present, else the nearest level below it, else 0.0.
+pub(crate) fn ask_depth_at_levels(levels: &[(f64, f64)], price: f64) -> f64 {
+    const EPSILON: f64 = 1e-9;
+
+    if let Some(&(_, ls)) = levels.iter().find(|(lp, _)| (lp - price).abs() < EPSILON) {
+        return ls;
+    }
+    levels
+        .iter()
+        .filter(|(lp, _)| *lp <= price)
+        .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+        .map_or(0.0, |&(_, ls)| ls)
+}
+
 /// Parse the return value of on_tick into a Vec<Action>.
 fn parse_actions(result: Dynamic) -> Vec<Action> {
     let arr = match result.into_array() {
@@ -332,27 +1164,59 @@ fn parse_actions(result: Dynamic) -> Vec<Action> {
     actions
 }
 
+/// A side as scripts spell it: `"yes"`/`"no"`, any of the usual cases.
+fn script_side(side: &str) -> Option<Side> {
+    match side {
+        "yes" | "Yes" | "YES" => Some(Side::Yes),
+        "no" | "No" | "NO" => Some(Side::No),
+        _ => None,
+    }
+}
+
 fn parse_one_action(map: &Map) -> Option<Action> {
     let action_type = map.get("type")?.clone().into_string().ok()?;
     let side_str = map.get("side")?.clone().into_string().ok()?;
 
-    let side = match side_str.as_str() {
-        "yes" | "Yes" | "YES" => Side::Yes,
-        "no" | "No" | "NO" => Side::No,
-        _ => return None,
-    };
+    let side = script_side(&side_str)?;
 
     match action_type.as_str() {
         "bid" => {
             let price = map.get("price")?.as_float().ok()?;
             let shares = map.get("shares")?.as_float().ok()?;
-            Some(Action::PlaceBid {
+            Some(Action::PlaceBid {
+                side,
+                price: Price::from_f64(price),
+                shares: Shares::from_f64(shares),
+            })
+        }
+        "take" => {
+            let shares = map.get("shares")?.as_float().ok()?;
+            let max_price = map.get("max_price")?.as_float().ok()?;
+            Some(Action::TakeAsk {
+                side,
+                shares: Shares::from_f64(shares),
+                max_price: Price::from_f64(max_price),
+            })
+        }
+        "cancel" => Some(Action::Cancel { side }),
+        "amend" => {
+            let price = map.get("price")?.as_float().ok()?;
+            let shares = map.get("shares")?.as_float().ok()?;
+            Some(Action::Amend {
                 side,
-                price,
-                shares,
+                new_price: Price::from_f64(price),
+                new_shares: Shares::from_f64(shares),
+            })
+        }
+        "sell" => {
+            let price = map.get("price")?.as_float().ok()?;
+            let shares = map.get("shares")?.as_float().ok()?;
+            Some(Action::Sell {
+                side,
+                price: Price::from_f64(price),
+                shares: Shares::from_f64(shares),
             })
         }
-        "cancel" => Some(Action::Cancel { side }),
         _ => None,
     }
 }
@@ -361,6 +1225,7 @@ fn parse_one_action(map: &Map) -> Option<Action> {
 mod tests {
     use super::*;
     use crate::strategies::make_test_snap;
+    use crate::types::{OffsetMs, UnixSecs};
 
     #[test]
     fn test_load_valid_script() {
@@ -396,13 +1261,30 @@ fn on_reset() {}
                 shares,
             } => {
                 assert_eq!(*side, Side::Yes);
-                assert!((price - 0.49).abs() < f64::EPSILON);
-                assert!((shares - 10.0).abs() < f64::EPSILON);
+                assert_eq!(*price, Price::from_f64(0.49));
+                assert_eq!(*shares, Shares::from_f64(10.0));
             }
             _ => panic!("expected PlaceBid"),
         }
     }
 
+    #[test]
+    fn test_take_returns_taker_action() {
+        let source = r#"
+fn on_tick(snap) {
+    [take("no", 20.0, 0.55)]
+}
+fn on_reset() {}
+"#;
+        let mut strat = RhaiStrategy::from_source("test", source, 10.0, 0.49).unwrap();
+        let actions = strat.on_tick(&make_test_snap(0, Some(50000.0), 500.0, 500.0));
+        assert!(matches!(
+            actions[..],
+            [Action::TakeAsk { side: Side::No, shares, max_price }]
+                if shares == Shares::from_f64(20.0) && max_price == Price::from_f64(0.55)
+        ));
+    }
+
     #[test]
     fn test_on_reset_clears_state() {
         let source = r#"
@@ -526,8 +1408,8 @@ fn on_reset() {}
             Action::PlaceBid {
                 price, shares, ..
             } => {
-                assert!((price - 0.48).abs() < f64::EPSILON);
-                assert!((shares - 25.0).abs() < f64::EPSILON);
+                assert_eq!(*price, Price::from_f64(0.48));
+                assert_eq!(*shares, Shares::from_f64(25.0));
             }
             _ => panic!("expected PlaceBid"),
         }
@@ -571,6 +1453,70 @@ fn on_reset() {}
         assert_eq!(actions.len(), 1);
     }
 
+    #[test]
+    fn test_price_helpers() {
+        let source = r#"
+fn on_tick(snap) {
+    let prev = history(0);
+    if snap.yes_mid() == 0.5 && prev.no_mid() == 0.5 && snap.implied_prob("yes") == 0.5
+        && (snap.spread_bps("no") - 400.0).abs() < 1e-6 && (prev.spread_bps("No") - 400.0).abs() < 1e-6
+        && snap.implied_prob("maybe") == () && snap.combined_bid() < 0.99 {
+        [bid("yes", snap.yes_bid, SHARES)]
+    } else {
+        []
+    }
+}
+fn on_reset() {}
+"#;
+        let mut strat = RhaiStrategy::from_source("test", source, 10.0, 0.49).unwrap();
+        // make_test_snap quotes 0.49 / 0.51 on both sides.
+        let snap = make_test_snap(0, Some(50000.0), 500.0, 300.0);
+        assert_eq!(strat.on_tick(&snap).len(), 1);
+
+        let mut one_sided = snap.clone();
+        one_sided.no = Arc::new(SideState { best_ask: Some(0.51), ..Default::default() });
+        let source = r#"
+fn on_tick(snap) {
+    if snap.combined_bid() == () && snap.no_mid() == () && history(0).no_mid() == () {
+        [bid("yes", snap.yes_bid, SHARES)]
+    } else {
+        []
+    }
+}
+fn on_reset() {}
+"#;
+        let mut strat = RhaiStrategy::from_source("test", source, 10.0, 0.49).unwrap();
+        assert_eq!(strat.on_tick(&one_sided).len(), 1);
+    }
+
+    #[test]
+    fn test_ask_depth_at_functions() {
+        let source = r#"
+fn on_tick(snap) {
+    // YES asks mirror NO bids: 300 shares at 0.51.
+    let ya = yes_ask_depth_at(snap, 0.51);
+    let na = no_ask_depth_at(snap, 0.52);
+    if ya == 300.0 && na == 500.0 && yes_ask_depth_at(snap, 0.50) == 0.0 {
+        [bid("yes", snap.yes_ask_depth[0].price, ya)]
+    } else {
+        []
+    }
+}
+fn on_reset() {}
+"#;
+        let mut strat = RhaiStrategy::from_source("test", source, 10.0, 0.49).unwrap();
+        let snap = make_test_snap(0, Some(50000.0), 500.0, 300.0);
+        let actions = strat.on_tick(&snap);
+        assert_eq!(actions.len(), 1);
+        match actions[0] {
+            Action::PlaceBid { price, shares, .. } => {
+                assert_eq!(price, Price::from_f64(0.51));
+                assert_eq!(shares, Shares::from_f64(300.0));
+            }
+            _ => panic!("expected PlaceBid"),
+        }
+    }
+
     #[test]
     fn test_on_market_open_called() {
         let source = r#"
@@ -604,4 +1550,507 @@ fn on_reset() {
         let actions = strat.on_tick(&snap);
         assert_eq!(actions.len(), 1);
     }
+
+    #[test]
+    fn test_orders_exposed_to_script() {
+        let source = r#"
+fn on_tick(snap) {
+    if snap.orders.yes.filled && snap.orders.no.placed && !snap.orders.no.filled {
+        [cancel("no")]
+    } else {
+        []
+    }
+}
+fn on_reset() {}
+"#;
+        let mut strat = RhaiStrategy::from_source("test", source, 10.0, 0.49).unwrap();
+        let snap = make_test_snap(1000, Some(50000.0), 500.0, 500.0);
+
+        let resting = SideOrderState {
+            placed: true,
+            price: Price::from_f64(0.49),
+            shares: Shares::from_f64(10.0),
+            queue_ahead: 200.0,
+            ..Default::default()
+        };
+        let mut orders = OrderState {
+            yes: resting.clone(),
+            no: resting,
+            ..Default::default()
+        };
+        strat.on_order_update(&orders);
+        assert!(strat.on_tick(&snap).is_empty());
+
+        orders.yes.filled = true;
+        orders.yes.filled_at_ms = Some(OffsetMs::new(1000));
+        orders.yes.queue_ahead = 0.0;
+        strat.on_order_update(&orders);
+        let actions = strat.on_tick(&snap);
+        assert!(matches!(actions[..], [Action::Cancel { side: Side::No }]));
+
+        // State is cleared between windows.
+        strat.reset();
+        assert!(strat.on_tick(&snap).is_empty());
+    }
+
+    #[test]
+    fn test_on_fill_actions_sent_with_next_tick() {
+        let source = r#"
+fn on_fill(order) {
+    if order.side == "yes" && order.fill_shares == 4.0 && order.remaining == 6.0 {
+        [sell("yes", 0.55, order.fill_shares)]
+    } else {
+        [bid("no", 0.40, 1.0)]
+    }
+}
+fn on_tick(snap) { [] }
+fn on_reset() {}
+"#;
+        let mut strat = RhaiStrategy::from_source("test", source, 10.0, 0.49).unwrap();
+        let snap = make_test_snap(1000, Some(50000.0), 500.0, 500.0);
+
+        let mut orders = OrderState {
+            yes: SideOrderState {
+                placed: true,
+                price: Price::from_f64(0.49),
+                shares: Shares::from_f64(10.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        strat.on_order_update(&orders);
+        assert!(strat.on_tick(&snap).is_empty());
+
+        orders.yes.filled_shares = Shares::from_f64(4.0);
+        orders.yes.filled_at_ms = Some(OffsetMs::new(1000));
+        strat.on_order_update(&orders);
+        let actions = strat.on_tick(&snap);
+        assert!(
+            matches!(actions[..], [Action::Sell { side: Side::Yes, .. }]),
+            "{:?}",
+            actions
+        );
+
+        // Only new fills call back.
+        strat.on_order_update(&orders);
+        assert!(strat.on_tick(&snap).is_empty());
+    }
+
+    #[test]
+    fn test_every_order_exposed_to_script() {
+        let source = r#"
+fn on_tick(snap) {
+    let all = snap.orders.all;
+    if all.len() == 2 && all[0].id == 1 && all[0].cancelled_at_ms == 500 && all[0].filled_at_ms == ()
+        && all[1].side == "yes" && all[1].filled_shares == 4.0 && all[1].filled_at_ms == 900
+        && snap.orders.yes.filled_at_ms == 900 {
+        [cancel("yes")]
+    } else {
+        []
+    }
+}
+fn on_reset() {}
+"#;
+        let mut strat = RhaiStrategy::from_source("test", source, 10.0, 0.49).unwrap();
+        let snap = make_test_snap(1000, Some(50000.0), 500.0, 500.0);
+        let order = |id, side, cancelled_at_ms: Option<i64>| SimOrder {
+            id: crate::types::OrderId::new(id),
+            side,
+            price: Price::from_f64(0.49),
+            shares: Shares::from_f64(10.0),
+            placed_at_ms: OffsetMs::new(0),
+            queue_ahead: 0.0,
+            queue_consumed: 0.0,
+            filled: false,
+            filled_shares: Shares::default(),
+            filled_at_ms: None,
+            cancelled_at_ms: cancelled_at_ms.map(OffsetMs::new),
+            taker: false,
+            sell: false,
+        };
+        let mut yes = order(2, Side::Yes, None);
+        yes.fill(Shares::from_f64(4.0), OffsetMs::new(900));
+        let orders = OrderState {
+            yes: SideOrderState {
+                placed: true,
+                filled_shares: yes.filled_shares,
+                filled_at_ms: yes.filled_at_ms,
+                ..Default::default()
+            },
+            orders: vec![order(1, Side::No, Some(500)), yes],
+            ..Default::default()
+        };
+        strat.on_order_update(&orders);
+        let actions = strat.on_tick(&snap);
+        assert!(matches!(actions[..], [Action::Cancel { side: Side::Yes }]), "{:?}", actions);
+    }
+
+    #[test]
+    fn test_market_metadata_exposed_to_script() {
+        let source = r#"
+fn on_tick(snap) {
+    if snap.category == "btc" && snap.offset_ms >= snap.duration_ms - 15000 {
+        [bid("yes", BID_PRICE, SHARES)]
+    } else {
+        []
+    }
+}
+fn on_reset() {}
+"#;
+        let mut strat = RhaiStrategy::from_source("test", source, 10.0, 0.49).unwrap();
+        strat.on_market(&Market {
+            id: "btc-updown-5m-1700000000".to_string(),
+            platform: crate::types::Platform::Polymarket,
+            description: String::new(),
+            category: "btc".to_string(),
+            open_ts: UnixSecs::new(1_700_000_000),
+            close_ts: UnixSecs::new(1_700_000_300),
+            duration_secs: 300,
+            outcome: None,
+            outcome_ts: None,
+        });
+
+        let early = make_test_snap(280_000, Some(50000.0), 500.0, 500.0);
+        assert!(strat.on_tick(&early).is_empty());
+        let late = make_test_snap(286_000, Some(50000.0), 500.0, 500.0);
+        assert_eq!(strat.on_tick(&late).len(), 1);
+    }
+
+    #[test]
+    fn test_snapshot_handle_reads_like_the_map() {
+        // Every way a script can read the handle agrees with snap_to_dynamic.
+        let source = r#"
+fn on_tick(snap) {
+    let map = snap.to_map();
+    let ok = snap.keys().len() == map.keys().len()
+        && snap["yes_bid"] == map.yes_bid
+        && "no_depth" in snap && !("nope" in snap)
+        && snap.nope == ()
+        && snap.yes_depth[0].size == map.yes_depth[0].size
+        && yes_depth_at(snap, 0.49) == yes_depth_at(map, 0.49)
+        && no_ask_depth_at(snap, 0.51) == no_ask_depth_at(map, 0.51)
+        && history(0).offset_ms == snap.offset_ms;
+    map.yes_bid = 0.0;
+    if ok && snap.yes_bid > 0.0 { [bid("yes", BID_PRICE, SHARES)] } else { [] }
+}
+fn on_reset() {}
+"#;
+        let mut strat = RhaiStrategy::from_source("test", source, 10.0, 0.49).unwrap();
+        let snap = make_test_snap(1_000, Some(50000.0), 420.0, 500.0);
+        assert_eq!(strat.on_tick(&snap).len(), 1);
+        assert_eq!(strat.error_count(), 0);
+
+        let handle = ScriptSnapshot::new(&snap, &OrderState::default(), None);
+        assert_eq!(handle.to_map().to_string(), snap_to_dynamic(&snap, &OrderState::default(), None).to_string());
+    }
+
+    #[test]
+    fn test_parse_script_param_types() {
+        let (k, v) = parse_script_param("MIN_BPS=7.5").unwrap();
+        assert_eq!(k, "MIN_BPS");
+        assert_eq!(v.as_float().unwrap(), 7.5);
+        assert_eq!(parse_script_param("N=3").unwrap().1.as_int().unwrap(), 3);
+        assert!(parse_script_param("on=true").unwrap().1.as_bool().unwrap());
+        assert_eq!(
+            parse_script_param("side=yes").unwrap().1.into_string().unwrap(),
+            "yes"
+        );
+
+        assert!(parse_script_param("novalue").is_err());
+        assert!(parse_script_param("1x=2").is_err());
+        assert!(parse_script_param("a-b=2").is_err());
+    }
+
+    #[test]
+    fn test_script_params_in_scope() {
+        let source = r#"
+let threshold = MIN_BPS * 2.0;
+fn on_tick(snap) {
+    if threshold > 10.0 && SIDE == "no" {
+        [bid(SIDE, BID_PRICE, SHARES)]
+    } else {
+        []
+    }
+}
+fn on_reset() {}
+"#;
+        let snap = make_test_snap(0, Some(50000.0), 500.0, 500.0);
+        let params = |bps: &str| {
+            vec![
+                parse_script_param(&format!("MIN_BPS={}", bps)).unwrap(),
+                parse_script_param("SIDE=no").unwrap(),
+            ]
+        };
+
+        let mut low =
+            RhaiStrategy::from_source_with_params("t", source, 10.0, 0.49, &params("4.0")).unwrap();
+        assert!(low.on_tick(&snap).is_empty());
+
+        let mut high =
+            RhaiStrategy::from_source_with_params("t", source, 10.0, 0.49, &params("6.0")).unwrap();
+        assert!(matches!(
+            high.on_tick(&snap)[..],
+            [Action::PlaceBid { side: Side::No, .. }]
+        ));
+
+        let reserved = vec![parse_script_param("SHARES=5").unwrap()];
+        assert!(RhaiStrategy::from_source_with_params("t", source, 10.0, 0.49, &reserved).is_err());
+    }
+
+    #[test]
+    fn test_indicator_state_across_ticks() {
+        let source = r#"
+let mom = bps_since_open();
+let avg = sma(2);
+
+fn on_tick(snap) {
+    mom.update(snap.oracle_price);
+    avg.update(snap.oracle_price);
+    if avg.ready && mom.value > 5.0 {
+        [bid("yes", BID_PRICE, SHARES)]
+    } else {
+        []
+    }
+}
+fn on_reset() {
+    mom.reset();
+    avg.reset();
+}
+"#;
+        let mut strat = RhaiStrategy::from_source("test", source, 10.0, 0.49).unwrap();
+        let tick = |oracle| make_test_snap(0, Some(oracle), 500.0, 500.0);
+
+        assert!(strat.on_tick(&tick(50_000.0)).is_empty());
+        assert_eq!(strat.on_tick(&tick(50_050.0)).len(), 1);
+
+        strat.reset();
+        assert!(strat.on_tick(&tick(50_050.0)).is_empty());
+    }
+
+    const LOOP_ON_SECOND_TICK: &str = r#"
+let ticks = 0;
+fn on_tick(snap) {
+    ticks += 1;
+    if ticks == 1 {
+        return [bid("yes", BID_PRICE, SHARES)];
+    }
+    loop {}
+}
+fn on_reset() { ticks = 0; }
+"#;
+
+    #[test]
+    fn test_history_helpers() {
+        let source = r#"
+fn on_tick(snap) {
+    let prev = history(1);
+    let ago = oracle_ago(2);
+    if prev == () || ago == () {
+        return [];
+    }
+    if snap.oracle_price > ago && history(3) == () {
+        return [bid("yes", prev.yes_bid, SHARES)];
+    }
+    []
+}
+fn on_reset() {}
+"#;
+        let mut strat = RhaiStrategy::from_source("hist", source, 10.0, 0.49).unwrap();
+        // Ticks one second apart: 0s, 1s, 2s -- oracle_ago(2) first resolves on the third.
+        assert!(strat.on_tick(&make_test_snap(0, Some(100.0), 500.0, 500.0)).is_empty());
+        assert!(strat.on_tick(&make_test_snap(1000, Some(101.0), 500.0, 500.0)).is_empty());
+        let actions = strat.on_tick(&make_test_snap(2000, Some(102.0), 500.0, 500.0));
+        assert_eq!(actions.len(), 1);
+
+        // Reset clears the buffer for the next window.
+        strat.reset();
+        assert!(strat.on_tick(&make_test_snap(2000, Some(102.0), 500.0, 500.0)).is_empty());
+
+        // A two-entry buffer cannot reach back two seconds.
+        let mut short = RhaiStrategy::from_source("hist", source, 10.0, 0.49)
+            .unwrap()
+            .with_history(2);
+        for (i, px) in [100.0, 101.0, 102.0].into_iter().enumerate() {
+            let snap = make_test_snap(i as i64 * 1000, Some(px), 500.0, 500.0);
+            assert!(short.on_tick(&snap).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_scheduling_helpers() {
+        let source = r#"
+fn on_tick(snap) {
+    if schedule(240000) {
+        return [bid("yes", BID_PRICE, SHARES)];
+    }
+    if remaining_ms(snap) <= 15000 && elapsed_fraction(snap) >= 0.95 {
+        return [cancel("yes")];
+    }
+    []
+}
+fn on_reset() {}
+"#;
+        let mut strat = RhaiStrategy::from_source("sched", source, 10.0, 0.49).unwrap();
+        strat.on_market(&Market {
+            id: "btc-updown-5m-1700000000".to_string(),
+            platform: crate::types::Platform::Polymarket,
+            description: String::new(),
+            category: "btc".to_string(),
+            open_ts: UnixSecs::new(1_700_000_000),
+            close_ts: UnixSecs::new(1_700_000_300),
+            duration_secs: 300,
+            outcome: None,
+            outcome_ts: None,
+        });
+
+        let tick = |strat: &mut RhaiStrategy, offset| {
+            strat.on_tick(&make_test_snap(offset, Some(50000.0), 500.0, 500.0))
+        };
+        assert!(tick(&mut strat, 239_000).is_empty());
+        // Fires once on the first tick past the target, even if it skips it.
+        assert!(matches!(tick(&mut strat, 241_000)[..], [Action::PlaceBid { .. }]));
+        assert!(tick(&mut strat, 242_000).is_empty());
+        assert!(matches!(tick(&mut strat, 290_000)[..], [Action::Cancel { .. }]));
+
+        // Each window gets its own trigger.
+        strat.reset();
+        assert!(matches!(tick(&mut strat, 250_000)[..], [Action::PlaceBid { .. }]));
+    }
+
+    #[test]
+    fn test_log_and_debug_available() {
+        let source = r#"
+fn on_tick(snap) {
+    log(`tick at ${snap.offset_ms}`);
+    debug(snap.yes_bid);
+    [bid("yes", BID_PRICE, SHARES)]
+}
+fn on_reset() { log("reset"); }
+"#;
+        let mut strat = RhaiStrategy::from_source("logging", source, 10.0, 0.49).unwrap();
+        let snap = make_test_snap(0, Some(50000.0), 500.0, 500.0);
+        // An unknown function would be a script error and yield no actions.
+        assert_eq!(strat.on_tick(&snap).len(), 1);
+        strat.reset();
+    }
+
+    #[test]
+    fn test_persistent_state_across_windows() {
+        let source = r#"
+let PERSIST_STATE = true;
+fn on_tick(snap) {
+    if state.windows == () { state.windows = 0; }
+    state.windows += 1;
+    [bid("yes", BID_PRICE, state.windows.to_float())]
+}
+fn on_reset() {}
+"#;
+        let shares = |actions: Vec<Action>| match actions[0] {
+            Action::PlaceBid { shares, .. } => shares.to_f64(),
+            _ => panic!("expected a bid"),
+        };
+        let snap = make_test_snap(0, Some(50000.0), 500.0, 500.0);
+        let shared = PersistentState::default();
+
+        // Fresh instance per window, as the engine does.
+        for expected in [1.0, 2.0, 3.0] {
+            let mut strat = RhaiStrategy::from_source("persist", source, 10.0, 0.49)
+                .unwrap()
+                .with_persistent_state(&shared);
+            strat.reset();
+            assert_eq!(shares(strat.on_tick(&snap)), expected);
+        }
+        shared.clear();
+        let mut strat = RhaiStrategy::from_source("persist", source, 10.0, 0.49)
+            .unwrap()
+            .with_persistent_state(&shared);
+        assert_eq!(shares(strat.on_tick(&snap)), 1.0);
+
+        // Without the opt-in, state is per-window even on a shared handle.
+        let source = source.replace("let PERSIST_STATE = true;", "");
+        let mut strat = RhaiStrategy::from_source("fresh", &source, 10.0, 0.49)
+            .unwrap()
+            .with_persistent_state(&shared);
+        assert_eq!(shares(strat.on_tick(&snap)), 1.0);
+        strat.reset();
+        assert_eq!(shares(strat.on_tick(&snap)), 1.0);
+    }
+
+    #[test]
+    fn test_operation_limit_aborts() {
+        let mut strat =
+            RhaiStrategy::from_source("loop", LOOP_ON_SECOND_TICK, 10.0, 0.49).unwrap();
+        let snap = make_test_snap(0, Some(50000.0), 500.0, 500.0);
+
+        assert_eq!(strat.on_tick(&snap).len(), 1);
+        assert!(strat.abort_reason().is_none());
+
+        assert!(strat.on_tick(&snap).is_empty());
+        let reason = strat.abort_reason().unwrap();
+        assert!(reason.contains("operation limit"), "reason: {}", reason);
+
+        // Stays aborted until reset; reset restores normal operation.
+        assert!(strat.on_tick(&snap).is_empty());
+        strat.reset();
+        assert!(strat.abort_reason().is_none());
+        assert_eq!(strat.on_tick(&snap).len(), 1);
+    }
+
+    #[test]
+    fn test_timeout_and_call_depth_abort() {
+        let mut strat = RhaiStrategy::from_source("loop", LOOP_ON_SECOND_TICK, 10.0, 0.49)
+            .unwrap()
+            .with_limits(ScriptLimits {
+                max_operations: 0,
+                timeout: Duration::from_millis(20),
+                ..ScriptLimits::default()
+            });
+        let snap = make_test_snap(0, Some(50000.0), 500.0, 500.0);
+        strat.on_tick(&snap);
+        strat.on_tick(&snap);
+        assert!(strat.abort_reason().unwrap().contains("timed out"));
+
+        let source = r#"
+fn deep(n) { deep(n + 1) }
+fn on_tick(snap) { deep(0); [] }
+fn on_reset() {}
+"#;
+        let mut strat = RhaiStrategy::from_source("deep", source, 10.0, 0.49).unwrap();
+        strat.on_tick(&snap);
+        assert!(strat.abort_reason().unwrap().contains("call depth"));
+    }
+
+    #[test]
+    fn test_runaway_initialization_rejected() {
+        let source = "loop {} fn on_tick(snap) { [] } fn on_reset() {}";
+        let err = RhaiStrategy::from_source("init", source, 10.0, 0.49).unwrap_err();
+        assert!(err.to_string().contains("operation limit"), "{}", err);
+    }
+
+    #[test]
+    fn test_compiled_script_starts_fresh_instances() {
+        let source = r#"
+let count = 0;
+fn on_tick(snap) {
+    count += 1;
+    if count == 1 { [bid("yes", BID_PRICE, SHARES)] } else { [] }
+}
+fn on_reset() {}
+"#;
+        let script = CompiledScript::new("test", source, 10.0, 0.49, &[]).unwrap();
+        let snap = make_test_snap(0, Some(50000.0), 500.0, 500.0);
+        let mut first = RhaiStrategy::from_compiled(&script);
+        assert_eq!(first.on_tick(&snap).len(), 1);
+        assert!(first.on_tick(&snap).is_empty());
+        let mut second = RhaiStrategy::from_compiled(&script);
+        assert_eq!(second.on_tick(&snap).len(), 1, "the top level runs again");
+
+        // A top level that fails aborts the instance instead of panicking.
+        let source = "loop {} fn on_tick(snap) { [] } fn on_reset() {}";
+        let script = CompiledScript::new("init", source, 10.0, 0.49, &[]).unwrap();
+        let mut strat = RhaiStrategy::from_compiled(&script);
+        strat.reset();
+        assert!(strat.abort_reason().unwrap().contains("operation limit"));
+        assert!(strat.on_tick(&snap).is_empty());
+    }
 }
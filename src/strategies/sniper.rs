@@ -0,0 +1,94 @@
+use crate::fill::queue::queue_position;
+use crate::strategies::Strategy;
+use crate::types::{Action, BookSnapshot, Price, Shares, Side};
+
+/// Queue-aware sniper: bid a side only when the queue there is short.
+///
+/// Like spread_arb it bids both sides at a fixed price, but it estimates
+/// its own queue position first ([`queue_position`] on the current
+/// snapshot, the same estimate the fill model starts from) and refuses to
+/// join while more than `max_queue_ahead` shares would be ahead of it.
+/// Each side is bid at most once per window, on the first tick its queue
+/// is short enough.
+pub struct QueueSniper {
+    bid_price: Price,
+    shares: Shares,
+    /// Most shares worth queueing behind.
+    max_queue_ahead: f64,
+    /// Whether each side (YES, NO) has been bid this window.
+    placed: [bool; 2],
+}
+
+impl QueueSniper {
+    pub fn new(bid_price: f64, shares: f64, max_queue_ahead: f64) -> Self {
+        Self {
+            bid_price: Price::from_f64(bid_price),
+            shares: Shares::from_f64(shares),
+            max_queue_ahead,
+            placed: [false; 2],
+        }
+    }
+}
+
+impl Strategy for QueueSniper {
+    fn name(&self) -> &str {
+        "sniper"
+    }
+
+    fn description(&self) -> &str {
+        "Queue-aware sniper: bid each side only while the estimated queue ahead is short"
+    }
+
+    fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action> {
+        let mut actions = vec![];
+        for (i, side) in [Side::Yes, Side::No].into_iter().enumerate() {
+            if self.placed[i] || queue_position(snap, side, self.bid_price.to_f64()) > self.max_queue_ahead {
+                continue;
+            }
+            self.placed[i] = true;
+            actions.push(Action::PlaceBid {
+                side,
+                price: self.bid_price,
+                shares: self.shares,
+            });
+        }
+        actions
+    }
+
+    fn reset(&mut self) {
+        self.placed = [false; 2];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategies::make_test_snap;
+
+    #[test]
+    fn bids_only_behind_short_queues() {
+        let mut strat = QueueSniper::new(0.49, 10.0, 100.0);
+        // YES has 50 shares ahead at 0.49, NO has 500.
+        let actions = strat.on_tick(&make_test_snap(0, None, 50.0, 500.0));
+        assert!(matches!(actions[..], [Action::PlaceBid { side: Side::Yes, .. }]), "{:?}", actions);
+
+        // NO's queue thins out; YES isn't bid twice.
+        assert!(strat.on_tick(&make_test_snap(1_000, None, 50.0, 200.0)).is_empty());
+        let actions = strat.on_tick(&make_test_snap(2_000, None, 50.0, 100.0));
+        assert!(matches!(actions[..], [Action::PlaceBid { side: Side::No, .. }]), "{:?}", actions);
+        assert!(strat.on_tick(&make_test_snap(3_000, None, 0.0, 0.0)).is_empty());
+
+        strat.reset();
+        assert_eq!(strat.on_tick(&make_test_snap(0, None, 0.0, 0.0)).len(), 2);
+    }
+
+    #[test]
+    fn reads_the_queue_at_its_own_price() {
+        // The captured level is 0.49; above it nothing is ahead.
+        let mut strat = QueueSniper::new(0.50, 10.0, 100.0);
+        assert_eq!(strat.on_tick(&make_test_snap(0, None, 500.0, 500.0)).len(), 2);
+        // Below it, all 500 shares are.
+        let mut strat = QueueSniper::new(0.48, 10.0, 100.0);
+        assert!(strat.on_tick(&make_test_snap(0, None, 500.0, 500.0)).is_empty());
+    }
+}
@@ -1,21 +1,21 @@
 use crate::strategies::Strategy;
-use crate::types::{Action, BookSnapshot, Side};
+use crate::types::{Action, BookSnapshot, Price, Shares, Side};
 
 /// Naive spread arb: place YES + NO bids at T+0, never cancel.
 ///
 /// This is the baseline "dumb" strategy. It always bids both sides at the
 /// configured price and hopes both fill for a guaranteed profit.
 pub struct NaiveSpreadArb {
-    bid_price: f64,
-    shares: f64,
+    bid_price: Price,
+    shares: Shares,
     placed: bool,
 }
 
 impl NaiveSpreadArb {
     pub fn new(bid_price: f64, shares: f64) -> Self {
         Self {
-            bid_price,
-            shares,
+            bid_price: Price::from_f64(bid_price),
+            shares: Shares::from_f64(shares),
             placed: false,
         }
     }
@@ -69,16 +69,16 @@ mod tests {
         match &actions[0] {
             Action::PlaceBid { side, price, shares } => {
                 assert_eq!(*side, Side::Yes);
-                assert!((price - 0.49).abs() < f64::EPSILON);
-                assert!((shares - 100.0).abs() < f64::EPSILON);
+                assert_eq!(*price, Price::from_f64(0.49));
+                assert_eq!(*shares, Shares::from_f64(100.0));
             }
             _ => panic!("expected PlaceBid"),
         }
         match &actions[1] {
             Action::PlaceBid { side, price, shares } => {
                 assert_eq!(*side, Side::No);
-                assert!((price - 0.49).abs() < f64::EPSILON);
-                assert!((shares - 100.0).abs() < f64::EPSILON);
+                assert_eq!(*price, Price::from_f64(0.49));
+                assert_eq!(*shares, Shares::from_f64(100.0));
             }
             _ => panic!("expected PlaceBid"),
         }
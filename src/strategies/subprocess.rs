@@ -0,0 +1,433 @@
+//! Strategies run as a separate process (`pf run --exec`).
+//!
+//! The engine talks to the program over its stdin and stdout, one JSON
+//! object per line, so a strategy can be written in any language that reads
+//! and writes JSON, without embedding an interpreter. Each message to the
+//! process has a `type`:
+//!
+//! - `init`: once, after the process starts, with `shares` and `bid_price`.
+//! - `market`: at the start of each window, with the window's `market`.
+//! - `open`: on the window's first snapshot, with `snap` and `orders`.
+//! - `tick`: on every snapshot, with `snap` and `orders`. The process
+//!   replies with one line: a JSON array of actions.
+//! - `reset`: between windows.
+//!
+//! Only `tick` expects a reply. `snap` is a [`BookSnapshot`] in its wire
+//! format. `orders` holds the strategy's order on each side under `yes` and
+//! `no`, with the fields of a Rhai script's `snap.orders` maps. Actions are
+//! the objects the script helpers build, e.g. `{"type": "bid", "side":
+//! "yes", "price": 0.49, "shares": 10}`; `take` has `shares` and
+//! `max_price`, `amend` and `sell` have `price` and `shares`, and `cancel`
+//! only `side`. What the process writes to stderr passes through.
+//!
+//! Processes come from a [`SubprocessPool`]. A strategy takes an idle
+//! process or starts one, and hands it back when dropped, so windows run
+//! one after another share a process and parallel Monte Carlo runs each get
+//! their own. A process that exits, or doesn't reply in time, aborts its
+//! window and is discarded; the next window starts a fresh one. Processes
+//! should exit when their input closes.
+
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Value};
+
+use crate::strategies::Strategy;
+use crate::types::{Action, BookSnapshot, Market, OrderState, Price, Shares, Side, SideOrderState};
+
+/// The program to run and the settings sent with `init`.
+#[derive(Debug, Clone)]
+pub struct SubprocessCommand {
+    pub program: PathBuf,
+    pub args: Vec<String>,
+    pub shares: f64,
+    pub bid_price: f64,
+    /// How long to wait for a `tick` reply before aborting the window.
+    pub timeout: Duration,
+}
+
+impl SubprocessCommand {
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+            shares: 10.0,
+            bid_price: 0.49,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// The program's file stem, used as the strategy name.
+    fn name(&self) -> String {
+        self.program
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "subprocess".to_string())
+    }
+}
+
+/// A running strategy process.
+struct Process {
+    child: Child,
+    stdin: Option<BufWriter<ChildStdin>>,
+    /// Lines of stdout, read on a thread so replies can time out.
+    lines: Receiver<std::io::Result<String>>,
+}
+
+impl Process {
+    fn spawn(command: &SubprocessCommand) -> Result<Self> {
+        let mut child = Command::new(&command.program)
+            .args(&command.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .with_context(|| format!("failed to start {}", command.program.display()))?;
+        let stdin = BufWriter::new(child.stdin.take().expect("stdin is piped"));
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (tx, lines) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut process = Self {
+            child,
+            stdin: Some(stdin),
+            lines,
+        };
+        process.send(&json!({
+            "type": "init",
+            "shares": command.shares,
+            "bid_price": command.bid_price,
+        }))?;
+        Ok(process)
+    }
+
+    fn send(&mut self, message: &Value) -> Result<()> {
+        let stdin = self.stdin.as_mut().expect("stdin is open until drop");
+        serde_json::to_writer(&mut *stdin, message)?;
+        stdin.write_all(b"\n")?;
+        stdin.flush().context("strategy process closed its input")
+    }
+
+    fn receive(&mut self, timeout: Duration) -> Result<String> {
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => line.context("failed to read from strategy process"),
+            Err(RecvTimeoutError::Timeout) => bail!("no reply within {:?}", timeout),
+            Err(RecvTimeoutError::Disconnected) => bail!("strategy process exited"),
+        }
+    }
+}
+
+impl Drop for Process {
+    /// Close the process's input and give it a moment to exit on its own.
+    fn drop(&mut self) {
+        drop(self.stdin.take());
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if !matches!(self.child.try_wait(), Ok(None)) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+struct PoolInner {
+    command: SubprocessCommand,
+    idle: Mutex<Vec<Process>>,
+}
+
+/// Starts strategy processes and keeps the idle ones; clones share them.
+#[derive(Clone)]
+pub struct SubprocessPool(Arc<PoolInner>);
+
+impl SubprocessPool {
+    pub fn new(command: SubprocessCommand) -> Self {
+        Self(Arc::new(PoolInner {
+            command,
+            idle: Mutex::new(Vec::new()),
+        }))
+    }
+
+    fn take(&self) -> Result<Process> {
+        let idle = self.0.idle.lock().unwrap().pop();
+        match idle {
+            Some(process) => Ok(process),
+            None => Process::spawn(&self.0.command),
+        }
+    }
+
+    /// A strategy backed by an idle process, or a new one.
+    pub fn strategy(&self) -> Result<SubprocessStrategy> {
+        Ok(SubprocessStrategy {
+            process: Some(self.take()?),
+            pool: self.clone(),
+            name: self.0.command.name(),
+            description: self.0.command.program.display().to_string(),
+            orders: OrderState::default(),
+            errors: 0,
+            aborted: None,
+        })
+    }
+}
+
+/// A strategy whose decisions come from a process; see the
+/// [module docs](self).
+pub struct SubprocessStrategy {
+    process: Option<Process>,
+    pool: SubprocessPool,
+    name: String,
+    description: String,
+    orders: OrderState,
+    errors: usize,
+    aborted: Option<String>,
+}
+
+impl std::fmt::Debug for SubprocessStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubprocessStrategy")
+            .field("name", &self.name)
+            .field("program", &self.description)
+            .finish()
+    }
+}
+
+impl SubprocessStrategy {
+    /// Drop the process and abort the window.
+    fn fail(&mut self, err: anyhow::Error) {
+        self.process = None;
+        self.aborted = Some(format!("{:#}", err));
+    }
+
+    /// Send a message that needs no reply.
+    fn notify(&mut self, message: Value) {
+        let Some(process) = self.process.as_mut() else {
+            return;
+        };
+        if let Err(e) = process.send(&message) {
+            self.fail(e);
+        }
+    }
+
+    fn message(&self, kind: &str, snap: &BookSnapshot) -> Value {
+        json!({
+            "type": kind,
+            "snap": snap,
+            "orders": {
+                "yes": side_order_json(Side::Yes, &self.orders.yes),
+                "no": side_order_json(Side::No, &self.orders.no),
+            },
+        })
+    }
+}
+
+impl Drop for SubprocessStrategy {
+    fn drop(&mut self) {
+        if let Some(process) = self.process.take() {
+            self.pool.0.idle.lock().unwrap().push(process);
+        }
+    }
+}
+
+impl Strategy for SubprocessStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn on_market(&mut self, market: &Market) {
+        self.notify(json!({ "type": "market", "market": market }));
+    }
+
+    fn on_market_open(&mut self, snap: &BookSnapshot) {
+        self.notify(self.message("open", snap));
+    }
+
+    fn on_order_update(&mut self, orders: &OrderState) {
+        self.orders = orders.clone();
+    }
+
+    fn on_tick(&mut self, snap: &BookSnapshot) -> Vec<Action> {
+        let message = self.message("tick", snap);
+        let Some(process) = self.process.as_mut() else {
+            return vec![];
+        };
+        let timeout = self.pool.0.command.timeout;
+        let line = match process.send(&message).and_then(|()| process.receive(timeout)) {
+            Ok(line) => line,
+            Err(e) => {
+                self.fail(e);
+                return vec![];
+            }
+        };
+        match serde_json::from_str::<Value>(&line) {
+            Ok(Value::Array(items)) => items.iter().filter_map(parse_action).collect(),
+            _ => {
+                self.errors += 1;
+                tracing::warn!(strategy = %self.name, "expected a JSON array of actions, got `{}`", line);
+                vec![]
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.orders = OrderState::default();
+        self.errors = 0;
+        self.aborted = None;
+        if self.process.is_none() {
+            match self.pool.take() {
+                Ok(process) => self.process = Some(process),
+                Err(e) => self.fail(e),
+            }
+        }
+        self.notify(json!({ "type": "reset" }));
+    }
+
+    fn abort_reason(&self) -> Option<&str> {
+        self.aborted.as_deref()
+    }
+
+    fn error_count(&self) -> usize {
+        self.errors
+    }
+}
+
+/// One side's order state, with the fields of a script's `snap.orders`
+/// map.
+fn side_order_json(side: Side, state: &SideOrderState) -> Value {
+    json!({
+        "side": side.label().to_lowercase(),
+        "placed": state.placed,
+        "cancelled": state.cancelled,
+        "filled": state.filled,
+        "price": state.price.to_f64(),
+        "shares": state.shares.to_f64(),
+        "filled_shares": state.filled_shares.to_f64(),
+        "remaining": (state.shares - state.filled_shares).to_f64(),
+        "filled_at_ms": state.filled_at_ms.map(|t| t.ms()),
+        "fill_time_ms": state.filled_at_ms.map(|t| t.ms()),
+        "queue_ahead": state.queue_ahead,
+        "sold_shares": state.sold_shares.to_f64(),
+    })
+}
+
+/// Parse one action object; malformed ones are skipped.
+fn parse_action(item: &Value) -> Option<Action> {
+    let get_f64 = |key: &str| item.get(key)?.as_f64();
+    let side = match item.get("side")?.as_str()?.to_ascii_lowercase().as_str() {
+        "yes" => Side::Yes,
+        "no" => Side::No,
+        _ => return None,
+    };
+
+    match item.get("type")?.as_str()? {
+        "bid" => Some(Action::PlaceBid {
+            side,
+            price: Price::from_f64(get_f64("price")?),
+            shares: Shares::from_f64(get_f64("shares")?),
+        }),
+        "take" => Some(Action::TakeAsk {
+            side,
+            shares: Shares::from_f64(get_f64("shares")?),
+            max_price: Price::from_f64(get_f64("max_price")?),
+        }),
+        "cancel" => Some(Action::Cancel { side }),
+        "amend" => Some(Action::Amend {
+            side,
+            new_price: Price::from_f64(get_f64("price")?),
+            new_shares: Shares::from_f64(get_f64("shares")?),
+        }),
+        "sell" => Some(Action::Sell {
+            side,
+            price: Price::from_f64(get_f64("price")?),
+            shares: Shares::from_f64(get_f64("shares")?),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::strategies::make_test_snap;
+
+    /// Bids as many shares as ticks this process has seen.
+    const COUNTER: &str = r#"
+n=0
+while read -r line; do
+  case "$line" in
+    *'"type":"tick"'*)
+      n=$((n+1))
+      echo "[{\"type\":\"bid\",\"side\":\"yes\",\"price\":0.49,\"shares\":$n}, {\"type\":\"nope\"}]" ;;
+  esac
+done
+"#;
+
+    fn pool(script: &str) -> SubprocessPool {
+        let mut command = SubprocessCommand::new("sh");
+        command.args = vec!["-c".into(), script.into()];
+        SubprocessPool::new(command)
+    }
+
+    fn bid_shares(actions: &[Action]) -> Vec<f64> {
+        actions
+            .iter()
+            .map(|a| match a {
+                Action::PlaceBid { shares, .. } => shares.to_f64(),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_windows_share_a_process() {
+        let pool = pool(COUNTER);
+        let snap = make_test_snap(1000, Some(50000.0), 500.0, 500.0);
+
+        let mut first = pool.strategy().unwrap();
+        first.reset();
+        assert_eq!(first.name(), "sh");
+        assert_eq!(bid_shares(&first.on_tick(&snap)), vec![1.0]);
+        drop(first);
+
+        // Returned to the pool: the next window sees the same process.
+        let mut second = pool.strategy().unwrap();
+        second.reset();
+        assert_eq!(bid_shares(&second.on_tick(&snap)), vec![2.0]);
+
+        // A window running at the same time gets its own.
+        let mut third = pool.strategy().unwrap();
+        assert_eq!(bid_shares(&third.on_tick(&snap)), vec![1.0]);
+    }
+
+    #[test]
+    fn test_bad_replies() {
+        let snap = make_test_snap(1000, Some(50000.0), 500.0, 500.0);
+
+        let mut garbled = pool("while read -r line; do echo 'not json'; done").strategy().unwrap();
+        assert!(garbled.on_tick(&snap).is_empty());
+        assert_eq!(garbled.error_count(), 1);
+        assert!(garbled.abort_reason().is_none());
+
+        let mut exited = pool("read -r line; exit 0").strategy().unwrap();
+        assert!(exited.on_tick(&snap).is_empty());
+        assert!(exited.abort_reason().is_some());
+
+        assert!(SubprocessPool::new(SubprocessCommand::new("/nonexistent/strategy")).strategy().is_err());
+    }
+}
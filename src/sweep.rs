@@ -0,0 +1,411 @@
+//! Parameter sweeps (grid search).
+//!
+//! A [`SweepGrid`] lists the values to try for each strategy parameter
+//! (`bid_price`, `shares`, `min_bps`, `signal_offset_ms`) and each DeLise
+//! fill-model parameter (`rf`, `adverse_fill_prob`,
+//! `winner_queue_threshold`). [`Sweep::run`] backtests every combination of
+//! a built-in strategy over the same markets with the same fill seed, so
+//! differences between points come from the parameters rather than the
+//! draws, and returns one [`Report`] per point ranked by realistic PnL:
+//!
+//! ```no_run
+//! use phantomfill::sweep::{parse_axis, Sweep, SweepGrid};
+//! # let markets = vec![];
+//! # let snapshots = |_: &str| Ok(vec![]);
+//! let sweep = Sweep {
+//!     strategy: "momentum".into(),
+//!     grid: SweepGrid {
+//!         bid_price: parse_axis("0.45:0.49:0.01")?,
+//!         min_bps: parse_axis("5,10,20")?,
+//!         ..SweepGrid::default()
+//!     },
+//!     seed: Some(42),
+//!     ..Sweep::default()
+//! };
+//! let ranked = sweep.run(&markets, &snapshots, |_, _| {})?;
+//! println!("best: {:?}", ranked[0].point);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use rand::Rng;
+use serde::Serialize;
+
+use crate::backtest::BacktestBuilder;
+use crate::fill::{DeLiseConfig, DeLiseFillModel};
+use crate::replay::ReplayConfig;
+use crate::report::Report;
+use crate::strategies::{BuiltinFactory, StrategyParams, DEFAULT_SIGNAL_OFFSET_MS};
+use crate::types::{BookSnapshot, Market};
+
+/// Values to try for each parameter. Every axis needs at least one value;
+/// the default grid is the single `pf run` default point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepGrid {
+    pub bid_price: Vec<f64>,
+    pub shares: Vec<f64>,
+    pub min_bps: Vec<f64>,
+    pub signal_offset_ms: Vec<i64>,
+    /// DeLise non-adverse fill probability per second.
+    pub rf: Vec<f64>,
+    /// DeLise fill probability on an adverse tick.
+    pub adverse_fill_prob: Vec<f64>,
+    /// DeLise max queue ahead for winner fills after the signal.
+    pub winner_queue_threshold: Vec<f64>,
+}
+
+impl Default for SweepGrid {
+    fn default() -> Self {
+        let fill = DeLiseConfig::default();
+        Self {
+            bid_price: vec![0.49],
+            shares: vec![10.0],
+            min_bps: vec![5.0],
+            signal_offset_ms: vec![DEFAULT_SIGNAL_OFFSET_MS],
+            rf: vec![fill.rf],
+            adverse_fill_prob: vec![fill.adverse_fill_prob],
+            winner_queue_threshold: vec![fill.winner_queue_threshold],
+        }
+    }
+}
+
+/// One combination of grid values.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SweepPoint {
+    pub bid_price: f64,
+    pub shares: f64,
+    pub min_bps: f64,
+    pub signal_offset_ms: i64,
+    pub rf: f64,
+    pub adverse_fill_prob: f64,
+    pub winner_queue_threshold: f64,
+}
+
+impl SweepGrid {
+    /// Number of points in the grid.
+    pub fn len(&self) -> usize {
+        self.bid_price.len()
+            * self.shares.len()
+            * self.min_bps.len()
+            * self.signal_offset_ms.len()
+            * self.rf.len()
+            * self.adverse_fill_prob.len()
+            * self.winner_queue_threshold.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every combination, with the last axis varying fastest.
+    pub fn points(&self) -> Vec<SweepPoint> {
+        let mut points = Vec::with_capacity(self.len());
+        for &bid_price in &self.bid_price {
+            for &shares in &self.shares {
+                for &min_bps in &self.min_bps {
+                    for &signal_offset_ms in &self.signal_offset_ms {
+                        for &rf in &self.rf {
+                            for &adverse_fill_prob in &self.adverse_fill_prob {
+                                for &winner_queue_threshold in &self.winner_queue_threshold {
+                                    points.push(SweepPoint {
+                                        bid_price,
+                                        shares,
+                                        min_bps,
+                                        signal_offset_ms,
+                                        rf,
+                                        adverse_fill_prob,
+                                        winner_queue_threshold,
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        points
+    }
+}
+
+/// Parse one axis: a comma-separated list (`0.45,0.47,0.49`) or an
+/// inclusive range `START:END:STEP` (`0.45:0.49:0.02`).
+pub fn parse_axis(s: &str) -> Result<Vec<f64>> {
+    let number = |v: &str| -> Result<f64> {
+        v.trim()
+            .parse::<f64>()
+            .with_context(|| format!("`{}` is not a number", v.trim()))
+    };
+    let parts: Vec<&str> = s.split(':').collect();
+    let values = match parts[..] {
+        [list] => list.split(',').map(number).collect::<Result<Vec<_>>>()?,
+        [start, end, step] => {
+            let (start, end, step) = (number(start)?, number(end)?, number(step)?);
+            if step <= 0.0 || end < start {
+                bail!("range `{}` needs START <= END and a positive STEP", s);
+            }
+            let steps = ((end - start) / step + 1e-9).floor() as usize;
+            // Round away the drift of repeated float steps (0.47000000000000003).
+            (0..=steps)
+                .map(|i| ((start + i as f64 * step) * 1e9).round() / 1e9)
+                .collect()
+        }
+        _ => bail!("`{}` is neither a list (a,b,c) nor a range (START:END:STEP)", s),
+    };
+    if values.iter().any(|v| !v.is_finite()) {
+        bail!("`{}` contains a value that is not finite", s);
+    }
+    Ok(values)
+}
+
+/// One point's backtest.
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepResult {
+    pub point: SweepPoint,
+    pub report: Report,
+}
+
+/// A grid search over a built-in strategy.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    /// Built-in strategy name (see [`create_strategy`](crate::strategies::create_strategy)).
+    pub strategy: String,
+    pub grid: SweepGrid,
+    /// Strategy settings; `bid_price`, `shares`, `min_bps` and
+    /// `signal_offset_ms` come from each point.
+    pub params: StrategyParams,
+    /// Engine settings; `bid_price` and `shares` come from each point.
+    pub config: ReplayConfig,
+    /// Fill seed shared by every point; `None` picks one at random.
+    pub seed: Option<u64>,
+}
+
+impl Default for Sweep {
+    fn default() -> Self {
+        Self {
+            strategy: "momentum".to_string(),
+            grid: SweepGrid::default(),
+            params: StrategyParams::default(),
+            config: ReplayConfig::default(),
+            seed: None,
+        }
+    }
+}
+
+impl Sweep {
+    /// Backtest every grid point over `markets`, calling `on_point` with
+    /// each point's index and result as it finishes. Returns the results
+    /// ranked by realistic PnL, best first; ties keep grid order.
+    pub fn run(
+        &self,
+        markets: &[Market],
+        snapshots: &dyn Fn(&str) -> Result<Vec<BookSnapshot>>,
+        mut on_point: impl FnMut(usize, &SweepResult),
+    ) -> Result<Vec<SweepResult>> {
+        if self.grid.is_empty() {
+            bail!("every sweep axis needs at least one value");
+        }
+        // Fail on a bad name before running anything.
+        BuiltinFactory::new(&self.strategy, self.params.clone())?;
+        let seed = self.seed.unwrap_or_else(|| rand::thread_rng().gen());
+
+        let mut results = Vec::with_capacity(self.grid.len());
+        for (i, point) in self.grid.points().into_iter().enumerate() {
+            let factory = BuiltinFactory::new(
+                &self.strategy,
+                StrategyParams {
+                    bid_price: point.bid_price,
+                    shares: point.shares,
+                    min_bps: point.min_bps,
+                    signal_offset_ms: point.signal_offset_ms,
+                    ..self.params.clone()
+                },
+            )?;
+            let fill = DeLiseConfig {
+                rf: point.rf,
+                adverse_fill_prob: point.adverse_fill_prob,
+                winner_queue_threshold: point.winner_queue_threshold,
+                signal_offset_ms: point.signal_offset_ms,
+                ..DeLiseConfig::default()
+            };
+            let output = BacktestBuilder::new()
+                .markets(markets.to_vec(), snapshots)
+                .factory(factory)
+                .fill_model(move |seed| Box::new(DeLiseFillModel::new(DeLiseConfig { seed, ..fill.clone() })))
+                .replay_config(ReplayConfig {
+                    bid_price: point.bid_price,
+                    shares: point.shares,
+                    ..self.config.clone()
+                })
+                .seed(seed)
+                .run()
+                .with_context(|| format!("sweep point {} failed", i + 1))?;
+            let result = SweepResult {
+                point,
+                report: output.report,
+            };
+            on_point(i, &result);
+            results.push(result);
+        }
+        rank(&mut results);
+        Ok(results)
+    }
+}
+
+/// Sort `results` by realistic PnL, best first; ties keep their order.
+pub fn rank(results: &mut [SweepResult]) {
+    results.sort_by(|a, b| b.report.realistic_total_pnl.total_cmp(&a.report.realistic_total_pnl));
+}
+
+/// One CSV row: the point's parameters and its headline stats.
+#[derive(Serialize)]
+struct CsvRow {
+    rank: usize,
+    strategy: String,
+    bid_price: f64,
+    shares: f64,
+    min_bps: f64,
+    signal_offset_ms: i64,
+    rf: f64,
+    adverse_fill_prob: f64,
+    winner_queue_threshold: f64,
+    trades: usize,
+    fills: usize,
+    fill_rate: f64,
+    realistic_win_rate: f64,
+    naive_total_pnl: f64,
+    realistic_total_pnl: f64,
+    phantom_fill_gap: f64,
+}
+
+/// Write ranked results to a CSV file, one row per point.
+pub fn export_csv(results: &[SweepResult], path: &Path) -> Result<()> {
+    let mut wtr =
+        csv::Writer::from_path(path).with_context(|| format!("failed to create CSV at {}", path.display()))?;
+    for (i, r) in results.iter().enumerate() {
+        let p = r.point;
+        wtr.serialize(CsvRow {
+            rank: i + 1,
+            strategy: r.report.strategy_name.clone(),
+            bid_price: p.bid_price,
+            shares: p.shares,
+            min_bps: p.min_bps,
+            signal_offset_ms: p.signal_offset_ms,
+            rf: p.rf,
+            adverse_fill_prob: p.adverse_fill_prob,
+            winner_queue_threshold: p.winner_queue_threshold,
+            trades: r.report.trades_taken,
+            fills: r.report.fills,
+            fill_rate: r.report.fill_rate,
+            realistic_win_rate: r.report.realistic_win_rate,
+            naive_total_pnl: r.report.naive_total_pnl,
+            realistic_total_pnl: r.report.realistic_total_pnl,
+            phantom_fill_gap: r.report.phantom_fill_gap,
+        })
+        .with_context(|| format!("failed to write CSV row {}", i + 1))?;
+    }
+    wtr.flush().context("failed to flush CSV")?;
+    Ok(())
+}
+
+/// Print the first `top` ranked results as a table.
+pub fn print_table(results: &[SweepResult], top: usize) {
+    println!();
+    println!("{}", "=".repeat(103));
+    println!("  Parameter sweep ({} points, top {})", results.len(), top.min(results.len()));
+    println!("{}", "=".repeat(103));
+    println!(
+        "  {:>4} {:>6} {:>7} {:>7} {:>8} {:>6} {:>6} {:>6} {:>6} {:>6} {:>9} {:>9} {:>9}",
+        "rank", "bid", "shares", "min_bps", "signal_s", "rf", "adv", "queue", "trades", "fill%", "naive", "realistic", "gap"
+    );
+    for (i, r) in results.iter().take(top).enumerate() {
+        let (p, rep) = (&r.point, &r.report);
+        println!(
+            "  {:>4} {:>6.2} {:>7} {:>7} {:>8.0} {:>6.3} {:>6.2} {:>6} {:>6} {:>6.1} {:>+9.2} {:>+9.2} {:>9.2}",
+            i + 1,
+            p.bid_price,
+            p.shares,
+            p.min_bps,
+            p.signal_offset_ms as f64 / 1000.0,
+            p.rf,
+            p.adverse_fill_prob,
+            p.winner_queue_threshold,
+            rep.trades_taken,
+            rep.fill_rate * 100.0,
+            rep.naive_total_pnl,
+            rep.realistic_total_pnl,
+            rep.phantom_fill_gap
+        );
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenarios;
+
+    #[test]
+    fn test_parse_axis() {
+        assert_eq!(parse_axis("0.45, 0.49").unwrap(), vec![0.45, 0.49]);
+        assert_eq!(parse_axis("0.45:0.49:0.01").unwrap(), vec![0.45, 0.46, 0.47, 0.48, 0.49]);
+        assert_eq!(parse_axis("60000:120000:30000").unwrap(), vec![60_000.0, 90_000.0, 120_000.0]);
+        assert!(parse_axis("0.49:0.45:0.01").is_err());
+        assert!(parse_axis("1:2").is_err());
+        assert!(parse_axis("abc").is_err());
+    }
+
+    #[test]
+    fn test_sweep_ranks_every_point() {
+        let library = scenarios::library();
+        let markets: Vec<Market> = library.iter().map(|s| s.market.clone()).collect();
+        let snapshots = |id: &str| -> Result<Vec<BookSnapshot>> {
+            Ok(library.iter().find(|s| s.market.id == id).unwrap().snapshots.clone())
+        };
+        let sweep = Sweep {
+            strategy: "momentum".into(),
+            grid: SweepGrid {
+                bid_price: vec![0.45, 0.49],
+                min_bps: vec![5.0, 10_000.0],
+                signal_offset_ms: vec![60_000, 120_000],
+                ..SweepGrid::default()
+            },
+            seed: Some(7),
+            ..Sweep::default()
+        };
+        assert_eq!(sweep.grid.len(), 8);
+
+        let mut seen = Vec::new();
+        let ranked = sweep.run(&markets, &snapshots, |i, _| seen.push(i)).unwrap();
+        assert_eq!(seen, (0..8).collect::<Vec<_>>());
+        assert_eq!(ranked.len(), 8);
+        assert!(ranked
+            .windows(2)
+            .all(|w| w[0].report.realistic_total_pnl >= w[1].report.realistic_total_pnl));
+        // No move clears 10,000 bps, so those points never trade.
+        for r in ranked.iter().filter(|r| r.point.min_bps == 10_000.0) {
+            assert_eq!(r.report.trades_taken, 0);
+        }
+        assert!(ranked.iter().any(|r| r.report.trades_taken > 0));
+
+        // Same seed, same results.
+        let again = sweep.run(&markets, &snapshots, |_, _| {}).unwrap();
+        let pnl = |rs: &[SweepResult]| rs.iter().map(|r| r.report.realistic_total_pnl).collect::<Vec<_>>();
+        assert_eq!(pnl(&ranked), pnl(&again));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sweep.csv");
+        export_csv(&ranked, &path).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert!(csv.starts_with("rank,strategy,bid_price,shares,min_bps,signal_offset_ms,rf,"), "{}", csv);
+        assert_eq!(csv.lines().count(), 9);
+
+        assert!(Sweep {
+            strategy: "nope".into(),
+            ..Sweep::default()
+        }
+        .run(&markets, &snapshots, |_, _| {})
+        .is_err());
+    }
+}
@@ -0,0 +1,320 @@
+//! Tick-by-tick log of one window (`pf run --trace-market`).
+//!
+//! When a window fills and shouldn't have, or doesn't and should, the
+//! aggregate report can't say why. [`trace_window`] replays the window and
+//! writes every snapshot the strategy saw, the actions it returned, what
+//! the engine made of them, and how the fill model judged each resting
+//! order on each tick. For the DeLise model that is whether the tick was
+//! adverse, how far the sweeps have eaten into the queue ahead, and each
+//! draw against the fill probability; build it with
+//! [`DeLiseFillModel::traced`](crate::fill::DeLiseFillModel::traced) to
+//! see those.
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+use crate::events::{EngineEvent, EventBus};
+use crate::fill::{FillCheck, FillModel, FillRule};
+use crate::replay::{ReplayConfig, ReplayEngine};
+use crate::strategies::recorder::Recorder;
+use crate::strategies::Strategy;
+use crate::types::{Action, BookSnapshot, Market, SideState, WindowResult};
+
+/// Lines waiting to be written, in the order things happened.
+type Log = Arc<Mutex<Vec<String>>>;
+
+/// Replay `market` through `strategy` and write its trace to `out`. Returns
+/// the window's result, or `None` if the market has no outcome to settle
+/// against (its ticks are still traced). `seed` is the run's seed, for
+/// latency jitter (see [`ReplayEngine::with_seed`]).
+pub fn trace_window<F: FillModel>(
+    fill_model: F,
+    config: ReplayConfig,
+    seed: u64,
+    market: &Market,
+    snapshots: &[BookSnapshot],
+    strategy: &mut dyn Strategy,
+    out: &mut dyn Write,
+) -> Result<Option<WindowResult>> {
+    let log = Log::default();
+    let bus = EventBus::new();
+    let events = log.clone();
+    bus.subscribe(move |event| {
+        if let Some(line) = describe_event(event) {
+            events.lock().unwrap().push(line);
+        }
+    });
+    let engine = ReplayEngine::new(fill_model, config).with_seed(seed).with_events(bus);
+    let flush = |out: &mut dyn Write| -> Result<()> {
+        for line in log.lock().unwrap().drain(..) {
+            writeln!(out, "{:>11}{}", "", line)?;
+        }
+        Ok(())
+    };
+
+    writeln!(
+        out,
+        "{} ({}, {}s window, outcome {}): {} snapshots",
+        market.id,
+        market.open_ts,
+        market.duration_secs,
+        market.outcome.map_or_else(|| "unknown".to_string(), |o| o.to_string()),
+        snapshots.len()
+    )?;
+    let actions = log.clone();
+    let mut strategy = Recorder::new(strategy, move |_, list: &[Action]| {
+        if !list.is_empty() {
+            let list: Vec<String> = list.iter().map(describe_action).collect();
+            actions.lock().unwrap().push(format!("strategy: {}", list.join(", ")));
+        }
+    });
+    let mut state = engine.begin_window(market, &mut strategy);
+    for snap in snapshots {
+        writeln!(out, "{}", describe_snapshot(snap))?;
+        engine.feed(&mut state, snap, &mut strategy);
+        flush(out)?;
+    }
+
+    let Some(outcome) = market.outcome else {
+        writeln!(out, "No outcome: the window is not settled.")?;
+        return Ok(None);
+    };
+    let result = engine.finish_window(market, state, outcome, &mut strategy);
+    flush(out)?;
+
+    let predicted = result.predicted.as_deref().unwrap_or("none");
+    writeln!(
+        out,
+        "Outcome {}, predicted {}{}",
+        result.outcome,
+        predicted,
+        if result.predicted.is_some() && result.correct { " (correct)" } else { "" }
+    )?;
+    match result.fill_time_ms {
+        Some(ms) => writeln!(
+            out,
+            "Filled at {:.3}s with {} ahead at placement",
+            ms as f64 / 1000.0,
+            num(result.queue_ahead_at_place)
+        )?,
+        None if result.filled => writeln!(out, "Filled in part")?,
+        None => writeln!(out, "No fill")?,
+    }
+    writeln!(out, "PnL: realistic {:+.2}, naive {:+.2}", result.realistic_pnl, result.naive_pnl)?;
+    if let Some(ref reason) = result.aborted {
+        writeln!(out, "Aborted: {}", reason)?;
+    }
+    Ok(Some(result))
+}
+
+/// `0.49`, `0.4967` or `1`.
+fn num(x: f64) -> String {
+    let s = format!("{:.4}", x);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn describe_side(state: &SideState) -> String {
+    let level = |price: Option<f64>, size: Option<f64>| match price {
+        Some(p) => format!("{}x{}", num(p), num(size.unwrap_or(0.0))),
+        None => "-".to_string(),
+    };
+    format!(
+        "{} / {}",
+        level(state.best_bid, state.best_bid_size),
+        level(state.best_ask, state.best_ask_size)
+    )
+}
+
+fn describe_snapshot(snap: &BookSnapshot) -> String {
+    let mut line = format!(
+        "{:>9.3}s  YES {}  NO {}",
+        snap.offset_ms.secs_f64(),
+        describe_side(&snap.yes),
+        describe_side(&snap.no)
+    );
+    if let Some(oracle) = snap.oracle_price {
+        line.push_str(&format!("  oracle {}", num(oracle)));
+    }
+    line
+}
+
+fn describe_action(action: &Action) -> String {
+    match action {
+        Action::PlaceBid { side, price, shares } => format!("bid {} {} @ {}", side, shares, price),
+        Action::TakeAsk { side, shares, max_price } => format!("take {} {} up to {}", side, shares, max_price),
+        Action::Cancel { side } => format!("cancel {}", side),
+        Action::Amend { side, new_price, new_shares } => format!("amend {} to {} @ {}", side, new_shares, new_price),
+        Action::Sell { side, price, shares } => format!("sell {} {} @ {}", side, shares, price),
+    }
+}
+
+fn describe_check(check: &FillCheck) -> String {
+    let head = format!("{} {}", check.order_id, check.side);
+    let draw = |fills: &str| match (check.roll, check.probability) {
+        (Some(roll), Some(p)) if roll < p => format!("roll {:.3} < {}: {} {}", roll, num(p), fills, num(check.filled)),
+        (Some(roll), Some(p)) => format!("roll {:.3} >= {}: no fill", roll, num(p)),
+        _ => "no fill".to_string(),
+    };
+    match check.rule {
+        FillRule::JustPlaced => format!("{}: placed this tick, can't fill yet", head),
+        FillRule::Adverse if check.roll.is_none() => format!(
+            "{}: adverse tick, sweeps {} of {} ahead: not reached",
+            head,
+            num(check.queue_consumed),
+            num(check.queue_ahead)
+        ),
+        FillRule::Adverse => format!(
+            "{}: adverse tick, sweeps {} of {} ahead, {}",
+            head,
+            num(check.queue_consumed),
+            num(check.queue_ahead),
+            draw("swept")
+        ),
+        FillRule::Flow => format!("{}: no sweep, flow {}", head, draw("filled")),
+        FillRule::CancelPending => format!("{}: cancel in flight, no sweep to beat it", head),
+    }
+}
+
+fn describe_event(event: &EngineEvent) -> Option<String> {
+    Some(match event {
+        EngineEvent::OrderPlaced {
+            order_id,
+            side,
+            price,
+            shares,
+            queue_ahead,
+            taker,
+            sell,
+            ..
+        } => {
+            let kind = if *sell { "sell" } else if *taker { "take" } else { "bid" };
+            let mut line = format!("placed {} {} {} {} @ {}", order_id, kind, side, num(*shares), num(*price));
+            if !taker {
+                line.push_str(&format!(", {} ahead", num(*queue_ahead)));
+            }
+            line
+        }
+        EngineEvent::OrderRejected {
+            side,
+            price,
+            shares,
+            reason,
+            ..
+        } => format!("rejected {} {} @ {}: {}", side, num(*shares), num(*price), reason),
+        EngineEvent::FillChecked { check, .. } => describe_check(check),
+        EngineEvent::OrderPartiallyFilled {
+            order_id,
+            side,
+            shares,
+            remaining,
+            ..
+        } => format!("{} {} filled {}, {} left", order_id, side, num(*shares), num(*remaining)),
+        EngineEvent::OrderFilled {
+            order_id,
+            side,
+            shares,
+            price,
+            ..
+        } => format!("{} {} filled in full: {} @ {}", order_id, side, num(*shares), num(*price)),
+        EngineEvent::OrderAmended { order_id, side, shares, .. } => {
+            format!("{} {} amended to {} shares", order_id, side, num(*shares))
+        }
+        EngineEvent::OrderCancelled { order_id, side, .. } => format!("{} {} cancelled", order_id, side),
+        EngineEvent::OrderExpired { order_id, side, .. } => format!("{} {} expired", order_id, side),
+        EngineEvent::OrderResolved { order_id, side, pnl, .. } => {
+            format!("{} {} resolved: {:+.2}", order_id, side, pnl)
+        }
+        EngineEvent::WindowCompleted(_) | EngineEvent::Progress { .. } | EngineEvent::RunCompleted { .. } => {
+            return None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use crate::fill::{DeLiseConfig, DeLiseFillModel};
+    use crate::strategies::spread_arb::NaiveSpreadArb;
+    use crate::types::{OffsetMs, Outcome, Platform, PriceLevel, UnixSecs};
+
+    fn side(ask: f64, ask_size: f64) -> Arc<SideState> {
+        Arc::new(SideState {
+            best_bid: Some(0.48),
+            best_bid_size: Some(20.0),
+            best_ask: Some(ask),
+            best_ask_size: Some(ask_size),
+            depth: [PriceLevel { price: 0.49, cumulative_size: 20.0 }].into_iter().collect(),
+            total_bid_depth: 20.0,
+            total_ask_depth: ask_size,
+        })
+    }
+
+    fn snap(offset_ms: i64, yes: Arc<SideState>, no: Arc<SideState>) -> BookSnapshot {
+        BookSnapshot {
+            market_id: "m1".into(),
+            offset_ms: OffsetMs::new(offset_ms),
+            timestamp_ms: offset_ms,
+            yes,
+            no,
+            reference_price: None,
+            oracle_price: Some(100_000.0),
+        }
+    }
+
+    #[test]
+    fn test_trace_explains_fills() {
+        let market = Market {
+            id: "m1".into(),
+            platform: Platform::Polymarket,
+            description: String::new(),
+            category: "btc".into(),
+            open_ts: UnixSecs::new(0),
+            close_ts: UnixSecs::new(300),
+            duration_secs: 300,
+            outcome: Some(Outcome::Yes),
+            outcome_ts: None,
+        };
+        let calm = side(0.51, 50.0);
+        let snapshots = vec![
+            snap(0, calm.clone(), calm.clone()),
+            // YES is swept through our bid, past the 20 ahead of us.
+            snap(1000, side(0.49, 40.0), calm.clone()),
+        ];
+        let model = DeLiseFillModel::new(DeLiseConfig {
+            rf: 0.0,
+            adverse_fill_prob: 1.0,
+            seed: Some(7),
+            ..DeLiseConfig::default()
+        })
+        .traced();
+        let mut strategy = NaiveSpreadArb::new(0.49, 10.0);
+        let mut out = Vec::new();
+        let result = trace_window(model, ReplayConfig::default(), 7, &market, &snapshots, &mut strategy, &mut out)
+            .unwrap()
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        let expected = [
+            "0.000s  YES 0.48x20 / 0.51x50  NO 0.48x20 / 0.51x50  oracle 100000",
+            "strategy: bid YES 10 @ 0.49, bid NO 10 @ 0.49",
+            "placed #1 bid YES 10 @ 0.49, 20 ahead",
+            "#1 YES: adverse tick, sweeps 40 of 20 ahead, roll",
+            "#2 NO: no sweep, flow roll",
+            "#1 YES filled in full: 10 @ 0.49",
+            "#2 NO expired",
+            "#1 YES resolved: +5.10",
+            "Outcome YES, predicted YES (correct)",
+            "Filled at 1.000s with 20 ahead at placement",
+        ];
+        let mut rest = text.as_str();
+        for line in expected {
+            let at = rest.find(line).unwrap_or_else(|| panic!("missing `{}` in:\n{}", line, text));
+            rest = &rest[at + line.len()..];
+        }
+        assert!(result.filled);
+    }
+}
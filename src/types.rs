@@ -1,24 +1,74 @@
 //! Platform-agnostic types for prediction market simulation.
 
+use std::sync::Arc;
+
 use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
 
-/// Supported platforms.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
+/// Venue a market trades on. Venues without built-in support are kept by
+/// name as [`Platform::Other`], so data from a new prediction market can be
+/// imported, stored and filtered without changing this type.
+///
+/// Names are lowercase; `Platform::from("Kalshi")` is [`Platform::Kalshi`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum Platform {
     Polymarket,
     Kalshi,
+    /// Any other venue, by its lowercase name.
+    Other(String),
 }
 
-impl std::fmt::Display for Platform {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Platform {
+    pub fn as_str(&self) -> &str {
         match self {
-            Platform::Polymarket => write!(f, "polymarket"),
-            Platform::Kalshi => write!(f, "kalshi"),
+            Platform::Polymarket => "polymarket",
+            Platform::Kalshi => "kalshi",
+            Platform::Other(name) => name,
         }
     }
 }
 
+impl From<&str> for Platform {
+    fn from(name: &str) -> Self {
+        let name = name.trim().to_ascii_lowercase();
+        match name.as_str() {
+            "polymarket" => Platform::Polymarket,
+            "kalshi" => Platform::Kalshi,
+            _ => Platform::Other(name),
+        }
+    }
+}
+
+impl From<String> for Platform {
+    fn from(name: String) -> Self {
+        Platform::from(name.as_str())
+    }
+}
+
+impl From<Platform> for String {
+    fn from(platform: Platform) -> Self {
+        match platform {
+            Platform::Other(name) => name,
+            known => known.as_str().to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for Platform {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Platform::from(s))
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Binary outcome side.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Side {
@@ -53,6 +103,11 @@ impl std::fmt::Display for Side {
 pub enum Outcome {
     Yes,
     No,
+    /// The venue cancelled the market (e.g. an invalid question) and
+    /// refunded every stake.
+    Void,
+    /// The window closed but had not resolved when the data was exported.
+    Unresolved,
 }
 
 impl Outcome {
@@ -63,10 +118,29 @@ impl Outcome {
         )
     }
 
+    /// Whether the market paid out one side. Void and unresolved windows
+    /// carry no PnL.
+    pub fn is_settled(&self) -> bool {
+        matches!(self, Outcome::Yes | Outcome::No)
+    }
+
     pub fn label(&self) -> &str {
         match self {
             Outcome::Yes => "YES",
             Outcome::No => "NO",
+            Outcome::Void => "VOID",
+            Outcome::Unresolved => "UNRESOLVED",
+        }
+    }
+
+    /// Inverse of [`label`](Self::label).
+    pub fn from_label(label: &str) -> Option<Outcome> {
+        match label {
+            "YES" => Some(Outcome::Yes),
+            "NO" => Some(Outcome::No),
+            "VOID" => Some(Outcome::Void),
+            "UNRESOLVED" => Some(Outcome::Unresolved),
+            _ => None,
         }
     }
 }
@@ -87,27 +161,64 @@ pub struct Market {
     pub description: String,
     /// Asset/category tag (e.g. "btc", "weather", "politics").
     pub category: String,
-    /// Market open timestamp (Unix seconds).
-    pub open_ts: i64,
-    /// Market close/expiry timestamp (Unix seconds).
-    pub close_ts: i64,
+    /// Market open time.
+    pub open_ts: UnixSecs,
+    /// Market close/expiry time.
+    pub close_ts: UnixSecs,
     /// Duration in seconds.
     pub duration_secs: i64,
     /// Actual outcome (if resolved).
     pub outcome: Option<Outcome>,
+    /// When the outcome was resolved, if known. Resolution often lags the
+    /// close; the book can still trade in between.
+    #[serde(default)]
+    pub outcome_ts: Option<UnixSecs>,
+}
+
+impl Market {
+    /// The close, as an offset from the open.
+    pub fn close_offset(&self) -> OffsetMs {
+        self.open_ts.offset_of(self.close_ts.to_millis())
+    }
+
+    /// The resolution, as an offset from the open: [`close_offset`](Self::close_offset)
+    /// when `outcome_ts` is unknown or not after the close.
+    pub fn resolution_offset(&self) -> OffsetMs {
+        let resolved = self.outcome_ts.map_or(self.close_ts, |ts| ts.max(self.close_ts));
+        self.open_ts.offset_of(resolved.to_millis())
+    }
 }
 
+/// Depth levels kept inline in [`DepthLevels`]; deeper ladders spill to the
+/// heap. Legacy captures ([`LEGACY_DEPTH_PRICES`]) and the HuggingFace
+/// import fit inline; full bid ladders usually don't.
+pub const INLINE_DEPTH_LEVELS: usize = 4;
+
+/// The only prices older captures record bid depth at (their
+/// `depth_at_049`, `depth_at_050` and `depth_at_051` columns). A ladder with
+/// no other levels is sparse: size may rest between its levels without
+/// showing up, so queue position interpolates across the gaps.
+pub const LEGACY_DEPTH_PRICES: [f64; 3] = [0.49, 0.50, 0.51];
+
+/// A side's depth ladder. Serializes as a plain list.
+pub type DepthLevels = SmallVec<[PriceLevel; INLINE_DEPTH_LEVELS]>;
+
+/// A market id shared by every tick and snapshot of the market, so
+/// cloning one is a reference-count bump rather than a string copy.
+/// Serializes as a plain string.
+pub type MarketId = Arc<str>;
+
 /// A single orderbook snapshot for one side of a market.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BookTick {
     /// Market identifier.
-    pub market_id: String,
+    pub market_id: MarketId,
     /// Which side of the market this tick represents.
     pub side: Side,
     /// Absolute timestamp (Unix milliseconds).
     pub timestamp_ms: i64,
-    /// Milliseconds from market open.
-    pub offset_ms: i64,
+    /// Time since market open.
+    pub offset_ms: OffsetMs,
 
     // Top of book
     pub best_bid: Option<f64>,
@@ -116,7 +227,7 @@ pub struct BookTick {
     pub best_ask_size: Option<f64>,
 
     // Depth at key price levels (cumulative shares at or better than price)
-    pub depth: Vec<PriceLevel>,
+    pub depth: DepthLevels,
 
     // Total book depth
     pub total_bid_depth: f64,
@@ -134,28 +245,83 @@ pub struct PriceLevel {
     pub cumulative_size: f64,
 }
 
+/// Which side of an executed trade crossed the spread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TakerSide {
+    /// The taker bought, lifting asks.
+    Buy,
+    /// The taker sold, hitting bids.
+    Sell,
+}
+
+impl TakerSide {
+    pub fn label(&self) -> &str {
+        match self {
+            TakerSide::Buy => "BUY",
+            TakerSide::Sell => "SELL",
+        }
+    }
+
+    /// Parse `BUY`/`SELL` in any case.
+    pub fn from_label(label: &str) -> Option<TakerSide> {
+        match label.to_ascii_uppercase().as_str() {
+            "BUY" => Some(TakerSide::Buy),
+            "SELL" => Some(TakerSide::Sell),
+            _ => None,
+        }
+    }
+}
+
+/// One executed trade in one side's token, from a market's trade tape.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trade {
+    pub market_id: MarketId,
+    pub side: Side,
+    /// Absolute timestamp (Unix milliseconds).
+    pub timestamp_ms: i64,
+    /// Time since market open.
+    pub offset_ms: OffsetMs,
+    pub price: f64,
+    /// Shares traded.
+    pub size: f64,
+    /// Who crossed the spread, when the feed says.
+    pub taker_side: Option<TakerSide>,
+}
+
 /// Combined snapshot of both sides at approximately the same time.
-#[derive(Debug, Clone)]
+///
+/// The serialized form is a wire format: see
+/// [`SnapshotSet`](crate::data::snapshots::SnapshotSet) for the versioned
+/// envelope.
+///
+/// Sides are shared: a side that did not change since the previous snapshot
+/// points at the same [`SideState`] (see
+/// [`ticks_to_snapshots`](crate::data::ticks_to_snapshots)), so cloning a
+/// snapshot never copies a depth ladder. They serialize as plain objects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BookSnapshot {
-    pub market_id: String,
-    pub offset_ms: i64,
+    pub market_id: MarketId,
+    pub offset_ms: OffsetMs,
     pub timestamp_ms: i64,
-    pub yes: SideState,
-    pub no: SideState,
+    pub yes: Arc<SideState>,
+    pub no: Arc<SideState>,
     /// External reference (e.g. BTC/USD spot price).
     pub reference_price: Option<f64>,
     /// Oracle resolution price (e.g. Chainlink BTC/USD).
     pub oracle_price: Option<f64>,
 }
 
-/// State of one side of the book at a point in time.
-#[derive(Debug, Clone, Default)]
+/// State of one side of the book at a point in time. Missing fields
+/// deserialize as an empty side.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SideState {
     pub best_bid: Option<f64>,
     pub best_bid_size: Option<f64>,
     pub best_ask: Option<f64>,
     pub best_ask_size: Option<f64>,
-    pub depth: Vec<PriceLevel>,
+    pub depth: DepthLevels,
     pub total_bid_depth: f64,
     pub total_ask_depth: f64,
 }
@@ -181,37 +347,901 @@ impl SideState {
             .map(|l| l.cumulative_size)
             .unwrap_or(0.0)
     }
+
+    /// Midpoint of the best bid and ask, if the side has both.
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.best_bid? + self.best_ask?) / 2.0)
+    }
+
+    /// Best ask minus best bid, if the side has both.
+    pub fn spread(&self) -> Option<f64> {
+        Some(self.best_ask? - self.best_bid?)
+    }
+
+    /// [`spread`](Self::spread) in basis points of the mid.
+    pub fn spread_bps(&self) -> Option<f64> {
+        let mid = self.mid().filter(|&m| m > 0.0)?;
+        Some(self.spread()? / mid * 10_000.0)
+    }
+}
+
+impl BookSnapshot {
+    /// The book of one side.
+    pub fn side(&self, side: Side) -> &SideState {
+        match side {
+            Side::Yes => &self.yes,
+            Side::No => &self.no,
+        }
+    }
+
+    pub fn yes_mid(&self) -> Option<f64> {
+        self.yes.mid()
+    }
+
+    pub fn no_mid(&self) -> Option<f64> {
+        self.no.mid()
+    }
+
+    /// Mid of `side`'s book; see [`SideState::mid`].
+    pub fn mid(&self, side: Side) -> Option<f64> {
+        self.side(side).mid()
+    }
+
+    /// The market's probability that `side` resolves true: `side`'s mid,
+    /// else one minus the opposite side's mid, else whichever of `side`'s
+    /// bid and ask it has.
+    pub fn implied_prob(&self, side: Side) -> Option<f64> {
+        let book = self.side(side);
+        book.mid()
+            .or_else(|| self.mid(side.opposite()).map(|m| 1.0 - m))
+            .or(book.best_bid)
+            .or(book.best_ask)
+    }
+
+    /// YES best bid plus NO best bid, if both sides have one. Below 1.0,
+    /// buying both at the bid locks in the difference.
+    pub fn combined_bid(&self) -> Option<f64> {
+        Some(self.yes.best_bid? + self.no.best_bid?)
+    }
+
+    /// Spread of `side`'s book in basis points of its mid.
+    pub fn spread_bps(&self, side: Side) -> Option<f64> {
+        self.side(side).spread_bps()
+    }
+
+    /// Ask ladder for `side`: cumulative shares offered at or below each
+    /// price, sorted by price.
+    ///
+    /// Captures only record bid levels, so this is mirrored from the
+    /// opposite side's bids -- a YES ask at `p` is a NO bid at `1 - p`.
+    pub fn ask_depth(&self, side: Side) -> Vec<PriceLevel> {
+        let opposite = match side {
+            Side::Yes => &self.no,
+            Side::No => &self.yes,
+        };
+        let mut levels: Vec<PriceLevel> = opposite
+            .depth
+            .iter()
+            .map(|l| PriceLevel {
+                price: 1.0 - l.price,
+                cumulative_size: l.cumulative_size,
+            })
+            .collect();
+        levels.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap_or(std::cmp::Ordering::Equal));
+        levels
+    }
+
+    /// Cumulative ask depth on `side` at a given price.
+    ///
+    /// Exact level first; otherwise the nearest level at or below the
+    /// requested price (the shares a taker could buy without paying more).
+    pub fn ask_depth_at(&self, side: Side, price: f64) -> f64 {
+        const EPSILON: f64 = 1e-9;
+
+        let levels = self.ask_depth(side);
+        if let Some(level) = levels.iter().find(|l| (l.price - price).abs() < EPSILON) {
+            return level.cumulative_size;
+        }
+        levels
+            .iter()
+            .filter(|l| l.price <= price)
+            .max_by(|a, b| a.price.partial_cmp(&b.price).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|l| l.cumulative_size)
+            .unwrap_or(0.0)
+    }
+}
+
+/// An order price as a whole number of hundredths of a cent, so prices
+/// compare exactly at tick boundaries: `0.49000000000000004` from the feed
+/// and a `0.49` bid are the same `Price`. Sub-cent ticks (Polymarket's
+/// $0.001 near 0 and 1) stay exact too.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Price(i64);
+
+impl Price {
+    /// Units per dollar.
+    pub const SCALE: i64 = 10_000;
+    /// $1, what a winning share pays out.
+    pub const ONE: Price = Price(Self::SCALE);
+
+    pub const fn from_units(units: i64) -> Self {
+        Self(units)
+    }
+
+    /// The nearest `Price` to a dollar amount.
+    pub fn from_f64(price: f64) -> Self {
+        Self((price * Self::SCALE as f64).round() as i64)
+    }
+
+    pub const fn units(self) -> i64 {
+        self.0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / Self::SCALE as f64
+    }
+}
+
+impl std::ops::Sub for Price {
+    type Output = Price;
+
+    fn sub(self, rhs: Price) -> Price {
+        Price(self.0 - rhs.0)
+    }
+}
+
+impl std::fmt::Display for Price {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_f64())
+    }
+}
+
+/// An order size in millionths of a share (the 6 decimals of Polymarket's
+/// outcome tokens).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Shares(i64);
+
+impl Shares {
+    /// Units per share.
+    pub const SCALE: i64 = 1_000_000;
+    /// The largest order any venue rule accepts, a billion shares: well
+    /// inside what a window's share totals can sum to without overflow.
+    pub const MAX_ORDER: Shares = Shares(1_000_000_000 * Self::SCALE);
+
+    pub const fn from_units(units: i64) -> Self {
+        Self(units)
+    }
+
+    /// The nearest `Shares` to a share count.
+    pub fn from_f64(shares: f64) -> Self {
+        Self((shares * Self::SCALE as f64).round() as i64)
+    }
+
+    pub const fn units(self) -> i64 {
+        self.0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / Self::SCALE as f64
+    }
+}
+
+impl std::ops::Add for Shares {
+    type Output = Shares;
+
+    fn add(self, rhs: Shares) -> Shares {
+        Shares(self.0 + rhs.0)
+    }
+}
+
+impl std::ops::Sub for Shares {
+    type Output = Shares;
+
+    fn sub(self, rhs: Shares) -> Shares {
+        Shares(self.0 - rhs.0)
+    }
+}
+
+impl std::fmt::Display for Shares {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_f64())
+    }
+}
+
+/// A dollar amount exact to the product of a [`Shares`] and a [`Price`],
+/// so a window's PnL adds up to the same value whatever the order of its
+/// fills. Held in an `i128`, since a notional past about $922M already
+/// overflows an `i64` of these units.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cash(i128);
+
+impl Cash {
+    /// Units per dollar.
+    pub const SCALE: i128 = Price::SCALE as i128 * Shares::SCALE as i128;
+
+    pub const fn units(self) -> i128 {
+        self.0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / Self::SCALE as f64
+    }
+
+    /// The nearest `Cash` to a dollar amount.
+    pub fn from_f64(dollars: f64) -> Self {
+        Self((dollars * Self::SCALE as f64).round() as i128)
+    }
+}
+
+impl std::ops::Mul<Price> for Shares {
+    type Output = Cash;
+
+    fn mul(self, price: Price) -> Cash {
+        Cash(self.0 as i128 * price.0 as i128)
+    }
+}
+
+impl std::ops::Add for Cash {
+    type Output = Cash;
+
+    fn add(self, rhs: Cash) -> Cash {
+        Cash(self.0 + rhs.0)
+    }
+}
+
+impl std::ops::AddAssign for Cash {
+    fn add_assign(&mut self, rhs: Cash) {
+        self.0 += rhs.0;
+    }
+}
+
+impl std::ops::SubAssign for Cash {
+    fn sub_assign(&mut self, rhs: Cash) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl std::ops::Sub for Cash {
+    type Output = Cash;
+
+    fn sub(self, rhs: Cash) -> Cash {
+        Cash(self.0 - rhs.0)
+    }
+}
+
+/// Unix timestamps above this are taken to be milliseconds: as seconds they
+/// would be past the year 5000.
+const MAX_UNIX_SECS: i64 = 100_000_000_000;
+
+/// A wall-clock time in whole seconds since the Unix epoch, UTC. Market
+/// open and close times use it so they can't be mixed up with the
+/// millisecond tick timestamps. Serializes as a plain integer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct UnixSecs(i64);
+
+impl UnixSecs {
+    pub const fn new(secs: i64) -> Self {
+        Self(secs)
+    }
+
+    /// A timestamp from an importer, rejecting values that are evidently
+    /// milliseconds rather than seconds.
+    pub fn checked(secs: i64) -> anyhow::Result<Self> {
+        if !(0..MAX_UNIX_SECS).contains(&secs) {
+            anyhow::bail!("timestamp {} is not in Unix seconds", secs);
+        }
+        Ok(Self(secs))
+    }
+
+    /// The second containing a Unix millisecond timestamp.
+    pub const fn from_millis(ms: i64) -> Self {
+        Self(ms.div_euclid(1000))
+    }
+
+    pub const fn secs(self) -> i64 {
+        self.0
+    }
+
+    pub const fn to_millis(self) -> i64 {
+        self.0 * 1000
+    }
+
+    /// How far into a window opening at `self` a Unix millisecond timestamp
+    /// falls.
+    pub const fn offset_of(self, timestamp_ms: i64) -> OffsetMs {
+        OffsetMs(timestamp_ms - self.to_millis())
+    }
+
+    /// The Unix millisecond timestamp `offset` into a window opening at
+    /// `self`.
+    pub const fn at(self, offset: OffsetMs) -> i64 {
+        self.to_millis() + offset.0
+    }
+
+    pub fn from_datetime(dt: chrono::DateTime<chrono::Utc>) -> Self {
+        Self(dt.timestamp())
+    }
+
+    /// `None` outside chrono's representable range.
+    pub fn to_datetime(self) -> Option<chrono::DateTime<chrono::Utc>> {
+        chrono::DateTime::from_timestamp(self.0, 0)
+    }
+}
+
+impl std::ops::Add<i64> for UnixSecs {
+    type Output = UnixSecs;
+
+    /// `secs` seconds later.
+    fn add(self, secs: i64) -> UnixSecs {
+        UnixSecs(self.0 + secs)
+    }
+}
+
+impl std::ops::Sub for UnixSecs {
+    type Output = i64;
+
+    /// Seconds between two times.
+    fn sub(self, rhs: UnixSecs) -> i64 {
+        self.0 - rhs.0
+    }
+}
+
+impl std::fmt::Display for UnixSecs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Milliseconds since a market opened. Compares directly with plain
+/// millisecond literals (`snap.offset_ms >= 240_000`); the difference of
+/// two offsets is a plain `i64` of milliseconds. Serializes as a plain
+/// integer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OffsetMs(i64);
+
+impl OffsetMs {
+    pub const fn new(ms: i64) -> Self {
+        Self(ms)
+    }
+
+    pub const fn from_secs(secs: i64) -> Self {
+        Self(secs * 1000)
+    }
+
+    pub const fn ms(self) -> i64 {
+        self.0
+    }
+
+    pub fn secs_f64(self) -> f64 {
+        self.0 as f64 / 1000.0
+    }
+}
+
+impl std::ops::Add<i64> for OffsetMs {
+    type Output = OffsetMs;
+
+    fn add(self, ms: i64) -> OffsetMs {
+        OffsetMs(self.0 + ms)
+    }
+}
+
+impl std::ops::Sub<i64> for OffsetMs {
+    type Output = OffsetMs;
+
+    fn sub(self, ms: i64) -> OffsetMs {
+        OffsetMs(self.0 - ms)
+    }
+}
+
+impl std::ops::Sub for OffsetMs {
+    type Output = i64;
+
+    fn sub(self, rhs: OffsetMs) -> i64 {
+        self.0 - rhs.0
+    }
+}
+
+impl PartialEq<i64> for OffsetMs {
+    fn eq(&self, ms: &i64) -> bool {
+        self.0 == *ms
+    }
+}
+
+impl PartialOrd<i64> for OffsetMs {
+    fn partial_cmp(&self, ms: &i64) -> Option<std::cmp::Ordering> {
+        self.0.partial_cmp(ms)
+    }
+}
+
+impl std::fmt::Display for OffsetMs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Order constraints of a venue. The engine rejects bids that break them
+/// rather than simulating orders the exchange would refuse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VenueRules {
+    /// Price increment.
+    pub tick_size: Price,
+    /// Increment for prices within `fine_band` of $0 or $1.
+    pub fine_tick_size: Price,
+    pub fine_band: Price,
+    /// Smallest order.
+    pub min_shares: Shares,
+    /// Order sizes must be a multiple of this.
+    pub share_increment: Shares,
+    /// Largest order, if capped.
+    pub max_shares: Option<Shares>,
+}
+
+/// Polymarket's rules, the platform of every bundled importer.
+impl Default for VenueRules {
+    fn default() -> Self {
+        Self::for_platform(&Platform::Polymarket)
+    }
+}
+
+impl VenueRules {
+    /// Polymarket quotes whole cents, tenths of a cent below 4c and above
+    /// 96c, with a 5-share minimum; Kalshi trades whole contracts at whole
+    /// cents. Other venues get whole cents and 0.01-share steps, with no
+    /// minimum beyond one step.
+    pub fn for_platform(platform: &Platform) -> Self {
+        let cent = Price::from_units(Price::SCALE / 100);
+        match platform {
+            Platform::Polymarket => Self {
+                tick_size: cent,
+                fine_tick_size: Price::from_units(Price::SCALE / 1000),
+                fine_band: Price::from_units(4 * cent.units()),
+                min_shares: Shares::from_units(5 * Shares::SCALE),
+                share_increment: Shares::from_units(Shares::SCALE / 100),
+                max_shares: None,
+            },
+            Platform::Kalshi => Self {
+                tick_size: cent,
+                fine_tick_size: cent,
+                fine_band: Price::default(),
+                min_shares: Shares::from_units(Shares::SCALE),
+                share_increment: Shares::from_units(Shares::SCALE),
+                max_shares: None,
+            },
+            Platform::Other(_) => Self {
+                tick_size: cent,
+                fine_tick_size: cent,
+                fine_band: Price::default(),
+                min_shares: Shares::from_units(Shares::SCALE / 100),
+                share_increment: Shares::from_units(Shares::SCALE / 100),
+                max_shares: None,
+            },
+        }
+    }
+
+    /// Price increment at `price`.
+    pub fn tick_size_at(&self, price: Price) -> Price {
+        if price < self.fine_band || price > Price::ONE - self.fine_band {
+            self.fine_tick_size
+        } else {
+            self.tick_size
+        }
+    }
+
+    /// Why a bid for `shares` at `price` would be refused, or `None` if it
+    /// is a valid order.
+    pub fn rejection(&self, price: Price, shares: Shares) -> Option<String> {
+        if price <= Price::default() || price >= Price::ONE {
+            return Some(format!("price {} outside (0, 1)", price));
+        }
+        let tick = self.tick_size_at(price);
+        if tick.units() > 0 && price.units() % tick.units() != 0 {
+            return Some(format!("price {} off the {} tick", price, tick));
+        }
+        if shares < self.min_shares || shares.units() <= 0 {
+            return Some(format!("{} shares below the {} minimum", shares, self.min_shares));
+        }
+        if shares > Shares::MAX_ORDER {
+            return Some(format!("{} shares above the {} maximum", shares, Shares::MAX_ORDER));
+        }
+        let step = self.share_increment.units();
+        if step > 0 && shares.units() % step != 0 {
+            return Some(format!("{} shares not a multiple of {}", shares, self.share_increment));
+        }
+        match self.max_shares {
+            Some(max) if shares > max => Some(format!("{} shares above the {} maximum", shares, max)),
+            _ => None,
+        }
+    }
+}
+
+/// How a trade fee rate scales with the fill price.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum FeeCurve {
+    /// `rate × price` a share: a fraction of the notional.
+    #[default]
+    Notional,
+    /// `rate × price × (1 − price)` a share, largest at 50c and vanishing
+    /// near 0 and 1. Kalshi's trade fee has this shape (0.07 for takers).
+    Variance,
+}
+
+/// Fees and rebates on filled shares, taken out of both naive and
+/// realistic PnL. Rates are fractions (0.02 is 2%) and a negative rate is
+/// a rebate. Fees are exact, not rounded up to the cent per order as some
+/// venues do. The default charges nothing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeeModel {
+    /// Trade fee rate on resting (maker) fills.
+    pub maker_fee: f64,
+    /// Trade fee rate on taker fills.
+    pub taker_fee: f64,
+    /// Shape of both trade fees.
+    pub curve: FeeCurve,
+    /// Dollars a share charged on every filled share at settlement,
+    /// winning or losing.
+    pub settlement_fee: f64,
+    /// Fraction of a winning share's profit ($1 − price) taken at payout.
+    pub winner_fee: f64,
+}
+
+impl FeeModel {
+    /// Every fee on `shares` bought at `price` (trade, settlement and, if
+    /// the shares `won`, winner fees). Negative for a net rebate.
+    pub fn fees(&self, price: Price, shares: Shares, taker: bool, won: bool) -> Cash {
+        let (price, shares) = (price.to_f64(), shares.to_f64());
+        let winner = if won { self.winner_fee * (1.0 - price) } else { 0.0 };
+        Cash::from_f64((self.trade_fee(price, taker) + self.settlement_fee + winner) * shares)
+    }
+
+    /// Fees on `shares` sold at `price` out of shares bought at `entry`:
+    /// the trade fee on the sale, less the settlement (and, had they `won`,
+    /// winner) fees that [`fees`](Self::fees) charged the purchase and the
+    /// sold shares no longer pay.
+    pub fn exit_fees(&self, price: Price, shares: Shares, taker: bool, won: bool, entry: Price) -> Cash {
+        let (price, shares) = (price.to_f64(), shares.to_f64());
+        let winner = if won { self.winner_fee * (1.0 - entry.to_f64()) } else { 0.0 };
+        Cash::from_f64((self.trade_fee(price, taker) - self.settlement_fee - winner) * shares)
+    }
+
+    /// Trade fee on one share traded at `price`.
+    fn trade_fee(&self, price: f64, taker: bool) -> f64 {
+        let rate = if taker { self.taker_fee } else { self.maker_fee };
+        match self.curve {
+            FeeCurve::Notional => rate * price,
+            FeeCurve::Variance => rate * price * (1.0 - price),
+        }
+    }
+}
+
+/// How [`Bankroll`] sizes each order.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum Sizing {
+    /// The share count the strategy asked for.
+    Fixed,
+    /// Stake `fraction` of the bankroll on each order.
+    FixedFraction { fraction: f64 },
+    /// Stake `fraction` of the Kelly criterion for a share bought at `p`
+    /// that wins with probability `win_prob`: `(win_prob − p) / (1 − p)`
+    /// of the bankroll. An order with no edge is refused.
+    Kelly { win_prob: f64, fraction: f64 },
+}
+
+/// Starting capital and a sizing rule. With one set on the engine, orders
+/// are sized from the running bankroll instead of a fixed share count, and
+/// never stake more cash than the window has left.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Bankroll {
+    pub starting_capital: f64,
+    pub sizing: Sizing,
+}
+
+impl Bankroll {
+    /// Shares to buy at `price` given the `requested` count, the bankroll
+    /// at the start of the window and the `cash` not yet committed in it,
+    /// rounded down to a multiple of `increment`. `Err` says why no order
+    /// can be sized.
+    pub fn size(&self, price: Price, requested: Shares, bankroll: Cash, cash: Cash, increment: Shares) -> Result<Shares, String> {
+        if cash <= Cash::default() {
+            return Err("bankroll exhausted".to_string());
+        }
+        // The venue rules refuse these prices anyway.
+        if price <= Price::default() || price >= Price::ONE {
+            return Ok(requested);
+        }
+        let stake = match self.sizing {
+            Sizing::Fixed => requested * price,
+            Sizing::FixedFraction { fraction } => Cash::from_f64(fraction * bankroll.to_f64()),
+            Sizing::Kelly { win_prob, fraction } => {
+                let p = price.to_f64();
+                let edge = (win_prob - p) / (1.0 - p);
+                if edge <= 0.0 {
+                    return Err(format!("no Kelly edge at {} with win probability {}", price, win_prob));
+                }
+                Cash::from_f64(fraction * edge * bankroll.to_f64())
+            }
+        };
+        // Cash units are share units times price units, so this is exact.
+        let units = (stake.min(cash).units() / price.units() as i128).min(i64::MAX as i128) as i64;
+        let step = increment.units().max(1);
+        Ok(Shares::from_units(units - units % step))
+    }
+}
+
+/// Order-entry latency. An action a strategy emits on one snapshot reaches
+/// the venue `ms` later, plus up to `jitter_ms` more drawn per action, and
+/// takes effect on the first snapshot at or after that time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Latency {
+    pub ms: i64,
+    #[serde(default)]
+    pub jitter_ms: i64,
 }
 
 /// An action a strategy can request.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Action {
     /// Place a maker buy at `price` for `shares` on the given side.
     PlaceBid {
+        side: Side,
+        price: Price,
+        shares: Shares,
+    },
+    /// Cancel the resting orders, buy and sell, on the given side.
+    Cancel { side: Side },
+    /// Buy up to `shares` on the given side immediately from the displayed
+    /// asks, cheapest first, paying no more than `max_price` a share.
+    /// Whatever the asks can't supply is cancelled.
+    TakeAsk {
+        side: Side,
+        shares: Shares,
+        max_price: Price,
+    },
+    /// Move the resting order on the given side to `new_price`, with
+    /// `new_shares` left to fill. Shrinking it at the same price keeps its
+    /// place in the queue; any other change sends it to the back of the
+    /// queue at the new price, as a new order.
+    Amend {
+        side: Side,
+        new_price: Price,
+        new_shares: Shares,
+    },
+    /// Sell up to `shares` of those bought on the given side for `price` a
+    /// share or better, capped at what is held and not already offered.
+    /// A price at or below the best bid takes from the bids at once (the
+    /// rest is cancelled); a higher one rests on the asks until it fills or
+    /// is cancelled.
+    Sell {
+        side: Side,
+        price: Price,
+        shares: Shares,
+    },
+}
+
+/// Identifies an order within its window: the engine numbers accepted
+/// orders from 1 in placement order. Serializes as a plain integer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OrderId(u32);
+
+impl OrderId {
+    pub const fn new(id: u32) -> Self {
+        Self(id)
+    }
+
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for OrderId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// One step in an order's life, as kept in [`WindowResult::events`].
+/// Offsets are milliseconds since window open. Sells are reported on the
+/// side they sell, at their sale price.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OrderEvent {
+    /// The engine accepted an order from the strategy.
+    Placed {
+        order_id: OrderId,
         side: Side,
         price: f64,
         shares: f64,
+        offset_ms: i64,
+        /// Queue depth ahead of the order when placed; 0 for taker orders.
+        queue_ahead: f64,
+        /// Placed by [`Action::TakeAsk`].
+        taker: bool,
+        /// Placed by [`Action::Sell`].
+        sell: bool,
+    },
+    /// Shrunk in place by [`Action::Amend`], to `shares` in all. Amends
+    /// that move the price show up as a cancel and a new order instead.
+    Amended {
+        order_id: OrderId,
+        side: Side,
+        shares: f64,
+        offset_ms: i64,
+    },
+    Cancelled {
+        order_id: OrderId,
+        side: Side,
+        offset_ms: i64,
+    },
+    /// Filled `shares` more, with `remaining` still to fill.
+    PartiallyFilled {
+        order_id: OrderId,
+        side: Side,
+        price: f64,
+        shares: f64,
+        remaining: f64,
+        offset_ms: i64,
+    },
+    /// Filled its last `shares`.
+    Filled {
+        order_id: OrderId,
+        side: Side,
+        price: f64,
+        shares: f64,
+        offset_ms: i64,
+    },
+    /// Still resting when the window closed.
+    Expired {
+        order_id: OrderId,
+        side: Side,
+        offset_ms: i64,
+    },
+    /// At settlement the fill model's adverse selection filter threw out
+    /// the order's fill: its `shares` count toward naive PnL only.
+    AdverseFiltered {
+        order_id: OrderId,
+        side: Side,
+        shares: f64,
     },
-    /// Cancel a previously placed order on the given side.
-    Cancel { side: Side },
 }
 
 /// A simulated order tracked through its lifecycle.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SimOrder {
+    /// Assigned by the engine when it accepts the order; fill models leave
+    /// the default.
+    pub id: OrderId,
     pub side: Side,
-    pub price: f64,
-    pub shares: f64,
-    /// When the order was placed (offset_ms from market open).
-    pub placed_at_ms: i64,
+    pub price: Price,
+    pub shares: Shares,
+    /// When the order was placed.
+    pub placed_at_ms: OffsetMs,
     /// Queue depth ahead of us when order was placed.
     pub queue_ahead: f64,
     /// How much queue has been consumed since placement.
     pub queue_consumed: f64,
-    /// Whether this order has been filled.
+    /// Whether this order has been filled in full.
+    pub filled: bool,
+    /// Shares filled so far; see [`fill`](Self::fill).
+    pub filled_shares: Shares,
+    /// When it first filled, in part or in full.
+    pub filled_at_ms: Option<OffsetMs>,
+    /// When the strategy cancelled it (or the engine did, for an aborted
+    /// window). Shares already filled stay filled.
+    pub cancelled_at_ms: Option<OffsetMs>,
+    /// Placed by [`Action::TakeAsk`]: filled on placement, in part or in
+    /// full, at its average price, with the rest cancelled.
+    pub taker: bool,
+    /// Placed by [`Action::Sell`]: sells shares bought on `side` at `price`.
+    /// Fill models only ever see bids, so the engine hands them a sell as
+    /// its mirror, a bid on the other side at `1 − price`: a YES ask at `p`
+    /// is a NO bid at `1 − p` in a binary market.
+    pub sell: bool,
+}
+
+impl SimOrder {
+    /// Still resting: neither filled nor cancelled. Fill models only
+    /// advance open orders.
+    pub fn is_open(&self) -> bool {
+        !self.filled && self.cancelled_at_ms.is_none()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled_at_ms.is_some()
+    }
+
+    /// A resting 10-share bid at 0.49 on `side`, placed at `placed_at_ms`
+    /// behind `queue_ahead`, for fill model tests.
+    #[cfg(test)]
+    pub fn test_bid(side: Side, placed_at_ms: i64, queue_ahead: f64) -> Self {
+        Self {
+            id: OrderId::default(),
+            side,
+            price: Price::from_f64(0.49),
+            shares: Shares::from_f64(10.0),
+            placed_at_ms: OffsetMs::new(placed_at_ms),
+            queue_ahead,
+            queue_consumed: 0.0,
+            filled: false,
+            filled_shares: Shares::default(),
+            filled_at_ms: None,
+            cancelled_at_ms: None,
+            taker: false,
+            sell: false,
+        }
+    }
+
+    /// Shares still to fill.
+    pub fn remaining(&self) -> Shares {
+        self.shares - self.filled_shares
+    }
+
+    /// Fill up to `shares` more at `offset_ms`, capped at what remains, and
+    /// return the shares filled. The order is [`filled`](Self::filled) once
+    /// nothing remains.
+    pub fn fill(&mut self, shares: Shares, offset_ms: OffsetMs) -> Shares {
+        let shares = shares.min(self.remaining());
+        if shares <= Shares::default() {
+            return Shares::default();
+        }
+        self.filled_shares = self.filled_shares + shares;
+        self.filled_at_ms.get_or_insert(offset_ms);
+        self.filled = self.remaining() <= Shares::default();
+        shares
+    }
+
+    /// Fill everything that remains at `offset_ms`.
+    pub fn fill_all(&mut self, offset_ms: OffsetMs) -> Shares {
+        self.fill(self.remaining(), offset_ms)
+    }
+}
+
+/// A strategy's view of its order on one side of the book.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SideOrderState {
+    /// An order has been placed on this side this window.
+    pub placed: bool,
+    /// The order was cancelled before filling in full.
+    pub cancelled: bool,
+    /// The order has filled in full.
     pub filled: bool,
-    /// When filled (offset_ms).
-    pub filled_at_ms: Option<i64>,
+    pub price: Price,
+    pub shares: Shares,
+    /// Shares filled so far, which may be part of `shares` on an order that
+    /// is still resting or was cancelled.
+    pub filled_shares: Shares,
+    /// When it first filled.
+    pub filled_at_ms: Option<OffsetMs>,
+    /// Queue depth still ahead of the order (0 once filled or cancelled).
+    pub queue_ahead: f64,
+    /// Shares sold back this window with [`Action::Sell`]; what is held is
+    /// what the side's buys filled less this.
+    pub sold_shares: Shares,
+}
+
+/// Simulated order state for both sides, handed to strategies each tick
+/// after the fill model has run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OrderState {
+    pub yes: SideOrderState,
+    pub no: SideOrderState,
+    /// Every order of the window in placement order, sells included; the
+    /// side states above only describe each side's latest bid.
+    pub orders: Vec<SimOrder>,
+}
+
+impl OrderState {
+    pub fn side(&self, side: Side) -> &SideOrderState {
+        match side {
+            Side::Yes => &self.yes,
+            Side::No => &self.no,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -310,6 +1340,173 @@ mod tests {
             "bid_depth_at with no depth levels should return 0.0"
         );
     }
+
+    #[test]
+    fn test_depth_levels_inline_and_serialize_as_list() {
+        let side = make_side_with_depth(vec![(0.49, 30.0), (0.48, 60.0), (0.47, 90.0), (0.46, 120.0)]);
+        assert!(!side.depth.spilled());
+        let json = serde_json::to_value(&side).unwrap();
+        assert_eq!(json["depth"][3]["cumulative_size"], 120.0);
+        let back: SideState = serde_json::from_value(json).unwrap();
+        assert_eq!(back.depth.len(), 4);
+    }
+
+    #[test]
+    fn test_ask_depth_mirrors_opposite_bids() {
+        let snap = BookSnapshot {
+            market_id: "m".into(),
+            offset_ms: OffsetMs::new(0),
+            timestamp_ms: 0,
+            yes: Arc::new(make_side_with_depth(vec![(0.49, 500.0)])),
+            no: Arc::new(make_side_with_depth(vec![(0.49, 300.0), (0.50, 120.0), (0.51, 50.0)])),
+            reference_price: None,
+            oracle_price: None,
+        };
+
+        let yes_asks = snap.ask_depth(Side::Yes);
+        let prices: Vec<f64> = yes_asks.iter().map(|l| (l.price * 100.0).round()).collect();
+        assert_eq!(prices, vec![49.0, 50.0, 51.0]);
+        assert_eq!(snap.ask_depth_at(Side::Yes, 0.51), 300.0);
+        assert_eq!(snap.ask_depth_at(Side::Yes, 0.49), 50.0);
+        // Between levels: the cheaper level is what a taker can actually get.
+        assert_eq!(snap.ask_depth_at(Side::Yes, 0.505), 120.0);
+        assert_eq!(snap.ask_depth_at(Side::Yes, 0.40), 0.0);
+        assert_eq!(snap.ask_depth_at(Side::No, 0.51), 500.0);
+    }
+
+    #[test]
+    fn test_implied_probability_helpers() {
+        let quote = |bid: Option<f64>, ask: Option<f64>| {
+            Arc::new(SideState { best_bid: bid, best_ask: ask, ..Default::default() })
+        };
+        let mut snap = BookSnapshot {
+            market_id: "m".into(),
+            offset_ms: OffsetMs::new(0),
+            timestamp_ms: 0,
+            yes: quote(Some(0.60), Some(0.64)),
+            no: quote(Some(0.35), None),
+            reference_price: None,
+            oracle_price: None,
+        };
+        assert!((snap.yes_mid().unwrap() - 0.62).abs() < 1e-9);
+        assert_eq!(snap.no_mid(), None);
+        assert!((snap.combined_bid().unwrap() - 0.95).abs() < 1e-9);
+        assert!((snap.spread_bps(Side::Yes).unwrap() - 645.16).abs() < 0.01);
+        assert_eq!(snap.spread_bps(Side::No), None);
+        assert!((snap.implied_prob(Side::Yes).unwrap() - 0.62).abs() < 1e-9);
+        // NO has no mid of its own, so it's the complement of YES's.
+        assert!((snap.implied_prob(Side::No).unwrap() - 0.38).abs() < 1e-9);
+
+        // A missing bid is missing, not a free 0.0.
+        snap.yes = quote(None, Some(0.64));
+        assert_eq!(snap.combined_bid(), None);
+        assert_eq!(snap.implied_prob(Side::Yes), Some(0.64));
+        assert_eq!(snap.implied_prob(Side::No), Some(0.35));
+    }
+
+    #[test]
+    fn test_fixed_point_prices_compare_exactly() {
+        assert_eq!(Price::from_f64(0.1 + 0.2), Price::from_f64(0.3));
+        assert_eq!(Price::from_f64(0.49000000000000005), Price::from_f64(0.49));
+        assert!(Price::from_f64(0.491) > Price::from_f64(0.49));
+        assert_eq!(Price::ONE - Price::from_f64(0.49), Price::from_f64(0.51));
+        assert_eq!(Shares::from_f64(10.0).units(), 10_000_000);
+    }
+
+    #[test]
+    fn test_venue_rules_rejections() {
+        let rules = VenueRules::for_platform(&Platform::Polymarket);
+        let bid = |price: f64, shares: f64| rules.rejection(Price::from_f64(price), Shares::from_f64(shares));
+        assert_eq!(bid(0.49, 10.0), None);
+        assert_eq!(bid(0.972, 5.0), None);
+        assert_eq!(bid(1.0, 10.0).unwrap(), "price 1 outside (0, 1)");
+        assert_eq!(bid(0.0, 10.0).unwrap(), "price 0 outside (0, 1)");
+        assert_eq!(bid(0.505, 10.0).unwrap(), "price 0.505 off the 0.01 tick");
+        assert_eq!(bid(0.0305, 10.0).unwrap(), "price 0.0305 off the 0.001 tick");
+        assert_eq!(bid(0.49, 4.0).unwrap(), "4 shares below the 5 minimum");
+        assert_eq!(bid(0.49, 5.555).unwrap(), "5.555 shares not a multiple of 0.01");
+        assert_eq!(bid(0.49, f64::NAN).unwrap(), "0 shares below the 5 minimum");
+    }
+
+    #[test]
+    fn test_cash_sums_are_order_independent() {
+        let fills = [(0.49, 10.0), (0.07, 3.3), (0.93, 0.1), (0.301, 1234.5)];
+        let legs: Vec<Cash> = fills
+            .iter()
+            .map(|&(price, shares)| Shares::from_f64(shares) * Price::from_f64(price))
+            .collect();
+        let forward = legs.iter().fold(Cash::default(), |acc, &c| acc + c);
+        let backward = legs.iter().rev().fold(Cash::default(), |acc, &c| acc + c);
+        assert_eq!(forward, backward);
+        assert_eq!(forward.units(), (4_900_000 + 231_000 + 93_000 + 371_584_500) * Price::SCALE as i128);
+    }
+
+    #[test]
+    fn test_large_notionals_dont_overflow() {
+        // $4.9B of notional, past what an i64 of cash units holds.
+        let cost = Shares::from_f64(1e10) * Price::from_f64(0.49);
+        assert_eq!(cost.to_f64(), 4.9e9);
+        assert_eq!(cost + cost - cost, cost);
+
+        let rules = VenueRules::default();
+        assert_eq!(rules.rejection(Price::from_f64(0.49), Shares::MAX_ORDER), None);
+        assert_eq!(
+            rules.rejection(Price::from_f64(0.49), Shares::from_f64(1e10)).unwrap(),
+            "10000000000 shares above the 1000000000 maximum"
+        );
+        assert!(rules.rejection(Price::from_f64(0.49), Shares::from_f64(f64::INFINITY)).is_some());
+    }
+
+    #[test]
+    fn test_bankroll_sizing() {
+        let cent = Shares::from_f64(0.01);
+        let size = |sizing: Sizing, price: f64, cash: f64| {
+            Bankroll { starting_capital: 100.0, sizing }.size(
+                Price::from_f64(price),
+                Shares::from_f64(10.0),
+                Cash::from_f64(100.0),
+                Cash::from_f64(cash),
+                cent,
+            )
+        };
+        assert_eq!(size(Sizing::Fixed, 0.49, 100.0), Ok(Shares::from_f64(10.0)));
+        assert_eq!(size(Sizing::Fixed, 0.49, 2.0), Ok(Shares::from_f64(4.08)));
+        let fraction = Sizing::FixedFraction { fraction: 0.1 };
+        assert_eq!(size(fraction, 0.49, 100.0), Ok(Shares::from_f64(20.4)));
+        // Kelly at 50c with a 60% win probability stakes 20% (here half of it).
+        let kelly = Sizing::Kelly { win_prob: 0.6, fraction: 0.5 };
+        assert_eq!(size(kelly, 0.5, 100.0), Ok(Shares::from_f64(20.0)));
+        assert_eq!(size(kelly, 0.6, 100.0).unwrap_err(), "no Kelly edge at 0.6 with win probability 0.6");
+        assert_eq!(size(Sizing::Fixed, 0.49, 0.0).unwrap_err(), "bankroll exhausted");
+    }
+
+    #[test]
+    fn test_platform_names_round_trip() {
+        assert_eq!(Platform::from("Kalshi"), Platform::Kalshi);
+        let other = Platform::from(" Manifold ");
+        assert_eq!(other, Platform::Other("manifold".into()));
+        assert_eq!(other.to_string(), "manifold");
+        assert_eq!(serde_json::to_value(&Platform::Polymarket).unwrap(), "polymarket");
+        let parsed: Platform = serde_json::from_str("\"manifold\"").unwrap();
+        assert_eq!(parsed, other);
+        assert_eq!(VenueRules::for_platform(&other).min_shares, Shares::from_f64(0.01));
+    }
+
+    #[test]
+    fn test_timestamp_conversions() {
+        let open = UnixSecs::checked(1_705_314_600).unwrap();
+        assert_eq!(open.to_datetime().unwrap().to_rfc3339(), "2024-01-15T10:30:00+00:00");
+        assert_eq!(UnixSecs::from_datetime(open.to_datetime().unwrap()), open);
+        assert!(UnixSecs::checked(open.to_millis()).is_err());
+        assert_eq!(UnixSecs::from_millis(open.to_millis() + 999), open);
+
+        let offset = open.offset_of(open.to_millis() + 90_500);
+        assert_eq!(offset, OffsetMs::new(90_500));
+        assert_eq!(open.at(offset), open.to_millis() + 90_500);
+        assert!(offset >= 90_000 && offset < OffsetMs::from_secs(91));
+        assert_eq!(offset - OffsetMs::from_secs(90), 500);
+        assert_eq!(serde_json::to_string(&(open, offset)).unwrap(), "[1705314600,90500]");
+    }
 }
 
 /// Complete result for one simulated market window.
@@ -342,4 +1539,39 @@ pub struct WindowResult {
     // Reference prices
     pub ref_price_open: Option<f64>,
     pub ref_price_close: Option<f64>,
+
+    /// Why the strategy was stopped mid-window (e.g. a script exceeded its
+    /// execution budget); `None` for a normal run.
+    #[serde(default)]
+    pub aborted: Option<String>,
+    /// Script runtime errors swallowed during the window.
+    #[serde(default)]
+    pub script_errors: usize,
+    /// Bids the engine refused under the venue's [`VenueRules`].
+    #[serde(default)]
+    pub rejected_orders: usize,
+    /// Why the first of them was refused.
+    #[serde(default)]
+    pub rejection: Option<String>,
+
+    // Bankroll
+    /// Dollars spent on the shares that filled.
+    #[serde(default)]
+    pub stake: f64,
+    /// The bankroll after the window settled, when the run was sized from
+    /// one (see [`Bankroll`]).
+    #[serde(default)]
+    pub bankroll: Option<f64>,
+
+    /// Every order's lifecycle in the window, in the order it happened.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<OrderEvent>,
+}
+
+impl WindowResult {
+    /// Whether the window's outcome paid out a side (see
+    /// [`Outcome::is_settled`]).
+    pub fn is_settled(&self) -> bool {
+        Outcome::from_label(&self.outcome).is_some_and(|o| o.is_settled())
+    }
 }
@@ -0,0 +1,147 @@
+//! Browser entry points (`wasm` feature).
+//!
+//! Builds without SQLite or HTTP: uploaded HuggingFace NDJSON files are
+//! parsed into a [`MemoryStore`] and backtests run on it exactly as
+//! `pf run` would, returning JSON for the page to chart.
+//!
+//! ```text
+//! cargo rustc --release --lib --target wasm32-unknown-unknown \
+//!     --no-default-features --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir web/pkg \
+//!     target/wasm32-unknown-unknown/release/phantomfill.wasm
+//! ```
+//!
+//! ```js
+//! const session = new Session();
+//! session.load_ndjson(file.name, await file.text());
+//! const { report } = JSON.parse(session.run('{"strategy": "momentum"}'));
+//! console.log(report.phantom_fill_gap);
+//! ```
+
+use wasm_bindgen::prelude::*;
+
+use crate::backtest::BacktestConfig;
+use crate::data::huggingface::{import_ndjson, outcome_from_final_book, parse_filename};
+use crate::data::{DataStore, MarketFilter, MemoryStore};
+use crate::types::Outcome;
+
+fn js_error(err: anyhow::Error) -> JsError {
+    JsError::new(&format!("{:#}", err))
+}
+
+/// Markets loaded in this page, and the backtests run over them.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct Session {
+    store: MemoryStore,
+}
+
+#[wasm_bindgen]
+impl Session {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    /// Load one HuggingFace NDJSON file and return its market id. The
+    /// filename carries the market, coin and window (as with
+    /// `pf-hf-import`). `outcome` is `"yes"`, `"no"` or `"void"`; when
+    /// omitted it is inferred from the final book, and the window is left
+    /// unresolved if neither side converged.
+    pub fn load_ndjson(
+        &self,
+        filename: &str,
+        contents: &str,
+        outcome: Option<String>,
+    ) -> Result<String, JsError> {
+        self.load(filename, contents, outcome.as_deref()).map_err(js_error)
+    }
+
+    /// Loaded markets as a JSON array.
+    pub fn markets(&self) -> Result<String, JsError> {
+        let markets = self
+            .store
+            .list_markets(&MarketFilter::default())
+            .map_err(js_error)?;
+        serde_json::to_string(&markets).map_err(|e| js_error(e.into()))
+    }
+
+    /// Run a backtest over the loaded markets. `config_json` takes the same
+    /// settings as the C API (`strategy`, `script_source`, `shares`, `seed`,
+    /// `runs`, ...); the result is `{"report", "results", "summary"}`.
+    pub fn run(&self, config_json: &str) -> Result<String, JsError> {
+        let config: BacktestConfig = serde_json::from_str(config_json)
+            .map_err(|e| JsError::new(&format!("invalid backtest config: {}", e)))?;
+        let output = config.run(&self.store).map_err(js_error)?;
+        serde_json::to_string(&output).map_err(|e| js_error(e.into()))
+    }
+}
+
+impl Session {
+    fn load(&self, filename: &str, contents: &str, outcome: Option<&str>) -> anyhow::Result<String> {
+        let parsed = parse_filename(filename)?;
+        let outcome = match outcome.map(str::to_ascii_lowercase).as_deref() {
+            Some("yes") | Some("up") => Some(Outcome::Yes),
+            Some("no") | Some("down") => Some(Outcome::No),
+            Some("void") => Some(Outcome::Void),
+            Some(other) => anyhow::bail!("unknown outcome `{}` (expected yes, no or void)", other),
+            None => None,
+        };
+        import_ndjson(contents.as_bytes(), filename, &parsed, &self.store, outcome)?;
+        if outcome.is_none() {
+            let ticks = self.store.load_ticks(&parsed.market_id)?;
+            if let Some(inferred) = outcome_from_final_book(&ticks) {
+                let mut markets = self.store.list_markets(&MarketFilter::default())?;
+                if let Some(market) = markets.iter_mut().find(|m| m.id == parsed.market_id) {
+                    market.outcome = Some(inferred);
+                    self.store.insert_market(market)?;
+                }
+            }
+        }
+        Ok(parsed.market_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(ts: i64, progress: f64, up: bool, bid: f64) -> String {
+        format!(
+            r#"{{"ts":{},"progress":{},"type":1,"outcome_up":{},"outcome_down":{},"best_bid":{},"best_bid_size":50.0,"best_ask":{},"best_ask_size":50.0,"bid_size_total":400.0,"ask_size_total":300.0}}"#,
+            ts,
+            progress,
+            up as i32,
+            !up as i32,
+            bid,
+            bid + 0.02
+        )
+    }
+
+    #[test]
+    fn test_session_loads_ndjson_and_runs() {
+        let session = Session::new();
+        let mut lines = Vec::new();
+        for i in 0..=20 {
+            let progress = i as f64 / 20.0;
+            let yes = 0.49 + 0.5 * progress;
+            lines.push(row(1_700_000_000_000 + i * 15_000, progress, true, yes.min(0.97)));
+            lines.push(row(1_700_000_000_000 + i * 15_000, progress, false, (0.49 - 0.5 * progress).max(0.01)));
+        }
+        let id = session
+            .load("btc5m_market7_2026-01-15_10-30-00.ndjson", &lines.join("\n"), None)
+            .unwrap();
+        assert_eq!(id, "hf-btc5m-7");
+
+        let markets: serde_json::Value = serde_json::from_str(&session.markets().unwrap()).unwrap();
+        assert_eq!(markets[0]["outcome"], "Yes");
+
+        let output = session.run(r#"{"strategy": "spread_arb", "seed": 3}"#).unwrap();
+        let output: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(output["report"]["total_windows"], 1);
+        assert_eq!(output["results"][0]["market_id"], "hf-btc5m-7");
+
+        assert!(session.load("not-a-window.ndjson", "", None).is_err());
+        assert!(session.load("btc5m_market8_2026-01-15_10-35-00.ndjson", "", Some("maybe")).is_err());
+    }
+}